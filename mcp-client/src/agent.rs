@@ -0,0 +1,559 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Write;
+use tracing::debug;
+
+use crate::mcp::{self, McpClient};
+use crate::backend::LlmBackend;
+use crate::ollama::{ChatMessage, Tool};
+
+/// A single step of an agent conversation
+#[derive(Debug, Clone)]
+pub enum TranscriptEntry {
+    User(String),
+    Assistant(String),
+    ToolCall {
+        name: String,
+        arguments: serde_json::Map<String, Value>,
+    },
+    ToolResult {
+        name: String,
+        output: String,
+        is_error: bool,
+    },
+}
+
+impl fmt::Display for TranscriptEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TranscriptEntry::User(text) => write!(f, "User: {}", text),
+            TranscriptEntry::Assistant(text) => write!(f, "Assistant: {}", text),
+            TranscriptEntry::ToolCall { name, arguments } => {
+                write!(f, "Tool call: {} {}", name, Value::Object(arguments.clone()))
+            }
+            TranscriptEntry::ToolResult { name, output, is_error } => {
+                let label = if *is_error { "Tool error" } else { "Tool result" };
+                write!(f, "{} ({}): {}", label, name, output.trim_end())
+            }
+        }
+    }
+}
+
+/// Why the agent loop stopped
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentOutcome {
+    /// The model produced a final answer
+    Answer(String),
+    /// The model kept calling tools until the iteration limit was hit
+    MaxIterations,
+    /// The model repeated an identical tool call
+    LoopDetected { tool_name: String },
+}
+
+/// The result of one agent run
+#[derive(Debug)]
+pub struct AgentRun {
+    pub outcome: AgentOutcome,
+    /// What happened during this run, for display
+    pub transcript: Vec<TranscriptEntry>,
+    /// Chat messages added by this run, starting with the system prompt for a
+    /// new conversation or the user's prompt for a continued one
+    pub new_messages: Vec<ChatMessage>,
+}
+
+/// Decides whether a proposed tool call may run, given the tool name and arguments
+pub type ConfirmToolCall<'a> = &'a dyn Fn(&str, &serde_json::Map<String, Value>) -> bool;
+
+/// What the model is told when the user declines a tool call
+const DECLINED_MESSAGE: &str = "The user declined to run this tool call.";
+
+/// Runs a model in a loop where it can call MCP tools, see their results and
+/// call further tools before answering.
+pub struct Agent<'a> {
+    llm: &'a dyn LlmBackend,
+    mcp: &'a McpClient,
+    model: &'a str,
+    system_prompt: String,
+    tools: Vec<Tool>,
+    max_iterations: usize,
+    stream: bool,
+    confirm: Option<ConfirmToolCall<'a>>,
+}
+
+impl<'a> Agent<'a> {
+    pub fn new(
+        llm: &'a dyn LlmBackend,
+        mcp: &'a McpClient,
+        model: &'a str,
+        system_prompt: String,
+        tools: Vec<Tool>,
+        max_iterations: usize,
+    ) -> Self {
+        Self {
+            llm,
+            mcp,
+            model,
+            system_prompt,
+            tools,
+            max_iterations,
+            stream: false,
+            confirm: None,
+        }
+    }
+
+    /// Print the model's text to stdout as it is generated
+    pub fn with_streaming(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Ask `confirm` before each tool call; declined calls are reported back
+    /// to the model instead of being executed
+    pub fn with_confirmation(mut self, confirm: ConfirmToolCall<'a>) -> Self {
+        self.confirm = Some(confirm);
+        self
+    }
+
+    /// Runs the agent on `prompt`. `history` holds the messages of an earlier
+    /// conversation to continue; when empty a new one is started with the
+    /// system prompt.
+    pub async fn run(&self, history: Vec<ChatMessage>, prompt: &str) -> Result<AgentRun> {
+        let mut transcript = vec![TranscriptEntry::User(prompt.to_string())];
+        let mut messages = history;
+        let history_len = messages.len();
+        if messages.is_empty() {
+            messages.push(ChatMessage::system(self.system_prompt.clone()));
+        }
+        messages.push(ChatMessage::user(prompt));
+        let mut seen_calls = HashSet::new();
+
+        for iteration in 1..=self.max_iterations {
+            debug!("Agent iteration {}/{}", iteration, self.max_iterations);
+            let reply = if self.stream {
+                let reply = self.llm
+                    .chat_streaming(self.model, &messages, &self.tools, &mut |token| {
+                        print!("{}", token);
+                        let _ = std::io::stdout().flush();
+                    })
+                    .await?;
+                if !reply.content.is_empty() {
+                    println!();
+                }
+                reply
+            } else {
+                self.llm.chat(self.model, &messages, &self.tools).await?
+            };
+            debug!("Reply from model: {:?}", reply);
+
+            if reply.tool_calls.is_empty() {
+                let answer = reply.content.clone();
+                transcript.push(TranscriptEntry::Assistant(answer.clone()));
+                messages.push(reply);
+                return Ok(AgentRun {
+                    outcome: AgentOutcome::Answer(answer),
+                    transcript,
+                    new_messages: messages.split_off(history_len),
+                });
+            }
+
+            // Approved calls run together, as one batch over JSON-RPC
+            let mut planned = Vec::new();
+            let mut repeated = None;
+            for call in reply.tool_calls.clone() {
+                let tool_name = call.function.name;
+                let arguments = call.function.arguments;
+
+                let call_key = format!("{}:{}", tool_name, Value::Object(arguments.clone()));
+                if !seen_calls.insert(call_key) {
+                    repeated = Some((tool_name, arguments));
+                    break;
+                }
+
+                let approved = match self.confirm {
+                    Some(confirm) if !confirm(&tool_name, &arguments) => {
+                        println!("Skipped tool: {}", tool_name);
+                        false
+                    }
+                    _ => {
+                        println!("Using tool: {} with arguments: {}",
+                            tool_name,
+                            serde_json::to_string_pretty(&arguments)?
+                        );
+                        true
+                    }
+                };
+                planned.push((call.id, tool_name, arguments, approved));
+            }
+            messages.push(reply);
+
+            let calls: Vec<_> = planned
+                .iter()
+                .filter(|(.., approved)| *approved)
+                .map(|(_, tool_name, arguments, _)| (tool_name.clone(), arguments.clone()))
+                .collect();
+            let count = calls.len();
+            let mut results = match self.mcp.call_tools(calls).await {
+                Ok(results) => results,
+                // The server could not be reached, so every call failed
+                Err(e) => (0..count).map(|_| Err(anyhow::anyhow!("{}", e))).collect(),
+            }
+            .into_iter();
+
+            for (call_id, tool_name, arguments, approved) in planned {
+                transcript.push(TranscriptEntry::ToolCall {
+                    name: tool_name.clone(),
+                    arguments,
+                });
+
+                if !approved {
+                    messages.push(ChatMessage::tool(&tool_name, call_id, DECLINED_MESSAGE));
+                    transcript.push(TranscriptEntry::ToolResult {
+                        name: tool_name,
+                        output: "declined by user".to_string(),
+                        is_error: true,
+                    });
+                    continue;
+                }
+
+                let result = results.next().unwrap_or_else(|| Err(anyhow::anyhow!("No result for tool {}", tool_name)));
+                let (output, is_error) = match result {
+                    Ok(blocks) => {
+                        let output = render_content(&blocks);
+                        println!("Tool result:\n{}", output);
+                        (output, false)
+                    }
+                    Err(e) => {
+                        println!("Tool {} failed: {}", tool_name, e);
+                        (e.to_string(), true)
+                    }
+                };
+
+                messages.push(ChatMessage::tool(&tool_name, call_id, output.clone()));
+                transcript.push(TranscriptEntry::ToolResult { name: tool_name, output, is_error });
+            }
+
+            if let Some((tool_name, arguments)) = repeated {
+                transcript.push(TranscriptEntry::ToolCall {
+                    name: tool_name.clone(),
+                    arguments,
+                });
+                return Ok(AgentRun {
+                    outcome: AgentOutcome::LoopDetected { tool_name },
+                    transcript,
+                    new_messages: messages.split_off(history_len),
+                });
+            }
+        }
+
+        Ok(AgentRun {
+            outcome: AgentOutcome::MaxIterations,
+            transcript,
+            new_messages: messages.split_off(history_len),
+        })
+    }
+}
+
+/// Converts MCP tool definitions into the function tools `/api/chat` expects
+pub fn chat_tools(tools: &[mcp::ToolDefinition]) -> Vec<Tool> {
+    tools
+        .iter()
+        .map(|tool| Tool::function(&tool.name, &tool.description, tool.input_schema.clone()))
+        .collect()
+}
+
+/// Tool-definition formats `export-tools` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ToolSchemaFormat {
+    /// Anthropic Messages API tool use: name, description, input_schema
+    Anthropic,
+    /// OpenAI function tools, also accepted by Ollama's /api/chat
+    Openai,
+    /// The MCP server's own definitions
+    Mcp,
+}
+
+/// The tool list in `format`, ready to paste into another agent's request
+pub fn export_tools(tools: &[mcp::ToolDefinition], format: ToolSchemaFormat) -> Value {
+    match format {
+        ToolSchemaFormat::Anthropic => chat_tools(tools).iter().map(crate::anthropic::tool_definition).collect(),
+        ToolSchemaFormat::Openai => serde_json::to_value(chat_tools(tools)).unwrap(),
+        ToolSchemaFormat::Mcp => serde_json::to_value(tools).unwrap(),
+    }
+}
+
+/// A tool result as text. Structured blocks repeat the text sent alongside
+/// them, so they are only rendered when the result has no text blocks.
+pub fn render_content(blocks: &[mcp::ContentBlock]) -> String {
+    let has_text = blocks.iter().any(|block| block.as_text().is_some());
+    let mut output = String::new();
+    for block in blocks {
+        match block {
+            mcp::ContentBlock::Text { text } => output.push_str(text),
+            _ if has_text => continue,
+            mcp::ContentBlock::Json { json } => output.push_str(&serde_json::to_string_pretty(json).unwrap_or_default()),
+            mcp::ContentBlock::Data { mime_type, data } if mime_type.starts_with("text/") => output.push_str(data),
+            mcp::ContentBlock::Data { mime_type, .. } => output.push_str(&format!("[{} content]", mime_type)),
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ollama::OllamaClient;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn tool_call_reply(name: &str, arguments: Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{"function": {"name": name, "arguments": arguments}}]
+            },
+            "done": true
+        }))
+    }
+
+    #[test]
+    fn test_chat_tools_from_definitions() {
+        let tools = chat_tools(&[mcp::ToolDefinition {
+            name: "system_info".to_string(),
+            description: "Get system information".to_string(),
+            input_schema: json!({"type": "object"}),
+            tags: Vec::new(),
+            annotations: None,
+        }]);
+        let value = serde_json::to_value(&tools).unwrap();
+        assert_eq!(value[0]["type"], "function");
+        assert_eq!(value[0]["function"]["name"], "system_info");
+        assert_eq!(value[0]["function"]["parameters"]["type"], "object");
+    }
+
+    #[tokio::test]
+    async fn test_agent_chains_tool_calls() {
+        let ollama_server = MockServer::start().await;
+        let mcp_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains(r#""tool_name":"second""#))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "message": {"role": "assistant", "content": "All done."},
+                "done": true
+            })))
+            .with_priority(1)
+            .mount(&ollama_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains(r#""tool_name":"first""#))
+            .respond_with(tool_call_reply("second", json!({})))
+            .with_priority(2)
+            .mount(&ollama_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(tool_call_reply("first", json!({})))
+            .with_priority(3)
+            .mount(&ollama_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/tools/call"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "content": [{"type": "text", "text": "ok"}],
+                "error": null
+            })))
+            .mount(&mcp_server)
+            .await;
+
+        let ollama = OllamaClient::new(&ollama_server.uri());
+        let mcp = McpClient::new(&mcp_server.uri());
+        let agent = Agent::new(&ollama, &mcp, "llama2", "system".to_string(), Vec::new(), 5);
+
+        let run = agent.run(Vec::new(), "do things").await.unwrap();
+        assert_eq!(run.outcome, AgentOutcome::Answer("All done.".to_string()));
+        assert_eq!(run.transcript.len(), 6);
+        // system, user, two tool call/result pairs and the answer
+        assert_eq!(run.new_messages.len(), 7);
+        assert_eq!(run.new_messages[0].role, "system");
+    }
+
+    #[tokio::test]
+    async fn test_agent_detects_repeated_tool_call() {
+        let ollama_server = MockServer::start().await;
+        let mcp_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(tool_call_reply("same", json!({"x": 1})))
+            .mount(&ollama_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/tools/call"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "content": [{"type": "text", "text": "ok"}],
+                "error": null
+            })))
+            .expect(1)
+            .mount(&mcp_server)
+            .await;
+
+        let ollama = OllamaClient::new(&ollama_server.uri());
+        let mcp = McpClient::new(&mcp_server.uri());
+        let agent = Agent::new(&ollama, &mcp, "llama2", "system".to_string(), Vec::new(), 5);
+
+        let run = agent.run(Vec::new(), "loop forever").await.unwrap();
+        assert_eq!(run.outcome, AgentOutcome::LoopDetected { tool_name: "same".to_string() });
+    }
+
+    #[tokio::test]
+    async fn test_agent_skips_declined_tool_calls() {
+        let ollama_server = MockServer::start().await;
+        let mcp_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains(DECLINED_MESSAGE))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "message": {"role": "assistant", "content": "Okay, I won't restart it."},
+                "done": true
+            })))
+            .with_priority(1)
+            .mount(&ollama_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(tool_call_reply("call_service", json!({"service": "restart"})))
+            .with_priority(2)
+            .mount(&ollama_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/tools/call"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mcp_server)
+            .await;
+
+        let ollama = OllamaClient::new(&ollama_server.uri());
+        let mcp = McpClient::new(&mcp_server.uri());
+        let asked = std::cell::Cell::new(0);
+        let decline = |name: &str, arguments: &serde_json::Map<String, Value>| {
+            assert_eq!(name, "call_service");
+            assert_eq!(arguments["service"], "restart");
+            asked.set(asked.get() + 1);
+            false
+        };
+        let agent = Agent::new(&ollama, &mcp, "llama2", "system".to_string(), Vec::new(), 5)
+            .with_confirmation(&decline);
+
+        let run = agent.run(Vec::new(), "restart the service").await.unwrap();
+        assert_eq!(run.outcome, AgentOutcome::Answer("Okay, I won't restart it.".to_string()));
+        assert_eq!(asked.get(), 1);
+        assert_eq!(run.transcript[2].to_string(), "Tool error (call_service): declined by user");
+    }
+
+    #[tokio::test]
+    async fn test_agent_streaming_returns_full_answer() {
+        let ollama_server = MockServer::start().await;
+        let mcp_server = MockServer::start().await;
+
+        let body = format!(
+            "{}\n{}\n",
+            json!({"message": {"role": "assistant", "content": "Hello "}, "done": false}),
+            json!({"message": {"role": "assistant", "content": "there."}, "done": true}),
+        );
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains(r#""stream":true"#))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&ollama_server)
+            .await;
+
+        let ollama = OllamaClient::new(&ollama_server.uri());
+        let mcp = McpClient::new(&mcp_server.uri());
+        let agent = Agent::new(&ollama, &mcp, "llama2", "system".to_string(), Vec::new(), 5)
+            .with_streaming(true);
+
+        let run = agent.run(Vec::new(), "hi").await.unwrap();
+        assert_eq!(run.outcome, AgentOutcome::Answer("Hello there.".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_agent_continues_history() {
+        let ollama_server = MockServer::start().await;
+        let mcp_server = MockServer::start().await;
+
+        // The earlier exchange must be sent back to the model
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains("earlier question"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "message": {"role": "assistant", "content": "Follow-up answer."},
+                "done": true
+            })))
+            .mount(&ollama_server)
+            .await;
+
+        let ollama = OllamaClient::new(&ollama_server.uri());
+        let mcp = McpClient::new(&mcp_server.uri());
+        let agent = Agent::new(&ollama, &mcp, "llama2", "system".to_string(), Vec::new(), 5);
+
+        let history = vec![
+            ChatMessage::system("system"),
+            ChatMessage::user("earlier question"),
+        ];
+        let run = agent.run(history, "and now?").await.unwrap();
+        assert_eq!(run.outcome, AgentOutcome::Answer("Follow-up answer.".to_string()));
+        assert_eq!(run.new_messages.len(), 2);
+        assert_eq!(run.new_messages[0].content, "and now?");
+    }
+
+    #[test]
+    fn test_export_tools_formats() {
+        let tools = vec![mcp::ToolDefinition {
+            name: "system_info".to_string(),
+            description: "Get system information".to_string(),
+            input_schema: serde_json::json!({"type": "object", "properties": {}}),
+            tags: Vec::new(),
+            annotations: None,
+        }];
+
+        let anthropic = export_tools(&tools, ToolSchemaFormat::Anthropic);
+        assert_eq!(anthropic, serde_json::json!([{
+            "name": "system_info",
+            "description": "Get system information",
+            "input_schema": {"type": "object", "properties": {}}
+        }]));
+
+        let openai = export_tools(&tools, ToolSchemaFormat::Openai);
+        assert_eq!(openai[0]["type"], "function");
+        assert_eq!(openai[0]["function"]["parameters"]["type"], "object");
+
+        let mcp = export_tools(&tools, ToolSchemaFormat::Mcp);
+        assert_eq!(mcp[0]["inputSchema"]["type"], "object");
+        assert!(mcp[0].get("input_schema").is_none());
+    }
+
+    #[test]
+    fn test_render_content_prefers_text_over_structured_blocks() {
+        let value = json!({"cpu": 12});
+        let both = vec![
+            mcp::ContentBlock::Text { text: "cpu: 12".to_string() },
+            mcp::ContentBlock::Json { json: value.clone() },
+        ];
+        assert_eq!(render_content(&both), "cpu: 12\n");
+
+        let structured_only = vec![mcp::ContentBlock::Json { json: value }];
+        assert_eq!(render_content(&structured_only), "{\n  \"cpu\": 12\n}\n");
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::backend::LlmBackend;
+use crate::ollama::{ChatMessage, FunctionCall, Tool, ToolCall};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
+const MAX_TOKENS: u32 = 4096;
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text { text: String },
+    ToolUse { id: String, name: String, input: Value },
+    #[serde(other)]
+    Other,
+}
+
+/// Client for the Anthropic Messages API
+pub struct AnthropicClient {
+    base_url: String,
+    api_key: String,
+    client: reqwest::Client,
+}
+
+impl AnthropicClient {
+    pub fn new(base_url: &str, api_key: String) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmBackend for AnthropicClient {
+    async fn chat(&self, model: &str, messages: &[ChatMessage], tools: &[Tool]) -> Result<ChatMessage> {
+        let mut request = build_request(model, messages);
+        if !tools.is_empty() {
+            request["tools"] = tools.iter().map(tool_definition).collect();
+        }
+
+        let response = self.client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!(
+                "Anthropic API returned error status: {} with body: {}",
+                status,
+                error_text
+            ));
+        }
+
+        let response_data: MessagesResponse = response.json().await?;
+        let mut reply = ChatMessage::assistant("");
+        for block in response_data.content {
+            match block {
+                ContentBlock::Text { text } => reply.content.push_str(&text),
+                ContentBlock::ToolUse { id, name, input } => reply.tool_calls.push(ToolCall {
+                    id: Some(id),
+                    function: FunctionCall {
+                        name,
+                        arguments: input.as_object().cloned().unwrap_or_default(),
+                    },
+                }),
+                ContentBlock::Other => {}
+            }
+        }
+        Ok(reply)
+    }
+}
+
+/// Converts the conversation into a Messages API request. System messages
+/// become the top-level `system` field, and tool results become `tool_result`
+/// blocks in a user turn, since the API has no `tool` role.
+/// A tool in the Messages API's tool-use format
+pub fn tool_definition(tool: &Tool) -> Value {
+    json!({
+        "name": tool.function.name,
+        "description": tool.function.description,
+        "input_schema": tool.function.parameters,
+    })
+}
+
+fn build_request(model: &str, messages: &[ChatMessage]) -> Value {
+    let mut system = Vec::new();
+    let mut turns: Vec<(&str, Vec<Value>)> = Vec::new();
+
+    for message in messages {
+        let (role, blocks) = match message.role.as_str() {
+            "system" => {
+                system.push(message.content.as_str());
+                continue;
+            }
+            "assistant" => {
+                let mut blocks = Vec::new();
+                if !message.content.is_empty() {
+                    blocks.push(json!({"type": "text", "text": message.content}));
+                }
+                for call in &message.tool_calls {
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id.clone().unwrap_or_else(|| format!("call_{}", call.function.name)),
+                        "name": call.function.name,
+                        "input": call.function.arguments,
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            "tool" => {
+                let tool_name = message.tool_name.as_deref().unwrap_or_default();
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id.clone().unwrap_or_else(|| format!("call_{}", tool_name)),
+                    "content": message.content,
+                });
+                ("user", vec![block])
+            }
+            _ => ("user", vec![json!({"type": "text", "text": message.content})]),
+        };
+
+        // Consecutive messages with the same role are merged into one turn
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+
+    let mut request = json!({
+        "model": model,
+        "max_tokens": MAX_TOKENS,
+        "messages": turns
+            .into_iter()
+            .map(|(role, content)| json!({"role": role, "content": content}))
+            .collect::<Vec<_>>(),
+    });
+    if !system.is_empty() {
+        request["system"] = json!(system.join("\n\n"));
+    }
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_build_request_maps_roles() {
+        let mut tool_request = ChatMessage::assistant("Checking.");
+        tool_request.tool_calls.push(ToolCall {
+            id: Some("toolu_1".to_string()),
+            function: FunctionCall {
+                name: "system_info".to_string(),
+                arguments: json!({"action": "get_disks"}).as_object().unwrap().clone(),
+            },
+        });
+        let messages = vec![
+            ChatMessage::system("Be helpful"),
+            ChatMessage::user("How full are my disks?"),
+            tool_request,
+            ChatMessage::tool("system_info", Some("toolu_1".to_string()), "/: 40%"),
+        ];
+
+        let request = build_request("claude-3-5-sonnet-latest", &messages);
+        assert_eq!(request["system"], "Be helpful");
+        assert_eq!(request["max_tokens"], MAX_TOKENS);
+
+        let turns = request["messages"].as_array().unwrap();
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[1]["role"], "assistant");
+        assert_eq!(turns[1]["content"][1]["type"], "tool_use");
+        assert_eq!(turns[1]["content"][1]["input"]["action"], "get_disks");
+        assert_eq!(turns[2]["role"], "user");
+        assert_eq!(turns[2]["content"][0]["type"], "tool_result");
+        assert_eq!(turns[2]["content"][0]["tool_use_id"], "toolu_1");
+    }
+
+    #[tokio::test]
+    async fn test_chat_parses_text_and_tool_use() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("x-api-key", "test-key"))
+            .and(header("anthropic-version", API_VERSION))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "content": [
+                    {"type": "text", "text": "Let me check."},
+                    {"type": "tool_use", "id": "toolu_2", "name": "system_info", "input": {"action": "get_network"}}
+                ],
+                "stop_reason": "tool_use"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = AnthropicClient::new(&mock_server.uri(), "test-key".to_string());
+        let tools = vec![Tool::function("system_info", "System info", json!({"type": "object"}))];
+        let reply = client
+            .chat("claude-3-5-sonnet-latest", &[ChatMessage::user("Network?")], &tools)
+            .await
+            .unwrap();
+
+        assert_eq!(reply.content, "Let me check.");
+        assert_eq!(reply.tool_calls[0].id.as_deref(), Some("toolu_2"));
+        assert_eq!(reply.tool_calls[0].function.arguments["action"], "get_network");
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::anthropic::{self, AnthropicClient};
+use crate::ollama::{ChatMessage, OllamaClient, Tool};
+use crate::openai::{self, OpenAiClient};
+
+/// Which LLM service the client talks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// A local Ollama server
+    Ollama,
+    /// Any service exposing the OpenAI `/v1/chat/completions` API
+    Openai,
+    /// The Anthropic Messages API
+    Anthropic,
+}
+
+/// A chat model that can call tools. Messages and tools use the Ollama wire
+/// types; other backends convert to and from their own formats.
+#[async_trait]
+pub trait LlmBackend: Send + Sync {
+    /// Sends a conversation and returns the model's reply, which may contain
+    /// `tool_calls` instead of text
+    async fn chat(&self, model: &str, messages: &[ChatMessage], tools: &[Tool]) -> Result<ChatMessage>;
+
+    /// Like `chat`, but calls `on_token` with content as it is generated.
+    /// Backends without streaming support deliver the whole reply at once.
+    async fn chat_streaming(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        tools: &[Tool],
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<ChatMessage> {
+        let reply = self.chat(model, messages, tools).await?;
+        on_token(&reply.content);
+        Ok(reply)
+    }
+
+    /// Answers a single prompt without tools
+    async fn generate(&self, model: &str, prompt: &str) -> Result<String> {
+        let reply = self.chat(model, &[ChatMessage::user(prompt)], &[]).await?;
+        Ok(reply.content)
+    }
+
+    /// Like `generate`, but calls `on_token` with the answer as it is generated
+    async fn generate_streaming(
+        &self,
+        model: &str,
+        prompt: &str,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<String> {
+        let reply = self
+            .chat_streaming(model, &[ChatMessage::user(prompt)], &[], on_token)
+            .await?;
+        Ok(reply.content)
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OllamaClient {
+    async fn chat(&self, model: &str, messages: &[ChatMessage], tools: &[Tool]) -> Result<ChatMessage> {
+        OllamaClient::chat(self, model, messages, tools).await
+    }
+
+    async fn chat_streaming(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        tools: &[Tool],
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<ChatMessage> {
+        OllamaClient::chat_streaming(self, model, messages, tools, on_token).await
+    }
+
+    async fn generate(&self, model: &str, prompt: &str) -> Result<String> {
+        OllamaClient::generate(self, model, prompt).await
+    }
+
+    async fn generate_streaming(
+        &self,
+        model: &str,
+        prompt: &str,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<String> {
+        OllamaClient::generate_streaming(self, model, prompt, on_token).await
+    }
+}
+
+/// Creates the backend selected on the command line. Hosted backends read
+/// their API key from `OPENAI_API_KEY` or `ANTHROPIC_API_KEY`.
+pub fn create(kind: BackendKind, ollama_url: &str, api_url: Option<&str>) -> Result<Box<dyn LlmBackend>> {
+    Ok(match kind {
+        BackendKind::Ollama => Box::new(OllamaClient::new(ollama_url)),
+        BackendKind::Openai => Box::new(OpenAiClient::new(
+            api_url.unwrap_or(openai::DEFAULT_BASE_URL),
+            std::env::var("OPENAI_API_KEY").ok(),
+        )),
+        BackendKind::Anthropic => {
+            let api_key = std::env::var("ANTHROPIC_API_KEY")
+                .map_err(|_| anyhow::anyhow!("ANTHROPIC_API_KEY must be set to use the anthropic backend"))?;
+            Box::new(AnthropicClient::new(
+                api_url.unwrap_or(anthropic::DEFAULT_BASE_URL),
+                api_key,
+            ))
+        }
+    })
+}
@@ -0,0 +1,171 @@
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Instant;
+
+use crate::agent::render_content;
+use crate::mcp::{ContentBlock, McpClient};
+
+/// One entry of the `call-tools` input array
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchCall {
+    pub name: String,
+    #[serde(default)]
+    pub args: serde_json::Map<String, Value>,
+}
+
+/// Outcome of one call, in the order the calls were given
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub name: String,
+    pub success: bool,
+    pub duration_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<Vec<ContentBlock>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Combined report printed by `call-tools`
+#[derive(Debug, Serialize)]
+pub struct BatchReport {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchResult>,
+}
+
+impl BatchReport {
+    pub fn print_text(&self) {
+        for result in &self.results {
+            if result.success {
+                println!("[ok] {} ({} ms)", result.name, result.duration_ms);
+                let output = render_content(result.content.as_deref().unwrap_or_default());
+                for line in output.trim_end().lines() {
+                    println!("    {}", line);
+                }
+            } else {
+                println!(
+                    "[failed] {} ({} ms): {}",
+                    result.name,
+                    result.duration_ms,
+                    result.error.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        println!("\n{} succeeded, {} failed", self.succeeded, self.failed);
+    }
+}
+
+/// Runs `calls` with at most `concurrency` in flight at once
+pub async fn run(client: &McpClient, calls: Vec<BatchCall>, concurrency: usize) -> BatchReport {
+    let results: Vec<BatchResult> = stream::iter(calls)
+        .map(|call| async move {
+            let started = Instant::now();
+            let result = client.call_tool(&call.name, call.args).await;
+            let duration_ms = started.elapsed().as_millis();
+            match result {
+                Ok(content) => BatchResult {
+                    name: call.name,
+                    success: true,
+                    duration_ms,
+                    content: Some(content),
+                    error: None,
+                },
+                Err(e) => BatchResult {
+                    name: call.name,
+                    success: false,
+                    duration_ms,
+                    content: None,
+                    error: Some(e.to_string()),
+                },
+            }
+        })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let succeeded = results.iter().filter(|result| result.success).count();
+    BatchReport {
+        succeeded,
+        failed: results.len() - succeeded,
+        results,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_run_keeps_order_and_reports_failures() {
+        let mock_server = MockServer::start().await;
+
+        // The first call is the slowest, so results must not come back in completion order
+        Mock::given(method("POST"))
+            .and(path("/tools/call"))
+            .and(body_partial_json(json!({"tool_name": "slow"})))
+            .respond_with(ResponseTemplate::new(200)
+                .set_delay(Duration::from_millis(100))
+                .set_body_json(json!({"success": true, "content": [{"type": "text", "text": "done"}], "error": null})))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/tools/call"))
+            .and(body_partial_json(json!({"tool_name": "broken"})))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_json(json!({"success": false, "content": [], "error": "boom"})))
+            .mount(&mock_server)
+            .await;
+
+        let calls: Vec<BatchCall> = serde_json::from_value(json!([
+            {"name": "slow", "args": {"x": 1}},
+            {"name": "broken"}
+        ]))
+        .unwrap();
+
+        let client = McpClient::new(&mock_server.uri());
+        let report = run(&client, calls, 2).await;
+
+        assert_eq!((report.succeeded, report.failed), (1, 1));
+        assert_eq!(report.results[0].name, "slow");
+        assert!(report.results[0].success);
+        assert_eq!(report.results[1].name, "broken");
+        assert!(report.results[1].error.as_deref().unwrap().contains("boom"));
+    }
+
+    #[tokio::test]
+    async fn test_run_bounds_concurrency() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/tools/call"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_delay(Duration::from_millis(100))
+                .set_body_json(json!({"success": true, "content": [], "error": null})))
+            .mount(&mock_server)
+            .await;
+
+        let calls = vec![BatchCall { name: "t".to_string(), args: Default::default() }; 4];
+        let client = McpClient::new(&mock_server.uri());
+
+        // Four 100 ms calls, two at a time, take at least two rounds
+        let started = Instant::now();
+        let report = run(&client, calls, 2).await;
+        assert_eq!(report.succeeded, 4);
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_rejects_unknown_fields() {
+        let result: Result<Vec<BatchCall>, _> =
+            serde_json::from_value(json!([{"name": "t", "arguments": {}}]));
+        assert!(result.is_err());
+    }
+}
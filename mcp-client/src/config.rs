@@ -0,0 +1,127 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::backend::BackendKind;
+use crate::mcp::Protocol;
+
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+pub const DEFAULT_MCP_URL: &str = "http://localhost:3001";
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// Settings a profile can provide; anything left out falls back to the
+/// command line flags or the built-in defaults
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub ollama_url: Option<String>,
+    pub mcp_url: Option<String>,
+    pub mcp_protocol: Option<Protocol>,
+    pub model: Option<String>,
+    pub log_level: Option<String>,
+    pub backend: Option<BackendKind>,
+    pub api_url: Option<String>,
+}
+
+/// Contents of `config.toml`:
+///
+/// ```toml
+/// default_profile = "home"
+///
+/// [profiles.home]
+/// mcp_url = "http://homelab:3001"
+/// model = "llama3.1"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+impl ConfigFile {
+    /// `MCP_CLIENT_CONFIG` if set, otherwise `$XDG_CONFIG_HOME/mcp-client/config.toml`
+    /// or `~/.config/mcp-client/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var("MCP_CLIENT_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+
+        let config_home = match std::env::var("XDG_CONFIG_HOME") {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => Path::new(&std::env::var("HOME").ok()?).join(".config"),
+        };
+        Some(config_home.join("mcp-client").join("config.toml"))
+    }
+
+    /// Reads the config file; a missing file is the same as an empty one
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents)
+                .with_context(|| format!("Invalid config file {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read config file {}", path.display())),
+        }
+    }
+
+    /// The profile named on the command line, else `default_profile`, else a
+    /// profile called `default` if there is one. Naming a missing profile is an error.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        match name.or(self.default_profile.as_deref()) {
+            Some(name) => self.profiles.get(name).cloned().ok_or_else(|| {
+                anyhow::anyhow!("Profile '{}' not found in config file", name)
+            }),
+            None => Ok(self.profiles.get("default").cloned().unwrap_or_default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default_profile = "home"
+
+[profiles.home]
+mcp_url = "http://homelab:3001"
+model = "llama3.1"
+
+[profiles.cloud]
+backend = "openai"
+model = "gpt-4o-mini"
+log_level = "warn"
+"#;
+
+    #[test]
+    fn test_profile_selection() {
+        let config: ConfigFile = toml::from_str(CONFIG).unwrap();
+
+        let home = config.profile(None).unwrap();
+        assert_eq!(home.mcp_url.as_deref(), Some("http://homelab:3001"));
+        assert_eq!(home.ollama_url, None);
+
+        let cloud = config.profile(Some("cloud")).unwrap();
+        assert_eq!(cloud.backend, Some(BackendKind::Openai));
+        assert_eq!(cloud.log_level.as_deref(), Some("warn"));
+
+        assert!(config.profile(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_missing_file_is_empty_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ConfigFile::load(&dir.path().join("config.toml")).unwrap();
+        assert_eq!(config.profile(None).unwrap(), Profile::default());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[profiles.home]\nmcp-url = \"typo\"\n").unwrap();
+        assert!(ConfigFile::load(&path).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::agent::TranscriptEntry;
+use crate::ollama::ChatMessage;
+
+/// Summary of a stored conversation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConversationMeta {
+    pub id: String,
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+    pub title: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    pub meta: ConversationMeta,
+    pub messages: Vec<ChatMessage>,
+}
+
+impl Conversation {
+    /// The conversation as transcript entries, for display
+    pub fn transcript(&self) -> Vec<TranscriptEntry> {
+        let mut entries = Vec::new();
+        for message in &self.messages {
+            match message.role.as_str() {
+                "user" => entries.push(TranscriptEntry::User(message.content.clone())),
+                "assistant" => {
+                    if !message.content.is_empty() {
+                        entries.push(TranscriptEntry::Assistant(message.content.clone()));
+                    }
+                    for call in &message.tool_calls {
+                        entries.push(TranscriptEntry::ToolCall {
+                            name: call.function.name.clone(),
+                            arguments: call.function.arguments.clone(),
+                        });
+                    }
+                }
+                "tool" => entries.push(TranscriptEntry::ToolResult {
+                    name: message.tool_name.clone().unwrap_or_default(),
+                    output: message.content.clone(),
+                    is_error: false,
+                }),
+                _ => {}
+            }
+        }
+        entries
+    }
+}
+
+/// One line of a conversation file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum HistoryRecord {
+    Meta(ConversationMeta),
+    Message { message: ChatMessage },
+}
+
+/// Stores each conversation as a JSONL file: a metadata line followed by one
+/// line per chat message, including tool calls and their results.
+pub struct HistoryStore {
+    dir: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Uses `MCP_CLIENT_HISTORY_DIR` if set, otherwise
+    /// `$XDG_DATA_HOME/mcp-client/history` or `~/.local/share/mcp-client/history`
+    pub fn from_env() -> Result<Self> {
+        if let Ok(dir) = std::env::var("MCP_CLIENT_HISTORY_DIR") {
+            return Ok(Self::new(dir));
+        }
+
+        let data_home = match std::env::var("XDG_DATA_HOME") {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let home = std::env::var("HOME").context("HOME is not set")?;
+                Path::new(&home).join(".local").join("share")
+            }
+        };
+        Ok(Self::new(data_home.join("mcp-client").join("history")))
+    }
+
+    pub fn create(&self, model: &str, title: &str) -> Result<ConversationMeta> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create history directory {}", self.dir.display()))?;
+
+        let created_at = Utc::now();
+        let mut meta = ConversationMeta {
+            id: created_at.format("%Y%m%d-%H%M%S-%3f").to_string(),
+            model: model.to_string(),
+            created_at,
+            title: title.lines().next().unwrap_or_default().chars().take(80).collect(),
+        };
+
+        // Conversations started in the same millisecond get a numbered suffix
+        // rather than overwriting each other
+        let base = meta.id.clone();
+        let mut suffix = 1;
+        let mut file = loop {
+            match OpenOptions::new().write(true).create_new(true).open(self.path(&meta.id)?) {
+                Ok(file) => break file,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    meta.id = format!("{}-{}", base, suffix);
+                    suffix += 1;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create conversation {}", meta.id));
+                }
+            }
+        };
+        writeln!(file, "{}", serde_json::to_string(&HistoryRecord::Meta(meta.clone()))?)?;
+        Ok(meta)
+    }
+
+    pub fn append(&self, id: &str, messages: &[ChatMessage]) -> Result<()> {
+        let path = self.path(id)?;
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Conversation {} not found", id))?;
+
+        for message in messages {
+            let record = HistoryRecord::Message { message: message.clone() };
+            writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        }
+        Ok(())
+    }
+
+    /// All stored conversations, newest first
+    pub fn list(&self) -> Result<Vec<ConversationMeta>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut conversations = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let mut first_line = String::new();
+            BufReader::new(File::open(&path)?).read_line(&mut first_line)?;
+            match serde_json::from_str(&first_line) {
+                Ok(HistoryRecord::Meta(meta)) => conversations.push(meta),
+                _ => tracing::warn!("Skipping unreadable history file {}", path.display()),
+            }
+        }
+
+        conversations.sort_by_key(|meta| std::cmp::Reverse(meta.created_at));
+        Ok(conversations)
+    }
+
+    pub fn load(&self, id: &str) -> Result<Conversation> {
+        let path = self.path(id)?;
+        let file = File::open(&path).with_context(|| format!("Conversation {} not found", id))?;
+
+        let mut meta = None;
+        let mut messages = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line)
+                .with_context(|| format!("Corrupt history file {}", path.display()))?
+            {
+                HistoryRecord::Meta(m) => meta = Some(m),
+                HistoryRecord::Message { message } => messages.push(message),
+            }
+        }
+
+        let meta = meta.ok_or_else(|| anyhow::anyhow!("Conversation {} has no metadata", id))?;
+        Ok(Conversation { meta, messages })
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(anyhow::anyhow!("Invalid conversation id: {}", id));
+        }
+        Ok(self.dir.join(format!("{}.jsonl", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ollama::{FunctionCall, ToolCall};
+
+    #[test]
+    fn test_create_append_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path());
+
+        let meta = store.create("llama3.1", "How full are my disks?\nThanks").unwrap();
+        assert_eq!(meta.title, "How full are my disks?");
+
+        let mut tool_request = ChatMessage::assistant("");
+        tool_request.tool_calls.push(ToolCall {
+            id: None,
+            function: FunctionCall {
+                name: "system_info".to_string(),
+                arguments: serde_json::json!({"action": "get_disks"}).as_object().unwrap().clone(),
+            },
+        });
+        store.append(&meta.id, &[
+            ChatMessage::system("Be helpful"),
+            ChatMessage::user("How full are my disks?"),
+            tool_request,
+            ChatMessage::tool("system_info", None, "/: 40%"),
+        ]).unwrap();
+        store.append(&meta.id, &[ChatMessage::assistant("Your root disk is 40% full.")]).unwrap();
+
+        let conversation = store.load(&meta.id).unwrap();
+        assert_eq!(conversation.meta, meta);
+        assert_eq!(conversation.messages.len(), 5);
+        assert_eq!(conversation.messages[2].tool_calls[0].function.name, "system_info");
+
+        let transcript = conversation.transcript();
+        assert_eq!(transcript.len(), 4);
+        assert_eq!(transcript[2].to_string(), "Tool result (system_info): /: 40%");
+    }
+
+    #[test]
+    fn test_list_is_newest_first_and_ignores_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path().join("history"));
+        assert!(store.list().unwrap().is_empty());
+
+        let first = store.create("llama2", "first").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let second = store.create("llama2", "second").unwrap();
+
+        let ids: Vec<String> = store.list().unwrap().into_iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![second.id, first.id]);
+    }
+
+    #[test]
+    fn test_conversations_created_together_get_their_own_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path());
+
+        let ids: Vec<String> = (0..20).map(|i| store.create("llama3", &format!("chat {}", i)).unwrap().id).collect();
+
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+        assert_eq!(store.list().unwrap().len(), ids.len());
+        for (i, id) in ids.iter().enumerate() {
+            assert_eq!(store.load(id).unwrap().meta.title, format!("chat {}", i));
+        }
+    }
+
+    #[test]
+    fn test_rejects_path_like_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path());
+        assert!(store.load("../etc/passwd").is_err());
+        assert!(store.append("a/b", &[]).is_err());
+    }
+}
@@ -1,23 +1,75 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
-use tracing::{info, error};
+use std::io::Write;
+use std::process::ExitCode;
+use tracing::{info, error, warn};
 use tracing_subscriber::{prelude::*, EnvFilter};
 
+mod agent;
+mod anthropic;
+mod backend;
+mod batch;
+mod config;
+mod history;
 mod ollama;
+mod openai;
 mod mcp;
+mod output;
+mod prompt;
+mod rag;
+
+use mcp_core::logging::{LogFormat, LogOutput, LogRotation};
 
 #[derive(Parser)]
 #[command(name = "mcp-client")]
 #[command(about = "A CLI tool to interact with Ollama and MCP server")]
 struct Cli {
-    #[arg(long, default_value = "http://localhost:11434")]
-    ollama_url: String,
+    /// Ollama server URL [default: http://localhost:11434]
+    #[arg(long)]
+    ollama_url: Option<String>,
     
-    #[arg(long, default_value = "http://localhost:3001")]
-    mcp_url: String,
+    /// MCP server URL [default: http://localhost:3001]
+    #[arg(long)]
+    mcp_url: Option<String>,
+
+    /// How to talk to the MCP server; jsonrpc speaks MCP directly to mcp-server's /mcp endpoint [default: rest]
+    #[arg(long, value_enum)]
+    mcp_protocol: Option<mcp::Protocol>,
+
+    /// LLM service used by ask, chat and history resume [default: ollama]
+    #[arg(long, value_enum)]
+    backend: Option<backend::BackendKind>,
+
+    /// Base URL for the openai or anthropic backend, e.g. a local OpenAI-compatible server
+    #[arg(long)]
+    api_url: Option<String>,
     
-    #[arg(long, default_value = "info")]
-    log_level: String,
+    /// Log level [default: info]
+    #[arg(long)]
+    log_level: Option<String>,
+
+    #[arg(long, value_enum, global = true, default_value = "pretty")]
+    log_format: LogFormat,
+
+    /// Write logs to this file instead of stdout or stderr
+    #[arg(long, global = true, value_name = "FILE")]
+    log_file: Option<std::path::PathBuf>,
+
+    /// How often --log-file starts a new file
+    #[arg(long, value_enum, global = true, default_value = "daily")]
+    log_rotation: LogRotation,
+
+    /// Profile from ~/.config/mcp-client/config.toml to take defaults from
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Print model output only once it is complete instead of streaming it
+    #[arg(long, global = true)]
+    no_stream: bool,
+
+    /// Output format for list-tools, call-tool(s) and the model commands; json and yaml send logs to stderr
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    output: output::OutputFormat,
     
     #[command(subcommand)]
     command: Commands,
@@ -26,8 +78,19 @@ struct Cli {
 #[derive(clap::Subcommand)]
 enum Commands {
     /// List available tools from MCP server
-    ListTools,
+    ListTools {
+        /// Only tools with this tag (read-only, destructive, network, slow); repeat or separate
+        /// with commas to require several
+        #[arg(long, value_delimiter = ',')]
+        tag: Vec<String>,
+    },
     
+    /// Print the tool list in another agent framework's tool-definition format
+    ExportTools {
+        #[arg(long, value_enum, default_value = "anthropic")]
+        format: agent::ToolSchemaFormat,
+    },
+
     /// Call a specific tool
     CallTool {
         /// Name of the tool to call
@@ -39,59 +102,254 @@ enum Commands {
         args: Option<String>,
     },
     
+    /// Call several tools concurrently and print a combined report
+    CallTools {
+        /// JSON array of calls, e.g. '[{"name": "system_info", "args": {"action": "get_disks"}}]'
+        #[arg(long, conflicts_with = "file", required_unless_present = "file")]
+        calls: Option<String>,
+
+        /// File holding the JSON array of calls
+        #[arg(long)]
+        file: Option<std::path::PathBuf>,
+
+        /// Maximum number of calls in flight at once
+        #[arg(long, default_value = "4")]
+        concurrency: usize,
+    },
+    
     /// List available Ollama models
     ListModels,
+
+    /// Download a model to the Ollama server, showing progress
+    PullModel {
+        /// Name of the model, e.g. llama3.1 or llama3.1:70b
+        #[arg(long)]
+        name: String,
+    },
+
+    /// Remove a model from the Ollama server
+    DeleteModel {
+        /// Name of the model to remove
+        #[arg(long)]
+        name: String,
+    },
+
+    /// Show details of a model on the Ollama server
+    ShowModel {
+        /// Name of the model
+        #[arg(long)]
+        name: String,
+    },
     
     /// Ask a question to an Ollama model
     Ask {
-        /// Name of the model to use
+        /// Name of the model to use (defaults to the profile's model)
         #[arg(long)]
-        model: String,
+        model: Option<String>,
         
         /// The prompt/question to send
         #[arg(long)]
         prompt: String,
+
+        #[command(flatten)]
+        prompt_args: prompt::PromptArgs,
     },
 
     /// Chat with a model and let it use MCP tools
     Chat {
-        /// Name of the model to use
+        /// Name of the model to use (defaults to the profile's model)
         #[arg(long)]
-        model: String,
+        model: Option<String>,
         
         /// The prompt/question to send
         #[arg(long)]
         prompt: String,
+
+        /// Maximum number of model turns before giving up
+        #[arg(long, default_value = "5")]
+        max_iterations: usize,
+
+        /// Ask for approval before running each tool call the model proposes
+        #[arg(long)]
+        confirm: bool,
+
+        #[command(flatten)]
+        prompt_args: prompt::PromptArgs,
+    },
+
+    /// Chunk local files, embed them with Ollama and store them on the MCP server
+    Embed {
+        /// File or directory to index; may be repeated
+        #[arg(long = "path", required = true)]
+        paths: Vec<std::path::PathBuf>,
+
+        /// Collection to store the chunks in
+        #[arg(long, default_value = "default")]
+        collection: String,
+
+        /// Ollama embedding model
+        #[arg(long, default_value = rag::DEFAULT_EMBED_MODEL)]
+        embed_model: String,
+
+        /// Maximum chunk length in characters
+        #[arg(long, default_value = "1000")]
+        chunk_size: usize,
+
+        /// Characters shared between consecutive chunks
+        #[arg(long, default_value = "200")]
+        overlap: usize,
+    },
+
+    /// Answer a question using the most relevant chunks stored by `embed`
+    RagChat {
+        /// Name of the model to use (defaults to the profile's model)
+        #[arg(long)]
+        model: Option<String>,
+
+        /// The prompt/question to send
+        #[arg(long)]
+        prompt: String,
+
+        /// Collection to search
+        #[arg(long, default_value = "default")]
+        collection: String,
+
+        /// Ollama embedding model; must match the one used by `embed`
+        #[arg(long, default_value = rag::DEFAULT_EMBED_MODEL)]
+        embed_model: String,
+
+        /// Number of chunks to put in the prompt
+        #[arg(long, default_value = "4")]
+        top_k: usize,
+    },
+
+    /// Browse and continue saved chat conversations
+    History {
+        #[command(subcommand)]
+        command: HistoryCommands,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum HistoryCommands {
+    /// List saved conversations, newest first
+    List,
+
+    /// Show the full transcript of a conversation
+    Show {
+        /// Conversation id, as printed by `history list`
+        #[arg(long)]
+        id: String,
+    },
+
+    /// Continue a conversation with a new prompt
+    Resume {
+        /// Conversation id, as printed by `history list`
+        #[arg(long)]
+        id: String,
+
+        /// The follow-up prompt/question to send
+        #[arg(long)]
+        prompt: String,
+
+        /// Model to use instead of the one the conversation started with
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Maximum number of model turns before giving up
+        #[arg(long, default_value = "5")]
+        max_iterations: usize,
+
+        /// Ask for approval before running each tool call the model proposes
+        #[arg(long)]
+        confirm: bool,
     },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let cli = Cli::parse();
+
+    // Flags win over the selected profile, which wins over built-in defaults
+    let profile = match config::ConfigFile::default_path() {
+        Some(path) => config::ConfigFile::load(&path)?.profile(cli.profile.as_deref())?,
+        None if cli.profile.is_some() => anyhow::bail!("No config file location; HOME is not set"),
+        None => config::Profile::default(),
+    };
+    let settings = Settings {
+        ollama_url: cli.ollama_url.or(profile.ollama_url)
+            .unwrap_or_else(|| config::DEFAULT_OLLAMA_URL.to_string()),
+        mcp_url: cli.mcp_url.or(profile.mcp_url)
+            .unwrap_or_else(|| config::DEFAULT_MCP_URL.to_string()),
+        mcp_protocol: cli.mcp_protocol.or(profile.mcp_protocol).unwrap_or(mcp::Protocol::Rest),
+        backend: cli.backend.or(profile.backend).unwrap_or(backend::BackendKind::Ollama),
+        api_url: cli.api_url.or(profile.api_url),
+        model: profile.model,
+    };
+    let log_level = cli.log_level.or(profile.log_level)
+        .unwrap_or_else(|| config::DEFAULT_LOG_LEVEL.to_string());
     
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::new(&cli.log_level))
+    // Initialize logging, keeping stdout clean for structured output
+    let log_output = LogOutput {
+        format: cli.log_format,
+        file: cli.log_file.clone(),
+        rotation: cli.log_rotation,
+        stderr: cli.output.is_structured() || matches!(cli.command, Commands::ExportTools { .. }),
+    };
+    let (log_layer, _log_guard) = log_output.layer()?;
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(&log_level))
+        .with(log_layer)
         .init();
         
     info!("Starting MCP Client");
+
+    // Failures of list-tools, export-tools, call-tool(s) and the model commands are reflected in the exit code
+    let mut exit_code = ExitCode::SUCCESS;
     
     match cli.command {
-        Commands::ListTools => {
-            let client = mcp::McpClient::new(&cli.mcp_url);
+        Commands::ListTools { tag } => {
+            let client = settings.mcp_client();
             match client.list_tools().await {
                 Ok(tools) => {
-                    println!("Available tools:");
-                    for tool in tools {
-                        println!("- {}: {}", tool.name, tool.description);
-                    }
+                    let tools: Vec<_> = tools.into_iter().filter(|tool| tool.has_tags(&tag)).collect();
+                    cli.output.emit(&tools, |tools| {
+                        println!("Available tools:");
+                        for tool in tools {
+                            if tool.tags.is_empty() {
+                                println!("- {}: {}", tool.name, tool.description);
+                            } else {
+                                println!("- {}: {} [{}]", tool.name, tool.description, tool.tags.join(", "));
+                            }
+                        }
+                    })?
+                }
+                Err(e) => {
+                    error!("Failed to list tools: {}", e);
+                    exit_code = ExitCode::FAILURE;
                 }
-                Err(e) => error!("Failed to list tools: {}", e),
             }
         }
         
+        Commands::ExportTools { format } => {
+            let client = settings.mcp_client();
+            match client.list_tools().await {
+                Ok(tools) => {
+                    let exported = agent::export_tools(&tools, format);
+                    match cli.output {
+                        output::OutputFormat::Yaml => cli.output.emit(&exported, |_| {})?,
+                        _ => println!("{}", serde_json::to_string_pretty(&exported)?),
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to list tools: {}", e);
+                    exit_code = ExitCode::FAILURE;
+                }
+            }
+        }
+
         Commands::CallTool { name, args } => {
-            let client = mcp::McpClient::new(&cli.mcp_url);
+            let client = settings.mcp_client();
             let args = if let Some(args_str) = args {
                 serde_json::from_str(&args_str)?
             } else {
@@ -99,169 +357,472 @@ async fn main() -> Result<()> {
             };
             
             match client.call_tool(&name, args).await {
-                Ok(response) => println!("{}", serde_json::to_string_pretty(&response)?),
-                Err(e) => error!("Failed to call tool: {}", e),
+                Ok(content) if cli.output.is_structured() => cli.output.emit(
+                    &serde_json::json!({ "tool": name, "success": true, "content": content }),
+                    |_| {},
+                )?,
+                Ok(content) => println!("{}", serde_json::to_string_pretty(&content)?),
+                Err(e) => {
+                    error!("Failed to call tool: {}", e);
+                    if cli.output.is_structured() {
+                        cli.output.emit(
+                            &serde_json::json!({ "tool": name, "success": false, "error": e.to_string() }),
+                            |_| {},
+                        )?;
+                    }
+                    exit_code = ExitCode::FAILURE;
+                }
+            }
+        }
+        
+        Commands::CallTools { calls, file, concurrency } => {
+            let calls = match (calls, file) {
+                (Some(calls), _) => calls,
+                (None, Some(file)) => std::fs::read_to_string(&file)
+                    .with_context(|| format!("Failed to read {}", file.display()))?,
+                (None, None) => unreachable!("clap requires --calls or --file"),
+            };
+            let calls: Vec<batch::BatchCall> = serde_json::from_str(&calls)
+                .context("Calls must be a JSON array of {\"name\": ..., \"args\": {...}} objects")?;
+
+            let client = settings.mcp_client();
+            let report = batch::run(&client, calls, concurrency).await;
+            cli.output.emit(&report, batch::BatchReport::print_text)?;
+            if report.failed > 0 {
+                exit_code = ExitCode::FAILURE;
             }
         }
         
         Commands::ListModels => {
-            let client = ollama::OllamaClient::new(&cli.ollama_url);
+            let client = ollama::OllamaClient::new(&settings.ollama_url);
             match client.list_models().await {
-                Ok(models) => {
+                Ok(models) => cli.output.emit(&models, |models| {
                     println!("Available models:");
                     for model in models {
                         println!("- {}", model.name);
                     }
+                })?,
+                Err(e) => {
+                    error!("Failed to list models: {}", e);
+                    exit_code = ExitCode::FAILURE;
                 }
-                Err(e) => error!("Failed to list models: {}", e),
             }
         }
         
-        Commands::Ask { model, prompt } => {
-            let client = ollama::OllamaClient::new(&cli.ollama_url);
-            match client.generate(&model, &prompt).await {
-                Ok(response) => println!("{}", response),
-                Err(e) => error!("Failed to generate response: {}", e),
+        Commands::PullModel { name } => {
+            let client = ollama::OllamaClient::new(&settings.ollama_url);
+            match pull_with_progress(&client, &name, !cli.output.is_structured()).await {
+                Ok(()) => cli.output.emit(
+                    &serde_json::json!({ "model": name, "status": "success" }),
+                    |_| println!("Pulled model {}", name),
+                )?,
+                Err(e) => {
+                    error!("Failed to pull model: {}", e);
+                    exit_code = ExitCode::FAILURE;
+                }
             }
         }
 
-        Commands::Chat { model, prompt } => {
-            let mcp_client = mcp::McpClient::new(&cli.mcp_url);
-            let ollama_client = ollama::OllamaClient::new(&cli.ollama_url);
+        Commands::DeleteModel { name } => {
+            let client = ollama::OllamaClient::new(&settings.ollama_url);
+            match client.delete_model(&name).await {
+                Ok(()) => cli.output.emit(
+                    &serde_json::json!({ "model": name, "status": "deleted" }),
+                    |_| println!("Deleted model {}", name),
+                )?,
+                Err(e) => {
+                    error!("Failed to delete model: {}", e);
+                    exit_code = ExitCode::FAILURE;
+                }
+            }
+        }
 
-            // First get the list of available tools
-            let tools = match mcp_client.list_tools().await {
-                Ok(tools) => tools,
+        Commands::ShowModel { name } => {
+            let client = ollama::OllamaClient::new(&settings.ollama_url);
+            match client.show_model(&name).await {
+                Ok(info) => cli.output.emit(&info, |info| {
+                    println!("Model: {}", name);
+                    println!("  Family: {}", info.details.family);
+                    println!("  Parameters: {}", info.details.parameter_size);
+                    println!("  Quantization: {}", info.details.quantization_level);
+                    println!("  Format: {}", info.details.format);
+                    if !info.parameters.is_empty() {
+                        println!("\nParameters:\n{}", info.parameters);
+                    }
+                    if !info.template.is_empty() {
+                        println!("\nTemplate:\n{}", info.template);
+                    }
+                })?,
                 Err(e) => {
-                    error!("Failed to list tools: {}", e);
-                    return Ok(());
+                    error!("Failed to show model: {}", e);
+                    exit_code = ExitCode::FAILURE;
                 }
+            }
+        }
+        
+        Commands::Ask { model, prompt, prompt_args } => {
+            let model = settings.model(model)?;
+            let llm = backend::create(settings.backend, &settings.ollama_url, settings.api_url.as_deref())?;
+            let system_prompt = match prompt_args.template()? {
+                Some(template) => Some(prompt::render(
+                    &template,
+                    &prompt::PromptVars { tools: &[], input: &prompt, model: &model },
+                )?),
+                None => None,
             };
-            
-            // Create a system prompt that describes the available tools
-            let mut system_prompt = String::from(
-                "You are a helpful AI assistant with access to the following tools:\n\n"
-            );
-            
-            for tool in &tools {
-                system_prompt.push_str(&format!(
-                    "Tool: {}\nDescription: {}\nInput Schema: {}\n\n",
-                    tool.name,
-                    tool.description,
-                    serde_json::to_string_pretty(&tool.input_schema)?
-                ));
+            let result = ask(llm.as_ref(), &model, &prompt, system_prompt, !cli.no_stream).await;
+            match result {
+                Ok(response) if cli.no_stream => println!("{}", response),
+                Ok(_) => println!(),
+                Err(e) => {
+                    if !cli.no_stream {
+                        println!();
+                    }
+                    error!("Failed to generate response: {}", e);
+                }
             }
-            
-            system_prompt.push_str(
-                "\nRules for our interaction:\n\n"
-            );
-            system_prompt.push_str(
-                "1. When I ask about available tools, give me a natural language description of each tool.\n\n"
-            );
-            system_prompt.push_str(
-                "2. When you need to USE a tool, your entire response must be ONLY the JSON tool call:\n"
-            );
-            system_prompt.push_str(
-                r#"{"type":"tool","tool_name":"example","arguments":{"key":"value"}}"#
-            );
-            system_prompt.push_str(
-                "\n\nCritical rules for tool usage:\n"
-            );
-            system_prompt.push_str(
-                "- Your ENTIRE response must be the JSON object - no other text\n"
-            );
-            system_prompt.push_str(
-                "- No explanations before or after the JSON\n"
-            );
-            system_prompt.push_str(
-                "- No 'I will use' or other commentary\n"
-            );
-            system_prompt.push_str(
-                "- One JSON object on a single line\n"
-            );
-            system_prompt.push_str(
-                "- After getting tool results, you can explain them in natural language\n\n"
-            );
-            system_prompt.push_str(
-                "What would you like me to do?"
-            );
-
-            // Combine system prompt with user's prompt
-            let full_prompt = format!("{}\n\nUser: {}", system_prompt, prompt);
-            
-            // Get the model's response
-            match ollama_client.generate(&model, &full_prompt).await {
+        }
+
+        Commands::Embed { paths, collection, embed_model, chunk_size, overlap } => {
+            let ollama = ollama::OllamaClient::new(&settings.ollama_url);
+            let mcp_client = settings.mcp_client();
+            let options = rag::RagOptions {
+                ollama: &ollama,
+                mcp: &mcp_client,
+                embed_model: &embed_model,
+                collection: &collection,
+            };
+            match rag::embed_files(&options, &paths, chunk_size, overlap).await {
+                Ok(summary) => println!(
+                    "Stored {} chunks from {} files in collection '{}'",
+                    summary.chunks, summary.files, collection
+                ),
+                Err(e) => {
+                    error!("Failed to embed files: {}", e);
+                    exit_code = ExitCode::FAILURE;
+                }
+            }
+        }
+
+        Commands::RagChat { model, prompt, collection, embed_model, top_k } => {
+            let model = settings.model(model)?;
+            let llm = backend::create(settings.backend, &settings.ollama_url, settings.api_url.as_deref())?;
+            let ollama = ollama::OllamaClient::new(&settings.ollama_url);
+            let mcp_client = settings.mcp_client();
+            let options = rag::RagOptions {
+                ollama: &ollama,
+                mcp: &mcp_client,
+                embed_model: &embed_model,
+                collection: &collection,
+            };
+
+            let chunks = match rag::retrieve(&options, &prompt, top_k).await {
+                Ok(chunks) => chunks,
+                Err(e) => {
+                    error!("Failed to retrieve context: {}", e);
+                    return Ok(ExitCode::FAILURE);
+                }
+            };
+            if chunks.is_empty() {
+                warn!("No stored chunks found in collection '{}'; run `embed` first", collection);
+            }
+
+            match ask(llm.as_ref(), &model, &prompt, Some(rag::context_prompt(&chunks)), !cli.no_stream).await {
                 Ok(response) => {
-                    println!("Raw response from model: {}", response);
-                    
-                    // Extract JSON from the response by looking for the first '{' and last '}'
-                    let json_str = if let (Some(start), Some(end)) = (
-                        response.find('{'),
-                        response.rfind('}').map(|i| i + 1)
-                    ) {
-                        &response[start..end]
-                    } else {
-                        response.trim()
-                    };
-
-                    println!("Extracted JSON: {}", json_str);
-                    match serde_json::from_str::<serde_json::Value>(json_str) {
-                        Ok(tool_call) => {
-                            if tool_call["type"] == "tool" {
-                                if let (Some(tool_name), Some(arguments)) = (
-                                    tool_call["tool_name"].as_str(),
-                                    tool_call["arguments"].as_object()
-                                ) {
-                                    println!("Using tool: {} with arguments: {}", 
-                                        tool_name, 
-                                        serde_json::to_string_pretty(arguments).unwrap()
-                                    );
-                                    
-                                    match mcp_client.call_tool(tool_name, arguments.clone()).await {
-                                        Ok(result) => {
-                                            let mut tool_result = String::new();
-                                            for block in result {
-                                                match block {
-                                                    mcp::ContentBlock::Text { text } => {
-                                                        tool_result.push_str(&text);
-                                                        tool_result.push('\n');
-                                                    }
-                                                }
-                                            }
-                                            println!("Tool result:\n{}", tool_result);
-                                            
-                                            // Ask the model to interpret the results
-                                        let interpret_prompt = format!(
-                                            "I received this result from running a tool:\n\n{}\n\nPlease explain what this means in plain English. Do NOT return JSON - just explain the results as you would to a user.",
-                                            tool_result
-                                        );                                            match ollama_client.generate(&model, &interpret_prompt).await {
-                                                Ok(interpretation) => println!("\nInterpretation:\n{}", interpretation),
-                                                Err(e) => error!("Failed to interpret results: {}", e),
-                                            }
-                                        }
-                                        Err(e) => error!("Failed to call tool: {}. Tool: {}, Args: {}", 
-                                            e, 
-                                            tool_name, 
-                                            serde_json::to_string_pretty(arguments).unwrap()
-                                        ),
-                                    }
-                                } else {
-                                    println!("Invalid tool call format in response: {}", response);
-                                }
-                            } else {
-                                println!("{}", response);
-                            }
+                    if cli.no_stream {
+                        println!("{}", response);
+                    }
+                    println!("\nSources:");
+                    for chunk in &chunks {
+                        println!("- {} (score {:.2})", chunk.source(), chunk.score);
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to generate response: {}", e);
+                    exit_code = ExitCode::FAILURE;
+                }
+            }
+        }
+
+        Commands::Chat { model, prompt, max_iterations, confirm, prompt_args } => {
+            let model = settings.model(model)?;
+            let llm = backend::create(settings.backend, &settings.ollama_url, settings.api_url.as_deref())?;
+            let options = ChatOptions {
+                llm: llm.as_ref(),
+                mcp_url: &settings.mcp_url,
+                mcp_protocol: settings.mcp_protocol,
+                stream: !cli.no_stream,
+                max_iterations,
+                confirm,
+                system_template: prompt_args.template()?,
+            };
+            run_chat(&options, &model, &prompt, None).await?;
+        }
+
+        Commands::History { command } => {
+            let store = history::HistoryStore::from_env()?;
+            match command {
+                HistoryCommands::List => match store.list() {
+                    Ok(conversations) if conversations.is_empty() => println!("No saved conversations"),
+                    Ok(conversations) => {
+                        println!("Saved conversations:");
+                        for meta in conversations {
+                            println!(
+                                "- {} [{}] {} ({})",
+                                meta.id,
+                                meta.model,
+                                meta.title,
+                                meta.created_at.format("%Y-%m-%d %H:%M")
+                            );
                         }
-                        Err(e) => {
-                            println!("Failed to parse JSON: {}", e);
-                            println!("Original response: {}", response);
+                    }
+                    Err(e) => error!("Failed to list conversations: {}", e),
+                },
+
+                HistoryCommands::Show { id } => match store.load(&id) {
+                    Ok(conversation) => {
+                        println!(
+                            "Conversation {} with {} started {}",
+                            conversation.meta.id,
+                            conversation.meta.model,
+                            conversation.meta.created_at.format("%Y-%m-%d %H:%M")
+                        );
+                        for entry in conversation.transcript() {
+                            println!("{}", entry);
                         }
                     }
-                }
-                Err(e) => error!("Failed to generate response: {}", e),
+                    Err(e) => error!("Failed to load conversation: {}", e),
+                },
+
+                HistoryCommands::Resume { id, prompt, model, max_iterations, confirm } => match store.load(&id) {
+                    Ok(conversation) => {
+                        let model = model.unwrap_or_else(|| conversation.meta.model.clone());
+                        let llm = backend::create(settings.backend, &settings.ollama_url, settings.api_url.as_deref())?;
+                        let options = ChatOptions {
+                            llm: llm.as_ref(),
+                            mcp_url: &settings.mcp_url,
+                            mcp_protocol: settings.mcp_protocol,
+                            stream: !cli.no_stream,
+                            max_iterations,
+                            confirm,
+                            // The saved conversation already starts with its system prompt
+                            system_template: None,
+                        };
+                        run_chat(&options, &model, &prompt, Some(conversation)).await?;
+                    }
+                    Err(e) => error!("Failed to load conversation: {}", e),
+                },
             }
         }
     }
     
+    Ok(exit_code)
+}
+
+/// Pulls `model`, drawing one progress bar per downloaded layer on stderr
+/// when `show_progress` is set
+async fn pull_with_progress(client: &ollama::OllamaClient, model: &str, show_progress: bool) -> Result<()> {
+    let bars = if show_progress {
+        indicatif::MultiProgress::new()
+    } else {
+        indicatif::MultiProgress::with_draw_target(indicatif::ProgressDrawTarget::hidden())
+    };
+    let style = indicatif::ProgressStyle::with_template(
+        "{msg:20} [{bar:40}] {bytes}/{total_bytes} ({eta})",
+    )?
+    .progress_chars("=> ");
+    let status = bars.add(indicatif::ProgressBar::new_spinner());
+    let mut layers: std::collections::HashMap<String, indicatif::ProgressBar> = std::collections::HashMap::new();
+
+    let result = client
+        .pull_model(model, |progress| match (&progress.digest, progress.total) {
+            (Some(digest), Some(total)) => {
+                let bar = layers.entry(digest.clone()).or_insert_with(|| {
+                    let short = digest.trim_start_matches("sha256:").chars().take(12).collect::<String>();
+                    bars.insert_before(&status, indicatif::ProgressBar::new(total))
+                        .with_style(style.clone())
+                        .with_message(short)
+                });
+                bar.set_position(progress.completed.unwrap_or_default());
+            }
+            _ => status.set_message(progress.status.clone()),
+        })
+        .await;
+
+    for bar in layers.values() {
+        bar.finish();
+    }
+    status.finish_and_clear();
+    result
+}
+
+/// Connection settings after applying the config profile
+struct Settings {
+    ollama_url: String,
+    mcp_url: String,
+    mcp_protocol: mcp::Protocol,
+    backend: backend::BackendKind,
+    api_url: Option<String>,
+    model: Option<String>,
+}
+
+impl Settings {
+    fn mcp_client(&self) -> mcp::McpClient {
+        mcp::McpClient::new(&self.mcp_url).with_protocol(self.mcp_protocol)
+    }
+
+    /// The `--model` flag, or the profile's model if it was left out
+    fn model(&self, flag: Option<String>) -> Result<String> {
+        flag.or_else(|| self.model.clone())
+            .ok_or_else(|| anyhow::anyhow!("--model is required unless the profile sets a model"))
+    }
+}
+
+struct ChatOptions<'a> {
+    llm: &'a dyn backend::LlmBackend,
+    mcp_url: &'a str,
+    mcp_protocol: mcp::Protocol,
+    stream: bool,
+    max_iterations: usize,
+    /// Ask before each tool call
+    confirm: bool,
+    /// Overrides `prompt::DEFAULT_CHAT_TEMPLATE`
+    system_template: Option<String>,
+}
+
+/// Asks on the terminal whether a tool call may run. Anything but `y` or
+/// `yes`, including end of input, declines it.
+fn confirm_tool_call(tool_name: &str, arguments: &serde_json::Map<String, serde_json::Value>) -> bool {
+    print!(
+        "\nThe model wants to run {} with arguments {}\nAllow? [y/N] ",
+        tool_name,
+        serde_json::Value::Object(arguments.clone())
+    );
+    let _ = std::io::stdout().flush();
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Answers a single prompt without tools, streaming it to stdout unless
+/// `stream` is off. Returns the full answer.
+async fn ask(
+    llm: &dyn backend::LlmBackend,
+    model: &str,
+    prompt: &str,
+    system_prompt: Option<String>,
+    stream: bool,
+) -> Result<String> {
+    let mut print_token = |token: &str| {
+        print!("{}", token);
+        let _ = std::io::stdout().flush();
+    };
+
+    match system_prompt {
+        Some(system_prompt) => {
+            let messages = [ollama::ChatMessage::system(system_prompt), ollama::ChatMessage::user(prompt)];
+            let reply = if stream {
+                llm.chat_streaming(model, &messages, &[], &mut print_token).await?
+            } else {
+                llm.chat(model, &messages, &[]).await?
+            };
+            Ok(reply.content)
+        }
+        None if stream => llm.generate_streaming(model, prompt, &mut print_token).await,
+        None => llm.generate(model, prompt).await,
+    }
+}
+
+/// Runs the tool-using agent on `prompt`, continuing `conversation` if given,
+/// and records the exchange in the history store
+async fn run_chat(
+    options: &ChatOptions<'_>,
+    model: &str,
+    prompt: &str,
+    conversation: Option<history::Conversation>,
+) -> Result<()> {
+    let mcp_client = mcp::McpClient::new(options.mcp_url).with_protocol(options.mcp_protocol);
+
+    // First get the list of available tools
+    let tools = match mcp_client.list_tools().await {
+        Ok(tools) => tools,
+        Err(e) => {
+            error!("Failed to list tools: {}", e);
+            return Ok(());
+        }
+    };
+
+    let template = options.system_template.as_deref().unwrap_or(prompt::DEFAULT_CHAT_TEMPLATE);
+    let system_prompt = prompt::render(template, &prompt::PromptVars { tools: &tools, input: prompt, model })?;
+
+    let agent = agent::Agent::new(
+        options.llm,
+        &mcp_client,
+        model,
+        system_prompt,
+        agent::chat_tools(&tools),
+        options.max_iterations,
+    )
+    .with_streaming(options.stream);
+    let agent = if options.confirm {
+        agent.with_confirmation(&confirm_tool_call)
+    } else {
+        agent
+    };
+
+    let (history, conversation_id) = match conversation {
+        Some(conversation) => (conversation.messages, Some(conversation.meta.id)),
+        None => (Vec::new(), None),
+    };
+
+    let run = match agent.run(history, prompt).await {
+        Ok(run) => run,
+        Err(e) => {
+            error!("Failed to generate response: {}", e);
+            return Ok(());
+        }
+    };
+
+    match &run.outcome {
+        // A streamed answer has already been printed as it was generated
+        agent::AgentOutcome::Answer(_) if options.stream => {}
+        agent::AgentOutcome::Answer(answer) => println!("\nAnswer:\n{}", answer),
+        agent::AgentOutcome::MaxIterations => println!(
+            "\nStopped after {} iterations without a final answer", options.max_iterations
+        ),
+        agent::AgentOutcome::LoopDetected { tool_name } => println!(
+            "\nStopped: the model repeated an identical call to {}", tool_name
+        ),
+    }
+
+    println!("\n--- Transcript ---");
+    for entry in &run.transcript {
+        println!("{}", entry);
+    }
+
+    // Failing to save history should not fail the chat itself
+    match save_history(model, prompt, conversation_id, &run.new_messages) {
+        Ok(id) => println!("\nConversation saved as {}", id),
+        Err(e) => warn!("Failed to save conversation history: {}", e),
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
+
+fn save_history(
+    model: &str,
+    prompt: &str,
+    conversation_id: Option<String>,
+    messages: &[ollama::ChatMessage],
+) -> Result<String> {
+    let store = history::HistoryStore::from_env()?;
+    let id = match conversation_id {
+        Some(id) => id,
+        None => store.create(model, prompt)?.id,
+    };
+    store.append(&id, messages)?;
+    Ok(id)
+}
@@ -1,12 +1,46 @@
 use anyhow::Result;
+use futures_util::future::join_all;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::OnceCell;
+
+/// MCP protocol versions this client understands, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2024-11-05"];
+
+/// How the client talks to the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// The bridge's REST endpoints (`GET /tools`, `POST /tools/call`)
+    Rest,
+    /// MCP JSON-RPC over HTTP (`POST /mcp`), directly against mcp-server
+    Jsonrpc,
+}
 
+/// A tool as the bridge or the server lists it. The schema is written
+/// `inputSchema`, as MCP spells it; `input_schema`, which older bridges
+/// send, is read too.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ToolDefinition {
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(rename = "inputSchema", alias = "input_schema")]
     pub input_schema: Value,
+    /// `read-only`, `destructive`, `network` or `slow`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// MCP tool annotations, e.g. `{"readOnlyHint": true}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Value>,
+}
+
+impl ToolDefinition {
+    /// Whether the tool has every one of `tags`
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -14,11 +48,53 @@ pub struct ToolDefinition {
 pub enum ContentBlock {
     #[serde(rename = "text")]
     Text { text: String },
+    /// Structured output, usually alongside a text block with the same data
+    #[serde(rename = "json")]
+    Json { json: Value },
+    /// Output in another format; `data` is base64 unless the MIME type is textual
+    #[serde(rename = "data")]
+    Data {
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+        data: String,
+    },
+}
+
+impl ContentBlock {
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            ContentBlock::Text { text } => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_json(&self) -> Option<&Value> {
+        match self {
+            ContentBlock::Json { json } => Some(json),
+            _ => None,
+        }
+    }
 }
 
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    id: Option<Value>,
+    result: Option<Value>,
+    error: Option<mcp_core::JsonRpcError>,
+}
+
+/// Header the server uses to identify a JSON-RPC session
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+
 pub struct McpClient {
     base_url: String,
     client: reqwest::Client,
+    protocol: Protocol,
+    /// Protocol version agreed during the JSON-RPC initialize handshake
+    negotiated_version: OnceCell<String>,
+    /// Session the server started for this client, sent back as `Mcp-Session-Id`
+    session_id: std::sync::OnceLock<String>,
+    next_id: AtomicU64,
 }
 
 impl McpClient {
@@ -26,10 +102,61 @@ impl McpClient {
         Self {
             base_url: base_url.to_string(),
             client: reqwest::Client::new(),
+            protocol: Protocol::Rest,
+            negotiated_version: OnceCell::new(),
+            session_id: std::sync::OnceLock::new(),
+            next_id: AtomicU64::new(1),
         }
     }
 
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
     pub async fn list_tools(&self) -> Result<Vec<ToolDefinition>> {
+        match self.protocol {
+            Protocol::Rest => self.rest_list_tools().await,
+            Protocol::Jsonrpc => self.rpc_list_tools().await,
+        }
+    }
+
+    pub async fn call_tool(&self, tool_name: &str, arguments: serde_json::Map<String, Value>) -> Result<Vec<ContentBlock>> {
+        match self.protocol {
+            Protocol::Rest => self.rest_call_tool(tool_name, arguments).await,
+            Protocol::Jsonrpc => self.rpc_call_tool(tool_name, arguments).await,
+        }
+    }
+
+    /// Calls several tools at once, one result per call in order. Over
+    /// JSON-RPC they are sent as one batch; REST calls are made concurrently.
+    pub async fn call_tools(&self, calls: Vec<(String, serde_json::Map<String, Value>)>) -> Result<Vec<Result<Vec<ContentBlock>>>> {
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+        match self.protocol {
+            Protocol::Rest => Ok(join_all(
+                calls
+                    .into_iter()
+                    .map(|(tool_name, arguments)| async move { self.rest_call_tool(&tool_name, arguments).await }),
+            )
+            .await),
+            Protocol::Jsonrpc => {
+                let requests = calls
+                    .into_iter()
+                    .map(|(tool_name, arguments)| ("tools/call", json!({ "name": tool_name, "arguments": arguments })))
+                    .collect();
+                Ok(self
+                    .rpc_batch(requests)
+                    .await?
+                    .into_iter()
+                    .map(|result| result.and_then(tool_call_content))
+                    .collect())
+            }
+        }
+    }
+
+    async fn rest_list_tools(&self) -> Result<Vec<ToolDefinition>> {
         let response = self.client
             .get(&format!("{}/tools", self.base_url))
             .send()
@@ -54,8 +181,8 @@ impl McpClient {
         Ok(response_data.tools)
     }
 
-    pub async fn call_tool(&self, tool_name: &str, arguments: serde_json::Map<String, Value>) -> Result<Vec<ContentBlock>> {
-                #[derive(Serialize)]
+    async fn rest_call_tool(&self, tool_name: &str, arguments: serde_json::Map<String, Value>) -> Result<Vec<ContentBlock>> {
+        #[derive(Serialize)]
         struct ToolCallRequest {
             tool_name: String,
             arguments: serde_json::Map<String, Value>,
@@ -100,13 +227,231 @@ impl McpClient {
 
         Ok(response_data.content.unwrap_or_default())
     }
+
+    async fn rpc_list_tools(&self) -> Result<Vec<ToolDefinition>> {
+        #[derive(Deserialize)]
+        struct ToolsListResult {
+            tools: Vec<ToolDefinition>,
+        }
+
+        let result: ToolsListResult = serde_json::from_value(self.rpc("tools/list", json!({})).await?)?;
+        Ok(result.tools)
+    }
+
+    async fn rpc_call_tool(&self, tool_name: &str, arguments: serde_json::Map<String, Value>) -> Result<Vec<ContentBlock>> {
+        tool_call_content(self.rpc("tools/call", json!({ "name": tool_name, "arguments": arguments })).await?)
+    }
+
+    /// Sends a request after making sure the initialize handshake has happened
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value> {
+        self.negotiated_version
+            .get_or_try_init(|| self.rpc_initialize())
+            .await?;
+        self.rpc_request(method, Some(params)).await
+    }
+
+    /// Sends requests as one JSON-RPC batch after the handshake. Returns one
+    /// result per request, in order, whatever order the server answered in.
+    async fn rpc_batch(&self, requests: Vec<(&str, Value)>) -> Result<Vec<Result<Value>>> {
+        self.negotiated_version
+            .get_or_try_init(|| self.rpc_initialize())
+            .await?;
+        let ids: Vec<u64> = requests.iter().map(|_| self.next_id.fetch_add(1, Ordering::SeqCst)).collect();
+        let batch: Vec<Value> = requests
+            .iter()
+            .zip(&ids)
+            .map(|((method, params), id)| json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .collect();
+
+        let (body, is_sse) = self.post_rpc(&Value::Array(batch)).await?;
+        let messages = if is_sse { sse_messages(&body) } else { vec![body] };
+        let mut responses = std::collections::HashMap::new();
+        for message in messages {
+            let batch = match serde_json::from_str(&message)? {
+                Value::Array(batch) => batch,
+                response => vec![response],
+            };
+            for response in batch {
+                let response: JsonRpcResponse = serde_json::from_value(response)?;
+                if let Some(id) = response.id.as_ref().and_then(Value::as_u64) {
+                    responses.insert(id, response);
+                }
+            }
+        }
+
+        Ok(requests
+            .iter()
+            .zip(&ids)
+            .map(|((method, _), id)| match responses.remove(id) {
+                Some(response) => rpc_result(method, response),
+                None => Err(anyhow::anyhow!("No response to {} in batch", method)),
+            })
+            .collect())
+    }
+
+    async fn rpc_initialize(&self) -> Result<String> {
+        let result = self
+            .rpc_request(
+                "initialize",
+                Some(json!({
+                    "protocolVersion": SUPPORTED_PROTOCOL_VERSIONS[0],
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                })),
+            )
+            .await?;
+
+        let version = result["protocolVersion"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("MCP server did not report a protocol version"))?;
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&version) {
+            return Err(anyhow::anyhow!(
+                "MCP server wants protocol version {}, but this client supports {}",
+                version,
+                SUPPORTED_PROTOCOL_VERSIONS.join(", ")
+            ));
+        }
+        tracing::debug!("Negotiated MCP protocol version {}", version);
+
+        self.rpc_notify("notifications/initialized").await?;
+        Ok(version.to_string())
+    }
+
+    async fn rpc_request(&self, method: &str, params: Option<Value>) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+        let (body, is_sse) = self.post_rpc(&request).await?;
+        let response: JsonRpcResponse = if is_sse {
+            sse_messages(&body)
+                .into_iter()
+                .filter_map(|data| serde_json::from_str::<JsonRpcResponse>(&data).ok())
+                .find(|message| message.id == Some(json!(id)))
+                .ok_or_else(|| anyhow::anyhow!("No response to {} in event stream", method))?
+        } else {
+            serde_json::from_str(&body)?
+        };
+        rpc_result(method, response)
+    }
+
+    /// Posts a request or batch and returns the response body, and whether
+    /// it is an event stream
+    async fn post_rpc(&self, message: &Value) -> Result<(String, bool)> {
+        let response = self.post_mcp(message).send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!(
+                "MCP server returned error status: {} with body: {}",
+                status,
+                error_text
+            ));
+        }
+
+        if let Some(session) = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            let _ = self.session_id.set(session.to_string());
+        }
+
+        let is_sse = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        Ok((response.text().await?, is_sse))
+    }
+
+    fn post_mcp(&self, message: &Value) -> reqwest::RequestBuilder {
+        let request = self.client
+            .post(format!("{}/mcp", self.base_url))
+            .header("Accept", "application/json, text/event-stream")
+            .json(message);
+        match self.session_id.get() {
+            Some(session) => request.header(SESSION_HEADER, session),
+            None => request,
+        }
+    }
+
+    async fn rpc_notify(&self, method: &str) -> Result<()> {
+        let response = self
+            .post_mcp(&json!({ "jsonrpc": "2.0", "method": method }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "MCP server rejected {} with status {}",
+                method,
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The result of a response to `method`, or its error
+fn rpc_result(method: &str, response: JsonRpcResponse) -> Result<Value> {
+    // Kept typed, so callers can tell refusals such as rate limits apart
+    if let Some(error) = response.error {
+        return Err(mcp_core::Error::Rpc(error).context(format!("MCP server returned an error for {}", method)).into());
+    }
+    Ok(response.result.unwrap_or(Value::Null))
+}
+
+/// The content of a tools/call result; results flagged `isError` become
+/// errors, and `structuredContent` not already in a `json` block becomes one
+fn tool_call_content(result: Value) -> Result<Vec<ContentBlock>> {
+    #[derive(Deserialize)]
+    struct ToolCallResult {
+        #[serde(default)]
+        content: Vec<ContentBlock>,
+        #[serde(default, rename = "structuredContent")]
+        structured_content: Option<Value>,
+        #[serde(default, rename = "isError")]
+        is_error: bool,
+    }
+
+    let mut result: ToolCallResult = serde_json::from_value(result)?;
+    if result.is_error {
+        let message: Vec<&str> = result.content.iter().filter_map(ContentBlock::as_text).collect();
+        return Err(anyhow::anyhow!("Tool call failed: {}", message.join("\n")));
+    }
+    if let Some(structured) = result.structured_content.filter(Value::is_object) {
+        if !result.content.iter().any(|block| block.as_json() == Some(&structured)) {
+            result.content.push(ContentBlock::Json { json: structured });
+        }
+    }
+    Ok(result.content)
+}
+
+/// Extracts the `data` payload of each event in a `text/event-stream` body
+fn sse_messages(body: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut data = Vec::new();
+    for line in body.lines().chain(std::iter::once("")) {
+        if line.is_empty() {
+            if !data.is_empty() {
+                messages.push(data.join("\n"));
+                data.clear();
+            }
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    messages
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use wiremock::{
-        matchers::{method, path, body_json},
+        matchers::{method, path, body_json, body_partial_json, header},
         Mock, MockServer, ResponseTemplate,
     };
     use serde_json::json;
@@ -268,11 +613,7 @@ mod tests {
         let content = client.call_tool("system_info", args).await.unwrap();
 
         assert_eq!(content.len(), 1);
-        match &content[0] {
-            ContentBlock::Text { text } => {
-                assert_eq!(text, "System: Ubuntu 22.04, CPU: 8 cores");
-            }
-        }
+        assert_eq!(content[0].as_text(), Some("System: Ubuntu 22.04, CPU: 8 cores"));
     }
 
     #[tokio::test]
@@ -312,12 +653,8 @@ mod tests {
         let content = client.call_tool("multi_output", args).await.unwrap();
 
         assert_eq!(content.len(), 2);
-        match &content[0] {
-            ContentBlock::Text { text } => assert_eq!(text, "First output"),
-        }
-        match &content[1] {
-            ContentBlock::Text { text } => assert_eq!(text, "Second output"),
-        }
+        assert_eq!(content[0].as_text(), Some("First output"));
+        assert_eq!(content[1].as_text(), Some("Second output"));
     }
 
     #[tokio::test]
@@ -538,11 +875,7 @@ mod tests {
         let content = client.call_tool("file_processor", complex_args).await.unwrap();
 
         assert_eq!(content.len(), 1);
-        match &content[0] {
-            ContentBlock::Text { text } => {
-                assert_eq!(text, "File processed successfully");
-            }
-        }
+        assert_eq!(content[0].as_text(), Some("File processed successfully"));
     }
 
     #[tokio::test]
@@ -567,6 +900,11 @@ mod tests {
         assert_eq!(tool.input_schema["type"], "object");
         assert!(tool.input_schema["properties"]["param1"].is_object());
         assert!(tool.input_schema["required"].is_array());
+
+        // Read in either spelling, written in MCP's
+        let serialized = serde_json::to_value(&tool).unwrap();
+        assert_eq!(serialized["inputSchema"]["required"], json!(["param1"]));
+        assert!(serialized.get("input_schema").is_none());
     }
 
     #[tokio::test]
@@ -578,11 +916,7 @@ mod tests {
 
         let content_block: ContentBlock = serde_json::from_value(json_data).unwrap();
         
-        match content_block {
-            ContentBlock::Text { text } => {
-                assert_eq!(text, "This is a text content block");
-            }
-        }
+        assert_eq!(content_block.as_text(), Some("This is a text content block"));
     }
 
     #[tokio::test]
@@ -599,4 +933,183 @@ mod tests {
 
         assert_json_eq!(json_value, expected);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_structured_content_blocks() {
+        let blocks: Vec<ContentBlock> = serde_json::from_value(json!([
+            {"type": "text", "text": "{\"cpu\": 12}"},
+            {"type": "json", "json": {"cpu": 12}},
+            {"type": "data", "mimeType": "image/png", "data": "iVBORw0KGgo="}
+        ]))
+        .unwrap();
+
+        assert_eq!(blocks[0].as_json(), None);
+        assert_eq!(blocks[1].as_json(), Some(&json!({"cpu": 12})));
+        assert!(matches!(&blocks[2], ContentBlock::Data { mime_type, .. } if mime_type == "image/png"));
+    }
+
+    #[test]
+    fn test_tool_call_content_reads_structured_content_and_is_error() {
+        let content = tool_call_content(json!({
+            "content": [{"type": "text", "text": "{\"cpu\": 12}"}],
+            "structuredContent": {"cpu": 12}
+        }))
+        .unwrap();
+        assert_eq!(content.len(), 2);
+        assert_eq!(content[1].as_json(), Some(&json!({"cpu": 12})));
+
+        // Not repeated when the server also sent it as a block
+        let content = tool_call_content(json!({
+            "content": [{"type": "json", "json": {"cpu": 12}}],
+            "structuredContent": {"cpu": 12}
+        }))
+        .unwrap();
+        assert_eq!(content.len(), 1);
+
+        let failed = tool_call_content(json!({"content": [{"type": "text", "text": "disk full"}], "isError": true}));
+        assert_eq!(failed.unwrap_err().to_string(), "Tool call failed: disk full");
+    }
+
+    async fn mount_rpc(mock_server: &MockServer, rpc_method: &str, response: ResponseTemplate) {
+        Mock::given(method("POST"))
+            .and(path("/mcp"))
+            .and(body_partial_json(json!({"method": rpc_method})))
+            .respond_with(response)
+            .mount(mock_server)
+            .await;
+    }
+
+    async fn mount_handshake(mock_server: &MockServer, version: &str) {
+        mount_rpc(mock_server, "initialize", ResponseTemplate::new(200)
+            .insert_header(SESSION_HEADER, "session-1")
+            .set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "protocolVersion": version,
+                "capabilities": {"tools": {}},
+                "serverInfo": {"name": "mcp-server", "version": "0.1.0"}
+            }
+        }))).await;
+        Mock::given(method("POST"))
+            .and(path("/mcp"))
+            .and(header(SESSION_HEADER, "session-1"))
+            .and(body_partial_json(json!({"method": "notifications/initialized"})))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_jsonrpc_list_tools() {
+        let mock_server = MockServer::start().await;
+        mount_handshake(&mock_server, "2024-11-05").await;
+        // Later requests carry the session the server started
+        Mock::given(method("POST"))
+            .and(path("/mcp"))
+            .and(header(SESSION_HEADER, "session-1"))
+            .and(body_partial_json(json!({"method": "tools/list"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "result": {
+                    "tools": [{
+                        "name": "system_info",
+                        "description": "Get system information",
+                        "inputSchema": {"type": "object", "properties": {"action": {"type": "string"}}}
+                    }]
+                }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = McpClient::new(&mock_server.uri()).with_protocol(Protocol::Jsonrpc);
+        let tools = client.list_tools().await.unwrap();
+
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "system_info");
+        assert!(tools[0].input_schema["properties"]["action"].is_object());
+        assert_eq!(client.negotiated_version.get().map(String::as_str), Some("2024-11-05"));
+    }
+
+    #[tokio::test]
+    async fn test_jsonrpc_call_tool_over_event_stream() {
+        let mock_server = MockServer::start().await;
+        mount_handshake(&mock_server, "2024-11-05").await;
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":2,\"result\":\
+            {\"content\":[{\"type\":\"text\",\"text\":\"CPU: 12%\"}],\"isError\":false}}\n\n";
+        mount_rpc(&mock_server, "tools/call", ResponseTemplate::new(200)
+            .set_body_raw(body, "text/event-stream")).await;
+
+        let client = McpClient::new(&mock_server.uri()).with_protocol(Protocol::Jsonrpc);
+        let result = client.call_tool("system_info", serde_json::Map::new()).await.unwrap();
+
+        assert_eq!(result[0].as_text(), Some("CPU: 12%"));
+    }
+
+    #[tokio::test]
+    async fn test_jsonrpc_error_response() {
+        let mock_server = MockServer::start().await;
+        mount_handshake(&mock_server, "2024-11-05").await;
+        mount_rpc(&mock_server, "tools/call", ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "error": {"code": -32602, "message": "Unknown tool: nope"}
+        }))).await;
+
+        let client = McpClient::new(&mock_server.uri()).with_protocol(Protocol::Jsonrpc);
+        let error = client.call_tool("nope", serde_json::Map::new()).await.unwrap_err();
+        let error_msg = error.to_string();
+        assert!(error_msg.contains("-32602"));
+        assert!(error_msg.contains("Unknown tool: nope"));
+        let error = error.downcast_ref::<mcp_core::Error>().unwrap();
+        assert_eq!((error.kind(), error.http_status()), (mcp_core::ErrorKind::InvalidParams, 404));
+    }
+
+    #[tokio::test]
+    async fn test_jsonrpc_call_tools_in_one_batch() {
+        let mock_server = MockServer::start().await;
+        mount_handshake(&mock_server, "2024-11-05").await;
+        Mock::given(method("POST"))
+            .and(path("/mcp"))
+            .and(body_partial_json(json!([
+                {"id": 2, "method": "tools/call", "params": {"name": "system_info"}},
+                {"id": 3, "method": "tools/call", "params": {"name": "nope"}}
+            ])))
+            // Answered out of order, as batches may be
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"jsonrpc": "2.0", "id": 3, "error": {"code": -32602, "message": "Unknown tool: nope"}},
+                {"jsonrpc": "2.0", "id": 2, "result": {"content": [{"type": "text", "text": "CPU: 12%"}]}}
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = McpClient::new(&mock_server.uri()).with_protocol(Protocol::Jsonrpc);
+        let calls = vec![
+            ("system_info".to_string(), serde_json::Map::new()),
+            ("nope".to_string(), serde_json::Map::new()),
+        ];
+        let results = client.call_tools(calls).await.unwrap();
+
+        assert_eq!(results[0].as_ref().unwrap()[0].as_text(), Some("CPU: 12%"));
+        assert!(results[1].as_ref().unwrap_err().to_string().contains("Unknown tool: nope"));
+    }
+
+    #[tokio::test]
+    async fn test_jsonrpc_rejects_unsupported_protocol_version() {
+        let mock_server = MockServer::start().await;
+        mount_handshake(&mock_server, "1999-01-01").await;
+
+        let client = McpClient::new(&mock_server.uri()).with_protocol(Protocol::Jsonrpc);
+        let error_msg = client.list_tools().await.unwrap_err().to_string();
+        assert!(error_msg.contains("1999-01-01"));
+        assert!(error_msg.contains("2024-11-05"));
+    }
+
+    #[test]
+    fn test_sse_messages() {
+        let body = "event: message\ndata: {\"a\":1}\n\n: keep-alive\n\ndata: {\"b\":\ndata: 2}\n";
+        assert_eq!(sse_messages(body), vec!["{\"a\":1}", "{\"b\":\n2}"]);
+    }
+}
@@ -1,11 +1,63 @@
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use futures_util::StreamExt;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Model {
     pub name: String,
 }
 
+/// One status update from `/api/pull`. Download steps carry the layer
+/// `digest` with `total` and `completed` byte counts.
+#[derive(Debug, Deserialize)]
+pub struct PullProgress {
+    #[serde(default)]
+    pub status: String,
+    pub digest: Option<String>,
+    pub total: Option<u64>,
+    pub completed: Option<u64>,
+    error: Option<String>,
+}
+
+/// Response of `/api/show`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModelInfo {
+    #[serde(default)]
+    pub details: ModelDetails,
+    #[serde(default)]
+    pub parameters: String,
+    #[serde(default)]
+    pub template: String,
+    #[serde(default)]
+    pub license: String,
+    #[serde(default)]
+    pub modelfile: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ModelDetails {
+    #[serde(default)]
+    pub format: String,
+    #[serde(default)]
+    pub family: String,
+    #[serde(default)]
+    pub parameter_size: String,
+    #[serde(default)]
+    pub quantization_level: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Serialize)]
+struct ModelRequest<'a> {
+    model: &'a str,
+}
+
 #[derive(Debug, Serialize)]
 struct GenerateRequest<'a> {
     model: &'a str,
@@ -18,6 +70,112 @@ struct GenerateResponse {
     done: bool,
 }
 
+/// A message in an `/api/chat` conversation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChatMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    /// Id of the tool call this message answers; required by hosted backends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    fn new(role: &str, content: impl Into<String>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_name: None,
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new("system", content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new("assistant", content)
+    }
+
+    /// The result of running `tool_name`, sent back to the model
+    pub fn tool(tool_name: &str, tool_call_id: Option<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_name: Some(tool_name.to_string()),
+            tool_call_id,
+            ..Self::new("tool", content)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub function: FunctionCall,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Map<String, Value>,
+}
+
+/// A tool the model may call, in the format `/api/chat` expects
+#[derive(Debug, Clone, Serialize)]
+pub struct Tool {
+    #[serde(rename = "type")]
+    pub tool_type: &'static str,
+    pub function: FunctionDefinition,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+impl Tool {
+    pub fn function(name: &str, description: &str, parameters: Value) -> Self {
+        Self {
+            tool_type: "function",
+            function: FunctionDefinition {
+                name: name.to_string(),
+                description: description.to_string(),
+                parameters,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    #[serde(skip_serializing_if = "<[Tool]>::is_empty")]
+    tools: &'a [Tool],
+    stream: bool,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ChatMessage,
+    #[serde(default)]
+    done: bool,
+}
+
 pub struct OllamaClient {
     base_url: String,
     client: reqwest::Client,
@@ -56,41 +214,227 @@ impl OllamaClient {
         Ok(response_data.models)
     }
 
-    pub async fn generate(&self, model: &str, prompt: &str) -> Result<String> {
-        let request = GenerateRequest { model, prompt };
+    /// Downloads `model`, calling `on_progress` with each status update
+    pub async fn pull_model<F>(&self, model: &str, mut on_progress: F) -> Result<()>
+    where
+        F: FnMut(&PullProgress),
+    {
+        let response = self.client
+            .post(format!("{}/api/pull", self.base_url))
+            .json(&ModelRequest { model })
+            .send()
+            .await?;
+        let response = check_status(response).await?;
+
+        // Failures part-way through arrive as an `error` object in the stream
+        let mut error = None;
+        let mut finished = false;
+        read_ndjson(response, |progress: PullProgress| {
+            if let Some(message) = &progress.error {
+                error = Some(message.clone());
+                return true;
+            }
+            on_progress(&progress);
+            finished = progress.status == "success";
+            finished
+        })
+        .await?;
+
+        if let Some(message) = error {
+            return Err(anyhow::anyhow!("Failed to pull {}: {}", model, message));
+        }
+        if !finished {
+            return Err(anyhow::anyhow!("Pull of {} ended before it completed", model));
+        }
+        Ok(())
+    }
 
+    pub async fn delete_model(&self, model: &str) -> Result<()> {
         let response = self.client
-            .post(&format!("{}/api/generate", self.base_url))
-            .json(&request)
+            .delete(format!("{}/api/delete", self.base_url))
+            .json(&ModelRequest { model })
             .send()
             .await?;
+        check_status(response).await?;
+        Ok(())
+    }
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await?;
+    pub async fn show_model(&self, model: &str) -> Result<ModelInfo> {
+        let response = self.client
+            .post(format!("{}/api/show", self.base_url))
+            .json(&ModelRequest { model })
+            .send()
+            .await?;
+        let response = check_status(response).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Embeds each of `inputs` with an embedding model such as `nomic-embed-text`
+    pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        #[derive(Deserialize)]
+        struct EmbedResponse {
+            embeddings: Vec<Vec<f32>>,
+        }
+
+        let response = self.client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&EmbedRequest { model, input: inputs })
+            .send()
+            .await?;
+        let response = check_status(response).await?;
+
+        let response_data: EmbedResponse = response.json().await?;
+        if response_data.embeddings.len() != inputs.len() {
             return Err(anyhow::anyhow!(
-                "Ollama server returned error status: {} with body: {}",
-                status,
-                error_text
+                "Ollama returned {} embeddings for {} inputs",
+                response_data.embeddings.len(),
+                inputs.len()
             ));
         }
+        Ok(response_data.embeddings)
+    }
+
+    pub async fn generate(&self, model: &str, prompt: &str) -> Result<String> {
+        self.generate_streaming(model, prompt, |_| {}).await
+    }
+
+    /// Like `generate`, but calls `on_token` with each piece of the response as it arrives
+    pub async fn generate_streaming<F>(&self, model: &str, prompt: &str, mut on_token: F) -> Result<String>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let request = GenerateRequest { model, prompt };
+
+        let response = self.client
+            .post(format!("{}/api/generate", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+        let response = check_status(response).await?;
 
         let mut response_text = String::new();
-        let mut stream = response.bytes_stream();
-        use futures_util::StreamExt;
-        
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            let text = String::from_utf8_lossy(&chunk);
-            let response_data: GenerateResponse = serde_json::from_str(&text)?;
-            response_text.push_str(&response_data.response);
-            if response_data.done {
-                break;
+        read_ndjson(response, |chunk: GenerateResponse| {
+            on_token(&chunk.response);
+            response_text.push_str(&chunk.response);
+            chunk.done
+        })
+        .await?;
+
+        Ok(response_text)
+    }
+
+    /// Sends a conversation to `/api/chat` and returns the model's reply,
+    /// which may contain `tool_calls` instead of text
+    pub async fn chat(&self, model: &str, messages: &[ChatMessage], tools: &[Tool]) -> Result<ChatMessage> {
+        self.send_chat(model, messages, tools, None).await
+    }
+
+    /// Like `chat`, but streams the reply and calls `on_token` with each piece of content
+    pub async fn chat_streaming<F>(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        tools: &[Tool],
+        mut on_token: F,
+    ) -> Result<ChatMessage>
+    where
+        F: FnMut(&str) + Send,
+    {
+        self.send_chat(model, messages, tools, Some(&mut on_token)).await
+    }
+
+    async fn send_chat(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        tools: &[Tool],
+        mut on_token: Option<&mut (dyn FnMut(&str) + Send)>,
+    ) -> Result<ChatMessage> {
+        let request = ChatRequest {
+            model,
+            messages,
+            tools,
+            stream: on_token.is_some(),
+        };
+
+        let response = self.client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+        let response = check_status(response).await?;
+
+        // Streamed replies arrive as many partial messages; tool calls usually
+        // come in one of them, so content and tool calls are accumulated
+        let mut reply: Option<ChatMessage> = None;
+        read_ndjson(response, |chunk: ChatResponse| {
+            if let Some(on_token) = on_token.as_mut() {
+                on_token(&chunk.message.content);
+            }
+            match reply.as_mut() {
+                Some(reply) => {
+                    reply.content.push_str(&chunk.message.content);
+                    reply.tool_calls.extend(chunk.message.tool_calls);
+                }
+                None => reply = Some(chunk.message),
+            }
+            chunk.done
+        })
+        .await?;
+
+        reply.ok_or_else(|| anyhow::anyhow!("Ollama server returned an empty chat response"))
+    }
+}
+
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow::anyhow!(
+            "Ollama server returned error status: {} with body: {}",
+            status,
+            error_text
+        ));
+    }
+    Ok(response)
+}
+
+/// Reads a newline-delimited JSON stream, passing each object to `on_item`
+/// until it returns `true` or the stream ends. Objects may be split across
+/// network chunks and a chunk may hold several objects.
+async fn read_ndjson<T, F>(response: reqwest::Response, mut on_item: F) -> Result<()>
+where
+    T: DeserializeOwned,
+    F: FnMut(T) -> bool,
+{
+    let mut stream = response.bytes_stream();
+    let mut buffer: Vec<u8> = Vec::new();
+
+    while let Some(chunk) = stream.next().await {
+        buffer.extend_from_slice(&chunk?);
+        while let Some(newline) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline).collect();
+            if parse_ndjson_line(&line, &mut on_item)? {
+                return Ok(());
             }
         }
-        
-        Ok(response_text)
     }
+
+    // The last object may not be followed by a newline
+    parse_ndjson_line(&buffer, &mut on_item)?;
+    Ok(())
+}
+
+fn parse_ndjson_line<T, F>(line: &[u8], on_item: &mut F) -> Result<bool>
+where
+    T: DeserializeOwned,
+    F: FnMut(T) -> bool,
+{
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok(false);
+    }
+    let item: T = serde_json::from_slice(line)?;
+    Ok(on_item(item))
 }
 
 #[cfg(test)]
@@ -188,6 +532,129 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_pull_model_reports_progress() {
+        let mock_server = MockServer::start().await;
+        let body = [
+            json!({"status": "pulling manifest"}),
+            json!({"status": "pulling abc123", "digest": "sha256:abc123", "total": 100, "completed": 40}),
+            json!({"status": "pulling abc123", "digest": "sha256:abc123", "total": 100, "completed": 100}),
+            json!({"status": "success"}),
+        ]
+        .iter()
+        .map(|line| line.to_string() + "\n")
+        .collect::<String>();
+
+        Mock::given(method("POST"))
+            .and(path("/api/pull"))
+            .and(body_json(json!({"model": "llama3.1"})))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&mock_server)
+            .await;
+
+        let client = OllamaClient::new(&mock_server.uri());
+        let mut completed = Vec::new();
+        client
+            .pull_model("llama3.1", |progress| completed.push(progress.completed))
+            .await
+            .unwrap();
+
+        assert_eq!(completed, vec![None, Some(40), Some(100), None]);
+    }
+
+    #[tokio::test]
+    async fn test_pull_model_stream_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/pull"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "{\"status\":\"pulling manifest\"}\n{\"error\":\"pull model manifest: file does not exist\"}\n",
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let client = OllamaClient::new(&mock_server.uri());
+        let error_msg = client.pull_model("nope", |_| {}).await.unwrap_err().to_string();
+        assert!(error_msg.contains("file does not exist"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_model() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/api/delete"))
+            .and(body_json(json!({"model": "llama2"})))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/delete"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("{\"error\":\"model not found\"}"))
+            .with_priority(10)
+            .mount(&mock_server)
+            .await;
+
+        let client = OllamaClient::new(&mock_server.uri());
+        client.delete_model("llama2").await.unwrap();
+        let error_msg = client.delete_model("missing").await.unwrap_err().to_string();
+        assert!(error_msg.contains("404"));
+    }
+
+    #[tokio::test]
+    async fn test_embed() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/embed"))
+            .and(body_json(json!({"model": "nomic-embed-text", "input": ["a", "b"]})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "nomic-embed-text",
+                "embeddings": [[0.1, 0.2], [0.3, 0.4]]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = OllamaClient::new(&mock_server.uri());
+        let embeddings = client
+            .embed("nomic-embed-text", &["a".to_string(), "b".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    }
+
+    #[tokio::test]
+    async fn test_show_model() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/show"))
+            .and(body_json(json!({"model": "llama3.1"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "modelfile": "FROM llama3.1",
+                "parameters": "stop \"<|eot_id|>\"",
+                "template": "{{ .Prompt }}",
+                "details": {
+                    "format": "gguf",
+                    "family": "llama",
+                    "parameter_size": "8.0B",
+                    "quantization_level": "Q4_K_M"
+                },
+                "model_info": {"general.architecture": "llama"}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = OllamaClient::new(&mock_server.uri());
+        let info = client.show_model("llama3.1").await.unwrap();
+
+        assert_eq!(info.details.family, "llama");
+        assert_eq!(info.details.parameter_size, "8.0B");
+        assert_eq!(info.template, "{{ .Prompt }}");
+        assert_eq!(info.license, "");
+    }
+
     #[tokio::test]
     async fn test_list_models_invalid_json() {
         let mock_server = MockServer::start().await;
@@ -439,4 +906,154 @@ mod tests {
         assert_eq!(response.response, "Partial response");
         assert_eq!(response.done, false);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_chat_sends_tools_and_parses_tool_calls() {
+        let mock_server = MockServer::start().await;
+
+        let expected_request = json!({
+            "model": "llama3.1",
+            "messages": [
+                {"role": "system", "content": "Be helpful"},
+                {"role": "user", "content": "How full are my disks?"}
+            ],
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "system_info",
+                    "description": "Get system information",
+                    "parameters": {"type": "object", "properties": {}}
+                }
+            }],
+            "stream": false
+        });
+
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_json(&expected_request))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "llama3.1",
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{
+                        "function": {"name": "system_info", "arguments": {"action": "get_disks"}}
+                    }]
+                },
+                "done": true
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = OllamaClient::new(&mock_server.uri());
+        let messages = vec![
+            ChatMessage::system("Be helpful"),
+            ChatMessage::user("How full are my disks?"),
+        ];
+        let tools = vec![Tool::function(
+            "system_info",
+            "Get system information",
+            json!({"type": "object", "properties": {}}),
+        )];
+
+        let reply = client.chat("llama3.1", &messages, &tools).await.unwrap();
+        assert_eq!(reply.role, "assistant");
+        assert_eq!(reply.tool_calls.len(), 1);
+        assert_eq!(reply.tool_calls[0].function.name, "system_info");
+        assert_eq!(reply.tool_calls[0].function.arguments["action"], "get_disks");
+    }
+
+    #[tokio::test]
+    async fn test_chat_server_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("model does not support tools"))
+            .mount(&mock_server)
+            .await;
+
+        let client = OllamaClient::new(&mock_server.uri());
+        let result = client.chat("llama2", &[ChatMessage::user("hi")], &[]).await;
+
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("400"));
+        assert!(error_msg.contains("model does not support tools"));
+    }
+
+    #[test]
+    fn test_tool_message_serialization() {
+        let message = ChatMessage::tool("system_info", None, "CPU: 12%");
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({"role": "tool", "content": "CPU: 12%", "tool_name": "system_info"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_multi_chunk_stream() {
+        let mock_server = MockServer::start().await;
+
+        let body = [
+            json!({"response": "The capital ", "done": false}),
+            json!({"response": "of France ", "done": false}),
+            json!({"response": "is Paris.", "done": true}),
+        ]
+        .iter()
+        .map(|chunk| chunk.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&mock_server)
+            .await;
+
+        let client = OllamaClient::new(&mock_server.uri());
+        let mut tokens = Vec::new();
+        let result = client
+            .generate_streaming("llama2:7b", "What is the capital of France?", |token| {
+                tokens.push(token.to_string())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result, "The capital of France is Paris.");
+        assert_eq!(tokens, vec!["The capital ", "of France ", "is Paris."]);
+    }
+
+    #[tokio::test]
+    async fn test_chat_streaming_accumulates_content_and_tool_calls() {
+        let mock_server = MockServer::start().await;
+
+        let body = [
+            json!({"message": {"role": "assistant", "content": "Let me "}, "done": false}),
+            json!({"message": {"role": "assistant", "content": "check.", "tool_calls": [
+                {"function": {"name": "system_info", "arguments": {}}}
+            ]}, "done": false}),
+            json!({"message": {"role": "assistant", "content": ""}, "done": true}),
+        ]
+        .iter()
+        .map(|chunk| format!("{}\n", chunk))
+        .collect::<String>();
+
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(wiremock::matchers::body_partial_json(json!({"stream": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&mock_server)
+            .await;
+
+        let client = OllamaClient::new(&mock_server.uri());
+        let mut streamed = String::new();
+        let reply = client
+            .chat_streaming("llama3.1", &[ChatMessage::user("hi")], &[], |token| streamed.push_str(token))
+            .await
+            .unwrap();
+
+        assert_eq!(reply.content, "Let me check.");
+        assert_eq!(streamed, "Let me check.");
+        assert_eq!(reply.tool_calls.len(), 1);
+    }
+}
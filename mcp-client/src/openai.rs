@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::backend::LlmBackend;
+use crate::ollama::{ChatMessage, FunctionCall, Tool, ToolCall};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com";
+
+#[derive(Deserialize)]
+struct CompletionResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: CompletionMessage,
+}
+
+#[derive(Deserialize)]
+struct CompletionMessage {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<CompletionToolCall>,
+}
+
+#[derive(Deserialize)]
+struct CompletionToolCall {
+    id: String,
+    function: CompletionFunction,
+}
+
+#[derive(Deserialize)]
+struct CompletionFunction {
+    name: String,
+    /// JSON-encoded arguments
+    arguments: String,
+}
+
+/// Client for any service implementing the OpenAI `/v1/chat/completions` API
+pub struct OpenAiClient {
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl OpenAiClient {
+    pub fn new(base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OpenAiClient {
+    async fn chat(&self, model: &str, messages: &[ChatMessage], tools: &[Tool]) -> Result<ChatMessage> {
+        let mut request = json!({
+            "model": model,
+            "messages": messages.iter().map(to_wire_message).collect::<Vec<_>>(),
+        });
+        if !tools.is_empty() {
+            request["tools"] = serde_json::to_value(tools)?;
+        }
+
+        let mut builder = self.client
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .json(&request);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+        let response = builder.send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!(
+                "OpenAI-compatible server returned error status: {} with body: {}",
+                status,
+                error_text
+            ));
+        }
+
+        let response_data: CompletionResponse = response.json().await?;
+        let message = response_data
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("OpenAI-compatible server returned no choices"))?
+            .message;
+
+        let mut reply = ChatMessage::assistant(message.content.unwrap_or_default());
+        for call in message.tool_calls {
+            let arguments = if call.function.arguments.trim().is_empty() {
+                serde_json::Map::new()
+            } else {
+                serde_json::from_str(&call.function.arguments).with_context(|| {
+                    format!("Invalid arguments for tool call {}", call.function.name)
+                })?
+            };
+            reply.tool_calls.push(ToolCall {
+                id: Some(call.id),
+                function: FunctionCall { name: call.function.name, arguments },
+            });
+        }
+        Ok(reply)
+    }
+}
+
+fn to_wire_message(message: &ChatMessage) -> Value {
+    let mut wire = json!({ "role": message.role, "content": message.content });
+
+    if !message.tool_calls.is_empty() {
+        wire["tool_calls"] = message.tool_calls
+            .iter()
+            .map(|call| json!({
+                "id": call.id.clone().unwrap_or_else(|| format!("call_{}", call.function.name)),
+                "type": "function",
+                "function": {
+                    "name": call.function.name,
+                    "arguments": Value::Object(call.function.arguments.clone()).to_string(),
+                }
+            }))
+            .collect();
+    }
+
+    if message.role == "tool" {
+        let tool_name = message.tool_name.as_deref().unwrap_or_default();
+        wire["tool_call_id"] = json!(message
+            .tool_call_id
+            .clone()
+            .unwrap_or_else(|| format!("call_{}", tool_name)));
+    }
+
+    wire
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{body_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_chat_round_trips_tool_calls() {
+        let mock_server = MockServer::start().await;
+
+        let mut tool_request = ChatMessage::assistant("");
+        tool_request.tool_calls.push(ToolCall {
+            id: Some("call_abc".to_string()),
+            function: FunctionCall {
+                name: "system_info".to_string(),
+                arguments: json!({"action": "get_disks"}).as_object().unwrap().clone(),
+            },
+        });
+        let messages = vec![
+            ChatMessage::user("How full are my disks?"),
+            tool_request,
+            ChatMessage::tool("system_info", Some("call_abc".to_string()), "/: 40%"),
+        ];
+
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer sk-test"))
+            .and(body_json(json!({
+                "model": "gpt-4o-mini",
+                "messages": [
+                    {"role": "user", "content": "How full are my disks?"},
+                    {"role": "assistant", "content": "", "tool_calls": [{
+                        "id": "call_abc",
+                        "type": "function",
+                        "function": {"name": "system_info", "arguments": "{\"action\":\"get_disks\"}"}
+                    }]},
+                    {"role": "tool", "content": "/: 40%", "tool_call_id": "call_abc"}
+                ],
+                "tools": [{
+                    "type": "function",
+                    "function": {"name": "system_info", "description": "System info", "parameters": {"type": "object"}}
+                }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_def",
+                            "type": "function",
+                            "function": {"name": "system_info", "arguments": "{\"action\":\"get_memory_usage\"}"}
+                        }]
+                    }
+                }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = OpenAiClient::new(&mock_server.uri(), Some("sk-test".to_string()));
+        let tools = vec![Tool::function("system_info", "System info", json!({"type": "object"}))];
+        let reply = client.chat("gpt-4o-mini", &messages, &tools).await.unwrap();
+
+        assert_eq!(reply.content, "");
+        assert_eq!(reply.tool_calls[0].id.as_deref(), Some("call_def"));
+        assert_eq!(reply.tool_calls[0].function.arguments["action"], "get_memory_usage");
+    }
+
+    #[tokio::test]
+    async fn test_chat_server_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("invalid api key"))
+            .mount(&mock_server)
+            .await;
+
+        let client = OpenAiClient::new(&mock_server.uri(), None);
+        let error_msg = client
+            .chat("gpt-4o-mini", &[ChatMessage::user("hi")], &[])
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error_msg.contains("401"));
+        assert!(error_msg.contains("invalid api key"));
+    }
+}
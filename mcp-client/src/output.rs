@@ -0,0 +1,47 @@
+use anyhow::Result;
+use serde::Serialize;
+
+/// How list-tools, call-tool(s) and the model commands print their results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable output
+    Text,
+    /// JSON, for piping into jq or other scripts
+    Json,
+    /// YAML
+    Yaml,
+}
+
+impl OutputFormat {
+    pub fn is_structured(self) -> bool {
+        self != OutputFormat::Text
+    }
+
+    /// Prints `value` as JSON or YAML, or hands it to `text` for the human-readable format
+    pub fn emit<T: Serialize>(self, value: &T, text: impl FnOnce(&T)) -> Result<()> {
+        match self {
+            OutputFormat::Text => text(value),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_text_format_uses_callback() {
+        let mut called = false;
+        OutputFormat::Text.emit(&json!({"a": 1}), |_| called = true).unwrap();
+        assert!(called);
+
+        let mut called = false;
+        OutputFormat::Json.emit(&json!({"a": 1}), |_| called = true).unwrap();
+        assert!(!called);
+        assert!(OutputFormat::Yaml.is_structured());
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use crate::mcp::ToolDefinition;
+
+/// System prompt used by `chat` when neither `--system-prompt` nor
+/// `--template` is given. Tools are passed to the model natively, so it only
+/// sets expectations.
+pub const DEFAULT_CHAT_TEMPLATE: &str = include_str!("../templates/chat.txt");
+
+const VARIABLES: &[&str] = &["tools", "input", "model"];
+
+/// `--system-prompt` and `--template`, shared by ask and chat
+#[derive(Debug, Default, clap::Args)]
+pub struct PromptArgs {
+    /// System prompt to use instead of the default; may contain {{tools}}, {{input}} and {{model}}
+    #[arg(long, conflicts_with = "template")]
+    pub system_prompt: Option<String>,
+
+    /// File holding the system prompt template, with the same variables as --system-prompt
+    #[arg(long)]
+    pub template: Option<PathBuf>,
+}
+
+impl PromptArgs {
+    /// The system prompt template the user asked for, if any
+    pub fn template(&self) -> Result<Option<String>> {
+        if let Some(path) = &self.template {
+            let template = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read template {}", path.display()))?;
+            return Ok(Some(template));
+        }
+        Ok(self.system_prompt.clone())
+    }
+}
+
+/// Values substituted into a template
+pub struct PromptVars<'a> {
+    pub tools: &'a [ToolDefinition],
+    pub input: &'a str,
+    pub model: &'a str,
+}
+
+/// Replaces `{{tools}}`, `{{input}}` and `{{model}}` in `template`. Any other
+/// `{{name}}` is an error, so typos don't silently reach the model.
+pub fn render(template: &str, vars: &PromptVars) -> Result<String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + len].trim();
+        match name {
+            "tools" => output.push_str(&tool_list(vars.tools)),
+            "input" => output.push_str(vars.input),
+            "model" => output.push_str(vars.model),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unknown template variable {{{{{}}}}}; available: {}",
+                    name,
+                    VARIABLES.join(", ")
+                ))
+            }
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output.trim_end().to_string())
+}
+
+fn tool_list(tools: &[ToolDefinition]) -> String {
+    tools
+        .iter()
+        .map(|tool| format!("- {}: {}", tool.name, tool.description))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars<'a>(tools: &'a [ToolDefinition], input: &'a str) -> PromptVars<'a> {
+        PromptVars { tools, input, model: "llama3.1" }
+    }
+
+    #[test]
+    fn test_render_substitutes_variables() {
+        let tools = vec![ToolDefinition {
+            name: "system_info".to_string(),
+            description: "Get system information".to_string(),
+            input_schema: json!({"type": "object"}),
+            tags: Vec::new(),
+            annotations: None,
+        }];
+        let rendered = render(
+            "Model {{model}} has:\n{{ tools }}\nQuestion: {{input}}\n",
+            &vars(&tools, "How full is /?"),
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            "Model llama3.1 has:\n- system_info: Get system information\nQuestion: How full is /?"
+        );
+    }
+
+    #[test]
+    fn test_render_rejects_unknown_variables() {
+        let error_msg = render("Hello {{user}}", &vars(&[], "")).unwrap_err().to_string();
+        assert!(error_msg.contains("{{user}}"));
+        assert!(error_msg.contains("tools, input, model"));
+    }
+
+    #[test]
+    fn test_unclosed_braces_are_left_alone() {
+        assert_eq!(render("JSON looks like {{\"a\": 1", &vars(&[], "")).unwrap(), "JSON looks like {{\"a\": 1");
+    }
+
+    #[test]
+    fn test_default_template_renders() {
+        assert!(render(DEFAULT_CHAT_TEMPLATE, &vars(&[], "hi")).unwrap().starts_with("You are a helpful"));
+    }
+}
@@ -0,0 +1,294 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::agent::render_content;
+use crate::mcp::{ContentBlock, McpClient};
+use crate::ollama::OllamaClient;
+
+pub const DEFAULT_EMBED_MODEL: &str = "nomic-embed-text";
+
+/// MCP tool that stores and searches embeddings
+const EMBEDDINGS_TOOL: &str = "embeddings";
+
+/// Chunks sent to Ollama and the server per request
+const BATCH_SIZE: usize = 32;
+
+/// Settings shared by `embed` and `rag-chat`
+pub struct RagOptions<'a> {
+    pub ollama: &'a OllamaClient,
+    pub mcp: &'a McpClient,
+    pub embed_model: &'a str,
+    pub collection: &'a str,
+}
+
+/// A stored chunk returned by a similarity search
+#[derive(Debug, Deserialize)]
+pub struct RetrievedChunk {
+    pub text: String,
+    pub score: f32,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+impl RetrievedChunk {
+    pub fn source(&self) -> &str {
+        self.metadata["source"].as_str().unwrap_or("unknown")
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct EmbedSummary {
+    pub files: usize,
+    pub chunks: usize,
+}
+
+/// Splits `text` into chunks of at most `chunk_size` characters, each
+/// starting `overlap` characters before the previous one ended. Chunks end at
+/// whitespace where possible so words are not cut in half.
+pub fn chunk_text(text: &str, chunk_size: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let chunk_size = chunk_size.max(1);
+    let overlap = overlap.min(chunk_size / 2);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = (start + chunk_size).min(chars.len());
+        if end < chars.len() {
+            // Back off to the last whitespace in the second half of the window
+            if let Some(space) = (start + chunk_size / 2..end).rev().find(|&i| chars[i].is_whitespace()) {
+                end = space;
+            }
+        }
+
+        let chunk: String = chars[start..end].iter().collect();
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_string());
+        }
+        if end == chars.len() {
+            break;
+        }
+        start = (end - overlap).max(start + 1);
+    }
+
+    chunks
+}
+
+/// Expands directories into the files below them, skipping hidden entries
+pub fn collect_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+                .with_context(|| format!("Failed to read directory {}", path.display()))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<_>>()?;
+            entries.retain(|entry| !is_hidden(entry));
+            entries.sort();
+            files.extend(collect_files(&entries)?);
+        } else if path.is_file() {
+            files.push(path.clone());
+        } else {
+            return Err(anyhow::anyhow!("No such file or directory: {}", path.display()));
+        }
+    }
+    Ok(files)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+/// Chunks each file, embeds the chunks and stores them in the collection.
+/// Files that are not UTF-8 text are skipped.
+pub async fn embed_files(
+    options: &RagOptions<'_>,
+    paths: &[PathBuf],
+    chunk_size: usize,
+    overlap: usize,
+) -> Result<EmbedSummary> {
+    let mut summary = EmbedSummary::default();
+
+    for path in collect_files(paths)? {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("Skipping {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let source = path.display().to_string();
+        let chunks = chunk_text(&text, chunk_size, overlap);
+        debug!("Embedding {} chunks of {}", chunks.len(), source);
+
+        for (batch_index, batch) in chunks.chunks(BATCH_SIZE).enumerate() {
+            let embeddings = options.ollama.embed(options.embed_model, batch).await?;
+            let items: Vec<Value> = batch
+                .iter()
+                .zip(embeddings)
+                .enumerate()
+                .map(|(i, (text, embedding))| {
+                    let index = batch_index * BATCH_SIZE + i;
+                    json!({
+                        "id": format!("{}#{}", source, index),
+                        "text": text,
+                        "embedding": embedding,
+                        "metadata": { "source": source, "chunk": index },
+                    })
+                })
+                .collect();
+
+            call_embeddings_tool(options.mcp, json!({
+                "action": "store",
+                "collection": options.collection,
+                "items": items,
+            }))
+            .await?;
+        }
+
+        summary.files += 1;
+        summary.chunks += chunks.len();
+    }
+
+    Ok(summary)
+}
+
+/// The `top_k` stored chunks most similar to `question`
+pub async fn retrieve(options: &RagOptions<'_>, question: &str, top_k: usize) -> Result<Vec<RetrievedChunk>> {
+    #[derive(Deserialize)]
+    struct SearchResult {
+        results: Vec<RetrievedChunk>,
+    }
+
+    let embedding = options
+        .ollama
+        .embed(options.embed_model, &[question.to_string()])
+        .await?
+        .remove(0);
+    let result = call_embeddings_tool(options.mcp, json!({
+        "action": "search",
+        "collection": options.collection,
+        "embedding": embedding,
+        "top_k": top_k,
+    }))
+    .await?;
+
+    let result: SearchResult = serde_json::from_value(result)
+        .context("Unexpected search result from the embeddings tool")?;
+    Ok(result.results)
+}
+
+/// System prompt that puts the retrieved chunks in front of the model
+pub fn context_prompt(chunks: &[RetrievedChunk]) -> String {
+    let mut prompt = String::from(
+        "Answer the user's question using the context below. If the context does not contain \
+        the answer, say so instead of guessing. Mention which source you used.\n",
+    );
+    for (i, chunk) in chunks.iter().enumerate() {
+        prompt.push_str(&format!("\n[{}] Source: {}\n{}\n", i + 1, chunk.source(), chunk.text));
+    }
+    prompt
+}
+
+async fn call_embeddings_tool(mcp: &McpClient, arguments: Value) -> Result<Value> {
+    let arguments = match arguments {
+        Value::Object(map) => map,
+        _ => unreachable!("embeddings tool arguments are always an object"),
+    };
+    let blocks = mcp.call_tool(EMBEDDINGS_TOOL, arguments).await?;
+    if let Some(json) = blocks.iter().find_map(ContentBlock::as_json) {
+        return Ok(json.clone());
+    }
+    serde_json::from_str(&render_content(&blocks))
+        .context("The embeddings tool returned a non-JSON result")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_chunk_text_overlaps_on_word_boundaries() {
+        let text = "alpha beta gamma delta epsilon zeta eta theta";
+        let chunks = chunk_text(text, 20, 6);
+
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 20));
+        assert_eq!(chunks[0], "alpha beta gamma");
+        // The next chunk repeats the end of the previous one
+        assert!(chunks[1].starts_with("gamma"));
+        assert!(chunks.last().unwrap().ends_with("theta"));
+    }
+
+    #[test]
+    fn test_chunk_text_short_and_empty_input() {
+        assert_eq!(chunk_text("short", 100, 10), vec!["short"]);
+        assert!(chunk_text("   \n ", 100, 10).is_empty());
+    }
+
+    #[test]
+    fn test_collect_files_recurses_and_skips_hidden() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/b.md"), "b").unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::write(dir.path().join(".secret"), "s").unwrap();
+
+        let files = collect_files(&[dir.path().to_path_buf()]).unwrap();
+        let names: Vec<String> = files
+            .iter()
+            .map(|f| f.strip_prefix(dir.path()).unwrap().display().to_string())
+            .collect();
+        assert_eq!(names, vec!["a.txt", "docs/b.md"]);
+
+        assert!(collect_files(&[dir.path().join("missing")]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_embeds_question_and_searches() {
+        let ollama_server = MockServer::start().await;
+        let mcp_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/embed"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"embeddings": [[0.5, 0.5]]})))
+            .mount(&ollama_server)
+            .await;
+        let search_result = json!({
+            "collection": "notes",
+            "results": [{"id": "a.md#0", "text": "The NAS is at 10.0.0.5", "score": 0.92, "metadata": {"source": "a.md"}}]
+        });
+        Mock::given(method("POST"))
+            .and(path("/tools/call"))
+            .and(body_partial_json(json!({
+                "tool_name": "embeddings",
+                "arguments": {"action": "search", "collection": "notes", "embedding": [0.5, 0.5], "top_k": 3}
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "content": [{"type": "text", "text": search_result.to_string()}],
+                "error": null
+            })))
+            .mount(&mcp_server)
+            .await;
+
+        let ollama = OllamaClient::new(&ollama_server.uri());
+        let mcp = McpClient::new(&mcp_server.uri());
+        let options = RagOptions { ollama: &ollama, mcp: &mcp, embed_model: DEFAULT_EMBED_MODEL, collection: "notes" };
+
+        let chunks = retrieve(&options, "Where is the NAS?", 3).await.unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].source(), "a.md");
+
+        let prompt = context_prompt(&chunks);
+        assert!(prompt.contains("[1] Source: a.md\nThe NAS is at 10.0.0.5"));
+    }
+}
@@ -1,4 +1,5 @@
 use assert_cmd::Command;
+use mcp_test_support::mocks;
 use predicates::prelude::*;
 use serde_json::json;
 use wiremock::{
@@ -8,7 +9,15 @@ use wiremock::{
 
 /// Helper function to create a Command with the CLI binary
 fn cli_command() -> Command {
-    Command::cargo_bin("mcp-client").unwrap()
+    let mut cmd = Command::cargo_bin("mcp-client").unwrap();
+    // Keep chat history written by tests out of the real data directory
+    cmd.env(
+        "MCP_CLIENT_HISTORY_DIR",
+        std::env::temp_dir().join("mcp-client-test-history"),
+    );
+    // Ignore any config file of the user running the tests
+    cmd.env("MCP_CLIENT_CONFIG", "/nonexistent/mcp-client/config.toml");
+    cmd
 }
 
 /// Helper to start MCP mock server
@@ -84,7 +93,7 @@ async fn test_list_tools_command_server_error() {
         .arg("list-tools");
 
     cmd.assert()
-        .success() // The command succeeds but logs an error
+        .failure()
         .stdout(predicate::str::contains("Failed to list tools"));
 }
 
@@ -131,22 +140,7 @@ async fn test_call_tool_command() {
 async fn test_call_tool_command_without_args() {
     let mock_server = start_mcp_mock_server().await;
 
-    Mock::given(method("POST"))
-        .and(path("/tools/call"))
-        .and(body_json(json!({
-            "tool_name": "simple_tool",
-            "arguments": {}
-        })))
-        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "success": true,
-            "content": [
-                {
-                    "type": "text",
-                    "text": "Simple tool executed without arguments"
-                }
-            ],
-            "error": null
-        })))
+    mocks::bridge_tool_call("simple_tool", json!({}), "Simple tool executed without arguments")
         .mount(&mock_server)
         .await;
 
@@ -180,6 +174,182 @@ async fn test_call_tool_command_invalid_json() {
         .stderr(predicate::str::contains("expected"));
 }
 
+#[tokio::test]
+async fn test_list_tools_command_json_output() {
+    let mock_server = start_mcp_mock_server().await;
+
+    Mock::given(method("GET"))
+        .and(path("/tools"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "tools": [
+                {
+                    "name": "test_tool",
+                    "description": "A test tool",
+                    "input_schema": {"type": "object", "properties": {}}
+                }
+            ]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mock_server.uri())
+        .arg("--output")
+        .arg("json")
+        .arg("list-tools");
+
+    // Logs go to stderr, so stdout must be exactly the JSON document
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let tools: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(tools[0]["name"], "test_tool");
+    assert_eq!(tools[0]["inputSchema"]["type"], "object");
+}
+
+#[tokio::test]
+async fn test_list_tools_command_filters_by_tag() {
+    let mock_server = start_mcp_mock_server().await;
+    let mut system_info = mocks::tool_definition("system_info", "Get system information");
+    system_info["tags"] = json!(["read-only"]);
+    let mut homeassistant = mocks::tool_definition("homeassistant", "Control devices");
+    homeassistant["tags"] = json!(["network", "destructive"]);
+    mocks::bridge_tools(vec![system_info, homeassistant])
+        .mount(&mock_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mock_server.uri())
+        .arg("list-tools")
+        .arg("--tag")
+        .arg("read-only");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("- system_info: Get system information [read-only]"))
+        .stdout(predicate::str::contains("homeassistant").not());
+}
+
+#[tokio::test]
+async fn test_export_tools_command_anthropic_format() {
+    let mock_server = start_mcp_mock_server().await;
+    mocks::bridge_tools(vec![mocks::tool_definition("system_info", "Get system information")])
+        .mount(&mock_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mock_server.uri())
+        .arg("export-tools")
+        .arg("--format")
+        .arg("anthropic");
+
+    // Pipeable as-is: logs go to stderr even without --output json
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let tools: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(tools[0]["name"], "system_info");
+    assert_eq!(tools[0]["description"], "Get system information");
+    assert!(tools[0]["input_schema"].is_object());
+    assert!(tools[0].get("function").is_none());
+}
+
+#[tokio::test]
+async fn test_call_tool_command_structured_failure() {
+    let mock_server = start_mcp_mock_server().await;
+
+    Mock::given(method("POST"))
+        .and(path("/tools/call"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": false,
+            "content": null,
+            "error": "disk not found"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mock_server.uri())
+        .arg("call-tool")
+        .arg("--name")
+        .arg("system_info")
+        .arg("--output")
+        .arg("yaml");
+
+    let output = cmd.assert().failure().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("success: false"));
+    assert!(stdout.contains("disk not found"));
+}
+
+#[tokio::test]
+async fn test_call_tools_command_report() {
+    let mock_server = start_mcp_mock_server().await;
+
+    mocks::bridge_tool_call("system_info", json!({"action": "get_disks"}), "/: 40% used")
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/tools/call"))
+        .and(body_json(json!({"tool_name": "new_plugin", "arguments": {}})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": false,
+            "content": [],
+            "error": "Tool not found: new_plugin"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mock_server.uri())
+        .arg("call-tools")
+        .arg("--calls")
+        .arg(r#"[{"name": "system_info", "args": {"action": "get_disks"}}, {"name": "new_plugin"}]"#)
+        .arg("--concurrency")
+        .arg("2");
+
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("[ok] system_info"))
+        .stdout(predicate::str::contains("/: 40% used"))
+        .stdout(predicate::str::contains("[failed] new_plugin"))
+        .stdout(predicate::str::contains("1 succeeded, 1 failed"));
+}
+
+#[tokio::test]
+async fn test_call_tools_command_json_from_file() {
+    let mock_server = start_mcp_mock_server().await;
+
+    Mock::given(method("POST"))
+        .and(path("/tools/call"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "content": [{"type": "text", "text": "ok"}],
+            "error": null
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("calls.json");
+    std::fs::write(&file, r#"[{"name": "a"}, {"name": "b"}, {"name": "c"}]"#).unwrap();
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mock_server.uri())
+        .arg("--output")
+        .arg("json")
+        .arg("call-tools")
+        .arg("--file")
+        .arg(&file);
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["succeeded"], 3);
+    assert_eq!(report["results"][2]["name"], "c");
+}
+
 #[tokio::test]
 async fn test_list_models_command() {
     let mock_server = start_ollama_mock_server().await;
@@ -231,22 +401,124 @@ async fn test_list_models_command_server_error() {
         .arg("list-models");
 
     cmd.assert()
-        .success() // The command succeeds but logs an error
+        .failure()
         .stdout(predicate::str::contains("Failed to list models"));
 }
 
+#[tokio::test]
+async fn test_pull_model_command() {
+    let mock_server = start_ollama_mock_server().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/pull"))
+        .and(body_json(json!({"model": "llama3.1"})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+            "{\"status\":\"pulling manifest\"}\n",
+            "{\"status\":\"pulling abc\",\"digest\":\"sha256:abc\",\"total\":10,\"completed\":10}\n",
+            "{\"status\":\"success\"}\n",
+        )))
+        .mount(&mock_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--ollama-url")
+        .arg(mock_server.uri())
+        .arg("pull-model")
+        .arg("--name")
+        .arg("llama3.1");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Pulled model llama3.1"));
+}
+
+#[tokio::test]
+async fn test_delete_model_command_not_found() {
+    let mock_server = start_ollama_mock_server().await;
+
+    Mock::given(method("DELETE"))
+        .and(path("/api/delete"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("{\"error\":\"model 'nope' not found\"}"))
+        .mount(&mock_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--ollama-url")
+        .arg(mock_server.uri())
+        .arg("delete-model")
+        .arg("--name")
+        .arg("nope");
+
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Failed to delete model"));
+}
+
+#[tokio::test]
+async fn test_show_model_command_json_output() {
+    let mock_server = start_ollama_mock_server().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/show"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "template": "{{ .Prompt }}",
+            "details": {"format": "gguf", "family": "llama", "parameter_size": "8.0B", "quantization_level": "Q4_0"}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--ollama-url")
+        .arg(mock_server.uri())
+        .arg("--output")
+        .arg("json")
+        .arg("show-model")
+        .arg("--name")
+        .arg("llama3.1");
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let info: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(info["details"]["parameter_size"], "8.0B");
+}
+
 #[tokio::test]
 async fn test_ask_command() {
     let mock_server = start_ollama_mock_server().await;
 
+    mocks::ollama_generate("llama2:latest", "What is the capital of France?", "The capital of France is Paris.")
+        .mount(&mock_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--ollama-url")
+        .arg(format!("{}", mock_server.uri()))
+        .arg("ask")
+        .arg("--model")
+        .arg("llama2:latest")
+        .arg("--prompt")
+        .arg("What is the capital of France?");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("The capital of France is Paris."));
+}
+
+#[tokio::test]
+async fn test_ask_command_with_system_prompt() {
+    let mock_server = start_ollama_mock_server().await;
+
     Mock::given(method("POST"))
-        .and(path("/api/generate"))
+        .and(path("/api/chat"))
         .and(body_json(json!({
             "model": "llama2:latest",
-            "prompt": "What is the capital of France?"
+            "messages": [
+                {"role": "system", "content": "Answer in one word. The question was: Capital of France?"},
+                {"role": "user", "content": "Capital of France?"}
+            ],
+            "stream": false
         })))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "response": "The capital of France is Paris.",
+            "message": {"role": "assistant", "content": "Paris"},
             "done": true
         })))
         .mount(&mock_server)
@@ -254,16 +526,51 @@ async fn test_ask_command() {
 
     let mut cmd = cli_command();
     cmd.arg("--ollama-url")
-        .arg(format!("{}", mock_server.uri()))
+        .arg(mock_server.uri())
+        .arg("--no-stream")
         .arg("ask")
         .arg("--model")
         .arg("llama2:latest")
         .arg("--prompt")
-        .arg("What is the capital of France?");
+        .arg("Capital of France?")
+        .arg("--system-prompt")
+        .arg("Answer in one word. The question was: {{input}}");
 
     cmd.assert()
         .success()
-        .stdout(predicate::str::contains("The capital of France is Paris."));
+        .stdout(predicate::str::contains("Paris"));
+}
+
+#[tokio::test]
+async fn test_ask_command_streams_multi_chunk_response() {
+    let mock_server = start_ollama_mock_server().await;
+
+    let body = format!(
+        "{}\n{}\n",
+        json!({"response": "The capital of France ", "done": false}),
+        json!({"response": "is Paris.", "done": true})
+    );
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .mount(&mock_server)
+        .await;
+
+    for extra_args in [vec![], vec!["--no-stream"]] {
+        let mut cmd = cli_command();
+        cmd.arg("--ollama-url")
+            .arg(mock_server.uri())
+            .arg("ask")
+            .arg("--model")
+            .arg("llama2:latest")
+            .arg("--prompt")
+            .arg("What is the capital of France?")
+            .args(&extra_args);
+
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("The capital of France is Paris."));
+    }
 }
 
 #[tokio::test]
@@ -321,11 +628,15 @@ async fn test_chat_command_tool_listing() {
         .mount(&mcp_server)
         .await;
 
-    // Mock Ollama generation with simple response (not a tool call)
+    // Mock Ollama chat with a plain reply (no tool calls), checking the tool was offered
     Mock::given(method("POST"))
-        .and(path("/api/generate"))
+        .and(path("/api/chat"))
+        .and(wiremock::matchers::body_string_contains(r#""name":"weather_tool""#))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "response": "I can help you with weather information using the weather_tool. Just ask me about the weather in any location!",
+            "message": {
+                "role": "assistant",
+                "content": "I can help you with weather information using the weather_tool. Just ask me about the weather in any location!"
+            },
             "done": true
         })))
         .mount(&ollama_server)
@@ -347,6 +658,75 @@ async fn test_chat_command_tool_listing() {
         .stdout(predicate::str::contains("I can help you with weather information"));
 }
 
+#[tokio::test]
+async fn test_chat_command_with_template_file() {
+    let mcp_server = start_mcp_mock_server().await;
+    let ollama_server = start_ollama_mock_server().await;
+
+    Mock::given(method("GET"))
+        .and(path("/tools"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "tools": [{
+                "name": "weather_tool",
+                "description": "Get weather information for a location",
+                "input_schema": {"type": "object"}
+            }]
+        })))
+        .mount(&mcp_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .and(wiremock::matchers::body_string_contains(
+            r#""content":"Tools for llama2:latest:\n- weather_tool: Get weather information for a location""#,
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message": {"role": "assistant", "content": "Templated reply"},
+            "done": true
+        })))
+        .mount(&ollama_server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("system.txt");
+    std::fs::write(&template, "Tools for {{model}}:\n{{tools}}\n").unwrap();
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mcp_server.uri())
+        .arg("--ollama-url")
+        .arg(ollama_server.uri())
+        .arg("chat")
+        .arg("--model")
+        .arg("llama2:latest")
+        .arg("--prompt")
+        .arg("Hi")
+        .arg("--template")
+        .arg(&template);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Templated reply"));
+}
+
+#[test]
+fn test_system_prompt_and_template_conflict() {
+    let mut cmd = cli_command();
+    cmd.arg("ask")
+        .arg("--model")
+        .arg("llama2:latest")
+        .arg("--prompt")
+        .arg("Hi")
+        .arg("--system-prompt")
+        .arg("Be brief")
+        .arg("--template")
+        .arg("system.txt");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
 #[tokio::test]
 async fn test_chat_command_tool_execution() {
     let mcp_server = start_mcp_mock_server().await;
@@ -376,12 +756,18 @@ async fn test_chat_command_tool_execution() {
         .mount(&mcp_server)
         .await;
 
-    // Mock tool call response from Ollama (when prompt contains system information about tools)
+    // Mock tool call response from Ollama for the initial question
     Mock::given(method("POST"))
-        .and(path("/api/generate"))
+        .and(path("/api/chat"))
         .and(wiremock::matchers::body_string_contains("What's the weather like in Paris?"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "response": r#"{"type":"tool","tool_name":"weather_tool","arguments":{"location":"Paris"}}"#,
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [
+                    {"function": {"name": "weather_tool", "arguments": {"location": "Paris"}}}
+                ]
+            },
             "done": true
         })))
         .mount(&ollama_server)
@@ -409,14 +795,19 @@ async fn test_chat_command_tool_execution() {
         .mount(&mcp_server)
         .await;
 
-    // Mock interpretation response from Ollama (when prompt contains "I received this result")
+    // Mock interpretation response from Ollama (once the conversation contains a tool message)
     Mock::given(method("POST"))
-        .and(path("/api/generate"))
-        .and(wiremock::matchers::body_string_contains("I received this result from running a tool"))
+        .and(path("/api/chat"))
+        .and(wiremock::matchers::body_string_contains(r#""role":"tool""#))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "response": "The weather in Paris is currently 22°C with sunny skies and light clouds. It's a pleasant day!",
+            "message": {
+                "role": "assistant",
+                "content": "The weather in Paris is currently 22°C with sunny skies and light clouds. It's a pleasant day!"
+            },
             "done": true
         })))
+        // Follow-up requests also contain the original question, so this must win
+        .with_priority(1)
         .mount(&ollama_server)
         .await;
 
@@ -438,6 +829,76 @@ async fn test_chat_command_tool_execution() {
         .stdout(predicate::str::contains("pleasant day"));
 }
 
+#[tokio::test]
+async fn test_chat_command_confirm_declines_tool_call() {
+    let mcp_server = start_mcp_mock_server().await;
+    let ollama_server = start_ollama_mock_server().await;
+
+    Mock::given(method("GET"))
+        .and(path("/tools"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "tools": [{
+                "name": "call_service",
+                "description": "Call a Home Assistant service",
+                "input_schema": {"type": "object"}
+            }]
+        })))
+        .mount(&mcp_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/tools/call"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mcp_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .and(wiremock::matchers::body_string_contains("declined"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message": {"role": "assistant", "content": "Understood, the lights stay on."},
+            "done": true
+        })))
+        .with_priority(1)
+        .mount(&ollama_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [
+                    {"function": {"name": "call_service", "arguments": {"service": "light.turn_off"}}}
+                ]
+            },
+            "done": true
+        })))
+        .with_priority(2)
+        .mount(&ollama_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mcp_server.uri())
+        .arg("--ollama-url")
+        .arg(ollama_server.uri())
+        .arg("--no-stream")
+        .arg("chat")
+        .arg("--model")
+        .arg("llama3.1")
+        .arg("--prompt")
+        .arg("Turn off the lights")
+        .arg("--confirm")
+        .write_stdin("n\n");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("The model wants to run call_service"))
+        .stdout(predicate::str::contains("Skipped tool: call_service"))
+        .stdout(predicate::str::contains("the lights stay on"));
+}
+
 #[tokio::test]
 async fn test_chat_command_mcp_server_failure() {
     let mcp_server = start_mcp_mock_server().await;
@@ -489,11 +950,14 @@ async fn test_chat_command_invalid_tool_call() {
         .mount(&mcp_server)
         .await;
 
-    // Mock invalid JSON response from Ollama
+    // Mock a reply that looks like a tool call but has no tool_calls field
     Mock::given(method("POST"))
-        .and(path("/api/generate"))
+        .and(path("/api/chat"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "response": "This is not a valid JSON tool call",
+            "message": {
+                "role": "assistant",
+                "content": r#"This is not a valid JSON tool call: {"tool_name":"test_tool"}"#
+            },
             "done": true
         })))
         .mount(&ollama_server)
@@ -508,14 +972,293 @@ async fn test_chat_command_invalid_tool_call() {
         .arg("--model")
         .arg("llama2:latest")
         .arg("--prompt")
-        .arg("Test prompt");
+        .arg("Test prompt")
+        .arg("--no-stream");
 
+    // Text content is never parsed for tool calls; it is the final answer
     cmd.assert()
         .success()
-        .stdout(predicate::str::contains("Failed to parse JSON"))
+        .stdout(predicate::str::contains("Answer:"))
         .stdout(predicate::str::contains("This is not a valid JSON tool call"));
 }
 
+#[tokio::test]
+async fn test_chat_command_stops_at_max_iterations() {
+    let mcp_server = start_mcp_mock_server().await;
+    let ollama_server = start_ollama_mock_server().await;
+
+    Mock::given(method("GET"))
+        .and(path("/tools"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "tools": [
+                {
+                    "name": "counter",
+                    "description": "Counts",
+                    "input_schema": {"type": "object", "properties": {}}
+                }
+            ]
+        })))
+        .mount(&mcp_server)
+        .await;
+
+    // The model asks for a different tool call every turn and never answers
+    for step in 0..3 {
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{"function": {"name": "counter", "arguments": {"step": step}}}]
+                },
+                "done": true
+            })))
+            .up_to_n_times(1)
+            .with_priority(step + 1)
+            .mount(&ollama_server)
+            .await;
+    }
+
+    Mock::given(method("POST"))
+        .and(path("/tools/call"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "content": [{"type": "text", "text": "counted"}],
+            "error": null
+        })))
+        .mount(&mcp_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mcp_server.uri())
+        .arg("--ollama-url")
+        .arg(ollama_server.uri())
+        .arg("chat")
+        .arg("--model")
+        .arg("llama2:latest")
+        .arg("--prompt")
+        .arg("Count")
+        .arg("--max-iterations")
+        .arg("2");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Stopped after 2 iterations"))
+        .stdout(predicate::str::contains("--- Transcript ---"));
+}
+
+#[tokio::test]
+async fn test_history_records_and_resumes_chat() {
+    let mcp_server = start_mcp_mock_server().await;
+    let ollama_server = start_ollama_mock_server().await;
+    let history_dir = tempfile::tempdir().unwrap();
+
+    Mock::given(method("GET"))
+        .and(path("/tools"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"tools": []})))
+        .mount(&mcp_server)
+        .await;
+
+    // The resumed chat must include the first exchange
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .and(wiremock::matchers::body_string_contains("Paris is the capital."))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message": {"role": "assistant", "content": "About 2.1 million people live there."},
+            "done": true
+        })))
+        .with_priority(1)
+        .mount(&ollama_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message": {"role": "assistant", "content": "Paris is the capital."},
+            "done": true
+        })))
+        .mount(&ollama_server)
+        .await;
+
+    let run = |args: &[&str]| {
+        let mut cmd = cli_command();
+        cmd.env("MCP_CLIENT_HISTORY_DIR", history_dir.path())
+            .arg("--mcp-url")
+            .arg(mcp_server.uri())
+            .arg("--ollama-url")
+            .arg(ollama_server.uri())
+            .args(args);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap()
+    };
+
+    let output = run(&["chat", "--model", "llama3.1", "--prompt", "What is the capital of France?"]);
+    let id = output
+        .lines()
+        .find_map(|line| line.strip_prefix("Conversation saved as "))
+        .expect("conversation id is printed")
+        .to_string();
+
+    let listing = run(&["history", "list"]);
+    assert!(listing.contains(&id));
+    assert!(listing.contains("What is the capital of France?"));
+
+    let resumed = run(&["history", "resume", "--id", &id, "--prompt", "How many people live there?"]);
+    assert!(resumed.contains("2.1 million"));
+    assert!(resumed.contains(&format!("Conversation saved as {}", id)));
+
+    let shown = run(&["history", "show", "--id", &id]);
+    assert!(shown.contains("User: What is the capital of France?"));
+    assert!(shown.contains("Assistant: Paris is the capital."));
+    assert!(shown.contains("User: How many people live there?"));
+}
+
+#[tokio::test]
+async fn test_chat_command_openai_backend() {
+    let mcp_server = start_mcp_mock_server().await;
+    let llm_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/tools"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "tools": [
+                {
+                    "name": "system_info",
+                    "description": "Get system information",
+                    "input_schema": {"type": "object", "properties": {}}
+                }
+            ]
+        })))
+        .mount(&mcp_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(wiremock::matchers::body_string_contains(r#""role":"tool""#))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"role": "assistant", "content": "Your CPU is mostly idle."}}]
+        })))
+        .with_priority(1)
+        .mount(&llm_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "system_info", "arguments": "{}"}
+                }]
+            }}]
+        })))
+        .mount(&llm_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/tools/call"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "content": [{"type": "text", "text": "CPU: 3%"}],
+            "error": null
+        })))
+        .mount(&mcp_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mcp_server.uri())
+        .arg("--backend")
+        .arg("openai")
+        .arg("--api-url")
+        .arg(llm_server.uri())
+        .arg("chat")
+        .arg("--model")
+        .arg("gpt-4o-mini")
+        .arg("--prompt")
+        .arg("How busy is my CPU?");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Using tool: system_info"))
+        .stdout(predicate::str::contains("Your CPU is mostly idle."));
+}
+
+#[tokio::test]
+async fn test_anthropic_backend_requires_api_key() {
+    let mut cmd = cli_command();
+    cmd.env_remove("ANTHROPIC_API_KEY")
+        .arg("--backend")
+        .arg("anthropic")
+        .arg("ask")
+        .arg("--model")
+        .arg("claude-3-5-haiku-latest")
+        .arg("--prompt")
+        .arg("hi");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("ANTHROPIC_API_KEY"));
+}
+
+#[tokio::test]
+async fn test_config_profile_supplies_defaults() {
+    let ollama_server = start_ollama_mock_server().await;
+    let config_dir = tempfile::tempdir().unwrap();
+    let config_path = config_dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "default_profile = \"local\"\n\n[profiles.local]\nollama_url = \"{}\"\nmodel = \"profile-model\"\n\n[profiles.other]\nmodel = \"other-model\"\n",
+            ollama_server.uri()
+        ),
+    )
+    .unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .and(body_json(json!({"model": "profile-model", "prompt": "Hello"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "response": "Hi from the profile model", "done": true
+        })))
+        .mount(&ollama_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .and(body_json(json!({"model": "flag-model", "prompt": "Hello"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "response": "Hi from the flag model", "done": true
+        })))
+        .mount(&ollama_server)
+        .await;
+
+    // The default profile provides both the URL and the model
+    let mut cmd = cli_command();
+    cmd.env("MCP_CLIENT_CONFIG", &config_path)
+        .args(["ask", "--prompt", "Hello"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Hi from the profile model"));
+
+    // Flags override the profile
+    let mut cmd = cli_command();
+    cmd.env("MCP_CLIENT_CONFIG", &config_path)
+        .args(["ask", "--model", "flag-model", "--prompt", "Hello"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Hi from the flag model"));
+
+    // Unknown profiles are reported rather than silently ignored
+    let mut cmd = cli_command();
+    cmd.env("MCP_CLIENT_CONFIG", &config_path)
+        .args(["--profile", "missing", "ask", "--prompt", "Hello"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Profile 'missing' not found"));
+}
+
 #[tokio::test]
 async fn test_cli_custom_urls() {
     let mcp_server = start_mcp_mock_server().await;
@@ -597,4 +1340,127 @@ async fn test_missing_required_arguments() {
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("required"));
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_embed_command_stores_chunks() {
+    let mcp_server = start_mcp_mock_server().await;
+    let ollama_server = start_ollama_mock_server().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/embed"))
+        .and(body_json(json!({"model": "nomic-embed-text", "input": ["The NAS lives at 10.0.0.5."]})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"embeddings": [[0.1, 0.9]]})))
+        .expect(1)
+        .mount(&ollama_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/tools/call"))
+        .and(wiremock::matchers::body_partial_json(json!({
+            "tool_name": "embeddings",
+            "arguments": {"action": "store", "collection": "homelab"}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "content": [{"type": "text", "text": "{\"collection\": \"homelab\", \"stored\": 1}"}],
+            "error": null
+        })))
+        .expect(1)
+        .mount(&mcp_server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("nas.md"), "The NAS lives at 10.0.0.5.\n").unwrap();
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mcp_server.uri())
+        .arg("--ollama-url")
+        .arg(ollama_server.uri())
+        .arg("embed")
+        .arg("--path")
+        .arg(dir.path())
+        .arg("--collection")
+        .arg("homelab");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Stored 1 chunks from 1 files in collection 'homelab'"));
+}
+
+#[tokio::test]
+async fn test_rag_chat_command_injects_retrieved_chunks() {
+    let mcp_server = start_mcp_mock_server().await;
+    let ollama_server = start_ollama_mock_server().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/embed"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"embeddings": [[0.1, 0.9]]})))
+        .mount(&ollama_server)
+        .await;
+    let search_result = json!({
+        "collection": "default",
+        "results": [{"id": "nas.md#0", "text": "The NAS lives at 10.0.0.5.", "score": 0.87, "metadata": {"source": "nas.md"}}]
+    });
+    Mock::given(method("POST"))
+        .and(path("/tools/call"))
+        .and(wiremock::matchers::body_partial_json(json!({"arguments": {"action": "search"}})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "content": [{"type": "text", "text": search_result.to_string()}],
+            "error": null
+        })))
+        .mount(&mcp_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .and(wiremock::matchers::body_string_contains("The NAS lives at 10.0.0.5."))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message": {"role": "assistant", "content": "Your NAS is at 10.0.0.5 (nas.md)."},
+            "done": true
+        })))
+        .mount(&ollama_server)
+        .await;
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(mcp_server.uri())
+        .arg("--ollama-url")
+        .arg(ollama_server.uri())
+        .arg("--no-stream")
+        .arg("rag-chat")
+        .arg("--model")
+        .arg("llama3.1")
+        .arg("--prompt")
+        .arg("Where is my NAS?");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Your NAS is at 10.0.0.5"))
+        .stdout(predicate::str::contains("- nas.md (score 0.87)"));
+}
+
+/// Runs against `mcp-mock-server` (from mcp-server) with its default `echo`
+/// tool; skipped unless `MCP_MOCK_SERVER_URL` points at one
+#[test]
+fn test_call_tool_command_against_mock_server() {
+    let Ok(url) = std::env::var("MCP_MOCK_SERVER_URL") else {
+        println!("MCP_MOCK_SERVER_URL is not set; skipping");
+        return;
+    };
+
+    let mut cmd = cli_command();
+    cmd.arg("--mcp-url")
+        .arg(url)
+        .arg("--mcp-protocol")
+        .arg("jsonrpc")
+        .arg("call-tool")
+        .arg("--name")
+        .arg("echo")
+        .arg("--args")
+        .arg(r#"{"message": "hi"}"#);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""text": "{\"message\":\"hi\"}""#));
+}
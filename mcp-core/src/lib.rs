@@ -0,0 +1,406 @@
+//! Errors shared by the MCP server, the HTTP bridge and the client, and how
+//! they map to JSON-RPC error objects and HTTP statuses. With the `logging`
+//! feature, also the log output options the three binaries take.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+
+#[cfg(feature = "logging")]
+pub mod logging;
+
+/// The message is not JSON
+pub const PARSE_ERROR: i32 = -32700;
+/// The message is JSON but not a valid request
+pub const INVALID_REQUEST: i32 = -32600;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+/// Bad or missing params, including names of tools, prompts, jobs and
+/// workflows the server does not know
+pub const INVALID_PARAMS: i32 = -32602;
+pub const INTERNAL_ERROR: i32 = -32603;
+
+// Server errors, from -32000 down to -32099
+
+/// A tool ran and failed. Only calls made outside `tools/call`, such as
+/// jobs and workflow steps, see this; `tools/call` reports the failure in
+/// an `isError` result instead.
+pub const TOOL_EXECUTION_FAILED: i32 = -32000;
+pub const SESSION_NOT_FOUND: i32 = -32001;
+/// As defined by MCP for `resources/read`
+pub const RESOURCE_NOT_FOUND: i32 = -32002;
+/// The session has not sent `notifications/initialized` yet
+pub const SESSION_NOT_INITIALIZED: i32 = -32003;
+pub const RATE_LIMITED: i32 = -32005;
+pub const BUDGET_EXCEEDED: i32 = -32006;
+/// The server is draining for shutdown and starts no new sessions
+pub const SHUTTING_DOWN: i32 = -32007;
+/// The tool's plugin failed to initialize and is being retried
+pub const PLUGIN_UNAVAILABLE: i32 = -32008;
+/// The caller's deadline (`_meta.timeoutMs`) passed before the tool
+/// finished, and the server stopped it
+pub const DEADLINE_EXCEEDED: i32 = -32009;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// What went wrong, sent as `kind` in every error's data so clients such as
+/// the HTTP bridge can act on it without parsing messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    ParseError,
+    InvalidRequest,
+    MethodNotFound,
+    InvalidParams,
+    /// Invalid params naming something the server does not know
+    NotFound,
+    Internal,
+    ToolFailed,
+    SessionNotFound,
+    ResourceNotFound,
+    SessionNotInitialized,
+    RateLimited,
+    BudgetExceeded,
+    ShuttingDown,
+    PluginUnavailable,
+    DeadlineExceeded,
+}
+
+impl ErrorKind {
+    /// The kind of errors with `code`, unless they are more specific
+    pub fn for_code(code: i32) -> Self {
+        match code {
+            PARSE_ERROR => ErrorKind::ParseError,
+            INVALID_REQUEST => ErrorKind::InvalidRequest,
+            METHOD_NOT_FOUND => ErrorKind::MethodNotFound,
+            INVALID_PARAMS => ErrorKind::InvalidParams,
+            TOOL_EXECUTION_FAILED => ErrorKind::ToolFailed,
+            SESSION_NOT_FOUND => ErrorKind::SessionNotFound,
+            RESOURCE_NOT_FOUND => ErrorKind::ResourceNotFound,
+            SESSION_NOT_INITIALIZED => ErrorKind::SessionNotInitialized,
+            RATE_LIMITED => ErrorKind::RateLimited,
+            BUDGET_EXCEEDED => ErrorKind::BudgetExceeded,
+            SHUTTING_DOWN => ErrorKind::ShuttingDown,
+            PLUGIN_UNAVAILABLE => ErrorKind::PluginUnavailable,
+            DEADLINE_EXCEEDED => ErrorKind::DeadlineExceeded,
+            _ => ErrorKind::Internal,
+        }
+    }
+
+    /// The code errors of this kind are sent with
+    pub fn code(self) -> i32 {
+        match self {
+            ErrorKind::ParseError => PARSE_ERROR,
+            ErrorKind::InvalidRequest => INVALID_REQUEST,
+            ErrorKind::MethodNotFound => METHOD_NOT_FOUND,
+            ErrorKind::InvalidParams | ErrorKind::NotFound => INVALID_PARAMS,
+            ErrorKind::Internal => INTERNAL_ERROR,
+            ErrorKind::ToolFailed => TOOL_EXECUTION_FAILED,
+            ErrorKind::SessionNotFound => SESSION_NOT_FOUND,
+            ErrorKind::ResourceNotFound => RESOURCE_NOT_FOUND,
+            ErrorKind::SessionNotInitialized => SESSION_NOT_INITIALIZED,
+            ErrorKind::RateLimited => RATE_LIMITED,
+            ErrorKind::BudgetExceeded => BUDGET_EXCEEDED,
+            ErrorKind::ShuttingDown => SHUTTING_DOWN,
+            ErrorKind::PluginUnavailable => PLUGIN_UNAVAILABLE,
+            ErrorKind::DeadlineExceeded => DEADLINE_EXCEEDED,
+        }
+    }
+
+    /// The `message` errors of this kind are sent with
+    pub fn message(self) -> &'static str {
+        match self {
+            ErrorKind::ParseError => "Parse error",
+            ErrorKind::InvalidRequest => "Invalid Request",
+            ErrorKind::MethodNotFound => "Method not found",
+            ErrorKind::InvalidParams | ErrorKind::NotFound => "Invalid params",
+            ErrorKind::Internal => "Internal error",
+            ErrorKind::ToolFailed => "Tool execution failed",
+            ErrorKind::SessionNotFound => "Session not found",
+            ErrorKind::ResourceNotFound => "Resource not found",
+            ErrorKind::SessionNotInitialized => "Session not initialized",
+            ErrorKind::RateLimited => "Rate limit exceeded",
+            ErrorKind::BudgetExceeded => "Session budget exceeded",
+            ErrorKind::ShuttingDown => "Server is shutting down",
+            ErrorKind::PluginUnavailable => "Plugin unavailable",
+            ErrorKind::DeadlineExceeded => "Deadline exceeded",
+        }
+    }
+
+    /// The status an HTTP front end, such as the bridge, answers errors of
+    /// this kind with
+    pub fn http_status(self) -> u16 {
+        match self {
+            ErrorKind::NotFound | ErrorKind::ResourceNotFound | ErrorKind::MethodNotFound | ErrorKind::SessionNotFound => 404,
+            ErrorKind::InvalidParams | ErrorKind::InvalidRequest | ErrorKind::ParseError => 400,
+            ErrorKind::RateLimited | ErrorKind::BudgetExceeded => 429,
+            ErrorKind::PluginUnavailable | ErrorKind::ShuttingDown => 503,
+            ErrorKind::DeadlineExceeded => 504,
+            ErrorKind::Internal | ErrorKind::ToolFailed | ErrorKind::SessionNotInitialized => 500,
+        }
+    }
+}
+
+/// Error data of `kind`: a string `data` becomes `detail`, and the fields
+/// of an object `data`, such as `retryAfter`, are kept
+pub fn error_data(kind: ErrorKind, data: Option<Value>) -> Value {
+    let mut fields = Map::new();
+    fields.insert("kind".to_string(), serde_json::to_value(kind).unwrap());
+    match data {
+        Some(Value::Object(data)) => fields.extend(data),
+        Some(Value::Null) | None => {}
+        Some(Value::String(detail)) => {
+            fields.insert("detail".to_string(), Value::String(detail));
+        }
+        Some(detail) => {
+            fields.insert("detail".to_string(), detail);
+        }
+    }
+    Value::Object(fields)
+}
+
+/// The error of a JSON-RPC response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i32,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    /// An error with `code`, whose data gets the code's kind
+    pub fn new(code: i32, message: &str, data: Option<Value>) -> Self {
+        Self::of_kind(ErrorKind::for_code(code), code, message, data)
+    }
+
+    /// An error of `kind` with its usual code and message
+    pub fn from_kind(kind: ErrorKind, data: Option<Value>) -> Self {
+        Self::of_kind(kind, kind.code(), kind.message(), data)
+    }
+
+    /// Invalid params naming a tool, prompt, job or workflow that does not
+    /// exist
+    pub fn not_found(detail: impl Into<String>) -> Self {
+        Self::from_kind(ErrorKind::NotFound, Some(Value::String(detail.into())))
+    }
+
+    fn of_kind(kind: ErrorKind, code: i32, message: &str, data: Option<Value>) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            data: Some(error_data(kind, data)),
+        }
+    }
+
+    /// The `kind` in the error's data; `None` from servers that send none
+    pub fn kind(&self) -> Option<ErrorKind> {
+        serde_json::from_value(self.data.as_ref()?.get("kind")?.clone()).ok()
+    }
+
+    /// The human `detail` in the error's data
+    pub fn detail(&self) -> Option<&str> {
+        self.data.as_ref()?.get("detail")?.as_str()
+    }
+
+    /// The status an HTTP front end answers the error with: by its kind or,
+    /// from servers that send none, its code
+    pub fn http_status(&self) -> u16 {
+        if let Some(kind) = self.kind() {
+            return kind.http_status();
+        }
+        match self.code {
+            // Servers without error kinds also use invalid params for unknown names
+            INVALID_PARAMS | RESOURCE_NOT_FOUND | METHOD_NOT_FOUND | SESSION_NOT_FOUND => 404,
+            code => ErrorKind::for_code(code).http_status(),
+        }
+    }
+}
+
+impl fmt::Display for JsonRpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)?;
+        match self.data.as_ref().map(|data| data.get("detail").unwrap_or(data)) {
+            Some(Value::String(detail)) => write!(f, ": {}", detail)?,
+            Some(data) if self.kind().is_none() => write!(f, ": {}", data)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for JsonRpcError {}
+
+/// Anything that can go wrong between a client and a tool
+#[derive(Debug)]
+pub enum Error {
+    /// The peer answered with a JSON-RPC error
+    Rpc(JsonRpcError),
+    /// A tool, prompt, resource or other name that does not exist
+    NotFound(String),
+    /// Arguments that are missing or wrong
+    InvalidParams(String),
+    /// A failure on this side
+    Internal(String),
+    /// The peer could not be reached, or did not answer with JSON-RPC
+    Transport(String),
+    /// A message that could not be encoded or decoded
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    /// `source`, which happened while doing `context`
+    Context { context: String, source: Box<Error> },
+}
+
+impl Error {
+    /// The kind of error, that of the underlying one for `Context`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Rpc(e) => e.kind().unwrap_or_else(|| ErrorKind::for_code(e.code)),
+            Error::NotFound(_) => ErrorKind::NotFound,
+            Error::InvalidParams(_) => ErrorKind::InvalidParams,
+            Error::Internal(_) | Error::Transport(_) | Error::Json(_) | Error::Io(_) => ErrorKind::Internal,
+            Error::Context { source, .. } => source.kind(),
+        }
+    }
+
+    /// The status an HTTP front end answers the error with
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Error::Rpc(e) => e.http_status(),
+            Error::Context { source, .. } => source.http_status(),
+            _ => self.kind().http_status(),
+        }
+    }
+
+    /// The error as sent in a JSON-RPC response: a peer's error as it was,
+    /// others with their kind's code and the whole message as `detail`
+    pub fn to_rpc(&self) -> JsonRpcError {
+        match self {
+            Error::Rpc(e) => e.clone(),
+            Error::Context { source, .. } if matches!(source.root(), Error::Rpc(_)) => source.to_rpc(),
+            _ => JsonRpcError::from_kind(self.kind(), Some(Value::String(self.to_string()))),
+        }
+    }
+
+    /// The error without the context added to it
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            e => e,
+        }
+    }
+
+    /// Adds what was being done when the error happened
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Error::Context { context: context.into(), source: Box::new(self) }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Rpc(e) => e.fmt(f),
+            Error::NotFound(detail) | Error::InvalidParams(detail) | Error::Internal(detail) | Error::Transport(detail) => {
+                f.write_str(detail)
+            }
+            Error::Json(e) => write!(f, "Invalid JSON: {}", e),
+            Error::Io(e) => e.fmt(f),
+            Error::Context { context, source } => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Rpc(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<JsonRpcError> for Error {
+    fn from(e: JsonRpcError) -> Self {
+        Error::Rpc(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Adds context to the error of any result whose error converts into
+/// [`Error`]
+pub trait ResultExt<T> {
+    fn context(self, context: impl Into<String>) -> Result<T>;
+
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|e| e.into().context(context()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_error_data() {
+        assert_eq!(error_data(ErrorKind::for_code(-32602), None), json!({"kind": "invalidParams"}));
+        assert_eq!(
+            error_data(ErrorKind::NotFound, Some(json!("Unknown job 7"))),
+            json!({"kind": "notFound", "detail": "Unknown job 7"})
+        );
+        assert_eq!(
+            error_data(ErrorKind::for_code(RATE_LIMITED), Some(json!({"retryAfter": 3}))),
+            json!({"kind": "rateLimited", "retryAfter": 3})
+        );
+        assert_eq!(ErrorKind::for_code(-32099), ErrorKind::Internal);
+    }
+
+    #[test]
+    fn test_http_status_follows_the_kind_or_else_the_code() {
+        assert_eq!(JsonRpcError::not_found("Unknown tool: nope").http_status(), 404);
+        assert_eq!(JsonRpcError::new(RATE_LIMITED, "Rate limited", Some(json!({"retryAfter": 3}))).http_status(), 429);
+        assert_eq!(JsonRpcError::new(PLUGIN_UNAVAILABLE, "Plugin unavailable", None).http_status(), 503);
+        assert_eq!(JsonRpcError::from_kind(ErrorKind::DeadlineExceeded, None).http_status(), 504);
+        // Servers without kinds
+        let bare = JsonRpcError { code: INVALID_PARAMS, message: "Unknown tool".to_string(), data: None };
+        assert_eq!((bare.kind(), bare.http_status()), (None, 404));
+        let bare = JsonRpcError { code: INVALID_REQUEST, message: "Bad".to_string(), data: None };
+        assert_eq!(bare.http_status(), 400);
+    }
+
+    #[test]
+    fn test_context_keeps_the_kind_and_a_peers_error() {
+        let missing: Result<(), _> = Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no such file"));
+        let error = missing.context("Reading tools.json").unwrap_err();
+        assert_eq!(error.to_string(), "Reading tools.json: no such file");
+        assert_eq!((error.kind(), error.http_status()), (ErrorKind::Internal, 500));
+        assert_eq!(error.to_rpc().detail(), Some("Reading tools.json: no such file"));
+
+        let refused = Error::from(JsonRpcError::new(BUDGET_EXCEEDED, "Session budget exceeded", Some(json!({"limit": "calls"}))));
+        let error = refused.context("Calling echo");
+        assert_eq!(error.kind(), ErrorKind::BudgetExceeded);
+        assert_eq!(error.to_rpc().data, Some(json!({"kind": "budgetExceeded", "limit": "calls"})));
+        assert_eq!(Error::NotFound("Unknown tool: nope".to_string()).to_rpc().code, INVALID_PARAMS);
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use tracing::Subscriber;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::{Error, Result, ResultExt};
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Pretty,
+    /// One JSON object per event, for log collectors
+    Json,
+}
+
+/// When `--log-file` starts a new file; older files keep a date suffix
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+/// Where and how a binary logs, from `--log-format`, `--log-file` and
+/// `--log-rotation`
+#[derive(Debug, Clone, Default)]
+pub struct LogOutput {
+    pub format: LogFormat,
+    pub file: Option<PathBuf>,
+    pub rotation: LogRotation,
+    /// Without a file, write to stderr rather than stdout, which then
+    /// carries protocol messages or command output
+    pub stderr: bool,
+}
+
+impl LogOutput {
+    /// The formatting layer, and a guard that flushes the log file when
+    /// dropped; keep it alive until the binary exits
+    pub fn layer<S>(&self) -> Result<(Box<dyn Layer<S> + Send + Sync>, Option<WorkerGuard>)>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let (writer, guard) = match &self.file {
+            Some(path) => {
+                let (writer, guard) = tracing_appender::non_blocking(self.appender(path)?);
+                (BoxMakeWriter::new(writer), Some(guard))
+            }
+            None if self.stderr => (BoxMakeWriter::new(std::io::stderr), None),
+            None => (BoxMakeWriter::new(std::io::stdout), None),
+        };
+        let layer = tracing_subscriber::fmt::layer().with_ansi(self.file.is_none());
+        let layer = match self.format {
+            LogFormat::Pretty => layer.with_writer(writer).boxed(),
+            LogFormat::Json => layer.json().with_writer(writer).boxed(),
+        };
+        Ok((layer, guard))
+    }
+
+    fn appender(&self, path: &Path) -> Result<RollingFileAppender> {
+        let directory = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let name = path
+            .file_name()
+            .ok_or_else(|| Error::InvalidParams(format!("Log file {} has no file name", path.display())))?;
+        let rotation = match self.rotation {
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        };
+        RollingFileAppender::builder()
+            .rotation(rotation)
+            .filename_prefix(name.to_string_lossy())
+            .build(directory)
+            .map_err(|e| Error::Internal(e.to_string()))
+            .with_context(|| format!("Cannot write logs to {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_json_logs_go_to_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.log");
+        let output = LogOutput {
+            format: LogFormat::Json,
+            file: Some(path.clone()),
+            rotation: LogRotation::Never,
+            stderr: false,
+        };
+        let (layer, guard) = output.layer().unwrap();
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            tracing::info!(tool = "echo", "Tool called");
+        });
+        drop(guard);
+
+        let line: serde_json::Value = serde_json::from_str(std::fs::read_to_string(&path).unwrap().trim()).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["fields"]["message"], "Tool called");
+        assert_eq!(line["fields"]["tool"], "echo");
+    }
+}
@@ -0,0 +1,38 @@
+//! `GET /anthropic/tools`: the tool list in the Anthropic Messages API's
+//! tool-use format, for Claude-based agents. Tool calls come back as
+//! `tool_use` blocks whose `name` and `input` map straight onto `/tools/call`.
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::error;
+use utoipa::ToSchema;
+
+use crate::AppState;
+
+/// A tool as the `tools` parameter of a Messages request takes it
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AnthropicTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+pub async fn list_tools_handler(State(state): State<AppState>) -> Result<Json<Vec<AnthropicTool>>, StatusCode> {
+    match state.mcp_client.list_tools().await {
+        Ok(tools) => Ok(Json(
+            tools
+                .into_iter()
+                .map(|tool| AnthropicTool {
+                    name: tool.name,
+                    description: tool.description,
+                    input_schema: tool.input_schema,
+                })
+                .collect(),
+        )),
+        Err(e) => {
+            error!("Failed to list tools: {:#}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
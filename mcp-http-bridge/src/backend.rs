@@ -0,0 +1,474 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::breaker::{BreakerState, BreakerStatus};
+use crate::mcp_client::{JsonRpcError, McpClient, ToolDefinition};
+use crate::{ContentBlock, JobListQuery, LogTailQuery, PromptInfo, PromptMessage, PromptResponse, ResourceContents, ResourceInfo, ToolUsage, UpstreamHealth};
+
+/// What the HTTP handlers need from an MCP server. `McpClient` talks to a real
+/// one; `MockBackend` answers from canned data so handlers can be tested.
+#[async_trait]
+pub trait McpBackend: Send + Sync {
+    async fn initialize(&self) -> Result<()>;
+
+    async fn list_tools(&self) -> Result<Vec<ToolDefinition>>;
+
+    async fn call_tool(&self, tool_name: &str, arguments: serde_json::Map<String, Value>) -> Result<Vec<ContentBlock>>;
+
+    /// Calls several tools, one result per call in order. Backends that can
+    /// send them in one batch override this; by default they are called one
+    /// after another.
+    async fn call_tools(&self, calls: Vec<(String, serde_json::Map<String, Value>)>) -> Result<Vec<Result<Vec<ContentBlock>>>> {
+        let mut results = Vec::with_capacity(calls.len());
+        for (tool_name, arguments) in calls {
+            results.push(self.call_tool(&tool_name, arguments).await);
+        }
+        Ok(results)
+    }
+
+    /// Recent server log lines, oldest first
+    async fn tail_logs(&self, query: &LogTailQuery) -> Result<Vec<Value>>;
+
+    /// Call counts, error rates and latency per tool, most called first
+    async fn tool_stats(&self) -> Result<Vec<ToolUsage>>;
+
+    /// Background jobs, newest first
+    async fn list_jobs(&self, query: &JobListQuery) -> Result<Vec<Value>>;
+
+    async fn get_job(&self, id: &str) -> Result<Value>;
+
+    async fn list_prompts(&self) -> Result<Vec<PromptInfo>>;
+
+    async fn get_prompt(&self, name: &str, arguments: HashMap<String, String>) -> Result<PromptResponse>;
+
+    async fn list_resources(&self) -> Result<Vec<ResourceInfo>>;
+
+    async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>>;
+
+    /// Drops any cached tool list so the next `list_tools` asks the server
+    async fn invalidate_tools(&self) {}
+
+    /// Whether the server can be reached, and what the bridge knows of it
+    async fn health(&self) -> UpstreamHealth;
+}
+
+#[async_trait]
+impl McpBackend for McpClient {
+    async fn initialize(&self) -> Result<()> {
+        McpClient::initialize(self).await
+    }
+
+    async fn list_tools(&self) -> Result<Vec<ToolDefinition>> {
+        McpClient::list_tools(self).await
+    }
+
+    async fn call_tool(&self, tool_name: &str, arguments: serde_json::Map<String, Value>) -> Result<Vec<ContentBlock>> {
+        McpClient::call_tool(self, tool_name, arguments).await
+    }
+
+    async fn call_tools(&self, calls: Vec<(String, serde_json::Map<String, Value>)>) -> Result<Vec<Result<Vec<ContentBlock>>>> {
+        McpClient::call_tools(self, calls).await
+    }
+
+    async fn tail_logs(&self, query: &LogTailQuery) -> Result<Vec<Value>> {
+        McpClient::tail_logs(self, query).await
+    }
+
+    async fn tool_stats(&self) -> Result<Vec<ToolUsage>> {
+        McpClient::tool_stats(self).await
+    }
+
+    async fn list_jobs(&self, query: &JobListQuery) -> Result<Vec<Value>> {
+        McpClient::list_jobs(self, query).await
+    }
+
+    async fn get_job(&self, id: &str) -> Result<Value> {
+        McpClient::get_job(self, id).await
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<PromptInfo>> {
+        McpClient::list_prompts(self).await
+    }
+
+    async fn get_prompt(&self, name: &str, arguments: HashMap<String, String>) -> Result<PromptResponse> {
+        McpClient::get_prompt(self, name, arguments).await
+    }
+
+    async fn list_resources(&self) -> Result<Vec<ResourceInfo>> {
+        McpClient::list_resources(self).await
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>> {
+        McpClient::read_resource(self, uri).await
+    }
+
+    async fn health(&self) -> UpstreamHealth {
+        McpClient::health(self).await
+    }
+}
+
+/// Serves `list_tools` from a cache that expires after `ttl`, since clients
+/// such as n8n poll the tool list far more often than it changes. Everything
+/// else goes straight to the wrapped backend.
+pub struct CachedBackend {
+    inner: Arc<dyn McpBackend>,
+    ttl: Duration,
+    /// Held while fetching, so concurrent requests on an expired cache wait
+    /// for one fetch instead of each making their own
+    tools: tokio::sync::Mutex<Option<(Instant, Vec<ToolDefinition>)>>,
+}
+
+impl CachedBackend {
+    pub fn new(inner: Arc<dyn McpBackend>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            tools: tokio::sync::Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl McpBackend for CachedBackend {
+    async fn initialize(&self) -> Result<()> {
+        self.inner.initialize().await
+    }
+
+    async fn list_tools(&self) -> Result<Vec<ToolDefinition>> {
+        let mut cached = self.tools.lock().await;
+        if let Some((fetched, tools)) = cached.as_ref() {
+            if fetched.elapsed() < self.ttl {
+                return Ok(tools.clone());
+            }
+        }
+        let tools = self.inner.list_tools().await?;
+        *cached = Some((Instant::now(), tools.clone()));
+        Ok(tools)
+    }
+
+    async fn call_tool(&self, tool_name: &str, arguments: serde_json::Map<String, Value>) -> Result<Vec<ContentBlock>> {
+        self.inner.call_tool(tool_name, arguments).await
+    }
+
+    async fn call_tools(&self, calls: Vec<(String, serde_json::Map<String, Value>)>) -> Result<Vec<Result<Vec<ContentBlock>>>> {
+        self.inner.call_tools(calls).await
+    }
+
+    async fn tail_logs(&self, query: &LogTailQuery) -> Result<Vec<Value>> {
+        self.inner.tail_logs(query).await
+    }
+
+    async fn tool_stats(&self) -> Result<Vec<ToolUsage>> {
+        self.inner.tool_stats().await
+    }
+
+    async fn list_jobs(&self, query: &JobListQuery) -> Result<Vec<Value>> {
+        self.inner.list_jobs(query).await
+    }
+
+    async fn get_job(&self, id: &str) -> Result<Value> {
+        self.inner.get_job(id).await
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<PromptInfo>> {
+        self.inner.list_prompts().await
+    }
+
+    async fn get_prompt(&self, name: &str, arguments: HashMap<String, String>) -> Result<PromptResponse> {
+        self.inner.get_prompt(name, arguments).await
+    }
+
+    async fn list_resources(&self) -> Result<Vec<ResourceInfo>> {
+        self.inner.list_resources().await
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>> {
+        self.inner.read_resource(uri).await
+    }
+
+    async fn invalidate_tools(&self) {
+        *self.tools.lock().await = None;
+        self.inner.invalidate_tools().await;
+    }
+
+    async fn health(&self) -> UpstreamHealth {
+        self.inner.health().await
+    }
+}
+
+/// A backend with canned tools, tool results, prompts, resources, log lines,
+/// tool stats and jobs that records the tool calls it receives
+#[derive(Default)]
+pub struct MockBackend {
+    tools: Vec<ToolDefinition>,
+    results: HashMap<String, Result<Vec<ContentBlock>, String>>,
+    logs: Vec<Value>,
+    stats: Vec<ToolUsage>,
+    jobs: Vec<Value>,
+    prompts: Vec<PromptInfo>,
+    /// Resources with their text
+    resources: Vec<(ResourceInfo, String)>,
+    /// When set, every call fails with this message
+    unavailable: Option<String>,
+    calls: Mutex<Vec<(String, serde_json::Map<String, Value>)>>,
+    tool_lists: Mutex<usize>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tool(mut self, name: &str, description: &str) -> Self {
+        self.tools.push(ToolDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            input_schema: serde_json::json!({ "type": "object", "properties": {} }),
+            output_schema: None,
+            tags: Vec::new(),
+            annotations: None,
+        });
+        self
+    }
+
+    pub fn with_tagged_tool(mut self, name: &str, tags: &[&str]) -> Self {
+        self.tools.push(ToolDefinition {
+            name: name.to_string(),
+            description: String::new(),
+            input_schema: serde_json::json!({ "type": "object", "properties": {} }),
+            output_schema: None,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            annotations: Some(serde_json::json!({ "readOnlyHint": tags.contains(&"read-only") })),
+        });
+        self
+    }
+
+    pub fn with_tool_schema(mut self, name: &str, input_schema: Value) -> Self {
+        self.tools.push(ToolDefinition {
+            name: name.to_string(),
+            description: String::new(),
+            input_schema,
+            output_schema: None,
+            tags: Vec::new(),
+            annotations: None,
+        });
+        self
+    }
+
+    /// Declares the shape of `tool_name`'s structured results; the tool
+    /// must have been added already
+    pub fn with_output_schema(mut self, tool_name: &str, output_schema: Value) -> Self {
+        if let Some(tool) = self.tools.iter_mut().find(|tool| tool.name == tool_name) {
+            tool.output_schema = Some(output_schema);
+        }
+        self
+    }
+
+    /// Calls to `tool_name` return `text`
+    pub fn with_result(mut self, tool_name: &str, text: &str) -> Self {
+        let content = vec![ContentBlock::Text { text: text.to_string() }];
+        self.results.insert(tool_name.to_string(), Ok(content));
+        self
+    }
+
+    /// Calls to `tool_name` fail with `message`
+    pub fn with_error(mut self, tool_name: &str, message: &str) -> Self {
+        self.results.insert(tool_name.to_string(), Err(message.to_string()));
+        self
+    }
+
+    pub fn with_logs(mut self, entries: Vec<Value>) -> Self {
+        self.logs = entries;
+        self
+    }
+
+    pub fn with_stats(mut self, tools: Vec<ToolUsage>) -> Self {
+        self.stats = tools;
+        self
+    }
+
+    /// A job as `jobs/get` returns it; it needs an `id`
+    pub fn with_job(mut self, job: Value) -> Self {
+        self.jobs.push(job);
+        self
+    }
+
+    /// A prompt without arguments; getting it returns `description` as a
+    /// user message followed by any arguments given
+    pub fn with_prompt(mut self, name: &str, description: &str) -> Self {
+        self.prompts.push(PromptInfo {
+            name: name.to_string(),
+            description: description.to_string(),
+            arguments: Vec::new(),
+        });
+        self
+    }
+
+    pub fn with_resource(mut self, uri: &str, name: &str, text: &str) -> Self {
+        let resource = ResourceInfo {
+            uri: uri.to_string(),
+            name: name.to_string(),
+            description: String::new(),
+            mime_type: Some("text/plain".to_string()),
+        };
+        self.resources.push((resource, text.to_string()));
+        self
+    }
+
+    /// Every call fails as if the server could not be reached
+    pub fn unavailable(mut self, message: &str) -> Self {
+        self.unavailable = Some(message.to_string());
+        self
+    }
+
+    /// How many times the tool list was asked for
+    pub fn tool_list_requests(&self) -> usize {
+        *self.tool_lists.lock().unwrap()
+    }
+
+    /// Tool calls received so far, oldest first
+    pub fn calls(&self) -> Vec<(String, serde_json::Map<String, Value>)> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn check_available(&self) -> Result<()> {
+        match &self.unavailable {
+            Some(message) => Err(anyhow!("{}", message)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl McpBackend for MockBackend {
+    async fn initialize(&self) -> Result<()> {
+        self.check_available()
+    }
+
+    async fn list_tools(&self) -> Result<Vec<ToolDefinition>> {
+        self.check_available()?;
+        *self.tool_lists.lock().unwrap() += 1;
+        Ok(self.tools.clone())
+    }
+
+    async fn call_tool(&self, tool_name: &str, arguments: serde_json::Map<String, Value>) -> Result<Vec<ContentBlock>> {
+        self.check_available()?;
+        self.calls.lock().unwrap().push((tool_name.to_string(), arguments));
+        match self.results.get(tool_name) {
+            Some(Ok(content)) => Ok(content.clone()),
+            Some(Err(message)) => Err(anyhow!("Tool call failed: {}", message)),
+            None => Err(JsonRpcError::not_found(format!("Tool not found: {}", tool_name)).into()),
+        }
+    }
+
+    async fn tail_logs(&self, _query: &LogTailQuery) -> Result<Vec<Value>> {
+        self.check_available()?;
+        Ok(self.logs.clone())
+    }
+
+    async fn tool_stats(&self) -> Result<Vec<ToolUsage>> {
+        self.check_available()?;
+        Ok(self.stats.clone())
+    }
+
+    async fn list_jobs(&self, query: &JobListQuery) -> Result<Vec<Value>> {
+        self.check_available()?;
+        let matches = |job: &&Value, field: &str, wanted: &Option<String>| {
+            wanted.as_ref().is_none_or(|wanted| job[field] == wanted.as_str())
+        };
+        Ok(self
+            .jobs
+            .iter()
+            .rev()
+            .filter(|job| matches(job, "status", &query.status) && matches(job, "tool", &query.tool))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_job(&self, id: &str) -> Result<Value> {
+        self.check_available()?;
+        let job = self.jobs.iter().find(|job| job["id"] == id).ok_or_else(|| JsonRpcError::not_found(format!("Unknown job {}", id)))?;
+        Ok(job.clone())
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<PromptInfo>> {
+        self.check_available()?;
+        Ok(self.prompts.clone())
+    }
+
+    async fn get_prompt(&self, name: &str, arguments: HashMap<String, String>) -> Result<PromptResponse> {
+        self.check_available()?;
+        let prompt = self.prompts.iter().find(|prompt| prompt.name == name).ok_or_else(|| JsonRpcError::not_found(format!("Unknown prompt: {}", name)))?;
+        let mut arguments: Vec<_> = arguments.into_iter().collect();
+        arguments.sort();
+        let text = arguments
+            .iter()
+            .fold(prompt.description.clone(), |text, (key, value)| format!("{}\n{}: {}", text, key, value));
+        Ok(PromptResponse {
+            description: prompt.description.clone(),
+            messages: vec![PromptMessage { role: "user".to_string(), content: ContentBlock::Text { text } }],
+        })
+    }
+
+    async fn list_resources(&self) -> Result<Vec<ResourceInfo>> {
+        self.check_available()?;
+        Ok(self.resources.iter().map(|(resource, _)| resource.clone()).collect())
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>> {
+        self.check_available()?;
+        let (resource, text) = self.resources.iter().find(|(resource, _)| resource.uri == uri).ok_or_else(|| {
+            JsonRpcError::from_kind(mcp_core::ErrorKind::ResourceNotFound, Some(json!({ "uri": uri })))
+        })?;
+        Ok(vec![ResourceContents {
+            uri: resource.uri.clone(),
+            mime_type: resource.mime_type.clone(),
+            text: text.clone(),
+        }])
+    }
+
+    async fn health(&self) -> UpstreamHealth {
+        UpstreamHealth {
+            reachable: self.unavailable.is_none(),
+            version: self.unavailable.is_none().then(|| "mock".to_string()),
+            error: self.unavailable.clone(),
+            last_tools_list_at: None,
+            circuit_breaker: BreakerStatus { state: BreakerState::Closed, consecutive_failures: 0 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tool_list_is_cached_until_expired_or_invalidated() {
+        let mock = Arc::new(MockBackend::new().with_tool("echo", "Echo"));
+        let cached = CachedBackend::new(mock.clone(), Duration::from_millis(50));
+
+        cached.list_tools().await.unwrap();
+        cached.list_tools().await.unwrap();
+        assert_eq!(mock.tool_list_requests(), 1);
+
+        cached.invalidate_tools().await;
+        cached.list_tools().await.unwrap();
+        assert_eq!(mock.tool_list_requests(), 2);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(cached.list_tools().await.unwrap()[0].name, "echo");
+        assert_eq!(mock.tool_list_requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_fetches_are_not_cached() {
+        let cached = CachedBackend::new(Arc::new(MockBackend::new().unavailable("down")), Duration::from_secs(60));
+        assert!(cached.list_tools().await.is_err());
+        assert!(cached.list_tools().await.is_err());
+    }
+}
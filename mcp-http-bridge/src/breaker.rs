@@ -0,0 +1,134 @@
+//! Circuit breaker for the MCP server. After `threshold` requests in a row
+//! fail to get an answer, further requests fail at once for `cooldown`
+//! rather than each waiting on a server that is down. Then requests are let
+//! through again ("half open"): the first answer closes the breaker, another
+//! failure opens it for a new cooldown.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// Failures in a row that open the breaker
+pub const DEFAULT_THRESHOLD: u32 = 5;
+
+/// How long an open breaker refuses requests
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Requests go to the server
+    Closed,
+    /// Requests fail without being sent
+    Open,
+    /// The cooldown is over; the next answer closes the breaker
+    HalfOpen,
+}
+
+/// The breaker as `/health` reports it
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    /// Requests in a row that got no answer
+    pub consecutive_failures: u32,
+}
+
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<Failures>,
+}
+
+#[derive(Default)]
+struct Failures {
+    in_a_row: u32,
+    opened_at: Option<Instant>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(DEFAULT_THRESHOLD, DEFAULT_COOLDOWN)
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            inner: Mutex::new(Failures::default()),
+        }
+    }
+
+    fn state_of(&self, failures: &Failures) -> BreakerState {
+        match failures.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Fails while the breaker is open
+    pub fn check(&self) -> Result<()> {
+        let failures = self.inner.lock().unwrap();
+        match (self.state_of(&failures), failures.opened_at) {
+            (BreakerState::Open, Some(opened_at)) => Err(anyhow!(
+                "MCP server did not answer {} requests in a row; not trying again for {} s",
+                failures.in_a_row,
+                self.cooldown.saturating_sub(opened_at.elapsed()).as_secs().max(1)
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.inner.lock().unwrap() = Failures::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut failures = self.inner.lock().unwrap();
+        failures.in_a_row += 1;
+        if failures.in_a_row >= self.threshold {
+            failures.opened_at = Some(Instant::now());
+        }
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let failures = self.inner.lock().unwrap();
+        BreakerStatus {
+            state: self.state_of(&failures),
+            consecutive_failures: failures.in_a_row,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_after_failures_in_a_row_and_closes_on_success() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(30));
+        breaker.record_failure();
+        assert!(breaker.check().is_ok());
+        assert_eq!(breaker.status().state, BreakerState::Closed);
+
+        breaker.record_failure();
+        assert!(breaker.check().is_err());
+        assert_eq!(breaker.status().state, BreakerState::Open);
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(breaker.check().is_ok());
+        assert_eq!(breaker.status().state, BreakerState::HalfOpen);
+        // Failing again while half open opens it for another cooldown
+        breaker.record_failure();
+        assert_eq!(breaker.status().state, BreakerState::Open);
+
+        std::thread::sleep(Duration::from_millis(40));
+        breaker.record_success();
+        assert_eq!(breaker.status().state, BreakerState::Closed);
+        assert_eq!(breaker.status().consecutive_failures, 0);
+    }
+}
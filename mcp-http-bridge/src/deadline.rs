@@ -0,0 +1,120 @@
+//! Per-route timeouts. Every request gets a deadline from its route's
+//! timeout; a request still running then is answered `504 Gateway Timeout`.
+//! The time left is sent along with tool calls as `_meta.timeoutMs`, so the
+//! MCP server stops plugin work the caller has given up on instead of
+//! spending upstream quota on it.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::{request_id, ToolCallResponse};
+
+/// The timeout of routes without one of their own
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long each route may take, by its pattern as in the router, e.g.
+/// `/tools/call` or `/tools/:name/invoke`
+#[derive(Debug, Clone)]
+pub struct RouteTimeouts {
+    pub default: Duration,
+    pub routes: HashMap<String, Duration>,
+}
+
+impl Default for RouteTimeouts {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMEOUT)
+    }
+}
+
+impl RouteTimeouts {
+    pub fn new(default: Duration) -> Self {
+        Self { default, routes: HashMap::new() }
+    }
+
+    pub fn with_route(mut self, route: impl Into<String>, timeout: Duration) -> Self {
+        self.routes.insert(route.into(), timeout);
+        self
+    }
+
+    pub fn for_route(&self, route: &str) -> Duration {
+        self.routes.get(route).copied().unwrap_or(self.default)
+    }
+}
+
+/// Parses `ROUTE=SECONDS`, e.g. `/tools/call=120`
+pub fn parse_route_timeout(value: &str) -> Result<(String, Duration), String> {
+    let (route, seconds) = value.rsplit_once('=').ok_or_else(|| format!("Expected ROUTE=SECONDS, got '{}'", value))?;
+    if !route.starts_with('/') {
+        return Err(format!("Route '{}' does not start with /", route));
+    }
+    let seconds: f64 = seconds.parse().map_err(|_| format!("Invalid number of seconds '{}'", seconds))?;
+    let timeout = Duration::try_from_secs_f64(seconds).map_err(|_| format!("Invalid number of seconds '{}'", seconds))?;
+    Ok((route.to_string(), timeout))
+}
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// Time left before the request being handled times out, outside of a
+/// request `None`
+pub fn remaining() -> Option<Duration> {
+    DEADLINE.try_with(|deadline| deadline.saturating_duration_since(Instant::now())).ok()
+}
+
+/// Runs the request with its route's deadline set, answering 504 when it
+/// passes. Dropping the handler drops its upstream request too.
+pub async fn enforce(State(timeouts): State<Arc<RouteTimeouts>>, request: Request, next: Next) -> Response {
+    let route = match request.extensions().get::<MatchedPath>() {
+        Some(matched) => matched.as_str().to_string(),
+        None => request.uri().path().to_string(),
+    };
+    let timeout = timeouts.for_route(&route);
+    let deadline = Instant::now() + timeout;
+
+    match DEADLINE.scope(deadline, tokio::time::timeout_at(deadline, next.run(request))).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!("{} did not finish within {} ms", route, timeout.as_millis());
+            (StatusCode::GATEWAY_TIMEOUT, Json(ToolCallResponse {
+                success: false,
+                content: None,
+                structured_content: None,
+                error: Some(format!("No answer within {} ms", timeout.as_millis())),
+                request_id: request_id::current(),
+            }))
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_route_timeout() {
+        assert_eq!(parse_route_timeout("/tools/call=120"), Ok(("/tools/call".to_string(), Duration::from_secs(120))));
+        assert_eq!(parse_route_timeout("/tools/:name/invoke=0.5"), Ok(("/tools/:name/invoke".to_string(), Duration::from_millis(500))));
+        assert!(parse_route_timeout("/tools/call").is_err());
+        assert!(parse_route_timeout("tools/call=1").is_err());
+        assert!(parse_route_timeout("/tools/call=-1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_remaining_is_set_only_inside_a_request() {
+        assert_eq!(remaining(), None);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let inside = DEADLINE.scope(deadline, async { remaining() }).await.unwrap();
+        assert!(inside > Duration::from_secs(9) && inside <= Duration::from_secs(10));
+    }
+}
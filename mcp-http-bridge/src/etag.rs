@@ -0,0 +1,64 @@
+//! ETag / If-None-Match for responses that UI clients fetch over and over
+//! (`/tools`, `/openapi.json`), so an unchanged body costs a 304 instead of
+//! the whole payload.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Tags successful responses with a weak ETag of their body and answers 304
+/// when the client already holds that version. Weak, because compression is
+/// applied outside this layer and changes the bytes on the wire.
+pub async fn etag(request: Request, next: Next) -> Response {
+    let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    let tag = format!("W/\"{:016x}\"", hasher.finish());
+    let tag_value = HeaderValue::from_str(&tag).unwrap();
+
+    if if_none_match.is_some_and(|value| matches(&value, &tag)) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, tag_value)]).into_response();
+    }
+    parts.headers.insert(header::ETAG, tag_value);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Whether an If-None-Match header names `tag`, compared weakly
+fn matches(if_none_match: &HeaderValue, tag: &str) -> bool {
+    let Ok(value) = if_none_match.to_str() else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    value.trim() == "*" || value.split(',').any(|candidate| opaque(candidate) == opaque(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_if_none_match_compares_weakly() {
+        let tag = r#"W/"00ff""#;
+        assert!(matches(&HeaderValue::from_static(r#"W/"00ff""#), tag));
+        assert!(matches(&HeaderValue::from_static(r#""00ff""#), tag));
+        assert!(matches(&HeaderValue::from_static(r#""aaaa", W/"00ff""#), tag));
+        assert!(matches(&HeaderValue::from_static("*"), tag));
+        assert!(!matches(&HeaderValue::from_static(r#"W/"0100""#), tag));
+    }
+}
@@ -0,0 +1,451 @@
+//! Hooks that rewrite tool calls passing through the bridge, e.g. to fill in
+//! default arguments or strip secrets from results, without changing the MCP
+//! server. `HookedBackend` runs them around every call, whichever endpoint it
+//! comes from, and over the errors, jobs, resources and prompts the server
+//! sends back; which hooks run is read from JSON:
+//!
+//! ```json
+//! {"hooks": [
+//!   {"type": "default_arguments", "tools": {"http_request": {"timeout": 10}}},
+//!   {"type": "redact_secrets", "keys": ["password", "token"], "env": ["HA_TOKEN"]}
+//! ]}
+//! ```
+
+use anyhow::{anyhow, Context as _, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::backend::McpBackend;
+use crate::mcp_client::{JsonRpcError, ToolDefinition};
+use crate::{ContentBlock, JobListQuery, LogTailQuery, PromptInfo, PromptResponse, ResourceContents, ResourceInfo, ToolUsage, UpstreamHealth};
+
+/// Rewrites tool calls. Every method defaults to leaving things as they are.
+pub trait ToolHook: Send + Sync {
+    /// Changes the arguments of a call to `tool` before it reaches the server
+    fn before_call(&self, _tool: &str, _arguments: &mut Map<String, Value>) {}
+
+    /// Changes what a call to `tool` returned before the caller sees it
+    fn after_call(&self, _tool: &str, _content: &mut Vec<ContentBlock>) {}
+
+    /// Changes anything else the server sent back before the caller sees it:
+    /// a job, an error, or the text of a resource or prompt. Text holding a
+    /// JSON object or array is passed as that JSON, other text as a string.
+    fn after_response(&self, _value: &mut Value) {}
+
+    /// Changes a tool as listed, e.g. to mark arguments the hook fills in as
+    /// optional, so clients and the bridge's validation agree with the hook
+    fn list_tool(&self, _tool: &mut ToolDefinition) {}
+}
+
+/// Hook selection, as read from `--hooks`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HooksConfig {
+    #[serde(default)]
+    pub hooks: Vec<HookSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HookSpec {
+    /// See `DefaultArguments`
+    DefaultArguments { tools: HashMap<String, Map<String, Value>> },
+    /// See `RedactSecrets`
+    RedactSecrets {
+        #[serde(default)]
+        keys: Vec<String>,
+        #[serde(default)]
+        env: Vec<String>,
+    },
+}
+
+impl HooksConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read hook config {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid hook config {}", path.display()))
+    }
+
+    /// The hooks, in the order they run
+    pub fn build(&self) -> Result<Vec<Arc<dyn ToolHook>>> {
+        self.hooks
+            .iter()
+            .map(|spec| -> Result<Arc<dyn ToolHook>> {
+                Ok(match spec {
+                    HookSpec::DefaultArguments { tools } => Arc::new(DefaultArguments { tools: tools.clone() }),
+                    HookSpec::RedactSecrets { keys, env } => Arc::new(RedactSecrets::new(keys, env)?),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Fills in arguments a call leaves out, by tool name; `*` applies to every
+/// tool, and a tool's own defaults win over it
+pub struct DefaultArguments {
+    pub tools: HashMap<String, Map<String, Value>>,
+}
+
+impl DefaultArguments {
+    fn defaults_for<'a>(&'a self, tool: &str) -> impl Iterator<Item = (&'a String, &'a Value)> {
+        self.tools.get(tool).into_iter().chain(self.tools.get("*")).flatten()
+    }
+}
+
+impl ToolHook for DefaultArguments {
+    fn before_call(&self, tool: &str, arguments: &mut Map<String, Value>) {
+        for (name, value) in self.defaults_for(tool) {
+            arguments.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    fn list_tool(&self, tool: &mut ToolDefinition) {
+        let defaulted: Vec<String> = self.defaults_for(&tool.name).map(|(name, _)| name.clone()).collect();
+        if let Some(required) = tool.input_schema.get_mut("required").and_then(Value::as_array_mut) {
+            required.retain(|name| !name.as_str().is_some_and(|name| defaulted.iter().any(|defaulted| defaulted == name)));
+        }
+    }
+}
+
+/// What secrets are replaced with
+pub const REDACTED: &str = "[redacted]";
+
+/// Strips secrets from results: the values of JSON fields whose name
+/// contains one of `keys` (ignoring case), in `json` blocks and in text
+/// blocks holding JSON, and the values of the `env` variables wherever they
+/// appear in text
+pub struct RedactSecrets {
+    keys: Vec<String>,
+    values: Vec<String>,
+}
+
+impl RedactSecrets {
+    /// Fails when one of the `env` variables is not set, rather than silently
+    /// letting its value through
+    pub fn new(keys: &[String], env: &[String]) -> Result<Self> {
+        let values = env
+            .iter()
+            .map(|name| std::env::var(name).map_err(|_| anyhow!("redact_secrets: environment variable {} is not set", name)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            keys: keys.iter().map(|key| key.to_lowercase()).collect(),
+            values: values.into_iter().filter(|value| !value.is_empty()).collect(),
+        })
+    }
+
+    fn is_secret_key(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        self.keys.iter().any(|secret| key.contains(secret.as_str()))
+    }
+
+    fn redact_text(&self, text: &str) -> String {
+        self.values.iter().fold(text.to_string(), |text, value| text.replace(value.as_str(), REDACTED))
+    }
+
+    fn redact_json(&self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    if self.is_secret_key(key) {
+                        *field = Value::String(REDACTED.to_string());
+                    } else {
+                        self.redact_json(field);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_json(item)),
+            Value::String(text) => *text = self.redact_text(text),
+            _ => {}
+        }
+    }
+}
+
+impl ToolHook for RedactSecrets {
+    fn after_call(&self, _tool: &str, content: &mut Vec<ContentBlock>) {
+        for block in content.iter_mut() {
+            rewrite_block(block, &|value| self.redact_json(value));
+        }
+    }
+
+    fn after_response(&self, value: &mut Value) {
+        self.redact_json(value);
+    }
+}
+
+/// Runs `rewrite` over the JSON in `block`, or over its text as a string;
+/// binary data is left alone
+fn rewrite_block(block: &mut ContentBlock, rewrite: &dyn Fn(&mut Value)) {
+    match block {
+        ContentBlock::Json { json } => rewrite(json),
+        ContentBlock::Text { text } => rewrite_text(text, rewrite),
+        ContentBlock::Data { mime_type, data } if mime_type.starts_with("text/") => rewrite_text(data, rewrite),
+        ContentBlock::Data { .. } => {}
+    }
+}
+
+/// Runs `rewrite` over the JSON object or array `text` holds, keeping it
+/// pretty-printed if it was, or else over `text` as a JSON string
+fn rewrite_text(text: &mut String, rewrite: &dyn Fn(&mut Value)) {
+    // Tools usually send their structured result as text too
+    let mut value = match serde_json::from_str::<Value>(text) {
+        Ok(json) if json.is_object() || json.is_array() => json,
+        _ => Value::String(std::mem::take(text)),
+    };
+    rewrite(&mut value);
+    *text = match value {
+        Value::String(rewritten) => rewritten,
+        json if text.contains('\n') => serde_json::to_string_pretty(&json).unwrap_or_default(),
+        json => json.to_string(),
+    };
+}
+
+/// Runs `hooks` around the tool calls of the wrapped backend, over the tools
+/// it lists, and over the errors, jobs, resources and prompts it returns.
+/// Logs and usage statistics go straight to the wrapped backend.
+pub struct HookedBackend {
+    inner: Arc<dyn McpBackend>,
+    hooks: Vec<Arc<dyn ToolHook>>,
+}
+
+impl HookedBackend {
+    pub fn new(inner: Arc<dyn McpBackend>, hooks: Vec<Arc<dyn ToolHook>>) -> Self {
+        Self { inner, hooks }
+    }
+
+    fn before_call(&self, tool: &str, arguments: &mut Map<String, Value>) {
+        self.hooks.iter().for_each(|hook| hook.before_call(tool, arguments));
+    }
+
+    fn after_call(&self, tool: &str, result: Result<Vec<ContentBlock>>) -> Result<Vec<ContentBlock>> {
+        let mut content = self.after_result(result)?;
+        self.hooks.iter().for_each(|hook| hook.after_call(tool, &mut content));
+        Ok(content)
+    }
+
+    fn after_response(&self, value: &mut Value) {
+        self.hooks.iter().for_each(|hook| hook.after_response(value));
+    }
+
+    /// Runs the hooks over an error, keeping a JSON-RPC error one so its
+    /// status is still reported
+    fn after_error(&self, error: anyhow::Error) -> anyhow::Error {
+        match error.downcast::<JsonRpcError>() {
+            Ok(mut rpc) => {
+                rewrite_text(&mut rpc.message, &|value| self.after_response(value));
+                if let Some(data) = rpc.data.as_mut() {
+                    self.after_response(data);
+                }
+                rpc.into()
+            }
+            Err(error) => {
+                let mut message = format!("{:#}", error);
+                rewrite_text(&mut message, &|value| self.after_response(value));
+                anyhow!(message)
+            }
+        }
+    }
+
+    fn after_result<T>(&self, result: Result<T>) -> Result<T> {
+        result.map_err(|error| self.after_error(error))
+    }
+}
+
+#[async_trait]
+impl McpBackend for HookedBackend {
+    async fn initialize(&self) -> Result<()> {
+        self.inner.initialize().await
+    }
+
+    async fn list_tools(&self) -> Result<Vec<ToolDefinition>> {
+        let mut tools = self.inner.list_tools().await?;
+        for tool in tools.iter_mut() {
+            self.hooks.iter().for_each(|hook| hook.list_tool(tool));
+        }
+        Ok(tools)
+    }
+
+    async fn call_tool(&self, tool_name: &str, mut arguments: Map<String, Value>) -> Result<Vec<ContentBlock>> {
+        self.before_call(tool_name, &mut arguments);
+        let result = self.inner.call_tool(tool_name, arguments).await;
+        self.after_call(tool_name, result)
+    }
+
+    async fn call_tools(&self, mut calls: Vec<(String, Map<String, Value>)>) -> Result<Vec<Result<Vec<ContentBlock>>>> {
+        for (tool_name, arguments) in calls.iter_mut() {
+            self.before_call(tool_name, arguments);
+        }
+        let names: Vec<String> = calls.iter().map(|(tool_name, _)| tool_name.clone()).collect();
+        let results = self.inner.call_tools(calls).await?;
+        Ok(names.iter().zip(results).map(|(tool_name, result)| self.after_call(tool_name, result)).collect())
+    }
+
+    async fn tail_logs(&self, query: &LogTailQuery) -> Result<Vec<Value>> {
+        self.inner.tail_logs(query).await
+    }
+
+    async fn tool_stats(&self) -> Result<Vec<ToolUsage>> {
+        self.inner.tool_stats().await
+    }
+
+    async fn list_jobs(&self, query: &JobListQuery) -> Result<Vec<Value>> {
+        let mut jobs = self.after_result(self.inner.list_jobs(query).await)?;
+        jobs.iter_mut().for_each(|job| self.after_response(job));
+        Ok(jobs)
+    }
+
+    async fn get_job(&self, id: &str) -> Result<Value> {
+        let mut job = self.after_result(self.inner.get_job(id).await)?;
+        self.after_response(&mut job);
+        Ok(job)
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<PromptInfo>> {
+        self.inner.list_prompts().await
+    }
+
+    async fn get_prompt(&self, name: &str, arguments: HashMap<String, String>) -> Result<PromptResponse> {
+        let mut prompt = self.after_result(self.inner.get_prompt(name, arguments).await)?;
+        rewrite_text(&mut prompt.description, &|value| self.after_response(value));
+        for message in prompt.messages.iter_mut() {
+            rewrite_block(&mut message.content, &|value| self.after_response(value));
+        }
+        Ok(prompt)
+    }
+
+    async fn list_resources(&self) -> Result<Vec<ResourceInfo>> {
+        self.inner.list_resources().await
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>> {
+        let mut contents = self.after_result(self.inner.read_resource(uri).await)?;
+        for content in contents.iter_mut() {
+            rewrite_text(&mut content.text, &|value| self.after_response(value));
+        }
+        Ok(contents)
+    }
+
+    async fn invalidate_tools(&self) {
+        self.inner.invalidate_tools().await;
+    }
+
+    async fn health(&self) -> UpstreamHealth {
+        self.inner.health().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockBackend;
+    use serde_json::json;
+
+    fn hooks(config: Value) -> Vec<Arc<dyn ToolHook>> {
+        serde_json::from_value::<HooksConfig>(config).unwrap().build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_default_arguments_fill_in_what_calls_leave_out() {
+        let mock = Arc::new(
+            MockBackend::new()
+                .with_tool_schema("fetch", json!({"type": "object", "required": ["url", "timeout"]}))
+                .with_result("fetch", "ok"),
+        );
+        let backend = HookedBackend::new(mock.clone(), hooks(json!({"hooks": [
+            {"type": "default_arguments", "tools": {"fetch": {"timeout": 10}, "*": {"timeout": 99, "trace": true}}}
+        ]})));
+
+        let mut arguments = Map::new();
+        arguments.insert("url".to_string(), json!("http://example.com"));
+        backend.call_tool("fetch", arguments).await.unwrap();
+        let mut arguments = Map::new();
+        arguments.insert("timeout".to_string(), json!(1));
+        backend.call_tool("fetch", arguments).await.unwrap();
+
+        let calls = mock.calls();
+        assert_eq!(Value::Object(calls[0].1.clone()), json!({"url": "http://example.com", "timeout": 10, "trace": true}));
+        assert_eq!(calls[1].1["timeout"], 1);
+        // Listed as optional, since the bridge fills it in
+        assert_eq!(backend.list_tools().await.unwrap()[0].input_schema["required"], json!(["url"]));
+    }
+
+    #[tokio::test]
+    async fn test_redact_secrets_strips_secret_fields_and_values() {
+        std::env::set_var("HOOKS_TEST_SECRET", "s3cr3t");
+        let text = json!({"user": "ada", "apiToken": "abc", "nested": [{"Password": "pw", "note": "key s3cr3t"}]}).to_string();
+        let mock = Arc::new(MockBackend::new().with_tool("login", "").with_result("login", &text).with_result("echo", "using s3cr3t"));
+        let backend = HookedBackend::new(mock, hooks(json!({"hooks": [
+            {"type": "redact_secrets", "keys": ["token", "password"], "env": ["HOOKS_TEST_SECRET"]}
+        ]})));
+
+        let content = backend.call_tool("login", Map::new()).await.unwrap();
+        let ContentBlock::Text { text } = &content[0] else { panic!("{:?}", content) };
+        let redacted: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(redacted, json!({"user": "ada", "apiToken": REDACTED, "nested": [{"Password": REDACTED, "note": "key [redacted]"}]}));
+
+        let content = backend.call_tool("echo", Map::new()).await.unwrap();
+        assert_eq!(ContentBlock::texts(&content), vec!["using [redacted]"]);
+    }
+
+    fn redacting(mock: MockBackend) -> HookedBackend {
+        std::env::set_var("HOOKS_TEST_SECRET", "s3cr3t");
+        HookedBackend::new(Arc::new(mock), hooks(json!({"hooks": [
+            {"type": "redact_secrets", "keys": ["token"], "env": ["HOOKS_TEST_SECRET"]}
+        ]})))
+    }
+
+    #[tokio::test]
+    async fn test_redact_secrets_strips_errors() {
+        let backend = redacting(MockBackend::new().with_error("login", "bad password s3cr3t"));
+        let error = backend.call_tool("login", Map::new()).await.unwrap_err();
+        assert_eq!(error.to_string(), "Tool call failed: bad password [redacted]");
+
+        // JSON-RPC errors stay JSON-RPC errors, so their status is kept
+        let error = backend.call_tool("s3cr3t", Map::new()).await.unwrap_err();
+        let rpc = error.downcast_ref::<JsonRpcError>().unwrap();
+        assert!(!rpc.to_string().contains("s3cr3t"), "{}", rpc);
+        assert_eq!(rpc.http_status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_redact_secrets_strips_jobs() {
+        let job = json!({"id": "job-1", "tool": "login", "result": {"apiToken": "abc", "note": "s3cr3t"}});
+        let backend = redacting(MockBackend::new().with_job(job));
+        let job = backend.get_job("job-1").await.unwrap();
+        assert_eq!(job["result"], json!({"apiToken": REDACTED, "note": REDACTED}));
+        let jobs = backend.list_jobs(&JobListQuery::default()).await.unwrap();
+        assert_eq!(jobs[0]["result"]["apiToken"], REDACTED);
+    }
+
+    #[tokio::test]
+    async fn test_redact_secrets_strips_resources() {
+        let backend = redacting(
+            MockBackend::new()
+                .with_resource("config://ha", "ha", r#"{"url": "http://ha", "token": "abc"}"#)
+                .with_resource("notes://today", "notes", "door code s3cr3t"),
+        );
+        let contents = backend.read_resource("config://ha").await.unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&contents[0].text).unwrap(), json!({"url": "http://ha", "token": REDACTED}));
+        let contents = backend.read_resource("notes://today").await.unwrap();
+        assert_eq!(contents[0].text, "door code [redacted]");
+    }
+
+    #[tokio::test]
+    async fn test_redact_secrets_strips_prompts() {
+        let backend = redacting(MockBackend::new().with_prompt("login", "Log in with s3cr3t"));
+        let arguments = HashMap::from([("user".to_string(), "ada".to_string())]);
+        let prompt = backend.get_prompt("login", arguments).await.unwrap();
+        assert_eq!(prompt.description, "Log in with [redacted]");
+        assert_eq!(ContentBlock::texts(&[prompt.messages[0].content.clone()]), vec!["Log in with [redacted]\nuser: ada"]);
+    }
+
+    #[test]
+    fn test_unset_secret_variables_and_unknown_hooks_are_rejected() {
+        let config: HooksConfig =
+            serde_json::from_value(json!({"hooks": [{"type": "redact_secrets", "env": ["HOOKS_TEST_UNSET"]}]})).unwrap();
+        assert!(config.build().is_err());
+        assert!(serde_json::from_value::<HooksConfig>(json!({"hooks": [{"type": "nope"}]})).is_err());
+    }
+}
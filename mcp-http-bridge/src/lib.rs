@@ -1,20 +1,42 @@
+pub mod anthropic;
+pub mod backend;
+pub mod breaker;
+pub mod deadline;
+pub mod etag;
+pub mod hooks;
+pub mod manifest;
 pub mod mcp_client;
+pub mod negotiate;
+pub mod openai;
 pub mod openapi;
+pub mod request_id;
+#[cfg(feature = "ui")]
+pub mod ui;
+pub mod validation;
+pub mod webhooks;
 
-pub use mcp_client::McpClient;
+pub use backend::{CachedBackend, McpBackend, MockBackend};
+pub use deadline::RouteTimeouts;
+pub use hooks::{HookedBackend, HooksConfig, ToolHook};
+pub use mcp_client::{JsonRpcError, McpClient};
+pub use negotiate::ResponseFormat;
+pub use validation::Violation;
+pub use webhooks::WebhookConfig;
 
 use anyhow::Result;
 use axum::{
-    extract::State,
-    http::{HeaderValue, Method, StatusCode},
-    response::Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Json, Response},
+    middleware,
     routing::{get, post},
     Router,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tower_http::cors::CorsLayer;
+use tower_http::{compression::CompressionLayer, cors::CorsLayer};
 use tracing::{error, info};
 use utoipa::ToSchema;
 
@@ -22,7 +44,40 @@ use openapi::openapi_handler;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub mcp_client: Arc<McpClient>,
+    pub mcp_client: Arc<dyn McpBackend>,
+    /// Argument mappings for `POST /webhooks/{name}`
+    pub webhooks: Arc<WebhookConfig>,
+    /// URL clients reach the bridge on, for links in `/manifest`; taken from
+    /// the request's Host header when unset
+    pub public_url: Option<String>,
+    /// How long each route may take before it is answered 504
+    pub timeouts: Arc<RouteTimeouts>,
+}
+
+impl AppState {
+    pub fn new(mcp_client: Arc<dyn McpBackend>) -> Self {
+        Self {
+            mcp_client,
+            webhooks: Arc::new(WebhookConfig::default()),
+            public_url: None,
+            timeouts: Arc::new(RouteTimeouts::default()),
+        }
+    }
+
+    pub fn with_timeouts(mut self, timeouts: RouteTimeouts) -> Self {
+        self.timeouts = Arc::new(timeouts);
+        self
+    }
+
+    pub fn with_public_url(mut self, public_url: impl Into<String>) -> Self {
+        self.public_url = Some(public_url.into());
+        self
+    }
+
+    pub fn with_webhooks(mut self, webhooks: WebhookConfig) -> Self {
+        self.webhooks = Arc::new(webhooks);
+        self
+    }
 }
 
 // API Types
@@ -42,8 +97,28 @@ pub struct ToolCallResponse {
     pub success: bool,
     /// Content returned by the tool (if successful)
     pub content: Option<Vec<ContentBlock>>,
+    /// The tool's output as a JSON object, when it has one; also in `content`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub structured_content: Option<Value>,
     /// Error message (if unsuccessful)
     pub error: Option<String>,
+    /// The request's `X-Request-Id`, to find its log lines by
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Arguments rejected before reaching the tool
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidationErrorResponse {
+    /// Always false
+    pub success: bool,
+    pub error: String,
+    /// Each way the arguments break the tool's input schema
+    pub violations: Vec<Violation>,
+    /// The request's `X-Request-Id`, to find its log lines by
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 /// List of available tools
@@ -60,12 +135,29 @@ pub struct ToolInfo {
     pub name: String,
     /// Tool description
     pub description: String,
-    /// JSON schema for tool input
+    /// JSON schema for tool input, spelled `inputSchema` as in MCP
+    #[serde(rename = "inputSchema")]
     pub input_schema: Value,
+    /// JSON schema of the tool's structured output, if it declares one
+    #[serde(rename = "outputSchema", skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    /// What to expect from the tool: `read-only`, `destructive`, `network`, `slow`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// MCP tool annotations, e.g. `{"readOnlyHint": true}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Value>,
+}
+
+/// Filter for `GET /tools`
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct ToolListQuery {
+    /// Comma-separated tags a tool must all have, e.g. `read-only,network`
+    pub tag: Option<String>,
 }
 
 /// Content block returned by tools
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "type")]
 pub enum ContentBlock {
     /// Text content
@@ -74,15 +166,243 @@ pub enum ContentBlock {
         /// The text content
         text: String 
     },
+    /// Structured output, usually alongside a text block with the same data
+    #[serde(rename = "json")]
+    Json {
+        /// The structured content
+        #[schema(value_type = Object)]
+        json: Value,
+    },
+    /// Output in another format
+    #[serde(rename = "data")]
+    Data {
+        /// MIME type of `data`
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+        /// Base64 unless the MIME type is textual
+        data: String,
+    },
+}
+
+impl ContentBlock {
+    /// A tool result as text, for consumers that only read text. Structured
+    /// blocks repeat the text sent alongside them, so they are only rendered
+    /// when the result has no text blocks.
+    pub fn texts(content: &[ContentBlock]) -> Vec<String> {
+        let texts: Vec<String> = content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.clone()),
+                _ => None,
+            })
+            .collect();
+        if !texts.is_empty() {
+            return texts;
+        }
+        content
+            .iter()
+            .map(|block| match block {
+                ContentBlock::Text { text } => text.clone(),
+                ContentBlock::Json { json } => serde_json::to_string_pretty(json).unwrap_or_default(),
+                ContentBlock::Data { mime_type, data } if mime_type.starts_with("text/") => data.clone(),
+                ContentBlock::Data { mime_type, .. } => format!("[{} content]", mime_type),
+            })
+            .collect()
+    }
+
+    /// The first `json` block holding an object, which MCP servers send as a
+    /// result's structured content
+    pub fn structured(content: &[ContentBlock]) -> Option<&Value> {
+        content.iter().find_map(|block| match block {
+            ContentBlock::Json { json } if json.is_object() => Some(json),
+            _ => None,
+        })
+    }
+}
+
+/// A prompt template offered by the MCP server
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PromptInfo {
+    /// Prompt name
+    pub name: String,
+    /// What the prompt is for
+    #[serde(default)]
+    pub description: String,
+    /// Arguments the prompt accepts
+    #[serde(default)]
+    pub arguments: Vec<PromptArgumentInfo>,
+}
+
+/// An argument a prompt accepts
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PromptArgumentInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Whether the prompt must be given this argument
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// List of available prompts
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PromptListResponse {
+    pub prompts: Vec<PromptInfo>,
+}
+
+/// One message of a rendered prompt
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PromptMessage {
+    /// `user` or `assistant`
+    pub role: String,
+    pub content: ContentBlock,
+}
+
+/// A prompt rendered with its arguments
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PromptResponse {
+    #[serde(default)]
+    pub description: String,
+    pub messages: Vec<PromptMessage>,
+}
+
+/// A resource the MCP server can read
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct ResourceInfo {
+    /// URI to pass to `GET /resources/read`
+    pub uri: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+}
+
+/// List of available resources
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ResourceListResponse {
+    pub resources: Vec<ResourceInfo>,
+}
+
+/// Contents of a resource
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    pub text: String,
+}
+
+/// Query for `GET /resources/read`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResourceReadQuery {
+    /// URI of the resource, as listed by `GET /resources`
+    pub uri: String,
+}
+
+/// Result of reading a resource
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ResourceReadResponse {
+    pub contents: Vec<ResourceContents>,
+}
+
+/// Filters for `GET /logs`; all optional
+#[derive(Debug, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct LogTailQuery {
+    /// Maximum number of lines (server default: 100)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Only lines logged for this MCP session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Only lines logged while handling this JSON-RPC request id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Only lines logged by this tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Least severe level to include, e.g. `warning`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+}
+
+/// Recent server log lines
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LogTailResponse {
+    /// Log lines, oldest first
+    pub entries: Vec<Value>,
+}
+
+/// Filters for `GET /jobs`; all optional
+#[derive(Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct JobListQuery {
+    /// Only jobs in this state: `queued`, `running`, `completed` or `failed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Only calls of this tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Maximum number of jobs (server default: 50)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+/// Background tool calls submitted with `jobs/submit`, newest first
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobListResponse {
+    pub jobs: Vec<Value>,
+}
+
+/// How one tool has been used since the MCP server started
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct ToolUsage {
+    pub tool: String,
+    pub calls: u64,
+    pub errors: u64,
+    /// Share of calls that failed, from 0 to 1
+    pub error_rate: f64,
+    pub average_latency_ms: f64,
+    /// RFC 3339 timestamp of the latest call
+    pub last_called_at: String,
+}
+
+/// Usage of every tool called so far, most called first
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ToolStatsResponse {
+    pub tools: Vec<ToolUsage>,
 }
 
 /// Health check response
 #[derive(Debug, Serialize, ToSchema)]
 pub struct HealthResponse {
-    /// Service status
+    /// `healthy`; `degraded` while the circuit breaker is not closed;
+    /// `unhealthy` when the MCP server cannot be reached
     pub status: String,
     /// Service version
     pub version: String,
+    /// The MCP server behind the bridge
+    pub upstream: UpstreamHealth,
+}
+
+/// The MCP server as the bridge sees it
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UpstreamHealth {
+    /// Whether the server answered just now
+    pub reachable: bool,
+    /// The server's version, when it answered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Why the server could not be reached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// When the server last answered `tools/list`; null if it has not yet
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub last_tools_list_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub circuit_breaker: breaker::BreakerStatus,
 }
 
 /// Create the application router with the given state
@@ -91,14 +411,37 @@ pub fn create_app_with_state(state: AppState) -> Router {
     let cors = CorsLayer::new()
         .allow_origin("*".parse::<HeaderValue>().unwrap())
         .allow_methods([Method::GET, Method::POST])
-        .allow_headers(tower_http::cors::Any);
+        .allow_headers(tower_http::cors::Any)
+        .expose_headers([request_id::HEADER]);
     
     // Build our application with routes
-    Router::new()
+    let router = Router::new()
         .route("/health", get(health_handler))
-        .route("/tools", get(list_tools_handler))
+        .route("/tools", get(list_tools_handler).layer(middleware::from_fn(etag::etag)))
+        .route("/tools/refresh", post(refresh_tools_handler))
         .route("/tools/call", post(call_tool_handler))
-        .route("/openapi.json", get(openapi_handler))
+        .route("/tools/:name/invoke", post(invoke_tool_handler))
+        .route("/manifest", get(manifest::manifest_handler))
+        .route("/webhooks/:name", post(webhook_handler))
+        .route("/openai/tools", get(openai::list_tools_handler))
+        .route("/anthropic/tools", get(anthropic::list_tools_handler))
+        .route("/openai/execute", post(openai::execute_handler))
+        .route("/prompts", get(list_prompts_handler))
+        .route("/prompts/:name", get(get_prompt_handler))
+        .route("/resources", get(list_resources_handler))
+        .route("/resources/read", get(read_resource_handler))
+        .route("/logs", get(logs_handler))
+        .route("/stats/tools", get(tool_stats_handler))
+        .route("/jobs", get(list_jobs_handler))
+        .route("/jobs/:id", get(get_job_handler))
+        .route("/openapi.json", get(openapi_handler).layer(middleware::from_fn(etag::etag)));
+    #[cfg(feature = "ui")]
+    let router = router.merge(ui::router());
+
+    router
+        .layer(middleware::from_fn_with_state(state.timeouts.clone(), deadline::enforce))
+        .layer(CompressionLayer::new())
+        .layer(middleware::from_fn(request_id::propagate))
         .layer(cors)
         .with_state(state)
 }
@@ -108,25 +451,51 @@ pub fn create_app_with_state(state: AppState) -> Router {
 pub fn create_app() -> Router {
     // Create a mock MCP client for testing
     let mcp_client = Arc::new(McpClient::new("http://mock-server:3002"));
-    let state = AppState { mcp_client };
+    let state = AppState::new(mcp_client);
     create_app_with_state(state)
 }
 
-async fn health_handler() -> Json<HealthResponse> {
+/// Always 200 while the bridge itself answers, so a liveness probe does not
+/// restart it over an MCP server that is down; `status` tells them apart
+async fn health_handler(State(state): State<AppState>) -> Json<HealthResponse> {
+    let upstream = state.mcp_client.health().await;
+    let status = if !upstream.reachable {
+        "unhealthy"
+    } else if upstream.circuit_breaker.state != breaker::BreakerState::Closed {
+        "degraded"
+    } else {
+        "healthy"
+    };
     Json(HealthResponse {
-        status: "healthy".to_string(),
+        status: status.to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
+        upstream,
     })
 }
 
-async fn list_tools_handler(State(state): State<AppState>) -> Result<Json<ToolListResponse>, StatusCode> {
+async fn list_tools_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ToolListQuery>,
+) -> Result<Json<ToolListResponse>, StatusCode> {
+    let wanted: Vec<&str> = query
+        .tag
+        .as_deref()
+        .map(|tags| tags.split(',').map(str::trim).filter(|tag| !tag.is_empty()).collect())
+        .unwrap_or_default();
     match state.mcp_client.list_tools().await {
         Ok(tools) => {
-            let tool_infos = tools.into_iter().map(|tool| ToolInfo {
-                name: tool.name,
-                description: tool.description,
-                input_schema: tool.input_schema,
-            }).collect();
+            let tool_infos = tools
+                .into_iter()
+                .filter(|tool| wanted.iter().all(|tag| tool.tags.iter().any(|t| t == tag)))
+                .map(|tool| ToolInfo {
+                    name: tool.name,
+                    description: tool.description,
+                    input_schema: tool.input_schema,
+                    output_schema: tool.output_schema,
+                    tags: tool.tags,
+                    annotations: tool.annotations,
+                })
+                .collect();
             
             info!("Successfully listed tools");
             Ok(Json(ToolListResponse { tools: tool_infos }))
@@ -138,11 +507,42 @@ async fn list_tools_handler(State(state): State<AppState>) -> Result<Json<ToolLi
     }
 }
 
+/// Drops the cached tool list and returns a freshly fetched one
+async fn refresh_tools_handler(State(state): State<AppState>) -> Result<Json<ToolListResponse>, StatusCode> {
+    state.mcp_client.invalidate_tools().await;
+    list_tools_handler(State(state), Query(ToolListQuery::default())).await
+}
+
+/// Answers in the format the `Accept` header asks for; see `ResponseFormat`
 async fn call_tool_handler(
-    State(state): State<AppState>, 
+    State(state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<ToolCallRequest>
-) -> Result<Json<ToolCallResponse>, StatusCode> {
-    
+) -> Response {
+    let format = ResponseFormat::from_headers(&headers);
+
+    // Checked against the (usually cached) tool list; tools it does not know,
+    // or a list that cannot be fetched, are left to the server to judge
+    if let Ok(tools) = state.mcp_client.list_tools().await {
+        if let Some(tool) = tools.iter().find(|tool| tool.name == request.tool_name) {
+            let violations = validation::validate(&tool.input_schema, &Value::Object(request.arguments.clone()));
+            if !violations.is_empty() {
+                info!("Rejected arguments for {}: {:?}", request.tool_name, violations);
+                let error = format!("Invalid arguments for tool '{}'", request.tool_name);
+                if format != ResponseFormat::Json {
+                    return format.render_violations(&error, &violations);
+                }
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(ValidationErrorResponse {
+                    success: false,
+                    error,
+                    violations,
+                    request_id: request_id::current(),
+                }))
+                    .into_response();
+            }
+        }
+    }
+
     info!("Calling tool: {} with args: {:?}", request.tool_name, request.arguments);
     info!("Converting request to JSON-RPC call with params: {}", serde_json::json!({
         "name": request.tool_name,
@@ -150,23 +550,178 @@ async fn call_tool_handler(
     }));
     
     match state.mcp_client.call_tool(&request.tool_name, request.arguments).await {
+        Ok(content) if format != ResponseFormat::Json => format.render_content(&content),
         Ok(content) => {
-            Ok(Json(ToolCallResponse {
+            Json(ToolCallResponse {
                 success: true,
+                structured_content: ContentBlock::structured(&content).cloned(),
                 content: Some(content),
                 error: None,
-            }))
+                request_id: request_id::current(),
+            })
+            .into_response()
         }
         Err(e) => {
             error!("Tool call failed: {}", e);
-            Ok(Json(ToolCallResponse {
+            // The server refusing the call is answered with its status; a
+            // tool that ran and failed is reported in the body
+            let refused = e.downcast_ref::<JsonRpcError>().is_some();
+            if format != ResponseFormat::Json && !refused {
+                return format.render_error(&e.to_string());
+            }
+            let status = if refused { error_status(&e) } else { StatusCode::OK };
+            (status, Json(ToolCallResponse {
                 success: false,
                 content: None,
+                structured_content: None,
                 error: Some(e.to_string()),
+                request_id: request_id::current(),
             }))
+            .into_response()
+        }
+    }
+}
+
+/// Calls one tool with the body as its arguments, so each tool has an
+/// endpoint of its own; answers like `/tools/call`
+async fn invoke_tool_handler(
+    State(state): State<AppState>,
+    Path(tool_name): Path<String>,
+    headers: HeaderMap,
+    Json(arguments): Json<serde_json::Map<String, Value>>,
+) -> Response {
+    call_tool_handler(State(state), headers, Json(ToolCallRequest { tool_name, arguments })).await
+}
+
+/// Turns a webhook delivery into a tool call using the webhook's mapping, then
+/// answers exactly as `/tools/call` would
+async fn webhook_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<Value>,
+) -> Response {
+    match state.webhooks.resolve(&name, &payload) {
+        Ok((tool_name, arguments)) => {
+            info!("Webhook {} triggers tool {}", name, tool_name);
+            call_tool_handler(State(state), headers, Json(ToolCallRequest { tool_name, arguments })).await
+        }
+        Err(e) => {
+            error!("Webhook {} rejected: {}", name, e);
+            (StatusCode::BAD_REQUEST, Json(ToolCallResponse {
+                success: false,
+                content: None,
+                structured_content: None,
+                error: Some(e),
+                request_id: request_id::current(),
+            }))
+                .into_response()
+        }
+    }
+}
+
+async fn logs_handler(
+    State(state): State<AppState>,
+    Query(query): Query<LogTailQuery>,
+) -> Result<Json<LogTailResponse>, StatusCode> {
+    match state.mcp_client.tail_logs(&query).await {
+        Ok(entries) => Ok(Json(LogTailResponse { entries })),
+        Err(e) => {
+            error!("Failed to fetch logs: {:#}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn tool_stats_handler(State(state): State<AppState>) -> Result<Json<ToolStatsResponse>, StatusCode> {
+    match state.mcp_client.tool_stats().await {
+        Ok(tools) => Ok(Json(ToolStatsResponse { tools })),
+        Err(e) => {
+            error!("Failed to fetch tool stats: {:#}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn list_jobs_handler(
+    State(state): State<AppState>,
+    Query(query): Query<JobListQuery>,
+) -> Result<Json<JobListResponse>, StatusCode> {
+    match state.mcp_client.list_jobs(&query).await {
+        Ok(jobs) => Ok(Json(JobListResponse { jobs })),
+        Err(e) => {
+            error!("Failed to list jobs: {:#}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn get_job_handler(State(state): State<AppState>, Path(id): Path<String>) -> Result<Json<Value>, StatusCode> {
+    match state.mcp_client.get_job(&id).await {
+        Ok(job) => Ok(Json(job)),
+        Err(e) => {
+            error!("Failed to get job {}: {:#}", id, e);
+            Err(error_status(&e))
         }
     }
 }
 
+async fn list_prompts_handler(State(state): State<AppState>) -> Result<Json<PromptListResponse>, StatusCode> {
+    match state.mcp_client.list_prompts().await {
+        Ok(prompts) => Ok(Json(PromptListResponse { prompts })),
+        Err(e) => {
+            error!("Failed to list prompts: {:#}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Renders a prompt; its arguments come from the query string
+async fn get_prompt_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(arguments): Query<HashMap<String, String>>,
+) -> Result<Json<PromptResponse>, StatusCode> {
+    match state.mcp_client.get_prompt(&name, arguments).await {
+        Ok(prompt) => Ok(Json(prompt)),
+        Err(e) => {
+            error!("Failed to get prompt {}: {:#}", name, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn list_resources_handler(State(state): State<AppState>) -> Result<Json<ResourceListResponse>, StatusCode> {
+    match state.mcp_client.list_resources().await {
+        Ok(resources) => Ok(Json(ResourceListResponse { resources })),
+        Err(e) => {
+            error!("Failed to list resources: {:#}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn read_resource_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ResourceReadQuery>,
+) -> Result<Json<ResourceReadResponse>, StatusCode> {
+    match state.mcp_client.read_resource(&query.uri).await {
+        Ok(contents) => Ok(Json(ResourceReadResponse { contents })),
+        Err(e) => {
+            error!("Failed to read resource {}: {:#}", query.uri, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// The status for a failed request: from the `kind` in the server's error
+/// data, or its code when it sent none; 500 for anything but a JSON-RPC error
+fn error_status(e: &anyhow::Error) -> StatusCode {
+    match e.downcast_ref::<JsonRpcError>() {
+        Some(rpc) => StatusCode::from_u16(rpc.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        None => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
 #[cfg(test)]
 mod tests;
\ No newline at end of file
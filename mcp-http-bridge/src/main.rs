@@ -1,9 +1,17 @@
 use anyhow::Result;
 use clap::Parser;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tracing::{error, info};
+use std::time::Duration;
+use tracing::{info, warn};
+use tracing_subscriber::{prelude::*, EnvFilter};
 
-use mcp_http_bridge::{AppState, McpClient, create_app_with_state};
+
+use mcp_core::logging::{LogFormat, LogOutput, LogRotation};
+
+use mcp_http_bridge::{AppState, CachedBackend, HookedBackend, HooksConfig, McpBackend, McpClient, RouteTimeouts, WebhookConfig, create_app_with_state};
+use mcp_http_bridge::breaker::CircuitBreaker;
+use mcp_http_bridge::deadline::parse_route_timeout;
 
 #[derive(Parser)]
 #[command(name = "mcp-http-bridge")]
@@ -14,9 +22,76 @@ struct Cli {
     
     #[arg(long, default_value = "info")]
     log_level: String,
+
+    #[arg(long, value_enum, default_value = "pretty")]
+    log_format: LogFormat,
+
+    /// Write logs to this file instead of stdout
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+
+    /// How often --log-file starts a new file
+    #[arg(long, value_enum, default_value = "daily")]
+    log_rotation: LogRotation,
     
     #[arg(long, value_name = "MCP_SERVER_URL", default_value = "http://mcp-server:3002")]
     mcp_server_path: String,
+
+    /// Seconds to serve the tool list from cache before asking the server
+    /// again (0 disables caching)
+    #[arg(long, default_value = "30", value_name = "SECONDS")]
+    tools_cache_ttl: u64,
+
+    /// JSON file mapping webhook payloads to tool arguments for
+    /// `POST /webhooks/{name}`
+    #[arg(long, value_name = "FILE")]
+    webhooks: Option<PathBuf>,
+
+    /// JSON file selecting hooks that rewrite tool arguments and results,
+    /// e.g. default arguments or redacting secrets
+    #[arg(long, value_name = "FILE")]
+    hooks: Option<PathBuf>,
+
+    /// URL clients reach the bridge on, used for the endpoint URLs in
+    /// /manifest (default: taken from each request's Host header)
+    #[arg(long, value_name = "URL")]
+    public_url: Option<String>,
+
+    /// Seconds a request may take before it is answered 504; the time left
+    /// is passed on with tool calls so the MCP server stops them too
+    #[arg(long, default_value = "60", value_name = "SECONDS")]
+    timeout: f64,
+
+    /// Timeout of one route, by its pattern, e.g. `/tools/call=120` or
+    /// `/tools/:name/invoke=5` (repeatable)
+    #[arg(long, value_name = "ROUTE=SECONDS", value_parser = parse_route_timeout)]
+    route_timeout: Vec<(String, Duration)>,
+
+    /// Requests in a row the MCP server must fail to answer before further
+    /// ones fail at once
+    #[arg(long, default_value = "5", value_name = "COUNT")]
+    breaker_threshold: u32,
+
+    /// Seconds requests fail at once after the breaker opens
+    #[arg(long, default_value = "30", value_name = "SECONDS")]
+    breaker_cooldown: u64,
+
+    /// Whether to start when the MCP server does not answer at startup
+    #[arg(long, value_enum, default_value = "strict")]
+    startup_policy: StartupPolicy,
+
+    /// Start without asking the MCP server for its tools first
+    #[arg(long)]
+    skip_checks: bool,
+}
+
+/// What to do when the MCP server fails its startup check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum StartupPolicy {
+    /// Start anyway; requests fail until the server answers
+    Degraded,
+    /// Refuse to start
+    Strict,
 }
 
 #[tokio::main]
@@ -24,26 +99,83 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
     
     // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(&cli.log_level)
+    let log_output = LogOutput {
+        format: cli.log_format,
+        file: cli.log_file.clone(),
+        rotation: cli.log_rotation,
+        stderr: false,
+    };
+    let (log_layer, _log_guard) = log_output.layer()?;
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(&cli.log_level))
+        .with(log_layer)
         .init();
 
     info!("Starting MCP HTTP Bridge v{}", env!("CARGO_PKG_VERSION"));
     
     // Initialize MCP client
-    let mcp_client = Arc::new(McpClient::new(&cli.mcp_server_path));
-    
-    // Initialize the MCP server
-    match mcp_client.initialize().await {
-        Ok(_) => info!("MCP server initialized successfully"),
-        Err(e) => {
-            error!("Failed to initialize MCP server: {}", e);
-            return Err(e);
+    let breaker = CircuitBreaker::new(cli.breaker_threshold, Duration::from_secs(cli.breaker_cooldown));
+    let admin_token = std::env::var("MCP_ADMIN_TOKEN").ok().filter(|token| !token.is_empty());
+    let mcp_client = Arc::new(
+        McpClient::new(&cli.mcp_server_path)
+            .with_breaker(breaker)
+            .with_admin_token(admin_token),
+    );
+    
+    // The upstream server is the bridge's only dependency
+    if !cli.skip_checks {
+        info!("Startup checks:");
+        match mcp_client.initialize().await {
+            Ok(_) => info!("  MCP server  OK      {}", cli.mcp_server_path),
+            Err(e) => {
+                warn!(
+                    "  MCP server  FAILED  {}: {} -> Check that mcp-server runs in HTTP mode and --mcp-server-path points at it",
+                    cli.mcp_server_path, e
+                );
+                if cli.startup_policy == StartupPolicy::Strict {
+                    return Err(e.context("MCP server failed its startup check; start with --startup-policy degraded to run without it"));
+                }
+                warn!("Starting without the MCP server; requests fail until it answers");
+            }
         }
     }
     
-    let state = AppState { mcp_client };
+    let backend: Arc<dyn McpBackend> = if cli.tools_cache_ttl > 0 {
+        let ttl = Duration::from_secs(cli.tools_cache_ttl);
+        Arc::new(CachedBackend::new(mcp_client, ttl))
+    } else {
+        mcp_client
+    };
+    let backend: Arc<dyn McpBackend> = match &cli.hooks {
+        Some(path) => {
+            let hooks = HooksConfig::load(path)?.build()?;
+            info!("Loaded {} hooks from {}", hooks.len(), path.display());
+            Arc::new(HookedBackend::new(backend, hooks))
+        }
+        None => backend,
+    };
+    let state = AppState::new(backend);
+    let state = match &cli.webhooks {
+        Some(path) => {
+            let webhooks = WebhookConfig::load(path)?;
+            info!("Loaded {} webhook mappings from {}", webhooks.webhooks.len(), path.display());
+            state.with_webhooks(webhooks)
+        }
+        None => state,
+    };
+    let state = match cli.public_url {
+        Some(url) => state.with_public_url(url),
+        None => state,
+    };
     
+    let timeouts = cli
+        .route_timeout
+        .into_iter()
+        .fold(RouteTimeouts::new(Duration::try_from_secs_f64(cli.timeout)?), |timeouts, (route, timeout)| {
+            timeouts.with_route(route, timeout)
+        });
+    let state = state.with_timeouts(timeouts);
+
     let app = create_app_with_state(state);
 
     // Run the server
@@ -0,0 +1,211 @@
+//! `GET /manifest`: the live tool registry as something agent frameworks can
+//! import directly. Every tool gets its own endpoint, `POST
+//! /tools/{name}/invoke`, taking the arguments object as the body, so a
+//! framework can treat each one as an ordinary HTTP operation.
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::error;
+use utoipa::ToSchema;
+
+use crate::mcp_client::ToolDefinition;
+use crate::AppState;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestFormat {
+    /// Tools with their schemas and endpoint URLs
+    #[default]
+    Generic,
+    /// An OpenAPI spec with one operation per tool, for LangChain's
+    /// `OpenAPIToolkit` / `OpenAPISpec.from_url`
+    Langchain,
+    /// Tool metadata (`name`, `description`, `fn_schema`) plus endpoints, for
+    /// building LlamaIndex `FunctionTool`s
+    Llamaindex,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ManifestQuery {
+    #[serde(default)]
+    pub format: ManifestFormat,
+}
+
+/// Where and how to call a tool
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ToolEndpoint {
+    pub method: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ManifestTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+    /// Shape of `structured_content` in the endpoint's JSON responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    pub endpoint: ToolEndpoint,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    /// URL the endpoints below are relative to
+    pub base_url: String,
+    pub openapi_url: String,
+    pub tools: Vec<ManifestTool>,
+}
+
+pub async fn manifest_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ManifestQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let tools = match state.mcp_client.list_tools().await {
+        Ok(tools) => tools,
+        Err(e) => {
+            error!("Failed to list tools: {:#}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let base_url = match &state.public_url {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => base_url_from(&headers),
+    };
+
+    let manifest = match query.format {
+        ManifestFormat::Generic => serde_json::to_value(generic(&base_url, tools)).unwrap(),
+        ManifestFormat::Langchain => langchain(&base_url, &tools),
+        ManifestFormat::Llamaindex => llamaindex(&base_url, &tools),
+    };
+    Ok(Json(manifest))
+}
+
+/// The URL clients reached us on, honouring `X-Forwarded-*` from a proxy
+fn base_url_from(headers: &HeaderMap) -> String {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let scheme = header("x-forwarded-proto").unwrap_or("http");
+    let host = header("x-forwarded-host")
+        .or_else(|| headers.get(header::HOST).and_then(|value| value.to_str().ok()))
+        .unwrap_or("localhost");
+    format!("{}://{}", scheme, host)
+}
+
+fn invoke_path(tool: &str) -> String {
+    format!("/tools/{}/invoke", tool)
+}
+
+fn generic(base_url: &str, tools: Vec<ToolDefinition>) -> Manifest {
+    Manifest {
+        name: "mcp-http-bridge".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        base_url: base_url.to_string(),
+        openapi_url: format!("{}/openapi.json", base_url),
+        tools: tools
+            .into_iter()
+            .map(|tool| ManifestTool {
+                endpoint: ToolEndpoint {
+                    method: "POST".to_string(),
+                    url: format!("{}{}", base_url, invoke_path(&tool.name)),
+                },
+                name: tool.name,
+                description: tool.description,
+                input_schema: tool.input_schema,
+                output_schema: tool.output_schema,
+            })
+            .collect(),
+    }
+}
+
+fn langchain(base_url: &str, tools: &[ToolDefinition]) -> Value {
+    let paths: serde_json::Map<String, Value> = tools
+        .iter()
+        .map(|tool| {
+            let mut output = json!({ "text/plain": { "schema": { "type": "string" } } });
+            // With `Accept: application/json` the structured output comes in the envelope
+            if let Some(output_schema) = &tool.output_schema {
+                output["application/json"] = json!({ "schema": {
+                    "type": "object",
+                    "properties": {
+                        "success": { "type": "boolean" },
+                        "structured_content": output_schema,
+                        "error": { "type": "string", "nullable": true }
+                    }
+                } });
+            }
+            let operation = json!({
+                "post": {
+                    "operationId": tool.name,
+                    "summary": tool.name,
+                    "description": tool.description,
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": tool.input_schema } }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Tool output",
+                            "content": output
+                        }
+                    }
+                }
+            });
+            (invoke_path(&tool.name), operation)
+        })
+        .collect();
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "MCP tools",
+            "description": "Tools of the MCP server behind mcp-http-bridge, one operation each",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "servers": [{ "url": base_url }],
+        "paths": paths
+    })
+}
+
+fn llamaindex(base_url: &str, tools: &[ToolDefinition]) -> Value {
+    let tools: Vec<Value> = tools
+        .iter()
+        .map(|tool| json!({
+            "metadata": {
+                "name": tool.name,
+                "description": tool.description,
+                "fn_schema": tool.input_schema
+            },
+            "endpoint": {
+                "method": "POST",
+                "url": format!("{}{}", base_url, invoke_path(&tool.name)),
+                "headers": { "Accept": "text/plain" }
+            }
+        }))
+        .collect();
+    json!({ "tools": tools })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_base_url_prefers_forwarded_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static("bridge:3001"));
+        assert_eq!(base_url_from(&headers), "http://bridge:3001");
+
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+        headers.insert("x-forwarded-host", HeaderValue::from_static("tools.example.com"));
+        assert_eq!(base_url_from(&headers), "https://tools.example.com");
+    }
+}
@@ -1,16 +1,23 @@
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
-use tracing::{debug, error, info};
+use tracing::{debug, error};
 
-use crate::ContentBlock;
+use crate::breaker::CircuitBreaker;
+use crate::{deadline, request_id};
+use crate::{ContentBlock, JobListQuery, LogTailQuery, PromptInfo, PromptResponse, ResourceContents, ResourceInfo, ToolUsage, UpstreamHealth};
+
+pub use mcp_core::JsonRpcError;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JsonRpcRequest {
     pub jsonrpc: String,
-    pub id: i32,
+    pub id: Value,
     pub method: String,
     pub params: Option<Value>,
 }
@@ -18,53 +25,152 @@ pub struct JsonRpcRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JsonRpcResponse {
     pub jsonrpc: String,
-    pub id: i32,
+    pub id: Value,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<JsonRpcError>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct JsonRpcError {
-    pub code: i32,
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<Value>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+/// A tool as the server lists it. Servers that spell the schemas
+/// `input_schema` and `output_schema` are understood too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolDefinition {
     pub name: String,
     pub description: String,
-    #[serde(rename = "inputSchema")]
+    #[serde(rename = "inputSchema", alias = "input_schema")]
     pub input_schema: Value,
+    /// Shape of the tool's structured results, if it declares one
+    #[serde(rename = "outputSchema", alias = "output_schema", default)]
+    pub output_schema: Option<Value>,
+    /// `read-only`, `destructive`, `network`, `slow`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// MCP tool annotations (`readOnlyHint`, `destructiveHint`, ...)
+    #[serde(default)]
+    pub annotations: Option<Value>,
 }
 
+/// How long `/health` waits for the server's version; a server that is up
+/// answers in far less, and probes should not wait on one that is not
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
 pub struct McpClient {
     mcp_server_path: String,
+    /// Shared by every request, so they reuse connections instead of each
+    /// setting up a client and connecting anew
+    http: reqwest::Client,
     request_id: Arc<Mutex<i32>>,
+    breaker: CircuitBreaker,
+    last_tools_list: std::sync::Mutex<Option<DateTime<Utc>>>,
+    /// The server's `MCP_ADMIN_TOKEN`, needed to read its logs
+    admin_token: Option<String>,
 }
 
 impl McpClient {
     pub fn new(mcp_server_path: &str) -> Self {
         Self {
             mcp_server_path: mcp_server_path.to_string(),
+            http: reqwest::Client::new(),
             request_id: Arc::new(Mutex::new(1)),
+            breaker: CircuitBreaker::default(),
+            last_tools_list: std::sync::Mutex::new(None),
+            admin_token: None,
+        }
+    }
+
+    pub fn with_admin_token(mut self, token: Option<String>) -> Self {
+        self.admin_token = token;
+        self
+    }
+
+    pub fn with_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// Sends `builder` unless the breaker is open, and tells the breaker
+    /// whether the server answered; a 5xx status counts as no answer
+    async fn send(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        self.breaker.check()?;
+        match builder.send().await {
+            Ok(response) if response.status().is_server_error() => {
+                self.breaker.record_failure();
+                Ok(response)
+            }
+            Ok(response) => {
+                self.breaker.record_success();
+                Ok(response)
+            }
+            Err(e) => {
+                self.breaker.record_failure();
+                Err(e.into())
+            }
+        }
+    }
+
+    /// The server as `/health` reports it. Asks for its version past the
+    /// breaker, so an open breaker does not hide a server that is back.
+    pub async fn health(&self) -> UpstreamHealth {
+        let url = format!("{}/version", self.mcp_server_path.trim_end_matches('/'));
+        let probe = async {
+            let response = Self::with_request_id(self.http.get(&url)).timeout(PROBE_TIMEOUT).send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(anyhow!("MCP server error: {}", status));
+            }
+            Ok(response.text().await?.trim().to_string())
+        };
+        let (version, error) = match probe.await {
+            Ok(version) => (Some(version), None),
+            Err(e) => (None, Some(format!("{:#}", e))),
+        };
+        UpstreamHealth {
+            reachable: version.is_some(),
+            version,
+            error,
+            last_tools_list_at: *self.last_tools_list.lock().unwrap(),
+            circuit_breaker: self.breaker.status(),
         }
     }
 
-    async fn get_next_id(&self) -> i32 {
+    /// The `X-Request-Id` of the request being handled, so the server tags
+    /// its log lines for the call with it; a running number otherwise
+    async fn get_next_id(&self) -> Value {
+        if let Some(id) = request_id::current() {
+            return Value::String(id);
+        }
         let mut id = self.request_id.lock().await;
         let current = *id;
         *id += 1;
-        current
+        Value::from(current)
+    }
+
+    /// Sends the current `X-Request-Id` along, for the server to log
+    fn with_request_id(builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match request_id::current() {
+            Some(id) => builder.header(request_id::HEADER.as_str(), id),
+            None => builder,
+        }
+    }
+
+    /// Params of a tools/call, with the time left until the request being
+    /// handled times out, so the server stops the call then too
+    fn tool_call_params(tool_name: &str, arguments: serde_json::Map<String, Value>) -> Value {
+        let mut params = serde_json::json!({
+            "name": tool_name,
+            "arguments": arguments
+        });
+        if let Some(remaining) = deadline::remaining() {
+            params["_meta"] = serde_json::json!({ "timeoutMs": remaining.as_millis() as u64 });
+        }
+        params
     }
 
     async fn execute_mcp_command(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse> {
         debug!("Executing MCP command: {} to {}", request.method, self.mcp_server_path);
         
-        let client = reqwest::Client::new();
+        let client = &self.http;
         let base_url = self.mcp_server_path.trim_end_matches('/').to_string();
         let url = if request.method == "tools/list" {
             format!("{}/tools/list", base_url)
@@ -91,20 +197,17 @@ impl McpClient {
         
         debug!("Sending JSON-RPC request: {}", json_rpc);
         
-        let response = if request.method == "tools/list" {
-            client.get(&url)
+        let builder = if request.method == "tools/list" {
+            Self::with_request_id(client.get(&url))
                 .header("Content-Type", "application/json")
                 .header("Accept", "application/json")
-                .send()
-                .await?
         } else {
-            client.post(&url)
+            Self::with_request_id(client.post(&url))
                 .header("Content-Type", "application/json")
                 .header("Accept", "application/json")
                 .json(&json_rpc)
-                .send()
-                .await?
         };
+        let response = self.send(builder).await?;
             
         let status = response.status();
         debug!("Response status: {}", status);
@@ -123,6 +226,7 @@ impl McpClient {
             if let Ok(tools_response) = serde_json::from_str::<serde_json::Value>(&response_text) {
                 debug!("Got raw tools response: {}", tools_response);
                 if let Some(tools) = tools_response.get("tools") {
+                    *self.last_tools_list.lock().unwrap() = Some(Utc::now());
                     return Ok(JsonRpcResponse {
                         jsonrpc: "2.0".to_string(),
                         id: request.id,
@@ -149,16 +253,8 @@ impl McpClient {
             params: None,
         };
 
-        match self.execute_mcp_command(request).await {
-            Ok(_) => {
-                info!("MCP server initialized successfully");
-                Ok(())
-            }
-            Err(e) => {
-                error!("Failed to initialize MCP server: {}", e);
-                Err(e)
-            }
-        }
+        // The caller reports the outcome
+        self.execute_mcp_command(request).await.map(drop)
     }
 
     pub async fn list_tools(&self) -> Result<Vec<ToolDefinition>> {
@@ -200,47 +296,190 @@ impl McpClient {
             jsonrpc: "2.0".to_string(),
             id,
             method: "tools/call".to_string(),
-            params: Some(serde_json::json!({
-                "name": tool_name,
-                "arguments": arguments
-            })),
+            params: Some(Self::tool_call_params(tool_name, arguments)),
+        };
+
+        let response = self.execute_mcp_command(request).await?;
+        tool_content(response)
+    }
+
+    /// Calls several tools in one JSON-RPC batch, which the server runs
+    /// concurrently. Returns one result per call, in order.
+    pub async fn call_tools(&self, calls: Vec<(String, serde_json::Map<String, Value>)>) -> Result<Vec<Result<Vec<ContentBlock>>>> {
+        // Calls in a batch need ids of their own: `<id>-1`, `<id>-2`, ...
+        let batch = match self.get_next_id().await {
+            Value::String(id) => id,
+            id => id.to_string(),
+        };
+        let mut requests = Vec::with_capacity(calls.len());
+        for (index, (tool_name, arguments)) in calls.into_iter().enumerate() {
+            requests.push(JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                id: Value::String(format!("{}-{}", batch, index + 1)),
+                method: "tools/call".to_string(),
+                params: Some(Self::tool_call_params(&tool_name, arguments)),
+            });
+        }
+        debug!("Making batch of {} tool calls", requests.len());
+
+        let url = format!("{}/tools/call", self.mcp_server_path.trim_end_matches('/'));
+        let builder = Self::with_request_id(self.http.post(&url))
+            .header("Accept", "application/json")
+            .json(&requests);
+        let response = self.send(builder).await?;
+        let status = response.status();
+        let response_text = response.text().await?;
+        if !status.is_success() {
+            error!("MCP server returned error status: {} with body: {}", status, response_text);
+            return Err(anyhow!("MCP server error: {} - {}", status, response_text));
+        }
+
+        let mut responses: HashMap<String, JsonRpcResponse> = serde_json::from_str::<Vec<JsonRpcResponse>>(&response_text)
+            .map_err(|e| anyhow!("JSON-RPC parse error: {} - Response: {}", e, response_text))?
+            .into_iter()
+            .map(|response| (response.id.to_string(), response))
+            .collect();
+        Ok(requests
+            .iter()
+            .map(|request| match responses.remove(&request.id.to_string()) {
+                Some(JsonRpcResponse { error: Some(error), .. }) => Err(anyhow::Error::new(error).context("tools/call failed")),
+                Some(response) => tool_content(response),
+                None => Err(anyhow!("No response to tool call {} in batch", request.id)),
+            })
+            .collect())
+    }
+
+    /// Recent log lines of all sessions from the server's
+    /// `GET /admin/logs`, oldest first
+    pub async fn tail_logs(&self, query: &LogTailQuery) -> Result<Vec<Value>> {
+        let Some(token) = &self.admin_token else {
+            return Err(anyhow!("Reading the server's logs needs its admin token in MCP_ADMIN_TOKEN"));
+        };
+        let url = format!("{}/admin/logs", self.mcp_server_path.trim_end_matches('/'));
+        let builder = Self::with_request_id(self.http.get(&url)).bearer_auth(token).query(query);
+        let response = self.send(builder).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow!("MCP server error: {} - {}", status, response.text().await?));
+        }
+        response.json::<Value>().await?
+            .get("entries")
+            .and_then(|entries| serde_json::from_value(entries.clone()).ok())
+            .ok_or_else(|| anyhow!("Invalid /admin/logs response format"))
+    }
+
+    /// Result of a request; a JSON-RPC error comes back as a `JsonRpcError`
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: self.get_next_id().await,
+            method: method.to_string(),
+            params: Some(params),
         };
 
         let response = self.execute_mcp_command(request).await?;
+        if let Some(error) = response.error {
+            return Err(anyhow::Error::new(error).context(format!("{} failed", method)));
+        }
+        response.result.ok_or_else(|| anyhow!("Invalid {} response format: no result field", method))
+    }
+
+    pub async fn tool_stats(&self) -> Result<Vec<ToolUsage>> {
+        let result = self.request("stats/tools", serde_json::json!({})).await?;
+        serde_json::from_value(result["tools"].clone())
+            .map_err(|e| anyhow!("Invalid stats/tools response format: {}", e))
+    }
+
+    pub async fn list_jobs(&self, query: &JobListQuery) -> Result<Vec<Value>> {
+        let result = self.request("jobs/list", serde_json::to_value(query)?).await?;
+        serde_json::from_value(result["jobs"].clone())
+            .map_err(|e| anyhow!("Invalid jobs/list response format: {}", e))
+    }
+
+    pub async fn get_job(&self, id: &str) -> Result<Value> {
+        let mut result = self.request("jobs/get", serde_json::json!({ "jobId": id })).await?;
+        match result["job"].take() {
+            Value::Null => Err(anyhow!("Invalid jobs/get response format: no job")),
+            job => Ok(job),
+        }
+    }
+
+    pub async fn list_prompts(&self) -> Result<Vec<PromptInfo>> {
+        let result = self.request("prompts/list", serde_json::json!({})).await?;
+        serde_json::from_value(result["prompts"].clone())
+            .map_err(|e| anyhow!("Invalid prompts/list response format: {}", e))
+    }
+
+    pub async fn get_prompt(&self, name: &str, arguments: HashMap<String, String>) -> Result<PromptResponse> {
+        let result = self
+            .request("prompts/get", serde_json::json!({ "name": name, "arguments": arguments }))
+            .await?;
+        serde_json::from_value(result).map_err(|e| anyhow!("Invalid prompts/get response format: {}", e))
+    }
+
+    pub async fn list_resources(&self) -> Result<Vec<ResourceInfo>> {
+        let result = self.request("resources/list", serde_json::json!({})).await?;
+        serde_json::from_value(result["resources"].clone())
+            .map_err(|e| anyhow!("Invalid resources/list response format: {}", e))
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>> {
+        let result = self.request("resources/read", serde_json::json!({ "uri": uri })).await?;
+        serde_json::from_value(result["contents"].clone())
+            .map_err(|e| anyhow!("Invalid resources/read response format: {}", e))
+    }
+}
+
+/// The content blocks of a tools/call response. A protocol error comes back
+/// as a `JsonRpcError`, a tool that ran and failed as a plain error.
+fn tool_content(response: JsonRpcResponse) -> Result<Vec<ContentBlock>> {
+    if let Some(error) = response.error {
+        return Err(anyhow::Error::new(error));
+    }
+    if let Some(result) = response.result {
+        debug!("Got result from MCP server: {:?}", result);
+
+        if result["isError"] == true {
+            let content: Vec<ContentBlock> = serde_json::from_value(result["content"].clone()).unwrap_or_default();
+            return Err(anyhow!("Tool execution failed: {}", ContentBlock::texts(&content).join("\n")));
+        }
         
-        if let Some(result) = response.result {
-            debug!("Got result from MCP server: {:?}", result);
-            
-            // Try to parse from the result.content field
-            if let Some(content_obj) = result.as_object().and_then(|obj| obj.get("content")) {
-                match serde_json::from_value::<Vec<ContentBlock>>(content_obj.clone()) {
-                    Ok(content) => {
-                        debug!("Successfully parsed content blocks: {:?}", content);
-                        return Ok(content);
+        // Try to parse from the result.content field
+        if let Some(content_obj) = result.as_object().and_then(|obj| obj.get("content")) {
+            match serde_json::from_value::<Vec<ContentBlock>>(content_obj.clone()) {
+                Ok(mut content) => {
+                    debug!("Successfully parsed content blocks: {:?}", content);
+                    // Servers that only send structured content as a field
+                    // still get a `json` block for it
+                    if let Some(structured) = result.get("structuredContent").filter(|structured| structured.is_object()) {
+                        if ContentBlock::structured(&content) != Some(structured) {
+                            content.push(ContentBlock::Json { json: structured.clone() });
+                        }
                     }
-                    Err(e) => {
-                        error!("Failed to parse content blocks: {}", e);
-                        error!("Raw result was: {:?}", result);
-                        return Err(anyhow!("Invalid tools/call response format: {}", e));
-                    }
-                }
-            }
-            
-            // Try to parse directly if no content field
-            match serde_json::from_value::<Vec<ContentBlock>>(result.clone()) {
-                Ok(content) => {
-                    debug!("Successfully parsed content blocks directly: {:?}", content);
                     return Ok(content);
                 }
                 Err(e) => {
-                    error!("Failed to parse content blocks directly: {}", e);
+                    error!("Failed to parse content blocks: {}", e);
                     error!("Raw result was: {:?}", result);
                     return Err(anyhow!("Invalid tools/call response format: {}", e));
                 }
             }
         }
         
-        error!("No result field in response");
-        Err(anyhow!("Invalid tools/call response format: no result field"))
+        // Try to parse directly if no content field
+        match serde_json::from_value::<Vec<ContentBlock>>(result.clone()) {
+            Ok(content) => {
+                debug!("Successfully parsed content blocks directly: {:?}", content);
+                return Ok(content);
+            }
+            Err(e) => {
+                error!("Failed to parse content blocks directly: {}", e);
+                error!("Raw result was: {:?}", result);
+                return Err(anyhow!("Invalid tools/call response format: {}", e));
+            }
+        }
     }
+    
+    error!("No result field in response");
+    Err(anyhow!("Invalid tools/call response format: no result field"))
 }
@@ -0,0 +1,157 @@
+//! Content negotiation for `/tools/call`: callers that only want the tool's
+//! text can ask for `text/plain` or `text/markdown` instead of the JSON
+//! envelope of content blocks.
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+
+use crate::{ContentBlock, Violation};
+
+/// How a tool call's result is written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// The `ToolCallResponse` envelope (the default)
+    Json,
+    /// Text blocks joined by newlines
+    Text,
+    /// Text blocks as Markdown, with JSON blocks fenced and pretty-printed
+    Markdown,
+}
+
+impl ResponseFormat {
+    /// The most preferred format the `Accept` header allows, honouring
+    /// q-values; JSON when there is no header or nothing we offer matches
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers.get(header::ACCEPT).and_then(|value| value.to_str().ok()) else {
+            return ResponseFormat::Json;
+        };
+
+        let mut ranges: Vec<(&str, f32)> = accept
+            .split(',')
+            .map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let media_type = parts.next().unwrap_or_default();
+                let quality = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                (media_type, quality)
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+        // Stable, so equal preferences keep the caller's order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        ranges
+            .iter()
+            .find_map(|(media_type, _)| match media_type.to_ascii_lowercase().as_str() {
+                "application/json" | "application/*" | "*/*" => Some(ResponseFormat::Json),
+                "text/plain" | "text/*" => Some(ResponseFormat::Text),
+                "text/markdown" => Some(ResponseFormat::Markdown),
+                _ => None,
+            })
+            .unwrap_or(ResponseFormat::Json)
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            ResponseFormat::Json => "application/json",
+            ResponseFormat::Text => "text/plain; charset=utf-8",
+            ResponseFormat::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+
+    /// A successful call's content; not for `Json`, which keeps the envelope
+    pub fn render_content(&self, content: &[ContentBlock]) -> Response {
+        let texts = ContentBlock::texts(content);
+        let body = match self {
+            ResponseFormat::Markdown => texts.iter().map(|text| markdown_block(text)).collect::<Vec<_>>().join("\n\n"),
+            _ => texts.join("\n"),
+        };
+        self.respond(StatusCode::OK, body)
+    }
+
+    /// A failed call. JSON keeps reporting failures in the envelope with a
+    /// 200, but a text body has nowhere else to say it failed, so it gets a 502.
+    pub fn render_error(&self, message: &str) -> Response {
+        let body = match self {
+            ResponseFormat::Markdown => format!("**Error:** {}", message),
+            _ => format!("Error: {}", message),
+        };
+        self.respond(StatusCode::BAD_GATEWAY, body)
+    }
+
+    /// Arguments rejected by schema validation, one violation per line
+    pub fn render_violations(&self, error: &str, violations: &[Violation]) -> Response {
+        let lines = violations.iter().map(|violation| {
+            let path = if violation.path.is_empty() { "/" } else { violation.path.as_str() };
+            match self {
+                ResponseFormat::Markdown => format!("- `{}`: {}", path, violation.message),
+                _ => format!("{}: {}", path, violation.message),
+            }
+        });
+        let heading = match self {
+            ResponseFormat::Markdown => format!("**{}**\n", error),
+            _ => error.to_string(),
+        };
+        let body = std::iter::once(heading).chain(lines).collect::<Vec<_>>().join("\n");
+        self.respond(StatusCode::UNPROCESSABLE_ENTITY, body)
+    }
+
+    fn respond(&self, status: StatusCode, body: String) -> Response {
+        (
+            status,
+            [
+                (header::CONTENT_TYPE, HeaderValue::from_static(self.content_type())),
+                (header::VARY, HeaderValue::from_static("accept")),
+            ],
+            body,
+        )
+            .into_response()
+    }
+}
+
+/// JSON objects and arrays are fenced so they render as code; other text is
+/// assumed to be Markdown or prose already
+fn markdown_block(text: &str) -> String {
+    match serde_json::from_str::<Value>(text) {
+        Ok(value @ (Value::Object(_) | Value::Array(_))) => {
+            format!("```json\n{}\n```", serde_json::to_string_pretty(&value).unwrap())
+        }
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_format_follows_accept_preferences() {
+        assert_eq!(ResponseFormat::from_headers(&HeaderMap::new()), ResponseFormat::Json);
+        assert_eq!(ResponseFormat::from_headers(&accept("text/plain")), ResponseFormat::Text);
+        assert_eq!(ResponseFormat::from_headers(&accept("text/markdown, text/plain")), ResponseFormat::Markdown);
+        assert_eq!(
+            ResponseFormat::from_headers(&accept("application/json;q=0.5, text/markdown;q=0.9")),
+            ResponseFormat::Markdown
+        );
+        assert_eq!(ResponseFormat::from_headers(&accept("text/plain;q=0, */*")), ResponseFormat::Json);
+        assert_eq!(ResponseFormat::from_headers(&accept("image/png")), ResponseFormat::Json);
+    }
+
+    #[test]
+    fn test_markdown_fences_json_blocks() {
+        assert_eq!(markdown_block(r#"{"a":1}"#), "```json\n{\n  \"a\": 1\n}\n```");
+        assert_eq!(markdown_block("# Report"), "# Report");
+        assert_eq!(markdown_block("42"), "42");
+    }
+}
@@ -0,0 +1,186 @@
+//! OpenAI function-calling compatibility: `GET /openai/tools` lists the tools
+//! in the shape the `tools` request parameter takes, and `POST /openai/execute`
+//! runs the `tool_calls` a model answers with and returns the `tool` messages
+//! to send back.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::{validation, AppState, ContentBlock};
+
+/// A tool as OpenAI expects it in the `tools` parameter
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OpenAiTool {
+    /// Always `function`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub function: OpenAiFunction,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OpenAiFunction {
+    pub name: String,
+    pub description: String,
+    /// The tool's input schema
+    pub parameters: Value,
+}
+
+/// A `tool_calls` entry from an assistant message
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OpenAiToolCall {
+    pub id: String,
+    pub function: OpenAiFunctionCall,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OpenAiFunctionCall {
+    pub name: String,
+    /// Arguments as a JSON-encoded string, as OpenAI sends them; a plain
+    /// object is accepted too
+    pub arguments: Value,
+}
+
+/// One tool call or all of an assistant message's `tool_calls`
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum OpenAiExecuteRequest {
+    One(OpenAiToolCall),
+    Many(Vec<OpenAiToolCall>),
+}
+
+/// A `tool` message answering one tool call
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OpenAiToolMessage {
+    /// Always `tool`
+    pub role: String,
+    pub tool_call_id: String,
+    /// The tool's text, or what went wrong, for the model to read
+    pub content: String,
+}
+
+/// Mirrors the request: one message for one call, an array for an array
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum OpenAiExecuteResponse {
+    One(OpenAiToolMessage),
+    Many(Vec<OpenAiToolMessage>),
+}
+
+pub async fn list_tools_handler(State(state): State<AppState>) -> Result<Json<Vec<OpenAiTool>>, StatusCode> {
+    match state.mcp_client.list_tools().await {
+        Ok(tools) => Ok(Json(
+            tools
+                .into_iter()
+                .map(|tool| OpenAiTool {
+                    kind: "function".to_string(),
+                    function: OpenAiFunction {
+                        name: tool.name,
+                        description: tool.description,
+                        parameters: tool.input_schema,
+                    },
+                })
+                .collect(),
+        )),
+        Err(e) => {
+            error!("Failed to list tools: {:#}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Runs the calls in order. Failures, including arguments the model got
+/// wrong, are reported in the message content rather than as HTTP errors, so
+/// the model can read them and try again.
+pub async fn execute_handler(
+    State(state): State<AppState>,
+    Json(request): Json<OpenAiExecuteRequest>,
+) -> Json<OpenAiExecuteResponse> {
+    match request {
+        OpenAiExecuteRequest::One(call) => Json(OpenAiExecuteResponse::One(execute(&state, call).await)),
+        OpenAiExecuteRequest::Many(calls) => {
+            let mut messages = Vec::with_capacity(calls.len());
+            for call in calls {
+                messages.push(execute(&state, call).await);
+            }
+            Json(OpenAiExecuteResponse::Many(messages))
+        }
+    }
+}
+
+async fn execute(state: &AppState, call: OpenAiToolCall) -> OpenAiToolMessage {
+    let content = match run(state, &call).await {
+        Ok(text) => text,
+        Err(e) => {
+            error!("OpenAI tool call {} ({}) failed: {}", call.id, call.function.name, e);
+            format!("Error: {}", e)
+        }
+    };
+    OpenAiToolMessage {
+        role: "tool".to_string(),
+        tool_call_id: call.id,
+        content,
+    }
+}
+
+async fn run(state: &AppState, call: &OpenAiToolCall) -> Result<String, String> {
+    let arguments = parse_arguments(&call.function.arguments)?;
+    let name = &call.function.name;
+
+    if let Ok(tools) = state.mcp_client.list_tools().await {
+        if let Some(tool) = tools.iter().find(|tool| &tool.name == name) {
+            let violations = validation::validate(&tool.input_schema, &Value::Object(arguments.clone()));
+            if !violations.is_empty() {
+                let details: Vec<String> = violations
+                    .iter()
+                    .map(|violation| {
+                        let path = if violation.path.is_empty() { "/" } else { violation.path.as_str() };
+                        format!("{}: {}", path, violation.message)
+                    })
+                    .collect();
+                return Err(format!("Invalid arguments for tool '{}': {}", name, details.join("; ")));
+            }
+        }
+    }
+
+    info!("OpenAI tool call {}: {}", call.id, name);
+    let content = state.mcp_client.call_tool(name, arguments).await.map_err(|e| e.to_string())?;
+    Ok(ContentBlock::texts(&content).join("\n"))
+}
+
+/// OpenAI encodes arguments as a JSON string; an empty string means none
+fn parse_arguments(arguments: &Value) -> Result<Map<String, Value>, String> {
+    let parsed = match arguments {
+        Value::String(text) if text.trim().is_empty() => Value::Object(Map::new()),
+        Value::String(text) => {
+            serde_json::from_str(text).map_err(|e| format!("Arguments are not valid JSON: {}", e))?
+        }
+        Value::Null => Value::Object(Map::new()),
+        other => other.clone(),
+    };
+    match parsed {
+        Value::Object(arguments) => Ok(arguments),
+        _ => Err("Arguments must be a JSON object".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_arguments_may_be_encoded_or_plain() {
+        assert_eq!(parse_arguments(&json!(r#"{"city":"Oslo"}"#)).unwrap()["city"], "Oslo");
+        assert_eq!(parse_arguments(&json!({ "city": "Oslo" })).unwrap()["city"], "Oslo");
+        assert!(parse_arguments(&json!("")).unwrap().is_empty());
+        assert!(parse_arguments(&json!("{not json")).unwrap_err().contains("not valid JSON"));
+        assert!(parse_arguments(&json!("[1]")).is_err());
+    }
+}
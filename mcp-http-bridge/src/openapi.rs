@@ -2,7 +2,12 @@ use axum::Json;
 use serde_json::{json, Value};
 use utoipa::{OpenApi, ToSchema};
 
-use crate::{ContentBlock, HealthResponse, ToolCallRequest, ToolCallResponse, ToolInfo, ToolListResponse};
+use crate::{
+    breaker::{BreakerState, BreakerStatus}, ContentBlock, HealthResponse, JobListQuery, JobListResponse, LogTailQuery, LogTailResponse, PromptArgumentInfo,
+    PromptInfo, PromptListResponse, PromptMessage, PromptResponse, ResourceContents, ResourceInfo,
+    ResourceListResponse, ResourceReadResponse, ToolCallRequest, ToolCallResponse, ToolInfo, ToolListResponse,
+    ToolStatsResponse, ToolUsage, UpstreamHealth, ValidationErrorResponse, Violation,
+};
 
 #[derive(OpenApi)]
 #[openapi(
@@ -12,17 +17,44 @@ use crate::{ContentBlock, HealthResponse, ToolCallRequest, ToolCallResponse, Too
     components(
         schemas(
             HealthResponse,
+            UpstreamHealth,
+            BreakerStatus,
+            BreakerState,
             ToolListResponse,
             ToolInfo,
             ToolCallRequest,
             ToolCallResponse,
+            ValidationErrorResponse,
+            Violation,
             ContentBlock,
+            LogTailQuery,
+            LogTailResponse,
+            ToolUsage,
+            ToolStatsResponse,
+            JobListQuery,
+            JobListResponse,
+            PromptInfo,
+            PromptArgumentInfo,
+            PromptListResponse,
+            PromptMessage,
+            PromptResponse,
+            ResourceInfo,
+            ResourceListResponse,
+            ResourceContents,
+            ResourceReadResponse,
             ApiError
         )
     ),
     tags(
         (name = "health", description = "Health check endpoints"),
         (name = "tools", description = "MCP tool management and execution"),
+        (name = "openai", description = "OpenAI function-calling compatibility"),
+        (name = "anthropic", description = "Anthropic tool-use compatibility"),
+        (name = "prompts", description = "MCP prompt templates"),
+        (name = "resources", description = "MCP resources"),
+        (name = "logs", description = "Recent MCP server log lines"),
+        (name = "stats", description = "Tool usage statistics"),
+        (name = "jobs", description = "Background tool calls and their results"),
         (name = "documentation", description = "API documentation endpoints")
     ),
     info(
@@ -87,10 +119,10 @@ pub async fn openapi_handler() -> Json<Value> {
                 "get": {
                     "tags": ["health"],
                     "summary": "Health check",
-                    "description": "Returns the health status and version of the service",
+                    "description": "Returns the status and version of the service and of the MCP server behind it",
                     "responses": {
                         "200": {
-                            "description": "Service is healthy",
+                            "description": "The bridge answers; `status` is `degraded` while the circuit breaker is not closed and `unhealthy` when the MCP server cannot be reached",
                             "content": {
                                 "application/json": {
                                     "schema": {
@@ -106,7 +138,32 @@ pub async fn openapi_handler() -> Json<Value> {
                 "get": {
                     "tags": ["tools"],
                     "summary": "List tools",
-                    "description": "Returns a list of all available MCP tools with their descriptions and input schemas",
+                    "description": "Returns a list of all available MCP tools with their descriptions, input schemas, tags and annotations",
+                    "parameters": [
+                        { "name": "tag", "in": "query", "schema": { "type": "string" }, "description": "Comma-separated tags a tool must all have, e.g. read-only,network" }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "List of available tools",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ToolListResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "500": {
+                            "description": "Internal server error"
+                        }
+                    }
+                }
+            },
+            "/tools/refresh": {
+                "post": {
+                    "tags": ["tools"],
+                    "summary": "Refresh tools",
+                    "description": "Drops the cached tool list and returns a freshly fetched one",
                     "responses": {
                         "200": {
                             "description": "List of available tools",
@@ -128,7 +185,7 @@ pub async fn openapi_handler() -> Json<Value> {
                 "post": {
                     "tags": ["tools"],
                     "summary": "Call tool",
-                    "description": "Execute a specific MCP tool with the provided arguments",
+                    "description": "Execute a specific MCP tool with the provided arguments. Send `Accept: text/plain` or `Accept: text/markdown` to get just the tool's text instead of the JSON envelope; failures then come back as 502.",
                     "requestBody": {
                         "required": true,
                         "content": {
@@ -147,8 +204,451 @@ pub async fn openapi_handler() -> Json<Value> {
                                     "schema": {
                                         "$ref": "#/components/schemas/ToolCallResponse"
                                     }
+                                },
+                                "text/plain": {
+                                    "schema": {
+                                        "type": "string"
+                                    }
+                                },
+                                "text/markdown": {
+                                    "schema": {
+                                        "type": "string"
+                                    }
+                                }
+                            }
+                        },
+                        "502": {
+                            "description": "Tool call failed (text/plain and text/markdown only)"
+                        },
+                        "422": {
+                            "description": "Arguments do not match the tool's input schema",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ValidationErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "504": {
+                            "description": "The route's timeout passed before the tool finished; the MCP server stops the call too",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ToolCallResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/tools/{name}/invoke": {
+                "post": {
+                    "tags": ["tools"],
+                    "summary": "Invoke tool",
+                    "description": "Calls one tool with the request body as its arguments. Answers like /tools/call, including validation and content negotiation.",
+                    "parameters": [
+                        { "name": "name", "in": "path", "required": true, "schema": { "type": "string" }, "description": "Tool name" }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "additionalProperties": true
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Tool execution result",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ToolCallResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "422": {
+                            "description": "Arguments do not match the tool's input schema"
+                        }
+                    }
+                }
+            },
+            "/manifest": {
+                "get": {
+                    "tags": ["tools"],
+                    "summary": "Tool manifest",
+                    "description": "The live tool list with schemas and per-tool endpoint URLs. `format=langchain` returns an OpenAPI spec with one operation per tool for LangChain's OpenAPI toolkit; `format=llamaindex` returns FunctionTool metadata with endpoints.",
+                    "parameters": [
+                        { "name": "format", "in": "query", "schema": { "type": "string", "enum": ["generic", "langchain", "llamaindex"] }, "description": "Manifest flavour (default: generic)" }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Tool manifest",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Unknown format"
+                        },
+                        "500": {
+                            "description": "Internal server error"
+                        }
+                    }
+                }
+            },
+            "/webhooks/{name}": {
+                "post": {
+                    "tags": ["tools"],
+                    "summary": "Trigger tool from webhook",
+                    "description": "Calls a tool with arguments taken from an arbitrary JSON payload, using the webhook's JSONPath mapping from --webhooks. Webhooks without a mapping call the tool of the same name with the payload object as its arguments. Answers like /tools/call, including content negotiation.",
+                    "parameters": [
+                        { "name": "name", "in": "path", "required": true, "schema": { "type": "string" }, "description": "Webhook name, or a tool name when unmapped" }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "additionalProperties": true
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Tool execution result",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ToolCallResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Payload could not be mapped to arguments"
+                        },
+                        "422": {
+                            "description": "Mapped arguments do not match the tool's input schema"
+                        }
+                    }
+                }
+            },
+            "/openai/tools": {
+                "get": {
+                    "tags": ["openai"],
+                    "summary": "List tools in OpenAI format",
+                    "description": "Returns the tools as OpenAI function tools, ready to pass as the `tools` parameter of a chat completion",
+                    "responses": {
+                        "200": {
+                            "description": "OpenAI function tools",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "properties": {
+                                                "type": { "type": "string", "example": "function" },
+                                                "function": {
+                                                    "type": "object",
+                                                    "properties": {
+                                                        "name": { "type": "string" },
+                                                        "description": { "type": "string" },
+                                                        "parameters": { "type": "object" }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "500": {
+                            "description": "Internal server error"
+                        }
+                    }
+                }
+            },
+            "/anthropic/tools": {
+                "get": {
+                    "tags": ["anthropic"],
+                    "summary": "List tools in Anthropic format",
+                    "description": "Returns the tools in the Anthropic Messages API tool-use format, ready to pass as the `tools` parameter",
+                    "responses": {
+                        "200": {
+                            "description": "Anthropic tool definitions",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "required": ["name", "description", "input_schema"],
+                                            "properties": {
+                                                "name": { "type": "string", "example": "system_info" },
+                                                "description": { "type": "string" },
+                                                "input_schema": { "type": "object" }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "500": {
+                            "description": "Internal server error"
+                        }
+                    }
+                }
+            },
+            "/openai/execute": {
+                "post": {
+                    "tags": ["openai"],
+                    "summary": "Execute OpenAI tool calls",
+                    "description": "Runs one `tool_calls` entry, or an array of them, and returns the matching `tool` messages. Failures are reported in the message content so the model can react to them.",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "oneOf": [
+                                        { "$ref": "#/components/schemas/OpenAiToolCall" },
+                                        { "type": "array", "items": { "$ref": "#/components/schemas/OpenAiToolCall" } }
+                                    ]
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Tool messages, one per call",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "oneOf": [
+                                            { "$ref": "#/components/schemas/OpenAiToolMessage" },
+                                            { "type": "array", "items": { "$ref": "#/components/schemas/OpenAiToolMessage" } }
+                                        ]
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/prompts": {
+                "get": {
+                    "tags": ["prompts"],
+                    "summary": "List prompts",
+                    "description": "Returns the prompt templates the MCP server offers, with their arguments",
+                    "responses": {
+                        "200": {
+                            "description": "List of available prompts",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/PromptListResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "500": {
+                            "description": "Internal server error"
+                        }
+                    }
+                }
+            },
+            "/prompts/{name}": {
+                "get": {
+                    "tags": ["prompts"],
+                    "summary": "Get prompt",
+                    "description": "Renders a prompt; its arguments are passed as query parameters",
+                    "parameters": [
+                        { "name": "name", "in": "path", "required": true, "schema": { "type": "string" }, "description": "Prompt name" }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Rendered prompt",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/PromptResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "Unknown prompt or missing required argument"
+                        },
+                        "500": {
+                            "description": "Internal server error"
+                        }
+                    }
+                }
+            },
+            "/resources": {
+                "get": {
+                    "tags": ["resources"],
+                    "summary": "List resources",
+                    "description": "Returns the resources the MCP server can read",
+                    "responses": {
+                        "200": {
+                            "description": "List of available resources",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ResourceListResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "500": {
+                            "description": "Internal server error"
+                        }
+                    }
+                }
+            },
+            "/resources/read": {
+                "get": {
+                    "tags": ["resources"],
+                    "summary": "Read resource",
+                    "description": "Returns the contents of a resource",
+                    "parameters": [
+                        { "name": "uri", "in": "query", "required": true, "schema": { "type": "string" }, "description": "Resource URI, as listed by /resources" }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Resource contents",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ResourceReadResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "Unknown resource"
+                        },
+                        "500": {
+                            "description": "Internal server error"
+                        }
+                    }
+                }
+            },
+            "/logs": {
+                "get": {
+                    "tags": ["logs"],
+                    "summary": "Tail server logs",
+                    "description": "Returns recent MCP server log lines, tagged with session, request and tool, for debugging without shell access. Needs the server's admin token in the bridge's MCP_ADMIN_TOKEN",
+                    "parameters": [
+                        { "name": "limit", "in": "query", "schema": { "type": "integer" }, "description": "Maximum number of lines (default: 100)" },
+                        { "name": "session_id", "in": "query", "schema": { "type": "string" }, "description": "Only lines logged for this MCP session" },
+                        { "name": "request_id", "in": "query", "schema": { "type": "string" }, "description": "Only lines logged while handling this JSON-RPC request id" },
+                        { "name": "tool", "in": "query", "schema": { "type": "string" }, "description": "Only lines logged by this tool" },
+                        { "name": "level", "in": "query", "schema": { "type": "string", "enum": ["debug", "info", "notice", "warning", "error", "critical", "alert", "emergency"] }, "description": "Least severe level to include" }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Recent log lines, oldest first",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/LogTailResponse"
+                                    }
                                 }
                             }
+                        },
+                        "500": {
+                            "description": "The MCP server could not be reached"
+                        }
+                    }
+                }
+            },
+            "/stats/tools": {
+                "get": {
+                    "tags": ["stats"],
+                    "summary": "Tool usage statistics",
+                    "description": "Call counts, error rates, average latency and time of the latest call for every tool called since the MCP server started, most called first",
+                    "responses": {
+                        "200": {
+                            "description": "Usage per tool",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ToolStatsResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "500": {
+                            "description": "The MCP server could not be reached"
+                        }
+                    }
+                }
+            },
+            "/jobs": {
+                "get": {
+                    "tags": ["jobs"],
+                    "summary": "List background jobs",
+                    "description": "Tool calls submitted to the MCP server with jobs/submit, newest first, with their status and, once finished, their result or error. Jobs survive server restarts when the server keeps them in a database file.",
+                    "parameters": [
+                        { "name": "status", "in": "query", "schema": { "type": "string", "enum": ["queued", "running", "completed", "failed"] }, "description": "Only jobs in this state" },
+                        { "name": "tool", "in": "query", "schema": { "type": "string" }, "description": "Only calls of this tool" },
+                        { "name": "limit", "in": "query", "schema": { "type": "integer" }, "description": "Maximum number of jobs (default: 50)" }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Matching jobs",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/JobListResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "500": {
+                            "description": "The MCP server could not be reached"
+                        }
+                    }
+                }
+            },
+            "/jobs/{id}": {
+                "get": {
+                    "tags": ["jobs"],
+                    "summary": "Get a background job",
+                    "parameters": [
+                        { "name": "id", "in": "path", "required": true, "schema": { "type": "string" }, "description": "Job id returned by jobs/submit" }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "The job",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/Job"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "No job with that id"
+                        },
+                        "500": {
+                            "description": "The MCP server could not be reached"
                         }
                     }
                 }
@@ -177,10 +677,11 @@ pub async fn openapi_handler() -> Json<Value> {
             "schemas": {
                 "HealthResponse": {
                     "type": "object",
-                    "required": ["status", "version"],
+                    "required": ["status", "version", "upstream"],
                     "properties": {
                         "status": {
                             "type": "string",
+                            "enum": ["healthy", "degraded", "unhealthy"],
                             "description": "Service status",
                             "example": "healthy"
                         },
@@ -188,6 +689,32 @@ pub async fn openapi_handler() -> Json<Value> {
                             "type": "string",
                             "description": "Service version",
                             "example": "0.1.0"
+                        },
+                        "upstream": {
+                            "$ref": "#/components/schemas/UpstreamHealth"
+                        }
+                    }
+                },
+                "UpstreamHealth": {
+                    "type": "object",
+                    "required": ["reachable", "last_tools_list_at", "circuit_breaker"],
+                    "properties": {
+                        "reachable": { "type": "boolean", "description": "Whether the MCP server answered just now" },
+                        "version": { "type": "string", "description": "The MCP server's version, when it answered", "example": "0.1.0" },
+                        "error": { "type": "string", "description": "Why the MCP server could not be reached" },
+                        "last_tools_list_at": {
+                            "type": "string",
+                            "format": "date-time",
+                            "nullable": true,
+                            "description": "When the MCP server last answered tools/list"
+                        },
+                        "circuit_breaker": {
+                            "type": "object",
+                            "required": ["state", "consecutive_failures"],
+                            "properties": {
+                                "state": { "type": "string", "enum": ["closed", "open", "half_open"] },
+                                "consecutive_failures": { "type": "integer", "description": "Requests in a row that got no answer" }
+                            }
                         }
                     }
                 },
@@ -206,7 +733,7 @@ pub async fn openapi_handler() -> Json<Value> {
                 },
                 "ToolInfo": {
                     "type": "object",
-                    "required": ["name", "description", "input_schema"],
+                    "required": ["name", "description", "inputSchema"],
                     "properties": {
                         "name": {
                             "type": "string",
@@ -218,9 +745,29 @@ pub async fn openapi_handler() -> Json<Value> {
                             "description": "Tool description",
                             "example": "Get system information"
                         },
-                        "input_schema": {
+                        "inputSchema": {
                             "type": "object",
                             "description": "JSON schema for tool input"
+                        },
+                        "outputSchema": {
+                            "type": "object",
+                            "description": "JSON schema of the tool's structured output, if it declares one"
+                        },
+                        "tags": {
+                            "type": "array",
+                            "items": { "type": "string", "enum": ["read-only", "destructive", "network", "slow"] },
+                            "description": "What to expect from the tool"
+                        },
+                        "annotations": {
+                            "type": "object",
+                            "description": "MCP tool annotations",
+                            "properties": {
+                                "title": { "type": "string" },
+                                "readOnlyHint": { "type": "boolean" },
+                                "destructiveHint": { "type": "boolean" },
+                                "idempotentHint": { "type": "boolean" },
+                                "openWorldHint": { "type": "boolean" }
+                            }
                         }
                     }
                 },
@@ -258,21 +805,256 @@ pub async fn openapi_handler() -> Json<Value> {
                         "error": {
                             "type": "string",
                             "description": "Error message (if unsuccessful)"
+                        },
+                        "request_id": {
+                            "type": "string",
+                            "description": "The request's X-Request-Id, to find its log lines by"
                         }
                     }
                 },
+                "ValidationErrorResponse": {
+                    "type": "object",
+                    "required": ["success", "error", "violations"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean",
+                            "description": "Always false"
+                        },
+                        "error": {
+                            "type": "string",
+                            "example": "Invalid arguments for tool 'system_info'"
+                        },
+                        "violations": {
+                            "type": "array",
+                            "description": "Each way the arguments break the tool's input schema",
+                            "items": {
+                                "type": "object",
+                                "required": ["path", "message"],
+                                "properties": {
+                                    "path": {
+                                        "type": "string",
+                                        "description": "JSON pointer into the arguments",
+                                        "example": "/detailed"
+                                    },
+                                    "message": {
+                                        "type": "string",
+                                        "example": "expected boolean, got string"
+                                    }
+                                }
+                            }
+                        },
+                        "request_id": {
+                            "type": "string",
+                            "description": "The request's X-Request-Id, to find its log lines by"
+                        }
+                    }
+                },
+                "OpenAiToolCall": {
+                    "type": "object",
+                    "required": ["id", "function"],
+                    "properties": {
+                        "id": { "type": "string", "example": "call_abc123" },
+                        "type": { "type": "string", "example": "function" },
+                        "function": {
+                            "type": "object",
+                            "required": ["name", "arguments"],
+                            "properties": {
+                                "name": { "type": "string", "example": "system_info" },
+                                "arguments": {
+                                    "type": "string",
+                                    "description": "JSON-encoded arguments, as OpenAI sends them",
+                                    "example": "{\"action\":\"get_system_info\"}"
+                                }
+                            }
+                        }
+                    }
+                },
+                "OpenAiToolMessage": {
+                    "type": "object",
+                    "required": ["role", "tool_call_id", "content"],
+                    "properties": {
+                        "role": { "type": "string", "example": "tool" },
+                        "tool_call_id": { "type": "string", "example": "call_abc123" },
+                        "content": { "type": "string" }
+                    }
+                },
+                "PromptListResponse": {
+                    "type": "object",
+                    "required": ["prompts"],
+                    "properties": {
+                        "prompts": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["name", "description", "arguments"],
+                                "properties": {
+                                    "name": { "type": "string", "example": "system_health_report" },
+                                    "description": { "type": "string" },
+                                    "arguments": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "required": ["name", "description", "required"],
+                                            "properties": {
+                                                "name": { "type": "string", "example": "focus" },
+                                                "description": { "type": "string" },
+                                                "required": { "type": "boolean" }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "PromptResponse": {
+                    "type": "object",
+                    "required": ["description", "messages"],
+                    "properties": {
+                        "description": { "type": "string" },
+                        "messages": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["role", "content"],
+                                "properties": {
+                                    "role": { "type": "string", "example": "user" },
+                                    "content": { "$ref": "#/components/schemas/ContentBlock" }
+                                }
+                            }
+                        }
+                    }
+                },
+                "ResourceListResponse": {
+                    "type": "object",
+                    "required": ["resources"],
+                    "properties": {
+                        "resources": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["uri", "name", "description"],
+                                "properties": {
+                                    "uri": { "type": "string", "example": "system://info" },
+                                    "name": { "type": "string" },
+                                    "description": { "type": "string" },
+                                    "mime_type": { "type": "string", "example": "application/json" }
+                                }
+                            }
+                        }
+                    }
+                },
+                "ResourceReadResponse": {
+                    "type": "object",
+                    "required": ["contents"],
+                    "properties": {
+                        "contents": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["uri", "text"],
+                                "properties": {
+                                    "uri": { "type": "string" },
+                                    "mime_type": { "type": "string" },
+                                    "text": { "type": "string" }
+                                }
+                            }
+                        }
+                    }
+                },
+                "LogTailResponse": {
+                    "type": "object",
+                    "required": ["entries"],
+                    "properties": {
+                        "entries": {
+                            "type": "array",
+                            "description": "Log lines, oldest first",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "timestamp": { "type": "string", "format": "date-time" },
+                                    "level": { "type": "string" },
+                                    "target": { "type": "string" },
+                                    "message": { "type": "string" },
+                                    "sessionId": { "type": "string" },
+                                    "requestId": { "type": "string" },
+                                    "tool": { "type": "string" }
+                                }
+                            }
+                        }
+                    }
+                },
+                "ToolStatsResponse": {
+                    "type": "object",
+                    "required": ["tools"],
+                    "properties": {
+                        "tools": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/ToolUsage" }
+                        }
+                    }
+                },
+                "JobListResponse": {
+                    "type": "object",
+                    "required": ["jobs"],
+                    "properties": {
+                        "jobs": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/Job" }
+                        }
+                    }
+                },
+                "Job": {
+                    "type": "object",
+                    "required": ["id", "tool", "arguments", "status", "createdAt"],
+                    "properties": {
+                        "id": { "type": "string" },
+                        "tool": { "type": "string" },
+                        "arguments": { "type": "object" },
+                        "sessionId": { "type": "string" },
+                        "status": { "type": "string", "enum": ["queued", "running", "completed", "failed"] },
+                        "result": { "type": "object", "description": "The tools/call result, once completed" },
+                        "error": { "type": "object", "description": "The JSON-RPC error, once failed" },
+                        "createdAt": { "type": "string", "format": "date-time" },
+                        "startedAt": { "type": "string", "format": "date-time" },
+                        "finishedAt": { "type": "string", "format": "date-time" }
+                    }
+                },
+                "ToolUsage": {
+                    "type": "object",
+                    "required": ["tool", "calls", "errors", "error_rate", "average_latency_ms", "last_called_at"],
+                    "properties": {
+                        "tool": { "type": "string" },
+                        "calls": { "type": "integer" },
+                        "errors": { "type": "integer" },
+                        "error_rate": { "type": "number", "description": "Share of calls that failed, from 0 to 1" },
+                        "average_latency_ms": { "type": "number" },
+                        "last_called_at": { "type": "string", "format": "date-time" }
+                    }
+                },
                 "ContentBlock": {
                     "type": "object",
                     "required": ["type"],
                     "properties": {
                         "type": {
                             "type": "string",
-                            "enum": ["text"],
+                            "enum": ["text", "json", "data"],
                             "description": "Content block type"
                         },
                         "text": {
                             "type": "string",
-                            "description": "The text content"
+                            "description": "The text content, for `text` blocks"
+                        },
+                        "json": {
+                            "description": "The structured content, for `json` blocks"
+                        },
+                        "mimeType": {
+                            "type": "string",
+                            "description": "MIME type of `data`, for `data` blocks"
+                        },
+                        "data": {
+                            "type": "string",
+                            "description": "Base64 unless the MIME type is textual, for `data` blocks"
                         }
                     }
                 }
@@ -287,6 +1069,30 @@ pub async fn openapi_handler() -> Json<Value> {
                 "name": "tools",
                 "description": "MCP tool management and execution"
             },
+            {
+                "name": "openai",
+                "description": "OpenAI function-calling compatibility"
+            },
+            {
+                "name": "anthropic",
+                "description": "Anthropic tool-use compatibility"
+            },
+            {
+                "name": "prompts",
+                "description": "MCP prompt templates"
+            },
+            {
+                "name": "resources",
+                "description": "MCP resources"
+            },
+            {
+                "name": "logs",
+                "description": "Recent MCP server log lines"
+            },
+            {
+                "name": "stats",
+                "description": "Tool usage statistics"
+            },
             {
                 "name": "documentation",
                 "description": "API documentation endpoints"
@@ -0,0 +1,72 @@
+//! `X-Request-Id` correlation. Every request gets an id, the caller's when
+//! it sends a usable one and a new UUID otherwise. The id is echoed in the
+//! response header, tags every line the bridge logs while handling the
+//! request, and is sent upstream as the JSON-RPC id, which the MCP server
+//! tags its own log lines with; `GET /logs?request_id=<id>` finds them.
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+pub const HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longer ids from callers are replaced rather than logged
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// The id of the request being handled, outside of a request `None`
+pub fn current() -> Option<String> {
+    CURRENT.try_with(String::clone).ok()
+}
+
+/// Printable ASCII without spaces, so it is safe in headers and log lines
+fn usable(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_LEN && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// Runs the request with its id set and in a span carrying it, and echoes
+/// the id in the response
+pub async fn propagate(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| usable(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let span = tracing::info_span!("request", request_id = %id, method = %request.method(), path = %request.uri().path());
+
+    let mut response = CURRENT.scope(id.clone(), next.run(request).instrument(span)).await;
+    // `usable` ids are valid header values
+    response.headers_mut().insert(HEADER, HeaderValue::from_str(&id).unwrap());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usable_ids() {
+        assert!(usable("3f2a-77"));
+        assert!(usable(&"a".repeat(MAX_LEN)));
+        assert!(!usable(""));
+        assert!(!usable("two words"));
+        assert!(!usable("line\nbreak"));
+        assert!(!usable(&"a".repeat(MAX_LEN + 1)));
+    }
+
+    #[tokio::test]
+    async fn test_current_is_set_only_inside_a_request() {
+        assert_eq!(current(), None);
+        let inside = CURRENT.scope("abc".to_string(), async { current() }).await;
+        assert_eq!(inside.as_deref(), Some("abc"));
+    }
+}
@@ -0,0 +1,33 @@
+//! The browser console at `/`: a single page, embedded in the binary, that
+//! lists tools, builds a form from each input schema and runs calls through
+//! `/tools/call`. Built only with the `ui` feature.
+
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "ui/"]
+struct Assets;
+
+/// `/` for the page and `/ui/*` for its scripts and styles
+pub fn router<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route("/", get(|| async { asset("index.html") }))
+        .route("/ui/*path", get(|Path(path): Path<String>| async move { asset(&path) }))
+}
+
+fn asset(path: &str) -> Response {
+    match Assets::get(path) {
+        Some(file) => {
+            let mime = mime_guess::from_path(path).first_or_octet_stream();
+            ([(header::CONTENT_TYPE, mime.as_ref())], file.data.into_owned()).into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
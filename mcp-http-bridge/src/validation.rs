@@ -0,0 +1,183 @@
+//! Checks tool arguments against a tool's `input_schema` before they are
+//! forwarded, so callers see what is wrong instead of whatever the plugin
+//! makes of it. Covers the JSON Schema keywords tool schemas use in practice:
+//! `type`, `properties`, `required`, `additionalProperties`, `items`, `enum`,
+//! `minimum`/`maximum` and `minLength`/`maxLength`. Anything else is ignored.
+
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// One way the arguments break the schema
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Violation {
+    /// Where in the arguments, as a JSON pointer (empty for the whole object)
+    pub path: String,
+    pub message: String,
+}
+
+/// Every violation of `schema` by `value`; empty when `value` is valid
+pub fn validate(schema: &Value, value: &Value) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    check(schema, value, "", &mut violations);
+    violations
+}
+
+fn check(schema: &Value, value: &Value, path: &str, violations: &mut Vec<Violation>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+    let mut violation = |message: String| {
+        violations.push(Violation { path: path.to_string(), message });
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+            violation(format!("expected {}, got {}", types.join(" or "), type_name(value)));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            violation(format!("must be one of {}", allowed.join(", ")));
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < minimum {
+                violation(format!("must be at least {}", minimum));
+            }
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+            if number > maximum {
+                violation(format!("must be at most {}", maximum));
+            }
+        }
+    }
+
+    if let Some(text) = value.as_str() {
+        let length = text.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+            if length < min {
+                violation(format!("must be at least {} characters", min));
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+            if length > max {
+                violation(format!("must be at most {} characters", max));
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    violation(format!("missing required property '{}'", name));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let closed = schema.get("additionalProperties") == Some(&Value::Bool(false));
+        // Sorted, so violations come out in the same order however the map is kept
+        let mut names: Vec<&String> = object.keys().collect();
+        names.sort();
+        for name in names {
+            let property = &object[name];
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property_schema) => {
+                    check(property_schema, property, &format!("{}/{}", path, name), violations)
+                }
+                None if closed => violations.push(Violation {
+                    path: format!("{}/{}", path, name),
+                    message: "unknown property".to_string(),
+                }),
+                None => {}
+            }
+        }
+    }
+
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (index, item) in array.iter().enumerate() {
+            check(items, item, &format!("{}/{}", path, index), violations);
+        }
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        // Unknown type names are not ours to reject
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "city": { "type": "string", "minLength": 2 },
+                "days": { "type": "integer", "minimum": 1, "maximum": 14 },
+                "units": { "type": "string", "enum": ["metric", "imperial"] },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["city"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_valid_arguments_pass() {
+        let arguments = json!({ "city": "Oslo", "days": 3, "units": "metric", "tags": ["a", "b"] });
+        assert!(validate(&schema(), &arguments).is_empty());
+    }
+
+    #[test]
+    fn test_violations_name_their_paths() {
+        let arguments = json!({ "days": 30, "units": "kelvin", "tags": ["a", 1], "extra": true });
+        let violations = validate(&schema(), &arguments);
+        let paths: Vec<&str> = violations.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(paths, ["", "/days", "/extra", "/tags/1", "/units"]);
+        assert_eq!(violations[0].message, "missing required property 'city'");
+        assert_eq!(violations[3].message, "expected string, got number");
+    }
+
+    #[test]
+    fn test_wrong_type_stops_further_checks() {
+        let violations = validate(&schema(), &json!({ "city": 5 }));
+        assert_eq!(violations, vec![Violation {
+            path: "/city".to_string(),
+            message: "expected string, got number".to_string(),
+        }]);
+    }
+}
@@ -0,0 +1,143 @@
+//! `POST /webhooks/{name}`: lets systems that can only send a webhook (n8n,
+//! GitHub, alerting tools) trigger a tool. Each webhook maps fields of the
+//! inbound payload to tool arguments with JSONPath; webhooks without a mapping
+//! pass a JSON object payload through as the arguments.
+
+use anyhow::{anyhow, Context as _, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Webhook mappings, read from JSON:
+///
+/// ```json
+/// {"webhooks": {"disk_alert": {"tool": "system_info",
+///                              "arguments": {"action": "get_disk_usage", "path": "$.alert.mount"}}}}
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(default)]
+    pub webhooks: HashMap<String, WebhookMapping>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookMapping {
+    /// Tool to call; defaults to the webhook's name
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// Argument name to a JSONPath into the payload (any string starting with
+    /// `$`) or to a literal value. Paths matching nothing leave the argument
+    /// out; paths matching several values pass them as an array.
+    #[serde(default)]
+    pub arguments: Map<String, Value>,
+}
+
+impl WebhookConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read webhook config {}", path.display()))?;
+        let config: WebhookConfig = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid webhook config {}", path.display()))?;
+        config.check_paths()?;
+        Ok(config)
+    }
+
+    /// Rejects malformed JSONPath expressions up front rather than on the
+    /// first delivery
+    fn check_paths(&self) -> Result<()> {
+        for (name, mapping) in &self.webhooks {
+            for (argument, spec) in &mapping.arguments {
+                if let Some(path) = json_path(spec) {
+                    jsonpath_lib::select(&Value::Null, path)
+                        .map_err(|e| anyhow!("Webhook '{}', argument '{}': invalid path {}: {}", name, argument, path, e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The tool a delivery to `name` calls, with its arguments
+    pub fn resolve(&self, name: &str, payload: &Value) -> Result<(String, Map<String, Value>), String> {
+        let Some(mapping) = self.webhooks.get(name) else {
+            return match payload {
+                Value::Object(arguments) => Ok((name.to_string(), arguments.clone())),
+                _ => Err(format!("Webhook '{}' has no mapping, so its payload must be a JSON object", name)),
+            };
+        };
+
+        let mut arguments = Map::new();
+        for (argument, spec) in &mapping.arguments {
+            let value = match json_path(spec) {
+                Some(path) => {
+                    let mut matches = jsonpath_lib::select(payload, path).map_err(|e| e.to_string())?;
+                    match matches.len() {
+                        0 => continue,
+                        1 => matches.remove(0).clone(),
+                        _ => Value::Array(matches.into_iter().cloned().collect()),
+                    }
+                }
+                None => spec.clone(),
+            };
+            arguments.insert(argument.clone(), value);
+        }
+        Ok((mapping.tool.clone().unwrap_or_else(|| name.to_string()), arguments))
+    }
+}
+
+fn json_path(spec: &Value) -> Option<&str> {
+    spec.as_str().filter(|spec| spec.starts_with('$'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> WebhookConfig {
+        serde_json::from_value(json!({
+            "webhooks": {
+                "disk_alert": {
+                    "tool": "system_info",
+                    "arguments": {
+                        "action": "get_disk_usage",
+                        "path": "$.alert.mount",
+                        "hosts": "$.targets[*].host",
+                        "missing": "$.nope"
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_mapping_pulls_arguments_from_the_payload() {
+        let payload = json!({
+            "alert": { "mount": "/var" },
+            "targets": [{ "host": "a" }, { "host": "b" }]
+        });
+        let (tool, arguments) = config().resolve("disk_alert", &payload).unwrap();
+        assert_eq!(tool, "system_info");
+        assert_eq!(Value::Object(arguments), json!({
+            "action": "get_disk_usage",
+            "path": "/var",
+            "hosts": ["a", "b"]
+        }));
+    }
+
+    #[test]
+    fn test_unmapped_webhooks_pass_objects_through() {
+        let (tool, arguments) = config().resolve("echo", &json!({ "text": "hi" })).unwrap();
+        assert_eq!(tool, "echo");
+        assert_eq!(arguments["text"], "hi");
+        assert!(config().resolve("echo", &json!([1, 2])).is_err());
+    }
+
+    #[test]
+    fn test_invalid_paths_are_rejected_on_load() {
+        let config: WebhookConfig =
+            serde_json::from_value(json!({ "webhooks": { "bad": { "arguments": { "x": "$.[" } } } })).unwrap();
+        assert!(config.check_paths().is_err());
+    }
+}
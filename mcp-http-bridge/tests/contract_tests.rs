@@ -0,0 +1,143 @@
+//! Contract tests: the bridge in front of a real mcp-server with the
+//! built-in plugins and tools, keeping context in memory. Every tool the
+//! server registers must come through `/tools` and `/tools/call` in the
+//! shapes the bridge documents, so a change on either side that the other
+//! does not follow fails here rather than in a consumer.
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use mcp_test_support::{RpcRequest, TestServer as RealServer};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+/// The server serving its REST endpoints and the bridge calling them
+async fn start() -> (RealServer, TestServer) {
+    let server = RealServer::builtin().await;
+    let mcp_client = Arc::new(mcp_http_bridge::McpClient::new(&server.serve_rest()));
+    let bridge = TestServer::new(mcp_http_bridge::create_app_with_state(mcp_http_bridge::AppState::new(mcp_client))).unwrap();
+    (server, bridge)
+}
+
+/// The tools as the server lists them over JSON-RPC
+async fn server_tools(server: &RealServer) -> Vec<Value> {
+    let listed = server.request(RpcRequest::new("tools/list")).await;
+    listed["result"]["tools"].as_array().unwrap().clone()
+}
+
+/// Arguments `schema` accepts: each required property gets its default,
+/// its first allowed value or a placeholder of its type
+fn arguments_for(schema: &Value) -> Map<String, Value> {
+    let required = schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str);
+    required
+        .map(|name| {
+            let property = &schema["properties"][name];
+            let value = if let Some(default) = property.get("default") {
+                default.clone()
+            } else if let Some(first) = property["enum"].get(0) {
+                first.clone()
+            } else {
+                match property["type"].as_str() {
+                    Some("integer") | Some("number") => json!(1),
+                    Some("boolean") => json!(false),
+                    Some("object") => json!({}),
+                    Some("array") => json!([]),
+                    _ => json!("contract"),
+                }
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_tools_lists_every_server_tool_in_the_documented_shape() {
+    let (server, bridge) = start().await;
+    let expected = server_tools(&server).await;
+    assert!(!expected.is_empty());
+
+    let response = bridge.get("/tools").await;
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    let listed = body["tools"].as_array().unwrap();
+
+    let names = |tools: &[Value]| tools.iter().map(|tool| tool["name"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+    assert_eq!(names(listed), names(&expected));
+    for (tool, original) in listed.iter().zip(&expected) {
+        let name = &tool["name"];
+        assert!(tool["description"].is_string(), "{}", name);
+        assert_eq!(tool["inputSchema"], original["inputSchema"], "{}", name);
+        assert_eq!(tool.get("outputSchema"), original.get("outputSchema"), "{}", name);
+        assert!(tool.get("input_schema").is_none() && tool.get("output_schema").is_none(), "{}", name);
+        assert_eq!(tool.get("tags").unwrap_or(&json!([])), original.get("tags").unwrap_or(&json!([])), "{}", name);
+        assert_eq!(tool.get("annotations"), original.get("annotations"), "{}", name);
+    }
+}
+
+#[tokio::test]
+async fn test_tools_call_answers_every_server_tool_in_the_documented_shape() {
+    let (server, bridge) = start().await;
+
+    for tool in server_tools(&server).await {
+        let name = tool["name"].as_str().unwrap();
+        let arguments = arguments_for(&tool["inputSchema"]);
+        let direct = server.request(RpcRequest::tool_call(name, Value::Object(arguments.clone()))).await;
+
+        let response = bridge.post("/tools/call").json(&json!({ "tool_name": name, "arguments": arguments })).await;
+        // Arguments the server's schema accepts pass the bridge's validation
+        assert_ne!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY, "{}: {}", name, response.text());
+        let body: Value = response.json();
+        assert!(body["success"].is_boolean(), "{}: {}", name, body);
+
+        if let Some(error) = direct.get("error") {
+            // Refused by the server: its status, and the reason in `error`
+            let kind = error["data"]["kind"].as_str().unwrap_or_default();
+            assert_ne!(response.status_code(), StatusCode::OK, "{} was refused ({})", name, kind);
+            assert_eq!(body["success"], false, "{}", name);
+            assert!(body["error"].is_string(), "{}: {}", name, body);
+        } else if direct["result"]["isError"] == true {
+            // Ran and failed: 200, with the failure in `error`
+            response.assert_status(StatusCode::OK);
+            assert_eq!(body["success"], false, "{}", name);
+            assert!(body["content"].is_null(), "{}: {}", name, body);
+            assert!(!body["error"].as_str().unwrap_or_default().is_empty(), "{}: {}", name, body);
+        } else {
+            // Ran: the same kinds of content blocks the server returned
+            response.assert_status(StatusCode::OK);
+            assert_eq!(body["success"], true, "{}: {}", name, body);
+            assert!(body["error"].is_null(), "{}: {}", name, body);
+            let types = |blocks: &Value| blocks.as_array().unwrap().iter().map(|block| block["type"].clone()).collect::<Vec<_>>();
+            assert_eq!(types(&body["content"]), types(&direct["result"]["content"]), "{}", name);
+            let structured = direct["result"].get("structuredContent").is_some();
+            assert_eq!(body.get("structured_content").is_some(), structured, "{}: {}", name, body);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_tools_call_refuses_unknown_tools_like_the_server() {
+    let (server, bridge) = start().await;
+    let direct = server.request(RpcRequest::tool_call("no_such_tool", json!({}))).await;
+    assert_eq!(direct["error"]["data"]["kind"], "notFound");
+
+    let response = bridge.post("/tools/call").json(&json!({ "tool_name": "no_such_tool", "arguments": {} })).await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    let body: Value = response.json();
+    assert_eq!(body["success"], false);
+    assert!(body["error"].as_str().unwrap().contains("no_such_tool"));
+}
+
+#[tokio::test]
+async fn test_health_reports_the_server_and_its_version() {
+    let (_server, bridge) = start().await;
+    bridge.get("/tools").await.assert_status(StatusCode::OK);
+
+    let response = bridge.get("/health").await;
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["status"], "healthy");
+    let upstream = &body["upstream"];
+    assert_eq!(upstream["reachable"], true);
+    assert!(upstream["version"].as_str().is_some_and(|version| version.split('.').count() == 3), "{}", upstream);
+    assert!(upstream["last_tools_list_at"].is_string(), "{}", upstream);
+    assert_eq!(upstream["circuit_breaker"]["state"], "closed");
+}
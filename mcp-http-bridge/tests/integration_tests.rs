@@ -31,7 +31,7 @@ async fn test_integration_list_tools_complete_flow() {
                     for tool in tools {
                         assert!(tool.get("name").is_some());
                         assert!(tool.get("description").is_some());
-                        assert!(tool.get("input_schema").is_some());
+                        assert!(tool.get("inputSchema").is_some());
                     }
                 }
             }
@@ -90,7 +90,8 @@ async fn test_integration_call_tool_error_handling() {
 async fn test_integration_health_check_reliability() {
     let server = common::create_test_server().await;
     
-    // Health check should always work regardless of MCP server status
+    // Health check should answer promptly regardless of MCP server status,
+    // reporting the bridge unhealthy while the server cannot be reached
     for i in 0..3 {
         let response = timeout(Duration::from_secs(2), server.get("/health")).await;
         
@@ -98,8 +99,9 @@ async fn test_integration_health_check_reliability() {
             Ok(response) => {
                 response.assert_status(axum::http::StatusCode::OK);
                 let body: Value = response.json();
-                assert_eq!(body["status"], "healthy");
+                assert_eq!(body["status"], "unhealthy");
                 assert_eq!(body["version"], "0.1.0");
+                assert_eq!(body["upstream"]["reachable"], false);
             }
             Err(_) => {
                 panic!("Health check should not timeout (attempt {})", i + 1);
@@ -340,4 +342,40 @@ async fn test_integration_concurrent_load() {
     
     // At least some requests should succeed
     assert!(success_count > 0, "At least some requests should succeed");
-}
\ No newline at end of file
+}
+/// End-to-end flow against `mcp-mock-server` (from mcp-server) with its default
+/// `echo` and `fail` tools; skipped unless `MCP_MOCK_SERVER_URL` points at one
+#[tokio::test]
+async fn test_integration_against_mock_server() {
+    let Ok(url) = std::env::var("MCP_MOCK_SERVER_URL") else {
+        println!("MCP_MOCK_SERVER_URL is not set; skipping");
+        return;
+    };
+    let server = common::create_test_server_with_url(&url).await;
+
+    let response = server.get("/tools").await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    let names: Vec<&str> = body["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"echo") && names.contains(&"fail"), "{:?}", names);
+
+    let response = server
+        .post("/tools/call")
+        .json(&json!({"tool_name": "echo", "arguments": {"message": "hi"}}))
+        .await;
+    let body: Value = response.json();
+    assert_eq!(body["success"], true);
+    assert_eq!(body["content"][0]["text"], r#"{"message":"hi"}"#);
+
+    let response = server
+        .post("/tools/call")
+        .json(&json!({"tool_name": "fail", "arguments": {}}))
+        .await;
+    let body: Value = response.json();
+    assert_eq!(body["success"], false);
+}
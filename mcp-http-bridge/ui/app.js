@@ -0,0 +1,194 @@
+// Browser console for the bridge: lists tools from /tools, builds a form from
+// each tool's inputSchema and runs it through /tools/call, showing the
+// result as it streams in.
+
+const state = { tools: [], selected: null };
+const $ = (id) => document.getElementById(id);
+
+async function loadTools(refresh = false) {
+  $("status").textContent = "Loading tools…";
+  try {
+    const response = await fetch(refresh ? "/tools/refresh" : "/tools", { method: refresh ? "POST" : "GET" });
+    if (!response.ok) throw new Error(`HTTP ${response.status}`);
+    state.tools = (await response.json()).tools;
+    state.tools.sort((a, b) => a.name.localeCompare(b.name));
+    $("status").textContent = `${state.tools.length} tools`;
+    renderToolList();
+  } catch (e) {
+    $("status").textContent = `Could not load tools: ${e.message}`;
+  }
+}
+
+function renderToolList() {
+  const filter = $("filter").value.toLowerCase();
+  const list = $("tools");
+  list.replaceChildren();
+  for (const tool of state.tools) {
+    if (filter && !`${tool.name} ${tool.description}`.toLowerCase().includes(filter)) continue;
+    const item = document.createElement("li");
+    item.textContent = tool.name;
+    item.title = tool.description;
+    if (state.selected && state.selected.name === tool.name) item.classList.add("selected");
+    item.addEventListener("click", () => selectTool(tool));
+    list.appendChild(item);
+  }
+}
+
+function selectTool(tool) {
+  state.selected = tool;
+  $("empty").hidden = true;
+  $("tool").hidden = false;
+  $("tool-name").textContent = tool.name;
+  $("tool-description").textContent = tool.description;
+  $("output").textContent = "";
+  $("output").className = "";
+  $("elapsed").textContent = "";
+  $("raw").value = "{}";
+  $("use-raw").checked = false;
+  renderFields(tool.inputSchema || {});
+  renderToolList();
+}
+
+// One input per top-level property; nested objects and arrays are edited as JSON
+function renderFields(schema) {
+  const fields = $("fields");
+  fields.replaceChildren();
+  const required = new Set(schema.required || []);
+  for (const [name, property] of Object.entries(schema.properties || {})) {
+    const field = document.createElement("div");
+    field.className = "field";
+
+    const label = document.createElement("label");
+    label.textContent = name;
+    label.htmlFor = `arg-${name}`;
+    if (required.has(name)) label.classList.add("required");
+    field.appendChild(label);
+
+    const input = inputFor(property);
+    input.id = `arg-${name}`;
+    input.dataset.name = name;
+    input.dataset.type = property.type || "string";
+    field.appendChild(input);
+
+    if (property.description) {
+      const hint = document.createElement("div");
+      hint.className = "hint";
+      hint.textContent = property.description;
+      field.appendChild(hint);
+    }
+    fields.appendChild(field);
+  }
+}
+
+function inputFor(property) {
+  if (Array.isArray(property.enum)) {
+    const select = document.createElement("select");
+    select.appendChild(new Option("", ""));
+    for (const value of property.enum) select.appendChild(new Option(String(value), JSON.stringify(value)));
+    select.dataset.enum = "true";
+    return select;
+  }
+  switch (property.type) {
+    case "boolean": {
+      const input = document.createElement("input");
+      input.type = "checkbox";
+      input.checked = property.default === true;
+      return input;
+    }
+    case "integer":
+    case "number": {
+      const input = document.createElement("input");
+      input.type = "number";
+      input.step = property.type === "integer" ? "1" : "any";
+      if (property.default !== undefined) input.value = property.default;
+      return input;
+    }
+    case "object":
+    case "array": {
+      const textarea = document.createElement("textarea");
+      textarea.rows = 4;
+      textarea.placeholder = property.type === "array" ? "[]" : "{}";
+      return textarea;
+    }
+    default: {
+      const input = document.createElement("input");
+      input.type = "text";
+      if (property.default !== undefined) input.value = property.default;
+      return input;
+    }
+  }
+}
+
+// Empty inputs are left out, so the server's defaults apply
+function collectArguments() {
+  if ($("use-raw").checked) return JSON.parse($("raw").value || "{}");
+  const args = {};
+  for (const input of $("fields").querySelectorAll("[data-name]")) {
+    const { name, type } = input.dataset;
+    if (input.dataset.enum) {
+      if (input.value !== "") args[name] = JSON.parse(input.value);
+    } else if (type === "boolean") {
+      args[name] = input.checked;
+    } else if (input.value.trim() === "") {
+      continue;
+    } else if (type === "integer" || type === "number") {
+      args[name] = Number(input.value);
+    } else if (type === "object" || type === "array") {
+      args[name] = JSON.parse(input.value);
+    } else {
+      args[name] = input.value;
+    }
+  }
+  return args;
+}
+
+async function runTool(event) {
+  event.preventDefault();
+  const output = $("output");
+  output.className = "";
+  output.textContent = "";
+
+  let args;
+  try {
+    args = collectArguments();
+  } catch (e) {
+    output.className = "error";
+    output.textContent = `Arguments are not valid JSON: ${e.message}`;
+    return;
+  }
+  $("raw").value = JSON.stringify(args, null, 2);
+
+  const started = performance.now();
+  const tick = setInterval(() => {
+    $("elapsed").textContent = `(${((performance.now() - started) / 1000).toFixed(1)}s…)`;
+  }, 100);
+  try {
+    // Plain text keeps the result readable and lets it render as it arrives
+    const response = await fetch("/tools/call", {
+      method: "POST",
+      headers: { "Content-Type": "application/json", Accept: "text/plain" },
+      body: JSON.stringify({ tool_name: state.selected.name, arguments: args }),
+    });
+    const reader = response.body.getReader();
+    const decoder = new TextDecoder();
+    for (;;) {
+      const { done, value } = await reader.read();
+      if (done) break;
+      output.textContent += decoder.decode(value, { stream: true });
+    }
+    output.textContent += decoder.decode();
+    if (!response.ok) output.className = "error";
+    $("elapsed").textContent = `(HTTP ${response.status}, ${Math.round(performance.now() - started)} ms)`;
+  } catch (e) {
+    output.className = "error";
+    output.textContent = `Request failed: ${e.message}`;
+    $("elapsed").textContent = "";
+  } finally {
+    clearInterval(tick);
+  }
+}
+
+$("filter").addEventListener("input", renderToolList);
+$("refresh").addEventListener("click", () => loadTools(true));
+$("tool-form").addEventListener("submit", runTool);
+loadTools();
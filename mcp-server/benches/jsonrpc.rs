@@ -0,0 +1,209 @@
+//! JSON-RPC parsing and dispatch through `McpServer`, to compare tool
+//! registry designs against. Each benchmark is measured in time and in heap
+//! allocations. Run with `cargo bench --bench jsonrpc`.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use mcp_server::mcp::{ContentBlock, JsonRpcRequest, McpServer, RawMessage};
+use mcp_server::tools::Tool;
+use serde_json::{json, Value};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting allocations on every thread
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Heap allocations and reallocations per iteration, instead of time
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> usize {
+        ALLOCATIONS.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, started: usize) -> usize {
+        ALLOCATIONS.load(Ordering::Relaxed) - started
+    }
+
+    fn add(&self, v1: &usize, v2: &usize) -> usize {
+        v1 + v2
+    }
+
+    fn zero(&self) -> usize {
+        0
+    }
+
+    fn to_f64(&self, value: &usize) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for Allocations {
+    fn scale_values(&self, _typical: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(&self, _typical: f64, throughput: &Throughput, values: &mut [f64]) -> &'static str {
+        let (units, unit) = match throughput {
+            Throughput::Bytes(bytes) | Throughput::BytesDecimal(bytes) => (*bytes, "allocs/byte"),
+            Throughput::Elements(elements) => (*elements, "allocs/elem"),
+        };
+        for value in values {
+            *value /= units as f64;
+        }
+        unit
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+struct Echo;
+
+#[async_trait]
+impl Tool for Echo {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn description(&self) -> &str {
+        "Returns its arguments"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({"type": "object"})
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        Ok(vec![ContentBlock::text(&serde_json::to_string(&args)?)])
+    }
+}
+
+fn message(id: u64, method: &str, params: Value) -> String {
+    json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}).to_string()
+}
+
+/// A server with the echo tool and an initialized session
+fn server(runtime: &Runtime) -> (Arc<McpServer>, String) {
+    runtime.block_on(async {
+        let server = Arc::new(McpServer::new());
+        server.register_tool(Box::new(Echo)).await;
+        let initialize = message(
+            0,
+            "initialize",
+            json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": {"name": "bench", "version": "0.1.0"}
+            }),
+        );
+        let response: Value = serde_json::from_str(&server.handle_message(&initialize).await.unwrap()).unwrap();
+        let session = response["result"]["sessionId"].as_str().unwrap().to_string();
+        server
+            .handle_session_message(Some(&session), r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .await
+            .unwrap();
+        (server, session)
+    })
+}
+
+fn parse<M: Measurement>(c: &mut Criterion<M>, name: &str) {
+    let call = message(1, "tools/call", json!({"name": "echo", "arguments": {"text": "hello", "count": 3}}));
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(call.len() as u64));
+    group.bench_function("request", |b| b.iter(|| serde_json::from_str::<JsonRpcRequest>(&call).unwrap()));
+    // As the server reads messages: the envelope in place, then id and params
+    group.bench_function("raw message", |b| b.iter(|| RawMessage::parse(&call).unwrap().into_request().unwrap()));
+    group.finish();
+}
+
+fn dispatch<M: Measurement>(c: &mut Criterion<M>, name: &str) {
+    let runtime = Runtime::new().unwrap();
+    let (server, session) = server(&runtime);
+    let list = message(1, "tools/list", json!({}));
+    let call = message(2, "tools/call", json!({"name": "echo", "arguments": {"text": "hello"}}));
+    let ping = message(3, "ping", json!({}));
+
+    let mut group = c.benchmark_group(name);
+    for (name, request) in [("ping", &ping), ("tools/list", &list), ("tools/call", &call)] {
+        group.bench_function(name, |b| {
+            b.to_async(&runtime)
+                .iter(|| async { server.handle_session_message(Some(&session), request).await.unwrap() })
+        });
+    }
+    group.finish();
+
+    // Many sessions at once, as with the HTTP transport
+    let mut group = c.benchmark_group(format!("{} concurrently", name));
+    const CALLS: usize = 64;
+    group.throughput(Throughput::Elements(CALLS as u64));
+    group.bench_function("tools/call", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let calls: Vec<_> = (0..CALLS)
+                .map(|_| {
+                    let server = server.clone();
+                    let session = session.clone();
+                    let call = call.clone();
+                    tokio::spawn(async move { server.handle_session_message(Some(&session), &call).await.unwrap() })
+                })
+                .collect();
+            for call in calls {
+                call.await.unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+fn time(c: &mut Criterion) {
+    parse(c, "parse");
+    dispatch(c, "dispatch");
+}
+
+fn allocations(c: &mut Criterion<Allocations>) {
+    parse(c, "parse allocations");
+    dispatch(c, "dispatch allocations");
+}
+
+criterion_group!(timing, time);
+criterion_group! {
+    name = allocating;
+    // Counts barely vary, which the plots cannot handle
+    config = Criterion::default().with_measurement(Allocations).without_plots();
+    targets = allocations
+}
+criterion_main!(timing, allocating);
@@ -0,0 +1,30 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    grpc_service();
+}
+
+/// Generates the tonic server and client for `McpService` from the message
+/// types in `src/grpc.rs`, without protoc; `proto/mcp.proto` describes the
+/// same service for clients in other languages
+#[cfg(feature = "grpc")]
+fn grpc_service() {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    let method = |name: &str, route: &str, input: &str, output: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::grpc::{}", input))
+            .output_type(format!("crate::grpc::{}", output))
+            .codec_path("tonic::codec::ProstCodec")
+    };
+    let service = Service::builder()
+        .name("McpService")
+        .package("mcp")
+        .method(method("list_tools", "ListTools", "ListToolsRequest", "ListToolsResponse").build())
+        .method(method("call_tool", "CallTool", "CallToolRequest", "CallToolResponse").build())
+        .method(method("stream_tool_call", "StreamToolCall", "CallToolRequest", "Content").server_streaming().build())
+        .build();
+    Builder::new().compile(&[service]);
+}
@@ -0,0 +1,185 @@
+//! Operator endpoints under `/admin` for HTTP mode: reloading plugins and
+//! the config file, switching plugins on and off, flushing caches, setting
+//! log levels, reading all logs, viewing and ending sessions and draining
+//! for shutdown. Every
+//! request needs the admin token as `Authorization: Bearer <token>`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use crate::config::ConfigWatcher;
+use crate::mcp::logs::LogQuery;
+use crate::mcp::notifications::LogLevel;
+use crate::mcp::session::same_token;
+use crate::mcp::McpServer;
+
+#[derive(Clone)]
+struct Admin {
+    server: Arc<McpServer>,
+    token: Arc<str>,
+    /// Notified once draining starts, for the HTTP server to shut down
+    shutdown: Arc<Notify>,
+    /// The watched config file, if the server has one
+    config: Option<Arc<ConfigWatcher>>,
+}
+
+/// The `/admin` routes, accepting requests that carry `token`. `shutdown`
+/// is notified when an operator drains the server.
+pub fn router(server: Arc<McpServer>, token: &str, shutdown: Arc<Notify>, config: Option<Arc<ConfigWatcher>>) -> Router {
+    let admin = Admin { server, token: token.into(), shutdown, config };
+    Router::new()
+        .route("/admin/sessions", get(list_sessions))
+        .route("/admin/sessions/:id", delete(end_session))
+        .route("/admin/plugins", get(list_plugins))
+        .route("/admin/plugins/:name/enable", post(enable_plugin))
+        .route("/admin/plugins/:name/disable", post(disable_plugin))
+        .route("/admin/reload", post(reload))
+        .route("/admin/config", get(show_config))
+        .route("/admin/config/reload", post(reload_config))
+        .route("/admin/caches/flush", post(flush_caches))
+        .route("/admin/logging", post(set_log_level))
+        .route("/admin/logs", get(tail_logs))
+        .route("/admin/drain", post(drain))
+        .route_layer(middleware::from_fn_with_state(admin.clone(), authorize))
+        .with_state(admin)
+}
+
+/// The admin token from `MCP_ADMIN_TOKEN`; without one there is no admin API
+pub fn token_from_env() -> Option<String> {
+    std::env::var("MCP_ADMIN_TOKEN").ok().filter(|token| !token.is_empty())
+}
+
+async fn authorize<B>(State(admin): State<Admin>, request: Request<B>, next: Next<B>) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if same_token(token, &admin.token) => next.run(request).await,
+        _ => {
+            warn!("Refused unauthorized admin request to {}", request.uri().path());
+            StatusCode::UNAUTHORIZED.into_response()
+        }
+    }
+}
+
+async fn list_sessions(State(admin): State<Admin>) -> Response {
+    Json(json!({ "sessions": admin.server.list_sessions().await })).into_response()
+}
+
+async fn end_session(State(admin): State<Admin>, Path(id): Path<String>) -> StatusCode {
+    if admin.server.end_session(&id).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+async fn list_plugins(State(admin): State<Admin>) -> Response {
+    let plugins: Vec<_> = admin
+        .server
+        .plugin_states()
+        .await
+        .into_iter()
+        .map(|(name, enabled)| json!({ "name": name, "enabled": enabled }))
+        .collect();
+    Json(json!({ "plugins": plugins })).into_response()
+}
+
+async fn enable_plugin(State(admin): State<Admin>, Path(name): Path<String>) -> Response {
+    set_plugin_enabled(&admin, &name, true).await
+}
+
+async fn disable_plugin(State(admin): State<Admin>, Path(name): Path<String>) -> Response {
+    set_plugin_enabled(&admin, &name, false).await
+}
+
+async fn set_plugin_enabled(admin: &Admin, name: &str, enabled: bool) -> Response {
+    if admin.server.set_plugin_enabled(name, enabled).await {
+        Json(json!({ "name": name, "enabled": enabled })).into_response()
+    } else {
+        (StatusCode::NOT_FOUND, format!("Plugin not found: {}", name)).into_response()
+    }
+}
+
+async fn reload(State(admin): State<Admin>) -> Response {
+    match admin.server.reload_plugins().await {
+        Ok(plugins) => Json(json!({ "reloaded": plugins })).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Plugin reload failed: {}", e)).into_response(),
+    }
+}
+
+/// The settings in effect and what the last reload of the file did
+async fn show_config(State(admin): State<Admin>) -> Response {
+    match &admin.config {
+        Some(config) => Json(json!({
+            "path": config.path(),
+            "config": config.config().masked(),
+            "lastReload": config.last_report(),
+        }))
+        .into_response(),
+        None => no_config_file(),
+    }
+}
+
+/// Reloads the config file now rather than when the watcher next looks;
+/// a file that is invalid as a whole gets `422 Unprocessable Entity`
+async fn reload_config(State(admin): State<Admin>) -> Response {
+    let Some(config) = &admin.config else {
+        return no_config_file();
+    };
+    let report = config.reload();
+    let status = if report.errors.is_empty() { StatusCode::OK } else { StatusCode::UNPROCESSABLE_ENTITY };
+    (status, Json(report)).into_response()
+}
+
+fn no_config_file() -> Response {
+    (StatusCode::NOT_FOUND, "The server was started without a config file").into_response()
+}
+
+async fn flush_caches(State(admin): State<Admin>) -> StatusCode {
+    admin.server.flush_caches().await;
+    StatusCode::NO_CONTENT
+}
+
+#[derive(Deserialize)]
+struct TargetLevel {
+    target: String,
+    level: LogLevel,
+}
+
+/// Logs one target, e.g. `mcp_server::plugins::http`, from the given level
+/// up, answering with the resulting filter
+async fn set_log_level(State(admin): State<Admin>, Json(body): Json<TargetLevel>) -> Response {
+    match admin.server.set_log_target_level(&body.target, body.level) {
+        Ok(filter) => Json(json!({ "filter": filter })).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// Recent log lines of all sessions, filtered as `logs/tail` filters them
+async fn tail_logs(State(admin): State<Admin>, Query(query): Query<LogQuery>) -> Response {
+    Json(json!({ "entries": admin.server.logs().tail(&query) })).into_response()
+}
+
+/// Stops new sessions and shuts the HTTP server down once the requests in
+/// flight are answered
+async fn drain(State(admin): State<Admin>) -> (StatusCode, Json<serde_json::Value>) {
+    admin.server.drain();
+    let sessions = admin.server.list_sessions().await.len();
+    info!("Draining for shutdown with {} sessions open", sessions);
+    admin.shutdown.notify_one();
+    (StatusCode::ACCEPTED, Json(json!({ "draining": true, "sessions": sessions })))
+}
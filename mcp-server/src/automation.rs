@@ -0,0 +1,428 @@
+use anyhow::{ensure, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::mcp::events::EventSubscription;
+use crate::mcp::{McpServer, Priority, ToolCallMeta, ToolCallParams};
+
+/// Source of the `automation.completed` and `automation.failed` events
+/// published after every run
+const SOURCE: &str = "automation";
+
+/// An automation rule: when the trigger fires, call the actions' tools in
+/// order, stopping at the first that fails
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    /// Written as a map with one key, e.g. `trigger: { schedule: { every_secs: 60 } }`
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub trigger: Trigger,
+    pub actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// An event on the bus whose topic matches `topic`, e.g. `motion.*`
+    Event {
+        topic: String,
+        /// Only events from this plugin
+        #[serde(default)]
+        source: Option<String>,
+    },
+    /// A number in a tool's result crossing a threshold
+    Metric(MetricTrigger),
+    /// Every `every_secs` seconds
+    Schedule { every_secs: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MetricTrigger {
+    /// Tool whose result holds the metric
+    #[serde(default = "default_metric_tool")]
+    pub tool: String,
+    #[serde(default)]
+    pub arguments: Map<String, Value>,
+    /// Dotted path to the number in the tool's result, e.g. `cpu_usage`
+    pub field: String,
+    #[serde(default)]
+    pub above: Option<f64>,
+    #[serde(default)]
+    pub below: Option<f64>,
+    /// How long the threshold must stay crossed before the rule fires
+    #[serde(default)]
+    pub for_secs: u64,
+    /// How often the tool is called
+    #[serde(default = "default_metric_interval")]
+    pub every_secs: u64,
+}
+
+fn default_metric_tool() -> String {
+    "system_info".to_string()
+}
+
+fn default_metric_interval() -> u64 {
+    60
+}
+
+/// A tool call. String arguments may contain `${path}` placeholders, filled
+/// from what triggered the rule: `event` (with `topic`, `source` and `data`),
+/// `value` for metrics, `rule`, and `previous`, the result of the action
+/// before. An argument that is only a placeholder keeps the value's type.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Action {
+    pub tool: String,
+    #[serde(default)]
+    pub arguments: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct RuleFile {
+    rules: Vec<Rule>,
+}
+
+impl Rule {
+    /// Rules from a YAML file holding a `rules:` list
+    pub fn load(path: &Path) -> Result<Vec<Self>> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read automation rules from {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid automation rules in {}", path.display()))
+    }
+
+    /// Rules from the file `MCP_AUTOMATION_RULES` names, if set and valid
+    pub fn from_env() -> Vec<Self> {
+        let Ok(path) = std::env::var("MCP_AUTOMATION_RULES") else {
+            return Vec::new();
+        };
+        match Self::load(Path::new(&path)) {
+            Ok(rules) => rules,
+            Err(e) => {
+                warn!("Ignoring MCP_AUTOMATION_RULES: {:#}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    fn parse(text: &str) -> Result<Vec<Self>> {
+        let file: RuleFile = serde_yaml::from_str(text)?;
+        for rule in &file.rules {
+            rule.validate().with_context(|| format!("Rule {}", rule.name))?;
+        }
+        Ok(file.rules)
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(!self.actions.is_empty(), "has no actions");
+        match &self.trigger {
+            Trigger::Event { .. } => {}
+            Trigger::Metric(metric) => {
+                ensure!(metric.above.is_some() || metric.below.is_some(), "metric trigger needs above or below");
+                ensure!(metric.every_secs > 0, "every_secs must be positive");
+            }
+            Trigger::Schedule { every_secs } => ensure!(*every_secs > 0, "every_secs must be positive"),
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates rules in the background against the server's event bus and
+/// tools, one task per rule
+pub struct RuleEngine {
+    server: Arc<McpServer>,
+    rules: Vec<Rule>,
+}
+
+impl RuleEngine {
+    pub fn new(server: Arc<McpServer>, rules: Vec<Rule>) -> Self {
+        Self { server, rules }
+    }
+
+    pub fn spawn(self) -> Vec<JoinHandle<()>> {
+        info!("Starting automation with {} rules", self.rules.len());
+        self.rules
+            .into_iter()
+            .map(|rule| {
+                // Subscribe now, so no event published after this returns is missed
+                let subscription = match &rule.trigger {
+                    Trigger::Event { topic, .. } => Some(self.server.events().subscribe(topic)),
+                    _ => None,
+                };
+                tokio::spawn(run_rule(self.server.clone(), rule, subscription))
+            })
+            .collect()
+    }
+}
+
+async fn run_rule(server: Arc<McpServer>, rule: Rule, subscription: Option<EventSubscription>) {
+    match (&rule.trigger, subscription) {
+        (Trigger::Event { source, .. }, Some(mut subscription)) => {
+            while let Some(event) = subscription.recv().await {
+                // A rule reacting to its own runs would never stop
+                if event.source == SOURCE && event.data["rule"] == rule.name {
+                    continue;
+                }
+                if source.as_ref().is_some_and(|source| *source != event.source) {
+                    continue;
+                }
+                fire(&server, &rule, json!({ "event": event })).await;
+            }
+        }
+        (Trigger::Schedule { every_secs }, _) => {
+            let mut ticker = tokio::time::interval(Duration::from_secs(*every_secs));
+            // The first tick is immediate; the rule first fires one interval in
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                fire(&server, &rule, json!({})).await;
+            }
+        }
+        (Trigger::Metric(metric), _) => {
+            let mut ticker = tokio::time::interval(Duration::from_secs(metric.every_secs));
+            let mut threshold = Threshold::default();
+            loop {
+                ticker.tick().await;
+                let value = match read_metric(&server, metric).await {
+                    Ok(value) => value,
+                    Err(e) => {
+                        warn!("Rule {} could not read {}: {:#}", rule.name, metric.field, e);
+                        continue;
+                    }
+                };
+                debug!("Rule {} read {} = {}", rule.name, metric.field, value);
+                if threshold.update(metric, value, Utc::now()) {
+                    fire(&server, &rule, json!({ "value": value })).await;
+                }
+            }
+        }
+        (Trigger::Event { .. }, None) => {}
+    }
+}
+
+async fn read_metric(server: &McpServer, metric: &MetricTrigger) -> Result<f64> {
+    let params = ToolCallParams { name: metric.tool.clone(), arguments: metric.arguments.clone().into_iter().collect(), meta: Some(ToolCallMeta { priority: Priority::Background, ..Default::default() }) };
+    let result = server
+        .call_tool(None, params)
+        .await
+        .map_err(|e| anyhow::anyhow!("{} failed: {}", metric.tool, e.message))?;
+    lookup(&structured(&result), &metric.field)
+        .and_then(Value::as_f64)
+        .with_context(|| format!("{} returned no number at {}", metric.tool, metric.field))
+}
+
+/// Runs a rule's actions and publishes how it went
+async fn fire(server: &McpServer, rule: &Rule, mut scope: Value) {
+    info!("Rule {} triggered", rule.name);
+    scope["rule"] = json!(rule.name);
+    for (index, action) in rule.actions.iter().enumerate() {
+        let arguments = action.arguments.iter().map(|(key, value)| (key.clone(), render(value, &scope))).collect();
+        match server.call_tool(None, ToolCallParams { name: action.tool.clone(), arguments, meta: Some(ToolCallMeta { priority: Priority::Background, ..Default::default() }) }).await {
+            Ok(result) => scope["previous"] = structured(&result),
+            Err(e) => {
+                warn!("Rule {} stopped at action {} ({}): {}", rule.name, index + 1, action.tool, e.message);
+                server.events().publish(
+                    SOURCE,
+                    "automation.failed",
+                    json!({ "rule": rule.name, "action": index, "tool": action.tool, "error": e }),
+                );
+                return;
+            }
+        }
+    }
+    server.events().publish(
+        SOURCE,
+        "automation.completed",
+        json!({ "rule": rule.name, "result": scope["previous"] }),
+    );
+}
+
+/// Whether a metric rule fires, tracked between readings so that it fires
+/// once per crossing
+#[derive(Debug, Default)]
+struct Threshold {
+    crossed_since: Option<DateTime<Utc>>,
+    fired: bool,
+}
+
+impl Threshold {
+    fn update(&mut self, metric: &MetricTrigger, value: f64, now: DateTime<Utc>) -> bool {
+        let crossed = metric.above.is_some_and(|above| value > above) || metric.below.is_some_and(|below| value < below);
+        if !crossed {
+            self.crossed_since = None;
+            self.fired = false;
+            return false;
+        }
+        let since = *self.crossed_since.get_or_insert(now);
+        if self.fired || now - since < chrono::Duration::seconds(metric.for_secs as i64) {
+            return false;
+        }
+        self.fired = true;
+        true
+    }
+}
+
+/// The structured part of a `tools/call` result, or its text, parsed if it
+/// is JSON
+fn structured(result: &Value) -> Value {
+    let content = result["content"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if let Some(block) = content.iter().find(|block| block["type"] == "json") {
+        return block["json"].clone();
+    }
+    match content.iter().find_map(|block| block["text"].as_str()) {
+        Some(text) => serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string())),
+        None => Value::Null,
+    }
+}
+
+/// The value at a dotted path such as `event.data.room` or `disks.0.usage`
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => value.get(key),
+    })
+}
+
+/// `value` with the `${path}` placeholders in its strings filled from `scope`
+fn render(value: &Value, scope: &Value) -> Value {
+    match value {
+        Value::String(text) => render_text(text, scope),
+        Value::Array(items) => Value::Array(items.iter().map(|item| render(item, scope)).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(key, item)| (key.clone(), render(item, scope))).collect()),
+        other => other.clone(),
+    }
+}
+
+fn render_text(text: &str, scope: &Value) -> Value {
+    let whole = text.strip_prefix("${").and_then(|rest| rest.strip_suffix('}'));
+    if let Some(path) = whole.filter(|path| !path.contains('}')) {
+        return lookup(scope, path).cloned().unwrap_or(Value::Null);
+    }
+    let mut rendered = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match lookup(scope, &rest[start + 2..start + length]) {
+            Some(Value::String(text)) => rendered.push_str(text),
+            Some(Value::Null) | None => {}
+            Some(other) => rendered.push_str(&other.to_string()),
+        }
+        rest = &rest[start + length + 1..];
+    }
+    rendered.push_str(rest);
+    Value::String(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+rules:
+  - name: hallway_lights
+    trigger:
+      event:
+        topic: motion.hallway
+        source: mqtt
+    actions:
+      - tool: homeassistant
+        arguments:
+          action: call_service
+          entity_id: light.hallway
+  - name: hot_cpu
+    trigger:
+      metric:
+        field: cpu_usage
+        above: 90
+        for_secs: 300
+    actions:
+      - tool: notification
+        arguments: { title: "CPU at ${value}%" }
+  - name: nightly
+    trigger:
+      schedule: { every_secs: 86400 }
+    actions:
+      - tool: system_info
+      - tool: embeddings
+        arguments: { action: add, text: "${previous.hostname}" }
+"#;
+
+    #[test]
+    fn test_parses_rules() {
+        let rules = Rule::parse(RULES).unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(
+            rules[0].trigger,
+            Trigger::Event { topic: "motion.hallway".to_string(), source: Some("mqtt".to_string()) }
+        );
+        let Trigger::Metric(metric) = &rules[1].trigger else { panic!("metric trigger") };
+        assert_eq!(metric.tool, "system_info");
+        assert_eq!(metric.above, Some(90.0));
+        assert_eq!(metric.every_secs, 60);
+        assert_eq!(rules[2].trigger, Trigger::Schedule { every_secs: 86400 });
+        assert_eq!(rules[2].actions[0].arguments, Map::new());
+
+        let invalid = "rules:\n  - name: x\n    trigger: { metric: { field: cpu_usage } }\n    actions: [{ tool: t }]";
+        assert!(format!("{:#}", Rule::parse(invalid).unwrap_err()).contains("above or below"));
+        assert!(Rule::parse("rules:\n  - name: x\n    trigger: { schedule: { every_secs: 5 } }\n    actions: []").is_err());
+    }
+
+    #[test]
+    fn test_placeholders_are_filled_from_the_trigger() {
+        let scope = json!({ "rule": "r", "value": 93.5, "event": { "data": { "room": "hall", "ids": [4, 5] } } });
+        let arguments = json!({
+            "value": "${value}",
+            "message": "Motion in ${event.data.room} (${value}), ${missing}",
+            "nested": ["${event.data.ids.1}", { "rule": "${rule}" }],
+            "count": 3,
+        });
+        assert_eq!(
+            render(&arguments, &scope),
+            json!({
+                "value": 93.5,
+                "message": "Motion in hall (93.5), ",
+                "nested": [5, { "rule": "r" }],
+                "count": 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_metric_fires_once_per_sustained_crossing() {
+        let metric = MetricTrigger {
+            tool: default_metric_tool(),
+            arguments: Map::new(),
+            field: "cpu_usage".to_string(),
+            above: Some(90.0),
+            below: None,
+            for_secs: 60,
+            every_secs: 10,
+        };
+        let mut threshold = Threshold::default();
+        let start = Utc::now();
+        let at = |secs| start + chrono::Duration::seconds(secs);
+
+        assert!(!threshold.update(&metric, 95.0, at(0)));
+        assert!(!threshold.update(&metric, 95.0, at(30)));
+        assert!(threshold.update(&metric, 95.0, at(60)));
+        assert!(!threshold.update(&metric, 99.0, at(90)));
+        assert!(!threshold.update(&metric, 50.0, at(100)));
+        assert!(!threshold.update(&metric, 95.0, at(110)));
+        assert!(threshold.update(&metric, 95.0, at(170)));
+    }
+
+    #[test]
+    fn test_structured_result() {
+        let result = json!({ "content": [{ "type": "text", "text": "{}" }, { "type": "json", "json": { "cpu_usage": 12.5 } }] });
+        assert_eq!(lookup(&structured(&result), "cpu_usage"), Some(&json!(12.5)));
+        assert_eq!(structured(&json!({ "content": [{ "type": "text", "text": "{\"a\": 1}" }] })), json!({ "a": 1 }));
+        assert_eq!(structured(&json!({ "content": [{ "type": "text", "text": "hello" }] })), json!("hello"));
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use mcp_server::mcp::framing::MessageReader;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::{ChildStdin, Command};
+use tokio::sync::{oneshot, Mutex, Semaphore};
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+#[derive(Parser)]
+#[command(name = "mcp-load-test")]
+#[command(about = "Drives tools/list and tools/call requests through an MCP server and reports latencies")]
+struct Cli {
+    /// `/mcp` endpoint of a server started with HTTP transport
+    #[arg(long, default_value = "http://127.0.0.1:8080/mcp", conflicts_with = "stdio")]
+    url: String,
+
+    /// Command that starts a server speaking MCP on stdio, e.g.
+    /// "target/release/mcp-server --stdio"; its requests are sent over
+    /// its stdin instead of HTTP
+    #[arg(long, value_name = "COMMAND")]
+    stdio: Option<String>,
+
+    /// Requests of each method to send
+    #[arg(long, default_value = "2000")]
+    requests: usize,
+
+    /// Requests in flight at once
+    #[arg(long, default_value = "32")]
+    concurrency: usize,
+
+    /// Which requests to send
+    #[arg(long, value_enum, default_value = "both")]
+    methods: Methods,
+
+    /// Tool to call
+    #[arg(long, default_value = "echo")]
+    tool: String,
+
+    /// Arguments of the tool call, as JSON
+    #[arg(long, default_value = "{}")]
+    arguments: String,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Methods {
+    List,
+    Call,
+    Both,
+}
+
+/// Sends JSON-RPC requests and returns their responses
+enum Transport {
+    Http {
+        client: reqwest::Client,
+        url: String,
+        session: Option<String>,
+    },
+    Stdio {
+        stdin: Mutex<ChildStdin>,
+        /// Requests waiting for their response, by id
+        pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>,
+    },
+}
+
+impl Transport {
+    async fn http(url: &str) -> Result<Self> {
+        let mut transport = Transport::Http { client: reqwest::Client::new(), url: url.to_string(), session: None };
+        let response = transport.initialize().await?;
+        if let Transport::Http { session, .. } = &mut transport {
+            *session = response["result"]["sessionId"].as_str().map(str::to_string);
+        }
+        transport.notify("notifications/initialized").await?;
+        Ok(transport)
+    }
+
+    async fn stdio(command: &str) -> Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next().context("Empty --stdio command")?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start {}", program))?;
+        let stdin = child.stdin.take().context("No stdin")?;
+        let mut messages = MessageReader::new(child.stdout.take().context("No stdout")?);
+        let pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>> = Arc::default();
+        let waiting = pending.clone();
+        tokio::spawn(async move {
+            // Keeps the server running until the responses stop
+            let _child = child;
+            while let Ok(Some(message)) = messages.next_message().await {
+                let Ok(response) = serde_json::from_str::<Value>(&message) else { continue };
+                let Some(id) = response["id"].as_u64() else { continue };
+                if let Some(sender) = waiting.lock().await.remove(&id) {
+                    let _ = sender.send(response);
+                }
+            }
+        });
+        let transport = Transport::Stdio { stdin: Mutex::new(stdin), pending };
+        transport.initialize().await?;
+        transport.notify("notifications/initialized").await?;
+        Ok(transport)
+    }
+
+    async fn initialize(&self) -> Result<Value> {
+        let params = json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "mcp-load-test", "version": env!("CARGO_PKG_VERSION")}
+        });
+        let response = self.request(0, "initialize", params).await?;
+        if let Some(error) = response.get("error") {
+            bail!("initialize failed: {}", error);
+        }
+        Ok(response)
+    }
+
+    async fn notify(&self, method: &str) -> Result<()> {
+        self.send(json!({"jsonrpc": "2.0", "method": method})).await.map(drop)
+    }
+
+    async fn request(&self, id: u64, method: &str, params: Value) -> Result<Value> {
+        let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        match self {
+            Transport::Http { .. } => self.send(message).await,
+            Transport::Stdio { pending, .. } => {
+                let (sender, receiver) = oneshot::channel();
+                pending.lock().await.insert(id, sender);
+                self.send(message).await?;
+                receiver.await.context("The server stopped before responding")
+            }
+        }
+    }
+
+    /// Sends `message`; over HTTP, returns the response to it
+    async fn send(&self, message: Value) -> Result<Value> {
+        match self {
+            Transport::Http { client, url, session } => {
+                let mut request = client.post(url).json(&message);
+                if let Some(session) = session {
+                    request = request.header(SESSION_HEADER, session);
+                }
+                let response = request.send().await?.error_for_status()?;
+                if message.get("id").is_none() {
+                    return Ok(Value::Null);
+                }
+                Ok(response.json().await?)
+            }
+            Transport::Stdio { stdin, .. } => {
+                let mut line = serde_json::to_vec(&message)?;
+                line.push(b'\n');
+                let mut stdin = stdin.lock().await;
+                stdin.write_all(&line).await?;
+                stdin.flush().await?;
+                Ok(Value::Null)
+            }
+        }
+    }
+}
+
+/// Latencies of one method's requests
+struct Report {
+    method: String,
+    latencies: Vec<Duration>,
+    errors: usize,
+    elapsed: Duration,
+}
+
+impl Report {
+    fn percentile(&self, percent: usize) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let index = (self.latencies.len() * percent).div_ceil(100).max(1) - 1;
+        self.latencies[index.min(self.latencies.len() - 1)]
+    }
+
+    fn print(&self) {
+        let throughput = self.latencies.len() as f64 / self.elapsed.as_secs_f64();
+        println!(
+            "{:<11} {:>6} ok {:>5} errors  p50 {:>9.3?}  p99 {:>9.3?}  max {:>9.3?}  {:>8.0} req/s",
+            self.method,
+            self.latencies.len(),
+            self.errors,
+            self.percentile(50),
+            self.percentile(99),
+            self.latencies.last().copied().unwrap_or_default(),
+            throughput,
+        );
+    }
+}
+
+/// Sends `count` requests, `concurrency` at a time, numbering them from
+/// `first_id`
+async fn run(transport: Arc<Transport>, method: &str, params: Value, count: usize, concurrency: usize, first_id: u64) -> Report {
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let started = Instant::now();
+    let mut requests = Vec::with_capacity(count);
+    for n in 0..count {
+        let permit = permits.clone().acquire_owned().await.unwrap();
+        let transport = transport.clone();
+        let method = method.to_string();
+        let params = params.clone();
+        requests.push(tokio::spawn(async move {
+            let _permit = permit;
+            let sent = Instant::now();
+            let response = transport.request(first_id + n as u64, &method, params).await;
+            let ok = matches!(&response, Ok(response) if response.get("error").is_none());
+            (sent.elapsed(), ok)
+        }));
+    }
+    let mut report = Report { method: method.to_string(), latencies: Vec::with_capacity(count), errors: 0, elapsed: Duration::ZERO };
+    for request in requests {
+        match request.await {
+            Ok((latency, true)) => report.latencies.push(latency),
+            _ => report.errors += 1,
+        }
+    }
+    report.elapsed = started.elapsed();
+    report.latencies.sort();
+    report
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let arguments: Value = serde_json::from_str(&cli.arguments).context("--arguments is not JSON")?;
+
+    let transport = Arc::new(match &cli.stdio {
+        Some(command) => Transport::stdio(command).await?,
+        None => Transport::http(&cli.url).await?,
+    });
+
+    let mut reports = Vec::new();
+    if cli.methods != Methods::Call {
+        reports.push(run(transport.clone(), "tools/list", json!({}), cli.requests, cli.concurrency, 1).await);
+    }
+    if cli.methods != Methods::List {
+        let params = json!({"name": cli.tool, "arguments": arguments});
+        let first_id = 1 + cli.requests as u64;
+        reports.push(run(transport.clone(), "tools/call", params, cli.requests, cli.concurrency, first_id).await);
+    }
+    for report in &reports {
+        report.print();
+    }
+    if reports.iter().any(|report| report.errors > 0) {
+        bail!("Some requests failed");
+    }
+    Ok(())
+}
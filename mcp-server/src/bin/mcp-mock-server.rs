@@ -0,0 +1,67 @@
+use anyhow::Result;
+use clap::Parser;
+use mcp_server::mcp::framing::MessageReader;
+use mcp_server::mock::{MockScript, MockServer};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{self, AsyncWriteExt};
+use tracing::info;
+
+#[derive(Parser)]
+#[command(name = "mcp-mock-server")]
+#[command(about = "An MCP server with scripted fake tools, for integration tests")]
+struct Cli {
+    #[arg(long, default_value = "8080")]
+    port: u16,
+
+    #[arg(long)]
+    stdio: bool,
+
+    /// JSON file listing the tools and their responses (default: `echo` and `fail`)
+    #[arg(long, value_name = "FILE")]
+    script: Option<PathBuf>,
+
+    #[arg(long, default_value = "info")]
+    log_level: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    // Logs go to stderr so they stay out of the stdio protocol stream
+    tracing_subscriber::fmt()
+        .with_env_filter(cli.log_level.as_str())
+        .with_writer(std::io::stderr)
+        .init();
+
+    let script = match &cli.script {
+        Some(path) => MockScript::load(path)?,
+        None => MockScript::default(),
+    };
+    info!("Mock tools: {:?}", script.tools.iter().map(|tool| &tool.name).collect::<Vec<_>>());
+    let server = Arc::new(MockServer::new(script));
+
+    if cli.stdio {
+        let mut stdout = io::stdout();
+        let mut messages = MessageReader::new(io::stdin());
+        let mut framed = Vec::new();
+        while let Some(message) = messages.next_message().await? {
+            let response = server.handle_message(&message).await;
+            if response.is_empty() {
+                continue;
+            }
+            messages.framing().frame_into(&response, &mut framed);
+            stdout.write_all(&framed).await?;
+            stdout.flush().await?;
+        }
+    } else {
+        let addr = std::net::SocketAddr::from(([0, 0, 0, 0], cli.port));
+        info!("Mock MCP server listening on {}", addr);
+        axum::Server::bind(&addr)
+            .serve(server.router().into_make_service())
+            .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,239 @@
+//! Startup checks: each configured dependency is probed once before the
+//! server starts serving, and the outcome is logged as a table with a hint
+//! for every failure. With `--startup-policy strict` the server refuses to
+//! start when a check failed; by default it starts without what failed.
+
+use clap::ValueEnum;
+use std::fmt;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::secrets::{Secret, Secrets};
+
+/// How long a probe may take
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What to do when a startup check fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum StartupPolicy {
+    /// Start anyway, without the dependencies that failed
+    #[default]
+    Degraded,
+    /// Refuse to start
+    Strict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Failed,
+    /// Not configured, so not probed
+    Skipped,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Ok => "OK",
+            Status::Failed => "FAILED",
+            Status::Skipped => "SKIPPED",
+        })
+    }
+}
+
+/// The outcome of probing one dependency
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What to do about a failure
+    pub hint: Option<&'static str>,
+}
+
+impl Check {
+    pub fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: Status::Ok, detail: detail.into(), hint: None }
+    }
+
+    pub fn failed(name: &'static str, detail: impl Into<String>, hint: &'static str) -> Self {
+        Self { name, status: Status::Failed, detail: detail.into(), hint: Some(hint) }
+    }
+
+    pub fn skipped(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: Status::Skipped, detail: detail.into(), hint: None }
+    }
+}
+
+/// The checks made at startup, in the order they were made
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    checks: Vec<Check>,
+}
+
+impl CheckReport {
+    pub fn push(&mut self, check: Check) {
+        self.checks.push(check);
+    }
+
+    pub fn failed(&self) -> Vec<&'static str> {
+        self.checks.iter().filter(|check| check.status == Status::Failed).map(|check| check.name).collect()
+    }
+
+    /// One row per check, with the hint after a failure's detail
+    pub fn rows(&self) -> Vec<String> {
+        let width = self.checks.iter().map(|check| check.name.len()).max().unwrap_or(0);
+        self.checks
+            .iter()
+            .map(|check| {
+                let row = format!("{:width$}  {:7}  {}", check.name, check.status.to_string(), check.detail, width = width);
+                match check.hint {
+                    Some(hint) => format!("{} -> {}", row, hint),
+                    None => row,
+                }
+            })
+            .collect()
+    }
+
+    /// Logs the table, failures as warnings
+    pub fn log(&self) {
+        info!("Startup checks:");
+        for (check, row) in self.checks.iter().zip(self.rows()) {
+            if check.status == Status::Failed {
+                warn!("  {}", row);
+            } else {
+                info!("  {}", row);
+            }
+        }
+    }
+
+    /// Fails under the strict policy if any check failed
+    pub fn enforce(&self, policy: StartupPolicy) -> anyhow::Result<()> {
+        let failed = self.failed();
+        match policy {
+            StartupPolicy::Strict if !failed.is_empty() => Err(anyhow::anyhow!(
+                "Startup checks failed: {}; fix them, or start with --startup-policy degraded to run without them",
+                failed.join(", ")
+            )),
+            StartupPolicy::Degraded if !failed.is_empty() => {
+                warn!("Starting without {}", failed.join(", "));
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder().timeout(PROBE_TIMEOUT).build().unwrap_or_default()
+}
+
+/// Asks Home Assistant's API whether it is running and accepts the token,
+/// which may be a `secret://` reference
+pub async fn probe_home_assistant(base_url: &str, token: Option<&str>) -> Check {
+    const NAME: &str = "Home Assistant";
+    let Some(token) = token.filter(|token| !token.is_empty()) else {
+        return Check::skipped(NAME, "HOMEASSISTANT_TOKEN not set");
+    };
+    let token = match Secrets::global().resolve(&Secret::from(token)).await {
+        Ok(token) => token,
+        Err(e) => return Check::failed(NAME, format!("token could not be resolved: {}", e), "Check the secret HOMEASSISTANT_TOKEN refers to and SECRETS_PROVIDER"),
+    };
+    let url = format!("{}/api/", base_url.trim_end_matches('/'));
+    match client().get(&url).bearer_auth(token).send().await {
+        Ok(response) if response.status().is_success() => Check::ok(NAME, base_url),
+        Ok(response) if matches!(response.status().as_u16(), 401 | 403) => Check::failed(
+            NAME,
+            format!("{} rejected the token ({})", base_url, response.status()),
+            "Create a long-lived access token in Home Assistant and set HOMEASSISTANT_TOKEN to it",
+        ),
+        Ok(response) => Check::failed(NAME, format!("{} answered {}", url, response.status()), "Check that HOMEASSISTANT_URL is Home Assistant's base URL"),
+        Err(e) => Check::failed(NAME, format!("{} unreachable: {}", base_url, e), "Check that Home Assistant is running and HOMEASSISTANT_URL points at it"),
+    }
+}
+
+/// Asks Ollama whether it has the model used for the sampling fallback
+pub async fn probe_ollama(base_url: &str, model: Option<&str>) -> Check {
+    const NAME: &str = "Ollama";
+    let Some(model) = model.filter(|model| !model.is_empty()) else {
+        return Check::skipped(NAME, "SAMPLING_OLLAMA_MODEL not set");
+    };
+    let url = format!("{}/api/tags", base_url.trim_end_matches('/'));
+    let tags: serde_json::Value = match client().get(&url).send().await {
+        Ok(response) if response.status().is_success() => match response.json().await {
+            Ok(tags) => tags,
+            Err(e) => return Check::failed(NAME, format!("{} answered with something else: {}", url, e), "Check that OLLAMA_BASE_URL points at Ollama"),
+        },
+        Ok(response) => return Check::failed(NAME, format!("{} answered {}", url, response.status()), "Check that OLLAMA_BASE_URL points at Ollama"),
+        Err(e) => return Check::failed(NAME, format!("{} unreachable: {}", base_url, e), "Start Ollama, or set OLLAMA_BASE_URL to where it runs"),
+    };
+    // Models without a tag are pulled as `latest`
+    let wanted = if model.contains(':') { model.to_string() } else { format!("{}:latest", model) };
+    let found = tags["models"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| tag["name"].as_str())
+        .any(|name| name == model || name == wanted);
+    if found {
+        Check::ok(NAME, format!("{} has {}", base_url, model))
+    } else {
+        Check::failed(NAME, format!("{} does not have the model {}", base_url, model), "Pull it with `ollama pull`, or set SAMPLING_OLLAMA_MODEL to a model Ollama has")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_rows_and_strict_policy() {
+        let mut report = CheckReport::default();
+        report.push(Check::ok("Neo4j", "bolt://localhost:7687"));
+        report.push(Check::failed("Ollama", "unreachable", "Start Ollama"));
+        report.push(Check::skipped("Home Assistant", "HOMEASSISTANT_TOKEN not set"));
+
+        let rows = report.rows();
+        assert_eq!(rows[0], "Neo4j           OK       bolt://localhost:7687");
+        assert_eq!(rows[1], "Ollama          FAILED   unreachable -> Start Ollama");
+        assert_eq!(report.failed(), ["Ollama"]);
+        assert!(report.enforce(StartupPolicy::Degraded).is_ok());
+        assert!(report.enforce(StartupPolicy::Strict).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_probe_home_assistant() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/"))
+            .and(header("authorization", "Bearer good"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(401)).mount(&server).await;
+
+        assert_eq!(probe_home_assistant(&server.uri(), Some("good")).await.status, Status::Ok);
+        let rejected = probe_home_assistant(&server.uri(), Some("bad")).await;
+        assert_eq!(rejected.status, Status::Failed);
+        assert!(rejected.detail.contains("rejected the token"));
+        assert_eq!(probe_home_assistant(&server.uri(), None).await.status, Status::Skipped);
+    }
+
+    #[tokio::test]
+    async fn test_probe_ollama_looks_for_the_model() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"models": [{"name": "llama3:latest"}]})))
+            .mount(&server)
+            .await;
+
+        assert_eq!(probe_ollama(&server.uri(), Some("llama3")).await.status, Status::Ok);
+        let missing = probe_ollama(&server.uri(), Some("mistral")).await;
+        assert_eq!(missing.status, Status::Failed);
+        assert!(missing.hint.unwrap().contains("ollama pull"));
+        assert_eq!(probe_ollama("http://127.0.0.1:9", Some("llama3")).await.status, Status::Failed);
+    }
+}
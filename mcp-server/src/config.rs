@@ -0,0 +1,593 @@
+//! The server's config file, named by `--config` or `MCP_CONFIG`: YAML
+//! settings that otherwise come from flags and environment variables, with
+//! named profiles that override them. Settings are layered: defaults, then
+//! the file, then environment variables, then flags. The file is watched
+//! while the server runs. A valid change to the log level, tool rate limits
+//! or CORS origins is applied live; changes to the other settings need a
+//! restart and are reported as rejected.
+
+use axum::http::HeaderValue;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+use crate::mcp::limits::MessageLimits;
+use crate::mcp::rate_limit::RateLimitConfig;
+use crate::mcp::McpServer;
+
+/// How often the watched file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Settings from the config file; environment variables and flags given on
+/// the command line win over them at startup
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    /// HTTP port
+    pub port: Option<u16>,
+    /// Log filter, e.g. `info,mcp_server::plugins::http=debug`
+    pub log_level: Option<String>,
+    /// Origins browsers may call the HTTP endpoints from; `*` allows any
+    pub cors_origins: Option<Vec<String>>,
+    /// As for `--tool-rate-limits`, e.g. `homeassistant=10/min,http=60/min`
+    pub tool_rate_limits: Option<String>,
+    /// Requests per minute each session may make
+    pub session_rate_limit: Option<u32>,
+    /// As for `--message-limits`, e.g. `bytes=1048576,depth=32,rate=50`
+    pub message_limits: Option<String>,
+    pub neo4j_uri: Option<String>,
+    pub neo4j_user: Option<String>,
+    /// The password, or a `secret://` reference to it
+    pub neo4j_password: Option<String>,
+    pub homeassistant_url: Option<String>,
+    /// The token, or a `secret://` reference to it
+    pub homeassistant_token: Option<String>,
+    pub ollama_base_url: Option<String>,
+    /// SQLite file to keep context in instead of Neo4j
+    pub context_sqlite_db: Option<String>,
+    /// Settings by profile name, overriding the ones above when the profile
+    /// is chosen with `--profile` or `MCP_PROFILE`
+    #[serde(default, skip_serializing)]
+    pub profiles: BTreeMap<String, ServerConfig>,
+}
+
+/// A setting that may come from the file, an environment variable or a
+/// flag of the same name
+struct Setting {
+    name: &'static str,
+    env: Option<&'static str>,
+    default: Option<&'static str>,
+    /// Masked when shown, unless it is a `secret://` reference
+    secret: bool,
+}
+
+const SETTINGS: &[Setting] = &[
+    Setting { name: "port", env: None, default: Some("8080"), secret: false },
+    Setting { name: "log_level", env: Some("RUST_LOG"), default: Some("debug"), secret: false },
+    Setting { name: "cors_origins", env: None, default: Some("*"), secret: false },
+    Setting { name: "tool_rate_limits", env: Some("MCP_TOOL_RATE_LIMITS"), default: None, secret: false },
+    Setting { name: "session_rate_limit", env: Some("MCP_SESSION_RATE_LIMIT"), default: None, secret: false },
+    Setting { name: "message_limits", env: Some("MCP_MESSAGE_LIMITS"), default: Some("bytes=4194304,depth=64"), secret: false },
+    Setting { name: "neo4j_uri", env: Some("NEO4J_URI"), default: Some("bolt://localhost:7687"), secret: false },
+    Setting { name: "neo4j_user", env: Some("NEO4J_USER"), default: Some("neo4j"), secret: false },
+    Setting { name: "neo4j_password", env: Some("NEO4J_PASSWORD"), default: None, secret: true },
+    Setting { name: "homeassistant_url", env: Some("HOMEASSISTANT_URL"), default: Some("http://localhost:8123"), secret: false },
+    Setting { name: "homeassistant_token", env: Some("HOMEASSISTANT_TOKEN"), default: None, secret: true },
+    Setting { name: "ollama_base_url", env: Some("OLLAMA_BASE_URL"), default: Some("http://localhost:11434"), secret: false },
+    Setting { name: "context_sqlite_db", env: Some("CONTEXT_SQLITE_DB"), default: None, secret: false },
+];
+
+fn setting(name: &str) -> Option<&'static Setting> {
+    SETTINGS.iter().find(|setting| setting.name == name)
+}
+
+/// `value` as it may be shown: secrets are masked, but not references to them
+fn shown(name: &str, value: Value) -> Value {
+    match value {
+        Value::String(text) if setting(name).is_some_and(|s| s.secret) && !text.starts_with("secret://") => {
+            Value::String("********".to_string())
+        }
+        value => value,
+    }
+}
+
+impl ServerConfig {
+    /// Reads and validates a config file, with the settings of `profile`
+    /// on top
+    pub fn load(path: &Path, profile: Option<&str>) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read config file {}: {}", path.display(), e))?;
+        Self::parse(&text, profile)
+            .map_err(|errors| anyhow::anyhow!("Invalid config file {}: {}", path.display(), errors.join("; ")))
+    }
+
+    /// Parses and validates YAML settings, with the settings of `profile`
+    /// on top; an empty document has none
+    pub fn parse(text: &str, profile: Option<&str>) -> Result<Self, Vec<String>> {
+        let config: Self = if text.trim().is_empty() {
+            Self::default()
+        } else {
+            serde_yaml::from_str(text).map_err(|e| vec![e.to_string()])?
+        };
+        let config = config.with_profile(profile)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// These settings with those of the profile `name` on top, and without
+    /// the profiles
+    fn with_profile(mut self, name: Option<&str>) -> Result<Self, Vec<String>> {
+        let profiles = std::mem::take(&mut self.profiles);
+        let Some(name) = name else {
+            return Ok(self);
+        };
+        let Some(profile) = profiles.get(name) else {
+            let known: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            return Err(vec![format!("Unknown profile '{}'; the file has {}", name, if known.is_empty() { "none".to_string() } else { known.join(", ") })]);
+        };
+        if !profile.profiles.is_empty() {
+            return Err(vec![format!("Profile '{}' has profiles of its own", name)]);
+        }
+        let mut merged = to_value(&self);
+        if let Value::Object(overrides) = to_value(profile) {
+            for (setting, value) in overrides.into_iter().filter(|(_, value)| !value.is_null()) {
+                merged[setting] = value;
+            }
+        }
+        serde_json::from_value(merged).map_err(|e| vec![e.to_string()])
+    }
+
+    /// Every invalid setting, each with what is wrong with it
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.port == Some(0) {
+            errors.push("port: must not be 0".to_string());
+        }
+        if let Some(level) = &self.log_level {
+            if let Err(e) = EnvFilter::try_new(level) {
+                errors.push(format!("log_level: {}", e));
+            }
+        }
+        for origin in self.cors_origins.iter().flatten() {
+            let valid = origin == "*"
+                || ((origin.starts_with("http://") || origin.starts_with("https://")) && HeaderValue::from_str(origin).is_ok());
+            if !valid {
+                errors.push(format!("cors_origins: '{}' is not * or an http(s) origin", origin));
+            }
+        }
+        if let Some(Err(e)) = self.tool_rate_limits.as_deref().map(str::parse::<RateLimitConfig>) {
+            errors.push(format!("tool_rate_limits: {}", e));
+        }
+        if let Some(Err(e)) = self.message_limits.as_deref().map(str::parse::<MessageLimits>) {
+            errors.push(format!("message_limits: {}", e));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn tool_rate_limits(&self) -> Option<RateLimitConfig> {
+        self.tool_rate_limits.as_deref()?.parse().ok()
+    }
+
+    /// The settings whose value differs in `new`, in name order, with
+    /// secrets masked
+    pub fn diff(&self, new: &ServerConfig) -> Vec<Change> {
+        let (Value::Object(old), Value::Object(new)) = (to_value(self), to_value(new)) else {
+            return Vec::new();
+        };
+        old.into_iter()
+            .zip(new)
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((setting, old), (_, new))| Change { old: shown(&setting, old), new: shown(&setting, new), setting })
+            .collect()
+    }
+
+    /// The settings as JSON, with secrets masked
+    pub fn masked(&self) -> Value {
+        match to_value(self) {
+            Value::Object(settings) => {
+                Value::Object(settings.into_iter().map(|(name, value)| (name.clone(), shown(&name, value))).collect())
+            }
+            value => value,
+        }
+    }
+
+    /// Sets the environment variable of each setting the file gives and the
+    /// environment does not, so the code reading the variable sees the
+    /// file's value. Call it before other threads start.
+    pub fn export_to_env(&self) {
+        let settings = to_value(self);
+        for setting in SETTINGS {
+            let Some(env) = setting.env else { continue };
+            if std::env::var_os(env).is_none() {
+                if let Some(value) = as_text(&settings[setting.name]) {
+                    std::env::set_var(env, value);
+                }
+            }
+        }
+    }
+
+    /// Every setting's effective value and where it came from, flags first,
+    /// then environment variables, then this file, then defaults. `flag`
+    /// gives the value of the flag named like a setting, if one was given.
+    pub fn resolve(&self, flag: impl Fn(&str) -> Option<String>) -> Vec<Resolved> {
+        let settings = to_value(self);
+        SETTINGS
+            .iter()
+            .map(|setting| {
+                let env = setting.env.and_then(|env| std::env::var(env).ok().map(|value| (env, value)));
+                let (value, source) = if let Some(value) = flag(setting.name) {
+                    (Some(value), Source::Flag)
+                } else if let Some((env, value)) = env {
+                    (Some(value), Source::Env(env))
+                } else if let Some(value) = as_text(&settings[setting.name]) {
+                    (Some(value), Source::File)
+                } else {
+                    (setting.default.map(String::from), Source::Default)
+                };
+                let value = value.map(|value| match shown(setting.name, Value::String(value)) {
+                    Value::String(value) => value,
+                    value => value.to_string(),
+                });
+                Resolved { name: setting.name, value, source }
+            })
+            .collect()
+    }
+}
+
+/// A setting's value as an environment variable would hold it; lists are
+/// joined with commas
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        Value::Array(items) => Some(items.iter().filter_map(as_text).collect::<Vec<_>>().join(",")),
+        value => Some(value.to_string()),
+    }
+}
+
+/// Where a setting's effective value came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Default,
+    File,
+    /// The environment variable named
+    Env(&'static str),
+    Flag,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File => write!(f, "config file"),
+            Source::Env(env) => write!(f, "env {}", env),
+            Source::Flag => write!(f, "flag"),
+        }
+    }
+}
+
+/// A setting's effective value, with secrets masked; `None` if unset
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved {
+    pub name: &'static str,
+    pub value: Option<String>,
+    pub source: Source,
+}
+
+fn to_value(config: &ServerConfig) -> Value {
+    serde_json::to_value(config).unwrap_or_default()
+}
+
+/// The config file from `MCP_CONFIG`, if set
+pub fn path_from_env() -> Option<PathBuf> {
+    std::env::var_os("MCP_CONFIG").filter(|path| !path.is_empty()).map(PathBuf::from)
+}
+
+/// The profile from `MCP_PROFILE`, if set
+pub fn profile_from_env() -> Option<String> {
+    std::env::var("MCP_PROFILE").ok().filter(|profile| !profile.is_empty())
+}
+
+/// A setting that changed, with its values as JSON (`null` when unset)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub setting: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// A change that was not applied, and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Rejected {
+    #[serde(flatten)]
+    pub change: Change,
+    pub reason: String,
+}
+
+/// What a reload of the config file did
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReloadReport {
+    pub at: DateTime<Utc>,
+    pub applied: Vec<Change>,
+    pub rejected: Vec<Rejected>,
+    /// Why the file as a whole was refused; nothing is applied then
+    pub errors: Vec<String>,
+}
+
+impl ReloadReport {
+    fn new() -> Self {
+        Self { at: Utc::now(), applied: Vec::new(), rejected: Vec::new(), errors: Vec::new() }
+    }
+}
+
+/// Origins the HTTP endpoints answer CORS requests from, shared with the
+/// config watcher so they can change live
+#[derive(Debug, Clone)]
+pub struct AllowedOrigins(Arc<RwLock<Vec<String>>>);
+
+impl AllowedOrigins {
+    pub fn new(origins: Vec<String>) -> Self {
+        Self(Arc::new(RwLock::new(origins)))
+    }
+
+    pub fn set(&self, origins: Vec<String>) {
+        *self.0.write().unwrap() = origins;
+    }
+
+    pub fn allows(&self, origin: &HeaderValue) -> bool {
+        self.0.read().unwrap().iter().any(|allowed| allowed == "*" || allowed.as_bytes() == origin.as_bytes())
+    }
+}
+
+impl Default for AllowedOrigins {
+    fn default() -> Self {
+        Self::new(vec!["*".to_string()])
+    }
+}
+
+/// Applies changes to the config file while the server runs
+pub struct ConfigWatcher {
+    path: PathBuf,
+    profile: Option<String>,
+    server: Arc<McpServer>,
+    origins: AllowedOrigins,
+    /// The settings in effect: the file's, less the changes rejected
+    current: Mutex<ServerConfig>,
+    last_report: Mutex<Option<ReloadReport>>,
+}
+
+impl ConfigWatcher {
+    /// Watches `path`, whose settings `config` with those of `profile` on
+    /// top the server started with
+    pub fn new(
+        path: PathBuf,
+        profile: Option<String>,
+        config: ServerConfig,
+        server: Arc<McpServer>,
+        origins: AllowedOrigins,
+    ) -> Self {
+        Self { path, profile, server, origins, current: Mutex::new(config), last_report: Mutex::new(None) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The settings in effect
+    pub fn config(&self) -> ServerConfig {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// What the last reload did, if there was one
+    pub fn last_report(&self) -> Option<ReloadReport> {
+        self.last_report.lock().unwrap().clone()
+    }
+
+    /// Checks the file every few seconds and reloads it when it changed
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        info!("Watching config file {}", self.path.display());
+        tokio::spawn(async move {
+            let mut seen = modified(&self.path);
+            let mut ticker = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                ticker.tick().await;
+                let now = modified(&self.path);
+                // A file that is briefly gone while an editor replaces it
+                // is reloaded once it is back
+                if now.is_some() && now != seen {
+                    self.reload();
+                }
+                seen = now;
+            }
+        })
+    }
+
+    /// Reads the file again and applies the settings that changed and can
+    /// change live. An invalid file changes nothing.
+    pub fn reload(&self) -> ReloadReport {
+        let config = std::fs::read_to_string(&self.path)
+            .map_err(|e| vec![format!("Failed to read {}: {}", self.path.display(), e)])
+            .and_then(|text| ServerConfig::parse(&text, self.profile.as_deref()));
+        let report = match config {
+            Ok(config) => self.apply(config),
+            Err(errors) => ReloadReport { errors, ..ReloadReport::new() },
+        };
+
+        for error in &report.errors {
+            warn!("Ignoring invalid config file {}: {}", self.path.display(), error);
+        }
+        for change in &report.applied {
+            info!("Config {} changed from {} to {}", change.setting, change.old, change.new);
+        }
+        for rejected in &report.rejected {
+            let change = &rejected.change;
+            warn!("Config {} not changed from {} to {}: {}", change.setting, change.old, change.new, rejected.reason);
+        }
+        *self.last_report.lock().unwrap() = Some(report.clone());
+        report
+    }
+
+    fn apply(&self, config: ServerConfig) -> ReloadReport {
+        let mut current = self.current.lock().unwrap();
+        let mut report = ReloadReport::new();
+        for change in current.diff(&config) {
+            match self.apply_setting(&config, &change.setting) {
+                Ok(()) => report.applied.push(change),
+                Err(reason) => report.rejected.push(Rejected { change, reason }),
+            }
+        }
+
+        // Rejected settings keep the value in effect
+        let mut next = to_value(&config);
+        for rejected in &report.rejected {
+            next[&rejected.change.setting] = rejected.change.old.clone();
+        }
+        *current = serde_json::from_value(next).unwrap_or(config);
+        report
+    }
+
+    fn apply_setting(&self, config: &ServerConfig, setting: &str) -> Result<(), String> {
+        let removed = || "Removing a setting needs a restart".to_string();
+        match setting {
+            "log_level" => {
+                let level = config.log_level.as_deref().ok_or_else(removed)?;
+                self.server.set_log_level(level).map(drop).map_err(|e| e.to_string())
+            }
+            "tool_rate_limits" => {
+                self.server.set_tool_rate_limits(config.tool_rate_limits().ok_or_else(removed)?);
+                Ok(())
+            }
+            "cors_origins" => {
+                self.origins.set(config.cors_origins.clone().ok_or_else(removed)?);
+                Ok(())
+            }
+            _ => Err("Needs a restart".to_string()),
+        }
+    }
+}
+
+/// When the file was last changed, and its size; `None` if it is missing
+fn modified(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_validate() {
+        let config = ServerConfig::parse("port: 9000\ncors_origins: [\"https://app.local\"]\ntool_rate_limits: http=5/s\n", None).unwrap();
+        assert_eq!(config.port, Some(9000));
+        assert_eq!(config.tool_rate_limits().unwrap().limits.len(), 1);
+        assert_eq!(ServerConfig::parse("", None).unwrap(), ServerConfig::default());
+
+        let errors = ServerConfig::parse("port: 0\ncors_origins: [\"app.local\"]\ntool_rate_limits: http\n", None).unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("port:"));
+        assert!(ServerConfig::parse("colour: blue\n", None).is_err());
+    }
+
+    #[test]
+    fn test_profiles_override_the_file() {
+        let text = "port: 8080\nneo4j_uri: bolt://localhost:7687\nprofiles:\n  prod:\n    neo4j_uri: bolt://neo4j:7687\n";
+        let prod = ServerConfig::parse(text, Some("prod")).unwrap();
+        assert_eq!(prod.neo4j_uri.as_deref(), Some("bolt://neo4j:7687"));
+        assert_eq!(prod.port, Some(8080));
+        assert!(prod.profiles.is_empty());
+        assert_eq!(ServerConfig::parse(text, None).unwrap().neo4j_uri.as_deref(), Some("bolt://localhost:7687"));
+        assert_eq!(ServerConfig::parse(text, Some("dev")).unwrap_err(), ["Unknown profile 'dev'; the file has prod"]);
+    }
+
+    #[test]
+    fn test_resolve_layers_flags_env_file_and_defaults() {
+        // Only read by main, so no other test sees it
+        std::env::set_var("CONTEXT_SQLITE_DB", "/tmp/env.db");
+        let config = ServerConfig {
+            port: Some(9000),
+            log_level: Some("info".to_string()),
+            context_sqlite_db: Some("/tmp/file.db".to_string()),
+            neo4j_password: Some("hunter2".to_string()),
+            homeassistant_token: Some("secret://ha/token".to_string()),
+            ..Default::default()
+        };
+        let resolved = config.resolve(|name| (name == "log_level").then(|| "warn".to_string()));
+        let get = |name: &str| resolved.iter().find(|setting| setting.name == name).unwrap().clone();
+
+        assert_eq!(get("log_level").value.as_deref(), Some("warn"));
+        assert_eq!(get("log_level").source, Source::Flag);
+        assert_eq!(get("context_sqlite_db").value.as_deref(), Some("/tmp/env.db"));
+        assert_eq!(get("context_sqlite_db").source, Source::Env("CONTEXT_SQLITE_DB"));
+        assert_eq!((get("port").value.as_deref(), get("port").source), (Some("9000"), Source::File));
+        assert_eq!((get("cors_origins").value.as_deref(), get("cors_origins").source), (Some("*"), Source::Default));
+        assert_eq!(get("tool_rate_limits").value, None);
+        // Secrets are masked, references to them are not
+        if std::env::var_os("NEO4J_PASSWORD").is_none() {
+            assert_eq!(get("neo4j_password").value.as_deref(), Some("********"));
+        }
+        if std::env::var_os("HOMEASSISTANT_TOKEN").is_none() {
+            assert_eq!(get("homeassistant_token").value.as_deref(), Some("secret://ha/token"));
+        }
+        std::env::remove_var("CONTEXT_SQLITE_DB");
+    }
+
+    #[test]
+    fn test_diff_lists_changed_settings() {
+        let old = ServerConfig { port: Some(8080), log_level: Some("info".to_string()), ..Default::default() };
+        let new = ServerConfig { port: Some(8080), log_level: Some("debug".to_string()), session_rate_limit: Some(60), ..Default::default() };
+        let changes = old.diff(&new);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0], Change { setting: "log_level".to_string(), old: "info".into(), new: "debug".into() });
+        assert_eq!((changes[1].setting.as_str(), &changes[1].old), ("session_rate_limit", &Value::Null));
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn test_allowed_origins() {
+        let origins = AllowedOrigins::new(vec!["https://app.local".to_string()]);
+        assert!(origins.allows(&HeaderValue::from_static("https://app.local")));
+        assert!(!origins.allows(&HeaderValue::from_static("https://evil.local")));
+        origins.set(vec!["*".to_string()]);
+        assert!(origins.allows(&HeaderValue::from_static("https://evil.local")));
+    }
+
+    #[tokio::test]
+    async fn test_reload_applies_live_settings_and_rejects_the_rest() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "port: 8080\ncors_origins: [\"https://a.local\"]\n").unwrap();
+        let config = ServerConfig::load(file.path(), None).unwrap();
+        let origins = AllowedOrigins::new(config.cors_origins.clone().unwrap());
+        let watcher = ConfigWatcher::new(file.path().to_path_buf(), None, config, Arc::new(McpServer::new()), origins.clone());
+
+        std::fs::write(file.path(), "port: 9090\ncors_origins: [\"https://b.local\"]\ntool_rate_limits: http=1/min\n").unwrap();
+        let report = watcher.reload();
+        assert!(report.errors.is_empty());
+        let applied: Vec<_> = report.applied.iter().map(|change| change.setting.as_str()).collect();
+        assert_eq!(applied, ["cors_origins", "tool_rate_limits"]);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].change.setting, "port");
+        assert!(origins.allows(&HeaderValue::from_static("https://b.local")));
+        // The port in effect is still the old one
+        assert_eq!(watcher.config().port, Some(8080));
+        assert_eq!(watcher.last_report(), Some(report));
+
+        std::fs::write(file.path(), "cors_origins: nope\n").unwrap();
+        let report = watcher.reload();
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.applied.is_empty());
+        assert_eq!(watcher.config().cors_origins, Some(vec!["https://b.local".to_string()]));
+    }
+}
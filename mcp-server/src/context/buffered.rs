@@ -0,0 +1,481 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use super::{ContextStore, DEFAULT_TENANT, Entity, KnowledgeGraph, Memory, MetricAggregate, MetricBucket, Provenance, Relation, RelationType};
+
+/// How often buffered writes are retried
+const REPLAY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Ids handed out for buffered records start with this, so relationships
+/// written before the record reached the store can be pointed at its real id
+const LOCAL_ID_PREFIX: &str = "buffered-";
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS pending_writes (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    tenant TEXT NOT NULL DEFAULT 'default',
+    write TEXT NOT NULL,
+    buffered_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS replayed_ids (
+    local_id TEXT PRIMARY KEY,
+    id TEXT NOT NULL
+)";
+
+/// A context store write that failed and waits to be replayed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BufferedWrite {
+    Metric {
+        local_id: String,
+        metric_type: String,
+        value: serde_json::Value,
+        timestamp: DateTime<Utc>,
+    },
+    SystemState {
+        local_id: String,
+        state: HashMap<String, serde_json::Value>,
+    },
+    Snapshot {
+        local_id: String,
+        state: HashMap<String, serde_json::Value>,
+        timestamp: DateTime<Utc>,
+    },
+    Relationship {
+        from_id: String,
+        to_id: String,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+        merge: bool,
+    },
+    Workflow {
+        id: String,
+        state: serde_json::Value,
+    },
+    Memory(Memory),
+    Knowledge {
+        provenance: Provenance,
+        entities: Vec<Entity>,
+        relations: Vec<Relation>,
+    },
+}
+
+impl BufferedWrite {
+    /// The id the caller is given for a record that was buffered
+    fn local_id(&self) -> Option<&str> {
+        match self {
+            BufferedWrite::Metric { local_id, .. }
+            | BufferedWrite::SystemState { local_id, .. }
+            | BufferedWrite::Snapshot { local_id, .. } => Some(local_id),
+            _ => None,
+        }
+    }
+}
+
+fn local_id() -> String {
+    format!("{}{}", LOCAL_ID_PREFIX, uuid::Uuid::new_v4())
+}
+
+/// Writes that failed, in the order they were made, kept in SQLite so that
+/// they survive a restart when the database is a file
+pub struct WriteBuffer {
+    connection: Mutex<Connection>,
+}
+
+impl WriteBuffer {
+    /// Writes in the database file at `path`, created if missing
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("Failed to open context buffer {}", path.display()))?;
+        Self::with_connection(connection)
+    }
+
+    /// Writes kept only for the life of the process
+    pub fn in_memory() -> Self {
+        Self::with_connection(Connection::open_in_memory().expect("in-memory SQLite"))
+            .expect("buffer tables in an in-memory database")
+    }
+
+    /// Writes in the file `CONTEXT_BUFFER_DB` names, or in memory if it is
+    /// unset or cannot be opened
+    pub fn from_env() -> Self {
+        let Ok(path) = std::env::var("CONTEXT_BUFFER_DB") else {
+            return Self::in_memory();
+        };
+        match Self::open(Path::new(&path)) {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                warn!("Ignoring CONTEXT_BUFFER_DB, buffering context writes in memory: {:#}", e);
+                Self::in_memory()
+            }
+        }
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        // Buffers written before there were tenants hold default-tenant writes
+        if connection.prepare("SELECT tenant FROM pending_writes LIMIT 0").is_err() {
+            connection.execute("ALTER TABLE pending_writes ADD COLUMN tenant TEXT NOT NULL DEFAULT 'default'", [])?;
+        }
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+
+    pub fn push(&self, tenant: &str, write: &BufferedWrite) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "INSERT INTO pending_writes (tenant, write, buffered_at) VALUES (?1, ?2, ?3)",
+            params![tenant, serde_json::to_string(write)?, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// The oldest pending write with its sequence number and tenant
+    pub fn oldest(&self) -> Result<Option<(i64, String, BufferedWrite)>> {
+        let connection = self.connection.lock().unwrap();
+        let row: Option<(i64, String, String)> = connection
+            .query_row("SELECT seq, tenant, write FROM pending_writes ORDER BY seq LIMIT 1", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .optional()?;
+        row.map(|(seq, tenant, write)| Ok((seq, tenant, serde_json::from_str(&write)?))).transpose()
+    }
+
+    /// Drops a write once it was replayed, remembering the id the store gave
+    /// the record it had a local id for
+    pub fn complete(&self, seq: i64, replayed: Option<(&str, &str)>) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM pending_writes WHERE seq = ?1", [seq])?;
+        if let Some((local_id, id)) = replayed {
+            transaction.execute(
+                "INSERT OR REPLACE INTO replayed_ids (local_id, id) VALUES (?1, ?2)",
+                [local_id, id],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        let connection = self.connection.lock().unwrap();
+        let count: i64 = connection.query_row("SELECT COUNT(*) FROM pending_writes", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// The store's id for `id` if it is the local id of a replayed record
+    pub fn resolve(&self, id: &str) -> Result<String> {
+        if !id.starts_with(LOCAL_ID_PREFIX) {
+            return Ok(id.to_string());
+        }
+        let connection = self.connection.lock().unwrap();
+        let replayed: Option<String> = connection
+            .query_row("SELECT id FROM replayed_ids WHERE local_id = ?1", [id], |row| row.get(0))
+            .optional()?;
+        Ok(replayed.unwrap_or_else(|| id.to_string()))
+    }
+}
+
+/// The buffer every tenant's [`BufferedStore`] shares
+struct Replay {
+    /// The backend, for the default tenant
+    backend: Arc<dyn ContextStore>,
+    buffer: WriteBuffer,
+    /// Held while replaying, so that writes are replayed once and in order
+    replaying: tokio::sync::Mutex<()>,
+}
+
+/// A context store that does not lose writes while its backend is down:
+/// a write that fails is kept in a [`WriteBuffer`] and the caller gets a
+/// local id, and buffered writes are replayed in order once the backend
+/// answers again. Reads always go to the backend, so they do not see
+/// buffered writes until those have been replayed.
+pub struct BufferedStore {
+    /// The backend, for this store's tenant
+    inner: Arc<dyn ContextStore>,
+    tenant: String,
+    replay: Arc<Replay>,
+}
+
+impl BufferedStore {
+    pub fn new(inner: Arc<dyn ContextStore>, buffer: WriteBuffer) -> Self {
+        let replay = Replay { backend: inner.clone(), buffer, replaying: tokio::sync::Mutex::new(()) };
+        Self { inner, tenant: DEFAULT_TENANT.to_string(), replay: Arc::new(replay) }
+    }
+
+    /// Writes still waiting for the backend, of every tenant
+    pub fn pending(&self) -> usize {
+        self.replay.buffer.len().unwrap_or(0)
+    }
+
+    /// Replays buffered writes every [`REPLAY_INTERVAL`]
+    pub fn spawn_replay(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let store = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REPLAY_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = store.replay().await {
+                    error!("Failed to replay buffered context writes: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Replays buffered writes, oldest first, until one fails while the
+    /// backend is down; returns whether the buffer is empty afterwards.
+    /// A write that fails while the backend answers pings would fail every
+    /// time, so it is logged and dropped rather than blocking the rest.
+    pub async fn replay(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let replay = &self.replay;
+        let _replaying = replay.replaying.lock().await;
+        let mut replayed = 0;
+        while let Some((seq, tenant, write)) = replay.buffer.oldest()? {
+            let store = replay.backend.clone().with_tenant(&tenant);
+            match self.apply(store.as_ref(), &write).await {
+                Ok(id) => {
+                    let local_id = write.local_id();
+                    replay.buffer.complete(seq, local_id.zip(id.as_deref()))?;
+                    replayed += 1;
+                }
+                Err(e) if replay.backend.ping().await.is_ok() => {
+                    error!("Dropping buffered context write of tenant {} that keeps failing ({}): {:?}", tenant, e, write);
+                    replay.buffer.complete(seq, None)?;
+                }
+                Err(_) => break,
+            }
+        }
+        if replayed > 0 {
+            info!("Replayed {} buffered context writes", replayed);
+        }
+        Ok(replay.buffer.is_empty()?)
+    }
+
+    /// Writes to `store`, returning the id of the record, if any
+    async fn apply(&self, store: &dyn ContextStore, write: &BufferedWrite) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        let id = match write {
+            BufferedWrite::Metric { metric_type, value, timestamp, .. } => {
+                Some(store.store_metric(metric_type, value.clone(), *timestamp).await?)
+            }
+            BufferedWrite::SystemState { state, .. } => Some(store.store_system_state(state.clone()).await?),
+            BufferedWrite::Snapshot { state, timestamp, .. } => {
+                Some(store.store_snapshot(state.clone(), *timestamp).await?)
+            }
+            BufferedWrite::Relationship { from_id, to_id, rel_type, properties, merge } => {
+                let buffer = &self.replay.buffer;
+                let (from_id, to_id) = (buffer.resolve(from_id)?, buffer.resolve(to_id)?);
+                if *merge {
+                    store.merge_relationship(&from_id, &to_id, rel_type.clone(), properties.clone()).await?;
+                } else {
+                    store.create_relationship(&from_id, &to_id, rel_type.clone(), properties.clone()).await?;
+                }
+                None
+            }
+            BufferedWrite::Workflow { id, state } => {
+                store.save_workflow(id, state.clone()).await?;
+                None
+            }
+            BufferedWrite::Memory(memory) => {
+                store.remember(memory.clone()).await?;
+                None
+            }
+            BufferedWrite::Knowledge { provenance, entities, relations } => {
+                store.ingest_knowledge(provenance.clone(), entities.clone(), relations.clone()).await?;
+                None
+            }
+        };
+        Ok(id)
+    }
+
+    /// Writes straight to the backend when nothing is waiting before this
+    /// write, and buffers it otherwise or when the backend fails. Returns
+    /// the id of the record: the backend's, or the local one when buffered.
+    async fn write(&self, write: BufferedWrite) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        if self.replay.buffer.is_empty()? || self.replay().await? {
+            match self.apply(self.inner.as_ref(), &write).await {
+                Ok(id) => return Ok(id),
+                Err(e) => warn!("Context store write failed, buffering it for replay: {}", e),
+            }
+        }
+        self.replay.buffer.push(&self.tenant, &write)?;
+        Ok(write.local_id().map(str::to_string))
+    }
+
+    async fn write_record(&self, write: BufferedWrite) -> Result<String, Box<dyn Error + Send + Sync>> {
+        Ok(self.write(write).await?.unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl ContextStore for BufferedStore {
+    fn with_tenant(self: Arc<Self>, tenant: &str) -> Arc<dyn ContextStore> {
+        Arc::new(BufferedStore {
+            inner: self.replay.backend.clone().with_tenant(tenant),
+            tenant: tenant.to_string(),
+            replay: Arc::clone(&self.replay),
+        })
+    }
+
+    async fn store_metric(
+        &self,
+        metric_type: &str,
+        value: serde_json::Value,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.write_record(BufferedWrite::Metric {
+            local_id: local_id(),
+            metric_type: metric_type.to_string(),
+            value,
+            timestamp,
+        })
+        .await
+    }
+
+    async fn store_system_state(
+        &self,
+        state: HashMap<String, serde_json::Value>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.write_record(BufferedWrite::SystemState { local_id: local_id(), state }).await
+    }
+
+    async fn aggregate_metrics(
+        &self,
+        metric_type: &str,
+        window: chrono::Duration,
+        aggregate: MetricAggregate,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<MetricBucket>, Box<dyn Error + Send + Sync>> {
+        self.inner.aggregate_metrics(metric_type, window, aggregate, since).await
+    }
+
+    async fn create_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.write(BufferedWrite::Relationship {
+            from_id: from_id.to_string(),
+            to_id: to_id.to_string(),
+            rel_type,
+            properties,
+            merge: false,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn merge_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.write(BufferedWrite::Relationship {
+            from_id: from_id.to_string(),
+            to_id: to_id.to_string(),
+            rel_type,
+            properties,
+            merge: true,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn store_snapshot(
+        &self,
+        state: HashMap<String, serde_json::Value>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.write_record(BufferedWrite::Snapshot { local_id: local_id(), state, timestamp }).await
+    }
+
+    async fn save_workflow(&self, id: &str, state: serde_json::Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.write(BufferedWrite::Workflow { id: id.to_string(), state }).await?;
+        Ok(())
+    }
+
+    async fn load_workflow(&self, id: &str) -> Result<Option<serde_json::Value>, Box<dyn Error + Send + Sync>> {
+        self.inner.load_workflow(id).await
+    }
+
+    async fn list_workflows(&self) -> Result<Vec<serde_json::Value>, Box<dyn Error + Send + Sync>> {
+        self.inner.list_workflows().await
+    }
+
+    async fn remember(&self, memory: Memory) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.write(BufferedWrite::Memory(memory)).await?;
+        Ok(())
+    }
+
+    async fn recall(&self, key: &str) -> Result<Option<Memory>, Box<dyn Error + Send + Sync>> {
+        self.inner.recall(key).await
+    }
+
+    async fn search_memories(&self, text: &str, limit: usize) -> Result<Vec<Memory>, Box<dyn Error + Send + Sync>> {
+        self.inner.search_memories(text, limit).await
+    }
+
+    async fn ingest_knowledge(
+        &self,
+        provenance: Provenance,
+        entities: Vec<Entity>,
+        relations: Vec<Relation>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.write(BufferedWrite::Knowledge { provenance, entities, relations }).await?;
+        Ok(())
+    }
+
+    async fn query_knowledge(&self, terms: &[String], limit: usize) -> Result<KnowledgeGraph, Box<dyn Error + Send + Sync>> {
+        self.inner.query_knowledge(terms, limit).await
+    }
+
+    async fn ping(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.inner.ping().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_buffer_keeps_writes_in_order_and_resolves_replayed_ids() {
+        let buffer = WriteBuffer::in_memory();
+        let metric = BufferedWrite::Metric {
+            local_id: local_id(),
+            metric_type: "cpu_usage".to_string(),
+            value: json!(42.5),
+            timestamp: Utc::now(),
+        };
+        let workflow = BufferedWrite::Workflow { id: "w1".to_string(), state: json!({ "step": 2 }) };
+        buffer.push(DEFAULT_TENANT, &metric).unwrap();
+        buffer.push("alice", &workflow).unwrap();
+        assert_eq!(buffer.len().unwrap(), 2);
+
+        let (seq, tenant, oldest) = buffer.oldest().unwrap().unwrap();
+        assert_eq!((tenant.as_str(), oldest), (DEFAULT_TENANT, metric.clone()));
+        let local_id = metric.local_id().unwrap();
+        assert_eq!(buffer.resolve(local_id).unwrap(), local_id);
+        buffer.complete(seq, Some((local_id, "metric-1"))).unwrap();
+
+        assert_eq!(buffer.resolve(local_id).unwrap(), "metric-1");
+        assert_eq!(buffer.resolve("state-7").unwrap(), "state-7");
+        let (_, tenant, oldest) = buffer.oldest().unwrap().unwrap();
+        assert_eq!((tenant.as_str(), oldest), ("alice", workflow));
+        assert!(!buffer.is_empty().unwrap());
+    }
+}
@@ -0,0 +1,370 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+use super::{ContextStore, Entity, KnowledgeGraph, Memory, MetricAggregate, MetricBucket, Provenance, Relation, RelationType};
+
+/// Encrypted values are stored as strings starting with this, followed by
+/// the base64 of the nonce and the ciphertext
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// Plaintext strings starting with `enc:` are stored behind this, so that
+/// reading them back never mistakes them for ciphertext
+const ESCAPED_PREFIX: &str = "enc:raw:";
+
+/// AES-GCM nonces are 96 bits
+const NONCE_LEN: usize = 12;
+
+/// Properties encrypted unless `CONTEXT_ENCRYPTED_FIELDS` says otherwise
+const DEFAULT_ENCRYPTED_FIELDS: &[&str] = &[
+    "prompt",
+    "system_prompt",
+    "systemPrompt",
+    "messages",
+    "password",
+    "token",
+    "access_token",
+    "api_key",
+    "secret",
+    "authorization",
+];
+
+/// Encrypts the values of designated properties with AES-256-GCM, each
+/// under a fresh nonce, and decrypts them again
+pub struct FieldCipher {
+    cipher: Aes256Gcm,
+    fields: Vec<String>,
+}
+
+impl FieldCipher {
+    /// A cipher for a 32-byte key that encrypts properties named in
+    /// `fields`, ignoring case
+    pub fn new(key: &[u8], fields: Vec<String>) -> Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| anyhow!("The encryption key must be 32 bytes, got {}", key.len()))?;
+        Ok(Self { cipher, fields })
+    }
+
+    /// Key from `CONTEXT_ENCRYPTION_KEY` (32 bytes, base64) and properties
+    /// from `CONTEXT_ENCRYPTED_FIELDS` (comma separated); `None` when no key
+    /// is set. A key that cannot be used is an error rather than a reason to
+    /// write in the clear.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(key) = std::env::var("CONTEXT_ENCRYPTION_KEY") else {
+            return Ok(None);
+        };
+        let key = BASE64.decode(key.trim()).context("CONTEXT_ENCRYPTION_KEY is not base64")?;
+        let fields = match std::env::var("CONTEXT_ENCRYPTED_FIELDS") {
+            Ok(fields) => fields.split(',').map(str::trim).filter(|f| !f.is_empty()).map(str::to_string).collect(),
+            Err(_) => DEFAULT_ENCRYPTED_FIELDS.iter().map(|f| f.to_string()).collect(),
+        };
+        Self::new(&key, fields).context("Invalid CONTEXT_ENCRYPTION_KEY").map(Some)
+    }
+
+    fn is_sensitive(&self, name: &str) -> bool {
+        self.fields.iter().any(|field| field.eq_ignore_ascii_case(name))
+    }
+
+    /// `value` as JSON, encrypted into a prefixed string
+    fn seal(&self, value: &Value) -> Result<Value> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, serde_json::to_vec(value)?.as_slice())
+            .map_err(|_| anyhow!("Failed to encrypt a context property"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(Value::String(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(sealed))))
+    }
+
+    fn open(&self, sealed: &str) -> Result<Value> {
+        let sealed = BASE64.decode(sealed).context("Encrypted context property is not base64")?;
+        if sealed.len() < NONCE_LEN {
+            bail!("Encrypted context property is too short");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt a context property; was it written with another key?"))?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Encrypts the value of every sensitive property in `value`, however
+    /// deeply nested, and escapes other strings that look encrypted
+    pub fn encrypt(&self, value: &mut Value) -> Result<()> {
+        match value {
+            Value::String(text) if text.starts_with("enc:") => text.insert_str(0, ESCAPED_PREFIX),
+            Value::Object(object) => {
+                for (name, value) in object.iter_mut() {
+                    self.encrypt_property(name, value)?;
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.encrypt(item)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn encrypt_property(&self, name: &str, value: &mut Value) -> Result<()> {
+        if self.is_sensitive(name) {
+            *value = self.seal(value)?;
+            Ok(())
+        } else {
+            self.encrypt(value)
+        }
+    }
+
+    /// Decrypts every encrypted string in `value`, whether or not its
+    /// property is still designated, and unescapes plaintext ones
+    pub fn decrypt(&self, value: &mut Value) -> Result<()> {
+        match value {
+            Value::String(text) => {
+                if let Some(sealed) = text.strip_prefix(ENCRYPTED_PREFIX) {
+                    *value = self.open(sealed)?;
+                } else if text.starts_with(ESCAPED_PREFIX) {
+                    text.drain(..ESCAPED_PREFIX.len());
+                }
+            }
+            Value::Object(object) => {
+                for value in object.values_mut() {
+                    self.decrypt(value)?;
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.decrypt(item)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn encrypt_map(&self, mut map: HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        for (name, value) in map.iter_mut() {
+            self.encrypt_property(name, value)?;
+        }
+        Ok(map)
+    }
+
+    /// A memory whose key is sensitive has its whole value encrypted
+    fn encrypt_memory(&self, mut memory: Memory) -> Result<Memory> {
+        self.encrypt_property(&memory.key, &mut memory.value)?;
+        Ok(memory)
+    }
+
+    fn decrypt_memory(&self, mut memory: Memory) -> Result<Memory> {
+        self.decrypt(&mut memory.value)?;
+        Ok(memory)
+    }
+}
+
+/// A context store that encrypts sensitive properties before they reach the
+/// backend and decrypts them on the way back, so prompts and credentials in
+/// tool arguments are not kept in the clear. Encrypted memory values cannot
+/// be found by `search_memories`; their keys still can.
+pub struct EncryptedStore {
+    inner: Arc<dyn ContextStore>,
+    cipher: Arc<FieldCipher>,
+}
+
+impl EncryptedStore {
+    pub fn new(inner: Arc<dyn ContextStore>, cipher: FieldCipher) -> Self {
+        Self { inner, cipher: Arc::new(cipher) }
+    }
+}
+
+#[async_trait]
+impl ContextStore for EncryptedStore {
+    fn with_tenant(self: Arc<Self>, tenant: &str) -> Arc<dyn ContextStore> {
+        Arc::new(EncryptedStore {
+            inner: self.inner.clone().with_tenant(tenant),
+            cipher: Arc::clone(&self.cipher),
+        })
+    }
+
+    async fn store_metric(
+        &self,
+        metric_type: &str,
+        mut value: Value,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.cipher.encrypt(&mut value)?;
+        self.inner.store_metric(metric_type, value, timestamp).await
+    }
+
+    async fn store_system_state(
+        &self,
+        state: HashMap<String, Value>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.inner.store_system_state(self.cipher.encrypt_map(state)?).await
+    }
+
+    async fn aggregate_metrics(
+        &self,
+        metric_type: &str,
+        window: chrono::Duration,
+        aggregate: MetricAggregate,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<MetricBucket>, Box<dyn Error + Send + Sync>> {
+        self.inner.aggregate_metrics(metric_type, window, aggregate, since).await
+    }
+
+    async fn create_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let properties = properties.map(|p| self.cipher.encrypt_map(p)).transpose()?;
+        self.inner.create_relationship(from_id, to_id, rel_type, properties).await
+    }
+
+    async fn merge_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let properties = properties.map(|p| self.cipher.encrypt_map(p)).transpose()?;
+        self.inner.merge_relationship(from_id, to_id, rel_type, properties).await
+    }
+
+    async fn store_snapshot(
+        &self,
+        state: HashMap<String, Value>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.inner.store_snapshot(self.cipher.encrypt_map(state)?, timestamp).await
+    }
+
+    async fn save_workflow(&self, id: &str, mut state: Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.cipher.encrypt(&mut state)?;
+        self.inner.save_workflow(id, state).await
+    }
+
+    async fn load_workflow(&self, id: &str) -> Result<Option<Value>, Box<dyn Error + Send + Sync>> {
+        let Some(mut state) = self.inner.load_workflow(id).await? else {
+            return Ok(None);
+        };
+        self.cipher.decrypt(&mut state)?;
+        Ok(Some(state))
+    }
+
+    async fn list_workflows(&self) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
+        let mut workflows = self.inner.list_workflows().await?;
+        for state in workflows.iter_mut() {
+            self.cipher.decrypt(state)?;
+        }
+        Ok(workflows)
+    }
+
+    async fn remember(&self, memory: Memory) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.inner.remember(self.cipher.encrypt_memory(memory)?).await
+    }
+
+    async fn recall(&self, key: &str) -> Result<Option<Memory>, Box<dyn Error + Send + Sync>> {
+        match self.inner.recall(key).await? {
+            Some(memory) => Ok(Some(self.cipher.decrypt_memory(memory)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn search_memories(&self, text: &str, limit: usize) -> Result<Vec<Memory>, Box<dyn Error + Send + Sync>> {
+        let memories = self.inner.search_memories(text, limit).await?;
+        Ok(memories.into_iter().map(|memory| self.cipher.decrypt_memory(memory)).collect::<Result<_>>()?)
+    }
+
+    async fn ingest_knowledge(
+        &self,
+        provenance: Provenance,
+        entities: Vec<Entity>,
+        relations: Vec<Relation>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.inner.ingest_knowledge(provenance, entities, relations).await
+    }
+
+    async fn query_knowledge(&self, terms: &[String], limit: usize) -> Result<KnowledgeGraph, Box<dyn Error + Send + Sync>> {
+        self.inner.query_knowledge(terms, limit).await
+    }
+
+    async fn ping(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.inner.ping().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::SqliteContextStore;
+    use serde_json::json;
+    use std::path::Path;
+
+    fn cipher(key: u8) -> FieldCipher {
+        FieldCipher::new(&[key; 32], DEFAULT_ENCRYPTED_FIELDS.iter().map(|f| f.to_string()).collect()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sensitive_properties_are_encrypted_at_rest_and_decrypted_on_read() {
+        let backend: Arc<dyn ContextStore> = Arc::new(SqliteContextStore::open(Path::new(":memory:")).unwrap());
+        let store = EncryptedStore::new(backend.clone(), cipher(7));
+        let state = json!({
+            "goal": "Dim the lights",
+            "steps": [{"tool": "homeassistant", "arguments": {"entity_id": "light.hall", "Token": "ha-secret"}}]
+        });
+        store.save_workflow("w1", state.clone()).await.unwrap();
+
+        let raw = backend.load_workflow("w1").await.unwrap().unwrap();
+        assert_eq!(raw["goal"], "Dim the lights");
+        assert_eq!(raw["steps"][0]["arguments"]["entity_id"], "light.hall");
+        assert!(raw["steps"][0]["arguments"]["Token"].as_str().unwrap().starts_with(ENCRYPTED_PREFIX));
+        assert!(!raw.to_string().contains("ha-secret"));
+        assert_eq!(store.load_workflow("w1").await.unwrap().unwrap(), state);
+        assert_eq!(store.list_workflows().await.unwrap(), vec![state]);
+
+        let memory = Memory { key: "prompt".to_string(), value: json!({"text": "Be brief"}), stored_at: Utc::now(), expires_at: None };
+        store.remember(memory.clone()).await.unwrap();
+        assert!(backend.recall("prompt").await.unwrap().unwrap().value.is_string());
+        assert_eq!(store.recall("prompt").await.unwrap().unwrap().value, memory.value);
+        assert_eq!(store.search_memories("prompt", 5).await.unwrap()[0].value, memory.value);
+
+        // Plaintext that looks encrypted is kept apart from ciphertext
+        let lookalike = json!({"note": "enc:v1:not a secret", "other": "enc:raw:x", "list": ["enc:"]});
+        store.save_workflow("w2", lookalike.clone()).await.unwrap();
+        assert_eq!(backend.load_workflow("w2").await.unwrap().unwrap()["note"], "enc:raw:enc:v1:not a secret");
+        assert_eq!(store.load_workflow("w2").await.unwrap().unwrap(), lookalike);
+        let memory = Memory { key: "note".to_string(), value: json!("enc:v1:AAAA"), stored_at: Utc::now(), expires_at: None };
+        store.remember(memory.clone()).await.unwrap();
+        assert_eq!(store.recall("note").await.unwrap().unwrap().value, memory.value);
+
+        // Another key cannot read what this one wrote
+        let other = EncryptedStore::new(backend, cipher(8));
+        assert!(other.load_workflow("w1").await.is_err());
+    }
+
+    #[test]
+    fn test_key_must_be_32_bytes() {
+        assert!(FieldCipher::new(&[0; 16], Vec::new()).is_err());
+        let cipher = cipher(1);
+        let mut value = json!({"password": [1, 2], "user": "ada"});
+        cipher.encrypt(&mut value).unwrap();
+        assert_ne!(value["password"], json!([1, 2]));
+        cipher.decrypt(&mut value).unwrap();
+        assert_eq!(value, json!({"password": [1, 2], "user": "ada"}));
+    }
+}
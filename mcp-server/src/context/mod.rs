@@ -1,3 +1,13 @@
+pub mod buffered;
+pub mod encrypted;
 pub mod neo4j;
+pub mod query;
+pub mod sqlite;
+pub mod store;
 
-pub use neo4j::{Neo4jContext, RelationType, get_neo4j_context};
\ No newline at end of file
+pub use buffered::{BufferedStore, WriteBuffer};
+pub use encrypted::{EncryptedStore, FieldCipher};
+pub use neo4j::{Neo4jContext, RelationType, get_neo4j_context};
+pub use sqlite::SqliteContextStore;
+pub use query::NodeQuery;
+pub use store::{ContextStore, DEFAULT_TENANT, Entity, KnowledgeGraph, Memory, MetricAggregate, MetricBucket, Provenance, Relation};
\ No newline at end of file
@@ -1,12 +1,14 @@
 use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
-use neo4rs::{Graph, Node, Query, Relation};
+use neo4rs::{BoltType, Graph, Node, Query, Relation};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::error::Error;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+use super::{NodeQuery, DEFAULT_TENANT};
 
 // Context node types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +20,19 @@ pub enum ContextNodeType {
     Pattern,
 }
 
+impl ContextNodeType {
+    /// The node label in Neo4j
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContextNodeType::Metric => "Metric",
+            ContextNodeType::SystemState => "SystemState",
+            ContextNodeType::UserInteraction => "UserInteraction",
+            ContextNodeType::ToolExecution => "ToolExecution",
+            ContextNodeType::Pattern => "Pattern",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextNode {
     pub node_type: ContextNodeType,
@@ -26,7 +41,7 @@ pub struct ContextNode {
 }
 
 // Relationship types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RelationType {
     Followed,
     Caused,
@@ -41,12 +56,15 @@ lazy_static! {
 
 pub struct Neo4jContext {
     graph: Graph,
+    /// Tenant every query is limited to
+    tenant: String,
 }
 
 impl std::fmt::Debug for Neo4jContext {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Neo4jContext")
             .field("graph", &"<Neo4j Graph>")
+            .field("tenant", &self.tenant)
             .finish()
     }
 }
@@ -74,7 +92,18 @@ impl Neo4jContext {
                         return Err(e);
                     }
                     
-                    return Ok(Neo4jContext { graph });
+                    let context = Neo4jContext { graph, tenant: DEFAULT_TENANT.to_string() };
+                    match context.migrate_tenants().await {
+                        Ok(0) => {}
+                        Ok(migrated) => info!("Assigned {} nodes to the default tenant", migrated),
+                        Err(e) => warn!("Failed to assign nodes to the default tenant: {}", e),
+                    }
+                    match context.migrate_property_encoding().await {
+                        Ok(0) => {}
+                        Ok(migrated) => info!("Migrated {} nodes to typed properties", migrated),
+                        Err(e) => warn!("Failed to migrate nodes to typed properties: {}", e),
+                    }
+                    return Ok(context);
                 }
                 Err(e) => {
                     error!("Connection attempt failed: {}", e);
@@ -103,6 +132,17 @@ impl Neo4jContext {
         }
     }
 
+    /// The same database with every query limited to `tenant`'s nodes
+    pub fn with_tenant(&self, tenant: &str) -> Neo4jContext {
+        Neo4jContext { graph: self.graph.clone(), tenant: tenant.to_string() }
+    }
+
+    /// Runs a trivial query, failing when the database cannot be reached
+    pub async fn ping(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.graph.run(Query::new("RETURN 1".to_string())).await?;
+        Ok(())
+    }
+
     async fn init_schema(graph: &Graph) -> Result<(), Box<dyn Error + Send + Sync>> {
         info!("Initializing Neo4j schema constraints");
         debug!("Using Neo4j 5.x constraint syntax");
@@ -112,6 +152,14 @@ impl Neo4jContext {
             "CREATE CONSTRAINT unique_user_interaction_id IF NOT EXISTS FOR (n:UserInteraction) REQUIRE n.id IS UNIQUE",
             "CREATE CONSTRAINT unique_tool_execution_id IF NOT EXISTS FOR (n:ToolExecution) REQUIRE n.id IS UNIQUE",
             "CREATE CONSTRAINT unique_pattern_id IF NOT EXISTS FOR (n:Pattern) REQUIRE n.id IS UNIQUE",
+            // Workflows, memories and entities are named by their tenant
+            "DROP CONSTRAINT unique_workflow_id IF EXISTS",
+            "DROP CONSTRAINT unique_memory_key IF EXISTS",
+            "DROP CONSTRAINT unique_entity_name IF EXISTS",
+            "CREATE CONSTRAINT unique_workflow_tenant_id IF NOT EXISTS FOR (n:Workflow) REQUIRE (n.tenant, n.id) IS UNIQUE",
+            "CREATE CONSTRAINT unique_memory_tenant_key IF NOT EXISTS FOR (n:Memory) REQUIRE (n.tenant, n.key) IS UNIQUE",
+            "CREATE CONSTRAINT unique_entity_tenant_name IF NOT EXISTS FOR (n:Entity) REQUIRE (n.tenant, n.name) IS UNIQUE",
+            "CREATE CONSTRAINT unique_provenance_id IF NOT EXISTS FOR (n:Provenance) REQUIRE n.id IS UNIQUE",
         ];
 
         for constraint in constraints {
@@ -135,36 +183,78 @@ impl Neo4jContext {
         value: serde_json::Value,
         timestamp: DateTime<Utc>,
     ) -> Result<Node, Box<dyn Error + Send + Sync>> {
-        log::debug!("Storing metric of type {} with value {}", metric_type, value);
+        tracing::debug!("Storing metric of type {} with value {}", metric_type, value);
         let query = Query::new(String::from(
             "CREATE (m:Metric {
                 id: randomUUID(),
+                tenant: $tenant,
                 type: $type,
-                value: $value,
                 timestamp: $timestamp
-            }) RETURN m"
+            }) SET m += $properties RETURN m"
         ))
+        .param("tenant", self.tenant.as_str())
         .param("type", metric_type)
-        .param("value", value.to_string())
+        .param("properties", metric_properties(&value))
         .param("timestamp", timestamp.to_rfc3339());
 
-        log::debug!("Executing Neo4j query to store metric");
+        tracing::debug!("Executing Neo4j query to store metric");
         let mut result = self.graph.execute(query).await?;
-        log::debug!("Query executed successfully");
+        tracing::debug!("Query executed successfully");
 
         let row = result.next().await?
             .ok_or_else(|| {
-                log::error!("No node was created when storing metric");
+                tracing::error!("No node was created when storing metric");
                 Box::new(std::io::Error::new(
                     std::io::ErrorKind::NotFound,
                     "No node created"
                 ))
             })?;
         let node = row.get("m")?;
-        log::info!("Successfully stored metric node");
+        tracing::info!("Successfully stored metric node");
         Ok(node)
     }
 
+    /// Buckets are computed in Cypher from the `Metric` nodes; readings that
+    /// are not numbers are skipped
+    pub async fn aggregate_metrics(
+        &self,
+        metric_type: &str,
+        window: chrono::Duration,
+        aggregate: super::MetricAggregate,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<super::MetricBucket>, Box<dyn Error + Send + Sync>> {
+        let function = match aggregate {
+            super::MetricAggregate::Avg => "avg(value)",
+            super::MetricAggregate::Min => "min(value)",
+            super::MetricAggregate::Max => "max(value)",
+            super::MetricAggregate::P95 => "percentileCont(value, 0.95)",
+        };
+        let query = Query::new(format!(
+            "MATCH (m:Metric {{tenant: $tenant, type: $type}})
+            WHERE m.timestamp >= $since
+            WITH datetime(m.timestamp).epochSeconds AS seconds, toFloat(m.value) AS value
+            WHERE value IS NOT NULL
+            WITH seconds - seconds % $window AS bucket, value
+            RETURN bucket, {} AS value, count(value) AS count
+            ORDER BY bucket",
+            function
+        ))
+        .param("tenant", self.tenant.as_str())
+        .param("type", metric_type)
+        .param("since", since.to_rfc3339())
+        .param("window", window.num_seconds().max(1));
+        let mut result = self.graph.execute(query).await?;
+        let mut buckets = Vec::new();
+        while let Some(row) = result.next().await? {
+            buckets.push(super::MetricBucket {
+                start: DateTime::from_timestamp(row.get("bucket")?, 0).unwrap_or_default(),
+                value: row.get("value")?,
+                count: row.get::<i64>("count")? as u64,
+            });
+        }
+        Ok(buckets)
+    }
+
     pub async fn store_system_state(
         &self,
         state: HashMap<String, serde_json::Value>,
@@ -173,18 +263,19 @@ impl Neo4jContext {
         let timestamp = Utc::now();
         debug!("Preparing Neo4j query for system state at {}", timestamp);
         
-        let state_json = serde_json::to_string(&state)?;
-        debug!("System state serialized to JSON (length: {})", state_json.len());
+        let properties = system_state_properties(&serde_json::Value::Object(state.into_iter().collect()));
+        debug!("System state flattened into {} properties", properties.len());
         
         let query = Query::new(String::from(
             "CREATE (s:SystemState {
                 id: randomUUID(),
-                timestamp: $timestamp,
-                state: $state
-            }) RETURN s"
+                tenant: $tenant,
+                timestamp: $timestamp
+            }) SET s += $properties RETURN s"
         ))
+        .param("tenant", self.tenant.as_str())
         .param("timestamp", timestamp.to_rfc3339())
-        .param("state", state_json);
+        .param("properties", properties);
 
         debug!("Executing Neo4j query to store system state");
         let mut result = match self.graph.execute(query).await {
@@ -204,22 +295,85 @@ impl Neo4jContext {
                     std::io::ErrorKind::NotFound,
                     "No node created"
                 );
-                log::error!("Failed to create system state node: {}", err);
+                tracing::error!("Failed to create system state node: {}", err);
                 Box::new(err)
             })?;
             
         match row.get("s") {
             Ok(node) => {
-                log::info!("Successfully stored system state node");
+                tracing::info!("Successfully stored system state node");
                 Ok(node)
             }
             Err(e) => {
-                log::error!("Failed to get created system state node from result: {}", e);
+                tracing::error!("Failed to get created system state node from result: {}", e);
                 Err(Box::new(e))
             }
         }
     }
 
+    /// Writes a system state snapshot, its metrics and the `CONTAINS`
+    /// relationships between them in one transaction: one query for the
+    /// state and one `UNWIND` over the metrics
+    pub async fn store_snapshot(
+        &self,
+        state: &HashMap<String, serde_json::Value>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let state_id = uuid::Uuid::new_v4().to_string();
+        debug!("Storing snapshot {} with {} metrics", state_id, state.len());
+        let metrics: Vec<BoltType> = state
+            .iter()
+            .map(|(metric_type, value)| {
+                BoltType::from(HashMap::from([
+                    ("id".to_string(), BoltType::from(uuid::Uuid::new_v4().to_string())),
+                    ("type".to_string(), BoltType::from(metric_type.as_str())),
+                    ("properties".to_string(), BoltType::from(metric_properties(value))),
+                ]))
+            })
+            .collect();
+        let state_properties = system_state_properties(&serde_json::Value::Object(
+            state.iter().map(|(key, value)| (key.clone(), value.clone())).collect(),
+        ));
+        let timestamp = timestamp.to_rfc3339();
+
+        self.run_in_transaction(vec![
+            Query::new(String::from(
+                "CREATE (s:SystemState {id: $id, tenant: $tenant, timestamp: $timestamp}) SET s += $properties"
+            ))
+            .param("id", state_id.as_str())
+            .param("tenant", self.tenant.as_str())
+            .param("timestamp", timestamp.as_str())
+            .param("properties", state_properties),
+            Query::new(String::from(
+                "MATCH (s:SystemState {id: $state_id, tenant: $tenant})
+                UNWIND $metrics AS metric
+                CREATE (m:Metric {id: metric.id, tenant: $tenant, type: metric.type, timestamp: $timestamp})
+                SET m += metric.properties
+                MERGE (s)-[c:CONTAINS]->(m)
+                SET c.timestamp = datetime($timestamp)"
+            ))
+            .param("state_id", state_id.as_str())
+            .param("tenant", self.tenant.as_str())
+            .param("timestamp", timestamp.as_str())
+            .param("metrics", metrics),
+        ])
+        .await?;
+        Ok(state_id)
+    }
+
+    /// Runs `queries` in order in one transaction, committing only if all
+    /// of them succeed
+    pub async fn run_in_transaction(&self, queries: Vec<Query>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut txn = self.graph.start_txn().await?;
+        if let Err(e) = txn.run_queries(queries).await {
+            error!("Rolling back transaction: {}", e);
+            txn.rollback().await?;
+            return Err(Box::new(e));
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
     pub async fn create_relationship(
         &self,
         from_id: &str,
@@ -227,7 +381,30 @@ impl Neo4jContext {
         rel_type: RelationType,
         properties: Option<HashMap<String, serde_json::Value>>,
     ) -> Result<Relation, Box<dyn Error + Send + Sync>> {
-        log::debug!("Creating relationship from {} to {}", from_id, to_id);
+        self.write_relationship(from_id, to_id, rel_type, properties, false).await
+    }
+
+    /// Like `create_relationship`, but reuses a relationship of the same
+    /// type between the two nodes if there is one, updating its properties
+    pub async fn merge_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<Relation, Box<dyn Error + Send + Sync>> {
+        self.write_relationship(from_id, to_id, rel_type, properties, true).await
+    }
+
+    async fn write_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+        merge: bool,
+    ) -> Result<Relation, Box<dyn Error + Send + Sync>> {
+        tracing::debug!("{} relationship from {} to {}", if merge { "Merging" } else { "Creating" }, from_id, to_id);
         
         let rel_type_str = match rel_type {
             RelationType::Followed => "FOLLOWED",
@@ -237,37 +414,36 @@ impl Neo4jContext {
             RelationType::Triggered => "TRIGGERED",
         };
         
-        log::debug!("Relationship type: {}", rel_type_str);
-        
-        log::debug!("Relationship type: {}", rel_type_str);        // Convert properties to a format that Neo4j can understand
-        let props: HashMap<String, String> = properties
-            .unwrap_or_default()
-            .into_iter()
-            .map(|(k, v)| (k, v.to_string()))
-            .collect();
+        tracing::debug!("Relationship type: {}", rel_type_str);
+
+        // Convert properties to a format that Neo4j can understand
+        let props = relationship_properties(properties.unwrap_or_default());
             
-        log::debug!("Relationship properties: {:?}", props);
+        tracing::debug!("Relationship properties: {:?}", props);
 
         let query_str = format!(
             "MATCH (a), (b)
-            WHERE a.id = $from_id AND b.id = $to_id
-            CREATE (a)-[r:{}]->(b)
-            SET r = $props
+            WHERE a.id = $from_id AND b.id = $to_id AND a.tenant = $tenant AND b.tenant = $tenant
+            {} (a)-[r:{}]->(b)
+            SET r {} $props
             RETURN r",
-            rel_type_str
+            if merge { "MERGE" } else { "CREATE" },
+            rel_type_str,
+            if merge { "+=" } else { "=" }
         );
-        log::debug!("Built Neo4j query: {}", query_str);
+        tracing::debug!("Built Neo4j query: {}", query_str);
 
         let query = Query::new(query_str)
             .param("from_id", from_id)
             .param("to_id", to_id)
+            .param("tenant", self.tenant.as_str())
             .param("props", props);
 
-        log::debug!("Executing Neo4j query to create relationship");
+        tracing::debug!("Executing Neo4j query to create relationship");
         let mut result = match self.graph.execute(query).await {
             Ok(r) => r,
             Err(e) => {
-                log::error!("Failed to execute relationship creation query: {}", e);
+                tracing::error!("Failed to execute relationship creation query: {}", e);
                 return Err(Box::new(e));
             }
         };
@@ -279,65 +455,436 @@ impl Neo4jContext {
                     std::io::ErrorKind::NotFound,
                     "No relation created"
                 );
-                log::error!("Failed to create relationship: No relation returned");
+                tracing::error!("Failed to create relationship: No relation returned");
                 return Err(Box::new(err));
             }
             Err(e) => {
-                log::error!("Failed to get next result row: {}", e);
+                tracing::error!("Failed to get next result row: {}", e);
                 return Err(Box::new(e));
             }
         };
 
         match row.get("r") {
             Ok(relation) => {
-                log::info!("Successfully created relationship");
+                tracing::info!("Successfully created relationship");
                 Ok(relation)
             }
             Err(e) => {
-                log::error!("Failed to get created relationship from result: {}", e);
+                tracing::error!("Failed to get created relationship from result: {}", e);
                 Err(Box::new(e))
             }
         }
     }
 
+    /// Rewrites `Metric` and `SystemState` nodes stored when values were
+    /// JSON text into typed properties, a batch at a time, and returns how
+    /// many it rewrote. Nodes already migrated are left alone, so this is
+    /// safe to run on every start.
+    pub async fn migrate_property_encoding(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let mut migrated = 0;
+        for (label, field) in [("Metric", "value"), ("SystemState", "state")] {
+            loop {
+                let query = Query::new(format!(
+                    "MATCH (n:{label}) WHERE n.encoding IS NULL
+                    RETURN n.id AS id, coalesce(toString(n.{field}), 'null') AS text
+                    LIMIT $batch"
+                ))
+                .param("batch", MIGRATION_BATCH_SIZE);
+                let mut result = self.graph.execute(query).await?;
+                let mut queries = Vec::new();
+                while let Some(row) = result.next().await? {
+                    let text: String = row.get("text")?;
+                    let value = serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text));
+                    let properties = match label {
+                        "Metric" => metric_properties(&value),
+                        _ => system_state_properties(&value),
+                    };
+                    queries.push(
+                        Query::new(format!("MATCH (n:{label} {{id: $id}}) REMOVE n.{field} SET n += $properties"))
+                            .param("id", row.get::<String>("id")?)
+                            .param("properties", properties),
+                    );
+                }
+                if queries.is_empty() {
+                    break;
+                }
+                migrated += queries.len();
+                debug!("Migrating {} {} nodes to typed properties", queries.len(), label);
+                self.run_in_transaction(queries).await?;
+            }
+        }
+        Ok(migrated)
+    }
+
+    /// Assigns nodes stored before there were tenants to the default tenant,
+    /// a batch at a time, and returns how many it assigned
+    pub async fn migrate_tenants(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let mut migrated = 0;
+        loop {
+            let query = Query::new(String::from(
+                "MATCH (n) WHERE n.tenant IS NULL
+                  AND (n:Metric OR n:SystemState OR n:Workflow OR n:Memory OR n:Entity OR n:Provenance)
+                WITH n LIMIT $batch
+                SET n.tenant = $tenant
+                RETURN count(n) AS count"
+            ))
+            .param("batch", MIGRATION_BATCH_SIZE)
+            .param("tenant", DEFAULT_TENANT);
+            let mut result = self.graph.execute(query).await?;
+            let count = match result.next().await? {
+                Some(row) => row.get::<i64>("count")? as usize,
+                None => 0,
+            };
+            if count == 0 {
+                return Ok(migrated);
+            }
+            migrated += count;
+        }
+    }
+
+    /// Saves a workflow's state as JSON on its `Workflow` node, creating the
+    /// node the first time
+    pub async fn save_workflow(&self, id: &str, state: &serde_json::Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+        debug!("Saving workflow {}", id);
+        let query = Query::new(String::from(
+            "MERGE (w:Workflow {tenant: $tenant, id: $id})
+            SET w.state = $state, w.updated_at = $updated_at"
+        ))
+        .param("tenant", self.tenant.as_str())
+        .param("id", id)
+        .param("state", state.to_string())
+        .param("updated_at", Utc::now().to_rfc3339());
+        self.graph.run(query).await?;
+        Ok(())
+    }
+
+    pub async fn load_workflow(&self, id: &str) -> Result<Option<serde_json::Value>, Box<dyn Error + Send + Sync>> {
+        let query = NodeQuery::label("Workflow").filter("id", id).limit(1);
+        match self.find_nodes(&query).await?.first() {
+            Some(node) => Ok(Some(serde_json::from_str(&node.get::<String>("state")?)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_workflows(&self) -> Result<Vec<serde_json::Value>, Box<dyn Error + Send + Sync>> {
+        let query = NodeQuery::label("Workflow").newest_first("updated_at");
+        let mut workflows = Vec::new();
+        for node in self.find_nodes(&query).await? {
+            workflows.push(serde_json::from_str(&node.get::<String>("state")?)?);
+        }
+        Ok(workflows)
+    }
+
+    /// Saves a memory on its `Memory` node, keyed by `key`
+    pub async fn remember(&self, memory: &super::Memory) -> Result<(), Box<dyn Error + Send + Sync>> {
+        debug!("Remembering {}", memory.key);
+        let query = Query::new(String::from(
+            "MERGE (m:Memory {tenant: $tenant, key: $key})
+            SET m.value = $value, m.stored_at = $stored_at, m.expires_at = $expires_at"
+        ))
+        .param("tenant", self.tenant.as_str())
+        .param("key", memory.key.as_str())
+        .param("value", memory.value.to_string())
+        .param("stored_at", memory.stored_at.to_rfc3339())
+        .param("expires_at", memory.expires_at.map(|expires_at| expires_at.to_rfc3339()));
+        self.graph.run(query).await?;
+        Ok(())
+    }
+
+    pub async fn recall(&self, key: &str) -> Result<Option<super::Memory>, Box<dyn Error + Send + Sync>> {
+        let query = Query::new(format!("MATCH (m:Memory {{tenant: $tenant, key: $key}}) {}", MEMORY_FIELDS))
+            .param("tenant", self.tenant.as_str())
+            .param("key", key);
+        let mut result = self.graph.execute(query).await?;
+        match result.next().await? {
+            Some(row) => Ok(Some(memory_from_row(&row)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn search_memories(&self, text: &str, limit: usize) -> Result<Vec<super::Memory>, Box<dyn Error + Send + Sync>> {
+        let query = Query::new(format!(
+            "MATCH (m:Memory {{tenant: $tenant}})
+            WHERE (toLower(m.key) CONTAINS toLower($text) OR toLower(m.value) CONTAINS toLower($text))
+              AND (m.expires_at IS NULL OR m.expires_at > $now)
+            WITH m ORDER BY m.stored_at DESC LIMIT $limit
+            {}",
+            MEMORY_FIELDS
+        ))
+        .param("tenant", self.tenant.as_str())
+        .param("text", text)
+        .param("now", Utc::now().to_rfc3339())
+        .param("limit", limit as i64);
+        let mut result = self.graph.execute(query).await?;
+        let mut memories = Vec::new();
+        while let Some(row) = result.next().await? {
+            memories.push(memory_from_row(&row)?);
+        }
+        Ok(memories)
+    }
+
+    /// Writes a `Provenance` node for the text, an `Entity` node per entity
+    /// `MENTIONED_IN` it and a `RELATES` relationship per relation, all in
+    /// one transaction
+    pub async fn ingest_knowledge(
+        &self,
+        provenance: &super::Provenance,
+        entities: &[super::Entity],
+        relations: &[super::Relation],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        debug!("Ingesting {} entities and {} relations from {}", entities.len(), relations.len(), provenance.id);
+        let mut queries = vec![Query::new(String::from(
+            "CREATE (p:Provenance {id: $id, tenant: $tenant, text: $text, source: $source, model: $model, ingested_at: $ingested_at})"
+        ))
+        .param("id", provenance.id.as_str())
+        .param("tenant", self.tenant.as_str())
+        .param("text", provenance.text.as_str())
+        .param("source", provenance.source.clone())
+        .param("model", provenance.model.clone())
+        .param("ingested_at", provenance.ingested_at.to_rfc3339())];
+        for entity in entities {
+            queries.push(
+                Query::new(String::from(
+                    "MATCH (p:Provenance {id: $provenance, tenant: $tenant})
+                    MERGE (e:Entity {tenant: $tenant, name: $name})
+                    SET e.type = CASE WHEN $type = '' THEN coalesce(e.type, '') ELSE $type END
+                    MERGE (e)-[:MENTIONED_IN]->(p)"
+                ))
+                .param("provenance", provenance.id.as_str())
+                .param("tenant", self.tenant.as_str())
+                .param("name", entity.name.as_str())
+                .param("type", entity.kind.as_str()),
+            );
+        }
+        for relation in relations {
+            queries.push(
+                Query::new(String::from(
+                    "MATCH (a:Entity {tenant: $tenant, name: $source}), (b:Entity {tenant: $tenant, name: $target})
+                    MERGE (a)-[:RELATES {type: $relation, provenance: $provenance}]->(b)"
+                ))
+                .param("tenant", self.tenant.as_str())
+                .param("source", relation.source.as_str())
+                .param("target", relation.target.as_str())
+                .param("relation", relation.relation.as_str())
+                .param("provenance", provenance.id.as_str()),
+            );
+        }
+        self.run_in_transaction(queries).await
+    }
+
+    pub async fn query_knowledge(&self, terms: &[String], limit: usize) -> Result<super::KnowledgeGraph, Box<dyn Error + Send + Sync>> {
+        let mut graph = super::KnowledgeGraph::default();
+        let query = Query::new(String::from(
+            "MATCH (e:Entity {tenant: $tenant}) WHERE any(term IN $terms WHERE toLower(e.name) CONTAINS term)
+            RETURN e.name AS name, coalesce(e.type, '') AS type ORDER BY e.name LIMIT $limit"
+        ))
+        .param("tenant", self.tenant.as_str())
+        .param("terms", terms.to_vec())
+        .param("limit", limit as i64);
+        let mut result = self.graph.execute(query).await?;
+        while let Some(row) = result.next().await? {
+            graph.entities.push(super::Entity { name: row.get("name")?, kind: row.get("type")? });
+        }
+
+        let names: Vec<String> = graph.entities.iter().map(|entity| entity.name.clone()).collect();
+        let query = Query::new(String::from(
+            "MATCH (a:Entity {tenant: $tenant})-[r:RELATES]->(b:Entity {tenant: $tenant})
+            WHERE a.name IN $names OR b.name IN $names
+            RETURN a.name AS source, coalesce(a.type, '') AS source_type, r.type AS relation,
+                   b.name AS target, coalesce(b.type, '') AS target_type, r.provenance AS provenance"
+        ))
+        .param("tenant", self.tenant.as_str())
+        .param("names", names);
+        let mut result = self.graph.execute(query).await?;
+        while let Some(row) = result.next().await? {
+            // Neighbours are part of the answer too
+            for (name, kind) in [("source", "source_type"), ("target", "target_type")] {
+                let name: String = row.get(name)?;
+                if !graph.entities.iter().any(|entity| entity.name == name) {
+                    graph.entities.push(super::Entity { name, kind: row.get(kind)? });
+                }
+            }
+            graph.relations.push(super::Relation {
+                source: row.get("source")?,
+                relation: row.get("relation")?,
+                target: row.get("target")?,
+                provenance: Some(row.get("provenance")?),
+            });
+        }
+
+        let mut ids: Vec<String> = graph.relations.iter().filter_map(|relation| relation.provenance.clone()).collect();
+        ids.sort();
+        ids.dedup();
+        let query = Query::new(String::from(
+            "MATCH (p:Provenance {tenant: $tenant}) WHERE p.id IN $ids
+            RETURN p.id AS id, p.text AS text, coalesce(p.source, '') AS source, coalesce(p.model, '') AS model,
+                   p.ingested_at AS ingested_at
+            ORDER BY p.ingested_at DESC"
+        ))
+        .param("tenant", self.tenant.as_str())
+        .param("ids", ids);
+        let mut result = self.graph.execute(query).await?;
+        while let Some(row) = result.next().await? {
+            let optional = |text: String| if text.is_empty() { None } else { Some(text) };
+            graph.sources.push(super::Provenance {
+                id: row.get("id")?,
+                text: row.get("text")?,
+                source: optional(row.get("source")?),
+                model: optional(row.get("model")?),
+                ingested_at: DateTime::parse_from_rfc3339(&row.get::<String>("ingested_at")?)?.with_timezone(&Utc),
+            });
+        }
+        Ok(graph)
+    }
+
+    /// Nodes of a type from the last `time_window`, oldest first
     pub async fn find_patterns(
         &self,
         node_type: ContextNodeType,
         time_window: chrono::Duration,
     ) -> Result<Vec<Node>, Box<dyn Error + Send + Sync>> {
-        let node_type_str = match node_type {
-            ContextNodeType::Metric => "Metric",
-            ContextNodeType::SystemState => "SystemState",
-            ContextNodeType::UserInteraction => "UserInteraction",
-            ContextNodeType::ToolExecution => "ToolExecution",
-            ContextNodeType::Pattern => "Pattern",
-        };
-
-        let since = (Utc::now() - time_window).to_rfc3339();
-        
-        let query_str = format!(
-            "MATCH (n:{})
-            WHERE n.timestamp >= $since
-            WITH n
-            ORDER BY n.timestamp
-            RETURN n",
-            node_type_str
-        );
-        
-        let query = Query::new(query_str)
-            .param("since", since);
+        let query = Query::new(format!(
+            "MATCH (n:{}) WHERE n.tenant = $tenant AND n.timestamp >= $since RETURN n ORDER BY n.timestamp",
+            node_type.label()
+        ))
+        .param("tenant", self.tenant.as_str())
+        .param("since", (Utc::now() - time_window).to_rfc3339());
 
         let mut result = self.graph.execute(query).await?;
         let mut nodes = Vec::new();
-        
         while let Some(row) = result.next().await? {
             nodes.push(row.get("n")?);
         }
+        Ok(nodes)
+    }
 
+    /// The nodes of this tenant that `query` matches
+    pub async fn find_nodes(&self, query: &NodeQuery) -> Result<Vec<Node>, Box<dyn Error + Send + Sync>> {
+        let query = query.clone().filter("tenant", self.tenant.as_str());
+        debug!("Finding nodes: {}", query.build().0);
+        let mut result = self.graph.execute(query.to_query()).await?;
+        let mut nodes = Vec::new();
+        while let Some(row) = result.next().await? {
+            nodes.push(row.get("n")?);
+        }
         Ok(nodes)
     }
 }
 
+/// Nodes rewritten per transaction by `migrate_property_encoding` and
+/// `migrate_tenants`
+const MIGRATION_BATCH_SIZE: i64 = 500;
+
+/// Marks nodes whose values are typed properties; nodes without it still
+/// hold JSON text
+const PROPERTY_ENCODING: &str = "properties";
+
+/// Adds the properties storing `value` as `name`: scalars as they are,
+/// objects flattened into `name_key` properties, lists of one kind of scalar
+/// as lists and other lists as JSON text. Nulls are left out, since Neo4j
+/// cannot store them.
+fn flatten_properties(name: &str, value: &serde_json::Value, properties: &mut HashMap<String, BoltType>) {
+    use serde_json::Value;
+    match value {
+        Value::Null => {}
+        Value::Bool(flag) => {
+            properties.insert(name.to_string(), (*flag).into());
+        }
+        Value::Number(number) => {
+            properties.insert(name.to_string(), number_property(number));
+        }
+        Value::String(text) => {
+            properties.insert(name.to_string(), text.as_str().into());
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                flatten_properties(&format!("{}_{}", name, key), field, properties);
+            }
+        }
+        Value::Array(items) => {
+            let list: Option<Vec<BoltType>> = if items.iter().all(Value::is_boolean) {
+                Some(items.iter().filter_map(Value::as_bool).map(BoltType::from).collect())
+            } else if items.iter().all(Value::is_i64) {
+                Some(items.iter().filter_map(Value::as_i64).map(BoltType::from).collect())
+            } else if items.iter().all(Value::is_number) {
+                Some(items.iter().filter_map(Value::as_f64).map(BoltType::from).collect())
+            } else if items.iter().all(Value::is_string) {
+                Some(items.iter().filter_map(Value::as_str).map(BoltType::from).collect())
+            } else {
+                None
+            };
+            let property = list.map_or_else(|| value.to_string().into(), BoltType::from);
+            properties.insert(name.to_string(), property);
+        }
+    }
+}
+
+fn number_property(number: &serde_json::Number) -> BoltType {
+    match number.as_i64() {
+        Some(integer) => integer.into(),
+        None => number.as_f64().unwrap_or(f64::NAN).into(),
+    }
+}
+
+/// Relationship properties with their types kept: RFC 3339 strings become
+/// datetimes, and other values are stored as by `flatten_properties`
+fn relationship_properties(properties: HashMap<String, serde_json::Value>) -> HashMap<String, BoltType> {
+    let mut converted = HashMap::new();
+    for (name, value) in properties {
+        match value.as_str().and_then(|text| DateTime::parse_from_rfc3339(text).ok()) {
+            Some(time) => {
+                converted.insert(name, time.into());
+            }
+            None => flatten_properties(&name, &value, &mut converted),
+        }
+    }
+    converted
+}
+
+/// `value` or `value_*` properties for a metric reading, with its JSON type
+/// in `value_type`
+fn metric_properties(value: &serde_json::Value) -> HashMap<String, BoltType> {
+    let value_type = match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "list",
+        serde_json::Value::Object(_) => "map",
+    };
+    let mut properties = HashMap::from([
+        ("encoding".to_string(), PROPERTY_ENCODING.into()),
+        ("value_type".to_string(), value_type.into()),
+    ]);
+    flatten_properties("value", value, &mut properties);
+    properties
+}
+
+/// `state_*` properties for a system state snapshot
+fn system_state_properties(state: &serde_json::Value) -> HashMap<String, BoltType> {
+    let mut properties = HashMap::from([("encoding".to_string(), PROPERTY_ENCODING.into())]);
+    flatten_properties("state", state, &mut properties);
+    properties
+}
+
+/// The columns `memory_from_row` reads; a memory without expiry has an
+/// empty `expires_at`
+const MEMORY_FIELDS: &str =
+    "RETURN m.key AS key, m.value AS value, m.stored_at AS stored_at, coalesce(m.expires_at, '') AS expires_at";
+
+fn memory_from_row(row: &neo4rs::Row) -> Result<super::Memory, Box<dyn Error + Send + Sync>> {
+    let time = |text: String| -> Result<DateTime<Utc>, chrono::ParseError> {
+        Ok(DateTime::parse_from_rfc3339(&text)?.with_timezone(&Utc))
+    };
+    let expires_at = row.get::<String>("expires_at")?;
+    Ok(super::Memory {
+        key: row.get("key")?,
+        value: serde_json::from_str(&row.get::<String>("value")?)?,
+        stored_at: time(row.get("stored_at")?)?,
+        expires_at: if expires_at.is_empty() { None } else { Some(time(expires_at)?) },
+    })
+}
+
 // Helper function to get or initialize Neo4j client
 pub async fn get_neo4j_context() -> Result<Arc<Neo4jContext>, Box<dyn Error + Send + Sync>> {
     let mut client = NEO4J_CLIENT.lock().await;
@@ -369,7 +916,7 @@ pub async fn get_neo4j_context() -> Result<Arc<Neo4jContext>, Box<dyn Error + Se
         let password = match std::env::var("NEO4J_PASSWORD") {
             Ok(p) => {
                 debug!("Found Neo4j password in environment");
-                p
+                crate::secrets::Secrets::global().resolve(&p.into()).await?
             },
             Err(_) => {
                 error!("NEO4J_PASSWORD environment variable is required");
@@ -396,7 +943,7 @@ pub async fn get_neo4j_context() -> Result<Arc<Neo4jContext>, Box<dyn Error + Se
         debug!("Reusing existing Neo4j connection");
         let graph = client.as_ref().unwrap().clone();
         debug!("Creating Neo4jContext from existing connection");
-        Ok(Arc::new(Neo4jContext { graph }))
+        Ok(Arc::new(Neo4jContext { graph, tenant: DEFAULT_TENANT.to_string() }))
     }
 }
 
@@ -664,4 +1211,57 @@ mod tests {
         let error_msg = result.unwrap_err().to_string();
         assert!(!error_msg.is_empty());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_metric_values_become_typed_properties() {
+        let properties = metric_properties(&json!(42.5));
+        assert_eq!(properties["value"], BoltType::from(42.5));
+        assert_eq!(properties["value_type"], BoltType::from("number"));
+        assert_eq!(properties["encoding"], BoltType::from(PROPERTY_ENCODING));
+
+        let properties = metric_properties(&json!({
+            "total": 16384,
+            "swap": {"used": 0.5, "enabled": true},
+            "note": null,
+            "mounts": ["/", "/data"],
+            "mixed": [1, "two"]
+        }));
+        assert!(!properties.contains_key("value"));
+        assert_eq!(properties["value_type"], BoltType::from("map"));
+        assert_eq!(properties["value_total"], BoltType::from(16384i64));
+        assert_eq!(properties["value_swap_used"], BoltType::from(0.5));
+        assert_eq!(properties["value_swap_enabled"], BoltType::from(true));
+        assert!(!properties.contains_key("value_note"));
+        assert_eq!(properties["value_mounts"], BoltType::from(vec!["/", "/data"]));
+        assert_eq!(properties["value_mixed"], BoltType::from("[1,\"two\"]"));
+    }
+
+    #[test]
+    fn test_system_state_is_flattened() {
+        let properties = system_state_properties(&json!({"cpu_usage": 12, "memory": {"used": 2048}}));
+        assert_eq!(properties["state_cpu_usage"], BoltType::from(12i64));
+        assert_eq!(properties["state_memory_used"], BoltType::from(2048i64));
+        assert_eq!(properties.len(), 3);
+    }
+
+    #[test]
+    fn test_relationship_properties_keep_their_types() {
+        let properties = relationship_properties(HashMap::from([
+            ("weight".to_string(), json!(0.75)),
+            ("count".to_string(), json!(3)),
+            ("confirmed".to_string(), json!(true)),
+            ("label".to_string(), json!("daily")),
+            ("timestamp".to_string(), json!("2024-05-01T12:00:00+00:00")),
+            ("ignored".to_string(), json!(null)),
+        ]));
+        assert_eq!(properties["weight"], BoltType::from(0.75));
+        assert_eq!(properties["count"], BoltType::from(3i64));
+        assert_eq!(properties["confirmed"], BoltType::from(true));
+        assert_eq!(properties["label"], BoltType::from("daily"));
+        assert_eq!(
+            properties["timestamp"],
+            BoltType::from(DateTime::parse_from_rfc3339("2024-05-01T12:00:00+00:00").unwrap())
+        );
+        assert!(!properties.contains_key("ignored"));
+    }
+}
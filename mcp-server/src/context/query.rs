@@ -0,0 +1,103 @@
+use neo4rs::{BoltType, Query};
+
+/// Cypher that matches nodes of one label, built from typed parts instead
+/// of formatted strings. Values always travel as parameters and property
+/// names are quoted, so nothing a caller passes can change the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeQuery {
+    label: &'static str,
+    filters: Vec<(String, BoltType)>,
+    newest_first: Option<String>,
+    limit: Option<usize>,
+}
+
+impl NodeQuery {
+    /// Nodes with any label known at compile time, such as `Workflow`
+    pub fn label(label: &'static str) -> Self {
+        Self { label, filters: Vec::new(), newest_first: None, limit: None }
+    }
+
+    /// Only nodes whose `property` equals `value`
+    pub fn filter(mut self, property: &str, value: impl Into<BoltType>) -> Self {
+        self.filters.push((property.to_string(), value.into()));
+        self
+    }
+
+    /// Sort by `property`, largest first
+    pub fn newest_first(mut self, property: &str) -> Self {
+        self.newest_first = Some(property.to_string());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// The Cypher text, returning each node as `n`, and its parameters
+    pub fn build(&self) -> (String, Vec<(String, BoltType)>) {
+        let mut cypher = format!("MATCH (n:{})", self.label);
+        let mut params = Vec::new();
+        for (i, (property, value)) in self.filters.iter().enumerate() {
+            let name = format!("p{}", i);
+            cypher.push_str(if i == 0 { " WHERE " } else { " AND " });
+            cypher.push_str(&format!("n.{} = ${}", quote(property), name));
+            params.push((name, value.clone()));
+        }
+        cypher.push_str(" RETURN n");
+        if let Some(property) = &self.newest_first {
+            cypher.push_str(&format!(" ORDER BY n.{} DESC", quote(property)));
+        }
+        if let Some(limit) = self.limit {
+            cypher.push_str(" LIMIT $limit");
+            params.push(("limit".to_string(), (limit as i64).into()));
+        }
+        (cypher, params)
+    }
+
+    pub fn to_query(&self) -> Query {
+        let (cypher, params) = self.build();
+        params.into_iter().fold(Query::new(cypher), |query, (name, value)| query.param(&name, value))
+    }
+}
+
+/// A property name as a quoted Cypher identifier
+fn quote(property: &str) -> String {
+    format!("`{}`", property.replace('`', "``"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_become_parameters() {
+        let (cypher, params) = NodeQuery::label("Workflow")
+            .filter("id", "nightly")
+            .filter("tenant", "alpha")
+            .newest_first("updated_at")
+            .limit(10)
+            .build();
+
+        assert_eq!(
+            cypher,
+            "MATCH (n:Workflow) WHERE n.`id` = $p0 AND n.`tenant` = $p1 RETURN n ORDER BY n.`updated_at` DESC LIMIT $limit"
+        );
+        assert_eq!(
+            params,
+            vec![
+                ("p0".to_string(), BoltType::from("nightly")),
+                ("p1".to_string(), BoltType::from("alpha")),
+                ("limit".to_string(), BoltType::from(10i64)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_property_names_cannot_escape_their_quotes() {
+        let (cypher, _) = NodeQuery::label("Workflow")
+            .filter("id` = 1 DETACH DELETE n //", "x")
+            .build();
+        assert_eq!(cypher, "MATCH (n:Workflow) WHERE n.`id`` = 1 DETACH DELETE n //` = $p0 RETURN n");
+    }
+}
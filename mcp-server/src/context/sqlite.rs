@@ -0,0 +1,653 @@
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info};
+
+use super::{ContextStore, DEFAULT_TENANT, Entity, KnowledgeGraph, Memory, MetricAggregate, MetricBucket, Provenance, Relation, RelationType};
+
+/// Schema changes in the order they were made. A database records how many
+/// it has applied in `PRAGMA user_version`, so only append to this list.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE metrics (
+        id TEXT PRIMARY KEY,
+        type TEXT NOT NULL,
+        value TEXT NOT NULL,
+        number REAL,
+        timestamp TEXT NOT NULL
+    );
+    CREATE INDEX metrics_type_timestamp ON metrics (type, timestamp);
+    CREATE TABLE system_states (
+        id TEXT PRIMARY KEY,
+        state TEXT NOT NULL,
+        timestamp TEXT NOT NULL
+    );
+    CREATE TABLE relationships (
+        from_id TEXT NOT NULL,
+        to_id TEXT NOT NULL,
+        type TEXT NOT NULL,
+        properties TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX relationships_ends ON relationships (from_id, to_id, type);
+    CREATE TABLE workflows (
+        id TEXT PRIMARY KEY,
+        state TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE memories (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL,
+        stored_at TEXT NOT NULL,
+        expires_at TEXT
+    )",
+    "CREATE TABLE entities (
+        name TEXT PRIMARY KEY,
+        type TEXT NOT NULL DEFAULT ''
+    );
+    CREATE TABLE provenance (
+        id TEXT PRIMARY KEY,
+        text TEXT NOT NULL,
+        source TEXT,
+        model TEXT,
+        ingested_at TEXT NOT NULL
+    );
+    CREATE TABLE relations (
+        source TEXT NOT NULL REFERENCES entities (name),
+        relation TEXT NOT NULL,
+        target TEXT NOT NULL REFERENCES entities (name),
+        provenance TEXT NOT NULL REFERENCES provenance (id),
+        UNIQUE (source, relation, target, provenance)
+    )",
+    // Tenants: names are unique per tenant, and data from before belongs
+    // to the default one
+    "ALTER TABLE metrics ADD COLUMN tenant TEXT NOT NULL DEFAULT 'default';
+    DROP INDEX metrics_type_timestamp;
+    CREATE INDEX metrics_tenant_type_timestamp ON metrics (tenant, type, timestamp);
+    ALTER TABLE system_states ADD COLUMN tenant TEXT NOT NULL DEFAULT 'default';
+    ALTER TABLE relationships ADD COLUMN tenant TEXT NOT NULL DEFAULT 'default';
+    DROP INDEX relationships_ends;
+    CREATE INDEX relationships_ends ON relationships (tenant, from_id, to_id, type);
+    ALTER TABLE provenance ADD COLUMN tenant TEXT NOT NULL DEFAULT 'default';
+    CREATE TABLE workflows_by_tenant (
+        tenant TEXT NOT NULL,
+        id TEXT NOT NULL,
+        state TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        PRIMARY KEY (tenant, id)
+    );
+    INSERT INTO workflows_by_tenant SELECT 'default', id, state, updated_at FROM workflows;
+    DROP TABLE workflows;
+    ALTER TABLE workflows_by_tenant RENAME TO workflows;
+    CREATE TABLE memories_by_tenant (
+        tenant TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        stored_at TEXT NOT NULL,
+        expires_at TEXT,
+        PRIMARY KEY (tenant, key)
+    );
+    INSERT INTO memories_by_tenant SELECT 'default', key, value, stored_at, expires_at FROM memories;
+    DROP TABLE memories;
+    ALTER TABLE memories_by_tenant RENAME TO memories;
+    CREATE TABLE relations_by_tenant (
+        tenant TEXT NOT NULL,
+        source TEXT NOT NULL,
+        relation TEXT NOT NULL,
+        target TEXT NOT NULL,
+        provenance TEXT NOT NULL REFERENCES provenance (id),
+        UNIQUE (tenant, source, relation, target, provenance)
+    );
+    INSERT INTO relations_by_tenant SELECT 'default', source, relation, target, provenance FROM relations;
+    DROP TABLE relations;
+    ALTER TABLE relations_by_tenant RENAME TO relations;
+    CREATE TABLE entities_by_tenant (
+        tenant TEXT NOT NULL,
+        name TEXT NOT NULL,
+        type TEXT NOT NULL DEFAULT '',
+        PRIMARY KEY (tenant, name)
+    );
+    INSERT INTO entities_by_tenant SELECT 'default', name, type FROM entities;
+    DROP TABLE entities;
+    ALTER TABLE entities_by_tenant RENAME TO entities",
+];
+
+/// Times are stored as fixed-width UTC text so that they sort and compare
+/// as strings
+fn time_text(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn parse_time(text: &str) -> rusqlite::Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+/// The number a reading stands for, as Neo4j's `toFloat` reads it
+fn metric_number(value: &serde_json::Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_str().and_then(|text| text.trim().parse().ok()))
+}
+
+fn memory_from_row(row: &Row) -> rusqlite::Result<Memory> {
+    let value: String = row.get("value")?;
+    Ok(Memory {
+        key: row.get("key")?,
+        value: serde_json::from_str(&value)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))?,
+        stored_at: parse_time(&row.get::<_, String>("stored_at")?)?,
+        expires_at: row.get::<_, Option<String>>("expires_at")?.as_deref().map(parse_time).transpose()?,
+    })
+}
+
+/// A context store in a single SQLite file, for running the context and
+/// memory features without a Neo4j server
+pub struct SqliteContextStore {
+    connection: Arc<Mutex<Connection>>,
+    /// Tenant every statement is limited to
+    tenant: String,
+}
+
+impl SqliteContextStore {
+    /// The store in the database file at `path`, created and migrated as
+    /// needed; `:memory:` keeps it only for the life of the process
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        info!("Opening SQLite context store {}", path.display());
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    fn with_connection(mut connection: Connection) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Self::migrate(&mut connection)?;
+        Ok(Self { connection: Arc::new(Mutex::new(connection)), tenant: DEFAULT_TENANT.to_string() })
+    }
+
+    /// Applies the migrations the database has not seen yet, each in its own transaction
+    fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+        let applied: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+            debug!("Migrating SQLite context store to version {}", version + 1);
+            let transaction = connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", version + 1)?;
+            transaction.commit()?;
+        }
+        Ok(())
+    }
+
+    fn insert_metric(
+        &self,
+        transaction: &Transaction,
+        metric_type: &str,
+        value: &serde_json::Value,
+        timestamp: DateTime<Utc>,
+    ) -> rusqlite::Result<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        transaction.execute(
+            "INSERT INTO metrics (id, tenant, type, value, number, timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, self.tenant, metric_type, value.to_string(), metric_number(value), time_text(timestamp)],
+        )?;
+        Ok(id)
+    }
+
+    fn insert_system_state(
+        &self,
+        transaction: &Transaction,
+        state: &HashMap<String, serde_json::Value>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let id = uuid::Uuid::new_v4().to_string();
+        transaction.execute(
+            "INSERT INTO system_states (id, tenant, state, timestamp) VALUES (?1, ?2, ?3, ?4)",
+            params![id, self.tenant, serde_json::to_string(state)?, time_text(timestamp)],
+        )?;
+        Ok(id)
+    }
+
+    /// Adds a relationship, or with `merge` updates the properties of one of
+    /// the same type between the same records if there is one
+    fn write_relationship(
+        &self,
+        transaction: &Transaction,
+        from_id: &str,
+        to_id: &str,
+        rel_type: &RelationType,
+        properties: Option<&HashMap<String, serde_json::Value>>,
+        merge: bool,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let rel_type = format!("{:?}", rel_type);
+        let properties = properties.cloned().unwrap_or_default();
+        if merge {
+            let existing: Option<(i64, String)> = transaction
+                .query_row(
+                    "SELECT rowid, properties FROM relationships
+                     WHERE tenant = ?1 AND from_id = ?2 AND to_id = ?3 AND type = ?4",
+                    params![self.tenant, from_id, to_id, rel_type],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            if let Some((rowid, existing)) = existing {
+                let mut merged: HashMap<String, serde_json::Value> = serde_json::from_str(&existing)?;
+                merged.extend(properties);
+                transaction.execute(
+                    "UPDATE relationships SET properties = ?2 WHERE rowid = ?1",
+                    params![rowid, serde_json::to_string(&merged)?],
+                )?;
+                return Ok(());
+            }
+        }
+        transaction.execute(
+            "INSERT INTO relationships (tenant, from_id, to_id, type, properties, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![self.tenant, from_id, to_id, rel_type, serde_json::to_string(&properties)?, time_text(Utc::now())],
+        )?;
+        Ok(())
+    }
+
+    /// Runs `write` in a transaction that is committed only if it succeeds
+    fn transact<T>(
+        &self,
+        write: impl FnOnce(&Transaction) -> Result<T, Box<dyn Error + Send + Sync>>,
+    ) -> Result<T, Box<dyn Error + Send + Sync>> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let result = write(&transaction)?;
+        transaction.commit()?;
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl ContextStore for SqliteContextStore {
+    fn with_tenant(self: Arc<Self>, tenant: &str) -> Arc<dyn ContextStore> {
+        Arc::new(SqliteContextStore { connection: Arc::clone(&self.connection), tenant: tenant.to_string() })
+    }
+
+    async fn store_metric(
+        &self,
+        metric_type: &str,
+        value: serde_json::Value,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.transact(|transaction| Ok(self.insert_metric(transaction, metric_type, &value, timestamp)?))
+    }
+
+    async fn store_system_state(
+        &self,
+        state: HashMap<String, serde_json::Value>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.transact(|transaction| self.insert_system_state(transaction, &state, Utc::now()))
+    }
+
+    async fn aggregate_metrics(
+        &self,
+        metric_type: &str,
+        window: chrono::Duration,
+        aggregate: MetricAggregate,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<MetricBucket>, Box<dyn Error + Send + Sync>> {
+        let window = window.num_seconds().max(1);
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT timestamp, number FROM metrics
+             WHERE tenant = ?1 AND type = ?2 AND timestamp >= ?3 AND number IS NOT NULL ORDER BY timestamp",
+        )?;
+        let readings = statement
+            .query_map(params![self.tenant, metric_type, time_text(since)], |row| {
+                Ok((parse_time(&row.get::<_, String>(0)?)?, row.get::<_, f64>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut buckets: BTreeMap<i64, Vec<f64>> = BTreeMap::new();
+        for (timestamp, number) in readings {
+            let seconds = timestamp.timestamp();
+            buckets.entry(seconds - seconds.rem_euclid(window)).or_default().push(number);
+        }
+        Ok(buckets
+            .into_iter()
+            .map(|(start, values)| MetricBucket {
+                start: DateTime::from_timestamp(start, 0).unwrap_or_default(),
+                value: aggregate.apply(&values),
+                count: values.len() as u64,
+            })
+            .collect())
+    }
+
+    async fn create_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.transact(|transaction| {
+            self.write_relationship(transaction, from_id, to_id, &rel_type, properties.as_ref(), false)
+        })
+    }
+
+    async fn merge_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.transact(|transaction| {
+            self.write_relationship(transaction, from_id, to_id, &rel_type, properties.as_ref(), true)
+        })
+    }
+
+    async fn store_snapshot(
+        &self,
+        state: HashMap<String, serde_json::Value>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.transact(|transaction| {
+            let state_id = self.insert_system_state(transaction, &state, timestamp)?;
+            let properties = HashMap::from([("timestamp".to_string(), serde_json::json!(timestamp.to_rfc3339()))]);
+            for (metric_type, value) in &state {
+                let metric_id = self.insert_metric(transaction, metric_type, value, timestamp)?;
+                self.write_relationship(transaction, &state_id, &metric_id, &RelationType::Contains, Some(&properties), true)?;
+            }
+            Ok(state_id)
+        })
+    }
+
+    async fn save_workflow(&self, id: &str, state: serde_json::Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.connection.lock().unwrap().execute(
+            "INSERT INTO workflows (tenant, id, state, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (tenant, id) DO UPDATE SET state = excluded.state, updated_at = excluded.updated_at",
+            params![self.tenant, id, state.to_string(), time_text(Utc::now())],
+        )?;
+        Ok(())
+    }
+
+    async fn load_workflow(&self, id: &str) -> Result<Option<serde_json::Value>, Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.lock().unwrap();
+        let state: Option<String> = connection
+            .query_row(
+                "SELECT state FROM workflows WHERE tenant = ?1 AND id = ?2",
+                [self.tenant.as_str(), id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(state.map(|state| serde_json::from_str(&state)).transpose()?)
+    }
+
+    async fn list_workflows(&self) -> Result<Vec<serde_json::Value>, Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement =
+            connection.prepare("SELECT state FROM workflows WHERE tenant = ?1 ORDER BY updated_at DESC, rowid DESC")?;
+        let states = statement
+            .query_map([&self.tenant], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(states.iter().map(|state| serde_json::from_str(state)).collect::<Result<_, _>>()?)
+    }
+
+    async fn remember(&self, memory: Memory) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO memories (tenant, key, value, stored_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                self.tenant,
+                memory.key,
+                memory.value.to_string(),
+                time_text(memory.stored_at),
+                memory.expires_at.map(time_text),
+            ],
+        )?;
+        Ok(())
+    }
+
+    async fn recall(&self, key: &str) -> Result<Option<Memory>, Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.lock().unwrap();
+        let memory = connection
+            .query_row(
+                "SELECT * FROM memories WHERE tenant = ?1 AND key = ?2",
+                [self.tenant.as_str(), key],
+                memory_from_row,
+            )
+            .optional()?;
+        Ok(memory.filter(|memory| !memory.is_expired(Utc::now())))
+    }
+
+    async fn search_memories(&self, text: &str, limit: usize) -> Result<Vec<Memory>, Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT * FROM memories
+             WHERE tenant = ?4
+               AND (instr(lower(key), lower(?1)) > 0 OR instr(lower(value), lower(?1)) > 0)
+               AND (expires_at IS NULL OR expires_at > ?2)
+             ORDER BY stored_at DESC LIMIT ?3",
+        )?;
+        let memories = statement
+            .query_map(params![text, time_text(Utc::now()), limit as i64, self.tenant], memory_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(memories)
+    }
+
+    async fn ingest_knowledge(
+        &self,
+        provenance: Provenance,
+        entities: Vec<Entity>,
+        relations: Vec<Relation>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        debug!("Ingesting {} entities and {} relations from {}", entities.len(), relations.len(), provenance.id);
+        self.transact(|transaction| {
+            transaction.execute(
+                "INSERT INTO provenance (id, tenant, text, source, model, ingested_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    provenance.id,
+                    self.tenant,
+                    provenance.text,
+                    provenance.source,
+                    provenance.model,
+                    time_text(provenance.ingested_at),
+                ],
+            )?;
+            for entity in &entities {
+                transaction.execute(
+                    "INSERT INTO entities (tenant, name, type) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (tenant, name) DO UPDATE SET type = CASE WHEN excluded.type = '' THEN type ELSE excluded.type END",
+                    params![self.tenant, entity.name, entity.kind],
+                )?;
+            }
+            for relation in &relations {
+                transaction.execute(
+                    "INSERT OR IGNORE INTO relations (tenant, source, relation, target, provenance)
+                     SELECT ?5, ?1, ?2, ?3, ?4
+                     WHERE EXISTS (SELECT 1 FROM entities WHERE tenant = ?5 AND name = ?1)
+                       AND EXISTS (SELECT 1 FROM entities WHERE tenant = ?5 AND name = ?3)",
+                    params![relation.source, relation.relation, relation.target, provenance.id, self.tenant],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    async fn query_knowledge(&self, terms: &[String], limit: usize) -> Result<KnowledgeGraph, Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.lock().unwrap();
+        let mut graph = KnowledgeGraph::default();
+
+        let mut statement = connection.prepare("SELECT name, type FROM entities WHERE tenant = ?1 ORDER BY name")?;
+        let entities = statement.query_map([&self.tenant], |row| Ok(Entity { name: row.get(0)?, kind: row.get(1)? }))?;
+        for entity in entities {
+            let entity = entity?;
+            let name = entity.name.to_lowercase();
+            if terms.iter().any(|term| name.contains(term.as_str())) {
+                graph.entities.push(entity);
+                if graph.entities.len() == limit {
+                    break;
+                }
+            }
+        }
+
+        let names: Vec<String> = graph.entities.iter().map(|entity| entity.name.clone()).collect();
+        let mut statement = connection.prepare(
+            "SELECT r.source, coalesce(a.type, ''), r.relation, r.target, coalesce(b.type, ''), r.provenance
+             FROM relations r
+             LEFT JOIN entities a ON a.tenant = r.tenant AND a.name = r.source
+             LEFT JOIN entities b ON b.tenant = r.tenant AND b.name = r.target
+             WHERE r.tenant = ?2 AND (r.source = ?1 OR r.target = ?1) ORDER BY r.rowid",
+        )?;
+        for name in &names {
+            let rows = statement.query_map([name, &self.tenant], |row| {
+                Ok((
+                    Entity { name: row.get(0)?, kind: row.get(1)? },
+                    row.get::<_, String>(2)?,
+                    Entity { name: row.get(3)?, kind: row.get(4)? },
+                    row.get::<_, String>(5)?,
+                ))
+            })?;
+            for row in rows {
+                let (source, relation, target, provenance) = row?;
+                let relation = Relation {
+                    source: source.name.clone(),
+                    relation,
+                    target: target.name.clone(),
+                    provenance: Some(provenance),
+                };
+                if graph.relations.contains(&relation) {
+                    continue;
+                }
+                // Neighbours are part of the answer too
+                for entity in [source, target] {
+                    if !graph.entities.iter().any(|known| known.name == entity.name) {
+                        graph.entities.push(entity);
+                    }
+                }
+                graph.relations.push(relation);
+            }
+        }
+
+        let mut ids: Vec<String> = graph.relations.iter().filter_map(|relation| relation.provenance.clone()).collect();
+        ids.sort();
+        ids.dedup();
+        let mut statement = connection.prepare("SELECT id, text, source, model, ingested_at FROM provenance WHERE tenant = ?1 AND id = ?2")?;
+        for id in ids {
+            let source = statement.query_row([&self.tenant, &id], |row| {
+                Ok(Provenance {
+                    id: row.get(0)?,
+                    text: row.get(1)?,
+                    source: row.get(2)?,
+                    model: row.get(3)?,
+                    ingested_at: parse_time(&row.get::<_, String>(4)?)?,
+                })
+            })?;
+            graph.sources.push(source);
+        }
+        graph.sources.sort_by_key(|source| std::cmp::Reverse(source.ingested_at));
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_migrations_are_applied_once_and_keep_data() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(MIGRATIONS[0]).unwrap();
+        connection.pragma_update(None, "user_version", 1).unwrap();
+        connection
+            .execute(
+                "INSERT INTO memories (key, value, stored_at) VALUES ('wifi', '\"Attic\"', ?1)",
+                [time_text(Utc::now())],
+            )
+            .unwrap();
+        SqliteContextStore::migrate(&mut connection).unwrap();
+        SqliteContextStore::migrate(&mut connection).unwrap();
+        let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        let store = SqliteContextStore::with_connection(connection).unwrap();
+        assert_eq!(store.recall("wifi").await.unwrap().unwrap().value, json!("Attic"));
+    }
+
+    #[tokio::test]
+    async fn test_tenants_only_see_their_own_data() {
+        let store = Arc::new(SqliteContextStore::open(Path::new(":memory:")).unwrap());
+        let (alice, bob) = (store.clone().with_tenant("alice"), store.clone().with_tenant("bob"));
+        let memory = |value: &str| Memory { key: "note".to_string(), value: json!(value), stored_at: Utc::now(), expires_at: None };
+        alice.remember(memory("alice's")).await.unwrap();
+        bob.remember(memory("bob's")).await.unwrap();
+        alice.save_workflow("w1", json!({"owner": "alice"})).await.unwrap();
+        alice.store_metric("cpu_usage", json!(10.0), Utc::now()).await.unwrap();
+
+        assert_eq!(alice.recall("note").await.unwrap().unwrap().value, json!("alice's"));
+        assert_eq!(bob.recall("note").await.unwrap().unwrap().value, json!("bob's"));
+        assert!(store.recall("note").await.unwrap().is_none());
+        assert!(bob.load_workflow("w1").await.unwrap().is_none());
+        assert!(bob.list_workflows().await.unwrap().is_empty());
+        let since = Utc::now() - chrono::Duration::hours(1);
+        let hour = chrono::Duration::hours(1);
+        assert!(bob.aggregate_metrics("cpu_usage", hour, MetricAggregate::Avg, since).await.unwrap().is_empty());
+        assert_eq!(alice.aggregate_metrics("cpu_usage", hour, MetricAggregate::Avg, since).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_metrics_are_linked_and_aggregated() {
+        let store = SqliteContextStore::open(Path::new(":memory:")).unwrap();
+        let start = DateTime::from_timestamp(1_700_000_100, 0).unwrap();
+        let state = HashMap::from([("cpu_usage".to_string(), json!(10.0)), ("status".to_string(), json!("ok"))]);
+        store.store_snapshot(state, start).await.unwrap();
+        store.store_metric("cpu_usage", json!("30"), start + chrono::Duration::seconds(60)).await.unwrap();
+        store.store_metric("cpu_usage", json!(90.0), start + chrono::Duration::seconds(400)).await.unwrap();
+
+        let buckets = store
+            .aggregate_metrics("cpu_usage", chrono::Duration::seconds(300), MetricAggregate::Avg, start)
+            .await
+            .unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!((buckets[0].value, buckets[0].count), (20.0, 2));
+        assert_eq!(buckets[1].value, 90.0);
+
+        let connection = store.connection.lock().unwrap();
+        let links: i64 = connection
+            .query_row("SELECT COUNT(*) FROM relationships WHERE type = 'Contains'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(links, 2);
+    }
+
+    #[tokio::test]
+    async fn test_memories_expire_and_are_searched() {
+        let store = SqliteContextStore::open(Path::new(":memory:")).unwrap();
+        let now = Utc::now();
+        let memory = |key: &str, value: serde_json::Value, expires_at| Memory { key: key.to_string(), value, stored_at: now, expires_at };
+        store.remember(memory("home.wifi", json!("Ssid: Attic"), None)).await.unwrap();
+        store.remember(memory("old", json!("attic key"), Some(now - chrono::Duration::seconds(1)))).await.unwrap();
+
+        assert_eq!(store.recall("home.wifi").await.unwrap().unwrap().value, json!("Ssid: Attic"));
+        assert!(store.recall("old").await.unwrap().is_none());
+        let found = store.search_memories("ATTIC", 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].key, "home.wifi");
+    }
+
+    #[tokio::test]
+    async fn test_knowledge_is_merged_and_queried_with_provenance() {
+        let store = SqliteContextStore::open(Path::new(":memory:")).unwrap();
+        let provenance = |id: &str| Provenance {
+            id: id.to_string(),
+            text: "Ada works at Acme".to_string(),
+            source: None,
+            model: Some("llama3".to_string()),
+            ingested_at: Utc::now(),
+        };
+        let entity = |name: &str, kind: &str| Entity { name: name.to_string(), kind: kind.to_string() };
+        let works_at = Relation { source: "Ada".to_string(), relation: "works_at".to_string(), target: "Acme".to_string(), provenance: None };
+        store
+            .ingest_knowledge(provenance("p1"), vec![entity("Ada", "person"), entity("Acme", "")], vec![works_at.clone()])
+            .await
+            .unwrap();
+        store.ingest_knowledge(provenance("p2"), vec![entity("Acme", "company"), entity("Ada", "")], vec![]).await.unwrap();
+
+        let graph = store.query_knowledge(&["ada".to_string()], 10).await.unwrap();
+        assert_eq!(graph.entities, vec![entity("Ada", "person"), entity("Acme", "company")]);
+        assert_eq!(graph.relations, vec![Relation { provenance: Some("p1".to_string()), ..works_at }]);
+        assert_eq!(graph.sources.len(), 1);
+        assert_eq!(graph.sources[0].model.as_deref(), Some("llama3"));
+    }
+}
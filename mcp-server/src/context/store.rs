@@ -0,0 +1,328 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+use super::{Neo4jContext, RelationType};
+
+/// Tenant of context data written outside any client session, and of all
+/// data when tenants are not told apart
+pub const DEFAULT_TENANT: &str = "default";
+
+/// A value an agent asked to remember
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Memory {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub stored_at: DateTime<Utc>,
+    /// When the memory is forgotten; `None` keeps it
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Memory {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Something the knowledge graph knows about, e.g. a person or a device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entity {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: String,
+}
+
+/// A fact linking two entities by name, e.g. `Ada` `works_at` `Acme`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relation {
+    pub source: String,
+    pub relation: String,
+    pub target: String,
+    /// Id of the [`Provenance`] the fact was extracted from, once stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<String>,
+}
+
+/// Text that entities and relations were extracted from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub id: String,
+    pub text: String,
+    /// Where the text came from, such as a URL, as the caller described it
+    pub source: Option<String>,
+    /// Model that did the extraction
+    pub model: Option<String>,
+    pub ingested_at: DateTime<Utc>,
+}
+
+/// Part of the knowledge graph: some entities, the relations touching them
+/// and the texts those relations came from
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct KnowledgeGraph {
+    pub entities: Vec<Entity>,
+    pub relations: Vec<Relation>,
+    pub sources: Vec<Provenance>,
+}
+
+/// How [`ContextStore::aggregate_metrics`] combines the readings in a bucket
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricAggregate {
+    #[default]
+    Avg,
+    Min,
+    Max,
+    /// 95th percentile, interpolated between the nearest readings
+    P95,
+}
+
+impl MetricAggregate {
+    /// Combines the readings of one bucket the way Neo4j's `avg`, `min`,
+    /// `max` and `percentileCont` do; `values` must not be empty
+    pub fn apply(self, values: &[f64]) -> f64 {
+        match self {
+            MetricAggregate::Avg => values.iter().sum::<f64>() / values.len() as f64,
+            MetricAggregate::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            MetricAggregate::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            MetricAggregate::P95 => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let position = 0.95 * (sorted.len() - 1) as f64;
+                let (below, above) = (position.floor() as usize, position.ceil() as usize);
+                sorted[below] + (sorted[above] - sorted[below]) * (position - below as f64)
+            }
+        }
+    }
+}
+
+/// The readings of a metric in one time window, combined
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricBucket {
+    /// Start of the window
+    pub start: DateTime<Utc>,
+    pub value: f64,
+    /// Numeric readings in the window
+    pub count: u64,
+}
+
+/// Backend-neutral storage for context data such as metrics and system
+/// state snapshots. Everything a store reads and writes belongs to one
+/// tenant, [`DEFAULT_TENANT`] unless the store came from `with_tenant`.
+#[async_trait]
+pub trait ContextStore: Send + Sync {
+    /// The same backend limited to `tenant`'s data: nothing written through
+    /// it can be read through a store of another tenant
+    fn with_tenant(self: Arc<Self>, tenant: &str) -> Arc<dyn ContextStore>;
+
+    /// Stores a single metric reading and returns the id of the stored record
+    async fn store_metric(
+        &self,
+        metric_type: &str,
+        value: serde_json::Value,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>>;
+
+    /// Stores a snapshot of the system state and returns the id of the stored record
+    async fn store_system_state(
+        &self,
+        state: HashMap<String, serde_json::Value>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>>;
+
+    /// Numeric readings of `metric_type` since `since`, grouped into
+    /// consecutive `window`s and combined with `aggregate`, oldest first.
+    /// Windows without readings are left out.
+    async fn aggregate_metrics(
+        &self,
+        metric_type: &str,
+        window: chrono::Duration,
+        aggregate: MetricAggregate,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<MetricBucket>, Box<dyn Error + Send + Sync>>;
+
+    /// Links two stored records
+    async fn create_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Links two stored records unless they are already linked by a
+    /// relationship of `rel_type`, whose properties are then updated; so
+    /// writing the same link again does not duplicate it
+    async fn merge_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Stores a system state snapshot with one metric per reading, each
+    /// linked from the state by a `Contains` relationship, and returns the
+    /// id of the state. This default writes record by record, so a failure
+    /// can leave part of the snapshot behind; backends that can should write
+    /// it all or nothing.
+    async fn store_snapshot(
+        &self,
+        state: HashMap<String, serde_json::Value>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let state_id = self.store_system_state(state.clone()).await?;
+        for (metric_type, value) in state {
+            let metric_id = self.store_metric(&metric_type, value, timestamp).await?;
+            let properties = HashMap::from([("timestamp".to_string(), serde_json::json!(timestamp.to_rfc3339()))]);
+            self.create_relationship(&state_id, &metric_id, RelationType::Contains, Some(properties)).await?;
+        }
+        Ok(state_id)
+    }
+
+    /// Stores a workflow's state under its id, replacing the state saved before
+    async fn save_workflow(&self, id: &str, state: serde_json::Value) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// The state last saved for a workflow
+    async fn load_workflow(&self, id: &str) -> Result<Option<serde_json::Value>, Box<dyn Error + Send + Sync>>;
+
+    /// The state of every saved workflow, most recently saved first
+    async fn list_workflows(&self) -> Result<Vec<serde_json::Value>, Box<dyn Error + Send + Sync>>;
+
+    /// Stores a memory, replacing any earlier one with the same key
+    async fn remember(&self, memory: Memory) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// The memory stored under `key`, unless it has expired
+    async fn recall(&self, key: &str) -> Result<Option<Memory>, Box<dyn Error + Send + Sync>>;
+
+    /// Unexpired memories whose key or value contains `text`, ignoring case,
+    /// most recently stored first
+    async fn search_memories(&self, text: &str, limit: usize) -> Result<Vec<Memory>, Box<dyn Error + Send + Sync>>;
+
+    /// Adds entities, merged with those of the same name, and relations
+    /// between them, both recorded as coming from `provenance`
+    async fn ingest_knowledge(
+        &self,
+        provenance: Provenance,
+        entities: Vec<Entity>,
+        relations: Vec<Relation>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Up to `limit` entities whose name contains one of the lowercase
+    /// `terms`, with the relations touching them and their provenance
+    async fn query_knowledge(&self, terms: &[String], limit: usize) -> Result<KnowledgeGraph, Box<dyn Error + Send + Sync>>;
+
+    /// Fails when the backend cannot be reached; stores that are always
+    /// reachable keep this default
+    async fn ping(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ContextStore for Neo4jContext {
+    fn with_tenant(self: Arc<Self>, tenant: &str) -> Arc<dyn ContextStore> {
+        Arc::new(Neo4jContext::with_tenant(&self, tenant))
+    }
+
+    async fn store_metric(
+        &self,
+        metric_type: &str,
+        value: serde_json::Value,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let node = Neo4jContext::store_metric(self, metric_type, value, timestamp).await?;
+        Ok(node.get::<String>("id")?)
+    }
+
+    async fn store_system_state(
+        &self,
+        state: HashMap<String, serde_json::Value>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let node = Neo4jContext::store_system_state(self, state).await?;
+        Ok(node.get::<String>("id")?)
+    }
+
+    async fn aggregate_metrics(
+        &self,
+        metric_type: &str,
+        window: chrono::Duration,
+        aggregate: MetricAggregate,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<MetricBucket>, Box<dyn Error + Send + Sync>> {
+        Neo4jContext::aggregate_metrics(self, metric_type, window, aggregate, since).await
+    }
+
+    async fn create_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Neo4jContext::create_relationship(self, from_id, to_id, rel_type, properties).await?;
+        Ok(())
+    }
+
+    async fn merge_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        properties: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Neo4jContext::merge_relationship(self, from_id, to_id, rel_type, properties).await?;
+        Ok(())
+    }
+
+    async fn store_snapshot(
+        &self,
+        state: HashMap<String, serde_json::Value>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        Neo4jContext::store_snapshot(self, &state, timestamp).await
+    }
+
+    async fn save_workflow(&self, id: &str, state: serde_json::Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Neo4jContext::save_workflow(self, id, &state).await
+    }
+
+    async fn load_workflow(&self, id: &str) -> Result<Option<serde_json::Value>, Box<dyn Error + Send + Sync>> {
+        Neo4jContext::load_workflow(self, id).await
+    }
+
+    async fn list_workflows(&self) -> Result<Vec<serde_json::Value>, Box<dyn Error + Send + Sync>> {
+        Neo4jContext::list_workflows(self).await
+    }
+
+    async fn remember(&self, memory: Memory) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Neo4jContext::remember(self, &memory).await
+    }
+
+    async fn recall(&self, key: &str) -> Result<Option<Memory>, Box<dyn Error + Send + Sync>> {
+        Ok(Neo4jContext::recall(self, key).await?.filter(|memory| !memory.is_expired(Utc::now())))
+    }
+
+    async fn search_memories(&self, text: &str, limit: usize) -> Result<Vec<Memory>, Box<dyn Error + Send + Sync>> {
+        Neo4jContext::search_memories(self, text, limit).await
+    }
+
+    async fn ingest_knowledge(
+        &self,
+        provenance: Provenance,
+        entities: Vec<Entity>,
+        relations: Vec<Relation>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Neo4jContext::ingest_knowledge(self, &provenance, &entities, &relations).await
+    }
+
+    async fn query_knowledge(&self, terms: &[String], limit: usize) -> Result<KnowledgeGraph, Box<dyn Error + Send + Sync>> {
+        Neo4jContext::query_knowledge(self, terms, limit).await
+    }
+
+    async fn ping(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Neo4jContext::ping(self).await
+    }
+}
+
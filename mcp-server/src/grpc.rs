@@ -0,0 +1,282 @@
+//! gRPC transport: `ListTools`, `CallTool` and `StreamToolCall` over tonic,
+//! for internal services that would rather not speak JSON-RPC. Calls go
+//! through the same `McpServer` as the HTTP and stdio transports, so budgets,
+//! fault injection, stats and result transforms apply alike. The messages
+//! here match `proto/mcp.proto`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use prost_types::value::Kind;
+use serde_json::{Map, Number, Value};
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+use tracing::info;
+use mcp_core::error_data;
+
+use crate::mcp::errors::ErrorKind;
+use crate::mcp::{ContentBlock, JsonRpcError, McpServer, ToolCallParams, ToolDefinition};
+
+include!(concat!(env!("OUT_DIR"), "/mcp.McpService.rs"));
+
+use mcp_service_server::{McpService, McpServiceServer};
+
+/// Metadata key naming the MCP session a call belongs to, as the
+/// `Mcp-Session-Id` header does over HTTP
+pub const SESSION_METADATA: &str = "mcp-session-id";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListToolsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListToolsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub tools: Vec<Tool>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Tool {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub description: String,
+    #[prost(message, optional, tag = "3")]
+    pub input_schema: Option<prost_types::Struct>,
+    #[prost(string, repeated, tag = "4")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CallToolRequest {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(message, optional, tag = "2")]
+    pub arguments: Option<prost_types::Struct>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CallToolResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub content: Vec<Content>,
+    /// The result's `_meta`, e.g. which result transforms ran
+    #[prost(message, optional, tag = "2")]
+    pub meta: Option<prost_types::Struct>,
+}
+
+/// One content block of a tool result
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Content {
+    #[prost(oneof = "content::Block", tags = "1, 2, 3")]
+    pub block: Option<content::Block>,
+}
+
+pub mod content {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Block {
+        #[prost(string, tag = "1")]
+        Text(String),
+        #[prost(message, tag = "2")]
+        Json(prost_types::Value),
+        #[prost(message, tag = "3")]
+        Data(super::Data),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Data {
+    #[prost(string, tag = "1")]
+    pub mime_type: String,
+    /// Base64 unless the MIME type is textual
+    #[prost(string, tag = "2")]
+    pub data: String,
+}
+
+/// Serves `McpService` for `server`
+pub struct GrpcService {
+    server: Arc<McpServer>,
+}
+
+impl GrpcService {
+    pub fn new(server: Arc<McpServer>) -> Self {
+        Self { server }
+    }
+
+    /// Runs the call and splits its result into content and `_meta`
+    async fn call(&self, request: Request<CallToolRequest>) -> Result<(Vec<Content>, Option<prost_types::Struct>), Status> {
+        let session = session_id(&request);
+        let request = request.into_inner();
+        let arguments: HashMap<String, Value> = match request.arguments {
+            Some(arguments) => struct_to_json(arguments).into_iter().collect(),
+            None => HashMap::new(),
+        };
+        let params = ToolCallParams { name: request.name, arguments, meta: None };
+        let mut result = self.server.call_tool(session.as_deref(), params).await.map_err(status)?;
+        let blocks: Vec<ContentBlock> = serde_json::from_value(result["content"].take())
+            .map_err(|e| Status::internal(format!("Invalid tool result: {}", e)))?;
+        let meta = match result.get_mut("_meta").map(Value::take) {
+            Some(Value::Object(meta)) => Some(json_to_struct(meta)),
+            _ => None,
+        };
+        Ok((blocks.into_iter().map(content).collect(), meta))
+    }
+}
+
+#[tonic::async_trait]
+impl McpService for GrpcService {
+    async fn list_tools(&self, request: Request<ListToolsRequest>) -> Result<Response<ListToolsResponse>, Status> {
+        let tools = self.server.list_tools(session_id(&request).as_deref()).await;
+        Ok(Response::new(ListToolsResponse { tools: tools.into_iter().map(tool).collect() }))
+    }
+
+    async fn call_tool(&self, request: Request<CallToolRequest>) -> Result<Response<CallToolResponse>, Status> {
+        let (content, meta) = self.call(request).await?;
+        Ok(Response::new(CallToolResponse { content, meta }))
+    }
+
+    type StreamToolCallStream = Pin<Box<dyn Stream<Item = Result<Content, Status>> + Send>>;
+
+    /// `CallTool` with each content block sent as its own message
+    async fn stream_tool_call(&self, request: Request<CallToolRequest>) -> Result<Response<Self::StreamToolCallStream>, Status> {
+        let (content, _) = self.call(request).await?;
+        Ok(Response::new(Box::pin(tokio_stream::iter(content.into_iter().map(Ok)))))
+    }
+}
+
+/// Serves gRPC on `port` until the process ends
+pub async fn serve(server: Arc<McpServer>, port: u16) -> anyhow::Result<()> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    info!("gRPC listening on {}", addr);
+    tonic::transport::Server::builder()
+        .add_service(McpServiceServer::new(GrpcService::new(server)))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+fn session_id<T>(request: &Request<T>) -> Option<String> {
+    request
+        .metadata()
+        .get(SESSION_METADATA)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// JSON-RPC errors as gRPC statuses; the error data goes in the message
+/// The status for an error, by the kind in its data
+fn status(error: JsonRpcError) -> Status {
+    let kind = error.kind().unwrap_or_else(|| ErrorKind::for_code(error.code));
+    let data = error.data.unwrap_or_else(|| error_data(kind, None));
+    let message = match data.get("detail") {
+        Some(Value::String(detail)) => format!("{}: {}", error.message, detail),
+        Some(detail) => format!("{}: {}", error.message, detail),
+        None if data.as_object().is_some_and(|data| data.len() > 1) => format!("{}: {}", error.message, data),
+        None => error.message,
+    };
+    match kind {
+        ErrorKind::ParseError | ErrorKind::InvalidRequest | ErrorKind::InvalidParams => Status::invalid_argument(message),
+        ErrorKind::NotFound | ErrorKind::MethodNotFound | ErrorKind::ResourceNotFound | ErrorKind::SessionNotFound => {
+            Status::not_found(message)
+        }
+        ErrorKind::SessionNotInitialized => Status::failed_precondition(message),
+        ErrorKind::RateLimited | ErrorKind::BudgetExceeded => Status::resource_exhausted(message),
+        ErrorKind::ShuttingDown | ErrorKind::PluginUnavailable => Status::unavailable(message),
+        ErrorKind::DeadlineExceeded => Status::deadline_exceeded(message),
+        ErrorKind::ToolFailed | ErrorKind::Internal => Status::internal(message),
+    }
+}
+
+fn tool(definition: ToolDefinition) -> Tool {
+    let input_schema = match definition.input_schema {
+        Value::Object(schema) => Some(json_to_struct(schema)),
+        _ => None,
+    };
+    let tags = serde_json::to_value(&definition.tags)
+        .ok()
+        .and_then(|tags| serde_json::from_value(tags).ok())
+        .unwrap_or_default();
+    Tool { name: definition.name, description: definition.description, input_schema, tags }
+}
+
+fn content(block: ContentBlock) -> Content {
+    let block = match block {
+        ContentBlock::Text { text } => content::Block::Text(text),
+        ContentBlock::Json { json } => content::Block::Json(json_to_value(json)),
+        ContentBlock::Data { mime_type, data } => content::Block::Data(Data { mime_type, data }),
+    };
+    Content { block: Some(block) }
+}
+
+fn json_to_struct(object: Map<String, Value>) -> prost_types::Struct {
+    prost_types::Struct {
+        fields: object.into_iter().map(|(key, value)| (key, json_to_value(value))).collect::<BTreeMap<_, _>>(),
+    }
+}
+
+fn json_to_value(value: Value) -> prost_types::Value {
+    let kind = match value {
+        Value::Null => Kind::NullValue(0),
+        Value::Bool(value) => Kind::BoolValue(value),
+        Value::Number(number) => Kind::NumberValue(number.as_f64().unwrap_or_default()),
+        Value::String(value) => Kind::StringValue(value),
+        Value::Array(items) => Kind::ListValue(prost_types::ListValue {
+            values: items.into_iter().map(json_to_value).collect(),
+        }),
+        Value::Object(object) => Kind::StructValue(json_to_struct(object)),
+    };
+    prost_types::Value { kind: Some(kind) }
+}
+
+fn struct_to_json(object: prost_types::Struct) -> Map<String, Value> {
+    object.fields.into_iter().map(|(key, value)| (key, value_to_json(value))).collect()
+}
+
+/// Protobuf numbers are all doubles; whole ones become integers again, since
+/// tools read counts and limits with `as_u64`
+fn value_to_json(value: prost_types::Value) -> Value {
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => Value::Null,
+        Some(Kind::BoolValue(value)) => Value::Bool(value),
+        Some(Kind::NumberValue(number)) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => {
+            Value::from(number as i64)
+        }
+        Some(Kind::NumberValue(number)) => Number::from_f64(number).map_or(Value::Null, Value::Number),
+        Some(Kind::StringValue(value)) => Value::String(value),
+        Some(Kind::ListValue(list)) => Value::Array(list.values.into_iter().map(value_to_json).collect()),
+        Some(Kind::StructValue(object)) => Value::Object(struct_to_json(object)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_round_trips_through_protobuf_values() {
+        let value = json!({"limit": 10, "ratio": 0.5, "tags": ["a", null], "nested": {"on": true}});
+        let Value::Object(object) = value.clone() else { unreachable!() };
+        assert_eq!(Value::Object(struct_to_json(json_to_struct(object))), value);
+    }
+
+    #[test]
+    fn test_errors_map_to_statuses() {
+        let error = JsonRpcError { code: -32006, message: "Session budget exceeded".to_string(), data: None };
+        assert_eq!(status(error).code(), tonic::Code::ResourceExhausted);
+        let error = JsonRpcError {
+            code: -32000,
+            message: "Tool execution failed".to_string(),
+            data: Some(error_data(ErrorKind::ToolFailed, Some(json!("boom")))),
+        };
+        let failed = status(error);
+        assert_eq!(failed.code(), tonic::Code::Internal);
+        assert_eq!(failed.message(), "Tool execution failed: boom");
+        let error = JsonRpcError {
+            code: -32602,
+            message: "Invalid params".to_string(),
+            data: Some(error_data(ErrorKind::NotFound, Some(json!("Unknown tool: nope")))),
+        };
+        assert_eq!(status(error).code(), tonic::Code::NotFound);
+    }
+}
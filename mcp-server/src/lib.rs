@@ -2,5 +2,15 @@ pub mod mcp;
 pub mod tools;
 pub mod plugins;
 pub mod context;
+pub mod sampler;
+pub mod automation;
+pub mod secrets;
+pub mod mock;
+pub mod admin;
+pub mod config;
+pub mod checks;
+pub mod rest;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 
 pub use mcp::McpServer;
\ No newline at end of file
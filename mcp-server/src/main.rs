@@ -1,177 +1,773 @@
 use anyhow::Result;
 use axum::{
-    extract::{Json, State},
-    http::{HeaderValue, Method, StatusCode},
-    response::IntoResponse,
-    routing::{get, post},
+    extract::{ConnectInfo, DefaultBodyLimit, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::post,
     Router,
 };
-use clap::Parser;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tower_http::cors::CorsLayer;
-use tracing::{info, error};
+use std::time::Duration;
+use tokio::io::{self, AsyncWriteExt};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::time::Interval;
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::{debug, info, error, warn};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod mcp;
 mod tools;
 mod plugins;
 mod context;
+mod sampler;
+mod automation;
+mod secrets;
+mod admin;
+mod config;
+mod checks;
+mod rest;
+#[cfg(feature = "grpc")]
+mod grpc;
 
-use mcp::McpServer;
+use mcp::{McpServer, RawMessage};
+use mcp::limits::{LimitExceeded, LimitKind, MessageLimits};
+use mcp::notifications::NotificationLayer;
+use mcp::logs::{LogCaptureLayer, LogFilter};
+use mcp::journal::{self, Journal};
+use mcp::jobs::JobStore;
+use mcp::budget::BudgetConfig;
+use mcp::rate_limit::RateLimitConfig;
+use tools::ConcurrencyConfig;
+use mcp::faults::FaultConfig;
+use mcp::framing::{MessageReader, MessageTooLarge};
+use mcp::transform::TransformConfig;
+use tools::VirtualTool;
+use automation::{Rule, RuleEngine};
+use plugins::proxy::ProxyConfig;
+use mcp_core::logging::{LogFormat, LogOutput, LogRotation};
+use config::{AllowedOrigins, ConfigWatcher, ServerConfig};
+use checks::{Check, CheckReport, StartupPolicy};
 
 #[derive(Parser)]
 #[command(name = "mcp-server")]
 #[command(about = "A Model Context Protocol (MCP) server")]
 struct Cli {
-    #[arg(long, default_value = "8080")]
-    port: u16,
+    /// YAML config file, watched for changes (overrides MCP_CONFIG;
+    /// environment variables and flags win over its settings)
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Profile of the config file whose settings override the file's
+    /// (overrides MCP_PROFILE)
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// HTTP port [default: the config file's, or 8080]
+    #[arg(long)]
+    port: Option<u16>,
     
-    #[arg(long, default_value = "debug")]
-    log_level: String,
+    /// Log filter, e.g. "info,mcp_server::plugins::home_assistant=debug"
+    /// [default: RUST_LOG, the config file's, or debug]
+    #[arg(long)]
+    log_level: Option<String>,
+
+    #[arg(long, value_enum, default_value = "pretty")]
+    log_format: LogFormat,
+
+    /// Write logs to this file instead of stdout (stderr in stdio mode)
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+
+    /// How often --log-file starts a new file
+    #[arg(long, value_enum, default_value = "daily")]
+    log_rotation: LogRotation,
     
     #[arg(long)]
     stdio: bool,
     
     #[arg(long)]
     quiet: bool,
+
+    /// In stdio mode, ping the client this often and log when it stops answering
+    #[arg(long, value_name = "SECONDS")]
+    keepalive: Option<u64>,
+
+    /// Requests per minute each client session may make (overrides MCP_SESSION_RATE_LIMIT)
+    #[arg(long)]
+    session_rate_limit: Option<u32>,
+
+    /// Tool calls, seconds and upstream HTTP bytes each client session may
+    /// use, e.g. "calls=200,seconds=900,bytes=50000000" (overrides MCP_SESSION_BUDGET)
+    #[arg(long, value_name = "SPEC")]
+    session_budget: Option<BudgetConfig>,
+
+    /// Calls each tool or plugin may make across all sessions, e.g.
+    /// "homeassistant=10/min,http=60/min" (overrides MCP_TOOL_RATE_LIMITS)
+    #[arg(long, value_name = "SPEC")]
+    tool_rate_limits: Option<RateLimitConfig>,
+
+    /// Calls of each tool that may run at once, e.g. "homeassistant=2";
+    /// queued client calls go before queued jobs (overrides MCP_TOOL_CONCURRENCY)
+    #[arg(long, value_name = "SPEC")]
+    tool_concurrency: Option<ConcurrencyConfig>,
+
+    /// Largest message, deepest nesting and messages per second per
+    /// connection accepted, e.g. "bytes=1048576,depth=32,rate=50"
+    /// (overrides MCP_MESSAGE_LIMITS)
+    #[arg(long, value_name = "SPEC")]
+    message_limits: Option<MessageLimits>,
+
+    /// Keep each client's context data in the tenant its bearer token maps
+    /// to in MCP_TENANT_TOKENS (same as MCP_TENANT_PER_CLIENT=true)
+    #[arg(long)]
+    tenant_per_client: bool,
+
+    /// Fail or delay a share of plugin executions on purpose, e.g.
+    /// "fail=10,delay=20,delay_ms=2000,seed=7" (percentages)
+    #[arg(long, value_name = "SPEC")]
+    fault_injection: Option<FaultConfig>,
+
+    /// Shrink tool output longer than max_chars before returning it, e.g.
+    /// "steps=redact+summarize+truncate,max_chars=8000" (overrides MCP_RESULT_TRANSFORMS)
+    #[arg(long, value_name = "SPEC")]
+    result_transforms: Option<TransformConfig>,
+
+    /// JSON file of virtual tools, which call another tool with preset
+    /// arguments (in addition to those in MCP_VIRTUAL_TOOLS)
+    #[arg(long, value_name = "FILE")]
+    virtual_tools: Option<PathBuf>,
+
+    /// JSON file of external stdio MCP servers whose tools to offer, in the
+    /// {"mcpServers": {...}} format (in addition to those in MCP_PROXY_SERVERS)
+    #[arg(long, value_name = "FILE")]
+    proxy_servers: Option<PathBuf>,
+
+    /// YAML file of automation rules, which call tools when an event,
+    /// metric threshold or schedule fires (in addition to those in
+    /// MCP_AUTOMATION_RULES)
+    #[arg(long, value_name = "FILE")]
+    rules: Option<PathBuf>,
+
+    /// Also serve gRPC (ListTools, CallTool, StreamToolCall) on this port
+    #[cfg(feature = "grpc")]
+    #[arg(long)]
+    grpc_port: Option<u16>,
+
+    /// SQLite database keeping jobs from jobs/submit across restarts
+    /// (overrides MCP_JOBS_DB; default: in memory)
+    #[arg(long, value_name = "FILE")]
+    jobs_db: Option<PathBuf>,
+
+    /// Append every request and its response to this file (JSON Lines), for replay
+    #[arg(long, value_name = "FILE")]
+    journal: Option<PathBuf>,
+
+    /// Whether to start when a dependency such as Neo4j or Home Assistant
+    /// fails its startup check
+    #[arg(long, value_enum, default_value = "degraded")]
+    startup_policy: StartupPolicy,
+
+    /// Do not probe Home Assistant and Ollama at startup, and start
+    /// whatever the checks that remain say
+    #[arg(long)]
+    skip_checks: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    
-    // Initialize tracing (only if not quiet)
-    if !cli.quiet {
-        tracing_subscriber::fmt()
-            .with_env_filter(&cli.log_level)
-            .init();
+#[derive(Subcommand)]
+enum Command {
+    /// Re-send the requests recorded with --journal to this server and report
+    /// the responses that changed
+    Replay {
+        /// Journal written by --journal
+        file: PathBuf,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the config file's settings, with secrets masked
+    Show {
+        /// Print every setting's effective value and where it came from:
+        /// a flag, an environment variable, the config file or the default
+        #[arg(long)]
+        resolved: bool,
+    },
+}
+
+/// Reads the flags and the config file, then starts the runtime. The file's
+/// settings reach the environment here, while this is the only thread, as
+/// setting variables is not safe once other threads may read them.
+fn main() -> Result<()> {
+    // The matches are kept to tell which flags were given
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let config_path = cli.config.clone().or_else(config::path_from_env);
+    let profile = cli.profile.clone().or_else(config::profile_from_env);
+    let file_config = match &config_path {
+        Some(path) => ServerConfig::load(path, profile.as_deref())?,
+        None if profile.is_some() => anyhow::bail!("A profile needs a config file (--config or MCP_CONFIG)"),
+        None => ServerConfig::default(),
+    };
+    if let Some(Command::Config { command: ConfigCommand::Show { resolved } }) = &cli.command {
+        return show_config(&file_config, *resolved, &matches);
     }
+    // Environment variables win over the file, which fills in those not set
+    file_config.export_to_env();
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(serve(cli, config_path, profile, file_config))
+}
+
+async fn serve(cli: Cli, config_path: Option<PathBuf>, profile: Option<String>, file_config: ServerConfig) -> Result<()> {
+    let server = match cli.session_rate_limit {
+        Some(limit) => McpServer::new().with_session_rate_limit(limit),
+        None => McpServer::new(),
+    };
+    let server = match cli.session_budget.clone() {
+        Some(budget) => server.with_session_budget(budget),
+        None => server,
+    };
+    let server = match cli.tool_rate_limits.clone() {
+        Some(config) => server.with_tool_rate_limits(config),
+        None => server,
+    };
+    let server = match cli.tool_concurrency.clone() {
+        Some(config) => server.with_tool_concurrency(config),
+        None => server,
+    };
+    let server = match cli.message_limits.clone() {
+        Some(limits) => server.with_message_limits(limits),
+        None => server,
+    };
+    let server = if cli.tenant_per_client { server.with_tenant_per_client() } else { server };
+    let server = match cli.result_transforms.clone() {
+        Some(config) => server.with_result_transforms(config),
+        None => server,
+    };
+    let server = match cli.fault_injection.clone() {
+        Some(config) => server.with_fault_injection(config),
+        None => server,
+    };
+    let server = match &cli.journal {
+        Some(path) => server.with_journal(Journal::open(path)?),
+        None => server,
+    };
+    let server = match &cli.jobs_db {
+        Some(path) => server.with_jobs(JobStore::open(path)?),
+        None => server,
+    };
+    let log_level = cli.log_level.clone()
+        .or_else(|| std::env::var("RUST_LOG").ok())
+        .unwrap_or_else(|| "debug".to_string());
+    let (log_filter_layer, log_filter) = LogFilter::new(&log_level);
+    let server = Arc::new(server.with_log_filter(log_filter));
+    
+    // Initialize tracing; log events also go to MCP clients that asked for
+    // them with logging/setLevel and to the buffer behind logs/tail, even
+    // when local output is quiet. In stdio mode stdout carries the protocol,
+    // so logs go to stderr.
+    let log_output = LogOutput {
+        format: cli.log_format,
+        file: cli.log_file.clone(),
+        rotation: cli.log_rotation,
+        stderr: cli.stdio,
+    };
+    let (log_layer, _log_guard) = log_output.layer()?;
+    tracing_subscriber::registry()
+        .with(log_filter_layer)
+        .with((!cli.quiet).then_some(log_layer))
+        .with(NotificationLayer::new(server.notifications()))
+        .with(LogCaptureLayer::new(server.logs()))
+        .init();
 
     info!("Starting MCP Server v{}", env!("CARGO_PKG_VERSION"));
 
-    // Test Neo4j connection at startup
-    match context::get_neo4j_context().await {
-        Ok(_ctx) => info!("Successfully connected to Neo4j"),
-        Err(e) => error!("Failed to connect to Neo4j: {}", e),
+    // A SQLite context store replaces Neo4j when one is configured;
+    // otherwise test the Neo4j connection at startup
+    let mut checks = CheckReport::default();
+    let context_store: Option<Arc<dyn context::ContextStore>> = if let Ok(path) = std::env::var("CONTEXT_SQLITE_DB") {
+        match context::SqliteContextStore::open(std::path::Path::new(&path)) {
+            Ok(store) => {
+                checks.push(Check::ok("SQLite", &path));
+                Some(Arc::new(store))
+            }
+            Err(e) => {
+                checks.push(Check::failed(
+                    "SQLite",
+                    format!("{}: {}", path, e),
+                    "Check that CONTEXT_SQLITE_DB is in a directory the server may write to",
+                ));
+                None
+            }
+        }
+    } else {
+        let uri = std::env::var("NEO4J_URI").unwrap_or_else(|_| "bolt://localhost:7687".to_string());
+        match context::get_neo4j_context().await {
+            Ok(ctx) => {
+                checks.push(Check::ok("Neo4j", uri));
+                let store = Arc::new(context::BufferedStore::new(ctx, context::WriteBuffer::from_env()));
+                if store.pending() > 0 {
+                    info!("{} context writes are buffered from an earlier run", store.pending());
+                }
+                store.spawn_replay();
+                Some(store)
+            }
+            Err(e) => {
+                checks.push(Check::failed(
+                    "Neo4j",
+                    format!("{}: {}", uri, e),
+                    "Check that Neo4j is running, NEO4J_URI points at it and NEO4J_USER and NEO4J_PASSWORD are right, or set CONTEXT_SQLITE_DB to use SQLite instead",
+                ));
+                None
+            }
+        }
+    };
+    if !cli.skip_checks {
+        let ha_url = std::env::var("HOMEASSISTANT_URL").unwrap_or_else(|_| "http://localhost:8123".to_string());
+        let ha_token = std::env::var("HOMEASSISTANT_TOKEN").ok();
+        checks.push(checks::probe_home_assistant(&ha_url, ha_token.as_deref()).await);
+        let ollama_url = std::env::var("OLLAMA_BASE_URL").unwrap_or_else(|_| "http://localhost:11434".to_string());
+        let ollama_model = std::env::var("SAMPLING_OLLAMA_MODEL").ok();
+        checks.push(checks::probe_ollama(&ollama_url, ollama_model.as_deref()).await);
+    }
+    checks.log();
+    if !cli.skip_checks {
+        checks.enforce(cli.startup_policy)?;
+    }
+    // Sensitive properties are encrypted before they reach either backend
+    let context_store = match (context_store, context::FieldCipher::from_env()?) {
+        (Some(store), Some(cipher)) => {
+            info!("Encrypting sensitive context properties");
+            Some(Arc::new(context::EncryptedStore::new(store, cipher)) as Arc<dyn context::ContextStore>)
+        }
+        (store, _) => store,
+    };
+    if let Some(store) = &context_store {
+        server.set_context_store(store.clone());
     }
     
-    let server = Arc::new(McpServer::new());
     server.initialize().await?;
+    if let Some(path) = &cli.proxy_servers {
+        for (name, config) in ProxyConfig::load(path)? {
+            server.add_proxy_server(&name, config).await?;
+        }
+    }
+    if let Some(path) = &cli.virtual_tools {
+        for tool in VirtualTool::load(path)? {
+            server.add_virtual_tool(tool).await;
+        }
+    }
     info!("MCP Server initialized successfully");
+
+    if let Some(Command::Replay { file }) = &cli.command {
+        return run_replay(&server, file).await;
+    }
+
+    server.spawn_job_worker();
+    server.spawn_plugin_retries();
+
+    let origins = file_config.cors_origins.clone().map(AllowedOrigins::new).unwrap_or_default();
+    let port = cli.port.or(file_config.port).unwrap_or(8080);
+    let config_watcher = config_path.map(|path| {
+        let watcher = Arc::new(ConfigWatcher::new(path, profile, file_config, server.clone(), origins.clone()));
+        watcher.clone().spawn();
+        watcher
+    });
+
+    let mut rules = Rule::from_env();
+    if let Some(path) = &cli.rules {
+        rules.extend(Rule::load(path)?);
+    }
+    if !rules.is_empty() {
+        RuleEngine::new(server.clone(), rules).spawn();
+    }
+
+    if let Some(config) = sampler::SamplerConfig::from_env() {
+        sampler::MetricsSampler::new(
+            config,
+            Arc::new(plugins::system_info::SystemInfoPlugin::new()),
+            context_store,
+            Arc::new(plugins::notification::NotificationPlugin::new()),
+        ).spawn();
+    }
     
+    #[cfg(feature = "grpc")]
+    if let Some(port) = cli.grpc_port {
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = grpc::serve(server, port).await {
+                error!("gRPC server failed: {}", e);
+            }
+        });
+    }
+
     if cli.stdio {
-        run_stdio_mode(server).await?;
+        run_stdio_mode(server, cli.keepalive.map(Duration::from_secs)).await?;
     } else {
-        run_http_mode(server, cli.port).await?;
+        run_http_mode(server, port, origins, config_watcher).await?;
     }
     
     Ok(())
 }
 
-async fn run_stdio_mode(server: Arc<McpServer>) -> Result<()> {
+/// Prints the config file's settings or, with `resolved`, every setting's
+/// effective value and its source; secrets are masked
+fn show_config(file: &ServerConfig, resolved: bool, matches: &ArgMatches) -> Result<()> {
+    if !resolved {
+        let mut settings = file.masked();
+        if let Some(settings) = settings.as_object_mut() {
+            settings.retain(|_, value| !value.is_null());
+        }
+        print!("{}", serde_yaml::to_string(&settings)?);
+        return Ok(());
+    }
+    let settings = file.resolve(|name| flag_value(matches, name));
+    let width = settings.iter().map(|setting| setting.name.len()).max().unwrap_or(0);
+    for setting in settings {
+        let value = setting.value.as_deref().unwrap_or("(unset)");
+        println!("{:width$}  {:32}  {}", setting.name, value, setting.source, width = width);
+    }
+    Ok(())
+}
+
+/// The value given on the command line for the flag named like a setting
+fn flag_value(matches: &ArgMatches, name: &str) -> Option<String> {
+    // Settings without a flag are not arguments at all
+    let values = matches.try_get_raw(name).ok().flatten()?;
+    Some(values.map(|value| value.to_string_lossy()).collect::<Vec<_>>().join(","))
+}
+
+/// Replays a journal and prints every response that changed; fails if any did
+async fn run_replay(server: &McpServer, file: &Path) -> Result<()> {
+    let report = journal::replay(server, file).await?;
+    for mismatch in &report.mismatches {
+        let show = |response: &Option<serde_json::Value>| {
+            response.as_ref().map_or_else(|| "(no response)".to_string(), |r| r.to_string())
+        };
+        println!("line {} ({}): response changed", mismatch.line, mismatch.method);
+        println!("  recorded: {}", show(&mismatch.recorded));
+        println!("  replayed: {}", show(&mismatch.replayed));
+    }
+    println!(
+        "Replayed {} messages: {} unchanged, {} changed",
+        report.replayed,
+        report.replayed - report.mismatches.len(),
+        report.mismatches.len()
+    );
+    if report.mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("{} responses changed", report.mismatches.len()))
+    }
+}
+
+async fn run_stdio_mode(server: Arc<McpServer>, keepalive: Option<Duration>) -> Result<()> {
     info!("Running in STDIO mode");
     
-    let stdin = io::stdin();
     let mut stdout = io::stdout();
-    let mut reader = BufReader::new(stdin);
-    let mut line = String::new();
+    // Newline-delimited or Content-Length framed, answered in kind
+    // Messages over the size limit are skipped rather than buffered
+    let mut messages = MessageReader::new(io::stdin()).with_max_bytes(server.message_limits().max_bytes);
+    // The stdio client is a single session, started by its initialize request
+    // and ended by shutdown or EOF
+    let mut session: Option<String> = None;
+    // Responses are framed into one buffer, reused for each
+    let mut framed = Vec::new();
+    let mut notifications: Option<UnboundedReceiver<String>> = None;
+    // Requests run concurrently, since a tool call may wait for the client to
+    // answer a sampling request on this same stream
+    let (responses, mut pending_responses) = mpsc::unbounded_channel::<String>();
+    let mut keepalive = keepalive.map(|period| tokio::time::interval_at(tokio::time::Instant::now() + period, period));
     
     loop {
-        line.clear();
-        match reader.read_line(&mut line).await {
-            Ok(0) => break, // EOF
-            Ok(_) => {
-                if let Ok(response) = server.handle_message(&line).await {
-                    stdout.write_all(response.as_bytes()).await?;
-                    stdout.write_all(b"\n").await?;
-                    stdout.flush().await?;
+        let message = tokio::select! {
+            line = messages.next_message() => match line {
+                Ok(Some(line)) if is_request(&line) => {
+                    let server = server.clone();
+                    let session = session.clone();
+                    let responses = responses.clone();
+                    tokio::spawn(async move {
+                        if let Ok(response) = server.handle_connection_message(STDIO, session.as_deref(), &line).await {
+                            let _ = responses.send(response);
+                        }
+                    });
+                    continue;
                 }
+                // Notifications and responses are handled in order
+                Ok(Some(line)) => match server.handle_connection_message(STDIO, session.as_deref(), &line).await {
+                    Ok(response) => response,
+                    Err(_) => continue,
+                },
+                Ok(None) => break, // EOF
+                Err(e) if e.get_ref().is_some_and(|e| e.is::<MessageTooLarge>()) => {
+                    server.limit_exceeded_response(LimitExceeded::new(LimitKind::Bytes, server.message_limits().max_bytes))
+                }
+                Err(e) => {
+                    error!("Error reading from stdin: {}", e);
+                    break;
+                }
+            },
+            Some(response) = pending_responses.recv() => response,
+            Some(notification) = recv_notification(&mut notifications) => notification,
+            // The ping goes out on the notification stream; its answer comes
+            // back on stdin like any other response
+            _ = next_tick(&mut keepalive), if session.is_some() => {
+                let server = server.clone();
+                let session = session.clone().unwrap_or_default();
+                tokio::spawn(async move {
+                    match server.ping_client(&session).await {
+                        Some(Ok(elapsed)) => debug!("Stdio client answered keepalive ping in {:?}", elapsed),
+                        Some(Err(e)) => warn!("Stdio client missed a keepalive ping: {}", e),
+                        None => {}
+                    }
+                });
+                continue;
             }
-            Err(e) => {
-                error!("Error reading from stdin: {}", e);
-                break;
-            }
+        };
+        if message.is_empty() {
+            continue;
         }
+
+        if let Some(id) = session_id_from_response(&message) {
+            notifications = server.subscribe_notifications(&id).await;
+            session = Some(id);
+        }
+        messages.framing().frame_into(&message, &mut framed);
+        stdout.write_all(&framed).await?;
+        stdout.flush().await?;
     }
-    
+
+    // Requests still running at EOF are answered before the session ends;
+    // the channel closes once the last of them has sent its response
+    drop(responses);
+    while let Some(response) = pending_responses.recv().await {
+        if response.is_empty() {
+            continue;
+        }
+        messages.framing().frame_into(&response, &mut framed);
+        stdout.write_all(&framed).await?;
+        stdout.flush().await?;
+    }
+
+    if let Some(session) = session {
+        server.end_session(&session).await;
+    }
+
     Ok(())
 }
 
-async fn run_http_mode(server: Arc<McpServer>, port: u16) -> Result<()> {
+/// The stdio stream's name among connections, for its message rate
+const STDIO: &str = "stdio";
+
+/// Whether a line is a JSON-RPC request other than initialize, which has to
+/// finish before the client sends anything else, or a batch of only such
+/// requests
+fn is_request(line: &str) -> bool {
+    let line = line.trim_start();
+    if line.starts_with('[') {
+        return serde_json::from_str::<Vec<&serde_json::value::RawValue>>(line)
+            .is_ok_and(|batch| !batch.is_empty() && batch.iter().all(|message| is_request(message.get())));
+    }
+    RawMessage::parse(line).is_ok_and(|message| message.is_request() && message.method.as_deref() != Some("initialize"))
+}
+
+/// Next notification for the stdio session; waits forever before there is one
+async fn recv_notification(notifications: &mut Option<UnboundedReceiver<String>>) -> Option<String> {
+    match notifications {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Next keepalive tick; waits forever without keepalives
+async fn next_tick(keepalive: &mut Option<Interval>) {
+    match keepalive {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// The session id an initialize response hands out
+fn session_id_from_response(response: &str) -> Option<String> {
+    // Only initialize responses have one, so most are never parsed
+    if !response.contains("\"sessionId\"") {
+        return None;
+    }
+    let response: serde_json::Value = serde_json::from_str(response).ok()?;
+    // Either the response itself or one in a batch
+    match response.as_array() {
+        Some(batch) => batch.iter().find_map(|response| response["result"]["sessionId"].as_str()),
+        None => response["result"]["sessionId"].as_str(),
+    }
+    .map(str::to_string)
+}
+
+async fn run_http_mode(
+    server: Arc<McpServer>,
+    port: u16,
+    origins: AllowedOrigins,
+    config: Option<Arc<ConfigWatcher>>,
+) -> Result<()> {
     info!("Running in HTTP mode on port {}", port);
     
+    // Notified when an operator drains the server through the admin API
+    let shutdown = Arc::new(tokio::sync::Notify::new());
+    let admin = match admin::token_from_env() {
+        Some(token) => admin::router(server.clone(), &token, shutdown.clone(), config),
+        None => Router::new(),
+    };
+
     let app = Router::new()
-        .route("/version", get(|| async { "1.0.0" }))
-        .route("/tools/list", get(get_tools))
-        .route("/tools/call", post(tool_call))
-        .with_state(server)
+        .route("/mcp", post(mcp_endpoint).get(mcp_notifications).delete(end_mcp_session))
+        .with_state(server.clone())
+        .merge(rest::router(server.clone()))
+        // Larger bodies get 413 Payload Too Large before they are buffered
+        .layer(DefaultBodyLimit::max(server.message_limits().max_bytes))
+        .merge(admin)
         .layer(
             CorsLayer::new()
-                .allow_origin("*".parse::<HeaderValue>().unwrap())
-                .allow_methods([Method::GET, Method::POST])
+                // Config reloads may change the origins
+                .allow_origin(AllowOrigin::predicate(move |origin, _| origins.allows(origin)))
+                .allow_methods([Method::GET, Method::POST, Method::DELETE])
+                .expose_headers([HeaderName::from_static(SESSION_HEADER)])
         );
 
     let addr = std::net::SocketAddr::from(([0, 0, 0, 0], port));
     info!("Listening on {}", addr);
     
+    // After a drain, requests in flight are answered before the server
+    // stops; ending the sessions closes their notification streams
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .with_graceful_shutdown(async move {
+            shutdown.notified().await;
+            server.end_all_sessions().await;
+        })
         .await?;
+    info!("HTTP server drained and stopped");
     
     Ok(())
 }
 
-async fn get_tools(
+/// Header carrying the session id on the `/mcp` endpoint
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// JSON-RPC endpoint for MCP clients. Replies with JSON, or with a single
+/// `message` event when the client only accepts `text/event-stream`.
+/// Notifications get `202 Accepted` without a body. Clients send back the
+/// `Mcp-Session-Id` header returned by their initialize request.
+async fn mcp_endpoint(
     State(server): State<Arc<McpServer>>,
-) -> impl IntoResponse {
-    // Create a tools/list JSON-RPC request
-    let request = serde_json::json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "tools/list"
-    });
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let session = headers
+        .get(SESSION_HEADER)
+        .and_then(|value| value.to_str().ok());
 
-    match server.handle_message(&request.to_string()).await {
-        Ok(response) => {
-            match serde_json::from_str::<serde_json::Value>(&response) {
-                Ok(json) => {
-                    if let Some(result) = json.as_object().and_then(|obj| obj.get("result")) {
-                        // Return the tools array directly without nesting
-                        Json(result.clone()).into_response()
-                    } else {
-                        Json(json).into_response()
-                    }
-                },
-                Err(e) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to parse response: {}", e),
-                ).into_response(),
-            }
-        },
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to get tools: {}", e),
-        ).into_response(),
+    // With per-client tenants, the token decides which tenant a new session
+    // gets; later requests must send the same token
+    let credential = bearer_token(&headers);
+    let connection = peer.to_string();
+    let handling = server.handle_connection_message(&connection, session, &body);
+    let response = match mcp::session::with_credential(credential, handling).await {
+        Ok(response) => response,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to handle message: {}", e),
+            ).into_response();
+        }
+    };
+
+    // Notifications have no response
+    if response.is_empty() {
+        return StatusCode::ACCEPTED.into_response();
     }
+
+    let new_session = session_id_from_response(&response);
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let mut http_response = if accept.contains("text/event-stream") && !accept.contains("application/json") {
+        (
+            [(header::CONTENT_TYPE, "text/event-stream")],
+            format!("event: message\ndata: {}\n\n", response),
+        ).into_response()
+    } else {
+        ([(header::CONTENT_TYPE, "application/json")], response).into_response()
+    };
+    if let Some(value) = new_session.and_then(|id| HeaderValue::from_str(&id).ok()) {
+        http_response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    http_response
+}
+
+/// The token of an `Authorization: Bearer` header
+fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string)
 }
 
-async fn tool_call(
+/// Event stream of server-initiated notifications for the session named in
+/// the `Mcp-Session-Id` header
+async fn mcp_notifications(
     State(server): State<Arc<McpServer>>,
-    Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    match server.handle_message(&serde_json::to_string(&request).unwrap()).await {
-        Ok(response) => {
-            match serde_json::from_str::<serde_json::Value>(&response) {
-                Ok(json) => Json(json).into_response(),
-                Err(e) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to parse response: {}", e),
-                ).into_response(),
-            }
-        },
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to handle tool call: {}", e),
-        ).into_response(),
+    headers: HeaderMap,
+) -> Response {
+    let Some(session) = headers.get(SESSION_HEADER).and_then(|value| value.to_str().ok()) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    if !server.session_owned_by(session, bearer_token(&headers).as_deref()).await {
+        return StatusCode::NOT_FOUND.into_response();
     }
-}
\ No newline at end of file
+    let Some(receiver) = server.subscribe_notifications(session).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let events = UnboundedReceiverStream::new(receiver)
+        .map(|notification| Ok::<_, Infallible>(Event::default().event("message").data(notification)));
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// Ends the session named in the `Mcp-Session-Id` header
+async fn end_mcp_session(
+    State(server): State<Arc<McpServer>>,
+    headers: HeaderMap,
+) -> StatusCode {
+    let Some(session) = headers.get(SESSION_HEADER).and_then(|value| value.to_str().ok()) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    if !server.session_owned_by(session, bearer_token(&headers).as_deref()).await {
+        return StatusCode::NOT_FOUND;
+    }
+    if server.end_session(session).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
@@ -0,0 +1,121 @@
+use serde::Serialize;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How much one session may use before its tool calls are refused, so a
+/// runaway agent loop cannot keep hitting Home Assistant or paid APIs.
+/// Parsed from a spec such as `calls=200,seconds=900,bytes=50000000`; limits
+/// left out are unlimited.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BudgetConfig {
+    /// Tool and plugin calls the session may make
+    pub max_tool_calls: Option<u64>,
+    /// How long after it started the session may still call tools
+    pub max_duration: Option<Duration>,
+    /// Bytes plugins may exchange with upstream HTTP services on the
+    /// session's behalf
+    pub max_upstream_bytes: Option<u64>,
+}
+
+impl FromStr for BudgetConfig {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut config = BudgetConfig::default();
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got '{}'", setting))?;
+            let value: u64 = value
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid {}: {}", key.trim(), e))?;
+            match key.trim() {
+                "calls" => config.max_tool_calls = Some(value),
+                "seconds" => config.max_duration = Some(Duration::from_secs(value)),
+                "bytes" => config.max_upstream_bytes = Some(value),
+                other => return Err(format!("Unknown session budget setting '{}'", other)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl BudgetConfig {
+    /// Budget from `MCP_SESSION_BUDGET`, if set and valid
+    pub fn from_env() -> Option<Self> {
+        let spec = std::env::var("MCP_SESSION_BUDGET").ok()?;
+        match spec.parse() {
+            Ok(config) => Some(config),
+            Err(e) => {
+                tracing::warn!("Ignoring MCP_SESSION_BUDGET: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// The limit a session ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BudgetKind {
+    ToolCalls,
+    Seconds,
+    UpstreamBytes,
+}
+
+/// Why a tool call was refused or cut short; sent as the error data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BudgetExceeded {
+    pub budget: BudgetKind,
+    pub limit: u64,
+    pub used: u64,
+}
+
+tokio::task_local! {
+    static UPSTREAM_BYTES: Arc<AtomicU64>;
+}
+
+/// Counts bytes a plugin sent to or received from an upstream service
+/// against the calling session's budget. Does nothing outside `metered`.
+pub fn count_upstream_bytes(bytes: usize) {
+    let _ = UPSTREAM_BYTES.try_with(|counter| counter.fetch_add(bytes as u64, Ordering::Relaxed));
+}
+
+/// Runs `execution`, returning its output and the upstream bytes it counted
+pub async fn metered<F: Future>(execution: F) -> (F::Output, u64) {
+    let counter = Arc::new(AtomicU64::new(0));
+    let output = UPSTREAM_BYTES.scope(counter.clone(), execution).await;
+    (output, counter.load(Ordering::Relaxed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        let config: BudgetConfig = "calls=100, seconds=60,bytes=2048".parse().unwrap();
+        assert_eq!(config.max_tool_calls, Some(100));
+        assert_eq!(config.max_duration, Some(Duration::from_secs(60)));
+        assert_eq!(config.max_upstream_bytes, Some(2048));
+
+        assert_eq!("calls=5".parse::<BudgetConfig>().unwrap().max_upstream_bytes, None);
+        assert!("calls=-1".parse::<BudgetConfig>().is_err());
+        assert!("tokens=5".parse::<BudgetConfig>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metered_counts_only_its_own_bytes() {
+        count_upstream_bytes(10);
+        let ((), bytes) = metered(async {
+            count_upstream_bytes(100);
+            count_upstream_bytes(23);
+        })
+        .await;
+        assert_eq!(bytes, 123);
+    }
+}
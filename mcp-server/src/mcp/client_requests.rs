@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+use super::notifications::NotificationDispatcher;
+
+type PendingReply = oneshot::Sender<Result<Value, Value>>;
+
+/// Sends requests from the server to a client, such as
+/// `sampling/createMessage` or `roots/list`, and matches up the responses the
+/// client posts back
+pub struct ClientRequests {
+    notifications: Arc<NotificationDispatcher>,
+    pending: Mutex<HashMap<String, PendingReply>>,
+}
+
+impl ClientRequests {
+    pub fn new(notifications: Arc<NotificationDispatcher>) -> Self {
+        Self {
+            notifications,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sends a request on the session's notification stream and waits for the
+    /// answer. `None` means the session has no open stream to send it on.
+    pub async fn send(&self, session_id: &str, method: &str, params: Value, timeout: Duration) -> Option<Result<Value>> {
+        let id = format!("{}-{}", method.replace('/', "-"), uuid::Uuid::new_v4());
+        let (reply, outcome) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), reply);
+
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if !self.notifications.send_to(session_id, request.to_string()) {
+            self.pending.lock().unwrap().remove(&id);
+            return None;
+        }
+        debug!("Sent {} request {} to session {}", method, id, session_id);
+
+        let outcome = tokio::time::timeout(timeout, outcome).await;
+        self.pending.lock().unwrap().remove(&id);
+        Some(match outcome {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(error))) => Err(anyhow!("Client returned an error for {}: {}", method, error)),
+            Ok(Err(_)) => Err(anyhow!("{} request {} was dropped", method, id)),
+            Err(_) => Err(anyhow!("Client did not answer {} within {:?}", method, timeout)),
+        })
+    }
+
+    /// Delivers a client's response to the request with that id; returns
+    /// false if no request is waiting for it
+    pub fn resolve(&self, id: &Value, outcome: Result<Value, Value>) -> bool {
+        let Some(id) = id.as_str() else {
+            return false;
+        };
+        match self.pending.lock().unwrap().remove(id) {
+            Some(reply) => reply.send(outcome).is_ok(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_without_stream_returns_none() {
+        let requests = ClientRequests::new(Arc::new(NotificationDispatcher::new()));
+        assert!(requests.send("nobody", "roots/list", json!({}), Duration::from_secs(1)).await.is_none());
+        assert!(requests.pending.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_request_times_out() {
+        let notifications = Arc::new(NotificationDispatcher::new());
+        let _stream = notifications.subscribe("session");
+        let requests = ClientRequests::new(notifications);
+
+        let outcome = requests.send("session", "roots/list", json!({}), Duration::from_millis(10)).await;
+        assert!(outcome.unwrap().unwrap_err().to_string().contains("did not answer"));
+    }
+
+    #[test]
+    fn test_unknown_response_ids_are_not_resolved() {
+        let requests = ClientRequests::new(Arc::new(NotificationDispatcher::new()));
+        assert!(!requests.resolve(&json!("sampling-unknown"), Ok(json!({}))));
+        assert!(!requests.resolve(&json!(7), Ok(json!({}))));
+    }
+}
@@ -0,0 +1,8 @@
+//! The error codes and kinds the server answers with. They live in
+//! mcp-core, so the bridge and the client read them the same way.
+
+pub use mcp_core::{
+    ErrorKind, BUDGET_EXCEEDED, DEADLINE_EXCEEDED, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR,
+    PLUGIN_UNAVAILABLE, RATE_LIMITED, RESOURCE_NOT_FOUND, SESSION_NOT_FOUND, SESSION_NOT_INITIALIZED, SHUTTING_DOWN,
+    TOOL_EXECUTION_FAILED,
+};
@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+/// Events kept for `events/tail` when `MCP_EVENT_BUFFER_SIZE` is not set
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// Entries `events/tail` returns when the caller gives no limit
+const DEFAULT_TAIL: usize = 100;
+
+/// Events a slow subscriber may fall behind by before it misses some
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Something a plugin announced on the [`EventBus`], e.g. a motion sensor
+/// firing or a notification being sent
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    /// Plugin that published the event
+    pub source: String,
+    /// Dot-separated name subscribers match on, e.g. `motion.hallway`
+    pub topic: String,
+    pub data: Value,
+}
+
+/// Parameters of `events/tail`; every filter is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventQuery {
+    /// Topic pattern, as for [`EventBus::subscribe`]
+    pub topic: Option<String>,
+    pub source: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl EventQuery {
+    fn matches(&self, event: &Event) -> bool {
+        self.topic.as_deref().is_none_or(|pattern| topic_matches(pattern, &event.topic))
+            && self.source.as_ref().is_none_or(|source| *source == event.source)
+    }
+}
+
+/// Whether `topic` matches `pattern`: `*` matches every topic, `motion.*`
+/// every topic under `motion`, anything else only itself
+pub fn topic_matches(pattern: &str, topic: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some("") => true,
+        Some(prefix) if prefix.ends_with('.') => topic.starts_with(prefix),
+        _ => pattern == topic,
+    }
+}
+
+struct Shared {
+    sender: broadcast::Sender<Event>,
+    recent: Mutex<VecDeque<Event>>,
+    capacity: usize,
+}
+
+/// Publish/subscribe channel between plugins. Every event goes to each
+/// current subscriber whose pattern matches, and the most recent ones are
+/// kept for `events/tail`. Clones share the same bus.
+#[derive(Clone)]
+pub struct EventBus {
+    shared: Arc<Shared>,
+}
+
+impl EventBus {
+    /// A bus keeping the last `capacity` events
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            shared: Arc::new(Shared {
+                sender,
+                recent: Mutex::new(VecDeque::with_capacity(capacity)),
+                capacity,
+            }),
+        }
+    }
+
+    /// Sized by `MCP_EVENT_BUFFER_SIZE`
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("MCP_EVENT_BUFFER_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+        Self::new(capacity)
+    }
+
+    /// Announces an event to current subscribers; nobody needs to be
+    /// listening
+    pub fn publish(&self, source: &str, topic: &str, data: Value) -> Event {
+        let event = Event {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            source: source.to_string(),
+            topic: topic.to_string(),
+            data,
+        };
+        debug!("Event {} from {}", event.topic, event.source);
+        if self.shared.capacity > 0 {
+            let mut recent = self.shared.recent.lock().unwrap();
+            if recent.len() == self.shared.capacity {
+                recent.pop_front();
+            }
+            recent.push_back(event.clone());
+        }
+        let _ = self.shared.sender.send(event.clone());
+        event
+    }
+
+    /// Events published from now on whose topic matches `pattern`
+    pub fn subscribe(&self, pattern: &str) -> EventSubscription {
+        EventSubscription {
+            receiver: self.shared.sender.subscribe(),
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// The last matching events, oldest first
+    pub fn tail(&self, query: &EventQuery) -> Vec<Event> {
+        let limit = query.limit.unwrap_or(DEFAULT_TAIL);
+        let recent = self.shared.recent.lock().unwrap();
+        let mut matching: Vec<Event> = recent
+            .iter()
+            .rev()
+            .filter(|event| query.matches(event))
+            .take(limit)
+            .cloned()
+            .collect();
+        matching.reverse();
+        matching
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+/// A subscriber's end of the [`EventBus`]
+pub struct EventSubscription {
+    receiver: broadcast::Receiver<Event>,
+    pattern: String,
+}
+
+impl EventSubscription {
+    /// The next matching event; `None` once the bus is gone. A subscriber
+    /// that falls too far behind skips the events it missed.
+    pub async fn recv(&mut self) -> Option<Event> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if topic_matches(&self.pattern, &event.topic) => return Some(event),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("Event subscriber for {} missed {} events", self.pattern, missed)
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_topic_patterns() {
+        assert!(topic_matches("*", "motion.hallway"));
+        assert!(topic_matches("motion.*", "motion.hallway"));
+        assert!(topic_matches("motion.*", "motion.hallway.ceiling"));
+        assert!(!topic_matches("motion.*", "motion"));
+        assert!(!topic_matches("motion.*", "motions.hallway"));
+        assert!(topic_matches("motion.hallway", "motion.hallway"));
+        assert!(!topic_matches("motion", "motion.hallway"));
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_get_matching_events() {
+        let bus = EventBus::new(10);
+        let mut motion = bus.subscribe("motion.*");
+        let mut everything = bus.subscribe("*");
+
+        bus.publish("mqtt", "door.front", json!({ "open": true }));
+        let published = bus.publish("mqtt", "motion.hallway", json!({ "detected": true }));
+
+        assert_eq!(motion.recv().await, Some(published));
+        assert_eq!(everything.recv().await.unwrap().topic, "door.front");
+        assert_eq!(everything.recv().await.unwrap().topic, "motion.hallway");
+    }
+
+    #[test]
+    fn test_tail_keeps_the_latest_events() {
+        let bus = EventBus::new(3);
+        for i in 0..5 {
+            bus.publish(if i % 2 == 0 { "mqtt" } else { "scheduler" }, &format!("tick.{}", i), json!(i));
+        }
+
+        let all = bus.tail(&EventQuery::default());
+        assert_eq!(all.iter().map(|e| e.data.clone()).collect::<Vec<_>>(), vec![json!(2), json!(3), json!(4)]);
+        let mqtt = bus.tail(&EventQuery { source: Some("mqtt".to_string()), limit: Some(1), ..Default::default() });
+        assert_eq!(mqtt[0].topic, "tick.4");
+        assert!(bus.tail(&EventQuery { topic: Some("tick.1".to_string()), ..Default::default() }).is_empty());
+    }
+}
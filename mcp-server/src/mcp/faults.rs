@@ -0,0 +1,160 @@
+use serde_json::{json, Value};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often plugin executions fail or slow down on purpose, for testing
+/// client retries and circuit breakers. Parsed from a spec such as
+/// `fail=10,delay=20,delay_ms=2000,seed=7`; percentages may be fractional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultConfig {
+    /// Percentage of executions that fail without running
+    pub failure_percent: f64,
+    /// Percentage of executions that are held back by `delay` first
+    pub delay_percent: f64,
+    pub delay: Duration,
+    /// Makes the sequence of faults repeatable
+    pub seed: Option<u64>,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            failure_percent: 0.0,
+            delay_percent: 0.0,
+            delay: Duration::from_millis(1000),
+            seed: None,
+        }
+    }
+}
+
+impl FromStr for FaultConfig {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut config = FaultConfig::default();
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got '{}'", setting))?;
+            let invalid = |e: &dyn std::fmt::Display| format!("Invalid {}: {}", key, e);
+            match key.trim() {
+                "fail" => config.failure_percent = value.parse().map_err(|e| invalid(&e))?,
+                "delay" => config.delay_percent = value.parse().map_err(|e| invalid(&e))?,
+                "delay_ms" => config.delay = Duration::from_millis(value.parse().map_err(|e| invalid(&e))?),
+                "seed" => config.seed = Some(value.parse().map_err(|e| invalid(&e))?),
+                other => return Err(format!("Unknown fault injection setting '{}'", other)),
+            }
+        }
+        for percent in [config.failure_percent, config.delay_percent] {
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(format!("Percentages must be between 0 and 100, got {}", percent));
+            }
+        }
+        if config.failure_percent + config.delay_percent > 100.0 {
+            return Err("fail and delay add up to more than 100%".to_string());
+        }
+        Ok(config)
+    }
+}
+
+/// What happened to one plugin execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    Failure,
+    Delay(Duration),
+}
+
+impl Fault {
+    /// Value of the `faultInjection` tag added to responses
+    pub fn describe(&self) -> Value {
+        match self {
+            Fault::Failure => json!({ "kind": "failure" }),
+            Fault::Delay(delay) => json!({ "kind": "delay", "delayMs": delay.as_millis() as u64 }),
+        }
+    }
+
+    /// Tags a successful result as delayed under `_meta.faultInjection`
+    pub fn tag_result(&self, result: &mut Value) {
+        if let Some(result) = result.as_object_mut() {
+            let meta = result.entry("_meta").or_insert_with(|| json!({}));
+            if let Some(meta) = meta.as_object_mut() {
+                meta.insert("faultInjection".to_string(), self.describe());
+            }
+        }
+    }
+}
+
+/// Decides, execution by execution, which faults to inject
+pub struct FaultInjector {
+    config: FaultConfig,
+    rng: Mutex<fastrand::Rng>,
+}
+
+impl FaultInjector {
+    pub fn new(config: FaultConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => fastrand::Rng::with_seed(seed),
+            None => fastrand::Rng::new(),
+        };
+        Self { config, rng: Mutex::new(rng) }
+    }
+
+    pub fn roll(&self) -> Option<Fault> {
+        let roll = self.rng.lock().unwrap().f64() * 100.0;
+        if roll < self.config.failure_percent {
+            Some(Fault::Failure)
+        } else if roll < self.config.failure_percent + self.config.delay_percent {
+            Some(Fault::Delay(self.config.delay))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        let config: FaultConfig = "fail=10, delay=2.5,delay_ms=250,seed=7".parse().unwrap();
+        assert_eq!(config, FaultConfig {
+            failure_percent: 10.0,
+            delay_percent: 2.5,
+            delay: Duration::from_millis(250),
+            seed: Some(7),
+        });
+
+        assert!("fail=110".parse::<FaultConfig>().is_err());
+        assert!("fail=60,delay=50".parse::<FaultConfig>().is_err());
+        assert!("crash=1".parse::<FaultConfig>().is_err());
+        assert!("fail".parse::<FaultConfig>().is_err());
+    }
+
+    #[test]
+    fn test_roll_follows_percentages_and_seed() {
+        let config: FaultConfig = "fail=20,delay=30,delay_ms=5,seed=42".parse().unwrap();
+        let first = FaultInjector::new(config.clone());
+        let rolls: Vec<Option<Fault>> = (0..1000).map(|_| first.roll()).collect();
+
+        let failures = rolls.iter().filter(|f| **f == Some(Fault::Failure)).count();
+        let delays = rolls.iter().filter(|f| **f == Some(Fault::Delay(Duration::from_millis(5)))).count();
+        assert!((150..250).contains(&failures), "{} failures", failures);
+        assert!((250..350).contains(&delays), "{} delays", delays);
+
+        // The same seed injects the same faults
+        let second = FaultInjector::new(config);
+        assert!(rolls.iter().all(|fault| *fault == second.roll()));
+
+        let never = FaultInjector::new(FaultConfig::default());
+        assert!((0..100).all(|_| never.roll().is_none()));
+    }
+
+    #[test]
+    fn test_delay_is_tagged_in_meta() {
+        let mut result = json!({ "content": [] });
+        Fault::Delay(Duration::from_millis(250)).tag_result(&mut result);
+        assert_eq!(result["_meta"]["faultInjection"], json!({ "kind": "delay", "delayMs": 250 }));
+    }
+}
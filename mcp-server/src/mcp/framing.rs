@@ -0,0 +1,357 @@
+use tokio::io::{self, AsyncRead, AsyncReadExt};
+use tracing::debug;
+
+/// How messages are delimited on a stdio stream: one JSON message per line,
+/// or LSP-style `Content-Length` headers followed by the message body, which
+/// some IDE integrations send
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    #[default]
+    Lines,
+    ContentLength,
+}
+
+impl Framing {
+    /// Replaces the contents of `out` with `message` framed for writing, so
+    /// a writer can reuse one buffer for every message
+    pub fn frame_into(self, message: &str, out: &mut Vec<u8>) {
+        out.clear();
+        if self == Framing::ContentLength {
+            out.extend_from_slice(format!("Content-Length: {}\r\n\r\n", message.len()).as_bytes());
+        }
+        out.extend_from_slice(message.as_bytes());
+        if self == Framing::Lines {
+            out.push(b'\n');
+        }
+    }
+}
+
+/// A message longer than the reader's limit. It is skipped without being
+/// buffered in full, and reading can go on after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageTooLarge {
+    pub max_bytes: usize,
+}
+
+impl std::fmt::Display for MessageTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Message longer than {} bytes", self.max_bytes)
+    }
+}
+
+impl std::error::Error for MessageTooLarge {}
+
+/// What is left to drop of a message that was too large
+#[derive(Debug, Clone, Copy)]
+enum Skip {
+    Line,
+    Bytes(usize),
+}
+
+/// Reads messages in either framing, detected per message; replies should
+/// use `framing()`, the framing of the last message read. Partly received
+/// messages stay buffered, so `next_message` is safe to cancel in `select!`.
+pub struct MessageReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    framing: Framing,
+    max_bytes: usize,
+    skipping: Option<Skip>,
+}
+
+impl<R: AsyncRead + Unpin> MessageReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, buffer: Vec::new(), framing: Framing::Lines, max_bytes: usize::MAX, skipping: None }
+    }
+
+    /// Rejects messages longer than `max_bytes` with a `MessageTooLarge`
+    /// error instead of buffering them
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Next message, skipping blank and non-JSON lines; `None` at EOF
+    pub async fn next_message(&mut self) -> io::Result<Option<String>> {
+        loop {
+            if self.skip_oversized() {
+                match parse(&self.buffer)? {
+                    Parsed::Message(message, framing, consumed) => {
+                        self.buffer.drain(..consumed);
+                        self.framing = framing;
+                        if message.len() > self.max_bytes {
+                            return Err(self.too_large());
+                        }
+                        return Ok(Some(message));
+                    }
+                    Parsed::Body { start, length } if length > self.max_bytes => {
+                        self.buffer.drain(..start);
+                        self.framing = Framing::ContentLength;
+                        self.skipping = Some(Skip::Bytes(length));
+                        return Err(self.too_large());
+                    }
+                    Parsed::Body { .. } => {}
+                    Parsed::Incomplete { skipped } => {
+                        self.buffer.drain(..skipped);
+                        if self.buffer.len() > self.max_bytes {
+                            self.skipping = Some(Skip::Line);
+                            return Err(self.too_large());
+                        }
+                    }
+                }
+            }
+            if self.reader.read_buf(&mut self.buffer).await? == 0 {
+                return self.finish();
+            }
+        }
+    }
+
+    fn too_large(&self) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, MessageTooLarge { max_bytes: self.max_bytes })
+    }
+
+    /// Drops what has arrived of a message that was too large; true once
+    /// all of it is gone
+    fn skip_oversized(&mut self) -> bool {
+        match &mut self.skipping {
+            None => return true,
+            Some(Skip::Bytes(remaining)) => {
+                let skipped = (*remaining).min(self.buffer.len());
+                self.buffer.drain(..skipped);
+                *remaining -= skipped;
+                if *remaining > 0 {
+                    return false;
+                }
+            }
+            Some(Skip::Line) => match self.buffer.iter().position(|&b| b == b'\n') {
+                Some(newline) => {
+                    self.buffer.drain(..=newline);
+                }
+                None => {
+                    self.buffer.clear();
+                    return false;
+                }
+            },
+        }
+        self.skipping = None;
+        true
+    }
+
+    /// At EOF a last line without a newline still counts; a partial
+    /// `Content-Length` message does not
+    fn finish(&mut self) -> io::Result<Option<String>> {
+        if self.skipping.take().is_some() {
+            return Ok(None);
+        }
+        self.buffer.push(b'\n');
+        let buffer = std::mem::take(&mut self.buffer);
+        if let Parsed::Message(message, framing, _) = parse(&buffer)? {
+            self.framing = framing;
+            if message.len() > self.max_bytes {
+                return Err(self.too_large());
+            }
+            return Ok(Some(message));
+        }
+        let lines = String::from_utf8_lossy(&buffer);
+        if lines.lines().any(|line| matches!(content_length(line.trim()), Ok(Some(_)))) {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "EOF inside a Content-Length message"));
+        }
+        Ok(None)
+    }
+}
+
+/// How far `parse` got in a buffer
+enum Parsed {
+    /// The first complete message, its framing and how many bytes it took
+    /// up with any blank lines before it
+    Message(String, Framing, usize),
+    /// A `Content-Length` header block, ending at `start`, whose body of
+    /// `length` bytes has not all arrived
+    Body { start: usize, length: usize },
+    /// More input is needed; the first `skipped` bytes were blank or
+    /// non-JSON lines
+    Incomplete { skipped: usize },
+}
+
+fn parse(buffer: &[u8]) -> io::Result<Parsed> {
+    let mut lines = Lines { buffer, position: 0 };
+    let (header, length) = loop {
+        let start = lines.position;
+        match lines.next()? {
+            None => return Ok(Parsed::Incomplete { skipped: start }),
+            Some("") => continue,
+            Some(line) => match content_length(line)? {
+                Some(length) => break (start, length),
+                None if is_json(line) => return Ok(Parsed::Message(line.to_string(), Framing::Lines, lines.position)),
+                None => debug!("Skipping non-JSON input line: {}", line),
+            },
+        }
+    };
+    // Other headers, such as Content-Type, are skipped up to the blank line
+    // ending the header block
+    loop {
+        match lines.next()? {
+            None => return Ok(Parsed::Incomplete { skipped: header }),
+            Some("") => break,
+            Some(_) => continue,
+        }
+    }
+    let end = lines.position + length;
+    if buffer.len() < end {
+        return Ok(Parsed::Body { start: lines.position, length });
+    }
+    let body = std::str::from_utf8(&buffer[lines.position..end]).map_err(invalid_data)?;
+    Ok(Parsed::Message(body.to_string(), Framing::ContentLength, end))
+}
+
+/// Complete, trimmed lines of a buffer
+struct Lines<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn next(&mut self) -> io::Result<Option<&'a str>> {
+        let rest = &self.buffer[self.position..];
+        let Some(newline) = rest.iter().position(|&b| b == b'\n') else {
+            return Ok(None);
+        };
+        self.position += newline + 1;
+        std::str::from_utf8(&rest[..newline]).map(|line| Some(line.trim())).map_err(invalid_data)
+    }
+}
+
+/// Whether a line may be a JSON-RPC message or batch. Anything else, such as
+/// log output a wrapper script interleaves with the messages, is skipped. A
+/// line starting with `[` is a batch if it is a JSON array, whatever its
+/// elements are, so that `[1]` is answered as invalid rather than skipped.
+fn is_json(line: &str) -> bool {
+    line.starts_with('{')
+        || line.strip_prefix('[').is_some_and(|rest| rest.trim_start().starts_with(['{', ']']))
+        || line.starts_with('[') && serde_json::from_str::<serde::de::IgnoredAny>(line).is_ok()
+}
+
+/// The length a `Content-Length` header line gives, or `None` for any other line
+fn content_length(line: &str) -> io::Result<Option<usize>> {
+    let Some((name, value)) = line.split_once(':') else {
+        return Ok(None);
+    };
+    if !name.trim().eq_ignore_ascii_case("content-length") {
+        return Ok(None);
+    }
+    value
+        .trim()
+        .parse()
+        .map(Some)
+        .map_err(|e| invalid_data(format!("Invalid Content-Length '{}': {}", value.trim(), e)))
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncWriteExt, BufReader};
+
+    const PING: &str = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+
+    fn frame(framing: Framing, message: &str) -> Vec<u8> {
+        let mut framed = b"left over".to_vec();
+        framing.frame_into(message, &mut framed);
+        framed
+    }
+
+    #[tokio::test]
+    async fn test_reads_both_framings_from_one_stream() {
+        let mut input = Vec::new();
+        input.extend(frame(Framing::Lines, PING));
+        input.extend(b"\n\r\nwrapper: starting server\n");
+        input.extend(frame(Framing::ContentLength, r#"{"name":"é"}"#));
+        input.extend(b"Content-Length: 2\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}");
+        // A one-byte buffer makes every read a partial one
+        let mut reader = MessageReader::new(BufReader::with_capacity(1, &input[..]));
+
+        assert_eq!(reader.next_message().await.unwrap().as_deref(), Some(PING));
+        assert_eq!(reader.framing(), Framing::Lines);
+        assert_eq!(reader.next_message().await.unwrap().as_deref(), Some(r#"{"name":"é"}"#));
+        assert_eq!(reader.framing(), Framing::ContentLength);
+        assert_eq!(reader.next_message().await.unwrap().as_deref(), Some("{}"));
+        assert_eq!(reader.next_message().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_skips_log_lines_between_messages() {
+        let input = format!("[INFO] starting\n{}\n2024-01-01 ready\n\n{}", PING, PING);
+        let mut reader = MessageReader::new(input.as_bytes());
+        assert_eq!(reader.next_message().await.unwrap().as_deref(), Some(PING));
+        assert_eq!(reader.next_message().await.unwrap().as_deref(), Some(PING));
+        let mut reader = MessageReader::new(&b"shutting down\n"[..]);
+        assert_eq!(reader.next_message().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_reads_batches_of_any_elements() {
+        let input = "[1]\n[INFO] [1] started\n[1, 2]\n[\"a\", [3]]\n";
+        let mut reader = MessageReader::new(input.as_bytes());
+        assert_eq!(reader.next_message().await.unwrap().as_deref(), Some("[1]"));
+        assert_eq!(reader.next_message().await.unwrap().as_deref(), Some("[1, 2]"));
+        assert_eq!(reader.next_message().await.unwrap().as_deref(), Some(r#"["a", [3]]"#));
+        assert_eq!(reader.next_message().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_the_rest_of_a_split_message() {
+        let (mut client, server) = io::duplex(64);
+        let mut reader = MessageReader::new(BufReader::new(server));
+        let framed = frame(Framing::ContentLength, PING);
+        let writer = tokio::spawn(async move {
+            for chunk in framed.chunks(7) {
+                client.write_all(chunk).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+        });
+
+        assert_eq!(reader.next_message().await.unwrap().as_deref(), Some(PING));
+        writer.await.unwrap();
+        assert_eq!(reader.next_message().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_skips_messages_over_the_limit() {
+        let mut input = Vec::new();
+        input.extend(format!("{{\"pad\":\"{}\"}}\n", "x".repeat(100)).bytes());
+        input.extend(frame(Framing::Lines, PING));
+        input.extend(format!("Content-Length: 500\r\n\r\n{{\"pad\":\"{}\"}}", "y".repeat(490)).bytes());
+        input.extend(frame(Framing::ContentLength, PING));
+        let mut reader = MessageReader::new(BufReader::with_capacity(16, &input[..])).with_max_bytes(64);
+
+        for expected in [None, Some(Framing::Lines), None, Some(Framing::ContentLength)] {
+            match (reader.next_message().await, expected) {
+                (Ok(message), Some(framing)) => {
+                    assert_eq!(message.as_deref(), Some(PING));
+                    assert_eq!(reader.framing(), framing);
+                }
+                (Err(e), None) => {
+                    let too_large = e.get_ref().and_then(|e| e.downcast_ref::<MessageTooLarge>());
+                    assert_eq!(too_large, Some(&MessageTooLarge { max_bytes: 64 }));
+                }
+                (outcome, _) => panic!("Unexpected {:?}", outcome),
+            }
+        }
+        assert_eq!(reader.next_message().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_truncated_and_invalid_frames() {
+        let input = b"Content-Length: 100\r\n\r\n{}";
+        assert!(MessageReader::new(&input[..]).next_message().await.is_err());
+        let input = b"Content-Length: many\r\n\r\n{}";
+        assert!(MessageReader::new(&input[..]).next_message().await.is_err());
+    }
+}
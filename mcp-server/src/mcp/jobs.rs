@@ -0,0 +1,288 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Jobs `jobs/list` returns when the caller gives no limit
+const DEFAULT_LIST_LIMIT: usize = 50;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    tool TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    session_id TEXT,
+    status TEXT NOT NULL,
+    result TEXT,
+    error TEXT,
+    created_at TEXT NOT NULL,
+    started_at TEXT,
+    finished_at TEXT
+)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl JobStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(status: &str) -> Option<Self> {
+        [JobStatus::Queued, JobStatus::Running, JobStatus::Completed, JobStatus::Failed]
+            .into_iter()
+            .find(|candidate| candidate.as_str() == status)
+    }
+}
+
+/// A tool call submitted with `jobs/submit` to run in the background
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    pub tool: String,
+    pub arguments: Map<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub status: JobStatus,
+    /// The `tools/call` result, once completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// The JSON-RPC error, once failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl Job {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        fn json<T: serde::de::DeserializeOwned>(text: Option<String>) -> Option<T> {
+            text.and_then(|text| serde_json::from_str(&text).ok())
+        }
+        fn time(text: Option<String>) -> Option<DateTime<Utc>> {
+            text.and_then(|text| DateTime::parse_from_rfc3339(&text).ok()).map(|time| time.with_timezone(&Utc))
+        }
+        Ok(Self {
+            id: row.get("id")?,
+            tool: row.get("tool")?,
+            arguments: json(row.get("arguments")?).unwrap_or_default(),
+            session_id: row.get("session_id")?,
+            status: JobStatus::parse(&row.get::<_, String>("status")?).unwrap_or(JobStatus::Failed),
+            result: json(row.get("result")?),
+            error: json(row.get("error")?),
+            created_at: time(row.get("created_at")?).unwrap_or_default(),
+            started_at: time(row.get("started_at")?),
+            finished_at: time(row.get("finished_at")?),
+        })
+    }
+}
+
+/// Parameters of `jobs/list`; every filter is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobQuery {
+    pub status: Option<JobStatus>,
+    pub tool: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Background tool calls and their results, kept in SQLite so that queued
+/// and finished jobs survive a restart when the database is a file
+pub struct JobStore {
+    connection: Mutex<Connection>,
+}
+
+impl JobStore {
+    /// Jobs in the database file at `path`, created if missing. Jobs that
+    /// were running when the server stopped are marked failed rather than
+    /// run again, since their tool may already have acted.
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection = Connection::open(path).with_context(|| format!("Failed to open job database {}", path.display()))?;
+        let store = Self::with_connection(connection)?;
+        let interrupted = store.connection.lock().unwrap().execute(
+            "UPDATE jobs SET status = 'failed', error = ?1, finished_at = ?2 WHERE status = 'running'",
+            params![
+                serde_json::json!({ "code": -32603, "message": "Server stopped while the job was running" }).to_string(),
+                Utc::now().to_rfc3339(),
+            ],
+        )?;
+        if interrupted > 0 {
+            tracing::warn!("Marked {} interrupted jobs as failed", interrupted);
+        }
+        Ok(store)
+    }
+
+    /// Jobs kept only for the life of the process
+    pub fn in_memory() -> Self {
+        Self::with_connection(Connection::open_in_memory().expect("in-memory SQLite"))
+            .expect("job table in an in-memory database")
+    }
+
+    /// Jobs in the file `MCP_JOBS_DB` names, or in memory if it is unset or
+    /// cannot be opened
+    pub fn from_env() -> Self {
+        let Ok(path) = std::env::var("MCP_JOBS_DB") else {
+            return Self::in_memory();
+        };
+        match Self::open(Path::new(&path)) {
+            Ok(store) => store,
+            Err(e) => {
+                tracing::warn!("Ignoring MCP_JOBS_DB, keeping jobs in memory: {:#}", e);
+                Self::in_memory()
+            }
+        }
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute(SCHEMA, [])?;
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+
+    /// Queues a call of `tool`
+    pub fn submit(&self, tool: &str, arguments: Map<String, Value>, session_id: Option<&str>) -> Result<Job> {
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            tool: tool.to_string(),
+            arguments,
+            session_id: session_id.map(str::to_string),
+            status: JobStatus::Queued,
+            result: None,
+            error: None,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+        };
+        self.connection.lock().unwrap().execute(
+            "INSERT INTO jobs (id, tool, arguments, session_id, status, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                job.id,
+                job.tool,
+                Value::Object(job.arguments.clone()).to_string(),
+                job.session_id,
+                job.status.as_str(),
+                job.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(job)
+    }
+
+    /// Marks the oldest queued job running and returns it
+    pub fn claim_next(&self) -> Result<Option<Job>> {
+        let connection = self.connection.lock().unwrap();
+        let id: Option<String> = connection
+            .query_row(
+                "SELECT id FROM jobs WHERE status = 'queued' ORDER BY rowid LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        connection.execute(
+            "UPDATE jobs SET status = 'running', started_at = ?2 WHERE id = ?1",
+            params![id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(connection.query_row("SELECT * FROM jobs WHERE id = ?1", [&id], Job::from_row).optional()?)
+    }
+
+    /// Records how a running job ended: its result or its error
+    pub fn finish(&self, id: &str, outcome: Result<Value, Value>) -> Result<()> {
+        let (status, result, error) = match outcome {
+            Ok(result) => (JobStatus::Completed, Some(result.to_string()), None),
+            Err(error) => (JobStatus::Failed, None, Some(error.to_string())),
+        };
+        self.connection.lock().unwrap().execute(
+            "UPDATE jobs SET status = ?2, result = ?3, error = ?4, finished_at = ?5 WHERE id = ?1",
+            params![id, status.as_str(), result, error, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Job>> {
+        let connection = self.connection.lock().unwrap();
+        Ok(connection.query_row("SELECT * FROM jobs WHERE id = ?1", [id], Job::from_row).optional()?)
+    }
+
+    /// Matching jobs, newest first
+    pub fn list(&self, query: &JobQuery) -> Result<Vec<Job>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT * FROM jobs WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR tool = ?2)
+             ORDER BY rowid DESC LIMIT ?3",
+        )?;
+        let limit = query.limit.unwrap_or(DEFAULT_LIST_LIMIT) as i64;
+        let jobs = statement
+            .query_map(params![query.status.map(JobStatus::as_str), query.tool, limit], Job::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(jobs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_jobs_run_in_order_and_keep_their_outcome() {
+        let store = JobStore::in_memory();
+        let first = store.submit("system_info", Map::new(), Some("s1")).unwrap();
+        let second = store.submit("http", json!({ "url": "http://x" }).as_object().unwrap().clone(), None).unwrap();
+
+        let claimed = store.claim_next().unwrap().unwrap();
+        assert_eq!(claimed.id, first.id);
+        assert_eq!(claimed.status, JobStatus::Running);
+        assert!(claimed.started_at.is_some());
+        store.finish(&first.id, Ok(json!({ "content": [] }))).unwrap();
+        assert_eq!(store.claim_next().unwrap().unwrap().id, second.id);
+        store.finish(&second.id, Err(json!({ "code": -1, "message": "boom" }))).unwrap();
+        assert!(store.claim_next().unwrap().is_none());
+
+        let first = store.get(&first.id).unwrap().unwrap();
+        assert_eq!(first.status, JobStatus::Completed);
+        assert_eq!(first.result, Some(json!({ "content": [] })));
+        assert_eq!(first.session_id.as_deref(), Some("s1"));
+        let failed = store.list(&JobQuery { status: Some(JobStatus::Failed), ..Default::default() }).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].error.as_ref().unwrap()["message"], "boom");
+        assert_eq!(failed[0].arguments["url"], "http://x");
+    }
+
+    #[test]
+    fn test_jobs_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.db");
+        let store = JobStore::open(&path).unwrap();
+        let done = store.submit("system_info", Map::new(), None).unwrap();
+        store.claim_next().unwrap();
+        store.finish(&done.id, Ok(json!({ "content": [] }))).unwrap();
+        let interrupted = store.submit("system_info", Map::new(), None).unwrap();
+        store.claim_next().unwrap();
+        let queued = store.submit("system_info", Map::new(), None).unwrap();
+        drop(store);
+
+        let store = JobStore::open(&path).unwrap();
+        assert_eq!(store.get(&done.id).unwrap().unwrap().status, JobStatus::Completed);
+        assert_eq!(store.get(&interrupted.id).unwrap().unwrap().status, JobStatus::Failed);
+        assert_eq!(store.claim_next().unwrap().unwrap().id, queued.id);
+        assert_eq!(store.list(&JobQuery { limit: Some(2), ..Default::default() }).unwrap()[0].id, queued.id);
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{anyhow, Context as _, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::warn;
+
+use super::McpServer;
+
+/// One message the server received, with its reply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub request: Value,
+    /// `None` for notifications, which get no reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+}
+
+/// Appends every request and response to a JSON Lines file, for `replay`
+pub struct Journal {
+    file: Mutex<std::fs::File>,
+}
+
+impl Journal {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open journal {}", path.display()))?;
+        Ok(Self { file: Mutex::new(file) })
+    }
+
+    /// Records a message and the server's reply. Responses from the client
+    /// (to sampling or roots requests) are skipped, since replaying them
+    /// means nothing.
+    pub fn record(&self, session_id: Option<&str>, message: &str, response: &str) {
+        let Ok(request) = serde_json::from_str::<Value>(message) else {
+            return;
+        };
+        if request.get("method").is_none() {
+            return;
+        }
+        let entry = JournalEntry {
+            timestamp: Utc::now(),
+            session_id: session_id.map(str::to_string),
+            request,
+            response: serde_json::from_str(response).ok(),
+        };
+
+        let mut line = serde_json::to_string(&entry).unwrap();
+        line.push('\n');
+        if let Err(e) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            warn!("Failed to write to the request journal: {}", e);
+        }
+    }
+}
+
+/// A replayed message whose response differs from the recorded one
+#[derive(Debug, Clone)]
+pub struct ReplayMismatch {
+    /// Line of the entry in the journal, from 1
+    pub line: usize,
+    pub method: String,
+    pub recorded: Option<Value>,
+    pub replayed: Option<Value>,
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub replayed: usize,
+    pub mismatches: Vec<ReplayMismatch>,
+}
+
+/// Re-sends every message in a journal to `server`, in order, and collects
+/// the responses that changed. Sessions recorded in the journal are mapped to
+/// the ones their replayed initialize requests start; session ids are left
+/// out of the comparison.
+pub async fn replay(server: &McpServer, path: &Path) -> Result<ReplayReport> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open journal {}", path.display()))?;
+    let mut sessions: HashMap<String, String> = HashMap::new();
+    let mut report = ReplayReport::default();
+
+    for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: JournalEntry = serde_json::from_str(&line)
+            .map_err(|e| anyhow!("Invalid journal entry on line {}: {}", index + 1, e))?;
+
+        let session_id = entry
+            .session_id
+            .as_ref()
+            .map(|recorded| sessions.get(recorded).unwrap_or(recorded).clone());
+        let response = server
+            .handle_session_message(session_id.as_deref(), &entry.request.to_string())
+            .await?;
+        let replayed: Option<Value> = serde_json::from_str(&response).ok();
+        report.replayed += 1;
+
+        if let (Some(recorded), Some(started)) = (
+            entry.response.as_ref().and_then(started_session),
+            replayed.as_ref().and_then(started_session),
+        ) {
+            sessions.insert(recorded, started);
+        }
+
+        let recorded = entry.response.map(without_session_id);
+        let replayed = replayed.map(without_session_id);
+        if recorded != replayed {
+            report.mismatches.push(ReplayMismatch {
+                line: index + 1,
+                method: entry.request["method"].as_str().unwrap_or_default().to_string(),
+                recorded,
+                replayed,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// The session id an initialize response hands out
+fn started_session(response: &Value) -> Option<String> {
+    response["result"]["sessionId"].as_str().map(str::to_string)
+}
+
+fn without_session_id(mut response: Value) -> Value {
+    if let Some(result) = response.get_mut("result").and_then(Value::as_object_mut) {
+        result.remove("sessionId");
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_journal_skips_client_responses() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let journal = Journal::open(&path).unwrap();
+
+        journal.record(Some("s1"), r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#, r#"{"jsonrpc":"2.0","id":1,"result":{}}"#);
+        journal.record(Some("s1"), r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#, "");
+        journal.record(Some("s1"), r#"{"jsonrpc":"2.0","id":"roots-list-1","result":{"roots":[]}}"#, "");
+
+        let entries: Vec<JournalEntry> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].session_id.as_deref(), Some("s1"));
+        assert_eq!(entries[0].response, Some(json!({"jsonrpc": "2.0", "id": 1, "result": {}})));
+        assert_eq!(entries[1].response, None);
+    }
+
+    #[test]
+    fn test_session_ids_are_not_compared() {
+        let first = json!({"result": {"protocolVersion": "2025-03-26", "sessionId": "a"}});
+        let second = json!({"result": {"protocolVersion": "2025-03-26", "sessionId": "b"}});
+        assert_eq!(started_session(&first).as_deref(), Some("a"));
+        assert_eq!(without_session_id(first), without_session_id(second));
+    }
+}
@@ -0,0 +1,236 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Limits on incoming messages, so one huge or deeply nested message cannot
+/// exhaust the server's memory and one connection cannot flood it. Parsed
+/// from a spec such as `bytes=1048576,depth=32,batch=50,rate=50`; settings
+/// left out keep their defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageLimits {
+    /// Largest message accepted, in bytes
+    pub max_bytes: usize,
+    /// Deepest nesting of arrays and objects accepted
+    pub max_depth: usize,
+    /// Most messages accepted in one batch
+    pub max_batch: usize,
+    /// Messages per second each connection may send
+    pub max_rate: Option<u32>,
+}
+
+impl Default for MessageLimits {
+    fn default() -> Self {
+        Self { max_bytes: 4 * 1024 * 1024, max_depth: 64, max_batch: 100, max_rate: None }
+    }
+}
+
+impl FromStr for MessageLimits {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut limits = MessageLimits::default();
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got '{}'", setting))?;
+            let value: usize = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|value| *value > 0)
+                .ok_or_else(|| format!("Invalid {}: '{}'", key.trim(), value.trim()))?;
+            match key.trim() {
+                "bytes" => limits.max_bytes = value,
+                "depth" => limits.max_depth = value,
+                "batch" => limits.max_batch = value,
+                "rate" => limits.max_rate = Some(u32::try_from(value).map_err(|e| format!("Invalid rate: {}", e))?),
+                other => return Err(format!("Unknown message limit '{}'", other)),
+            }
+        }
+        Ok(limits)
+    }
+}
+
+impl MessageLimits {
+    /// Limits from `MCP_MESSAGE_LIMITS`, if set and valid
+    pub fn from_env() -> Option<Self> {
+        let spec = std::env::var("MCP_MESSAGE_LIMITS").ok()?;
+        match spec.parse() {
+            Ok(limits) => Some(limits),
+            Err(e) => {
+                tracing::warn!("Ignoring MCP_MESSAGE_LIMITS: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Checks a message's size and nesting before it is parsed
+    pub fn check(&self, message: &str) -> Result<(), LimitExceeded> {
+        if message.len() > self.max_bytes {
+            return Err(LimitExceeded::new(LimitKind::Bytes, self.max_bytes));
+        }
+        if exceeds_depth(message.as_bytes(), self.max_depth) {
+            return Err(LimitExceeded::new(LimitKind::Depth, self.max_depth));
+        }
+        Ok(())
+    }
+}
+
+/// Whether arrays and objects in `json` nest deeper than `max`, counting
+/// brackets outside strings
+fn exceeds_depth(json: &[u8], max: usize) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for &byte in json {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > max {
+                    return true;
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    false
+}
+
+/// The limit a message ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LimitKind {
+    Bytes,
+    Depth,
+    Batch,
+    Rate,
+}
+
+/// Why a message was refused; sent as the error data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitExceeded {
+    pub limit: LimitKind,
+    pub max: u64,
+    /// Whole seconds until the connection may send again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+}
+
+impl LimitExceeded {
+    pub fn new(limit: LimitKind, max: usize) -> Self {
+        Self { limit, max: max as u64, retry_after: None }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    filled_at: Instant,
+}
+
+/// Connections that have been idle this long have a full bucket and are
+/// forgotten once there are many
+const IDLE_SECS: f64 = 60.0;
+const MAX_TRACKED: usize = 4096;
+
+/// A token bucket of messages per connection, e.g. per stdio stream or per
+/// HTTP peer address
+pub struct ConnectionRates {
+    per_sec: Option<u32>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl ConnectionRates {
+    pub fn new(per_sec: Option<u32>) -> Self {
+        Self { per_sec, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Takes a token for each of `messages` messages from `connection`, all
+    /// or none, so a batch counts as many messages as it holds. Up to a
+    /// second's worth of messages may come at once.
+    pub fn allow(&self, connection: &str, messages: usize) -> Result<(), LimitExceeded> {
+        self.allow_at(connection, messages, Instant::now())
+    }
+
+    fn allow_at(&self, connection: &str, messages: usize, now: Instant) -> Result<(), LimitExceeded> {
+        let Some(per_sec) = self.per_sec else {
+            return Ok(());
+        };
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED && !buckets.contains_key(connection) {
+            buckets.retain(|_, bucket| now.saturating_duration_since(bucket.filled_at).as_secs_f64() < IDLE_SECS);
+        }
+        let bucket = buckets
+            .entry(connection.to_string())
+            .or_insert(Bucket { tokens: per_sec as f64, filled_at: now });
+        let refilled = now.saturating_duration_since(bucket.filled_at).as_secs_f64() * per_sec as f64;
+        bucket.tokens = (bucket.tokens + refilled).min(per_sec as f64);
+        bucket.filled_at = now;
+        if bucket.tokens < messages as f64 {
+            return Err(LimitExceeded { retry_after: Some(1), ..LimitExceeded::new(LimitKind::Rate, per_sec as usize) });
+        }
+        bucket.tokens -= messages as f64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_spec() {
+        let limits: MessageLimits = "bytes=1024, batch=10, rate=20".parse().unwrap();
+        assert_eq!(limits, MessageLimits { max_bytes: 1024, max_depth: 64, max_batch: 10, max_rate: Some(20) });
+        assert!("depth=0".parse::<MessageLimits>().is_err());
+        assert!("lines=5".parse::<MessageLimits>().is_err());
+    }
+
+    #[test]
+    fn test_check_size_and_depth() {
+        let limits = MessageLimits { max_bytes: 64, max_depth: 3, ..MessageLimits::default() };
+        assert!(limits.check(r#"{"params":{"a":[1,{"b":2}]}}"#).is_err());
+        assert!(limits.check(r#"{"params":{"a":[1,2]},"b":{}}"#).is_ok());
+        // Brackets inside strings do not count
+        assert!(limits.check(r#"{"a":"[[[[\"{{{{"}"#).is_ok());
+        let large = format!(r#"{{"a":"{}"}}"#, "x".repeat(64));
+        assert_eq!(limits.check(&large).unwrap_err().limit, LimitKind::Bytes);
+    }
+
+    #[test]
+    fn test_rate_is_per_connection() {
+        let rates = ConnectionRates::new(Some(2));
+        let start = Instant::now();
+        assert!(rates.allow_at("a", 1, start).is_ok());
+        assert!(rates.allow_at("a", 1, start).is_ok());
+        assert_eq!(rates.allow_at("a", 1, start).unwrap_err().retry_after, Some(1));
+        assert!(rates.allow_at("b", 1, start).is_ok());
+        assert!(rates.allow_at("a", 1, start + Duration::from_millis(500)).is_ok());
+        assert!(ConnectionRates::new(None).allow_at("a", 1, start).is_ok());
+    }
+
+    #[test]
+    fn test_batch_takes_a_token_per_message() {
+        let rates = ConnectionRates::new(Some(4));
+        let start = Instant::now();
+        assert!(rates.allow_at("a", 3, start).is_ok());
+        // Refused whole, without taking the token that is left
+        assert!(rates.allow_at("a", 2, start).is_err());
+        assert!(rates.allow_at("a", 1, start).is_ok());
+        assert!(rates.allow_at("b", 5, start).is_err());
+    }
+}
@@ -0,0 +1,351 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use super::notifications::{LogLevel, MessageVisitor};
+
+/// Log lines kept when `MCP_LOG_BUFFER_SIZE` is not set
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// Entries `logs/tail` returns when the caller gives no limit
+const DEFAULT_TAIL: usize = 100;
+
+/// One captured log line, tagged with the request it was logged in
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+}
+
+/// Parameters of `logs/tail`; every filter is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogQuery {
+    pub limit: Option<usize>,
+    pub session_id: Option<String>,
+    pub request_id: Option<String>,
+    pub tool: Option<String>,
+    /// Least severe level to include
+    pub level: Option<LogLevel>,
+}
+
+impl LogQuery {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        let tag_matches = |wanted: &Option<String>, tag: &Option<String>| {
+            wanted.as_ref().is_none_or(|wanted| tag.as_ref() == Some(wanted))
+        };
+        tag_matches(&self.session_id, &entry.session_id)
+            && tag_matches(&self.request_id, &entry.request_id)
+            && tag_matches(&self.tool, &entry.tool)
+            && self.level.is_none_or(|level| entry.level >= level)
+    }
+}
+
+/// Ring buffer of the most recent log lines
+pub struct LogBuffer {
+    entries: Mutex<VecDeque<LogEntry>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Sized by `MCP_LOG_BUFFER_SIZE`
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("MCP_LOG_BUFFER_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+        Self::new(capacity)
+    }
+
+    pub fn push(&self, entry: LogEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// The last matching entries, oldest first
+    pub fn tail(&self, query: &LogQuery) -> Vec<LogEntry> {
+        let limit = query.limit.unwrap_or(DEFAULT_TAIL);
+        let entries = self.entries.lock().unwrap();
+        let mut matching: Vec<LogEntry> = entries
+            .iter()
+            .rev()
+            .filter(|entry| query.matches(entry))
+            .take(limit)
+            .cloned()
+            .collect();
+        matching.reverse();
+        matching
+    }
+}
+
+/// The request tags found on a span or event
+#[derive(Debug, Clone, Default)]
+struct RequestTags {
+    session_id: Option<String>,
+    request_id: Option<String>,
+    tool: Option<String>,
+}
+
+impl RequestTags {
+    fn is_empty(&self) -> bool {
+        self.session_id.is_none() && self.request_id.is_none() && self.tool.is_none()
+    }
+
+    /// Inner spans and the event itself override outer spans
+    fn merge(&mut self, inner: &RequestTags) {
+        for (tag, value) in [
+            (&mut self.session_id, &inner.session_id),
+            (&mut self.request_id, &inner.request_id),
+            (&mut self.tool, &inner.tool),
+        ] {
+            if value.is_some() {
+                *tag = value.clone();
+            }
+        }
+    }
+}
+
+impl Visit for RequestTags {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let tag = match field.name() {
+            "session_id" => &mut self.session_id,
+            "request_id" => &mut self.request_id,
+            "tool" => &mut self.tool,
+            _ => return,
+        };
+        *tag = Some(value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+/// Tracing layer that copies every event into a [`LogBuffer`], tagged with
+/// the `session_id`, `request_id` and `tool` fields of the spans it happened in
+pub struct LogCaptureLayer {
+    buffer: Arc<LogBuffer>,
+}
+
+impl LogCaptureLayer {
+    pub fn new(buffer: Arc<LogBuffer>) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<S> Layer<S> for LogCaptureLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut tags = RequestTags::default();
+        attrs.record(&mut tags);
+        if let (false, Some(span)) = (tags.is_empty(), ctx.span(id)) {
+            span.extensions_mut().insert(tags);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let mut tags = extensions.remove::<RequestTags>().unwrap_or_default();
+        values.record(&mut tags);
+        if !tags.is_empty() {
+            extensions.insert(tags);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut tags = RequestTags::default();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(span_tags) = span.extensions().get::<RequestTags>() {
+                    tags.merge(span_tags);
+                }
+            }
+        }
+        let mut event_tags = RequestTags::default();
+        event.record(&mut event_tags);
+        tags.merge(&event_tags);
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        self.buffer.push(LogEntry {
+            timestamp: Utc::now(),
+            level: LogLevel::from(metadata.level()),
+            target: metadata.target().to_string(),
+            message: visitor.message,
+            session_id: tags.session_id,
+            request_id: tags.request_id,
+            tool: tags.tool,
+        });
+    }
+}
+
+/// The server's own log filter, which `POST /admin/logging` can change at
+/// runtime: `base` as given by `--log-level`, the config file or
+/// `RUST_LOG`, plus a level per target set since
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    base: Mutex<String>,
+    targets: Mutex<BTreeMap<String, &'static str>>,
+}
+
+impl LogFilter {
+    /// The filter layer, to be added to the registry first, and the filter
+    /// controlling it
+    pub fn new(base: &str) -> (reload::Layer<EnvFilter, Registry>, Self) {
+        let (layer, handle) = reload::Layer::new(EnvFilter::new(base));
+        (layer, Self { handle, base: Mutex::new(base.to_string()), targets: Mutex::new(BTreeMap::new()) })
+    }
+
+    /// Replaces the base filter, keeping the levels set per target; returns
+    /// the resulting filter. An invalid filter changes nothing.
+    pub fn set_base(&self, base: &str) -> anyhow::Result<String> {
+        EnvFilter::try_new(base)?;
+        let mut current = self.base.lock().unwrap();
+        let directives = self.apply(base, &self.targets.lock().unwrap())?;
+        *current = base.to_string();
+        Ok(directives)
+    }
+
+    /// Logs `target`, e.g. `mcp_server::plugins::home_assistant`, from
+    /// `level` up; returns the resulting filter
+    pub fn set(&self, target: &str, level: LogLevel) -> anyhow::Result<String> {
+        let target = target.trim();
+        if target.is_empty() || target.contains([',', '=', '[', ']', ' ']) {
+            anyhow::bail!("Invalid log target '{}'", target);
+        }
+        let level = match level {
+            LogLevel::Debug => "debug",
+            LogLevel::Info | LogLevel::Notice => "info",
+            LogLevel::Warning => "warn",
+            _ => "error",
+        };
+        let base = self.base.lock().unwrap();
+        let mut targets = self.targets.lock().unwrap();
+        targets.insert(target.to_string(), level);
+        self.apply(&base, &targets)
+    }
+
+    fn apply(&self, base: &str, targets: &BTreeMap<String, &'static str>) -> anyhow::Result<String> {
+        let directives: Vec<String> = std::iter::once(base.to_string())
+            .chain(targets.iter().map(|(target, level)| format!("{}={}", target, level)))
+            .filter(|directive| !directive.is_empty())
+            .collect();
+        let directives = directives.join(",");
+        self.handle.reload(EnvFilter::try_new(&directives)?)?;
+        Ok(directives)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::subscriber::with_default;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_events_are_tagged_with_their_spans() {
+        let buffer = Arc::new(LogBuffer::new(10));
+        let subscriber = tracing_subscriber::registry().with(LogCaptureLayer::new(buffer.clone()));
+
+        with_default(subscriber, || {
+            tracing::info!("outside any request");
+            let request = tracing::info_span!("request", request_id = "7", session_id = tracing::field::Empty);
+            request.record("session_id", "s1");
+            let _request = request.enter();
+            tracing::debug!("dispatching");
+            let tool = tracing::info_span!("tool", tool = "system_info");
+            let _tool = tool.enter();
+            tracing::warn!(attempt = 2, "plugin slow");
+        });
+
+        let entries = buffer.tail(&LogQuery::default());
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].session_id, None);
+        assert_eq!(entries[1].session_id.as_deref(), Some("s1"));
+        assert_eq!(entries[1].tool, None);
+        assert_eq!(entries[2].request_id.as_deref(), Some("7"));
+        assert_eq!(entries[2].tool.as_deref(), Some("system_info"));
+        assert_eq!(entries[2].message, "plugin slow attempt=2");
+    }
+
+    #[test]
+    fn test_buffer_keeps_latest_and_filters() {
+        let buffer = LogBuffer::new(3);
+        for i in 0..5 {
+            buffer.push(LogEntry {
+                timestamp: Utc::now(),
+                level: if i % 2 == 0 { LogLevel::Info } else { LogLevel::Error },
+                target: "test".to_string(),
+                message: format!("line {}", i),
+                session_id: Some(format!("s{}", i % 2)),
+                request_id: None,
+                tool: None,
+            });
+        }
+
+        let all = buffer.tail(&LogQuery::default());
+        assert_eq!(all.iter().map(|e| e.message.as_str()).collect::<Vec<_>>(), ["line 2", "line 3", "line 4"]);
+
+        let errors = buffer.tail(&LogQuery { level: Some(LogLevel::Error), ..Default::default() });
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "line 3");
+
+        let session = buffer.tail(&LogQuery { session_id: Some("s0".to_string()), limit: Some(1), ..Default::default() });
+        assert_eq!(session[0].message, "line 4");
+    }
+
+    #[test]
+    fn test_filter_levels_can_change_per_target() {
+        let (layer, filter) = LogFilter::new("info");
+        let subscriber = tracing_subscriber::registry().with(layer);
+        with_default(subscriber, || {
+            assert!(!tracing::enabled!(target: "mcp_server::plugins::http", tracing::Level::DEBUG));
+            let directives = filter.set("mcp_server::plugins::http", LogLevel::Debug).unwrap();
+            assert_eq!(directives, "info,mcp_server::plugins::http=debug");
+            assert!(tracing::enabled!(target: "mcp_server::plugins::http", tracing::Level::DEBUG));
+            assert!(!tracing::enabled!(target: "mcp_server::plugins::neo4j", tracing::Level::DEBUG));
+
+            // A new base keeps the per-target levels
+            assert_eq!(filter.set_base("warn").unwrap(), "warn,mcp_server::plugins::http=debug");
+            assert!(!tracing::enabled!(target: "mcp_server::plugins::neo4j", tracing::Level::INFO));
+            assert!(tracing::enabled!(target: "mcp_server::plugins::http", tracing::Level::DEBUG));
+        });
+        assert!(filter.set("a=b", LogLevel::Info).is_err());
+        assert!(filter.set_base("info,x=loud").is_err());
+    }
+}
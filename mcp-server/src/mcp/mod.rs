@@ -1,37 +1,289 @@
 use serde::Serialize;
 use serde_json::Value;
-use tracing::{debug, error, info};
+use futures_util::future::join_all;
+use tracing::{debug, error, info, warn, Instrument};
 use std::sync::Arc;
 use std::collections::HashMap;
 
-use crate::tools::{ToolRegistry, SystemInfoTool, HomeAssistantTool, HttpTool, Neo4jTool};
+use crate::tools::{ConcurrencyConfig, Tool, ToolRegistry, VirtualTool, ProxyTool, SystemInfoTool, HomeAssistantTool, HttpTool, Neo4jTool, EmbeddingsTool, MemoryTool, KnowledgeGraphTool, MetricsTool, OllamaTool, SpeechTool, AnalyzeImageTool, NetworkTool, NetDiagTool, JiraTool, SummarizeTool, FileSystemTool};
+use crate::plugins::Plugin;
 use crate::plugins::system_info::SystemInfoPlugin;
 use crate::plugins::home_assistant::HomeAssistantPlugin;
 use crate::plugins::http::HttpPlugin;
+use crate::plugins::notification::NotificationPlugin;
+use crate::plugins::embeddings::EmbeddingsPlugin;
+use crate::plugins::memory::MemoryPlugin;
+use crate::plugins::knowledge_graph::KnowledgeGraphPlugin;
+use crate::plugins::metrics::MetricsPlugin;
+use crate::plugins::ollama::OllamaPlugin;
+use crate::plugins::speech::SpeechPlugin;
+use crate::plugins::vision::VisionPlugin;
+use crate::plugins::network::NetworkPlugin;
+use crate::plugins::netdiag::NetDiagPlugin;
+use crate::plugins::jira::JiraPlugin;
+use crate::plugins::summarize::SummarizePlugin;
+use crate::plugins::filesystem::FileSystemPlugin;
+use crate::plugins::proxy::{ProxyConfig, ProxyPlugin};
 
 pub mod types;
 pub mod plugin_registry;
 pub mod plugin_params;
+pub mod session;
+pub mod notifications;
+pub mod sampling;
+pub mod client_requests;
+pub mod roots;
+pub mod logs;
+pub mod journal;
+pub mod faults;
+pub mod jobs;
+pub mod events;
+pub mod workflows;
+pub mod framing;
+pub mod budget;
+pub mod transform;
+pub mod version;
+pub mod resources;
+pub mod prompts;
+pub mod stats;
+pub mod rate_limit;
+pub mod limits;
+pub mod errors;
 pub use types::*;
-use plugin_registry::PluginRegistry;
+use plugin_registry::{PluginRegistry, Unavailable};
 use plugin_params::PluginCallParams;
+use session::{SessionError, SessionManager, SessionState, SessionSummary, UnknownCredential};
+use notifications::{LogLevel, NotificationDispatcher};
+use sampling::{Sampler, SamplingBroker};
+use client_requests::ClientRequests;
+use logs::{LogBuffer, LogFilter, LogQuery};
+use jobs::{Job, JobQuery, JobStore};
+use events::{EventBus, EventQuery};
+use workflows::{Advance, AdvanceParams, CreateParams, StatusParams, Workflow, WorkflowStatus};
+use crate::context::{ContextStore, DEFAULT_TENANT};
+use journal::Journal;
+use stats::ToolStats;
+use transform::{ResultPipeline, TransformConfig};
+use faults::{Fault, FaultConfig, FaultInjector};
+use budget::{BudgetConfig, BudgetExceeded, BudgetKind};
+use rate_limit::{RateLimitConfig, RateLimited, RateLimiter};
+use limits::{ConnectionRates, LimitExceeded, LimitKind, MessageLimits};
+use errors::{
+    ErrorKind, BUDGET_EXCEEDED, DEADLINE_EXCEEDED, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR,
+    PLUGIN_UNAVAILABLE, RATE_LIMITED, RESOURCE_NOT_FOUND, SESSION_NOT_FOUND, SESSION_NOT_INITIALIZED, SHUTTING_DOWN, TOOL_EXECUTION_FAILED,
+};
+use version::Feature;
 
-use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::Mutex;
 
+pub use version::SUPPORTED_PROTOCOL_VERSIONS;
+
 pub struct McpServer {
     tool_registry: Mutex<ToolRegistry>,
     plugin_registry: Mutex<PluginRegistry>,
-    initialized: AtomicBool,
+    sessions: SessionManager,
+    notifications: Arc<NotificationDispatcher>,
+    client_requests: Arc<ClientRequests>,
+    sampling: Arc<SamplingBroker>,
+    logs: Arc<LogBuffer>,
+    events: EventBus,
+    log_filter: Option<LogFilter>,
+    jobs: JobStore,
+    /// Woken when a job is submitted, for the job worker
+    jobs_queued: tokio::sync::Notify,
+    /// Where workflows are kept, once Neo4j (or another store) is connected
+    context_store: std::sync::RwLock<Option<Arc<dyn ContextStore>>>,
+    /// Held per workflow while it is loaded, advanced and saved again
+    workflow_locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    journal: Option<Journal>,
+    faults: Option<FaultInjector>,
+    stats: ToolStats,
+    transforms: Option<ResultPipeline>,
+    /// Token buckets that keep tools and plugins within upstream quotas
+    rate_limits: RateLimiter,
+    message_limits: MessageLimits,
+    connection_rates: ConnectionRates,
+    /// Set by `drain`; no new sessions are started after it
+    draining: std::sync::atomic::AtomicBool,
 }
 
+/// How long to wait for a client to answer `roots/list`
+const ROOTS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Jobs the job worker runs at once
+const JOB_CONCURRENCY: usize = 4;
+
+/// How long to wait for a client to answer a keepalive `ping`
+const PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How long to wait before initializing an unavailable plugin again, at
+/// first and at most
+const PLUGIN_RETRY_MIN: std::time::Duration = std::time::Duration::from_secs(5);
+const PLUGIN_RETRY_MAX: std::time::Duration = std::time::Duration::from_secs(300);
+
 impl McpServer {
     pub fn new() -> Self {
+        let notifications = Arc::new(NotificationDispatcher::new());
+        let client_requests = Arc::new(ClientRequests::new(notifications.clone()));
+        let events = EventBus::from_env();
+        let message_limits = MessageLimits::from_env().unwrap_or_default();
+        let connection_rates = ConnectionRates::new(message_limits.max_rate);
         Self {
-            tool_registry: Mutex::new(ToolRegistry::new()),
-            plugin_registry: Mutex::new(PluginRegistry::new()),
-            initialized: AtomicBool::new(false),
+            tool_registry: Mutex::new(ToolRegistry::from_env()),
+            plugin_registry: Mutex::new(PluginRegistry::new().with_events(events.clone())),
+            sessions: SessionManager::from_env(),
+            sampling: Arc::new(SamplingBroker::new(client_requests.clone())),
+            client_requests,
+            notifications,
+            logs: Arc::new(LogBuffer::from_env()),
+            events,
+            log_filter: None,
+            jobs: JobStore::from_env(),
+            jobs_queued: tokio::sync::Notify::new(),
+            context_store: std::sync::RwLock::new(None),
+            workflow_locks: Mutex::new(HashMap::new()),
+            journal: None,
+            faults: None,
+            stats: ToolStats::new(),
+            transforms: TransformConfig::from_env().map(|config| ResultPipeline::new(&config)),
+            rate_limits: RateLimiter::new(RateLimitConfig::from_env().unwrap_or_default()),
+            message_limits,
+            connection_rates,
+            draining: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    /// Limit every session to `requests_per_minute` requests, overriding
+    /// `MCP_SESSION_RATE_LIMIT`
+    pub fn with_session_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.sessions.set_rate_limit(Some(requests_per_minute));
+        self
+    }
+
+    /// Refuse a session's tool calls once it has used up `budget`,
+    /// overriding `MCP_SESSION_BUDGET`
+    pub fn with_session_budget(mut self, budget: BudgetConfig) -> Self {
+        info!("Session budget: {:?}", budget);
+        self.sessions.set_budget(Some(budget));
+        self
+    }
+
+    /// Limit how often tools and plugins may be called across all sessions,
+    /// overriding `MCP_TOOL_RATE_LIMITS`
+    pub fn with_tool_rate_limits(mut self, config: RateLimitConfig) -> Self {
+        info!("Tool rate limits: {:?}", config);
+        self.rate_limits = RateLimiter::new(config);
+        self
+    }
+
+    /// Limit how many calls of each tool run at once, overriding
+    /// `MCP_TOOL_CONCURRENCY`
+    pub fn with_tool_concurrency(mut self, config: ConcurrencyConfig) -> Self {
+        self.tool_registry.get_mut().set_concurrency(config);
+        self
+    }
+
+    /// Limit the size, nesting and rate of incoming messages, overriding
+    /// `MCP_MESSAGE_LIMITS`
+    pub fn with_message_limits(mut self, limits: MessageLimits) -> Self {
+        info!("Message limits: {:?}", limits);
+        self.connection_rates = ConnectionRates::new(limits.max_rate);
+        self.message_limits = limits;
+        self
+    }
+
+    pub fn message_limits(&self) -> &MessageLimits {
+        &self.message_limits
+    }
+
+    /// Give each client its own tenant, the one its bearer token belongs to
+    /// in `MCP_TENANT_TOKENS`, so context data is not shared between clients
+    pub fn with_tenant_per_client(mut self) -> Self {
+        self.sessions.set_tenant_per_client(true);
+        self
+    }
+
+    /// Map bearer tokens to tenants, overriding `MCP_TENANT_TOKENS`
+    pub fn with_tenant_tokens(mut self, tokens: HashMap<String, String>) -> Self {
+        self.sessions.set_tenant_tokens(tokens);
+        self
+    }
+
+    /// Let the admin API change the server's own log filter
+    pub fn with_log_filter(mut self, filter: LogFilter) -> Self {
+        self.log_filter = Some(filter);
+        self
+    }
+
+    /// Keep background jobs in `store`, overriding `MCP_JOBS_DB`
+    pub fn with_jobs(mut self, store: JobStore) -> Self {
+        self.jobs = store;
+        self
+    }
+
+    /// Record every message and its response to `journal`, for `replay`
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Truncate, summarize or redact tool output longer than the configured
+    /// size before it is returned, overriding `MCP_RESULT_TRANSFORMS`
+    pub fn with_result_transforms(mut self, config: TransformConfig) -> Self {
+        info!("Result transforms: {:?}", config);
+        self.transforms = Some(ResultPipeline::new(&config));
+        self
+    }
+
+    /// Fail or delay a share of plugin executions on purpose; responses say
+    /// which fault was injected
+    pub fn with_fault_injection(mut self, config: FaultConfig) -> Self {
+        warn!("Fault injection enabled: {:?}", config);
+        self.faults = Some(FaultInjector::new(config));
+        self
+    }
+
+    /// Keep workflows, the `memory` plugin's memories and the knowledge graph
+    /// in `store`, and aggregate metrics from it; without one the workflows/*
+    /// methods, `memory`, `aggregate_metrics` and the `kg_*` tools fail. Set it before `initialize`, which hands it to the
+    /// plugins.
+    pub fn set_context_store(&self, store: Arc<dyn ContextStore>) {
+        *self.context_store.write().unwrap() = Some(store);
+    }
+
+    /// Dispatcher for server-initiated notifications, shared with the
+    /// logging layer
+    pub fn notifications(&self) -> Arc<NotificationDispatcher> {
+        self.notifications.clone()
+    }
+
+    /// Recent log lines, filled by a `LogCaptureLayer` and read by `logs/tail`
+    pub fn logs(&self) -> Arc<LogBuffer> {
+        self.logs.clone()
+    }
+
+    /// The bus plugins publish events on, for subscribers outside the
+    /// plugins, e.g. tests
+    pub fn events(&self) -> EventBus {
+        self.events.clone()
+    }
+
+    /// Opens the notification stream of a session; `None` if there is no
+    /// such session
+    pub async fn subscribe_notifications(&self, session_id: &str) -> Option<tokio::sync::mpsc::UnboundedReceiver<String>> {
+        if !self.sessions.exists(session_id).await {
+            return None;
         }
+        Some(self.notifications.subscribe(session_id))
+    }
+
+    /// Sends the client a keepalive `ping` on the session's notification
+    /// stream and returns how long the answer took; `None` if the session
+    /// has no open stream
+    pub async fn ping_client(&self, session_id: &str) -> Option<anyhow::Result<std::time::Duration>> {
+        let started = std::time::Instant::now();
+        let outcome = self.client_requests.send(session_id, "ping", serde_json::json!({}), PING_TIMEOUT).await?;
+        Some(outcome.map(|_| started.elapsed()))
     }
 
     pub async fn initialize(&self) -> anyhow::Result<()> {
@@ -39,57 +291,311 @@ impl McpServer {
         let system_info = Arc::new(SystemInfoPlugin::new());
         let home_assistant = Arc::new(HomeAssistantPlugin::new());
         let http = Arc::new(HttpPlugin::new());
+        let notification = Arc::new(NotificationPlugin::new());
+        let embeddings = Arc::new(EmbeddingsPlugin::new());
+        let summarize = Arc::new(SummarizePlugin::new());
+        let filesystem = Arc::new(FileSystemPlugin::new().with_max_read_bytes(self.message_limits.max_bytes as u64));
+        let context_store = self.context_store.read().unwrap().clone();
+        let memory = Arc::new(MemoryPlugin::new(context_store.clone()));
+        let knowledge_graph = Arc::new(KnowledgeGraphPlugin::new(context_store.clone()));
+        let metrics = Arc::new(MetricsPlugin::new(context_store));
+        let ollama = Arc::new(OllamaPlugin::new());
+        let speech = Arc::new(SpeechPlugin::new());
+        // Takes camera snapshots through the Home Assistant plugin
+        let vision = Arc::new(VisionPlugin::new(home_assistant.clone()));
+        let network = Arc::new(NetworkPlugin::new());
+        let netdiag = Arc::new(NetDiagPlugin::new());
+        let jira = Arc::new(JiraPlugin::new());
         
-        // Initialize Neo4j plugin
-        let neo4j = Arc::new(
-            crate::plugins::neo4j::Neo4jPlugin::new(
-                &std::env::var("NEO4J_URI").unwrap_or_else(|_| "bolt://neo4j:7687".to_string()),
-                &std::env::var("NEO4J_USER").unwrap_or_else(|_| "neo4j".to_string()),
-                &std::env::var("NEO4J_PASSWORD").expect("NEO4J_PASSWORD must be set")
-            ).await.map_err(|e| anyhow::anyhow!("Failed to create Neo4j plugin: {}", e))?
-        );
+        // Connects to Neo4j when initialized; until that succeeds the plugin
+        // is unavailable and retried in the background
+        let neo4j = Arc::new(crate::plugins::neo4j::Neo4jPlugin::from_env());
         
-        // Register plugins
+        // Register plugins, then initialize them in dependency order. One
+        // failing to initialize does not keep the others from being used.
+        let plugins: Vec<Arc<dyn Plugin + Send + Sync>> = vec![
+            system_info.clone(),
+            home_assistant.clone(),
+            http.clone(),
+            neo4j.clone(),
+            notification,
+            embeddings.clone(),
+            summarize.clone(),
+            filesystem.clone(),
+            memory.clone(),
+            knowledge_graph.clone(),
+            metrics.clone(),
+            ollama.clone(),
+            speech.clone(),
+            vision.clone(),
+            network.clone(),
+            netdiag.clone(),
+            jira.clone(),
+        ];
         let mut registry = self.plugin_registry.lock().await;
-        registry.register_plugin(system_info.clone()).await?;
-        registry.register_plugin(home_assistant.clone()).await?;
-        registry.register_plugin(http.clone()).await?;
-        registry.register_plugin(neo4j.clone()).await?;
+        for plugin in plugins {
+            registry.add_plugin(plugin);
+        }
+        // Unless SQLite replaces it, the context store the memory, graph and
+        // metrics plugins use is the Neo4j database
+        if std::env::var("CONTEXT_SQLITE_DB").is_err() {
+            for plugin in ["memory", "knowledge_graph", "metrics"] {
+                registry.depends_on(plugin, "neo4j")?;
+            }
+        }
+        for error in registry.initialize_all().await {
+            warn!("{}; its tools are unavailable until it recovers", error);
+        }
         drop(registry);
         
         // Register tools for each plugin capability
-        let mut tool_registry = self.tool_registry.lock().await;
-        
-        let system_info_tool = SystemInfoTool::new(system_info);
-        tool_registry.register(Box::new(system_info_tool));
-        
-        let home_assistant_tool = HomeAssistantTool::new(home_assistant);
-        tool_registry.register(Box::new(home_assistant_tool));
-        
-        let http_tool = HttpTool::new(http);
-        tool_registry.register(Box::new(http_tool));
-        
-        let neo4j_tool = Neo4jTool::new(neo4j);
-        tool_registry.register(Box::new(neo4j_tool));
-        
-        drop(tool_registry);
+        self.register_tool(Box::new(SystemInfoTool::new(system_info))).await;
+        self.register_tool(Box::new(HomeAssistantTool::new(home_assistant))).await;
+        self.register_tool(Box::new(HttpTool::new(http))).await;
+        if !self.sessions.tenant_per_client() {
+            self.register_tool(Box::new(Neo4jTool::new(neo4j))).await;
+        }
+        self.register_tool(Box::new(EmbeddingsTool::new(embeddings))).await;
+        self.register_tool(Box::new(SummarizeTool::new(summarize))).await;
+        self.register_tool(Box::new(FileSystemTool::new(filesystem))).await;
+        self.register_tool(Box::new(MemoryTool::new(memory))).await;
+        self.register_tool(Box::new(KnowledgeGraphTool::ingest(knowledge_graph.clone()))).await;
+        self.register_tool(Box::new(KnowledgeGraphTool::query(knowledge_graph))).await;
+        self.register_tool(Box::new(MetricsTool::new(metrics))).await;
+        self.register_tool(Box::new(OllamaTool::new(ollama))).await;
+        self.register_tool(Box::new(SpeechTool::new(speech))).await;
+        self.register_tool(Box::new(AnalyzeImageTool::new(vision))).await;
+        self.register_tool(Box::new(NetworkTool::new(network))).await;
+        self.register_tool(Box::new(NetDiagTool::new(netdiag))).await;
+        self.register_tool(Box::new(JiraTool::new(jira))).await;
+
+        // External MCP servers are optional; one that fails to start is skipped
+        for (name, config) in ProxyConfig::from_env() {
+            if let Err(e) = self.add_proxy_server(&name, config).await {
+                warn!("Not proxying {}: {:#}", name, e);
+            }
+        }
         
-        self.initialized.store(true, Ordering::SeqCst);
         Ok(())
     }
 
-    async fn call_plugin_as_tool(&self, name: &str, args: HashMap<String, Value>) -> anyhow::Result<Vec<ContentBlock>> {
+    /// Starts an external stdio MCP server and registers its tools as
+    /// `name.<tool>`
+    pub async fn add_proxy_server(&self, name: &str, config: ProxyConfig) -> anyhow::Result<()> {
+        let plugin = Arc::new(ProxyPlugin::new(name, config));
+        self.register_plugin(plugin.clone()).await?;
+        for definition in plugin.tools().await {
+            self.register_tool_in(name, Box::new(ProxyTool::new(plugin.clone(), definition))).await;
+        }
+        Ok(())
+    }
+
+    /// Adds a plugin besides the built-in ones, e.g. a fake one in tests
+    pub async fn register_plugin(&self, plugin: Arc<dyn Plugin + Send + Sync>) -> anyhow::Result<()> {
+        self.plugin_registry.lock().await.register_plugin(plugin).await
+    }
+
+    /// Adds a plugin that may fail to initialize: it is then registered as
+    /// unavailable, its tools are refused and `spawn_plugin_retries` keeps
+    /// trying to initialize it. Returns whether it is available.
+    pub async fn register_plugin_lazily(&self, plugin: Arc<dyn Plugin + Send + Sync>) -> bool {
+        match self.plugin_registry.lock().await.register_plugin_lazily(plugin).await {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("{:#}; its tools are unavailable until it recovers", e);
+                false
+            }
+        }
+    }
+
+    /// Initializes the unavailable plugins again, without holding the
+    /// registry meanwhile, and returns the names of those that recovered.
+    /// Plugins waiting for a dependency are retried once it recovers.
+    pub async fn retry_unavailable_plugins(&self) -> Vec<String> {
+        let mut recovered = Vec::new();
+        loop {
+            // Only plugins whose dependencies are available are listed, so
+            // those of a plugin that recovers come in the next round
+            let plugins = self.plugin_registry.lock().await.unavailable_plugins();
+            let mut progressed = false;
+            for plugin in plugins {
+                let name = plugin.name().to_string();
+                let outcome = plugin.initialize().await.map_err(|e| e.to_string());
+                match &outcome {
+                    Ok(()) => {
+                        info!("Plugin {} is available again", name);
+                        recovered.push(name.clone());
+                        progressed = true;
+                    }
+                    Err(e) => debug!("Plugin {} is still unavailable: {}", name, e),
+                }
+                self.plugin_registry.lock().await.retried(&name, outcome);
+            }
+            if !progressed {
+                return recovered;
+            }
+        }
+    }
+
+    /// Declares that `plugin` needs `dependency`, so it is initialized after
+    /// it and disabled or unavailable while it is. Fails on a cycle.
+    pub async fn add_plugin_dependency(&self, plugin: &str, dependency: &str) -> anyhow::Result<()> {
+        self.plugin_registry.lock().await.depends_on(plugin, dependency)?;
+        self.sessions.clear_tool_caches().await;
+        self.notifications.tools_list_changed();
+        Ok(())
+    }
+
+    /// Retries unavailable plugins until the process ends, backing off from
+    /// `PLUGIN_RETRY_MIN` to `PLUGIN_RETRY_MAX` while they keep failing
+    pub fn spawn_plugin_retries(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let server = self.clone();
+        tokio::spawn(async move {
+            let mut delay = PLUGIN_RETRY_MIN;
+            loop {
+                tokio::time::sleep(delay).await;
+                if server.plugin_registry.lock().await.unavailable_plugins().is_empty() {
+                    delay = PLUGIN_RETRY_MIN;
+                    continue;
+                }
+                server.retry_unavailable_plugins().await;
+                let still_unavailable = !server.plugin_registry.lock().await.unavailable_plugins().is_empty();
+                delay = if still_unavailable { (delay * 2).min(PLUGIN_RETRY_MAX) } else { PLUGIN_RETRY_MIN };
+            }
+        })
+    }
+
+    /// Adds a tool besides the built-in ones. `tools/call` runs tools that do
+    /// not map to a plugin through the registry.
+    pub async fn register_tool(&self, tool: Box<dyn Tool>) {
+        self.tool_registry.lock().await.register(tool);
+    }
+
+    /// Adds a tool as `namespace.name`, so tools of different plugins can
+    /// share a name
+    pub async fn register_tool_in(&self, namespace: &str, tool: Box<dyn Tool>) {
+        self.tool_registry.lock().await.register_in(namespace, tool);
+    }
+
+    /// Lets clients call `target` as `alias` too; calls by a `deprecated`
+    /// alias log a warning
+    pub async fn add_tool_alias(&self, alias: &str, target: &str, deprecated: bool) {
+        self.tool_registry.lock().await.add_alias(alias, target, deprecated);
+    }
+
+    /// Adds a tool that calls another with preset arguments
+    pub async fn add_virtual_tool(&self, tool: VirtualTool) {
+        self.tool_registry.lock().await.add_virtual(tool);
+    }
+
+    /// Sampling for a plugin call made in `session_id`: through that client
+    /// if it declared the capability, otherwise only the fallback
+    async fn sampler(&self, session_id: Option<&str>) -> Sampler {
+        let client_session = match session_id {
+            Some(id) if self.sessions.supports_sampling(id).await => Some(id.to_string()),
+            _ => None,
+        };
+        Sampler::new(self.sampling.clone(), client_session)
+    }
+
+    /// Rolls for an injected fault before a plugin execution, waiting out any
+    /// delay here
+    async fn inject_fault(&self) -> Option<Fault> {
+        let fault = self.faults.as_ref()?.roll()?;
+        warn!("Injecting fault: {:?}", fault);
+        if let Fault::Delay(delay) = fault {
+            tokio::time::sleep(delay).await;
+        }
+        Some(fault)
+    }
+
+    /// Charges a tool or plugin call to the caller's session budget; calls
+    /// outside a session are not budgeted
+    async fn charge_budget(&self, session_id: Option<&str>) -> Result<Option<std::time::Duration>, BudgetExceeded> {
+        match session_id {
+            Some(id) => self.sessions.charge_tool_call(id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Runs a tool or plugin execution for `session_id`, counting the upstream
+    /// bytes it uses and stopping it when the session runs out of time.
+    /// `remaining` is what `charge_tool_call` returned.
+    async fn within_budget<F: std::future::Future>(
+        &self,
+        session_id: Option<&str>,
+        remaining: Option<std::time::Duration>,
+        execution: F,
+    ) -> Result<F::Output, BudgetExceeded> {
+        let Some(id) = session_id else {
+            return Ok(execution.await);
+        };
+        let (output, bytes) = match remaining {
+            Some(remaining) => {
+                let (output, bytes) = budget::metered(tokio::time::timeout(remaining, execution)).await;
+                let output = output.map_err(|_| {
+                    let limit = self.sessions.budget().and_then(|budget| budget.max_duration).unwrap_or_default();
+                    BudgetExceeded { budget: BudgetKind::Seconds, limit: limit.as_secs(), used: limit.as_secs() }
+                });
+                (output, bytes)
+            }
+            None => {
+                let (output, bytes) = budget::metered(execution).await;
+                (Ok(output), bytes)
+            }
+        };
+        self.sessions.add_upstream_bytes(id, bytes).await;
+        output
+    }
+
+    fn budget_exceeded_response(&self, id: Option<Value>, exceeded: BudgetExceeded) -> String {
+        self.error_response(id, budget_exceeded_error(exceeded))
+    }
+
+    /// The client's roots for a call to `plugin_name`. Only plugins in
+    /// `FILE_PLUGINS` need them, and asking the client is a round trip, so
+    /// other plugins get `None`. A client that declared roots but cannot be
+    /// asked gets an empty list, which allows nothing.
+    async fn roots_for(&self, session_id: Option<&str>, plugin_name: &str) -> Option<Vec<std::path::PathBuf>> {
+        let id = session_id.filter(|_| FILE_PLUGINS.contains(&plugin_name))?;
+        if !self.sessions.supports_roots(id).await {
+            return None;
+        }
+        if let Some(roots) = self.sessions.cached_roots(id).await {
+            return Some(roots);
+        }
+
+        let result = match self.client_requests.send(id, "roots/list", serde_json::json!({}), ROOTS_TIMEOUT).await {
+            Some(Ok(result)) => result,
+            Some(Err(e)) => {
+                warn!("Could not get roots from session {}: {}", id, e);
+                return Some(Vec::new());
+            }
+            None => {
+                warn!("Session {} declared roots but has no notification stream to ask on", id);
+                return Some(Vec::new());
+            }
+        };
+        let roots: Vec<std::path::PathBuf> = match serde_json::from_value::<roots::ListRootsResult>(result) {
+            Ok(result) => result.roots.iter().filter_map(roots::Root::to_path).collect(),
+            Err(e) => {
+                warn!("Invalid roots/list result from session {}: {}", id, e);
+                return Some(Vec::new());
+            }
+        };
+        debug!("Session {} roots: {:?}", id, roots);
+        self.sessions.cache_roots(id, roots.clone()).await;
+        Some(roots)
+    }
+
+    async fn call_plugin_as_tool(&self, session_id: Option<&str>, name: &str, args: HashMap<String, Value>) -> anyhow::Result<Vec<ContentBlock>> {
         debug!("Mapping tool call to plugin: {} with args: {:?}", name, args);
-        let registry = self.plugin_registry.lock().await;
-        let plugin_name = match name {
-            "system_info" => "system_info",
-            "homeassistant" => "home_assistant",
-            "http_request" => "http",
-            "neo4j_query" => "neo4j",
-            _ => return Err(anyhow::anyhow!("Tool not found: {}", name))
+        let Some(plugin_name) = plugin_for_tool(name) else {
+            return self.tool_registry.lock().await.call_tool(name, args).await;
         };
 
-        let plugin = registry.get_plugin(plugin_name).ok_or_else(|| {
+        // Plugins may wait on the client (sampling), so don't hold the registry lock
+        let plugin = self.plugin_registry.lock().await.get_plugin(plugin_name).ok_or_else(|| {
             anyhow::anyhow!("Plugin not found: {}", plugin_name)
         })?;
 
@@ -100,7 +606,15 @@ impl McpServer {
                     .and_then(|v| v.as_str())
                     .unwrap_or("get_system_info");
                 debug!("Mapping system_info action '{}' to capability", action);
-                ("get_system_info", args)
+                match action {
+                    "get_system_info" => ("get_system_info", args),
+                    "get_memory_usage" => ("get_memory_usage", args),
+                    "get_processes" => ("get_processes", args),
+                    "get_disks" => ("get_disks", args),
+                    "get_network" => ("get_network", args),
+                    "get_gpu_info" => ("get_gpu_info", args),
+                    _ => return Err(anyhow::anyhow!("Unknown system_info action: {}", action))
+                }
             },
             "homeassistant" => {
                 let action = args.get("action")
@@ -119,6 +633,90 @@ impl McpServer {
                 debug!("Mapping http_request tool to http plugin 'request' capability");
                 ("request", args)
             },
+            "embeddings" => {
+                let action = args.get("action")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("action is required for embeddings"))?;
+                match action {
+                    "store" => ("store", args),
+                    "search" => ("search", args),
+                    "list_collections" => ("list_collections", args),
+                    "delete_collection" => ("delete_collection", args),
+                    _ => return Err(anyhow::anyhow!("Unknown embeddings action: {}", action))
+                }
+            },
+            "ollama" => {
+                let action = args.get("action")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("running_models");
+                match action {
+                    "list_models" => ("list_models", args),
+                    "model_info" => ("model_info", args),
+                    "running_models" => ("running_models", args),
+                    _ => return Err(anyhow::anyhow!("Unknown ollama action: {}", action))
+                }
+            },
+            "speech" => {
+                let action = args.get("action")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("action is required for speech"))?;
+                match action {
+                    "transcribe" => ("transcribe", args),
+                    "speak" => ("speak", args),
+                    _ => return Err(anyhow::anyhow!("Unknown speech action: {}", action))
+                }
+            },
+            "analyze_image" => ("analyze", args),
+            "network" => {
+                let action = args.get("action")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("action is required for network"))?;
+                match action {
+                    "wol" => ("wol", args),
+                    "ping" => ("ping", args),
+                    "port_scan" => ("port_scan", args),
+                    "arp_scan" => ("arp_scan", args),
+                    _ => return Err(anyhow::anyhow!("Unknown network action: {}", action))
+                }
+            },
+            "netdiag" => {
+                let action = args.get("action")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("action is required for netdiag"))?;
+                match action {
+                    "dns_lookup" => ("dns_lookup", args),
+                    "whois" => ("whois", args),
+                    "tls_cert_info" => ("tls_cert_info", args),
+                    _ => return Err(anyhow::anyhow!("Unknown netdiag action: {}", action))
+                }
+            },
+            "jira" => {
+                let action = args.get("action")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("action is required for jira"))?;
+                match action {
+                    "search" => ("search", args),
+                    "get_issue" => ("get_issue", args),
+                    "create_issue" => ("create_issue", args),
+                    "transition" => ("transition", args),
+                    _ => return Err(anyhow::anyhow!("Unknown jira action: {}", action))
+                }
+            },
+            "summarize" => ("summarize", args),
+            "kg_ingest" => ("ingest", args),
+            "kg_query" => ("query", args),
+            "filesystem" => {
+                let action = args.get("action")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("action is required for filesystem"))?;
+                match action {
+                    "list_roots" => ("list_roots", args),
+                    "list_directory" => ("list_directory", args),
+                    "read_file" => ("read_file", args),
+                    "file_info" => ("file_info", args),
+                    _ => return Err(anyhow::anyhow!("Unknown filesystem action: {}", action))
+                }
+            },
             _ => return Err(anyhow::anyhow!("Unknown tool: {}", name))
         };
 
@@ -126,18 +724,16 @@ impl McpServer {
             correlation_id: "tool_call".to_string(),
             timestamp: chrono::Utc::now(),
             parameters: mapped_args.clone(),
+            sampling: Some(self.sampler(session_id).await),
+            roots: self.roots_for(session_id, plugin_name).await,
+            tenant: self.tenant_for(session_id).await,
         };
 
         debug!("Executing plugin {} with capability {} and args {:?}", plugin_name, capability, mapped_args);
         let result = plugin.execute(capability, context, mapped_args).await
             .map_err(|e| anyhow::anyhow!("Plugin execution failed: {}", e))?;
 
-        // Convert plugin result to ContentBlock with proper formatting
-        let result_text = serde_json::to_string_pretty(&result.data)
-            .map_err(|e| anyhow::anyhow!("Failed to serialize plugin result: {}", e))?;
-            
-        let content_block = ContentBlock::text(&result_text);
-        Ok(vec![content_block])
+        Ok(ContentBlock::from_plugin_data(result.data))
     }
 
     async fn handle_plugins_list(&self, request: &JsonRpcRequest) -> String {
@@ -152,112 +748,561 @@ impl McpServer {
         )
     }
 
-    async fn handle_plugins_call(&self, request: &JsonRpcRequest) -> String {
-        let params: Result<PluginCallParams, _> = serde_json::from_value(request.params.clone().unwrap_or(Value::Null));
+    async fn handle_plugins_health(&self, request: &JsonRpcRequest) -> String {
+        let registry = self.plugin_registry.lock().await;
+        let statuses = registry.health_check().await;
+        let healthy = statuses.values().all(|status| status.healthy);
+
+        self.create_success_response(
+            request.id.clone(),
+            serde_json::json!({
+                "healthy": healthy,
+                "plugins": statuses
+            }),
+        )
+    }
+
+    /// Every plugin in initialization order with whether it is enabled and
+    /// available, the plugins it depends on and, for an unavailable one, why
+    /// and how often initializing it failed
+    async fn handle_plugins_status(&self, request: &JsonRpcRequest) -> String {
+        let registry = self.plugin_registry.lock().await;
+        let plugins: Vec<Value> = registry
+            .initialization_order()
+            .iter()
+            .map(|name| {
+                let mut status = serde_json::json!({
+                    "name": name,
+                    "enabled": registry.is_enabled(name),
+                    "available": registry.is_available(name),
+                });
+                if !registry.dependencies_of(name).is_empty() {
+                    status["dependsOn"] = serde_json::json!(registry.dependencies_of(name));
+                }
+                if let Some(unavailable) = registry.unavailable(name) {
+                    status["error"] = Value::String(unavailable.error.clone());
+                    status["attempts"] = unavailable.attempts.into();
+                }
+                status
+            })
+            .collect();
+
+        self.create_success_response(request.id.clone(), serde_json::json!({ "plugins": plugins }))
+    }
+
+    /// Sets the level of the log messages a session gets; the server's own
+    /// per-target levels are only set on the admin API
+    async fn handle_set_log_level(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
+        let level = request
+            .params
+            .as_ref()
+            .and_then(|params| params.get("level"))
+            .and_then(|level| serde_json::from_value::<LogLevel>(level.clone()).ok());
+        // The server's own filter affects every client, so only operators
+        // change it
+        if request.params.as_ref().is_some_and(|params| params.get("target").is_some()) {
+            return self.create_error_response(
+                request.id.clone(),
+                INVALID_PARAMS,
+                "Invalid params",
+                Some(Value::String("per-target levels are set with POST /admin/logging".to_string())),
+            );
+        }
+        let (Some(session_id), Some(level)) = (session_id, level) else {
+            return self.create_error_response(
+                request.id.clone(),
+                INVALID_PARAMS,
+                "Invalid params",
+                Some(Value::String("logging/setLevel needs a session and a valid level".to_string())),
+            );
+        };
+
+        self.notifications.set_log_level(session_id, level);
+        self.create_success_response(request.id.clone(), serde_json::json!({}))
+    }
+
+    async fn handle_plugins_call(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
+        let params: Result<PluginCallParams, _> = request.params_as(&Value::Null);
         
         let params = match params {
             Ok(p) => p,
             Err(e) => {
                 return self.create_error_response(
                     request.id.clone(),
-                    -32602,
+                    INVALID_PARAMS,
                     "Invalid params",
                     Some(Value::String(e.to_string())),
                 )
             }
         };
 
-        let registry = self.plugin_registry.lock().await;
-        let plugin = match registry.get_plugin(&params.name) {
+        if self.crosses_tenants(&params.name, &params.action) {
+            return self.create_error_response(request.id.clone(), METHOD_NOT_FOUND, CROSS_TENANT_REFUSAL, None);
+        }
+        let plugin = {
+            let registry = self.plugin_registry.lock().await;
+            if let Some(unavailable) = registry.unavailable(&params.name).filter(|_| registry.is_enabled(&params.name)) {
+                return self.error_response(request.id.clone(), plugin_unavailable_error(&params.name, &unavailable));
+            }
+            registry.get_plugin(&params.name).filter(|_| registry.is_enabled(&params.name))
+        };
+        let plugin = match plugin {
             Some(p) => p,
             None => {
                 return self.create_error_response(
                     request.id.clone(),
-                    -32601,
+                    METHOD_NOT_FOUND,
                     "Plugin not found",
                     None,
                 )
             }
         };
 
+        let deprecation = plugin
+            .capabilities()
+            .into_iter()
+            .find(|capability| capability.name == params.action)
+            .and_then(|capability| capability.deprecated);
+
+        if let Err(limited) = self.rate_limits.acquire(&[&params.name]) {
+            return self.error_response(request.id.clone(), rate_limited_error(limited));
+        }
+        let remaining = match self.charge_budget(session_id).await {
+            Ok(remaining) => remaining,
+            Err(exceeded) => return self.budget_exceeded_response(request.id.clone(), exceeded),
+        };
+        let fault = self.inject_fault().await;
+        if fault == Some(Fault::Failure) {
+            return self.create_error_response(
+                request.id.clone(),
+                INTERNAL_ERROR,
+                "Plugin execution failed",
+                Some(serde_json::json!({ "faultInjection": Fault::Failure.describe() })),
+            );
+        }
+
         let context = crate::plugins::Context {
             correlation_id: uuid::Uuid::new_v4().to_string(),
             timestamp: chrono::Utc::now(),
             parameters: params.args.clone(),
+            sampling: Some(self.sampler(session_id).await),
+            roots: self.roots_for(session_id, &params.name).await,
+            tenant: self.tenant_for(session_id).await,
         };
 
-        match plugin.execute(&params.action, context, params.args).await {
-            Ok(result) => self.create_success_response(request.id.clone(), serde_json::json!(result)),
-            Err(e) => self.create_error_response(
-                request.id.clone(),
-                -32603,
-                "Plugin execution failed",
-                Some(Value::String(e.to_string())),
-            ),
+        let span = tracing::info_span!("tool", tool = %params.name, action = %params.action);
+        let result = match self
+            .within_budget(session_id, remaining, plugin.execute(&params.action, context, params.args).instrument(span))
+            .await
+        {
+            Ok(result) => result,
+            Err(exceeded) => return self.budget_exceeded_response(request.id.clone(), exceeded),
+        };
+        match result {
+            Ok(result) => {
+                let mut result = serde_json::json!(result);
+                if let Some(fault) = fault {
+                    fault.tag_result(&mut result);
+                }
+                if let Some(deprecation) = &deprecation {
+                    tag_deprecation(&mut result, &format!("{}.{}", params.name, params.action), deprecation);
+                }
+                self.create_success_response(request.id.clone(), result)
+            }
+            Err(e) => match refusal(e.as_ref()) {
+                Some(refusal) => self.error_response(request.id.clone(), refusal),
+                None => self.create_error_response(
+                    request.id.clone(),
+                    INTERNAL_ERROR,
+                    "Plugin execution failed",
+                    Some(Value::String(e.to_string())),
+                ),
+            },
         }
     }
 
+    /// Handles a message that does not belong to a session, as sent by the
+    /// REST endpoints. These need no handshake. Initialize requests still
+    /// start a session, and its id is returned as `sessionId`.
     pub async fn handle_message(&self, message: &str) -> anyhow::Result<String> {
+        self.handle_session_message(None, message).await
+    }
+
+    /// Handles a message that arrived on `connection`, such as the stdio
+    /// stream or an HTTP peer, within the connection's message rate.
+    /// Requests over the rate get an error; other messages over it are
+    /// dropped.
+    pub async fn handle_connection_message(&self, connection: &str, session_id: Option<&str>, message: &str) -> anyhow::Result<String> {
+        let batch_len = self.batch_len(message);
+        if let Err(exceeded) = self.connection_rates.allow(connection, batch_len.unwrap_or(1)) {
+            warn!("Connection {} sent messages too fast", connection);
+            if batch_len.is_some() {
+                return Ok(self.error_response(None, limit_exceeded_error(exceeded)));
+            }
+            let id = RawMessage::parse(message).ok().filter(RawMessage::is_request).and_then(|message| parse_raw(message.id).ok().flatten());
+            return Ok(match id {
+                Some(id) => self.error_response(Some(id), limit_exceeded_error(exceeded)),
+                None => String::new(),
+            });
+        }
+        self.handle_session_message(session_id, message).await
+    }
+
+    /// How many messages a batch holds, or `None` if `message` is not a
+    /// batch. Messages over the size or depth limits are not counted, as they
+    /// are refused unread.
+    fn batch_len(&self, message: &str) -> Option<usize> {
+        let message = message.trim();
+        if !message.starts_with('[') || self.message_limits.check(message).is_err() {
+            return None;
+        }
+        serde_json::from_str::<Vec<&serde_json::value::RawValue>>(message).ok().map(|messages| messages.len())
+    }
+
+    /// The error response to a message refused for breaking a limit before
+    /// it could be read, such as one too large to buffer
+    pub fn limit_exceeded_response(&self, exceeded: LimitExceeded) -> String {
+        self.error_response(None, limit_exceeded_error(exceeded))
+    }
+
+    /// Handles a message sent within the session `session_id`, as set up by
+    /// an earlier initialize request. Until the client sends
+    /// `notifications/initialized`, only `ping` and `shutdown` are accepted.
+    /// Notifications never get a response, so an empty string is returned.
+    pub async fn handle_session_message(&self, session_id: Option<&str>, message: &str) -> anyhow::Result<String> {
+        let response = self.process_message(session_id, message).await?;
+        if let Some(journal) = &self.journal {
+            journal.record(session_id, message, &response);
+        }
+        Ok(response)
+    }
+
+    async fn process_message(&self, session_id: Option<&str>, message: &str) -> anyhow::Result<String> {
         let message = message.trim();
         if message.is_empty() {
             return Ok(String::new());
         }
 
+        if let Err(exceeded) = self.message_limits.check(message) {
+            return Ok(self.limit_exceeded_response(exceeded));
+        }
+
         debug!("Received message: {}", message);
 
-        let request: JsonRpcRequest = match serde_json::from_str(message) {
-            Ok(req) => req,
+        if message.starts_with('[') {
+            return Ok(self.process_batch(session_id, message).await);
+        }
+        self.process_single(session_id, message).await
+    }
+
+    /// Answers a batch: an array of messages handled concurrently, whose
+    /// responses come back as an array in the same order. Notifications and
+    /// replies get no entry, and a batch of only those gets no response.
+    async fn process_batch(&self, session_id: Option<&str>, message: &str) -> String {
+        let messages = match serde_json::from_str::<Vec<&serde_json::value::RawValue>>(message) {
+            Ok(messages) => messages,
             Err(e) => {
-                error!("Failed to parse JSON-RPC request: {}", e);
-                return Ok(self.create_error_response(None, -32700, "Parse error", None));
+                error!("Failed to parse JSON-RPC batch: {}", e);
+                return self.create_error_response(None, PARSE_ERROR, "Parse error", None);
             }
         };
-
-        // Only allow initialize method if not initialized
-        if !self.initialized.load(Ordering::SeqCst) && request.method != "initialize" {
-            return Ok(self.create_error_response(
-                request.id.clone(),
-                -32002,
-                "Server not initialized",
+        if messages.is_empty() {
+            return self.create_error_response(
                 None,
-            ));
+                INVALID_REQUEST,
+                "Invalid Request",
+                Some(Value::String("empty batch".to_string())),
+            );
+        }
+        if messages.len() > self.message_limits.max_batch {
+            return self.limit_exceeded_response(LimitExceeded::new(LimitKind::Batch, self.message_limits.max_batch));
         }
 
-        let response = match request.method.as_str() {
-            "initialize" => self.handle_initialize(&request).await,
-            "tools/list" => self.handle_tools_list(&request).await,
-            "tools/call" => self.handle_tool_call(&request).await,
-            "plugins/list" => self.handle_plugins_list(&request).await,
-            "plugins/call" => self.handle_plugins_call(&request).await,
-            _ => self.create_error_response(
-                request.id.clone(),
-                -32601,
-                "Method not found",
-                None,
-            ),
-        };
-
-        Ok(response)
+        let responses = join_all(
+            messages
+                .iter()
+                .map(|message| async move { self.process_single(session_id, message.get()).await.unwrap_or_default() }),
+        )
+        .await;
+        let responses: Vec<_> = responses.into_iter().filter(|response| !response.is_empty()).collect();
+        if responses.is_empty() {
+            return String::new();
+        }
+        format!("[{}]", responses.join(","))
     }
 
-    async fn handle_initialize(&self, request: &JsonRpcRequest) -> String {
-        info!("Handling initialize request");
-
-        // Check if already initialized
-        if self.initialized.load(Ordering::SeqCst) {
-            return self.create_error_response(
+    async fn process_single(&self, session_id: Option<&str>, message: &str) -> anyhow::Result<String> {
+        // Only the id and params are parsed into values; the rest of the
+        // message is read in place
+        let parsed = match RawMessage::parse(message) {
+            Ok(parsed) => parsed,
+            // JSON, but not a message object with the right field types
+            Err(e) if serde_json::from_str::<serde::de::IgnoredAny>(message).is_ok() => {
+                debug!("Invalid JSON-RPC message: {}", e);
+                return Ok(self.create_error_response(None, INVALID_REQUEST, "Invalid Request", Some(Value::String(e.to_string()))));
+            }
+            Err(e) => {
+                error!("Failed to parse JSON-RPC request: {}", e);
+                return Ok(self.create_error_response(None, PARSE_ERROR, "Parse error", None));
+            }
+        };
+        if let Err(reason) = parsed.validate() {
+            // Answering a reply could start an endless exchange
+            if parsed.is_reply() {
+                debug!("Ignoring invalid response: {}", reason);
+                return Ok(String::new());
+            }
+            debug!("Invalid JSON-RPC message: {}", reason);
+            return Ok(self.create_error_response(
+                parsed.valid_id(),
+                INVALID_REQUEST,
+                "Invalid Request",
+                Some(Value::String(reason.to_string())),
+            ));
+        }
+
+        if parsed.is_reply() {
+            // Responses to requests the server sent, such as roots/list
+            if let Err(e) = self.resolve_reply(parsed) {
+                error!("Failed to parse JSON-RPC response: {}", e);
+            }
+            return Ok(String::new());
+        }
+        let request = match parsed.into_request() {
+            Ok(request) => request,
+            Err(e) => {
+                error!("Failed to parse JSON-RPC request: {}", e);
+                return Ok(self.create_error_response(None, PARSE_ERROR, "Parse error", None));
+            }
+        };
+
+        if request.id.is_none() {
+            self.handle_notification(session_id, &request).await;
+            return Ok(String::new());
+        }
+
+        let span = tracing::info_span!(
+            "request",
+            request_id = %request_id_tag(request.id.as_ref()),
+            method = %request.method,
+            session_id = tracing::field::Empty,
+        );
+        if let Some(id) = session_id {
+            span.record("session_id", id);
+        }
+        Ok(self.handle_request(session_id, request).instrument(span).await)
+    }
+
+    /// Hands a reply to the request of the server's it answers
+    fn resolve_reply(&self, reply: RawMessage) -> serde_json::Result<()> {
+        let id = parse_raw(reply.id)?.unwrap_or(Value::Null);
+        let outcome = match parse_raw(reply.error)? {
+            Some(error) => Err(error),
+            None => Ok(parse_raw(reply.result)?.unwrap_or(Value::Null)),
+        };
+        if !self.client_requests.resolve(&id, outcome) {
+            debug!("Ignoring response to unknown request {}", id);
+        }
+        Ok(())
+    }
+
+    /// Answers a request that has an id. Runs inside the request's span, so
+    /// everything logged here is tagged with it.
+    async fn handle_request(&self, session_id: Option<&str>, request: JsonRpcRequest) -> String {
+        if let (Some(id), false) = (session_id, request.method == "initialize") {
+            match self.sessions.touch(id, session::credential().as_deref()).await {
+                Ok(SessionState::Ready) => {}
+                Ok(SessionState::Initializing) if matches!(request.method.as_str(), "ping" | "shutdown") => {}
+                Ok(SessionState::Initializing) => {
+                    return self.create_error_response(
+                        request.id.clone(),
+                        SESSION_NOT_INITIALIZED,
+                        "Session not initialized",
+                        Some(Value::String("send notifications/initialized first".to_string())),
+                    );
+                }
+                Err(SessionError::NotFound) => {
+                    return self.create_error_response(
+                        request.id.clone(),
+                        SESSION_NOT_FOUND,
+                        "Session not found",
+                        Some(Value::String(id.to_string())),
+                    );
+                }
+                Err(SessionError::WrongCredential) => {
+                    warn!("Refused a request for session {} with a token it was not started with", id);
+                    return self.create_error_response(
+                        request.id.clone(),
+                        INVALID_REQUEST,
+                        "Credential does not match the session",
+                        None,
+                    );
+                }
+                Err(SessionError::RateLimited { retry_after_secs }) => {
+                    return self.create_error_response(
+                        request.id.clone(),
+                        RATE_LIMITED,
+                        "Rate limit exceeded",
+                        Some(serde_json::json!({ "retryAfter": retry_after_secs })),
+                    );
+                }
+            }
+        }
+
+        // Sessions only get the methods their protocol version has
+        if let (Some(id), Some(feature)) = (session_id, Feature::for_method(&request.method)) {
+            let version = self.sessions.protocol_version(id).await.unwrap_or_default();
+            if !feature.enabled_in(&version) {
+                return self.create_error_response(
+                    request.id.clone(),
+                    METHOD_NOT_FOUND,
+                    "Method not found",
+                    Some(Value::String(format!(
+                        "{} needs protocol version {} or later",
+                        request.method,
+                        feature.since()
+                    ))),
+                );
+            }
+        }
+
+        let response = match request.method.as_str() {
+            "initialize" => self.handle_initialize(session_id, &request).await,
+            "ping" => self.create_success_response(request.id.clone(), serde_json::json!({})),
+            "shutdown" => self.handle_shutdown(session_id, &request).await,
+            "tools/list" => self.handle_tools_list(session_id, &request).await,
+            "tools/call" => self.handle_tool_call(session_id, &request).await,
+            "resources/list" => self.create_success_response(
+                request.id.clone(),
+                serde_json::json!({ "resources": resources::list() }),
+            ),
+            "resources/read" => self.handle_resources_read(session_id, &request).await,
+            "prompts/list" => self.create_success_response(
+                request.id.clone(),
+                serde_json::json!({ "prompts": prompts::list() }),
+            ),
+            "prompts/get" => self.handle_prompts_get(&request).await,
+            "roots/list" => self.handle_roots_list(session_id, &request).await,
+            "logs/tail" => self.handle_logs_tail(session_id, &request),
+            "events/tail" => self.handle_events_tail(&request),
+            "jobs/submit" => self.handle_jobs_submit(session_id, &request),
+            "jobs/get" => self.handle_jobs_get(&request),
+            "jobs/list" => self.handle_jobs_list(&request),
+            "workflows/create" => {
+                let result = self.handle_workflows_create(session_id, &request).await;
+                self.respond(request.id.clone(), result)
+            }
+            "workflows/advance" => {
+                let result = self.handle_workflows_advance(session_id, &request).await;
+                self.respond(request.id.clone(), result)
+            }
+            "workflows/status" => {
+                let result = self.handle_workflows_status(session_id, &request).await;
+                self.respond(request.id.clone(), result)
+            }
+            "stats/tools" => self.create_success_response(
+                request.id.clone(),
+                serde_json::json!({ "tools": self.stats.snapshot() }),
+            ),
+            "sessions/list" => self.handle_sessions_list(session_id, &request).await,
+            "sessions/end" => self.handle_sessions_end(session_id, &request).await,
+            "plugins/list" => self.handle_plugins_list(&request).await,
+            "plugins/call" => self.handle_plugins_call(session_id, &request).await,
+            "plugins/health" => self.handle_plugins_health(&request).await,
+            "plugins/status" => self.handle_plugins_status(&request).await,
+            // Reinitializing every plugin affects all sessions, so only
+            // operators do it, on the admin API
+            "plugins/reload" => self.create_error_response(
+                request.id.clone(),
+                INVALID_REQUEST,
+                "Plugins are reloaded on the admin API",
+                Some(Value::String("use POST /admin/reload".to_string())),
+            ),
+            "logging/setLevel" => self.handle_set_log_level(session_id, &request).await,
+            _ => self.create_error_response(
                 request.id.clone(),
-                -32002,
-                "Server already initialized",
+                METHOD_NOT_FOUND,
+                "Method not found",
                 None,
-            );
+            ),
+        };
+
+        response
+    }
+
+    async fn handle_notification(&self, session_id: Option<&str>, notification: &JsonRpcRequest) {
+        // Only the client that started a session may change it
+        if let Some(id) = session_id {
+            if !self.sessions.owned_by(id, session::credential().as_deref()).await {
+                debug!("Ignoring notification {} for a session the caller does not own", notification.method);
+                return;
+            }
+        }
+        match (notification.method.as_str(), session_id) {
+            ("notifications/initialized", Some(id)) => {
+                if self.sessions.mark_ready(id).await {
+                    info!("Session {} is ready", id);
+                }
+            }
+            ("notifications/roots/list_changed", Some(id)) => {
+                debug!("Session {} changed its roots", id);
+                self.sessions.clear_roots(id).await;
+            }
+            (method, _) => debug!("Ignoring notification {}", method),
+        }
+    }
+
+    /// Ends the caller's session after answering
+    async fn handle_shutdown(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
+        if let Some(id) = session_id {
+            self.end_session(id).await;
+        }
+        self.create_success_response(request.id.clone(), serde_json::json!({}))
+    }
+
+    async fn handle_initialize(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
+        info!("Handling initialize request");
+
+        // Each session is initialized once; clients start new ones by
+        // initializing without a session id
+        if self.is_draining() {
+            return self.create_error_response(request.id.clone(), SHUTTING_DOWN, "Server is shutting down", None);
+        }
+        if let Some(id) = session_id {
+            if self.sessions.exists(id).await {
+                return self.create_error_response(
+                    request.id.clone(),
+                    INVALID_REQUEST,
+                    "Session already initialized",
+                    None,
+                );
+            }
         }
         
+        let params: InitializeParams = match request.params_as(&Value::Null) {
+            Ok(params) => params,
+            Err(e) => {
+                return self.create_error_response(
+                    request.id.clone(),
+                    INVALID_PARAMS,
+                    "Invalid params",
+                    Some(Value::String(e.to_string())),
+                )
+            }
+        };
+
+        let protocol_version = version::negotiate(&params.protocol_version);
+        debug!("Client requested protocol {}, using {}", params.protocol_version, protocol_version);
+
+        // Only advertise what the negotiated version gets
+        let gated = |feature: Feature| feature.enabled_in(protocol_version).then(|| serde_json::json!({}));
         let init_result = InitializeResult {
-            protocol_version: "2024-11-05".to_string(),
+            protocol_version: protocol_version.to_string(),
             capabilities: Capabilities {
-                tools: Some(ToolCapabilities { list_changed: Some(false) }),
-                ..Default::default()
+                tools: Some(ToolCapabilities { list_changed: Some(true) }),
+                logging: Some(serde_json::json!({})),
+                resources: gated(Feature::Resources),
+                prompts: gated(Feature::Prompts),
             },
             server_info: ServerInfo {
                 name: "ollama-n8n-mcp-server".to_string(),
@@ -265,25 +1310,589 @@ impl McpServer {
             },
         };
 
-        // Set initialized flag atomically
-        self.initialized.store(true, Ordering::SeqCst);
+        let tenant = match self.sessions.tenant_for(session::credential().as_deref()) {
+            Ok(tenant) => tenant,
+            Err(UnknownCredential) => {
+                warn!("Refused initialize with a token that belongs to no tenant");
+                return self.create_error_response(request.id.clone(), INVALID_REQUEST, "Unknown credential", None);
+            }
+        };
+        let new_session = self.sessions
+            .create(Some(params.client_info), protocol_version, tenant, session::credential().as_deref())
+            .await;
+        self.sessions
+            .set_supports_sampling(&new_session, params.capabilities.sampling.is_some())
+            .await;
+        self.sessions
+            .set_supports_roots(&new_session, params.capabilities.roots.is_some())
+            .await;
+        info!("Started session {}", new_session);
 
-        self.create_success_response(request.id.clone(), init_result)
+        let mut result = serde_json::to_value(init_result).unwrap();
+        result["sessionId"] = Value::String(new_session);
+        self.create_success_response(request.id.clone(), result)
     }
 
-    async fn handle_tools_list(&self, request: &JsonRpcRequest) -> String {
+    async fn handle_tools_list(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
         debug!("Handling tools/list request");
+        let tools = self.list_tools(session_id).await;
+        self.create_success_response(request.id.clone(), ToolsListResult { tools })
+    }
+
+    /// The tools a session sees, for any transport; cached per session
+    pub async fn list_tools(&self, session_id: Option<&str>) -> Vec<ToolDefinition> {
+        if let Some(tools) = match session_id {
+            Some(id) => self.sessions.cached_tools(id).await,
+            None => None,
+        } {
+            return tools;
+        }
         
         let tool_registry = self.tool_registry.lock().await;
-        let tools = tool_registry.list_tools().await;
+        let mut tools = tool_registry.list_tools().await;
         drop(tool_registry);
-        
-        let result = ToolsListResult { tools };
-        
-        self.create_success_response(request.id.clone(), result)
+        let plugins = self.plugin_registry.lock().await;
+        tools.retain(|tool| tool_plugin(&tool.name).is_none_or(|plugin| plugins.is_enabled(plugin)));
+        drop(plugins);
+
+        if let Some(id) = session_id {
+            self.sessions.cache_tools(id, tools.clone()).await;
+        }
+        tools
+    }
+
+    async fn handle_resources_read(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
+        let uri = request
+            .params
+            .as_ref()
+            .and_then(|params| params.get("uri"))
+            .and_then(|uri| uri.as_str())
+            .unwrap_or_default();
+
+        let contents = match uri {
+            resources::PLUGINS_URI => {
+                let registry = self.plugin_registry.lock().await;
+                let mut names = registry.list_plugins();
+                names.sort();
+                let plugins: Vec<Value> = names
+                    .iter()
+                    .filter_map(|name| registry.get_plugin(name))
+                    .map(|plugin| serde_json::json!({
+                        "name": plugin.name(),
+                        "version": plugin.version(),
+                        "capabilities": plugin.capabilities(),
+                    }))
+                    .collect();
+                Ok(serde_json::json!({ "plugins": plugins }))
+            }
+            resources::SYSTEM_INFO_URI => {
+                let args = HashMap::from([("action".to_string(), Value::String("get_system_info".to_string()))]);
+                self.call_plugin_as_tool(session_id, "system_info", args).await.map(|blocks| {
+                    blocks
+                        .into_iter()
+                        .find_map(|block| match block {
+                            ContentBlock::Json { json } => Some(json),
+                            _ => None,
+                        })
+                        .unwrap_or(Value::Null)
+                })
+            }
+            _ => {
+                return self.create_error_response(
+                    request.id.clone(),
+                    RESOURCE_NOT_FOUND,
+                    "Resource not found",
+                    Some(Value::String(uri.to_string())),
+                )
+            }
+        };
+
+        match contents {
+            Ok(contents) => {
+                let contents = resources::ResourceContents {
+                    uri: uri.to_string(),
+                    mime_type: "application/json",
+                    text: serde_json::to_string_pretty(&contents).unwrap(),
+                };
+                self.create_success_response(request.id.clone(), serde_json::json!({ "contents": [contents] }))
+            }
+            Err(e) => self.create_error_response(
+                request.id.clone(),
+                INTERNAL_ERROR,
+                "Failed to read resource",
+                Some(Value::String(e.to_string())),
+            ),
+        }
+    }
+
+    async fn handle_prompts_get(&self, request: &JsonRpcRequest) -> String {
+        #[derive(serde::Deserialize)]
+        struct GetPromptParams {
+            name: String,
+            #[serde(default)]
+            arguments: HashMap<String, String>,
+        }
+
+        let params: GetPromptParams = match request.params_as(&Value::Null) {
+            Ok(params) => params,
+            Err(e) => {
+                return self.create_error_response(
+                    request.id.clone(),
+                    INVALID_PARAMS,
+                    "Invalid params",
+                    Some(Value::String(e.to_string())),
+                )
+            }
+        };
+
+        if !prompts::list().iter().any(|prompt| prompt.name == params.name) {
+            return self.error_response(request.id.clone(), JsonRpcError::not_found(format!("Unknown prompt: {}", params.name)));
+        }
+        match prompts::get(&params.name, &params.arguments) {
+            Ok(prompt) => self.create_success_response(request.id.clone(), prompt),
+            Err(e) => self.create_error_response(request.id.clone(), INVALID_PARAMS, "Invalid params", Some(Value::String(e))),
+        }
+    }
+
+    /// The directories file tools may use in this session: the server's roots,
+    /// narrowed to the client's own when it declared any
+    async fn handle_roots_list(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
+        let client_roots = self.roots_for(session_id, "filesystem").await;
+        let roots: Vec<roots::Root> = roots::effective(&roots::configured(), client_roots.as_deref())
+            .iter()
+            .filter_map(|path| roots::Root::from_path(path))
+            .collect();
+        self.create_success_response(request.id.clone(), roots::ListRootsResult { roots })
+    }
+
+    /// Log lines of the caller's own session. Other lines can hold other
+    /// clients' requests, so only operators read them, on `GET /admin/logs`.
+    fn handle_logs_tail(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
+        let mut query: LogQuery = match request.params_as(&serde_json::json!({})) {
+            Ok(query) => query,
+            Err(e) => {
+                return self.create_error_response(
+                    request.id.clone(),
+                    INVALID_PARAMS,
+                    "Invalid params",
+                    Some(Value::String(e.to_string())),
+                )
+            }
+        };
+        match (session_id, query.session_id.as_deref()) {
+            (Some(own), None) => query.session_id = Some(own.to_string()),
+            (Some(own), Some(named)) if named == own => {}
+            _ => {
+                return self.create_error_response(
+                    request.id.clone(),
+                    INVALID_PARAMS,
+                    "Invalid params",
+                    Some(Value::String(
+                        "logs/tail only reads the caller's own session; use GET /admin/logs".to_string(),
+                    )),
+                );
+            }
+        }
+        self.create_success_response(request.id.clone(), serde_json::json!({ "entries": self.logs.tail(&query) }))
     }
 
-    async fn handle_tool_call(&self, request: &JsonRpcRequest) -> String {
+    fn handle_events_tail(&self, request: &JsonRpcRequest) -> String {
+        let query: EventQuery = match request.params_as(&serde_json::json!({})) {
+            Ok(query) => query,
+            Err(e) => {
+                return self.create_error_response(
+                    request.id.clone(),
+                    INVALID_PARAMS,
+                    "Invalid params",
+                    Some(Value::String(e.to_string())),
+                )
+            }
+        };
+        self.create_success_response(request.id.clone(), serde_json::json!({ "events": self.events.tail(&query) }))
+    }
+
+    /// Queues a tool call, taking the same params as `tools/call`; the job
+    /// worker runs it and `jobs/get` returns its result
+    fn handle_jobs_submit(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
+        let params: ToolCallParams = match request.params_as(&Value::Null) {
+            Ok(params) => params,
+            Err(e) => return self.create_error_response(request.id.clone(), INVALID_PARAMS, "Invalid params", Some(Value::String(e.to_string()))),
+        };
+        match self.jobs.submit(&params.name, params.arguments.into_iter().collect(), session_id) {
+            Ok(job) => {
+                info!("Queued job {} for tool {}", job.id, job.tool);
+                self.jobs_queued.notify_one();
+                self.create_success_response(request.id.clone(), serde_json::json!({ "job": job }))
+            }
+            Err(e) => self.create_error_response(request.id.clone(), INTERNAL_ERROR, "Internal error", Some(Value::String(e.to_string()))),
+        }
+    }
+
+    fn handle_jobs_get(&self, request: &JsonRpcRequest) -> String {
+        let Some(id) = request.params.as_ref().and_then(|params| params.get("jobId")).and_then(Value::as_str) else {
+            return self.create_error_response(request.id.clone(), INVALID_PARAMS, "Invalid params", Some(Value::String("jobs/get needs a jobId".to_string())));
+        };
+        match self.jobs.get(id) {
+            Ok(Some(job)) => self.create_success_response(request.id.clone(), serde_json::json!({ "job": job })),
+            Ok(None) => self.error_response(request.id.clone(), JsonRpcError::not_found(format!("Unknown job {}", id))),
+            Err(e) => self.create_error_response(request.id.clone(), INTERNAL_ERROR, "Internal error", Some(Value::String(e.to_string()))),
+        }
+    }
+
+    fn handle_jobs_list(&self, request: &JsonRpcRequest) -> String {
+        let query: JobQuery = match request.params_as(&serde_json::json!({})) {
+            Ok(query) => query,
+            Err(e) => return self.create_error_response(request.id.clone(), INVALID_PARAMS, "Invalid params", Some(Value::String(e.to_string()))),
+        };
+        match self.jobs.list(&query) {
+            Ok(jobs) => self.create_success_response(request.id.clone(), serde_json::json!({ "jobs": jobs })),
+            Err(e) => self.create_error_response(request.id.clone(), INTERNAL_ERROR, "Internal error", Some(Value::String(e.to_string()))),
+        }
+    }
+
+    /// Runs queued jobs, including those left queued by an earlier run of
+    /// the server, a few at a time until the process ends
+    pub fn spawn_job_worker(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let server = self.clone();
+        let slots = Arc::new(tokio::sync::Semaphore::new(JOB_CONCURRENCY));
+        tokio::spawn(async move {
+            loop {
+                let slot = slots.clone().acquire_owned().await.expect("job slots are never closed");
+                let job = match server.jobs.claim_next() {
+                    Ok(Some(job)) => job,
+                    Ok(None) => {
+                        drop(slot);
+                        server.jobs_queued.notified().await;
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Failed to take the next job: {:#}", e);
+                        drop(slot);
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                let server = server.clone();
+                tokio::spawn(async move {
+                    server.run_job(job).await;
+                    drop(slot);
+                });
+            }
+        })
+    }
+
+    async fn run_job(&self, job: Job) {
+        // Sessions do not outlive the server, so a job queued before a
+        // restart runs without one
+        let session_id = match &job.session_id {
+            Some(id) if self.sessions.exists(id).await => Some(id.as_str()),
+            _ => None,
+        };
+        let params = ToolCallParams { name: job.tool.clone(), arguments: job.arguments.into_iter().collect(), meta: Some(ToolCallMeta { priority: Priority::Background, ..Default::default() }) };
+        let outcome = self
+            .call_tool(session_id, params)
+            .await
+            .map_err(|e| serde_json::to_value(e).unwrap_or(Value::Null));
+        info!("Job {} ({}) {}", job.id, job.tool, if outcome.is_ok() { "completed" } else { "failed" });
+        if let Err(e) = self.jobs.finish(&job.id, outcome) {
+            error!("Failed to record the outcome of job {}: {:#}", job.id, e);
+        }
+    }
+
+    fn respond(&self, id: Option<Value>, result: Result<Value, JsonRpcError>) -> String {
+        match result {
+            Ok(result) => self.create_success_response(id, result),
+            Err(error) => self.error_response(id, error),
+        }
+    }
+
+    /// Whether a plugin capability reads across tenants, which per-client
+    /// tenants forbid: raw Cypher sees every tenant's nodes
+    fn crosses_tenants(&self, plugin: &str, capability: &str) -> bool {
+        self.sessions.tenant_per_client() && plugin == "neo4j" && capability == "query"
+    }
+
+    /// The tenant of the calling session, if there is one
+    async fn tenant_for(&self, session_id: Option<&str>) -> Option<String> {
+        self.sessions.tenant(session_id?).await
+    }
+
+    /// The context store, scoped to the calling session's tenant
+    async fn workflow_store(&self, session_id: Option<&str>) -> Result<Arc<dyn ContextStore>, JsonRpcError> {
+        let store = self.context_store.read().unwrap().clone().ok_or_else(|| {
+            JsonRpcError::new(INTERNAL_ERROR, "Internal error", Some(Value::String("Workflows need a context store, such as Neo4j".to_string())))
+        })?;
+        let tenant = self.tenant_for(session_id).await;
+        Ok(store.with_tenant(tenant.as_deref().unwrap_or(DEFAULT_TENANT)))
+    }
+
+    async fn save_workflow(&self, store: &dyn ContextStore, workflow: &Workflow) -> Result<(), JsonRpcError> {
+        let state = serde_json::to_value(workflow).unwrap();
+        store
+            .save_workflow(&workflow.id, state)
+            .await
+            .map_err(|e| JsonRpcError::new(INTERNAL_ERROR, "Internal error", Some(Value::String(e.to_string()))))
+    }
+
+    async fn load_workflow(&self, store: &dyn ContextStore, id: &str) -> Result<Workflow, JsonRpcError> {
+        let state = store
+            .load_workflow(id)
+            .await
+            .map_err(|e| JsonRpcError::new(INTERNAL_ERROR, "Internal error", Some(Value::String(e.to_string()))))?
+            .ok_or_else(|| JsonRpcError::not_found(format!("Unknown workflow {}", id)))?;
+        serde_json::from_value(state).map_err(|e| JsonRpcError::new(INTERNAL_ERROR, "Internal error", Some(Value::String(e.to_string()))))
+    }
+
+    /// Starts a workflow from a goal and its plan, at the first step
+    async fn handle_workflows_create(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> Result<Value, JsonRpcError> {
+        let params: CreateParams = request.params_as(&Value::Null)
+            .map_err(|e| JsonRpcError::new(INVALID_PARAMS, "Invalid params", Some(Value::String(e.to_string()))))?;
+        let workflow = Workflow::new(params).map_err(|e| JsonRpcError::new(INVALID_PARAMS, "Invalid params", Some(Value::String(e))))?;
+        self.save_workflow(self.workflow_store(session_id).await?.as_ref(), &workflow).await?;
+        info!("Created workflow {} with {} steps: {}", workflow.id, workflow.steps.len(), workflow.goal);
+        Ok(serde_json::json!({ "workflow": workflow }))
+    }
+
+    /// Moves a workflow on by one step, by running the step's tool or
+    /// recording the outcome the agent reports, or pauses or resumes it
+    async fn handle_workflows_advance(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> Result<Value, JsonRpcError> {
+        let params: AdvanceParams = request.params_as(&Value::Null)
+            .map_err(|e| JsonRpcError::new(INVALID_PARAMS, "Invalid params", Some(Value::String(e.to_string()))))?;
+        let store = self.workflow_store(session_id).await?;
+        let lock = self.workflow_locks.lock().await.entry(params.workflow_id.clone()).or_default().clone();
+        let _guard = lock.lock().await;
+        let mut workflow = self.load_workflow(store.as_ref(), &params.workflow_id).await?;
+        let outcome = match params.action {
+            Advance::Pause => workflow.pause(),
+            Advance::Resume => workflow.resume(),
+            Advance::Complete => workflow.finish_step(Ok(params.result.unwrap_or(Value::Null))),
+            Advance::Fail => workflow.finish_step(Err(params.error.unwrap_or(Value::Null))),
+            Advance::Run => {
+                let step = workflow.current().map_err(|e| JsonRpcError::new(INVALID_PARAMS, "Invalid params", Some(Value::String(e))))?;
+                let Some(tool) = step.tool.clone() else {
+                    return Err(JsonRpcError::new(
+                        INVALID_PARAMS,
+                        "Invalid params",
+                        Some(Value::String(format!(
+                            "Step {} has no tool; report its outcome with action complete or fail",
+                            workflow.current_step + 1
+                        ))),
+                    ));
+                };
+                let params = ToolCallParams { name: tool, arguments: step.arguments.clone().into_iter().collect(), meta: None };
+                let outcome = self
+                    .call_tool(session_id, params)
+                    .await
+                    .map_err(|e| serde_json::to_value(e).unwrap_or(Value::Null));
+                workflow.finish_step(outcome)
+            }
+        };
+        outcome.map_err(|e| JsonRpcError::new(INVALID_PARAMS, "Invalid params", Some(Value::String(e))))?;
+        self.save_workflow(store.as_ref(), &workflow).await?;
+        if matches!(workflow.status, WorkflowStatus::Completed | WorkflowStatus::Failed) {
+            self.workflow_locks.lock().await.remove(&workflow.id);
+        }
+        info!("Workflow {} is {} after {} of {} steps", workflow.id, workflow.status.as_str(), workflow.current_step, workflow.steps.len());
+        self.events.publish(
+            "workflows",
+            &format!("workflow.{}", workflow.status.as_str()),
+            serde_json::json!({ "workflowId": workflow.id, "step": workflow.current_step }),
+        );
+        Ok(serde_json::json!({ "workflow": workflow }))
+    }
+
+    async fn handle_workflows_status(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> Result<Value, JsonRpcError> {
+        let params: StatusParams = request.params_as(&serde_json::json!({}))
+            .map_err(|e| JsonRpcError::new(INVALID_PARAMS, "Invalid params", Some(Value::String(e.to_string()))))?;
+        let store = self.workflow_store(session_id).await?;
+        if let Some(id) = &params.workflow_id {
+            let workflow = self.load_workflow(store.as_ref(), id).await?;
+            return Ok(serde_json::json!({ "workflow": workflow }));
+        }
+        let workflows: Vec<Workflow> = store
+            .list_workflows()
+            .await
+            .map_err(|e| JsonRpcError::new(INTERNAL_ERROR, "Internal error", Some(Value::String(e.to_string()))))?
+            .into_iter()
+            .filter_map(|state| serde_json::from_value(state).ok())
+            .filter(|workflow: &Workflow| params.status.is_none_or(|status| workflow.status == status))
+            .collect();
+        Ok(serde_json::json!({ "workflows": workflows }))
+    }
+
+    /// The caller's own session; all sessions are only listed on the admin
+    /// API
+    async fn handle_sessions_list(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
+        let sessions: Vec<SessionSummary> = match session_id {
+            Some(id) => self.sessions.summary(id).await.into_iter().collect(),
+            None => Vec::new(),
+        };
+        self.create_success_response(
+            request.id.clone(),
+            serde_json::json!({
+                "sessions": sessions
+            }),
+        )
+    }
+
+    /// Ends the caller's own session; other sessions are only ended on the
+    /// admin API
+    async fn handle_sessions_end(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
+        let named = request
+            .params
+            .as_ref()
+            .and_then(|params| params.get("sessionId"))
+            .and_then(|id| id.as_str());
+        let target = match (session_id, named) {
+            (Some(own), None) => own,
+            (Some(own), Some(named)) if named == own => own,
+            (None, None) => {
+                return self.create_error_response(
+                    request.id.clone(),
+                    INVALID_PARAMS,
+                    "Invalid params",
+                    Some(Value::String("there is no session to end".to_string())),
+                );
+            }
+            (_, Some(_)) => {
+                return self.create_error_response(
+                    request.id.clone(),
+                    INVALID_PARAMS,
+                    "Invalid params",
+                    Some(Value::String(
+                        "only the caller's own session can be ended; use DELETE /admin/sessions/{id}".to_string(),
+                    )),
+                );
+            }
+        };
+
+        if self.end_session(target).await {
+            self.create_success_response(request.id.clone(), serde_json::json!({ "ended": target }))
+        } else {
+            self.create_error_response(
+                request.id.clone(),
+                SESSION_NOT_FOUND,
+                "Session not found",
+                Some(Value::String(target.to_string())),
+            )
+        }
+    }
+
+    /// Whether the session exists and was started with the bearer token
+    /// `credential`
+    pub async fn session_owned_by(&self, session_id: &str, credential: Option<&str>) -> bool {
+        self.sessions.owned_by(session_id, credential).await
+    }
+
+    /// Ends a session when its transport connection closes; returns false
+    /// if there was no such session
+    pub async fn end_session(&self, session_id: &str) -> bool {
+        let ended = self.sessions.end(session_id).await;
+        self.notifications.unsubscribe(session_id);
+        if ended {
+            info!("Ended session {}", session_id);
+        }
+        ended
+    }
+
+    /// Summaries of all sessions, oldest first
+    pub async fn list_sessions(&self) -> Vec<SessionSummary> {
+        self.sessions.list().await
+    }
+
+    /// Ends every session, which also closes their notification streams
+    pub async fn end_all_sessions(&self) {
+        for session in self.sessions.list().await {
+            self.end_session(&session.id).await;
+        }
+    }
+
+    /// Reinitializes all plugins, so changed configuration is picked up, and
+    /// tells clients to fetch the tool list again. Returns the plugins'
+    /// names.
+    pub async fn reload_plugins(&self) -> anyhow::Result<Vec<String>> {
+        let mut registry = self.plugin_registry.lock().await;
+        registry.reload().await?;
+        let plugins = registry.list_plugins();
+        drop(registry);
+
+        info!("Reloaded {} plugins", plugins.len());
+        self.sessions.clear_tool_caches().await;
+        self.notifications.tools_list_changed();
+        Ok(plugins)
+    }
+
+    /// Every plugin's name and whether it is switched on, sorted by name
+    pub async fn plugin_states(&self) -> Vec<(String, bool)> {
+        let registry = self.plugin_registry.lock().await;
+        let mut names = registry.list_plugins();
+        names.sort();
+        names.into_iter().map(|name| {
+            let enabled = registry.is_enabled(&name);
+            (name, enabled)
+        }).collect()
+    }
+
+    /// Switches a plugin on or off. The tools of a disabled plugin are not
+    /// listed, and calls to them and to the plugin are refused as unknown.
+    /// Returns false if there is no such plugin.
+    pub async fn set_plugin_enabled(&self, name: &str, enabled: bool) -> bool {
+        if !self.plugin_registry.lock().await.set_enabled(name, enabled) {
+            return false;
+        }
+        info!("Plugin {} {}", name, if enabled { "enabled" } else { "disabled" });
+        self.sessions.clear_tool_caches().await;
+        self.notifications.tools_list_changed();
+        true
+    }
+
+    /// Replaces the tool and plugin rate limits while running
+    pub fn set_tool_rate_limits(&self, config: RateLimitConfig) {
+        info!("Tool rate limits: {:?}", config);
+        self.rate_limits.reconfigure(config);
+    }
+
+    /// Logs `target`, e.g. `mcp_server::plugins::http`, from `level` up in
+    /// the server's own log filter; returns the resulting filter
+    pub fn set_log_target_level(&self, target: &str, level: LogLevel) -> anyhow::Result<String> {
+        let Some(filter) = &self.log_filter else {
+            anyhow::bail!("The server's log filter cannot be changed");
+        };
+        let directives = filter.set(target, level)?;
+        info!("Log filter is now {}", directives);
+        Ok(directives)
+    }
+
+    /// Replaces the base of the server's own log filter, keeping levels set
+    /// per target on the admin API; returns the resulting filter
+    pub fn set_log_level(&self, base: &str) -> anyhow::Result<String> {
+        let Some(filter) = &self.log_filter else {
+            anyhow::bail!("The server's log filter cannot be changed");
+        };
+        filter.set_base(base)
+    }
+
+    /// Drops the sessions' cached tool lists and roots and the cached
+    /// secret values, so all are fetched again
+    pub async fn flush_caches(&self) {
+        self.sessions.clear_caches().await;
+        crate::secrets::Secrets::global().clear();
+        info!("Flushed caches");
+    }
+
+    /// Stops starting new sessions, ahead of shutting down; initialize
+    /// requests are refused from now on
+    pub fn drain(&self) {
+        if !self.draining.swap(true, std::sync::atomic::Ordering::SeqCst) {
+            info!("Draining: no new sessions are started");
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    async fn handle_tool_call(&self, session_id: Option<&str>, request: &JsonRpcRequest) -> String {
         debug!("Received tool call request: {:?}", request);
         
         let params = match request.params.as_ref() {
@@ -293,7 +1902,7 @@ impl McpServer {
                     error!("Invalid tool call parameters: {}", e);
                     return self.create_error_response(
                         request.id.clone(),
-                        -32602,
+                        INVALID_PARAMS,
                         "Invalid params",
                         None,
                     );
@@ -303,39 +1912,144 @@ impl McpServer {
                 error!("Missing parameters in tool call request");
                 return self.create_error_response(
                     request.id.clone(),
-                    -32602,
+                    INVALID_PARAMS,
                     "Missing params",
                     None,
                 );
             }
         };
 
+        match self.call_tool(session_id, params).await {
+            Ok(result) => self.create_success_response(request.id.clone(), result),
+            Err(error) if error.code == TOOL_EXECUTION_FAILED => {
+                self.create_success_response(request.id.clone(), tool_error_result(error))
+            }
+            Err(error) => self.error_response(request.id.clone(), error),
+        }
+    }
+
+    /// Runs a tool call for any transport, with the session's budget, fault
+    /// injection, concurrency limits, usage stats and result transforms
+    /// applied. Returns the `tools/call` result.
+    pub async fn call_tool(&self, session_id: Option<&str>, mut params: ToolCallParams) -> Result<Value, JsonRpcError> {
+        let timeout = params.timeout();
+        let deadline = timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+        let (expanded, scheduler, output_schema, deprecation) = {
+            let registry = self.tool_registry.lock().await;
+            let expanded = registry.expand(&params.name, std::mem::take(&mut params.arguments));
+            let (mut output_schema, mut deprecation) = (None, None);
+            if let Ok((name, _)) = &expanded {
+                if plugin_for_tool(name).is_none() && !registry.contains(name) {
+                    return Err(JsonRpcError::not_found(format!("Unknown tool: {}", name)));
+                }
+                output_schema = registry.output_schema(name);
+                deprecation = registry.deprecation(name);
+            }
+            (expanded, registry.scheduler(), output_schema, deprecation)
+        };
+        (params.name, params.arguments) = match expanded {
+            Ok(expanded) => expanded,
+            Err(e) => return Err(JsonRpcError::new(INVALID_PARAMS, "Invalid params", Some(Value::String(e.to_string())))),
+        };
+        if params.name == "neo4j_query" && self.crosses_tenants("neo4j", "query") {
+            return Err(JsonRpcError::not_found(CROSS_TENANT_REFUSAL));
+        }
+        if let Some(plugin) = tool_plugin(&params.name) {
+            let registry = self.plugin_registry.lock().await;
+            if !registry.is_enabled(plugin) {
+                return Err(JsonRpcError::not_found(format!("Tool {} is disabled", params.name)));
+            }
+            if let Some(unavailable) = registry.unavailable(plugin) {
+                return Err(plugin_unavailable_error(plugin, &unavailable));
+            }
+        }
         debug!("Handling tool call for {} with arguments {:?}", params.name, params.arguments);
-        match self.call_plugin_as_tool(&params.name, params.arguments).await {
-            Ok(result) => {
+        let plugin = plugin_for_tool(&params.name).unwrap_or_default();
+        self.rate_limits.acquire(&[&params.name, plugin]).map_err(rate_limited_error)?;
+        let remaining = self.charge_budget(session_id).await.map_err(budget_exceeded_error)?;
+        let fault = self.inject_fault().await;
+        if fault == Some(Fault::Failure) {
+            self.stats.record(&params.name, std::time::Duration::ZERO, false);
+            if let Some(id) = session_id {
+                self.sessions.record_tool_call(id, &params.name, false).await;
+            }
+            return Err(JsonRpcError::new(
+                TOOL_EXECUTION_FAILED,
+                "Tool execution failed",
+                Some(serde_json::json!({ "faultInjection": Fault::Failure.describe() })),
+            ));
+        }
+        // Waiting for a slot is not counted against the session's time
+        // budget, but is against the caller's deadline
+        let Some(_permit) = until(deadline, scheduler.acquire(&params.name, params.priority())).await else {
+            self.stats.record(&params.name, std::time::Duration::ZERO, false);
+            if let Some(id) = session_id {
+                self.sessions.record_tool_call(id, &params.name, false).await;
+            }
+            return Err(deadline_exceeded_error(&params.name, timeout.unwrap_or_default()));
+        };
+        let started = std::time::Instant::now();
+        // Dropped at the deadline, which stops the plugin's work
+        let execution = until(
+            deadline,
+            self.call_plugin_as_tool(session_id, &params.name, params.arguments)
+                .instrument(tracing::info_span!("tool", tool = %params.name)),
+        );
+        let result = match self.within_budget(session_id, remaining, execution).await {
+            Ok(Some(result)) => result,
+            Ok(None) => {
+                self.stats.record(&params.name, started.elapsed(), false);
+                if let Some(id) = session_id {
+                    self.sessions.record_tool_call(id, &params.name, false).await;
+                }
+                return Err(deadline_exceeded_error(&params.name, timeout.unwrap_or_default()));
+            }
+            Err(exceeded) => {
+                self.stats.record(&params.name, started.elapsed(), false);
+                if let Some(id) = session_id {
+                    self.sessions.record_tool_call(id, &params.name, false).await;
+                }
+                return Err(budget_exceeded_error(exceeded));
+            }
+        };
+        self.stats.record(&params.name, started.elapsed(), result.is_ok());
+        if let Some(id) = session_id {
+            self.sessions.record_tool_call(id, &params.name, result.is_ok()).await;
+        }
+        match result {
+            Ok(mut result) => {
                 debug!("Tool call succeeded with result length {}", result.len());
-                let response = ToolCallResult { content: result };
-                self.create_success_response(request.id.clone(), response)
+                // Checked before transforms, which may drop structured blocks
+                if cfg!(debug_assertions) {
+                    if let Some(schema) = &output_schema {
+                        check_output(&params.name, schema, &result);
+                    }
+                }
+                let transformed = match &self.transforms {
+                    Some(pipeline) => pipeline.apply(&mut result, &self.sampler(session_id).await).await,
+                    None => None,
+                };
+                let mut response = serde_json::to_value(ToolCallResult::new(result)).unwrap();
+                if let Some(fault) = fault {
+                    fault.tag_result(&mut response);
+                }
+                if let Some(transformed) = transformed {
+                    response["_meta"]["resultTransforms"] = transformed;
+                }
+                if let Some(deprecation) = &deprecation {
+                    tag_deprecation(&mut response, &params.name, deprecation);
+                }
+                Ok(response)
             }
             Err(e) => {
                 error!("Tool call failed: {}", e);
-                self.create_error_response(
-                    request.id.clone(),
-                    -1,
-                    "Tool execution failed",
-                    Some(Value::String(e.to_string())),
-                )
+                Err(tool_failure(&e))
             }
         }
     }
 
     fn create_success_response<T: Serialize>(&self, id: Option<Value>, result: T) -> String {
-        let response = JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            id,
-            result: Some(serde_json::to_value(result).unwrap()),
-            error: None,
-        };
+        let response = Success { jsonrpc: "2.0", id: &id, result: &result };
         serde_json::to_string(&response).unwrap()
     }
 
@@ -346,11 +2060,10 @@ impl McpServer {
         message: &str,
         data: Option<Value>,
     ) -> String {
-        let error = JsonRpcError {
-            code,
-            message: message.to_string(),
-            data,
-        };
+        self.error_response(id, JsonRpcError::new(code, message, data))
+    }
+
+    fn error_response(&self, id: Option<Value>, error: JsonRpcError) -> String {
         let response = JsonRpcResponse {
             jsonrpc: "2.0".to_string(),
             id,
@@ -359,4 +2072,165 @@ impl McpServer {
         };
         serde_json::to_string(&response).unwrap()
     }
-}
\ No newline at end of file
+}
+
+/// A successful `JsonRpcResponse` borrowing its parts, so results are
+/// written out directly instead of being copied into a `Value` first
+#[derive(Serialize)]
+struct Success<'a, T> {
+    jsonrpc: &'static str,
+    id: &'a Option<Value>,
+    result: &'a T,
+}
+
+fn parse_raw(raw: Option<&serde_json::value::RawValue>) -> serde_json::Result<Option<Value>> {
+    raw.map(|raw| serde_json::from_str(raw.get())).transpose()
+}
+
+/// The refusal a failure carries, such as a proxied server's invalid
+/// params, to pass on as it is
+fn refusal(error: &(dyn std::error::Error + 'static)) -> Option<JsonRpcError> {
+    let mut cause = Some(error);
+    while let Some(error) = cause {
+        match error.downcast_ref::<mcp_core::Error>() {
+            Some(error) if !matches!(error.kind(), ErrorKind::Internal | ErrorKind::ToolFailed) => return Some(error.to_rpc()),
+            _ => cause = error.source(),
+        }
+    }
+    None
+}
+
+/// A tool's failure: its refusal if it carries one, otherwise a tool
+/// execution failure
+fn tool_failure(e: &anyhow::Error) -> JsonRpcError {
+    refusal(e.as_ref()).unwrap_or_else(|| {
+        JsonRpcError::new(TOOL_EXECUTION_FAILED, "Tool execution failed", Some(Value::String(e.to_string())))
+    })
+}
+
+/// A failed tool execution as a `tools/call` result, so the client (and the
+/// model behind it) sees what went wrong; an injected fault stays tagged
+fn tool_error_result(error: JsonRpcError) -> Value {
+    let data = error.data.unwrap_or_default();
+    let text = data.get("detail").and_then(Value::as_str).unwrap_or(&error.message);
+    let mut result = serde_json::to_value(ToolCallResult::error(text)).unwrap();
+    if let Some(fault) = data.get("faultInjection") {
+        result["_meta"]["faultInjection"] = fault.clone();
+    }
+    result
+}
+
+/// Warns that `name` is deprecated, in the log and in the result's `_meta`
+fn tag_deprecation(result: &mut Value, name: &str, deprecation: &Deprecation) {
+    warn!("Deprecated {} was called: {}", name, deprecation.message);
+    result["_meta"]["deprecation"] = serde_json::to_value(deprecation).unwrap();
+}
+
+/// Logs how a tool's structured result breaks its output schema. Only done in
+/// debug builds, to catch tools that drift from the schemas they declare.
+fn check_output(tool: &str, schema: &Value, content: &[ContentBlock]) {
+    match ContentBlock::find_structured(content) {
+        Some(structured) => {
+            for violation in crate::tools::schema::violations(schema, structured) {
+                warn!("Result of {} does not match its output schema: {}", tool, violation);
+            }
+        }
+        None => warn!("Result of {} has no structured content, though it declares an output schema", tool),
+    }
+}
+
+/// Plugins that read or write files, and so are held to the client's roots
+const FILE_PLUGINS: &[&str] = &["filesystem", "speech", "vision"];
+
+const CROSS_TENANT_REFUSAL: &str = "Raw Neo4j queries are disabled while tenants are per client";
+
+/// The plugin a built-in tool runs on; `None` for tools that are not
+/// backed by a plugin
+fn plugin_for_tool(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "system_info" => "system_info",
+        "homeassistant" => "home_assistant",
+        "http_request" => "http",
+        "neo4j_query" => "neo4j",
+        "embeddings" => "embeddings",
+        "summarize" => "summarize",
+        "filesystem" => "filesystem",
+        "memory" => "memory",
+        "kg_ingest" | "kg_query" => "knowledge_graph",
+        "aggregate_metrics" => "metrics",
+        "ollama" => "ollama",
+        "speech" => "speech",
+        "analyze_image" => "vision",
+        "network" => "network",
+        "netdiag" => "netdiag",
+        "jira" => "jira",
+        _ => return None,
+    })
+}
+
+/// The plugin a tool runs on: a built-in tool's, or for proxied tools the
+/// server named by their namespace
+fn tool_plugin(name: &str) -> Option<&str> {
+    plugin_for_tool(name).or_else(|| name.split_once('.').map(|(namespace, _)| namespace))
+}
+
+/// A call to a plugin that failed to initialize and has not recovered yet
+fn plugin_unavailable_error(plugin: &str, unavailable: &Unavailable) -> JsonRpcError {
+    JsonRpcError::new(
+        PLUGIN_UNAVAILABLE,
+        "Plugin unavailable",
+        Some(serde_json::json!({ "plugin": plugin, "detail": unavailable.error, "attempts": unavailable.attempts })),
+    )
+}
+
+/// Runs `future` unless `deadline` passes first
+async fn until<F: std::future::Future>(deadline: Option<tokio::time::Instant>, future: F) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+/// The caller gave up on a call to `tool` after `timeout`
+fn deadline_exceeded_error(tool: &str, timeout: std::time::Duration) -> JsonRpcError {
+    warn!("Stopped {} at the caller's deadline of {} ms", tool, timeout.as_millis());
+    JsonRpcError::new(
+        DEADLINE_EXCEEDED,
+        "Deadline exceeded",
+        Some(serde_json::json!({
+            "detail": format!("{} did not finish within {} ms", tool, timeout.as_millis()),
+            "timeoutMs": timeout.as_millis() as u64,
+        })),
+    )
+}
+
+/// Uses the code of the session rate limit, so clients retry both the same way
+fn rate_limited_error(limited: RateLimited) -> JsonRpcError {
+    warn!("Tool rate limit reached: {:?}", limited);
+    JsonRpcError::new(RATE_LIMITED, "Rate limit exceeded", Some(serde_json::to_value(limited).unwrap()))
+}
+
+/// Too fast is a rate limit like the session's; too large or too deep makes
+/// an invalid request
+fn limit_exceeded_error(exceeded: LimitExceeded) -> JsonRpcError {
+    warn!("Message limit exceeded: {:?}", exceeded);
+    let data = Some(serde_json::to_value(&exceeded).unwrap());
+    match exceeded.limit {
+        LimitKind::Rate => JsonRpcError::new(RATE_LIMITED, "Rate limit exceeded", data),
+        LimitKind::Bytes | LimitKind::Depth | LimitKind::Batch => JsonRpcError::new(INVALID_REQUEST, "Invalid Request", data),
+    }
+}
+
+fn budget_exceeded_error(exceeded: BudgetExceeded) -> JsonRpcError {
+    warn!("Session budget exceeded: {:?}", exceeded);
+    JsonRpcError::new(BUDGET_EXCEEDED, "Session budget exceeded", Some(serde_json::to_value(exceeded).unwrap()))
+}
+
+/// How a request id appears in log tags: strings as they are, numbers as digits
+fn request_id_tag(id: Option<&Value>) -> String {
+    match id {
+        Some(Value::String(id)) => id.clone(),
+        Some(id) => id.to_string(),
+        None => String::new(),
+    }
+}
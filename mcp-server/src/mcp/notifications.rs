@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Severity of a `notifications/message`, as defined by MCP (RFC 5424 levels)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+impl From<&Level> for LogLevel {
+    fn from(level: &Level) -> Self {
+        match *level {
+            Level::ERROR => LogLevel::Error,
+            Level::WARN => LogLevel::Warning,
+            Level::INFO => LogLevel::Info,
+            _ => LogLevel::Debug,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Subscribers {
+    /// Open notification streams, by session
+    senders: HashMap<String, UnboundedSender<String>>,
+    /// Minimum level each session asked for with `logging/setLevel`.
+    /// Sessions that never asked get no log messages.
+    log_levels: HashMap<String, LogLevel>,
+}
+
+/// Delivers server-initiated notifications to the sessions listening for them.
+/// Transports call `subscribe` for a session and write whatever arrives on the
+/// receiver to the client.
+#[derive(Default)]
+pub struct NotificationDispatcher {
+    subscribers: Mutex<Subscribers>,
+}
+
+impl NotificationDispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a notification stream for the session, replacing any earlier one
+    pub fn subscribe(&self, session_id: &str) -> UnboundedReceiver<String> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers
+            .lock()
+            .unwrap()
+            .senders
+            .insert(session_id.to_string(), sender);
+        receiver
+    }
+
+    /// Forgets the session's stream and log level
+    pub fn unsubscribe(&self, session_id: &str) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.senders.remove(session_id);
+        subscribers.log_levels.remove(session_id);
+    }
+
+    pub fn set_log_level(&self, session_id: &str, level: LogLevel) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .log_levels
+            .insert(session_id.to_string(), level);
+    }
+
+    /// Sends a notification to every subscribed session
+    pub fn broadcast(&self, method: &str, params: Option<Value>) {
+        let message = notification(method, params);
+        self.subscribers
+            .lock()
+            .unwrap()
+            .senders
+            .retain(|_, sender| sender.send(message.clone()).is_ok());
+    }
+
+    /// Sends a message on one session's stream; returns false if the session
+    /// has no open stream
+    pub fn send_to(&self, session_id: &str, message: String) -> bool {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        match subscribers.senders.get(session_id) {
+            Some(sender) if sender.send(message).is_ok() => true,
+            Some(_) => {
+                subscribers.senders.remove(session_id);
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub fn tools_list_changed(&self) {
+        self.broadcast("notifications/tools/list_changed", None);
+    }
+
+    /// Sends a `notifications/message` to sessions whose log level is at or
+    /// below `level`
+    pub fn log(&self, level: LogLevel, logger: &str, data: Value) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let Subscribers { senders, log_levels } = &mut *subscribers;
+        if log_levels.is_empty() {
+            return;
+        }
+
+        let message = notification(
+            "notifications/message",
+            Some(json!({ "level": level, "logger": logger, "data": data })),
+        );
+        senders.retain(|session, sender| match log_levels.get(session) {
+            Some(minimum) if level >= *minimum => sender.send(message.clone()).is_ok(),
+            _ => true,
+        });
+    }
+}
+
+fn notification(method: &str, params: Option<Value>) -> String {
+    let mut message = json!({ "jsonrpc": "2.0", "method": method });
+    if let Some(params) = params {
+        message["params"] = params;
+    }
+    message.to_string()
+}
+
+/// Tracing layer that forwards server log events to MCP clients as
+/// `notifications/message`
+pub struct NotificationLayer {
+    dispatcher: Arc<NotificationDispatcher>,
+}
+
+impl NotificationLayer {
+    pub fn new(dispatcher: Arc<NotificationDispatcher>) -> Self {
+        Self { dispatcher }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for NotificationLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        self.dispatcher.log(
+            LogLevel::from(metadata.level()),
+            metadata.target(),
+            Value::String(visitor.message),
+        );
+    }
+}
+
+/// Flattens an event into one line: the message, then `name=value` for the
+/// other fields
+#[derive(Default)]
+pub(super) struct MessageVisitor {
+    pub(super) message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let message = format!("{:?}", value);
+            self.message = if self.message.is_empty() {
+                message
+            } else {
+                format!("{} {}", message, self.message)
+            };
+        } else {
+            if !self.message.is_empty() {
+                self.message.push(' ');
+            }
+            self.message.push_str(&format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broadcast_reaches_all_subscribers() {
+        let dispatcher = NotificationDispatcher::new();
+        let mut first = dispatcher.subscribe("a");
+        let mut second = dispatcher.subscribe("b");
+
+        dispatcher.tools_list_changed();
+
+        for receiver in [&mut first, &mut second] {
+            let message: Value = serde_json::from_str(&receiver.try_recv().unwrap()).unwrap();
+            assert_eq!(message["method"], "notifications/tools/list_changed");
+            assert!(message.get("id").is_none());
+        }
+    }
+
+    #[test]
+    fn test_log_respects_session_level() {
+        let dispatcher = NotificationDispatcher::new();
+        let mut verbose = dispatcher.subscribe("verbose");
+        let mut quiet = dispatcher.subscribe("quiet");
+        let mut silent = dispatcher.subscribe("silent");
+        dispatcher.set_log_level("verbose", LogLevel::Debug);
+        dispatcher.set_log_level("quiet", LogLevel::Error);
+
+        dispatcher.log(LogLevel::Info, "mcp_server", json!("plugin reloaded"));
+
+        let message: Value = serde_json::from_str(&verbose.try_recv().unwrap()).unwrap();
+        assert_eq!(message["method"], "notifications/message");
+        assert_eq!(message["params"]["level"], "info");
+        assert_eq!(message["params"]["data"], "plugin reloaded");
+        assert!(quiet.try_recv().is_err());
+        assert!(silent.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_closed_streams_are_dropped() {
+        let dispatcher = NotificationDispatcher::new();
+        drop(dispatcher.subscribe("gone"));
+        dispatcher.tools_list_changed();
+        assert!(dispatcher.subscribers.lock().unwrap().senders.is_empty());
+    }
+}
@@ -1,31 +1,162 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use anyhow::{Result, Error};
 
-use crate::plugins::Plugin;
+use super::events::EventBus;
+use crate::plugins::{HealthStatus, Plugin};
 
 pub struct PluginRegistry {
     plugins: HashMap<String, Arc<dyn Plugin + Send + Sync>>,
+    /// Plugins an operator switched off; they stay registered
+    disabled: HashSet<String>,
+    /// Plugins that failed to initialize, until a retry succeeds
+    unavailable: HashMap<String, Unavailable>,
+    /// The plugins each plugin needs, by name
+    dependencies: HashMap<String, Vec<String>>,
+    events: EventBus,
+}
+
+/// Recorded for plugins that were added but are waiting for their
+/// dependencies to be initialized
+const NOT_INITIALIZED: &str = "Not initialized yet";
+
+/// Why a plugin is unavailable
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Unavailable {
+    pub error: String,
+    /// How often initializing it failed so far
+    pub attempts: u32,
 }
 
 impl PluginRegistry {
     pub fn new() -> Self {
         Self {
             plugins: HashMap::new(),
+            disabled: HashSet::new(),
+            unavailable: HashMap::new(),
+            dependencies: HashMap::new(),
+            events: EventBus::default(),
         }
     }
 
+    /// Hand registered plugins `events` instead of a bus of their own
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
     pub async fn register_plugin(&mut self, plugin: Arc<dyn Plugin + Send + Sync>) -> Result<()> {
+        plugin.attach_events(&self.events);
+
         // Initialize the plugin
         if let Err(e) = plugin.initialize().await {
             return Err(Error::msg(format!("Failed to initialize plugin: {}", e)));
         }
         
         let name = plugin.name().to_string();
+        self.unavailable.remove(&name);
         self.plugins.insert(name, plugin);
         Ok(())
     }
 
+    /// Registers `plugin` even if it fails to initialize. It is then
+    /// unavailable, with the error returned, until a retry initializes it.
+    pub async fn register_plugin_lazily(&mut self, plugin: Arc<dyn Plugin + Send + Sync>) -> Result<()> {
+        plugin.attach_events(&self.events);
+        let name = plugin.name().to_string();
+        let outcome = plugin.initialize().await;
+        self.plugins.insert(name.clone(), plugin);
+        match outcome {
+            Ok(()) => {
+                self.unavailable.remove(&name);
+                Ok(())
+            }
+            Err(e) => {
+                let error = e.to_string();
+                self.mark_unavailable(&name, &error);
+                Err(Error::msg(format!("Failed to initialize plugin {}: {}", name, error)))
+            }
+        }
+    }
+
+    /// Registers `plugin` without initializing it, so dependencies can be
+    /// declared first; `initialize_all` initializes it
+    pub fn add_plugin(&mut self, plugin: Arc<dyn Plugin + Send + Sync>) {
+        plugin.attach_events(&self.events);
+        let name = plugin.name().to_string();
+        self.unavailable.insert(name.clone(), Unavailable { error: NOT_INITIALIZED.to_string(), attempts: 0 });
+        self.plugins.insert(name, plugin);
+    }
+
+    /// Declares that `plugin` needs `dependency`: it is initialized after
+    /// it, shut down before it, and disabled or unavailable while it is.
+    /// Fails if that would make a cycle.
+    pub fn depends_on(&mut self, plugin: &str, dependency: &str) -> Result<()> {
+        if plugin == dependency || self.needs(dependency, plugin) {
+            return Err(Error::msg(format!("{} cannot depend on {}: {} already depends on {}", plugin, dependency, dependency, plugin)));
+        }
+        let dependencies = self.dependencies.entry(plugin.to_string()).or_default();
+        if !dependencies.iter().any(|name| name == dependency) {
+            dependencies.push(dependency.to_string());
+        }
+        Ok(())
+    }
+
+    /// The plugins `name` was declared to depend on
+    pub fn dependencies_of(&self, name: &str) -> &[String] {
+        self.dependencies.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether `plugin` depends on `other`, directly or through others
+    fn needs(&self, plugin: &str, other: &str) -> bool {
+        self.dependencies_of(plugin).iter().any(|dependency| dependency == other || self.needs(dependency, other))
+    }
+
+    /// The registered plugins, each after its dependencies and otherwise
+    /// by name
+    pub fn initialization_order(&self) -> Vec<String> {
+        fn visit(registry: &PluginRegistry, name: &str, ordered: &mut Vec<String>) {
+            if ordered.iter().any(|placed| placed == name) || !registry.plugins.contains_key(name) {
+                return;
+            }
+            for dependency in registry.dependencies_of(name) {
+                visit(registry, dependency, ordered);
+            }
+            ordered.push(name.to_string());
+        }
+        let mut names = self.list_plugins();
+        names.sort();
+        let mut ordered = Vec::new();
+        for name in &names {
+            visit(self, name, &mut ordered);
+        }
+        ordered
+    }
+
+    /// Initializes every plugin in dependency order. A plugin whose
+    /// dependencies are unavailable is left uninitialized, to be retried
+    /// once they are. Returns the errors of those that failed.
+    pub async fn initialize_all(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for name in self.initialization_order() {
+            if self.unavailable_dependency(&name).is_some() {
+                self.unavailable.entry(name).or_insert_with(|| Unavailable { error: NOT_INITIALIZED.to_string(), attempts: 0 });
+                continue;
+            }
+            let plugin = self.plugins[&name].clone();
+            match plugin.initialize().await {
+                Ok(()) => {
+                    self.unavailable.remove(&name);
+                }
+                Err(e) => {
+                    errors.push(format!("Failed to initialize plugin {}: {}", name, e));
+                    self.mark_unavailable(&name, &e.to_string());
+                }
+            }
+        }
+        errors
+    }
+
     pub fn get_plugin(&self, name: &str) -> Option<Arc<dyn Plugin + Send + Sync>> {
         self.plugins.get(name).cloned()
     }
@@ -34,11 +165,106 @@ impl PluginRegistry {
         self.plugins.keys().cloned().collect()
     }
 
+    /// Switches a plugin on or off; returns false if there is no such plugin
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        if !self.plugins.contains_key(name) {
+            return false;
+        }
+        if enabled {
+            self.disabled.remove(name);
+        } else {
+            self.disabled.insert(name.to_string());
+        }
+        true
+    }
+
+    /// Whether `name` and the plugins it depends on are switched on; names
+    /// of unknown plugins are
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.contains(name) && self.dependencies_of(name).iter().all(|dependency| self.is_enabled(dependency))
+    }
+
+    /// Whether `name` and the plugins it depends on initialized; names of
+    /// unknown plugins are
+    pub fn is_available(&self, name: &str) -> bool {
+        self.unavailable(name).is_none()
+    }
+
+    /// Why `name` is unavailable, if it is: a dependency that is, or its
+    /// own failure to initialize
+    pub fn unavailable(&self, name: &str) -> Option<Unavailable> {
+        self.unavailable_dependency(name).or_else(|| self.unavailable.get(name).cloned())
+    }
+
+    fn unavailable_dependency(&self, name: &str) -> Option<Unavailable> {
+        self.dependencies_of(name).iter().find_map(|dependency| {
+            let error = if !self.plugins.contains_key(dependency) {
+                format!("Depends on {}, which is not registered", dependency)
+            } else if !self.is_available(dependency) {
+                format!("Depends on {}, which is unavailable", dependency)
+            } else {
+                return None;
+            };
+            Some(Unavailable { error, attempts: 0 })
+        })
+    }
+
+    fn mark_unavailable(&mut self, name: &str, error: &str) {
+        let attempts = self.unavailable.get(name).map_or(0, |unavailable| unavailable.attempts);
+        self.unavailable.insert(name.to_string(), Unavailable { error: error.to_string(), attempts: attempts + 1 });
+    }
+
+    /// The plugins that failed to initialize while their dependencies are
+    /// available, in dependency order, to initialize again without holding
+    /// the registry; report the outcome with `retried`
+    pub fn unavailable_plugins(&self) -> Vec<Arc<dyn Plugin + Send + Sync>> {
+        self.initialization_order()
+            .into_iter()
+            .filter(|name| self.unavailable.contains_key(name) && self.unavailable_dependency(name).is_none())
+            .map(|name| self.plugins[&name].clone())
+            .collect()
+    }
+
+    /// Records the outcome of initializing an unavailable plugin again
+    pub fn retried(&mut self, name: &str, outcome: std::result::Result<(), String>) {
+        if !self.plugins.contains_key(name) {
+            return;
+        }
+        match outcome {
+            Ok(()) => {
+                self.unavailable.remove(name);
+            }
+            Err(error) => self.mark_unavailable(name, &error),
+        }
+    }
+
+    pub async fn health_check(&self) -> HashMap<String, HealthStatus> {
+        let mut statuses = HashMap::new();
+        for (name, plugin) in &self.plugins {
+            statuses.insert(name.clone(), plugin.health_check().await);
+        }
+        statuses
+    }
+
+    /// Shuts every plugin down and initializes it again, so changed
+    /// configuration is picked up. Plugins that fail to initialize become
+    /// unavailable; the others are reloaded all the same.
+    pub async fn reload(&mut self) -> Result<()> {
+        self.shutdown().await?;
+        let errors = self.initialize_all().await;
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::msg(errors.join("\n")))
+        }
+    }
+
+    /// Shuts every plugin down, each before the plugins it depends on
     pub async fn shutdown(&self) -> Result<()> {
         let mut errors = Vec::new();
-        for plugin in self.plugins.values() {
-            if let Err(e) = plugin.shutdown().await {
-                errors.push(format!("Error shutting down plugin {}: {}", plugin.name(), e));
+        for name in self.initialization_order().iter().rev() {
+            if let Err(e) = self.plugins[name].shutdown().await {
+                errors.push(format!("Error shutting down plugin {}: {}", name, e));
             }
         }
         
@@ -108,6 +334,8 @@ mod tests {
                     parameter_type: ParameterType::String,
                     required: true,
                 }],
+                version: None,
+                deprecated: None,
             }]
         }
 
@@ -291,6 +519,112 @@ mod tests {
         assert_eq!(capabilities[0].parameters[0].name, "param1");
     }
 
+    #[tokio::test]
+    async fn test_health_check_defaults_to_healthy() {
+        let mut registry = PluginRegistry::new();
+        registry.register_plugin(Arc::new(MockPlugin::new("plugin1"))).await.unwrap();
+        registry.register_plugin(Arc::new(MockPlugin::new("plugin2"))).await.unwrap();
+
+        let statuses = registry.health_check().await;
+        assert_eq!(statuses.len(), 2);
+        assert!(statuses.values().all(|status| status.healthy));
+    }
+
+    #[tokio::test]
+    async fn test_plugins_can_be_disabled_and_enabled() {
+        let mut registry = PluginRegistry::new();
+        registry.register_plugin(Arc::new(MockPlugin::new("test_plugin"))).await.unwrap();
+        assert!(registry.is_enabled("test_plugin"));
+
+        assert!(registry.set_enabled("test_plugin", false));
+        assert!(!registry.is_enabled("test_plugin"));
+        // Disabled plugins stay registered
+        assert!(registry.get_plugin("test_plugin").is_some());
+
+        assert!(registry.set_enabled("test_plugin", true));
+        assert!(registry.is_enabled("test_plugin"));
+        assert!(!registry.set_enabled("nonexistent_plugin", false));
+    }
+
+    #[tokio::test]
+    async fn test_plugins_failing_to_initialize_can_be_registered_unavailable() {
+        let mut registry = PluginRegistry::new();
+        let result = registry.register_plugin_lazily(Arc::new(MockPlugin::new("failing_plugin").with_init_failure())).await;
+        assert!(result.is_err());
+        registry.register_plugin_lazily(Arc::new(MockPlugin::new("test_plugin"))).await.unwrap();
+
+        assert_eq!(registry.list_plugins().len(), 2);
+        assert!(registry.is_available("test_plugin"));
+        assert!(!registry.is_available("failing_plugin"));
+        assert_eq!(registry.unavailable("failing_plugin").unwrap().attempts, 1);
+        assert_eq!(registry.unavailable_plugins().len(), 1);
+
+        registry.retried("failing_plugin", Err("still down".to_string()));
+        let unavailable = registry.unavailable("failing_plugin").unwrap();
+        assert_eq!((unavailable.error.as_str(), unavailable.attempts), ("still down", 2));
+
+        registry.retried("failing_plugin", Ok(()));
+        assert!(registry.is_available("failing_plugin"));
+        assert!(registry.unavailable_plugins().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reload_marks_failing_plugins_unavailable() {
+        let mut registry = PluginRegistry::new();
+        registry.register_plugin(Arc::new(MockPlugin::new("plugin1"))).await.unwrap();
+        registry.plugins.insert("plugin2".to_string(), Arc::new(MockPlugin::new("plugin2").with_init_failure()));
+
+        let result = registry.reload().await;
+        assert!(result.unwrap_err().to_string().contains("plugin2"));
+        assert!(registry.is_available("plugin1"));
+        assert!(!registry.is_available("plugin2"));
+    }
+
+    #[tokio::test]
+    async fn test_dependencies_order_initialization_and_cannot_form_cycles() {
+        let mut registry = PluginRegistry::new();
+        for name in ["a_embeddings", "b_store", "c_search"] {
+            registry.add_plugin(Arc::new(MockPlugin::new(name)));
+        }
+        registry.depends_on("a_embeddings", "b_store").unwrap();
+        registry.depends_on("b_store", "c_search").unwrap();
+        assert!(registry.depends_on("c_search", "a_embeddings").is_err());
+        assert!(registry.depends_on("b_store", "b_store").is_err());
+
+        assert_eq!(registry.initialization_order(), ["c_search", "b_store", "a_embeddings"]);
+        assert!(!registry.is_available("a_embeddings"));
+        assert!(registry.initialize_all().await.is_empty());
+        assert!(registry.is_available("a_embeddings"));
+    }
+
+    #[tokio::test]
+    async fn test_dependents_are_disabled_and_unavailable_with_their_dependency() {
+        let mut registry = PluginRegistry::new();
+        registry.add_plugin(Arc::new(MockPlugin::new("neo4j").with_init_failure()));
+        registry.add_plugin(Arc::new(MockPlugin::new("memory")));
+        registry.add_plugin(Arc::new(MockPlugin::new("http")));
+        registry.depends_on("memory", "neo4j").unwrap();
+
+        let errors = registry.initialize_all().await;
+        assert_eq!(errors.len(), 1);
+        assert!(registry.is_available("http"));
+        let unavailable = registry.unavailable("memory").unwrap();
+        assert_eq!(unavailable.error, "Depends on neo4j, which is unavailable");
+        // Only neo4j is retried until it recovers
+        let retry: Vec<_> = registry.unavailable_plugins().iter().map(|plugin| plugin.name().to_string()).collect();
+        assert_eq!(retry, ["neo4j"]);
+
+        registry.retried("neo4j", Ok(()));
+        assert_eq!(registry.unavailable("memory").unwrap().error, NOT_INITIALIZED);
+        assert_eq!(registry.unavailable_plugins()[0].name(), "memory");
+        registry.retried("memory", Ok(()));
+        assert!(registry.is_available("memory"));
+
+        registry.set_enabled("neo4j", false);
+        assert!(!registry.is_enabled("memory"));
+        assert!(registry.is_enabled("http"));
+    }
+
     #[tokio::test]
     async fn test_shutdown_empty_registry() {
         let registry = PluginRegistry::new();
@@ -0,0 +1,126 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::types::ContentBlock;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptArgument {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub required: bool,
+}
+
+/// A prompt template, as listed by `prompts/list`
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptDefinition {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptMessage {
+    pub role: &'static str,
+    pub content: ContentBlock,
+}
+
+/// Result of `prompts/get`
+#[derive(Debug, Clone, Serialize)]
+pub struct GetPromptResult {
+    pub description: &'static str,
+    pub messages: Vec<PromptMessage>,
+}
+
+/// The built-in prompts
+pub fn list() -> Vec<PromptDefinition> {
+    vec![
+        PromptDefinition {
+            name: "system_health_report",
+            description: "Check the host with system_info and write a short health report",
+            arguments: vec![PromptArgument {
+                name: "focus",
+                description: "Area to look at closely, e.g. disks or memory",
+                required: false,
+            }],
+        },
+        PromptDefinition {
+            name: "summarize_tool_result",
+            description: "Explain a tool result in plain language",
+            arguments: vec![
+                PromptArgument {
+                    name: "tool",
+                    description: "Name of the tool that produced the result",
+                    required: true,
+                },
+                PromptArgument {
+                    name: "result",
+                    description: "The tool's output",
+                    required: true,
+                },
+            ],
+        },
+    ]
+}
+
+/// Fills in a prompt; unknown prompts and missing required arguments are errors
+pub fn get(name: &str, arguments: &HashMap<String, String>) -> Result<GetPromptResult, String> {
+    let definition = list()
+        .into_iter()
+        .find(|prompt| prompt.name == name)
+        .ok_or_else(|| format!("Unknown prompt: {}", name))?;
+    if let Some(missing) = definition
+        .arguments
+        .iter()
+        .find(|argument| argument.required && !arguments.contains_key(argument.name))
+    {
+        return Err(format!("Missing required argument: {}", missing.name));
+    }
+
+    let text = match name {
+        "system_health_report" => {
+            let mut text = "Use the system_info tool to check CPU, memory, disks and network, \
+                then write a short health report that flags anything unusual."
+                .to_string();
+            if let Some(focus) = arguments.get("focus") {
+                text.push_str(&format!(" Pay particular attention to {}.", focus));
+            }
+            text
+        }
+        "summarize_tool_result" => format!(
+            "The {} tool returned the following. Explain what it means in a few sentences.\n\n{}",
+            arguments["tool"], arguments["result"]
+        ),
+        _ => unreachable!("every listed prompt has a template"),
+    };
+
+    Ok(GetPromptResult {
+        description: definition.description,
+        messages: vec![PromptMessage {
+            role: "user",
+            content: ContentBlock::text(&text),
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_fills_in_arguments() {
+        let arguments = HashMap::from([("focus".to_string(), "disks".to_string())]);
+        let prompt = get("system_health_report", &arguments).unwrap();
+        let text = prompt.messages[0].content.as_text().unwrap();
+        assert!(text.ends_with("Pay particular attention to disks."));
+    }
+
+    #[test]
+    fn test_get_checks_required_arguments() {
+        let arguments = HashMap::from([("tool".to_string(), "system_info".to_string())]);
+        assert_eq!(
+            get("summarize_tool_result", &arguments).unwrap_err(),
+            "Missing required argument: result"
+        );
+        assert!(get("missing", &HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Calls a tool or plugin may make per period, e.g. `10/min`. Up to
+/// `calls` may be made at once; after that they are spread out evenly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub calls: u32,
+    pub per: Duration,
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    /// `<calls>/<period>`, where the period is `s`, `min` or `h`, optionally
+    /// with a count such as `30s`
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (calls, period) = spec
+            .split_once('/')
+            .ok_or_else(|| format!("Expected calls/period, e.g. 10/min, got '{}'", spec))?;
+        let calls: u32 = calls
+            .trim()
+            .parse()
+            .ok()
+            .filter(|calls| *calls > 0)
+            .ok_or_else(|| format!("Invalid number of calls '{}'", calls.trim()))?;
+        let period = period.trim();
+        let digits = period.find(|c: char| !c.is_ascii_digit()).unwrap_or(period.len());
+        let (count, unit) = period.split_at(digits);
+        let count: u64 = if count.is_empty() { 1 } else { count.parse().map_err(|e| format!("Invalid period '{}': {}", period, e))? };
+        let unit = match unit {
+            "s" | "sec" => 1,
+            "m" | "min" => 60,
+            "h" | "hour" => 3600,
+            other => return Err(format!("Unknown period unit '{}'; use s, min or h", other)),
+        };
+        if count == 0 {
+            return Err(format!("Invalid period '{}'", period));
+        }
+        Ok(RateLimit { calls, per: Duration::from_secs(count * unit) })
+    }
+}
+
+/// Rate limits by tool or plugin name, parsed from a spec such as
+/// `homeassistant=10/min,http=60/min`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimitConfig {
+    pub limits: HashMap<String, RateLimit>,
+}
+
+impl FromStr for RateLimitConfig {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut config = RateLimitConfig::default();
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (name, limit) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected name=calls/period, got '{}'", setting))?;
+            let limit = limit.parse().map_err(|e| format!("{}: {}", name.trim(), e))?;
+            config.limits.insert(name.trim().to_string(), limit);
+        }
+        Ok(config)
+    }
+}
+
+impl RateLimitConfig {
+    /// Limits from `MCP_TOOL_RATE_LIMITS`, if set and valid
+    pub fn from_env() -> Option<Self> {
+        let spec = std::env::var("MCP_TOOL_RATE_LIMITS").ok()?;
+        match spec.parse() {
+            Ok(config) => Some(config),
+            Err(e) => {
+                tracing::warn!("Ignoring MCP_TOOL_RATE_LIMITS: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Why a call was refused; sent as the error data
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimited {
+    /// The tool or plugin whose limit was reached
+    pub name: String,
+    pub limit: u32,
+    pub period_secs: u64,
+    /// Whole seconds until the call would be allowed
+    pub retry_after: u64,
+}
+
+struct Bucket {
+    tokens: f64,
+    filled_at: Instant,
+}
+
+/// Token buckets for the configured tools and plugins, shared by all
+/// sessions since the upstream quotas they protect are
+pub struct RateLimiter {
+    limits: RwLock<HashMap<String, RateLimit>>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self { limits: RwLock::new(config.limits), buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Replaces the limits; buckets whose limit changed start full again,
+    /// and the others keep their tokens
+    pub fn reconfigure(&self, config: RateLimitConfig) {
+        let mut limits = self.limits.write().unwrap();
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|name, _| limits.get(name) == config.limits.get(name));
+        *limits = config.limits;
+    }
+
+    /// Takes a token for each of `names` that has a limit, e.g. a tool and
+    /// the plugin behind it. Takes none if any bucket is empty.
+    pub fn acquire(&self, names: &[&str]) -> Result<(), RateLimited> {
+        self.acquire_at(names, Instant::now())
+    }
+
+    fn acquire_at(&self, names: &[&str], now: Instant) -> Result<(), RateLimited> {
+        let limits = self.limits.read().unwrap();
+        let mut buckets = self.buckets.lock().unwrap();
+        let mut limited: Vec<&str> = Vec::new();
+        for name in names {
+            let Some(limit) = limits.get(*name) else { continue };
+            if limited.contains(name) {
+                continue;
+            }
+            let bucket = buckets
+                .entry(name.to_string())
+                .or_insert(Bucket { tokens: limit.calls as f64, filled_at: now });
+            let per_token = limit.per.as_secs_f64() / limit.calls as f64;
+            let refilled = now.saturating_duration_since(bucket.filled_at).as_secs_f64() / per_token;
+            bucket.tokens = (bucket.tokens + refilled).min(limit.calls as f64);
+            bucket.filled_at = now;
+            if bucket.tokens < 1.0 {
+                let wait = (1.0 - bucket.tokens) * per_token;
+                return Err(RateLimited {
+                    name: name.to_string(),
+                    limit: limit.calls,
+                    period_secs: limit.per.as_secs(),
+                    retry_after: (wait.ceil() as u64).max(1),
+                });
+            }
+            limited.push(name);
+        }
+        for name in limited {
+            if let Some(bucket) = buckets.get_mut(name) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        let config: RateLimitConfig = "homeassistant=10/min, http=5/s,summarize=2/30s".parse().unwrap();
+        assert_eq!(config.limits["homeassistant"], RateLimit { calls: 10, per: Duration::from_secs(60) });
+        assert_eq!(config.limits["http"], RateLimit { calls: 5, per: Duration::from_secs(1) });
+        assert_eq!(config.limits["summarize"], RateLimit { calls: 2, per: Duration::from_secs(30) });
+
+        assert!("http=0/s".parse::<RateLimitConfig>().is_err());
+        assert!("http=5/day".parse::<RateLimitConfig>().is_err());
+        assert!("http".parse::<RateLimitConfig>().is_err());
+    }
+
+    #[test]
+    fn test_bucket_allows_bursts_then_refills() {
+        let limiter = RateLimiter::new("homeassistant=2/min,home_assistant=10/min".parse().unwrap());
+        let start = Instant::now();
+        assert!(limiter.acquire_at(&["homeassistant", "home_assistant"], start).is_ok());
+        assert!(limiter.acquire_at(&["homeassistant", "home_assistant"], start).is_ok());
+        let refused = limiter.acquire_at(&["homeassistant", "home_assistant"], start).unwrap_err();
+        assert_eq!(refused.name, "homeassistant");
+        assert_eq!(refused.retry_after, 30);
+
+        // A refused call takes nothing from the other bucket
+        for _ in 0..8 {
+            assert!(limiter.acquire_at(&["home_assistant"], start).is_ok());
+        }
+        assert!(limiter.acquire_at(&["home_assistant"], start).is_err());
+
+        assert!(limiter.acquire_at(&["homeassistant"], start + Duration::from_secs(30)).is_ok());
+        assert!(limiter.acquire_at(&["homeassistant"], start + Duration::from_secs(31)).is_err());
+        assert!(limiter.acquire_at(&["unlimited"], start).is_ok());
+    }
+
+    #[test]
+    fn test_reconfigure_refills_only_changed_buckets() {
+        let limiter = RateLimiter::new("http=1/min,summarize=1/min".parse().unwrap());
+        let start = Instant::now();
+        assert!(limiter.acquire_at(&["http", "summarize"], start).is_ok());
+
+        limiter.reconfigure("http=2/min,summarize=1/min,embeddings=1/h".parse().unwrap());
+        assert!(limiter.acquire_at(&["http"], start).is_ok());
+        assert!(limiter.acquire_at(&["summarize"], start).is_err());
+        assert!(limiter.acquire_at(&["embeddings"], start).is_ok());
+        assert!(limiter.acquire_at(&["embeddings"], start).is_err());
+
+        limiter.reconfigure(RateLimitConfig::default());
+        assert!(limiter.acquire_at(&["summarize"], start).is_ok());
+    }
+}
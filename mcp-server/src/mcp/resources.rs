@@ -0,0 +1,42 @@
+use serde::Serialize;
+
+/// Catalog of registered plugins and their capabilities
+pub const PLUGINS_URI: &str = "mcp://plugins";
+/// Current system_info snapshot
+pub const SYSTEM_INFO_URI: &str = "system://info";
+
+/// A readable resource, as listed by `resources/list`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceDefinition {
+    pub uri: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub mime_type: &'static str,
+}
+
+/// One entry of a `resources/read` result
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    pub mime_type: &'static str,
+    pub text: String,
+}
+
+pub fn list() -> Vec<ResourceDefinition> {
+    vec![
+        ResourceDefinition {
+            uri: PLUGINS_URI,
+            name: "Plugins",
+            description: "Registered plugins and the capabilities each one offers",
+            mime_type: "application/json",
+        },
+        ResourceDefinition {
+            uri: SYSTEM_INFO_URI,
+            name: "System information",
+            description: "CPU, memory and host details of the machine running the server",
+            mime_type: "application/json",
+        },
+    ]
+}
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Directories file access may ever touch, separated like `PATH`
+pub const ROOTS_ENV: &str = "MCP_FS_ROOTS";
+
+/// A directory a client lets the server work in, as exchanged by `roots/list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Root {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Root {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Some(Self {
+            uri: reqwest::Url::from_file_path(path).ok()?.to_string(),
+            name: path.file_name().map(|name| name.to_string_lossy().into_owned()),
+        })
+    }
+
+    /// Only `file://` roots name local directories
+    pub fn to_path(&self) -> Option<PathBuf> {
+        reqwest::Url::parse(&self.uri).ok()?.to_file_path().ok()
+    }
+}
+
+/// Result of `roots/list`, in either direction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListRootsResult {
+    pub roots: Vec<Root>,
+}
+
+/// The server's roots from `MCP_FS_ROOTS`. Directories that do not exist are
+/// skipped; an unset variable allows nothing.
+pub fn configured() -> Vec<PathBuf> {
+    let Some(paths) = std::env::var_os(ROOTS_ENV) else {
+        return Vec::new();
+    };
+    std::env::split_paths(&paths)
+        .filter(|path| !path.as_os_str().is_empty())
+        .filter_map(|path| match path.canonicalize() {
+            Ok(path) => Some(path),
+            Err(e) => {
+                warn!("Ignoring filesystem root {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Directories a call may use: the configured roots, narrowed to the client's
+/// roots when it declared any. A client root outside every configured root
+/// grants nothing.
+pub fn effective(configured: &[PathBuf], client: Option<&[PathBuf]>) -> Vec<PathBuf> {
+    let Some(client) = client else {
+        return configured.to_vec();
+    };
+    let mut allowed = Vec::new();
+    for client_root in client.iter().filter_map(|root| root.canonicalize().ok()) {
+        for root in configured {
+            let narrower = if client_root.starts_with(root) {
+                &client_root
+            } else if root.starts_with(&client_root) {
+                root
+            } else {
+                continue;
+            };
+            if !allowed.contains(narrower) {
+                allowed.push(narrower.clone());
+            }
+        }
+    }
+    allowed
+}
+
+/// A path refused by [`resolve`] or [`resolve_new`]
+#[derive(Debug)]
+pub struct RootError(String);
+
+impl std::fmt::Display for RootError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for RootError {}
+
+/// Canonicalizes `path`, which must exist, and checks it against `allowed`.
+/// Relative paths start at the first allowed root, and symlinks are resolved
+/// first, so they cannot lead outside.
+pub fn resolve(path: &str, allowed: &[PathBuf]) -> Result<PathBuf, RootError> {
+    let resolved = requested(path, allowed)?
+        .canonicalize()
+        .map_err(|e| RootError(format!("Cannot access {}: {}", path, e)))?;
+    within(path, resolved, allowed)
+}
+
+/// Like [`resolve`] for a file to be written: it need not exist yet, but
+/// its directory must, and it may not be a symlink, which could point outside
+pub fn resolve_new(path: &str, allowed: &[PathBuf]) -> Result<PathBuf, RootError> {
+    let requested = requested(path, allowed)?;
+    let name = requested.file_name().ok_or_else(|| RootError(format!("{} is not a file path", path)))?;
+    let resolved = requested
+        .parent()
+        .unwrap_or(Path::new("/"))
+        .canonicalize()
+        .map(|parent| parent.join(name))
+        .map_err(|e| RootError(format!("Cannot access {}: {}", path, e)))?;
+    let resolved = within(path, resolved, allowed)?;
+    if resolved.symlink_metadata().is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        return Err(RootError(format!("{} is a symbolic link", path)));
+    }
+    Ok(resolved)
+}
+
+/// `path` made absolute against the first allowed root
+fn requested(path: &str, allowed: &[PathBuf]) -> Result<PathBuf, RootError> {
+    let first = allowed
+        .first()
+        .ok_or_else(|| RootError(format!("No filesystem roots allow access to {}; set {}", path, ROOTS_ENV)))?;
+    Ok(first.join(path))
+}
+
+fn within(path: &str, resolved: PathBuf, allowed: &[PathBuf]) -> Result<PathBuf, RootError> {
+    if !allowed.iter().any(|root| resolved.starts_with(root)) {
+        return Err(RootError(format!("{} is outside the allowed roots", path)));
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_uri_round_trip() {
+        let root = Root::from_path(Path::new("/srv/data files")).unwrap();
+        assert_eq!(root.uri, "file:///srv/data%20files");
+        assert_eq!(root.name.as_deref(), Some("data files"));
+        assert_eq!(root.to_path(), Some(PathBuf::from("/srv/data files")));
+
+        let remote = Root { uri: "https://example.com/repo".to_string(), name: None };
+        assert_eq!(remote.to_path(), None);
+    }
+
+    #[test]
+    fn test_effective_roots_intersect_client_and_server() {
+        let server = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let dir = server.path().canonicalize().unwrap();
+        let project = dir.join("project");
+        let other = outside.path().canonicalize().unwrap();
+        std::fs::create_dir(&project).unwrap();
+        let configured = vec![dir.clone()];
+
+        assert_eq!(effective(&configured, None), configured);
+        // A client root inside a server root narrows it
+        assert_eq!(effective(&configured, Some(std::slice::from_ref(&project))), vec![project.clone()]);
+        // A client root around a server root is capped by it
+        assert_eq!(effective(&configured, Some(&[dir.parent().unwrap().to_path_buf()])), configured);
+        // Roots outside the server's, or that do not exist, grant nothing
+        assert!(effective(&configured, Some(&[other.clone(), dir.join("missing")])).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_keeps_paths_within_the_roots() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let dir = root.path().canonicalize().unwrap();
+        std::fs::write(dir.join("notes.txt"), "hi").unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "no").unwrap();
+        let allowed = vec![dir.clone()];
+
+        // Relative paths start at the first root, wherever the server runs
+        assert_eq!(resolve("notes.txt", &allowed).unwrap(), dir.join("notes.txt"));
+        assert_eq!(resolve(dir.join("notes.txt").to_str().unwrap(), &allowed).unwrap(), dir.join("notes.txt"));
+        assert!(resolve("../secret.txt", &allowed).unwrap_err().to_string().contains("Cannot access"));
+        let escape = outside.path().join("secret.txt");
+        assert!(resolve(escape.to_str().unwrap(), &allowed).unwrap_err().to_string().contains("outside the allowed roots"));
+        assert!(resolve("notes.txt", &[]).unwrap_err().to_string().contains(ROOTS_ENV));
+
+        // Files to be written need an existing directory within the roots
+        assert_eq!(resolve_new("out.wav", &allowed).unwrap(), dir.join("out.wav"));
+        assert!(resolve_new("missing/out.wav", &allowed).is_err());
+        assert!(resolve_new(escape.to_str().unwrap(), &allowed).unwrap_err().to_string().contains("outside the allowed roots"));
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&escape, dir.join("link.wav")).unwrap();
+            assert!(resolve_new("link.wav", &allowed).unwrap_err().to_string().contains("symbolic link"));
+        }
+    }
+}
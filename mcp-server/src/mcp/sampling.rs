@@ -0,0 +1,215 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::client_requests::ClientRequests;
+use super::types::ContentBlock;
+
+/// How long to wait for a client to answer a sampling request
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// One turn of the conversation sent for sampling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingMessage {
+    pub role: String,
+    pub content: ContentBlock,
+}
+
+impl SamplingMessage {
+    pub fn user(text: &str) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: ContentBlock::text(text),
+        }
+    }
+}
+
+/// Parameters of `sampling/createMessage`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageParams {
+    pub messages: Vec<SamplingMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    pub max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<Value>,
+}
+
+/// The completion returned by the client or the fallback
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    pub role: String,
+    pub content: ContentBlock,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+impl CreateMessageResult {
+    pub fn text(&self) -> &str {
+        self.content.as_text().unwrap_or_default()
+    }
+}
+
+/// Ollama model used when the calling client cannot sample. Configured with
+/// `SAMPLING_OLLAMA_MODEL` and `OLLAMA_BASE_URL`.
+struct OllamaFallback {
+    base_url: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+impl OllamaFallback {
+    fn from_env() -> Option<Self> {
+        let model = std::env::var("SAMPLING_OLLAMA_MODEL").ok().filter(|m| !m.is_empty())?;
+        let base_url = std::env::var("OLLAMA_BASE_URL").unwrap_or_else(|_| "http://localhost:11434".to_string());
+        Some(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    async fn create_message(&self, params: &CreateMessageParams) -> Result<CreateMessageResult> {
+        let mut messages = Vec::new();
+        if let Some(system) = &params.system_prompt {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        for message in &params.messages {
+            messages.push(json!({ "role": message.role, "content": message.content.as_text().unwrap_or_default() }));
+        }
+
+        let response = self.client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&json!({
+                "model": self.model,
+                "messages": messages,
+                "stream": false,
+                "options": { "num_predict": params.max_tokens },
+            }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(anyhow!("Ollama returned {}: {}", status, response.text().await?));
+        }
+
+        let body: Value = response.json().await?;
+        let text = body["message"]["content"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Ollama response has no message content"))?;
+        Ok(CreateMessageResult {
+            role: "assistant".to_string(),
+            content: ContentBlock::text(text),
+            model: self.model.clone(),
+            stop_reason: Some("endTurn".to_string()),
+        })
+    }
+}
+
+/// Sends `sampling/createMessage` requests to clients, with an Ollama model
+/// as the fallback
+pub struct SamplingBroker {
+    requests: Arc<ClientRequests>,
+    fallback: Option<OllamaFallback>,
+    timeout: Duration,
+}
+
+impl SamplingBroker {
+    pub fn new(requests: Arc<ClientRequests>) -> Self {
+        Self {
+            requests,
+            fallback: OllamaFallback::from_env(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    async fn ask_client(&self, session_id: &str, params: &CreateMessageParams) -> Option<Result<CreateMessageResult>> {
+        let params = serde_json::to_value(params).unwrap();
+        let result = self.requests
+            .send(session_id, "sampling/createMessage", params, self.timeout)
+            .await?;
+        Some(result.and_then(|result| {
+            serde_json::from_value(result).map_err(|e| anyhow!("Invalid sampling result from client: {}", e))
+        }))
+    }
+}
+
+/// Lets a plugin ask for an LLM completion: through the client that made the
+/// call when it supports sampling, otherwise through the Ollama fallback
+#[derive(Clone)]
+pub struct Sampler {
+    broker: Arc<SamplingBroker>,
+    /// Session to ask, if its client declared the sampling capability
+    session_id: Option<String>,
+}
+
+impl std::fmt::Debug for Sampler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sampler").field("session_id", &self.session_id).finish()
+    }
+}
+
+impl Sampler {
+    pub fn new(broker: Arc<SamplingBroker>, session_id: Option<String>) -> Self {
+        Self { broker, session_id }
+    }
+
+    pub async fn create_message(&self, params: CreateMessageParams) -> Result<CreateMessageResult> {
+        if let Some(session_id) = &self.session_id {
+            if let Some(result) = self.broker.ask_client(session_id, &params).await {
+                return result;
+            }
+        }
+        match &self.broker.fallback {
+            Some(fallback) => fallback.create_message(&params).await,
+            None => Err(anyhow!(
+                "Sampling is unavailable: the client does not support it and SAMPLING_OLLAMA_MODEL is not set"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mcp::notifications::NotificationDispatcher;
+
+    #[tokio::test]
+    async fn test_client_answers_sampling_request() {
+        let notifications = Arc::new(NotificationDispatcher::new());
+        let mut stream = notifications.subscribe("session");
+        let requests = Arc::new(ClientRequests::new(notifications));
+        let sampler = Sampler::new(Arc::new(SamplingBroker::new(requests.clone())), Some("session".to_string()));
+
+        let client = tokio::spawn(async move {
+            let request: Value = serde_json::from_str(&stream.recv().await.unwrap()).unwrap();
+            assert_eq!(request["method"], "sampling/createMessage");
+            assert_eq!(request["params"]["maxTokens"], 100);
+            requests.resolve(&request["id"], Ok(json!({
+                "role": "assistant",
+                "content": {"type": "text", "text": "A short summary"},
+                "model": "client-model",
+                "stopReason": "endTurn"
+            })))
+        });
+
+        let result = sampler
+            .create_message(CreateMessageParams {
+                messages: vec![SamplingMessage::user("Summarize this")],
+                system_prompt: None,
+                max_tokens: 100,
+                model_preferences: None,
+            })
+            .await
+            .unwrap();
+        assert!(client.await.unwrap());
+        assert_eq!(result.text(), "A short summary");
+        assert_eq!(result.model, "client-model");
+    }
+}
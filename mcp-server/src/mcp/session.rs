@@ -0,0 +1,599 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use super::budget::{BudgetConfig, BudgetExceeded, BudgetKind};
+use crate::context::DEFAULT_TENANT;
+use super::types::{ClientInfo, ToolDefinition};
+
+/// Tool calls remembered per session
+const MEMORY_LIMIT: usize = 50;
+
+/// Window the per-session rate limit is counted over
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+tokio::task_local! {
+    static CREDENTIAL: Option<String>;
+}
+
+/// Runs `handling` with the bearer token the client sent with the message,
+/// for an initialize request to find the client's tenant by, and for later
+/// requests to prove they come from the client that started the session
+pub async fn with_credential<F: Future>(token: Option<String>, handling: F) -> F::Output {
+    CREDENTIAL.scope(token, handling).await
+}
+
+/// The bearer token sent with the message being handled, if any
+pub fn credential() -> Option<String> {
+    CREDENTIAL.try_with(|token| token.clone()).ok().flatten()
+}
+
+/// A tool call made during a session
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCallRecord {
+    pub tool: String,
+    pub success: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Where a session is in the MCP lifecycle. Ending a session (`shutdown`,
+/// `sessions/end` or a closed connection) removes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionState {
+    /// Answered initialize, waiting for `notifications/initialized`
+    Initializing,
+    /// Handshake complete; all requests are accepted
+    Ready,
+}
+
+/// State kept for one client between its initialize request and the end of
+/// the session
+#[derive(Debug)]
+pub struct Session {
+    pub id: String,
+    pub client_info: Option<ClientInfo>,
+    pub protocol_version: String,
+    /// Tenant whose context data the session reads and writes
+    pub tenant: String,
+    /// SHA-256 of the bearer token the session was started with; every
+    /// later request has to present the same token
+    credential: Option<[u8; 32]>,
+    pub state: SessionState,
+    /// Whether the client declared the `sampling` capability
+    pub supports_sampling: bool,
+    /// Whether the client declared the `roots` capability
+    pub supports_roots: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+    pub request_count: u64,
+    /// Tool and plugin calls charged against the budget
+    pub tool_call_count: u64,
+    /// Bytes plugins exchanged with upstream services for this session
+    pub upstream_bytes: u64,
+    started: Instant,
+    memory: VecDeque<ToolCallRecord>,
+    window_start: Instant,
+    window_requests: u32,
+    cached_tools: Option<Vec<ToolDefinition>>,
+    /// The client's answer to `roots/list`, until it reports a change
+    roots: Option<Vec<PathBuf>>,
+}
+
+impl Session {
+    fn new(client_info: Option<ClientInfo>, protocol_version: String, tenant: String, credential: Option<&str>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            client_info,
+            protocol_version,
+            tenant,
+            credential: credential.map(digest),
+            state: SessionState::Initializing,
+            supports_sampling: false,
+            supports_roots: false,
+            created_at: now,
+            last_activity: now,
+            request_count: 0,
+            tool_call_count: 0,
+            upstream_bytes: 0,
+            started: Instant::now(),
+            memory: VecDeque::new(),
+            window_start: Instant::now(),
+            window_requests: 0,
+            cached_tools: None,
+            roots: None,
+        }
+    }
+
+    /// Whether `credential` is the token the session was started with
+    fn accepts(&self, credential: Option<&str>) -> bool {
+        self.credential == credential.map(digest)
+    }
+
+    fn summary(&self) -> SessionSummary {
+        SessionSummary {
+            id: self.id.clone(),
+            client_info: self.client_info.clone(),
+            protocol_version: self.protocol_version.clone(),
+            tenant: self.tenant.clone(),
+            state: self.state,
+            created_at: self.created_at,
+            last_activity: self.last_activity,
+            request_count: self.request_count,
+            tool_call_count: self.tool_call_count,
+            upstream_bytes: self.upstream_bytes,
+            recent_tool_calls: self.memory.iter().rev().take(5).cloned().collect(),
+        }
+    }
+}
+
+/// What `sessions/list` reports for each session
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSummary {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_info: Option<ClientInfo>,
+    pub protocol_version: String,
+    pub tenant: String,
+    pub state: SessionState,
+    pub created_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+    pub request_count: u64,
+    pub tool_call_count: u64,
+    pub upstream_bytes: u64,
+    /// Newest first
+    pub recent_tool_calls: Vec<ToolCallRecord>,
+}
+
+/// Refused because the client's credential belongs to no tenant
+#[derive(Debug, PartialEq)]
+pub struct UnknownCredential;
+
+#[derive(Debug, PartialEq)]
+pub enum SessionError {
+    NotFound,
+    /// The request's bearer token is not the one the session was started with
+    WrongCredential,
+    RateLimited { retry_after_secs: u64 },
+}
+
+/// Sessions of all clients connected to one server
+pub struct SessionManager {
+    sessions: Mutex<HashMap<String, Session>>,
+    /// Requests a session may make per minute; `None` means unlimited
+    rate_limit: Option<u32>,
+    budget: Option<BudgetConfig>,
+    /// Give each authenticated client its own tenant instead of sharing
+    /// the default
+    tenant_per_client: bool,
+    /// Tenant of each bearer token
+    tenant_tokens: HashMap<String, String>,
+}
+
+impl SessionManager {
+    pub fn new(rate_limit: Option<u32>) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            rate_limit,
+            budget: None,
+            tenant_per_client: false,
+            tenant_tokens: HashMap::new(),
+        }
+    }
+
+    /// Rate limit from `MCP_SESSION_RATE_LIMIT` (requests per minute) and
+    /// budget from `MCP_SESSION_BUDGET`; `MCP_TENANT_PER_CLIENT` turns on
+    /// per-client tenants, with the tokens in `MCP_TENANT_TOKENS`
+    pub fn from_env() -> Self {
+        let rate_limit = std::env::var("MCP_SESSION_RATE_LIMIT")
+            .ok()
+            .and_then(|limit| limit.parse().ok())
+            .filter(|limit| *limit > 0);
+        let mut manager = Self::new(rate_limit);
+        manager.budget = BudgetConfig::from_env();
+        manager.tenant_per_client = std::env::var("MCP_TENANT_PER_CLIENT")
+            .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+            .unwrap_or(false);
+        if let Ok(spec) = std::env::var("MCP_TENANT_TOKENS") {
+            match parse_tenant_tokens(&spec) {
+                Ok(tokens) => manager.set_tenant_tokens(tokens),
+                Err(e) => tracing::warn!("Ignoring MCP_TENANT_TOKENS: {}", e),
+            }
+        }
+        manager
+    }
+
+    pub fn set_rate_limit(&mut self, rate_limit: Option<u32>) {
+        self.rate_limit = rate_limit;
+    }
+
+    pub fn set_budget(&mut self, budget: Option<BudgetConfig>) {
+        self.budget = budget;
+    }
+
+    pub fn budget(&self) -> Option<&BudgetConfig> {
+        self.budget.as_ref()
+    }
+
+    pub fn set_tenant_per_client(&mut self, enabled: bool) {
+        self.tenant_per_client = enabled;
+    }
+
+    pub fn set_tenant_tokens(&mut self, tokens: HashMap<String, String>) {
+        self.tenant_tokens = tokens;
+    }
+
+    pub fn tenant_per_client(&self) -> bool {
+        self.tenant_per_client
+    }
+
+    /// The tenant a client belongs to. When tenants are per client that is
+    /// the tenant of the bearer token it presented; what the client says
+    /// about itself in initialize is never trusted. Clients without a token
+    /// share the default tenant, and an unknown token is refused.
+    pub fn tenant_for(&self, credential: Option<&str>) -> Result<String, UnknownCredential> {
+        match credential.filter(|_| self.tenant_per_client) {
+            None => Ok(DEFAULT_TENANT.to_string()),
+            Some(token) => self.tenant_tokens.iter()
+                .find(|(known, _)| same_token(token, known))
+                .map(|(_, tenant)| tenant.clone())
+                .ok_or(UnknownCredential),
+        }
+    }
+
+    /// Starts a session whose context data is kept in `tenant`, bound to the
+    /// bearer token it was started with, and returns its id
+    pub async fn create(
+        &self,
+        client_info: Option<ClientInfo>,
+        protocol_version: &str,
+        tenant: String,
+        credential: Option<&str>,
+    ) -> String {
+        let session = Session::new(client_info, protocol_version.to_string(), tenant, credential);
+        let id = session.id.clone();
+        self.sessions.lock().await.insert(id.clone(), session);
+        id
+    }
+
+    pub async fn set_supports_sampling(&self, id: &str, supported: bool) {
+        if let Some(session) = self.sessions.lock().await.get_mut(id) {
+            session.supports_sampling = supported;
+        }
+    }
+
+    pub async fn supports_sampling(&self, id: &str) -> bool {
+        self.sessions.lock().await.get(id).is_some_and(|session| session.supports_sampling)
+    }
+
+    pub async fn set_supports_roots(&self, id: &str, supported: bool) {
+        if let Some(session) = self.sessions.lock().await.get_mut(id) {
+            session.supports_roots = supported;
+        }
+    }
+
+    pub async fn supports_roots(&self, id: &str) -> bool {
+        self.sessions.lock().await.get(id).is_some_and(|session| session.supports_roots)
+    }
+
+    pub async fn cached_roots(&self, id: &str) -> Option<Vec<PathBuf>> {
+        self.sessions.lock().await.get(id)?.roots.clone()
+    }
+
+    pub async fn cache_roots(&self, id: &str, roots: Vec<PathBuf>) {
+        if let Some(session) = self.sessions.lock().await.get_mut(id) {
+            session.roots = Some(roots);
+        }
+    }
+
+    /// Forgets the client's roots so the next file access asks again
+    pub async fn clear_roots(&self, id: &str) {
+        if let Some(session) = self.sessions.lock().await.get_mut(id) {
+            session.roots = None;
+        }
+    }
+
+    pub async fn protocol_version(&self, id: &str) -> Option<String> {
+        Some(self.sessions.lock().await.get(id)?.protocol_version.clone())
+    }
+
+    pub async fn tenant(&self, id: &str) -> Option<String> {
+        Some(self.sessions.lock().await.get(id)?.tenant.clone())
+    }
+
+    pub async fn exists(&self, id: &str) -> bool {
+        self.sessions.lock().await.contains_key(id)
+    }
+
+    /// Whether the session exists and was started with `credential`
+    pub async fn owned_by(&self, id: &str, credential: Option<&str>) -> bool {
+        self.sessions.lock().await.get(id).is_some_and(|session| session.accepts(credential))
+    }
+
+    /// Moves the session to `Ready`; returns false if there is no such session
+    pub async fn mark_ready(&self, id: &str) -> bool {
+        match self.sessions.lock().await.get_mut(id) {
+            Some(session) => {
+                session.state = SessionState::Ready;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Records a request made with `credential` against the session,
+    /// enforcing the rate limit, and returns the session's lifecycle state
+    pub async fn touch(&self, id: &str, credential: Option<&str>) -> Result<SessionState, SessionError> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(id).ok_or(SessionError::NotFound)?;
+        if !session.accepts(credential) {
+            return Err(SessionError::WrongCredential);
+        }
+
+        if let Some(limit) = self.rate_limit {
+            let elapsed = session.window_start.elapsed();
+            if elapsed >= RATE_WINDOW {
+                session.window_start = Instant::now();
+                session.window_requests = 0;
+            } else if session.window_requests >= limit {
+                return Err(SessionError::RateLimited {
+                    retry_after_secs: (RATE_WINDOW - elapsed).as_secs().max(1),
+                });
+            }
+            session.window_requests += 1;
+        }
+
+        session.request_count += 1;
+        session.last_activity = Utc::now();
+        Ok(session.state)
+    }
+
+    /// Charges one tool call to the session's budget, refusing it once any
+    /// limit is used up. Returns how much longer the session may run calls,
+    /// if that is limited.
+    pub async fn charge_tool_call(&self, id: &str) -> Result<Option<Duration>, BudgetExceeded> {
+        let Some(budget) = &self.budget else {
+            return Ok(None);
+        };
+        let mut sessions = self.sessions.lock().await;
+        let Some(session) = sessions.get_mut(id) else {
+            return Ok(None);
+        };
+
+        if let Some(limit) = budget.max_tool_calls {
+            if session.tool_call_count >= limit {
+                return Err(BudgetExceeded { budget: BudgetKind::ToolCalls, limit, used: session.tool_call_count });
+            }
+        }
+        if let Some(limit) = budget.max_upstream_bytes {
+            if session.upstream_bytes >= limit {
+                return Err(BudgetExceeded { budget: BudgetKind::UpstreamBytes, limit, used: session.upstream_bytes });
+            }
+        }
+        let remaining = match budget.max_duration {
+            Some(limit) => {
+                let elapsed = session.started.elapsed();
+                if elapsed >= limit {
+                    return Err(BudgetExceeded {
+                        budget: BudgetKind::Seconds,
+                        limit: limit.as_secs(),
+                        used: elapsed.as_secs(),
+                    });
+                }
+                Some(limit - elapsed)
+            }
+            None => None,
+        };
+
+        session.tool_call_count += 1;
+        Ok(remaining)
+    }
+
+    pub async fn add_upstream_bytes(&self, id: &str, bytes: u64) {
+        if let Some(session) = self.sessions.lock().await.get_mut(id) {
+            session.upstream_bytes += bytes;
+        }
+    }
+
+    pub async fn record_tool_call(&self, id: &str, tool: &str, success: bool) {
+        if let Some(session) = self.sessions.lock().await.get_mut(id) {
+            if session.memory.len() == MEMORY_LIMIT {
+                session.memory.pop_front();
+            }
+            session.memory.push_back(ToolCallRecord {
+                tool: tool.to_string(),
+                success,
+                timestamp: Utc::now(),
+            });
+        }
+    }
+
+    pub async fn cached_tools(&self, id: &str) -> Option<Vec<ToolDefinition>> {
+        self.sessions.lock().await.get(id)?.cached_tools.clone()
+    }
+
+    pub async fn cache_tools(&self, id: &str, tools: Vec<ToolDefinition>) {
+        if let Some(session) = self.sessions.lock().await.get_mut(id) {
+            session.cached_tools = Some(tools);
+        }
+    }
+
+    /// Drops every session's cached tool list after the tools changed
+    pub async fn clear_tool_caches(&self) {
+        for session in self.sessions.lock().await.values_mut() {
+            session.cached_tools = None;
+        }
+    }
+
+    /// Drops every session's cached tool list and roots, so both are
+    /// fetched again
+    pub async fn clear_caches(&self) {
+        for session in self.sessions.lock().await.values_mut() {
+            session.cached_tools = None;
+            session.roots = None;
+        }
+    }
+
+    pub async fn summary(&self, id: &str) -> Option<SessionSummary> {
+        Some(self.sessions.lock().await.get(id)?.summary())
+    }
+
+    /// Summaries of all sessions, oldest first
+    pub async fn list(&self) -> Vec<SessionSummary> {
+        let sessions = self.sessions.lock().await;
+        let mut summaries: Vec<SessionSummary> = sessions.values().map(Session::summary).collect();
+        summaries.sort_by_key(|summary| summary.created_at);
+        summaries
+    }
+
+    /// Ends a session; returns false if there was no such session
+    pub async fn end(&self, id: &str) -> bool {
+        self.sessions.lock().await.remove(id).is_some()
+    }
+}
+
+/// Tenant tokens written as `tenant=token,...`
+fn parse_tenant_tokens(spec: &str) -> Result<HashMap<String, String>, String> {
+    let mut tokens = HashMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let (tenant, token) = entry.split_once('=')
+            .map(|(tenant, token)| (tenant.trim().to_lowercase(), token.trim()))
+            .filter(|(tenant, token)| !tenant.is_empty() && !token.is_empty())
+            .ok_or_else(|| format!("expected tenant=token, got {}", entry))?;
+        if tokens.insert(token.to_string(), tenant).is_some() {
+            return Err("the same token is given to two tenants".to_string());
+        }
+    }
+    Ok(tokens)
+}
+
+fn digest(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+/// Compares tokens in time that does not depend on where they differ; every
+/// token check, session and admin, goes through it
+pub(crate) fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len() && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_token() {
+        assert!(same_token("s3cret", "s3cret"));
+        assert!(!same_token("s3cres", "s3cret"));
+        assert!(!same_token("s3cret!", "s3cret"));
+        assert!(!same_token("", "s3cret"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_per_session() {
+        let manager = SessionManager::new(Some(2));
+        let first = manager.create(None, "2024-11-05", DEFAULT_TENANT.to_string(), None).await;
+        let second = manager.create(None, "2024-11-05", DEFAULT_TENANT.to_string(), None).await;
+
+        assert!(manager.touch(&first, None).await.is_ok());
+        assert!(manager.touch(&first, None).await.is_ok());
+        assert!(matches!(
+            manager.touch(&first, None).await,
+            Err(SessionError::RateLimited { .. })
+        ));
+        // Other sessions have their own budget
+        assert!(manager.touch(&second, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_budget_refuses_calls_once_used_up() {
+        let mut manager = SessionManager::new(None);
+        manager.set_budget(Some("calls=2,bytes=100".parse().unwrap()));
+        let id = manager.create(None, "2024-11-05", DEFAULT_TENANT.to_string(), None).await;
+
+        assert_eq!(manager.charge_tool_call(&id).await, Ok(None));
+        manager.add_upstream_bytes(&id, 60).await;
+        assert!(manager.charge_tool_call(&id).await.is_ok());
+        assert_eq!(
+            manager.charge_tool_call(&id).await,
+            Err(BudgetExceeded { budget: BudgetKind::ToolCalls, limit: 2, used: 2 })
+        );
+
+        let other = manager.create(None, "2024-11-05", DEFAULT_TENANT.to_string(), None).await;
+        manager.add_upstream_bytes(&other, 100).await;
+        assert_eq!(
+            manager.charge_tool_call(&other).await,
+            Err(BudgetExceeded { budget: BudgetKind::UpstreamBytes, limit: 100, used: 100 })
+        );
+        let summary = manager.list().await.into_iter().find(|summary| summary.id == id).unwrap();
+        assert_eq!(summary.tool_call_count, 2);
+        assert_eq!(summary.upstream_bytes, 60);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_follows_the_credential_when_per_client() {
+        let mut manager = SessionManager::new(None);
+        manager.set_tenant_tokens(parse_tenant_tokens("Alpha=token-a, beta=token-b").unwrap());
+        assert_eq!(manager.tenant_for(Some("token-a")).as_deref(), Ok(DEFAULT_TENANT));
+
+        manager.set_tenant_per_client(true);
+        assert_eq!(manager.tenant_for(Some("token-a")).as_deref(), Ok("alpha"));
+        assert_eq!(manager.tenant_for(Some("token-b")).as_deref(), Ok("beta"));
+        assert_eq!(manager.tenant_for(None).as_deref(), Ok(DEFAULT_TENANT));
+        assert_eq!(manager.tenant_for(Some("token-c")), Err(UnknownCredential));
+
+        let alpha = manager.create(None, "2024-11-05", "alpha".to_string(), None).await;
+        assert_eq!(manager.tenant(&alpha).await.as_deref(), Some("alpha"));
+        assert!(parse_tenant_tokens("alpha=x,beta=x").is_err());
+        assert!(parse_tenant_tokens("alpha").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_session_only_accepts_the_token_it_was_started_with() {
+        let manager = SessionManager::new(None);
+        let alpha = manager.create(None, "2024-11-05", "alpha".to_string(), Some("token-a")).await;
+        let open = manager.create(None, "2024-11-05", DEFAULT_TENANT.to_string(), None).await;
+
+        assert!(manager.touch(&alpha, Some("token-a")).await.is_ok());
+        assert_eq!(manager.touch(&alpha, Some("token-b")).await, Err(SessionError::WrongCredential));
+        assert_eq!(manager.touch(&alpha, None).await, Err(SessionError::WrongCredential));
+        assert_eq!(manager.touch(&open, Some("token-a")).await, Err(SessionError::WrongCredential));
+        assert!(manager.owned_by(&alpha, Some("token-a")).await);
+        assert!(!manager.owned_by(&alpha, Some("token-b")).await);
+        assert!(manager.owned_by(&open, None).await);
+    }
+
+    #[tokio::test]
+    async fn test_memory_keeps_recent_tool_calls() {
+        let manager = SessionManager::new(None);
+        let id = manager.create(None, "2024-11-05", DEFAULT_TENANT.to_string(), None).await;
+        for i in 0..MEMORY_LIMIT + 3 {
+            manager.record_tool_call(&id, &format!("tool{}", i), true).await;
+        }
+
+        let summary = manager.list().await.remove(0);
+        assert_eq!(summary.recent_tool_calls.len(), 5);
+        assert_eq!(summary.recent_tool_calls[0].tool, format!("tool{}", MEMORY_LIMIT + 2));
+
+        assert!(manager.end(&id).await);
+        assert_eq!(manager.touch(&id, None).await, Err(SessionError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_session_becomes_ready() {
+        let manager = SessionManager::new(None);
+        let id = manager.create(None, "2024-11-05", DEFAULT_TENANT.to_string(), None).await;
+        assert_eq!(manager.touch(&id, None).await, Ok(SessionState::Initializing));
+
+        assert!(manager.mark_ready(&id).await);
+        assert_eq!(manager.touch(&id, None).await, Ok(SessionState::Ready));
+        assert!(!manager.mark_ready("missing").await);
+    }
+}
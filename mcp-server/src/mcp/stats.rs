@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often one tool has been called since the server started, as
+/// `stats/tools` reports it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolUsage {
+    pub tool: String,
+    pub calls: u64,
+    pub errors: u64,
+    /// Share of calls that failed, from 0 to 1
+    pub error_rate: f64,
+    pub average_latency_ms: f64,
+    pub last_called_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct Counters {
+    calls: u64,
+    errors: u64,
+    total_latency: Duration,
+    last_called_at: DateTime<Utc>,
+}
+
+/// Per-tool call counts, failures and latency, kept for the life of the
+/// server
+#[derive(Debug, Default)]
+pub struct ToolStats {
+    tools: Mutex<HashMap<String, Counters>>,
+}
+
+impl ToolStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, tool: &str, latency: Duration, success: bool) {
+        let mut tools = self.tools.lock().unwrap();
+        let counters = tools.entry(tool.to_string()).or_insert_with(|| Counters {
+            calls: 0,
+            errors: 0,
+            total_latency: Duration::ZERO,
+            last_called_at: Utc::now(),
+        });
+        counters.calls += 1;
+        if !success {
+            counters.errors += 1;
+        }
+        counters.total_latency += latency;
+        counters.last_called_at = Utc::now();
+    }
+
+    /// Every tool called so far, most called first
+    pub fn snapshot(&self) -> Vec<ToolUsage> {
+        let tools = self.tools.lock().unwrap();
+        let mut usage: Vec<ToolUsage> = tools
+            .iter()
+            .map(|(tool, counters)| ToolUsage {
+                tool: tool.clone(),
+                calls: counters.calls,
+                errors: counters.errors,
+                error_rate: counters.errors as f64 / counters.calls as f64,
+                average_latency_ms: counters.total_latency.as_secs_f64() * 1000.0 / counters.calls as f64,
+                last_called_at: counters.last_called_at,
+            })
+            .collect();
+        usage.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.tool.cmp(&b.tool)));
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_averages_latency_and_errors() {
+        let stats = ToolStats::new();
+        stats.record("http", Duration::from_millis(100), true);
+        stats.record("http", Duration::from_millis(300), false);
+        stats.record("http", Duration::from_millis(200), true);
+        stats.record("system_info", Duration::from_millis(10), true);
+
+        let usage = stats.snapshot();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].tool, "http");
+        assert_eq!(usage[0].calls, 3);
+        assert_eq!(usage[0].errors, 1);
+        assert!((usage[0].error_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert!((usage[0].average_latency_ms - 200.0).abs() < 1e-9);
+        assert_eq!(usage[1].tool, "system_info");
+        assert_eq!(usage[1].error_rate, 0.0);
+    }
+}
@@ -0,0 +1,305 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use tracing::{info, warn};
+
+use super::sampling::{CreateMessageParams, Sampler, SamplingMessage};
+use super::types::ContentBlock;
+
+/// Keys whose values `redact` blanks out unless `redact_keys` says otherwise
+const DEFAULT_REDACT_KEYS: &[&str] = &["password", "token", "access_token", "api_key", "secret", "authorization"];
+
+/// Rewrites a tool's text output before it reaches the client
+#[async_trait]
+pub trait ResultTransformer: Send + Sync {
+    /// How the step is named in the spec and in `_meta.resultTransforms`
+    fn name(&self) -> &'static str;
+
+    async fn transform(&self, text: &str, sampler: &Sampler) -> Result<String>;
+}
+
+/// Cuts the text to `max_chars` characters and says how much was left out
+pub struct Truncate {
+    pub max_chars: usize,
+}
+
+#[async_trait]
+impl ResultTransformer for Truncate {
+    fn name(&self) -> &'static str {
+        "truncate"
+    }
+
+    async fn transform(&self, text: &str, _sampler: &Sampler) -> Result<String> {
+        let total = text.chars().count();
+        if total <= self.max_chars {
+            return Ok(text.to_string());
+        }
+        let kept: String = text.chars().take(self.max_chars).collect();
+        Ok(format!("{}\n[truncated {} of {} characters]", kept, total - self.max_chars, total))
+    }
+}
+
+/// Replaces the text with a summary from the client's model or, when the
+/// client cannot sample, the Ollama fallback (`SAMPLING_OLLAMA_MODEL`)
+pub struct Summarize {
+    pub max_words: u64,
+}
+
+#[async_trait]
+impl ResultTransformer for Summarize {
+    fn name(&self) -> &'static str {
+        "summarize"
+    }
+
+    async fn transform(&self, text: &str, sampler: &Sampler) -> Result<String> {
+        let result = sampler
+            .create_message(CreateMessageParams {
+                messages: vec![SamplingMessage::user(text)],
+                system_prompt: Some(format!(
+                    "This is the output of a tool call. Summarize it in at most {} words, keeping names, \
+                     identifiers and numbers the reader may need. Reply with the summary only.",
+                    self.max_words
+                )),
+                max_tokens: (self.max_words * 2) as u32 + 50,
+                model_preferences: None,
+            })
+            .await?;
+        Ok(format!("[summary by {}]\n{}", result.model, result.text()))
+    }
+}
+
+/// Blanks out the values of sensitive keys in JSON output; other text is
+/// left alone
+pub struct Redact {
+    pub keys: Vec<String>,
+}
+
+impl Redact {
+    fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object.iter_mut() {
+                    if self.keys.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+                        *value = json!("[redacted]");
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item)),
+            _ => {}
+        }
+    }
+}
+
+#[async_trait]
+impl ResultTransformer for Redact {
+    fn name(&self) -> &'static str {
+        "redact"
+    }
+
+    async fn transform(&self, text: &str, _sampler: &Sampler) -> Result<String> {
+        let Ok(mut value) = serde_json::from_str::<Value>(text) else {
+            return Ok(text.to_string());
+        };
+        self.redact(&mut value);
+        Ok(serde_json::to_string_pretty(&value)?)
+    }
+}
+
+/// Which steps run on oversized tool output, parsed from a spec such as
+/// `steps=redact+summarize+truncate,max_chars=8000,max_words=200,redact_keys=password+token`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformConfig {
+    /// In the order they run
+    pub steps: Vec<String>,
+    /// Outputs up to this many characters are passed through untouched
+    pub max_chars: usize,
+    /// Length of summaries
+    pub max_words: u64,
+    pub redact_keys: Vec<String>,
+}
+
+impl Default for TransformConfig {
+    fn default() -> Self {
+        Self {
+            steps: vec!["truncate".to_string()],
+            max_chars: 8000,
+            max_words: 200,
+            redact_keys: DEFAULT_REDACT_KEYS.iter().map(|key| key.to_string()).collect(),
+        }
+    }
+}
+
+impl FromStr for TransformConfig {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut config = TransformConfig::default();
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got '{}'", setting))?;
+            let list = |value: &str| -> Vec<String> {
+                value.split('+').map(str::trim).filter(|v| !v.is_empty()).map(String::from).collect()
+            };
+            let invalid = |e: &dyn std::fmt::Display| format!("Invalid {}: {}", key, e);
+            match key.trim() {
+                "steps" => {
+                    config.steps = list(value);
+                    if let Some(unknown) = config.steps.iter().find(|step| !matches!(step.as_str(), "truncate" | "summarize" | "redact")) {
+                        return Err(format!("Unknown result transform '{}'", unknown));
+                    }
+                }
+                "max_chars" => config.max_chars = value.trim().parse().map_err(|e| invalid(&e))?,
+                "max_words" => config.max_words = value.trim().parse().map_err(|e| invalid(&e))?,
+                "redact_keys" => config.redact_keys = list(value),
+                other => return Err(format!("Unknown result transform setting '{}'", other)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl TransformConfig {
+    /// Pipeline from `MCP_RESULT_TRANSFORMS`, if set and valid
+    pub fn from_env() -> Option<Self> {
+        let spec = std::env::var("MCP_RESULT_TRANSFORMS").ok()?;
+        match spec.parse() {
+            Ok(config) => Some(config),
+            Err(e) => {
+                warn!("Ignoring MCP_RESULT_TRANSFORMS: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Runs the configured steps over tool output longer than `max_chars`,
+/// stopping as soon as it fits
+pub struct ResultPipeline {
+    max_chars: usize,
+    steps: Vec<Box<dyn ResultTransformer>>,
+}
+
+impl ResultPipeline {
+    pub fn new(config: &TransformConfig) -> Self {
+        let steps = config
+            .steps
+            .iter()
+            .map(|step| -> Box<dyn ResultTransformer> {
+                match step.as_str() {
+                    "summarize" => Box::new(Summarize { max_words: config.max_words }),
+                    "redact" => Box::new(Redact { keys: config.redact_keys.clone() }),
+                    _ => Box::new(Truncate { max_chars: config.max_chars }),
+                }
+            })
+            .collect();
+        Self { max_chars: config.max_chars, steps }
+    }
+
+    /// Transforms oversized text blocks in place and drops oversized
+    /// structured blocks, whose text alongside them carries the transformed
+    /// output. Returns what was done, for `_meta.resultTransforms`, or `None`
+    /// if everything fit.
+    pub async fn apply(&self, content: &mut Vec<ContentBlock>, sampler: &Sampler) -> Option<Value> {
+        let mut applied: Vec<&'static str> = Vec::new();
+        let mut original_chars = 0;
+        let blocks = content.len();
+        content.retain(|block| match block {
+            ContentBlock::Text { .. } => true,
+            ContentBlock::Json { json } => json.to_string().chars().count() <= self.max_chars,
+            ContentBlock::Data { data, .. } => data.chars().count() <= self.max_chars,
+        });
+        let dropped = blocks - content.len();
+        for block in content.iter_mut() {
+            let ContentBlock::Text { text } = block else { continue };
+            let length = text.chars().count();
+            if length <= self.max_chars {
+                continue;
+            }
+            original_chars += length;
+            for step in &self.steps {
+                match step.transform(text, sampler).await {
+                    Ok(transformed) => {
+                        *text = transformed;
+                        if !applied.contains(&step.name()) {
+                            applied.push(step.name());
+                        }
+                    }
+                    // A failed step leaves the text to the next one
+                    Err(e) => warn!("Result transform {} failed: {:#}", step.name(), e),
+                }
+                if text.chars().count() <= self.max_chars {
+                    break;
+                }
+            }
+        }
+        if original_chars == 0 && dropped == 0 {
+            return None;
+        }
+        info!("Transformed {} characters of tool output with {:?}, dropping {} structured blocks", original_chars, applied, dropped);
+        Some(json!({ "steps": applied, "originalChars": original_chars, "droppedBlocks": dropped }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::client_requests::ClientRequests;
+    use crate::mcp::notifications::NotificationDispatcher;
+    use crate::mcp::sampling::SamplingBroker;
+    use std::sync::Arc;
+
+    /// A sampler with no client to ask; it only works if Ollama is configured
+    fn sampler() -> Sampler {
+        let requests = Arc::new(ClientRequests::new(Arc::new(NotificationDispatcher::new())));
+        Sampler::new(Arc::new(SamplingBroker::new(requests)), None)
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let config: TransformConfig = "steps=redact+truncate,max_chars=100,redact_keys=pin".parse().unwrap();
+        assert_eq!(config.steps, ["redact", "truncate"]);
+        assert_eq!(config.max_chars, 100);
+        assert_eq!(config.redact_keys, ["pin"]);
+        assert!("steps=shorten".parse::<TransformConfig>().is_err());
+        assert!("max_chars=lots".parse::<TransformConfig>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_stops_once_output_fits() {
+        let config: TransformConfig = "steps=redact+truncate,max_chars=60".parse().unwrap();
+        let pipeline = ResultPipeline::new(&config);
+        let output = json!({ "user": "ada", "token": "x".repeat(100) }).to_string();
+        let mut content = vec![ContentBlock::text(&output), ContentBlock::text("short")];
+        content.push(ContentBlock::Json { json: serde_json::from_str(&output).unwrap() });
+        content.push(ContentBlock::Json { json: json!({ "user": "ada" }) });
+
+        let meta = pipeline.apply(&mut content, &sampler()).await.unwrap();
+        assert_eq!(meta["steps"], json!(["redact"]));
+        assert_eq!(meta["originalChars"], output.len());
+        assert_eq!(meta["droppedBlocks"], 1);
+        assert_eq!(content.len(), 3);
+        let text = content[0].as_text().unwrap();
+        assert!(text.contains("[redacted]") && text.contains("ada"));
+        assert_eq!(content[1].as_text(), Some("short"));
+        assert!(matches!(&content[2], ContentBlock::Json { json } if json["user"] == "ada"));
+    }
+
+    #[tokio::test]
+    async fn test_truncate_reports_what_was_cut() {
+        let config: TransformConfig = "steps=summarize+truncate,max_chars=10".parse().unwrap();
+        let pipeline = ResultPipeline::new(&config);
+        let mut content = vec![ContentBlock::text(&"é".repeat(25))];
+
+        // Without a model to summarize, truncation still applies
+        if std::env::var("SAMPLING_OLLAMA_MODEL").is_err() {
+            let meta = pipeline.apply(&mut content, &sampler()).await.unwrap();
+            assert_eq!(meta["steps"], json!(["truncate"]));
+            assert_eq!(content[0].as_text().unwrap(), format!("{}\n[truncated 15 of 25 characters]", "é".repeat(10)));
+        }
+        assert!(pipeline.apply(&mut vec![ContentBlock::text("fits")], &sampler()).await.is_none());
+    }
+}
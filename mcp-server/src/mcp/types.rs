@@ -1,15 +1,121 @@
-use serde::{Deserialize, Serialize};
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::value::RawValue;
 use serde_json::Value;
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcRequest {
     pub jsonrpc: String,
+    /// Left out for notifications
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<Value>,
     pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub params: Option<Value>,
 }
 
+impl JsonRpcRequest {
+    /// The params as `T`, read in place rather than from a copy; missing
+    /// params are read as `missing`
+    pub fn params_as<T: DeserializeOwned>(&self, missing: &Value) -> serde_json::Result<T> {
+        T::deserialize(self.params.as_ref().unwrap_or(missing))
+    }
+}
+
+/// The top-level fields of an incoming message, borrowed from it and left
+/// unparsed, so requests, notifications and replies can be told apart
+/// without building a `Value` of the whole message
+#[derive(Debug, Deserialize)]
+pub struct RawMessage<'a> {
+    #[serde(default, borrow)]
+    pub jsonrpc: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "present")]
+    pub id: Option<&'a RawValue>,
+    #[serde(default, borrow)]
+    pub method: Option<Cow<'a, str>>,
+    #[serde(default, borrow)]
+    pub params: Option<&'a RawValue>,
+    #[serde(default, borrow, deserialize_with = "present")]
+    pub result: Option<&'a RawValue>,
+    #[serde(default, borrow, deserialize_with = "present")]
+    pub error: Option<&'a RawValue>,
+}
+
+/// Keeps a field that is `null`, since a reply's result may be, and a
+/// request with a null id is not a notification
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<&'de RawValue>, D::Error> {
+    <&RawValue>::deserialize(deserializer).map(Some)
+}
+
+/// Whether a raw id is a string or a number
+fn is_id(id: &RawValue) -> bool {
+    id.get().starts_with(|c: char| c == '"' || c == '-' || c.is_ascii_digit())
+}
+
+impl<'a> RawMessage<'a> {
+    pub fn parse(message: &'a str) -> serde_json::Result<Self> {
+        serde_json::from_str(message)
+    }
+
+    /// A reply to a request the server sent, such as roots/list
+    pub fn is_reply(&self) -> bool {
+        self.method.is_none() && (self.result.is_some() || self.error.is_some())
+    }
+
+    /// A request the sender expects an answer to
+    pub fn is_request(&self) -> bool {
+        self.method.is_some() && self.id.is_some()
+    }
+
+    /// Checks the envelope against JSON-RPC 2.0: the version is "2.0", a
+    /// request's id is a string or number, params are structured, and a
+    /// reply has exactly one of result and error. Returns what is wrong.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.jsonrpc.as_deref() != Some("2.0") {
+            return Err("jsonrpc must be \"2.0\"");
+        }
+        if self.method.is_some() {
+            if self.id.is_some_and(|id| !is_id(id)) {
+                return Err("id must be a string or number");
+            }
+            if self.params.is_some_and(|params| !params.get().starts_with(['{', '['])) {
+                return Err("params must be an object or array");
+            }
+            if self.result.is_some() || self.error.is_some() {
+                return Err("a request cannot have a result or error");
+            }
+            return Ok(());
+        }
+        // Replies to requests that could not be read have a null id
+        if self.id.is_some_and(|id| id.get() != "null" && !is_id(id)) {
+            return Err("id must be a string, number or null");
+        }
+        match (self.result.is_some(), self.error.is_some()) {
+            (false, false) => Err("method is missing"),
+            (true, true) => Err("a reply cannot have both result and error"),
+            _ => Ok(()),
+        }
+    }
+
+    /// The id, if it is one a response can carry
+    pub fn valid_id(&self) -> Option<Value> {
+        self.id.filter(|id| is_id(id)).and_then(|id| serde_json::from_str(id.get()).ok())
+    }
+
+    /// The message as a request or notification, parsing only its id and
+    /// params
+    pub fn into_request(self) -> serde_json::Result<JsonRpcRequest> {
+        Ok(JsonRpcRequest {
+            jsonrpc: self.jsonrpc.ok_or_else(|| serde_json::Error::missing_field("jsonrpc"))?.into_owned(),
+            id: self.id.map(|id| serde_json::from_str(id.get())).transpose()?,
+            method: self.method.ok_or_else(|| serde_json::Error::missing_field("method"))?.into_owned(),
+            params: self.params.map(|params| serde_json::from_str(params.get())).transpose()?,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcResponse {
     pub jsonrpc: String,
@@ -20,27 +126,27 @@ pub struct JsonRpcResponse {
     pub error: Option<JsonRpcError>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct JsonRpcError {
-    pub code: i32,
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<Value>,
-}
+pub use mcp_core::JsonRpcError;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InitializeParams {
     #[serde(rename = "protocolVersion")]
     pub protocol_version: String,
+    #[serde(default)]
     pub capabilities: ClientCapabilities,
     #[serde(rename = "clientInfo")]
     pub client_info: ClientInfo,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ClientCapabilities {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<ToolCapabilities>,
+    /// Present when the client answers `sampling/createMessage`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roots: Option<Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +168,13 @@ pub struct InitializeResult {
 pub struct Capabilities {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<ToolCapabilities>,
+    /// Present when the server sends `notifications/message`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,12 +194,78 @@ pub struct ToolsListResult {
     pub tools: Vec<ToolDefinition>,
 }
 
+/// A tool as `tools/list` lists it. The schemas are written `inputSchema`
+/// and `outputSchema`, as MCP spells them; `input_schema` and
+/// `output_schema` are accepted too, since older bridges and clients send
+/// those.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolDefinition {
     pub name: String,
     pub description: String,
-    #[serde(rename = "inputSchema")]
+    #[serde(rename = "inputSchema", alias = "input_schema")]
     pub input_schema: Value,
+    /// Shape of the result's `structuredContent`, when the tool declares one
+    #[serde(rename = "outputSchema", alias = "output_schema", default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    /// Set on tools registered in several versions; the listed name is then
+    /// `name@version`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<Deprecation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<ToolTag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+/// What kind of side effects and cost to expect from a tool, for clients
+/// that filter or confirm tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ToolTag {
+    /// Only reads; calling it changes nothing
+    ReadOnly,
+    /// May change or delete state outside the server
+    Destructive,
+    /// Talks to other services over the network
+    Network,
+    /// Usually takes seconds rather than milliseconds
+    Slow,
+}
+
+/// Hints about a tool's behaviour, as in MCP tool annotations. Hints are not
+/// guarantees; clients should not rely on them for security decisions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+impl ToolAnnotations {
+    /// The hints `tags` imply; `None` without tags
+    pub fn from_tags(tags: &[ToolTag]) -> Option<Self> {
+        if tags.is_empty() {
+            return None;
+        }
+        let read_only = tags.contains(&ToolTag::ReadOnly);
+        Some(Self {
+            read_only_hint: Some(read_only),
+            // Only meaningful for tools that write
+            destructive_hint: (!read_only).then(|| tags.contains(&ToolTag::Destructive)),
+            open_world_hint: Some(tags.contains(&ToolTag::Network)),
+            ..Self::default()
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -94,11 +273,79 @@ pub struct ToolCallParams {
     pub name: String,
     #[serde(default)]
     pub arguments: HashMap<String, Value>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ToolCallMeta>,
+}
+
+impl ToolCallParams {
+    pub fn priority(&self) -> Priority {
+        self.meta.as_ref().map(|meta| meta.priority).unwrap_or_default()
+    }
+
+    /// How long the caller waits for the result
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        self.meta.as_ref()?.timeout_ms.map(std::time::Duration::from_millis)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolCallMeta {
+    #[serde(default)]
+    pub priority: Priority,
+    /// Milliseconds from when the server receives the call until the caller
+    /// gives up on it; the call is stopped then. Relative, so the clocks of
+    /// caller and server need not agree.
+    #[serde(rename = "timeoutMs", default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Who is waiting on a tool call. When a tool is at its concurrency limit,
+/// queued interactive calls run before queued background ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// A client waiting for the result
+    #[default]
+    Interactive,
+    /// Scheduled jobs and automations
+    Background,
+}
+
+/// Why a tool or capability is on its way out, and what replaces it. Calls
+/// still work but their results carry it as `_meta.deprecation`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deprecation {
+    pub message: String,
+    /// Name of the tool or capability to use instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCallResult {
     pub content: Vec<ContentBlock>,
+    /// The tool's JSON output when it is an object, for clients of MCP
+    /// revisions that read structured results; it stays in `content` too
+    #[serde(rename = "structuredContent", default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    /// Set when the tool ran and failed; the content says why
+    #[serde(rename = "isError", default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl ToolCallResult {
+    /// A successful result; its first `json` block holding an object becomes
+    /// the structured content
+    pub fn new(content: Vec<ContentBlock>) -> Self {
+        let structured_content = ContentBlock::find_structured(&content).cloned();
+        Self { content, structured_content, is_error: false }
+    }
+
+    /// The result of a tool that ran and failed with `message`
+    pub fn error(message: &str) -> Self {
+        Self { content: vec![ContentBlock::text(message)], structured_content: None, is_error: true }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -106,6 +353,16 @@ pub struct ToolCallResult {
 pub enum ContentBlock {
     #[serde(rename = "text")]
     Text { text: String },
+    /// Structured output, so clients don't have to parse it back out of text
+    #[serde(rename = "json")]
+    Json { json: Value },
+    /// Output in another format; `data` is base64 unless the MIME type is textual
+    #[serde(rename = "data")]
+    Data {
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+        data: String,
+    },
 }
 
 impl ContentBlock {
@@ -114,6 +371,43 @@ impl ContentBlock {
             text: content.to_string(),
         }
     }
+
+    /// A JSON result as a pretty-printed text block, for clients that only
+    /// read text, followed by the same value as a `json` block
+    pub fn structured(value: Value) -> Vec<Self> {
+        let text = serde_json::to_string_pretty(&value).unwrap_or_else(|_| value.to_string());
+        vec![Self::Text { text }, Self::Json { json: value }]
+    }
+
+    /// A plugin's result as content. Binary output the plugin returns as
+    /// `data_base64` with its `mime_type`, such as audio, becomes a `data`
+    /// block of its own rather than a string inside the JSON.
+    pub fn from_plugin_data(mut value: Value) -> Vec<Self> {
+        let (Value::String(_), Value::String(mime_type)) = (&value["data_base64"], &value["mime_type"]) else {
+            return Self::structured(value);
+        };
+        let mime_type = mime_type.clone();
+        let data = value["data_base64"].take();
+        value.as_object_mut().map(|object| object.remove("data_base64"));
+        let mut content = Self::structured(value);
+        content.push(Self::Data { mime_type, data: data.as_str().unwrap_or_default().to_string() });
+        content
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text { text } => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The first `json` block of `content` that holds an object
+    pub fn find_structured(content: &[Self]) -> Option<&Value> {
+        content.iter().find_map(|block| match block {
+            Self::Json { json } if json.is_object() => Some(json),
+            _ => None,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -158,6 +452,51 @@ mod tests {
         assert!(request.params.is_none());
     }
 
+    #[test]
+    fn test_raw_message_kinds() {
+        let call = r#"{"jsonrpc":"2.0","id":"a\"1","method":"tools/call","params":{"name":"echo"}}"#;
+        let message = RawMessage::parse(call).unwrap();
+        assert!(message.is_request() && !message.is_reply());
+        let request = message.into_request().unwrap();
+        assert_eq!(request.id, Some(json!("a\"1")));
+        assert_eq!(request.params, Some(json!({"name": "echo"})));
+        assert_eq!(request.params_as::<ToolCallParams>(&Value::Null).unwrap().name, "echo");
+
+        let notification = RawMessage::parse(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).unwrap();
+        assert!(!notification.is_request() && !notification.is_reply());
+        assert!(notification.validate().is_ok());
+
+        // A null id makes a request, just not a valid one
+        let null_id = RawMessage::parse(r#"{"jsonrpc":"2.0","id":null,"method":"ping"}"#).unwrap();
+        assert!(null_id.is_request());
+        assert_eq!(null_id.validate(), Err("id must be a string or number"));
+        assert_eq!(null_id.valid_id(), None);
+
+        // A null result is still a reply
+        let reply = RawMessage::parse(r#"{"jsonrpc":"2.0","id":7,"result":null}"#).unwrap();
+        assert!(reply.is_reply());
+        assert_eq!(reply.result.unwrap().get(), "null");
+
+        let anonymous = RawMessage::parse(r#"{"id":1,"method":"ping"}"#).unwrap();
+        assert!(anonymous.into_request().is_err());
+        assert!(RawMessage::parse("[1]").is_err());
+    }
+
+    #[test]
+    fn test_raw_message_validate() {
+        let invalid = |message: &str| RawMessage::parse(message).unwrap().validate().unwrap_err();
+        assert_eq!(invalid(r#"{"jsonrpc":"1.0","id":1,"method":"ping"}"#), "jsonrpc must be \"2.0\"");
+        assert_eq!(invalid(r#"{"id":1,"method":"ping"}"#), "jsonrpc must be \"2.0\"");
+        assert_eq!(invalid(r#"{"jsonrpc":"2.0","id":{"a":1},"method":"ping"}"#), "id must be a string or number");
+        assert_eq!(invalid(r#"{"jsonrpc":"2.0","id":1,"method":"ping","params":3}"#), "params must be an object or array");
+        assert_eq!(invalid(r#"{"jsonrpc":"2.0","id":1}"#), "method is missing");
+        assert_eq!(invalid(r#"{"jsonrpc":"2.0","id":1,"result":{},"error":{}}"#), "a reply cannot have both result and error");
+
+        let valid = |message: &str| RawMessage::parse(message).unwrap().validate().is_ok();
+        assert!(valid(r#"{"jsonrpc":"2.0","id":-1.5,"method":"ping","params":[]}"#));
+        assert!(valid(r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"Parse error"}}"#));
+    }
+
     #[test]
     fn test_json_rpc_response_success() {
         let response = JsonRpcResponse {
@@ -200,6 +539,7 @@ mod tests {
                 tools: Some(ToolCapabilities {
                     list_changed: Some(true),
                 }),
+                ..Default::default()
             },
             client_info: ClientInfo {
                 name: "test-client".to_string(),
@@ -221,6 +561,7 @@ mod tests {
                 tools: Some(ToolCapabilities {
                     list_changed: Some(false),
                 }),
+                ..Default::default()
             },
             server_info: ServerInfo {
                 name: "mcp-server".to_string(),
@@ -245,12 +586,66 @@ mod tests {
                     "param1": {"type": "string"}
                 }
             }),
+            output_schema: None,
+            version: None,
+            deprecated: None,
+            tags: Vec::new(),
+            annotations: None,
         };
 
         let serialized = serde_json::to_string(&tool).unwrap();
         assert!(serialized.contains("inputSchema"));
+        assert!(!serialized.contains("outputSchema"));
         assert!(serialized.contains("test_tool"));
         assert!(serialized.contains("A test tool"));
+        assert!(!serialized.contains("tags"));
+        assert!(!serialized.contains("annotations"));
+    }
+
+    #[test]
+    fn test_tool_definition_accepts_either_schema_spelling() {
+        for (input, output) in [("inputSchema", "outputSchema"), ("input_schema", "output_schema")] {
+            let tool: ToolDefinition = serde_json::from_value(json!({
+                "name": "weather",
+                "description": "Current weather",
+                input: {"type": "object", "properties": {"city": {"type": "string"}}},
+                output: {"type": "object"}
+            }))
+            .unwrap();
+            assert_eq!(tool.input_schema["properties"]["city"]["type"], "string");
+
+            let serialized = serde_json::to_value(&tool).unwrap();
+            assert_eq!(serialized["inputSchema"]["properties"]["city"]["type"], "string");
+            assert_eq!(serialized["outputSchema"]["type"], "object");
+            assert!(serialized.get("input_schema").is_none());
+            assert!(serialized.get("output_schema").is_none());
+        }
+    }
+
+    #[test]
+    fn test_tool_tags_and_annotations() {
+        let tags = vec![ToolTag::ReadOnly, ToolTag::Network];
+        let tool = ToolDefinition {
+            name: "weather".to_string(),
+            description: "Current weather".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: Some(json!({"type": "object", "properties": {"celsius": {"type": "number"}}})),
+            version: Some("v2".to_string()),
+            deprecated: Some(Deprecation { message: "Use thermometer".to_string(), replaced_by: Some("thermometer".to_string()) }),
+            annotations: ToolAnnotations::from_tags(&tags),
+            tags,
+        };
+
+        let serialized = serde_json::to_value(&tool).unwrap();
+        assert_eq!(serialized["tags"], json!(["read-only", "network"]));
+        assert_eq!(serialized["annotations"], json!({"readOnlyHint": true, "openWorldHint": true}));
+        assert_eq!(serialized["outputSchema"]["properties"]["celsius"]["type"], "number");
+        assert_eq!(serialized["version"], "v2");
+        assert_eq!(serialized["deprecated"], json!({"message": "Use thermometer", "replacedBy": "thermometer"}));
+
+        let writer = ToolAnnotations::from_tags(&[ToolTag::Destructive]).unwrap();
+        assert_eq!(writer.destructive_hint, Some(true));
+        assert_eq!(ToolAnnotations::from_tags(&[]), None);
     }
 
     #[test]
@@ -260,11 +655,21 @@ mod tests {
                 name: "tool1".to_string(),
                 description: "First tool".to_string(),
                 input_schema: json!({"type": "object"}),
+                output_schema: None,
+                version: None,
+                deprecated: None,
+                tags: Vec::new(),
+                annotations: None,
             },
             ToolDefinition {
                 name: "tool2".to_string(),
                 description: "Second tool".to_string(),
                 input_schema: json!({"type": "object"}),
+                output_schema: None,
+                version: None,
+                deprecated: None,
+                tags: Vec::new(),
+                annotations: None,
             },
         ];
 
@@ -283,6 +688,7 @@ mod tests {
         let params = ToolCallParams {
             name: "test_tool".to_string(),
             arguments,
+            meta: None,
         };
 
         let serialized = serde_json::to_string(&params).unwrap();
@@ -296,20 +702,30 @@ mod tests {
         let params = ToolCallParams {
             name: "simple_tool".to_string(),
             arguments: HashMap::new(),
+            meta: None,
         };
 
         let serialized = serde_json::to_string(&params).unwrap();
         assert!(serialized.contains("simple_tool"));
         assert!(serialized.contains("arguments"));
+        assert!(!serialized.contains("_meta"));
+    }
+
+    #[test]
+    fn test_tool_call_priority() {
+        let params: ToolCallParams = serde_json::from_value(json!({"name": "report"})).unwrap();
+        assert_eq!(params.priority(), Priority::Interactive);
+
+        let params: ToolCallParams =
+            serde_json::from_value(json!({"name": "report", "_meta": {"priority": "background"}})).unwrap();
+        assert_eq!(params.priority(), Priority::Background);
     }
 
     #[test]
     fn test_content_block_text() {
         let block = ContentBlock::text("Hello, world!");
         
-        match block {
-            ContentBlock::Text { text } => assert_eq!(text, "Hello, world!"),
-        }
+        assert_eq!(block.as_text(), Some("Hello, world!"));
     }
 
     #[test]
@@ -328,23 +744,55 @@ mod tests {
         let json_str = r#"{"type":"text","text":"Deserialized content"}"#;
         let block: ContentBlock = serde_json::from_str(json_str).unwrap();
         
-        match block {
-            ContentBlock::Text { text } => assert_eq!(text, "Deserialized content"),
-        }
+        assert_eq!(block.as_text(), Some("Deserialized content"));
+    }
+
+    #[test]
+    fn test_plugin_data_moves_binary_output_into_a_data_block() {
+        let blocks = ContentBlock::from_plugin_data(json!({"mime_type": "audio/wav", "bytes": 4, "data_base64": "UklGRg=="}));
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(ContentBlock::find_structured(&blocks), Some(&json!({"mime_type": "audio/wav", "bytes": 4})));
+        assert!(matches!(&blocks[2], ContentBlock::Data { mime_type, data } if mime_type == "audio/wav" && data == "UklGRg=="));
+        assert_eq!(ContentBlock::from_plugin_data(json!({"cpu": 12})).len(), 2);
+    }
+
+    #[test]
+    fn test_structured_content_blocks() {
+        let blocks = ContentBlock::structured(json!({"cpu": 12}));
+        assert_eq!(
+            serde_json::to_value(&blocks).unwrap(),
+            json!([
+                {"type": "text", "text": "{\n  \"cpu\": 12\n}"},
+                {"type": "json", "json": {"cpu": 12}},
+            ])
+        );
+
+        let block: ContentBlock = serde_json::from_value(json!({"type": "data", "mimeType": "text/csv", "data": "a,b"})).unwrap();
+        assert!(matches!(&block, ContentBlock::Data { mime_type, data } if mime_type == "text/csv" && data == "a,b"));
+        assert_eq!(block.as_text(), None);
     }
 
     #[test]
     fn test_tool_call_result() {
-        let result = ToolCallResult {
-            content: vec![
-                ContentBlock::text("First result"),
-                ContentBlock::text("Second result"),
-            ],
-        };
+        let result = ToolCallResult::new(vec![
+            ContentBlock::text("First result"),
+            ContentBlock::text("Second result"),
+        ]);
 
         let serialized = serde_json::to_string(&result).unwrap();
         assert!(serialized.contains("First result"));
         assert!(serialized.contains("Second result"));
+        assert!(!serialized.contains("structuredContent"));
+        assert!(!serialized.contains("isError"));
+
+        let structured = serde_json::to_value(ToolCallResult::new(ContentBlock::structured(json!({"cpu": 3})))).unwrap();
+        assert_eq!(structured["structuredContent"], json!({"cpu": 3}));
+        assert_eq!(structured["content"][1]["json"], json!({"cpu": 3}));
+        // Only objects are structured content
+        assert!(ToolCallResult::new(ContentBlock::structured(json!([1, 2]))).structured_content.is_none());
+
+        let failed = serde_json::to_value(ToolCallResult::error("disk full")).unwrap();
+        assert_eq!(failed, json!({"content": [{"type": "text", "text": "disk full"}], "isError": true}));
     }
 
     #[test]
@@ -362,6 +810,7 @@ mod tests {
             tools: Some(ToolCapabilities {
                 list_changed: Some(true),
             }),
+            ..Default::default()
         };
 
         let serialized = serde_json::to_string(&caps).unwrap();
@@ -0,0 +1,69 @@
+/// MCP protocol versions this server understands, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-03-26", "2024-11-05"];
+
+/// Picks the version for a session. A client supports every revision up to
+/// the one it requests, so this is the newest supported version not after
+/// the request. Clients older than everything here get the newest version
+/// and can decide whether to continue.
+pub fn negotiate(requested: &str) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .copied()
+        .find(|version| *version <= requested)
+        .unwrap_or(SUPPORTED_PROTOCOL_VERSIONS[0])
+}
+
+/// Parts of the protocol that only some sessions get
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Resources,
+    Prompts,
+}
+
+impl Feature {
+    /// First protocol version in which this server offers the feature
+    pub fn since(self) -> &'static str {
+        match self {
+            Feature::Resources | Feature::Prompts => "2025-03-26",
+        }
+    }
+
+    /// Versions are dates, so they compare as strings
+    pub fn enabled_in(self, version: &str) -> bool {
+        version >= self.since()
+    }
+
+    /// The feature behind a method, if it is gated
+    pub fn for_method(method: &str) -> Option<Feature> {
+        match method {
+            "resources/list" | "resources/read" => Some(Feature::Resources),
+            "prompts/list" | "prompts/get" => Some(Feature::Prompts),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_picks_highest_mutual_version() {
+        assert_eq!(negotiate("2025-03-26"), "2025-03-26");
+        // A newer client also speaks our newest version
+        assert_eq!(negotiate("2025-06-18"), "2025-03-26");
+        assert_eq!(negotiate("2024-11-05"), "2024-11-05");
+        // Between two of ours: the older one
+        assert_eq!(negotiate("2025-01-01"), "2024-11-05");
+        // Older than anything we support
+        assert_eq!(negotiate("1999-01-01"), SUPPORTED_PROTOCOL_VERSIONS[0]);
+    }
+
+    #[test]
+    fn test_features_are_gated_by_version() {
+        assert!(Feature::Resources.enabled_in("2025-03-26"));
+        assert!(!Feature::Prompts.enabled_in("2024-11-05"));
+        assert_eq!(Feature::for_method("prompts/get"), Some(Feature::Prompts));
+        assert_eq!(Feature::for_method("tools/list"), None);
+    }
+}
@@ -0,0 +1,235 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkflowStatus {
+    Running,
+    Paused,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+/// One step of a workflow's plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowStep {
+    pub description: String,
+    /// Tool `workflows/advance` calls for this step; without one the agent
+    /// does the step itself and reports its result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub arguments: Map<String, Value>,
+    #[serde(default = "pending")]
+    pub status: StepStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+fn pending() -> StepStatus {
+    StepStatus::Pending
+}
+
+/// A multi-step agent task that can span hours: its goal, its plan and how
+/// far it got. Kept in the context store between calls.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workflow {
+    pub id: String,
+    pub goal: String,
+    pub status: WorkflowStatus,
+    pub steps: Vec<WorkflowStep>,
+    /// Index of the step to do next; equal to the number of steps once
+    /// every step is done
+    pub current_step: usize,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Parameters of `workflows/create`
+#[derive(Debug, Deserialize)]
+pub struct CreateParams {
+    pub goal: String,
+    pub steps: Vec<WorkflowStep>,
+}
+
+/// What `workflows/advance` does with a workflow
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Advance {
+    /// Call the current step's tool and record its outcome
+    #[default]
+    Run,
+    /// Record `result` as the outcome of the current step
+    Complete,
+    /// Record `error` as the outcome of the current step, failing the workflow
+    Fail,
+    Pause,
+    Resume,
+}
+
+/// Parameters of `workflows/advance`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdvanceParams {
+    pub workflow_id: String,
+    #[serde(default)]
+    pub action: Advance,
+    #[serde(default)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<Value>,
+}
+
+/// Parameters of `workflows/status`; without `workflowId` every workflow is
+/// listed, optionally only those in `status`
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusParams {
+    pub workflow_id: Option<String>,
+    pub status: Option<WorkflowStatus>,
+}
+
+impl Workflow {
+    pub fn new(params: CreateParams) -> Result<Self, String> {
+        if params.steps.is_empty() {
+            return Err("A workflow needs at least one step".to_string());
+        }
+        let now = Utc::now();
+        Ok(Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            goal: params.goal,
+            status: WorkflowStatus::Running,
+            steps: params
+                .steps
+                .into_iter()
+                .map(|step| WorkflowStep { status: StepStatus::Pending, result: None, error: None, finished_at: None, ..step })
+                .collect(),
+            current_step: 0,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// The step to do next, if the workflow is running
+    pub fn current(&self) -> Result<&WorkflowStep, String> {
+        match self.status {
+            WorkflowStatus::Running => Ok(&self.steps[self.current_step]),
+            status => Err(format!("Workflow {} is {}", self.id, status.as_str())),
+        }
+    }
+
+    /// Records the current step's outcome and moves on: to the next step, to
+    /// `completed` after the last one, or to `failed` if the step failed
+    pub fn finish_step(&mut self, outcome: Result<Value, Value>) -> Result<(), String> {
+        self.current()?;
+        let step = &mut self.steps[self.current_step];
+        step.finished_at = Some(Utc::now());
+        match outcome {
+            Ok(result) => {
+                step.status = StepStatus::Completed;
+                step.result = Some(result);
+                self.current_step += 1;
+                if self.current_step == self.steps.len() {
+                    self.status = WorkflowStatus::Completed;
+                }
+            }
+            Err(error) => {
+                step.status = StepStatus::Failed;
+                step.error = Some(error);
+                self.status = WorkflowStatus::Failed;
+            }
+        }
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+
+    pub fn pause(&mut self) -> Result<(), String> {
+        self.current()?;
+        self.status = WorkflowStatus::Paused;
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+
+    pub fn resume(&mut self) -> Result<(), String> {
+        if self.status != WorkflowStatus::Paused {
+            return Err(format!("Workflow {} is {}, not paused", self.id, self.status.as_str()));
+        }
+        self.status = WorkflowStatus::Running;
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+}
+
+impl WorkflowStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WorkflowStatus::Running => "running",
+            WorkflowStatus::Paused => "paused",
+            WorkflowStatus::Completed => "completed",
+            WorkflowStatus::Failed => "failed",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn workflow() -> Workflow {
+        let params: CreateParams = serde_json::from_value(json!({
+            "goal": "Free up disk space",
+            "steps": [
+                { "description": "Find the fullest disk", "tool": "system_info", "arguments": { "action": "get_disks" } },
+                { "description": "Decide what to delete" }
+            ]
+        }))
+        .unwrap();
+        Workflow::new(params).unwrap()
+    }
+
+    #[test]
+    fn test_steps_complete_in_order() {
+        let mut workflow = workflow();
+        assert_eq!(workflow.current().unwrap().tool.as_deref(), Some("system_info"));
+        workflow.finish_step(Ok(json!({ "disks": [] }))).unwrap();
+        assert_eq!(workflow.steps[0].status, StepStatus::Completed);
+        assert_eq!(workflow.current().unwrap().description, "Decide what to delete");
+
+        workflow.pause().unwrap();
+        assert!(workflow.finish_step(Ok(json!("nothing"))).unwrap_err().contains("paused"));
+        workflow.resume().unwrap();
+        workflow.finish_step(Ok(json!("nothing"))).unwrap();
+        assert_eq!(workflow.status, WorkflowStatus::Completed);
+        assert_eq!(workflow.current_step, 2);
+        assert!(workflow.resume().is_err());
+    }
+
+    #[test]
+    fn test_failed_step_fails_the_workflow() {
+        let mut workflow = workflow();
+        workflow.finish_step(Err(json!("disk unreadable"))).unwrap();
+        assert_eq!(workflow.status, WorkflowStatus::Failed);
+        assert_eq!(workflow.steps[0].error, Some(json!("disk unreadable")));
+        assert!(workflow.current().is_err());
+
+        let round_trip: Workflow = serde_json::from_value(serde_json::to_value(&workflow).unwrap()).unwrap();
+        assert_eq!(round_trip, workflow);
+        assert!(Workflow::new(CreateParams { goal: "nothing".to_string(), steps: vec![] }).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+//! A stand-in MCP server with fake tools, for testing clients and the HTTP
+//! bridge without Neo4j or real plugins. Tools and their responses come from
+//! a script; see `MockScript`.
+
+use anyhow::{Context as _, Result};
+use axum::{
+    extract::{Json, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use mcp_core::error_data;
+
+use crate::mcp::types::{ContentBlock, JsonRpcError, JsonRpcRequest, JsonRpcResponse, ToolCallResult, ToolDefinition};
+use crate::mcp::errors::{ErrorKind, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR};
+use crate::mcp::version;
+
+/// Header carrying the session id on the `/mcp` endpoint
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// The fake tools a mock server offers, read from JSON:
+///
+/// ```json
+/// {"tools": [{"name": "weather", "description": "...",
+///             "responses": [{"text": "sunny"}, {"error": "rate limited"},
+///                           {"structured": {"sky": "clear"}}]}]}
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockScript {
+    pub tools: Vec<MockTool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "empty_schema", alias = "input_schema")]
+    pub input_schema: Value,
+    #[serde(default, alias = "output_schema", skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    /// Answers to successive calls, in order; the last one repeats
+    pub responses: Vec<MockResponse>,
+}
+
+/// One canned answer to a tool call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockResponse {
+    /// Text returned as the tool's content; `{{arguments}}` is replaced by
+    /// the call's arguments as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Structured content returned with the text, which defaults to it as
+    /// JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured: Option<Value>,
+    /// Fails the call with this message instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Waits this long before answering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+}
+
+fn empty_schema() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+impl Default for MockScript {
+    /// An `echo` tool that returns its arguments and a `fail` tool that
+    /// always fails
+    fn default() -> Self {
+        Self {
+            tools: vec![
+                MockTool {
+                    name: "echo".to_string(),
+                    description: "Returns its arguments".to_string(),
+                    input_schema: empty_schema(),
+                    output_schema: None,
+                    responses: vec![MockResponse {
+                        text: Some("{{arguments}}".to_string()),
+                        ..Default::default()
+                    }],
+                },
+                MockTool {
+                    name: "fail".to_string(),
+                    description: "Always fails".to_string(),
+                    input_schema: empty_schema(),
+                    output_schema: None,
+                    responses: vec![MockResponse {
+                        error: Some("Mock failure".to_string()),
+                        ..Default::default()
+                    }],
+                },
+            ],
+        }
+    }
+}
+
+impl MockScript {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read mock script {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid mock script {}", path.display()))
+    }
+}
+
+/// A tool call the mock server received
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MockCall {
+    pub tool: String,
+    pub arguments: Value,
+}
+
+/// Answers MCP JSON-RPC messages from a `MockScript`
+pub struct MockServer {
+    script: MockScript,
+    calls: Mutex<Vec<MockCall>>,
+}
+
+impl MockServer {
+    pub fn new(script: MockScript) -> Self {
+        Self {
+            script,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Tool calls received so far, oldest first
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// The response to one message; empty for notifications
+    pub async fn handle_message(&self, message: &str) -> String {
+        let request: JsonRpcRequest = match serde_json::from_str(message) {
+            Ok(request) => request,
+            Err(e) => return error_response(None, PARSE_ERROR, ErrorKind::ParseError, &format!("Parse error: {}", e), None),
+        };
+        if request.id.is_none() {
+            return String::new();
+        }
+
+        let params = request.params.clone().unwrap_or(Value::Null);
+        match request.method.as_str() {
+            "initialize" => {
+                let requested = params["protocolVersion"].as_str().unwrap_or_default();
+                success_response(request.id, json!({
+                    "protocolVersion": version::negotiate(requested),
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "mcp-mock-server", "version": env!("CARGO_PKG_VERSION") },
+                    "sessionId": uuid::Uuid::new_v4().to_string(),
+                }))
+            }
+            "ping" => success_response(request.id, json!({})),
+            "tools/list" => {
+                let tools: Vec<ToolDefinition> = self
+                    .script
+                    .tools
+                    .iter()
+                    .map(|tool| ToolDefinition {
+                        name: tool.name.clone(),
+                        description: tool.description.clone(),
+                        input_schema: tool.input_schema.clone(),
+                        output_schema: tool.output_schema.clone(),
+                        version: None,
+                        deprecated: None,
+                        tags: Vec::new(),
+                        annotations: None,
+                    })
+                    .collect();
+                success_response(request.id, json!({ "tools": tools }))
+            }
+            "tools/call" => self.call_tool(request.id, params).await,
+            method => error_response(request.id, METHOD_NOT_FOUND, ErrorKind::MethodNotFound, &format!("Method not found: {}", method), None),
+        }
+    }
+
+    async fn call_tool(&self, id: Option<Value>, params: Value) -> String {
+        let Some(name) = params["name"].as_str() else {
+            return error_response(id, INVALID_PARAMS, ErrorKind::InvalidParams, "Invalid params: name is required", None);
+        };
+        let Some(tool) = self.script.tools.iter().find(|tool| tool.name == name) else {
+            return error_response(id, INVALID_PARAMS, ErrorKind::NotFound, "Invalid params", Some(json!(format!("Unknown tool: {}", name))));
+        };
+        let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+
+        let response = {
+            let mut calls = self.calls.lock().unwrap();
+            let made = calls.iter().filter(|call| call.tool == name).count();
+            calls.push(MockCall { tool: name.to_string(), arguments: arguments.clone() });
+            tool.responses
+                .get(made.min(tool.responses.len().saturating_sub(1)))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        if let Some(delay) = response.delay_ms {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+        // Scripted failures are failures of the tool, so they come back as
+        // results, as from mcp-server
+        if let Some(error) = response.error {
+            return success_response(id, ToolCallResult::error(&error));
+        }
+        let text = match (response.text, &response.structured) {
+            (Some(text), _) => text.replace("{{arguments}}", &arguments.to_string()),
+            (None, Some(structured)) => structured.to_string(),
+            (None, None) => String::new(),
+        };
+        let mut result = ToolCallResult::new(vec![ContentBlock::text(&text)]);
+        result.structured_content = response.structured;
+        success_response(id, result)
+    }
+
+    /// The same HTTP endpoints as mcp-server: `/tools/list`, `/tools/call`
+    /// and `/mcp`, plus `/mock/calls` listing the tool calls received
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/version", get(|| async { env!("CARGO_PKG_VERSION") }))
+            .route("/tools/list", get(list_tools))
+            .route("/tools/call", post(forward))
+            .route("/mcp", post(mcp_endpoint))
+            .route("/mock/calls", get(list_calls))
+            .with_state(self)
+    }
+}
+
+fn success_response<T: Serialize>(id: Option<Value>, result: T) -> String {
+    serde_json::to_string(&JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: Some(serde_json::to_value(result).unwrap()),
+        error: None,
+    })
+    .unwrap()
+}
+
+fn error_response(id: Option<Value>, code: i32, kind: ErrorKind, message: &str, data: Option<Value>) -> String {
+    serde_json::to_string(&JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: None,
+        error: Some(JsonRpcError { code, message: message.to_string(), data: Some(error_data(kind, data)) }),
+    })
+    .unwrap()
+}
+
+fn json_body(response: &str) -> Response {
+    ([(header::CONTENT_TYPE, "application/json")], response.to_string()).into_response()
+}
+
+/// Like mcp-server, answers with the bare `tools/list` result
+async fn list_tools(State(server): State<Arc<MockServer>>) -> Response {
+    let response = server
+        .handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#)
+        .await;
+    let response: Value = serde_json::from_str(&response).unwrap();
+    Json(response["result"].clone()).into_response()
+}
+
+async fn forward(State(server): State<Arc<MockServer>>, Json(request): Json<Value>) -> Response {
+    json_body(&server.handle_message(&request.to_string()).await)
+}
+
+async fn mcp_endpoint(State(server): State<Arc<MockServer>>, body: String) -> Response {
+    let response = server.handle_message(&body).await;
+    if response.is_empty() {
+        return StatusCode::ACCEPTED.into_response();
+    }
+    let session = serde_json::from_str::<Value>(&response)
+        .ok()
+        .and_then(|response| response["result"]["sessionId"].as_str().map(str::to_string));
+    let mut http_response = json_body(&response);
+    if let Some(value) = session.and_then(|id| HeaderValue::from_str(&id).ok()) {
+        http_response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    http_response
+}
+
+async fn list_calls(State(server): State<Arc<MockServer>>) -> Json<Vec<MockCall>> {
+    Json(server.calls())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": { "city": "Oslo" } }
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_responses_follow_the_script() {
+        let script: MockScript = serde_json::from_value(json!({
+            "tools": [{
+                "name": "weather",
+                "responses": [
+                    { "error": "rate limited" },
+                    { "structured": { "sky": "clear" } },
+                    { "text": "sunny in {{arguments}}" }
+                ]
+            }]
+        }))
+        .unwrap();
+        let server = MockServer::new(script);
+
+        let first: Value = serde_json::from_str(&server.handle_message(&call("weather")).await).unwrap();
+        assert_eq!(first["result"], json!({"content": [{"type": "text", "text": "rate limited"}], "isError": true}));
+        let second: Value = serde_json::from_str(&server.handle_message(&call("weather")).await).unwrap();
+        assert_eq!(second["result"]["structuredContent"], json!({ "sky": "clear" }));
+        assert_eq!(second["result"]["content"][0]["text"], r#"{"sky":"clear"}"#);
+
+        // The last response repeats
+        for _ in 0..2 {
+            let next: Value = serde_json::from_str(&server.handle_message(&call("weather")).await).unwrap();
+            assert_eq!(next["result"]["content"][0]["text"], r#"sunny in {"city":"Oslo"}"#);
+        }
+        assert_eq!(server.calls().len(), 4);
+        assert_eq!(server.calls()[0].arguments, json!({ "city": "Oslo" }));
+    }
+
+    #[tokio::test]
+    async fn test_default_script_lists_echo_and_fail() {
+        let server = MockServer::new(MockScript::default());
+        let list: Value = serde_json::from_str(
+            &server.handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#).await,
+        )
+        .unwrap();
+        let names: Vec<&str> = list["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["echo", "fail"]);
+
+        let failed: Value = serde_json::from_str(&server.handle_message(&call("fail")).await).unwrap();
+        assert_eq!(failed["result"]["isError"], true);
+        let unknown: Value = serde_json::from_str(&server.handle_message(&call("nope")).await).unwrap();
+        assert_eq!(unknown["error"]["code"], -32602);
+        assert_eq!(unknown["error"]["data"], json!({"kind": "notFound", "detail": "Unknown tool: nope"}));
+        assert!(server
+            .handle_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .await
+            .is_empty());
+    }
+}
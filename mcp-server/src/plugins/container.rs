@@ -0,0 +1,186 @@
+//! Container detection for `system_info`. Inside a container the OS reports
+//! the host's CPUs and memory; the cgroup the server runs in says what it may
+//! actually use. Reads cgroup v2 (`cpu.max`, `memory.max`) and v1
+//! (`cpu.cfs_quota_us`, `memory.limit_in_bytes`) files.
+
+use serde::Serialize;
+use std::path::Path;
+
+/// v1 reports "no limit" as a huge number rather than `max`
+const V1_UNLIMITED: u64 = 1 << 62;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Container {
+    /// `kubernetes`, `docker`, `podman`, `containerd` or `lxc`; `None`
+    /// outside a container
+    pub runtime: Option<String>,
+    /// 1 or 2, when cgroups are mounted
+    pub cgroup_version: Option<u8>,
+    /// CPUs the cgroup's quota allows, e.g. `1.5`; `None` when unlimited
+    pub cpu_limit: Option<f64>,
+    /// `None` when unlimited
+    pub memory_limit_bytes: Option<u64>,
+    /// Memory the cgroup uses, without page cache it could drop, as
+    /// `docker stats` counts it
+    pub memory_used_bytes: Option<u64>,
+    /// CPU time the cgroup has used, for working out its CPU usage between
+    /// two readings
+    #[serde(skip)]
+    pub cpu_time_usec: Option<u64>,
+}
+
+impl Container {
+    /// Whether the server runs in a container or under cgroup limits
+    pub fn is_containerized(&self) -> bool {
+        self.runtime.is_some() || self.cpu_limit.is_some() || self.memory_limit_bytes.is_some()
+    }
+}
+
+/// The container the server runs in, if any
+pub fn detect() -> Container {
+    let mut container = detect_in(Path::new("/"));
+    if container.runtime.is_none() && std::env::var_os("KUBERNETES_SERVICE_HOST").is_some() {
+        container.runtime = Some("kubernetes".to_string());
+    }
+    container
+}
+
+/// `detect` against the files under `root`
+fn detect_in(root: &Path) -> Container {
+    let read = |path: &str| std::fs::read_to_string(root.join(path)).ok();
+    let cgroups = root.join("sys/fs/cgroup");
+
+    let mut container = Container { runtime: runtime(root, read("proc/1/cgroup").as_deref()), ..Default::default() };
+    if cgroups.join("cgroup.controllers").exists() {
+        container.cgroup_version = Some(2);
+        container.cpu_limit = read("sys/fs/cgroup/cpu.max").and_then(|max| {
+            let mut fields = max.split_whitespace();
+            match (fields.next()?, fields.next()?) {
+                ("max", _) => None,
+                (quota, period) => Some(quota.parse::<f64>().ok()? / period.parse::<f64>().ok()?),
+            }
+        });
+        container.memory_limit_bytes = read("sys/fs/cgroup/memory.max").and_then(|max| max.trim().parse().ok());
+        container.memory_used_bytes = read("sys/fs/cgroup/memory.current")
+            .and_then(|current| current.trim().parse::<u64>().ok())
+            .map(|current| current.saturating_sub(stat(read("sys/fs/cgroup/memory.stat"), "inactive_file").unwrap_or(0)));
+        container.cpu_time_usec = stat(read("sys/fs/cgroup/cpu.stat"), "usage_usec");
+    } else if cgroups.join("memory").exists() || cgroups.join("cpu").exists() {
+        container.cgroup_version = Some(1);
+        let quota = read("sys/fs/cgroup/cpu/cpu.cfs_quota_us").and_then(|quota| quota.trim().parse::<i64>().ok());
+        let period = read("sys/fs/cgroup/cpu/cpu.cfs_period_us").and_then(|period| period.trim().parse::<i64>().ok());
+        container.cpu_limit = match (quota, period) {
+            (Some(quota), Some(period)) if quota > 0 && period > 0 => Some(quota as f64 / period as f64),
+            _ => None,
+        };
+        container.memory_limit_bytes = read("sys/fs/cgroup/memory/memory.limit_in_bytes")
+            .and_then(|limit| limit.trim().parse::<u64>().ok())
+            .filter(|limit| *limit < V1_UNLIMITED);
+        container.memory_used_bytes = read("sys/fs/cgroup/memory/memory.usage_in_bytes")
+            .and_then(|usage| usage.trim().parse::<u64>().ok())
+            .map(|usage| usage.saturating_sub(stat(read("sys/fs/cgroup/memory/memory.stat"), "total_inactive_file").unwrap_or(0)));
+        container.cpu_time_usec = read("sys/fs/cgroup/cpuacct/cpuacct.usage")
+            .and_then(|usage| usage.trim().parse::<u64>().ok())
+            .map(|nanoseconds| nanoseconds / 1000);
+    }
+    container
+}
+
+/// The runtime, from the marker files runtimes leave and from the cgroup
+/// paths of PID 1
+fn runtime(root: &Path, init_cgroup: Option<&str>) -> Option<String> {
+    let init_cgroup = init_cgroup.unwrap_or_default();
+    let runtime = if init_cgroup.contains("kubepods") {
+        "kubernetes"
+    } else if root.join(".dockerenv").exists() || init_cgroup.contains("docker") {
+        "docker"
+    } else if root.join("run/.containerenv").exists() || init_cgroup.contains("libpod") {
+        "podman"
+    } else if init_cgroup.contains("containerd") {
+        "containerd"
+    } else if init_cgroup.contains("lxc") {
+        "lxc"
+    } else {
+        return None;
+    };
+    Some(runtime.to_string())
+}
+
+/// A value from a `key value` per line file such as `memory.stat`
+fn stat(content: Option<String>, key: &str) -> Option<u64> {
+    content?.lines().find_map(|line| {
+        let (name, value) = line.split_once(' ')?;
+        (name == key).then(|| value.trim().parse().ok()).flatten()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_cgroup_v2_limits() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        write(root, ".dockerenv", "");
+        write(root, "proc/1/cgroup", "0::/\n");
+        write(root, "sys/fs/cgroup/cgroup.controllers", "cpu memory\n");
+        write(root, "sys/fs/cgroup/cpu.max", "150000 100000\n");
+        write(root, "sys/fs/cgroup/cpu.stat", "usage_usec 4200\nuser_usec 4000\n");
+        write(root, "sys/fs/cgroup/memory.max", "536870912\n");
+        write(root, "sys/fs/cgroup/memory.current", "104857600\n");
+        write(root, "sys/fs/cgroup/memory.stat", "anon 1\ninactive_file 4857600\n");
+
+        let container = detect_in(root);
+        assert_eq!(container, Container {
+            runtime: Some("docker".to_string()),
+            cgroup_version: Some(2),
+            cpu_limit: Some(1.5),
+            memory_limit_bytes: Some(536870912),
+            memory_used_bytes: Some(100000000),
+            cpu_time_usec: Some(4200),
+        });
+        assert!(container.is_containerized());
+
+        // Unlimited
+        write(root, "sys/fs/cgroup/cpu.max", "max 100000\n");
+        write(root, "sys/fs/cgroup/memory.max", "max\n");
+        let container = detect_in(root);
+        assert_eq!((container.cpu_limit, container.memory_limit_bytes), (None, None));
+    }
+
+    #[test]
+    fn test_cgroup_v1_limits() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        write(root, "proc/1/cgroup", "12:memory:/kubepods/burstable/pod1/abc\n");
+        write(root, "sys/fs/cgroup/cpu/cpu.cfs_quota_us", "50000\n");
+        write(root, "sys/fs/cgroup/cpu/cpu.cfs_period_us", "100000\n");
+        write(root, "sys/fs/cgroup/cpuacct/cpuacct.usage", "3000000\n");
+        write(root, "sys/fs/cgroup/memory/memory.limit_in_bytes", "9223372036854771712\n");
+        write(root, "sys/fs/cgroup/memory/memory.usage_in_bytes", "2048\n");
+        write(root, "sys/fs/cgroup/memory/memory.stat", "cache 10\ntotal_inactive_file 1024\n");
+
+        let container = detect_in(root);
+        assert_eq!(container.runtime.as_deref(), Some("kubernetes"));
+        assert_eq!(container.cgroup_version, Some(1));
+        assert_eq!(container.cpu_limit, Some(0.5));
+        assert_eq!(container.memory_limit_bytes, None);
+        assert_eq!(container.memory_used_bytes, Some(1024));
+        assert_eq!(container.cpu_time_usec, Some(3000));
+    }
+
+    #[test]
+    fn test_no_container() {
+        let root = tempfile::tempdir().unwrap();
+        let container = detect_in(root.path());
+        assert_eq!(container, Container::default());
+        assert!(!container.is_containerized());
+    }
+}
@@ -0,0 +1,392 @@
+use async_trait::async_trait;
+use tracing::{info, debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
+
+#[derive(Debug)]
+struct EmbeddingsPluginError(String);
+
+impl fmt::Display for EmbeddingsPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for EmbeddingsPluginError {}
+
+/// A stored text chunk with its embedding vector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingRecord {
+    pub id: String,
+    pub text: String,
+    pub embedding: Vec<f32>,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+/// A search hit, without the vector
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub id: String,
+    pub text: String,
+    pub metadata: Value,
+    pub score: f32,
+}
+
+type Collections = HashMap<String, Vec<EmbeddingRecord>>;
+
+/// Stores embedding vectors in named collections and finds the closest ones
+/// by cosine similarity. Collections are kept in memory and, when
+/// `EMBEDDINGS_STORE_PATH` is set, saved to that JSON file after every change.
+pub struct EmbeddingsPlugin {
+    collections: RwLock<Collections>,
+    store_path: Option<PathBuf>,
+}
+
+impl Default for EmbeddingsPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmbeddingsPlugin {
+    pub fn new() -> Self {
+        Self::with_store_path(std::env::var("EMBEDDINGS_STORE_PATH").ok().map(PathBuf::from))
+    }
+
+    pub fn with_store_path(store_path: Option<PathBuf>) -> Self {
+        Self {
+            collections: RwLock::new(HashMap::new()),
+            store_path,
+        }
+    }
+
+    /// Adds records to `collection`, replacing any with the same id. Every
+    /// vector in a collection must have the same dimension.
+    pub async fn store(&self, collection: &str, records: Vec<EmbeddingRecord>) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let mut collections = self.collections.write().await;
+
+        let dimension = collections
+            .get(collection)
+            .and_then(|entries| entries.first())
+            .or(records.first())
+            .map(|r| r.embedding.len());
+        if let Some(bad) = records.iter().find(|r| Some(r.embedding.len()) != dimension || r.embedding.is_empty()) {
+            return Err(Box::new(EmbeddingsPluginError(format!(
+                "Embedding for '{}' has dimension {}, expected {}",
+                bad.id,
+                bad.embedding.len(),
+                dimension.unwrap_or_default()
+            ))));
+        }
+
+        let count = records.len();
+        let entries = collections.entry(collection.to_string()).or_default();
+        for record in records {
+            entries.retain(|existing| existing.id != record.id);
+            entries.push(record);
+        }
+        debug!("Stored {} embeddings in collection '{}' ({} total)", count, collection, entries.len());
+
+        self.save(&collections).await?;
+        Ok(count)
+    }
+
+    /// The `top_k` records in `collection` most similar to `embedding`, best first
+    pub async fn search(&self, collection: &str, embedding: &[f32], top_k: usize) -> Vec<SearchHit> {
+        let collections = self.collections.read().await;
+        let mut hits: Vec<SearchHit> = collections
+            .get(collection)
+            .map(|records| {
+                records
+                    .iter()
+                    .filter(|record| record.embedding.len() == embedding.len())
+                    .map(|record| SearchHit {
+                        id: record.id.clone(),
+                        text: record.text.clone(),
+                        metadata: record.metadata.clone(),
+                        score: cosine_similarity(&record.embedding, embedding),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(top_k);
+        hits
+    }
+
+    /// Removes a collection, returning whether it existed
+    pub async fn delete_collection(&self, collection: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let mut collections = self.collections.write().await;
+        let existed = collections.remove(collection).is_some();
+        self.save(&collections).await?;
+        Ok(existed)
+    }
+
+    async fn save(&self, collections: &Collections) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if let Some(path) = &self.store_path {
+            let data = serde_json::to_vec(collections)?;
+            tokio::fs::write(path, data).await.map_err(|e| {
+                Box::new(EmbeddingsPluginError(format!("Failed to save embeddings to {}: {}", path.display(), e)))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+fn required_str<'a>(params: &'a HashMap<String, Value>, name: &str) -> Result<&'a str, Box<dyn Error + Send + Sync>> {
+    params.get(name)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Box::new(EmbeddingsPluginError(format!("{} is required", name))) as Box<dyn Error + Send + Sync>)
+}
+
+fn param<T: serde::de::DeserializeOwned>(params: &HashMap<String, Value>, name: &str) -> Result<T, Box<dyn Error + Send + Sync>> {
+    let value = params.get(name)
+        .ok_or_else(|| Box::new(EmbeddingsPluginError(format!("{} is required", name))))?;
+    serde_json::from_value(value.clone())
+        .map_err(|e| Box::new(EmbeddingsPluginError(format!("Invalid {}: {}", name, e))) as Box<dyn Error + Send + Sync>)
+}
+
+#[async_trait]
+impl Plugin for EmbeddingsPlugin {
+    fn name(&self) -> &str {
+        "embeddings"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        let collection = ParameterDefinition {
+            name: "collection".to_string(),
+            description: "Name of the collection".to_string(),
+            parameter_type: ParameterType::String,
+            required: true,
+        };
+
+        vec![
+            Capability {
+                name: "store".to_string(),
+                description: "Store text chunks with their embedding vectors".to_string(),
+                parameters: vec![
+                    collection.clone(),
+                    ParameterDefinition {
+                        name: "items".to_string(),
+                        description: "Array of {id, text, embedding, metadata} objects".to_string(),
+                        parameter_type: ParameterType::Array,
+                        required: true,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "search".to_string(),
+                description: "Find the stored chunks most similar to an embedding".to_string(),
+                parameters: vec![
+                    collection.clone(),
+                    ParameterDefinition {
+                        name: "embedding".to_string(),
+                        description: "Query embedding vector".to_string(),
+                        parameter_type: ParameterType::Array,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "top_k".to_string(),
+                        description: "Number of results to return (default: 5)".to_string(),
+                        parameter_type: ParameterType::Number,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "list_collections".to_string(),
+                description: "List collections and how many chunks each holds".to_string(),
+                parameters: vec![],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "delete_collection".to_string(),
+                description: "Remove a collection and all of its chunks".to_string(),
+                parameters: vec![collection],
+                version: None,
+                deprecated: None,
+            },
+        ]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        _context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        info!("Executing embeddings plugin capability: {}", capability);
+
+        let data = match capability {
+            "store" => {
+                let collection = required_str(&params, "collection")?;
+                let items: Vec<EmbeddingRecord> = param(&params, "items")?;
+                let stored = self.store(collection, items).await?;
+                json!({ "collection": collection, "stored": stored })
+            }
+            "search" => {
+                let collection = required_str(&params, "collection")?;
+                let embedding: Vec<f32> = param(&params, "embedding")?;
+                let top_k = params.get("top_k").and_then(|v| v.as_u64()).unwrap_or(5) as usize;
+                let results = self.search(collection, &embedding, top_k).await;
+                json!({ "collection": collection, "results": results })
+            }
+            "list_collections" => {
+                let collections = self.collections.read().await;
+                let counts: HashMap<&String, usize> = collections.iter().map(|(name, records)| (name, records.len())).collect();
+                json!({ "collections": counts })
+            }
+            "delete_collection" => {
+                let collection = required_str(&params, "collection")?;
+                let deleted = self.delete_collection(collection).await?;
+                json!({ "collection": collection, "deleted": deleted })
+            }
+            _ => return Err(Box::new(EmbeddingsPluginError(format!("Unknown capability: {}", capability)))),
+        };
+
+        Ok(PluginResult {
+            success: true,
+            data,
+            metrics: None,
+            context_updates: None,
+        })
+    }
+
+    async fn initialize(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let path = match &self.store_path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        match tokio::fs::read(path).await {
+            Ok(data) => {
+                let loaded: Collections = serde_json::from_slice(&data).map_err(|e| {
+                    Box::new(EmbeddingsPluginError(format!("Invalid embeddings store {}: {}", path.display(), e)))
+                })?;
+                info!("Loaded {} embedding collections from {}", loaded.len(), path.display());
+                *self.collections.write().await = loaded;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("No embeddings store at {}, starting empty", path.display());
+            }
+            Err(e) => warn!("Failed to read embeddings store {}: {}", path.display(), e),
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        let collections = self.collections.read().await;
+        HealthStatus::healthy().with_details(json!({
+            "collections": collections.len(),
+            "persistent": self.store_path.is_some(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, embedding: Vec<f32>) -> EmbeddingRecord {
+        EmbeddingRecord {
+            id: id.to_string(),
+            text: format!("text of {}", id),
+            embedding,
+            metadata: json!({"source": "notes.md"}),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_by_cosine_similarity() {
+        let plugin = EmbeddingsPlugin::with_store_path(None);
+        plugin.store("docs", vec![
+            record("a", vec![1.0, 0.0]),
+            record("b", vec![0.7, 0.7]),
+            record("c", vec![0.0, 1.0]),
+        ]).await.unwrap();
+
+        let hits = plugin.search("docs", &[1.0, 0.1], 2).await;
+        let ids: Vec<&str> = hits.iter().map(|hit| hit.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert!(hits[0].score > hits[1].score);
+        assert!(plugin.search("missing", &[1.0, 0.0], 2).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_store_replaces_ids_and_checks_dimensions() {
+        let plugin = EmbeddingsPlugin::with_store_path(None);
+        plugin.store("docs", vec![record("a", vec![1.0, 0.0])]).await.unwrap();
+        plugin.store("docs", vec![record("a", vec![0.0, 1.0])]).await.unwrap();
+
+        let hits = plugin.search("docs", &[0.0, 1.0], 5).await;
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].score - 1.0).abs() < 1e-6);
+
+        let error = plugin.store("docs", vec![record("b", vec![1.0, 0.0, 0.0])]).await.unwrap_err();
+        assert!(error.to_string().contains("dimension 3, expected 2"));
+    }
+
+    #[tokio::test]
+    async fn test_collections_persist_to_store_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("embeddings.json");
+
+        let plugin = EmbeddingsPlugin::with_store_path(Some(path.clone()));
+        plugin.initialize().await.unwrap();
+        plugin.store("docs", vec![record("a", vec![1.0, 0.0])]).await.unwrap();
+
+        let reloaded = EmbeddingsPlugin::with_store_path(Some(path));
+        reloaded.initialize().await.unwrap();
+        assert_eq!(reloaded.search("docs", &[1.0, 0.0], 1).await[0].id, "a");
+    }
+
+    #[tokio::test]
+    async fn test_execute_store_and_search() {
+        let plugin = EmbeddingsPlugin::with_store_path(None);
+        let params = HashMap::from([
+            ("collection".to_string(), json!("docs")),
+            ("items".to_string(), json!([{"id": "a", "text": "hello", "embedding": [0.5, 0.5]}])),
+        ]);
+        let result = plugin.execute("store", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["stored"], 1);
+
+        let params = HashMap::from([
+            ("collection".to_string(), json!("docs")),
+            ("embedding".to_string(), json!([0.5, 0.5])),
+        ]);
+        let result = plugin.execute("search", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["results"][0]["text"], "hello");
+
+        let result = plugin.execute("search", Context::for_test(), HashMap::new()).await;
+        assert!(result.unwrap_err().to_string().contains("collection is required"));
+    }
+}
@@ -0,0 +1,331 @@
+use async_trait::async_trait;
+use tracing::debug;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
+use crate::mcp::limits::MessageLimits;
+use crate::mcp::roots;
+
+/// Bytes returned by read_file unless the caller asks for more
+const DEFAULT_MAX_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug)]
+struct FileSystemPluginError(String);
+
+impl fmt::Display for FileSystemPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for FileSystemPluginError {}
+
+fn error(message: impl Into<String>) -> Box<dyn Error + Send + Sync> {
+    Box::new(FileSystemPluginError(message.into()))
+}
+
+/// Read-only access to local files. Every path must lie within the server's
+/// roots (`MCP_FS_ROOTS`) and, when the client declares roots, within one of
+/// those too.
+pub struct FileSystemPlugin {
+    roots: Vec<PathBuf>,
+    /// Most bytes read_file returns, whatever the caller asks for, so that
+    /// the response still fits in a message
+    max_read_bytes: u64,
+}
+
+impl Default for FileSystemPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystemPlugin {
+    pub fn new() -> Self {
+        Self::with_roots(roots::configured())
+    }
+
+    pub fn with_roots(roots: Vec<PathBuf>) -> Self {
+        Self { roots, max_read_bytes: MessageLimits::default().max_bytes as u64 }
+    }
+
+    /// Caps read_file at `limit` bytes, the server's message size limit
+    pub fn with_max_read_bytes(mut self, limit: u64) -> Self {
+        self.max_read_bytes = limit;
+        self
+    }
+
+    /// `path` within the allowed roots, explaining an empty `allowed` by
+    /// whether the server or the client left nothing to allow
+    fn resolve(&self, path: &str, allowed: &[PathBuf]) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+        if allowed.is_empty() {
+            return Err(error(if self.roots.is_empty() {
+                format!("No filesystem roots are configured; set {}", roots::ROOTS_ENV)
+            } else {
+                "None of the client's roots are within the server's roots".to_string()
+            }));
+        }
+        Ok(roots::resolve(path, allowed)?)
+    }
+
+    fn list_directory(&self, path: &Path) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            entries.push(json!({
+                "name": entry.file_name().to_string_lossy(),
+                "type": file_type(&metadata),
+                "size": metadata.len(),
+            }));
+        }
+        entries.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        Ok(json!({ "path": path, "entries": entries }))
+    }
+
+    fn read_file(&self, path: &Path, max_bytes: u64) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        use std::io::Read;
+
+        let size = std::fs::metadata(path)?.len();
+        let mut content = Vec::new();
+        std::fs::File::open(path)?.take(max_bytes).read_to_end(&mut content)?;
+        Ok(json!({
+            "path": path,
+            "size": size,
+            "truncated": size > content.len() as u64,
+            "content": String::from_utf8_lossy(&content),
+        }))
+    }
+
+    fn file_info(&self, path: &Path) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata.modified().ok().map(chrono::DateTime::<chrono::Utc>::from);
+        Ok(json!({
+            "path": path,
+            "type": file_type(&metadata),
+            "size": metadata.len(),
+            "readonly": metadata.permissions().readonly(),
+            "modified": modified,
+        }))
+    }
+}
+
+fn file_type(metadata: &std::fs::Metadata) -> &'static str {
+    if metadata.is_dir() {
+        "directory"
+    } else if metadata.is_file() {
+        "file"
+    } else {
+        "other"
+    }
+}
+
+fn path_parameter() -> ParameterDefinition {
+    ParameterDefinition {
+        name: "path".to_string(),
+        description: "Absolute path, or relative to the first allowed root".to_string(),
+        parameter_type: ParameterType::String,
+        required: true,
+    }
+}
+
+#[async_trait]
+impl Plugin for FileSystemPlugin {
+    fn name(&self) -> &str {
+        "filesystem"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![
+            Capability {
+                name: "list_roots".to_string(),
+                description: "List the directories this call may access".to_string(),
+                parameters: vec![],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "list_directory".to_string(),
+                description: "List the entries of a directory".to_string(),
+                parameters: vec![path_parameter()],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "read_file".to_string(),
+                description: "Read a text file".to_string(),
+                parameters: vec![
+                    path_parameter(),
+                    ParameterDefinition {
+                        name: "max_bytes".to_string(),
+                        description: format!(
+                            "Maximum bytes to return (default: {}, at most {})",
+                            DEFAULT_MAX_BYTES, self.max_read_bytes
+                        ),
+                        parameter_type: ParameterType::Number,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "file_info".to_string(),
+                description: "Get the type, size and modification time of a path".to_string(),
+                parameters: vec![path_parameter()],
+                version: None,
+                deprecated: None,
+            },
+        ]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        let allowed = roots::effective(&self.roots, context.roots.as_deref());
+        debug!("Filesystem {} within {:?}", capability, allowed);
+
+        let path = || -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+            let path = params.get("path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| error("path is required"))?;
+            self.resolve(path, &allowed)
+        };
+        let data = match capability {
+            "list_roots" => json!({ "roots": allowed }),
+            "list_directory" => self.list_directory(&path()?)?,
+            "read_file" => {
+                let max_bytes = params.get("max_bytes")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(DEFAULT_MAX_BYTES)
+                    .min(self.max_read_bytes);
+                self.read_file(&path()?, max_bytes)?
+            }
+            "file_info" => self.file_info(&path()?)?,
+            _ => return Err(error(format!("Unknown capability: {}", capability))),
+        };
+
+        Ok(PluginResult {
+            success: true,
+            data,
+            metrics: None,
+            context_updates: None,
+        })
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        if self.roots.is_empty() {
+            return HealthStatus::unhealthy(format!("No filesystem roots configured ({})", roots::ROOTS_ENV));
+        }
+        HealthStatus::healthy().with_details(json!({ "roots": self.roots }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(roots: Option<Vec<PathBuf>>) -> Context {
+        Context { roots, ..Context::for_test() }
+    }
+
+    fn path_params(path: &Path) -> HashMap<String, Value> {
+        HashMap::from([("path".to_string(), json!(path))])
+    }
+
+    #[tokio::test]
+    async fn test_paths_must_stay_within_roots() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("notes.txt"), "hello").unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "hidden").unwrap();
+        let plugin = FileSystemPlugin::with_roots(vec![root.path().canonicalize().unwrap()]);
+
+        let result = plugin
+            .execute("read_file", context(None), HashMap::from([("path".to_string(), json!("notes.txt"))]))
+            .await
+            .unwrap();
+        assert_eq!(result.data["content"], "hello");
+
+        let error = plugin
+            .execute("read_file", context(None), path_params(&outside.path().join("secret.txt")))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("outside the allowed roots"));
+
+        let escape = root.path().join("..").join(outside.path().file_name().unwrap()).join("secret.txt");
+        assert!(plugin.execute("read_file", context(None), path_params(&escape)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_roots_narrow_access() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        std::fs::write(project.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.path().join("other.txt"), "other").unwrap();
+        let plugin = FileSystemPlugin::with_roots(vec![root.path().canonicalize().unwrap()]);
+
+        let client_roots = Some(vec![project.clone()]);
+        let listing = plugin
+            .execute("list_directory", context(client_roots.clone()), path_params(&project))
+            .await
+            .unwrap();
+        assert_eq!(listing.data["entries"][0]["name"], "main.rs");
+        assert!(plugin
+            .execute("read_file", context(client_roots.clone()), path_params(&root.path().join("other.txt")))
+            .await
+            .is_err());
+
+        // A client that declared roots but approved none gets nothing
+        assert!(plugin
+            .execute("file_info", context(Some(vec![])), path_params(&project))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_no_configured_roots_allows_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = FileSystemPlugin::with_roots(vec![]);
+        let error = plugin
+            .execute("list_directory", context(None), path_params(dir.path()))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains(roots::ROOTS_ENV));
+        assert!(!plugin.health_check().await.healthy);
+    }
+
+    #[tokio::test]
+    async fn test_read_file_truncates() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("big.txt"), "0123456789").unwrap();
+        let plugin = FileSystemPlugin::with_roots(vec![root.path().canonicalize().unwrap()]);
+
+        let mut params = path_params(&root.path().join("big.txt"));
+        params.insert("max_bytes".to_string(), json!(4));
+        let result = plugin.execute("read_file", context(None), params).await.unwrap();
+        assert_eq!(result.data["content"], "0123");
+        assert_eq!(result.data["truncated"], true);
+        assert_eq!(result.data["size"], 10);
+
+        // Asking for more than the limit gets only the limit
+        let plugin = plugin.with_max_read_bytes(6);
+        let mut params = path_params(&root.path().join("big.txt"));
+        params.insert("max_bytes".to_string(), json!(u64::MAX));
+        let result = plugin.execute("read_file", context(None), params).await.unwrap();
+        assert_eq!(result.data["content"], "012345");
+        assert_eq!(result.data["truncated"], true);
+    }
+}
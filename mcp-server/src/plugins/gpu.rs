@@ -0,0 +1,262 @@
+//! GPU readings for `system_info`: utilization, memory and temperature per
+//! GPU. NVIDIA GPUs are read through NVML when built with the `nvml`
+//! feature and through `nvidia-smi` otherwise, AMD GPUs through `rocm-smi`,
+//! and Apple GPUs through `ioreg`. Sources that are not installed are
+//! skipped, so a machine without GPUs reports none.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::debug;
+
+/// How long a vendor tool may take to answer
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MIB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Gpu {
+    pub index: u32,
+    pub name: String,
+    /// `nvidia`, `amd` or `apple`
+    pub vendor: String,
+    /// Where the reading comes from: `nvml`, `nvidia-smi`, `rocm-smi` or `ioreg`
+    pub source: String,
+    pub utilization_percent: Option<f64>,
+    /// `None` for GPUs that share system memory
+    pub memory_total_bytes: Option<u64>,
+    pub memory_used_bytes: Option<u64>,
+    pub temperature_celsius: Option<f64>,
+}
+
+/// The GPUs of every vendor found
+pub async fn query() -> Vec<Gpu> {
+    let mut gpus = nvidia().await;
+    gpus.extend(amd().await);
+    if cfg!(target_os = "macos") {
+        gpus.extend(apple().await);
+    }
+    gpus
+}
+
+async fn nvidia() -> Vec<Gpu> {
+    #[cfg(feature = "nvml")]
+    match tokio::task::spawn_blocking(nvml).await {
+        Ok(Ok(gpus)) => return gpus,
+        Ok(Err(e)) => debug!("NVML unavailable, trying nvidia-smi: {}", e),
+        Err(e) => debug!("NVML query failed, trying nvidia-smi: {}", e),
+    }
+    run("nvidia-smi", &[
+        "--query-gpu=index,name,utilization.gpu,memory.total,memory.used,temperature.gpu",
+        "--format=csv,noheader,nounits",
+    ])
+    .await
+    .map(|output| parse_nvidia_smi(&output))
+    .unwrap_or_default()
+}
+
+#[cfg(feature = "nvml")]
+fn nvml() -> Result<Vec<Gpu>, nvml_wrapper::error::NvmlError> {
+    use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+
+    let nvml = nvml_wrapper::Nvml::init()?;
+    (0..nvml.device_count()?)
+        .map(|index| {
+            let device = nvml.device_by_index(index)?;
+            let memory = device.memory_info().ok();
+            Ok(Gpu {
+                index,
+                name: device.name()?,
+                vendor: "nvidia".to_string(),
+                source: "nvml".to_string(),
+                utilization_percent: device.utilization_rates().ok().map(|rates| rates.gpu as f64),
+                memory_total_bytes: memory.as_ref().map(|memory| memory.total),
+                memory_used_bytes: memory.as_ref().map(|memory| memory.used),
+                temperature_celsius: device.temperature(TemperatureSensor::Gpu).ok().map(|celsius| celsius as f64),
+            })
+        })
+        .collect()
+}
+
+async fn amd() -> Vec<Gpu> {
+    run("rocm-smi", &["--showuse", "--showmeminfo", "vram", "--showtemp", "--showproductname", "--json"])
+        .await
+        .and_then(|output| serde_json::from_str::<Value>(&output).ok())
+        .map(|report| parse_rocm_smi(&report))
+        .unwrap_or_default()
+}
+
+async fn apple() -> Vec<Gpu> {
+    run("ioreg", &["-r", "-d", "1", "-w", "0", "-c", "IOAccelerator"])
+        .await
+        .map(|output| parse_ioreg(&output))
+        .unwrap_or_default()
+}
+
+/// The command's stdout, `None` when it is not installed or fails
+async fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).kill_on_drop(true).output();
+    match tokio::time::timeout(COMMAND_TIMEOUT, output).await {
+        Ok(Ok(output)) if output.status.success() => Some(String::from_utf8_lossy(&output.stdout).into_owned()),
+        Ok(Ok(output)) => {
+            debug!("{} exited with {}", program, output.status);
+            None
+        }
+        Ok(Err(e)) => {
+            debug!("{} not available: {}", program, e);
+            None
+        }
+        Err(_) => {
+            debug!("{} did not answer within {} s", program, COMMAND_TIMEOUT.as_secs());
+            None
+        }
+    }
+}
+
+/// `nvidia-smi --format=csv,noheader,nounits` lines; memory is in MiB and
+/// unsupported readings are `[N/A]`
+fn parse_nvidia_smi(output: &str) -> Vec<Gpu> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [index, name, utilization, total, used, temperature] = fields[..] else {
+                return None;
+            };
+            let number = |value: &str| value.parse::<f64>().ok();
+            Some(Gpu {
+                index: index.parse().ok()?,
+                name: name.to_string(),
+                vendor: "nvidia".to_string(),
+                source: "nvidia-smi".to_string(),
+                utilization_percent: number(utilization),
+                memory_total_bytes: number(total).map(|mib| mib as u64 * MIB),
+                memory_used_bytes: number(used).map(|mib| mib as u64 * MIB),
+                temperature_celsius: number(temperature),
+            })
+        })
+        .collect()
+}
+
+/// `rocm-smi --json`: one object per `cardN` with string values
+fn parse_rocm_smi(report: &Value) -> Vec<Gpu> {
+    let Some(cards) = report.as_object() else {
+        return Vec::new();
+    };
+    let mut gpus: Vec<Gpu> = cards
+        .iter()
+        .filter_map(|(card, values)| {
+            let index = card.strip_prefix("card")?.parse().ok()?;
+            let number = |key: &str| values.get(key)?.as_str()?.trim().parse::<f64>().ok();
+            // The sensor names differ between GPU generations
+            let temperature = values.as_object()?.iter().find_map(|(key, value)| {
+                key.starts_with("Temperature").then(|| value.as_str()?.trim().parse::<f64>().ok()).flatten()
+            });
+            Some(Gpu {
+                index,
+                name: values.get("Card series").and_then(Value::as_str).unwrap_or(card).to_string(),
+                vendor: "amd".to_string(),
+                source: "rocm-smi".to_string(),
+                utilization_percent: number("GPU use (%)"),
+                memory_total_bytes: number("VRAM Total Memory (B)").map(|bytes| bytes as u64),
+                memory_used_bytes: number("VRAM Total Used Memory (B)").map(|bytes| bytes as u64),
+                temperature_celsius: temperature,
+            })
+        })
+        .collect();
+    gpus.sort_by_key(|gpu| gpu.index);
+    gpus
+}
+
+/// `ioreg -c IOAccelerator`: a `model` line and a `PerformanceStatistics`
+/// line per GPU. Apple GPUs share system memory and report no temperature.
+fn parse_ioreg(output: &str) -> Vec<Gpu> {
+    let mut gpus = Vec::new();
+    let mut name = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(model) = line.strip_prefix("\"model\" = ") {
+            name = Some(model.trim_matches(|c| c == '"' || c == '<' || c == '>').to_string());
+        } else if line.starts_with("\"PerformanceStatistics\"") {
+            let statistic = |key: &str| {
+                let start = line.find(&format!("\"{}\"=", key))? + key.len() + 3;
+                let digits: String = line[start..].chars().take_while(char::is_ascii_digit).collect();
+                digits.parse::<u64>().ok()
+            };
+            gpus.push(Gpu {
+                index: gpus.len() as u32,
+                name: name.take().unwrap_or_else(|| "Apple GPU".to_string()),
+                vendor: "apple".to_string(),
+                source: "ioreg".to_string(),
+                utilization_percent: statistic("Device Utilization %").map(|percent| percent as f64),
+                memory_total_bytes: None,
+                memory_used_bytes: statistic("In use system memory"),
+                temperature_celsius: None,
+            });
+        }
+    }
+    gpus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_nvidia_smi() {
+        let gpus = parse_nvidia_smi("0, NVIDIA GeForce RTX 4090, 37, 24564, 8192, 61\n1, Tesla T4, [N/A], 15360, 0, [N/A]\n");
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0], Gpu {
+            index: 0,
+            name: "NVIDIA GeForce RTX 4090".to_string(),
+            vendor: "nvidia".to_string(),
+            source: "nvidia-smi".to_string(),
+            utilization_percent: Some(37.0),
+            memory_total_bytes: Some(24564 * MIB),
+            memory_used_bytes: Some(8192 * MIB),
+            temperature_celsius: Some(61.0),
+        });
+        assert_eq!((gpus[1].utilization_percent, gpus[1].temperature_celsius), (None, None));
+        assert!(parse_nvidia_smi("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_rocm_smi() {
+        let gpus = parse_rocm_smi(&json!({
+            "card1": {"GPU use (%)": "5", "Card series": "Radeon RX 7900 XTX"},
+            "card0": {
+                "GPU use (%)": "42",
+                "VRAM Total Memory (B)": "25753026560",
+                "VRAM Total Used Memory (B)": "1073741824",
+                "Temperature (Sensor edge) (C)": "48.0",
+                "Card series": "Radeon RX 7900 XTX"
+            },
+            "system": {"Driver version": "6.7.0"}
+        }));
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].index, 0);
+        assert_eq!(gpus[0].utilization_percent, Some(42.0));
+        assert_eq!(gpus[0].memory_total_bytes, Some(25753026560));
+        assert_eq!(gpus[0].memory_used_bytes, Some(1073741824));
+        assert_eq!(gpus[0].temperature_celsius, Some(48.0));
+        assert_eq!(gpus[1].memory_total_bytes, None);
+    }
+
+    #[test]
+    fn test_parse_ioreg() {
+        let output = r#"+-o AGXAcceleratorG14X  <class AGXAcceleratorG14X, id 0x1000004a1>
+    {
+      "model" = "Apple M2 Pro"
+      "PerformanceStatistics" = {"In use system memory"=1369456640,"Device Utilization %"=23,"Renderer Utilization %"=21}
+    }
+"#;
+        let gpus = parse_ioreg(output);
+        assert_eq!(gpus.len(), 1);
+        assert_eq!(gpus[0].name, "Apple M2 Pro");
+        assert_eq!(gpus[0].utilization_percent, Some(23.0));
+        assert_eq!(gpus[0].memory_used_bytes, Some(1369456640));
+        assert_eq!(gpus[0].memory_total_bytes, None);
+    }
+}
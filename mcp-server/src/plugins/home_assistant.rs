@@ -1,12 +1,15 @@
 use async_trait::async_trait;
-use log::{info, error, debug};
+use tracing::{info, error, debug, warn};
+use reqwest::{Method, Response};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
-use reqwest;
+use std::sync::Arc;
+use std::time::Duration;
 
-use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType};
+use crate::secrets::{Secret, Secrets};
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
 
 #[derive(Debug)]
 struct HomeAssistantPluginError(String);
@@ -19,127 +22,243 @@ impl fmt::Display for HomeAssistantPluginError {
 
 impl Error for HomeAssistantPluginError {}
 
-pub struct HomeAssistantPlugin {
-    base_url: String,
-    token: Option<String>,
+/// Connection settings for the Home Assistant REST API
+#[derive(Debug, Clone)]
+pub struct HomeAssistantConfig {
+    pub base_url: String,
+    /// Long-lived access token, or a `secret://` reference to it
+    pub token: Option<Secret>,
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
 }
 
-impl HomeAssistantPlugin {
-    pub fn new() -> Self {
+impl HomeAssistantConfig {
+    pub fn from_env() -> Self {
+        let secs = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+
         Self {
             base_url: std::env::var("HOMEASSISTANT_URL")
                 .unwrap_or_else(|_| "http://localhost:8123".to_string()),
-            token: std::env::var("HOMEASSISTANT_TOKEN").ok(),
+            token: std::env::var("HOMEASSISTANT_TOKEN").ok().map(Secret::from),
+            timeout: Duration::from_secs(secs("HOMEASSISTANT_TIMEOUT_SECS", 10)),
+            connect_timeout: Duration::from_secs(secs("HOMEASSISTANT_CONNECT_TIMEOUT_SECS", 5)),
+            max_retries: secs("HOMEASSISTANT_MAX_RETRIES", 2) as u32,
+            retry_backoff: Duration::from_millis(250),
         }
     }
+}
+
+pub struct HomeAssistantPlugin {
+    config: HomeAssistantConfig,
+    client: reqwest::Client,
+    secrets: Arc<Secrets>,
+}
+
+impl HomeAssistantPlugin {
+    pub fn new() -> Self {
+        Self::with_config(HomeAssistantConfig::from_env())
+    }
+
+    pub fn with_config(config: HomeAssistantConfig) -> Self {
+        Self::with_secrets(config, Secrets::global())
+    }
+
+    /// Resolves a `secret://` token through `secrets` rather than the
+    /// process-wide provider
+    pub fn with_secrets(config: HomeAssistantConfig, secrets: Arc<Secrets>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.connect_timeout)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .pool_max_idle_per_host(4)
+            .build()
+            .unwrap_or_else(|e| {
+                error!("Failed to build Home Assistant HTTP client, using defaults: {}", e);
+                reqwest::Client::new()
+            });
+
+        Self { config, client, secrets }
+    }
 
-    fn get_auth_header(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
-        match &self.token {
-            Some(token) => Ok(format!("Bearer {}", token)),
+    async fn get_auth_header(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        match &self.config.token {
+            Some(token) => {
+                let token = self.secrets.resolve(token).await
+                    .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to look up the Home Assistant token: {:#}", e))))?;
+                Ok(format!("Bearer {}", token))
+            }
             None => Err(Box::new(HomeAssistantPluginError("Home Assistant token not configured. Set HOMEASSISTANT_TOKEN environment variable.".to_string())))
         }
     }
 
+    /// Sends a request to the HA API, retrying reads on connection errors,
+    /// timeouts and 5xx responses. Other requests, such as service calls, are
+    /// only retried when the connection failed, since HA may have acted on a
+    /// request that timed out or failed with 5xx. A token kept as a secret
+    /// reference is fetched again once if HA rejects it, in case it was
+    /// rotated.
+    async fn send(&self, method: Method, path: &str, body: Option<&Value>) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let response = self.send_with_retries(method.clone(), path, body).await?;
+        match &self.config.token {
+            Some(token @ Secret::Reference(_)) if response.status() == reqwest::StatusCode::UNAUTHORIZED => {
+                info!("Home Assistant rejected the token; fetching it again");
+                self.secrets.invalidate(token);
+                self.send_with_retries(method, path, body).await
+            }
+            _ => Ok(response),
+        }
+    }
+
+    async fn send_with_retries(&self, method: Method, path: &str, body: Option<&Value>) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let auth_header = self.get_auth_header().await?;
+        let url = format!("{}{}", self.config.base_url.trim_end_matches('/'), path);
+        let idempotent = method == Method::GET;
+        let mut attempt = 0;
+
+        loop {
+            let mut request = self.client
+                .request(method.clone(), &url)
+                .header("Authorization", &auth_header)
+                .header("Content-Type", "application/json");
+            if let Some(body) = body {
+                crate::mcp::budget::count_upstream_bytes(body.to_string().len());
+                request = request.json(body);
+            }
+
+            // Responses are counted as their body is read, in `read`
+            let response = request.send().await;
+            let retryable = match response {
+                Ok(response) if idempotent && response.status().is_server_error() => {
+                    let status = response.status();
+                    if attempt >= self.config.max_retries {
+                        return Ok(response);
+                    }
+                    format!("server returned {}", status)
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if (e.is_connect() || (idempotent && e.is_timeout())) && attempt < self.config.max_retries => e.to_string(),
+                Err(e) => return Err(Box::new(e)),
+            };
+
+            attempt += 1;
+            let delay = self.config.retry_backoff * 2u32.saturating_pow(attempt - 1);
+            warn!("Home Assistant request to {} failed ({}), retrying in {:?} (attempt {}/{})",
+                path, retryable, delay, attempt, self.config.max_retries);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
     async fn get_states(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
-        let auth_header = self.get_auth_header()?;
-        let client = reqwest::Client::new();
-        let url = format!("{}/api/states", self.base_url);
-        
         debug!("Fetching states from Home Assistant");
-        let response = client
-            .get(&url)
-            .header("Authorization", &auth_header)
-            .header("Content-Type", "application/json")
-            .send()
+        let response = self.send(Method::GET, "/api/states", None)
             .await
             .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to fetch states: {}", e))))?;
 
         if response.status().is_success() {
-            let states = response.json().await
+            let states = read_json(response).await
                 .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to parse states response: {}", e))))?;
             Ok(states)
         } else {
-            let error = response.text().await
+            let error = read_text(response).await
                 .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to read error response: {}", e))))?;
             Err(Box::new(HomeAssistantPluginError(format!("Failed to get states: {}", error))))
         }
     }
 
     async fn get_state(&self, entity_id: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
-        let auth_header = self.get_auth_header()?;
-        let client = reqwest::Client::new();
-        let url = format!("{}/api/states/{}", self.base_url, entity_id);
-        
         debug!("Fetching state for entity: {}", entity_id);
-        let response = client
-            .get(&url)
-            .header("Authorization", &auth_header)
-            .header("Content-Type", "application/json")
-            .send()
+        let response = self.send(Method::GET, &format!("/api/states/{}", entity_id), None)
             .await
             .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to fetch state: {}", e))))?;
 
         if response.status().is_success() {
-            let state = response.json().await
+            let state = read_json(response).await
                 .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to parse state response: {}", e))))?;
             Ok(state)
         } else {
-            let error = response.text().await
+            let error = read_text(response).await
                 .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to read error response: {}", e))))?;
             Err(Box::new(HomeAssistantPluginError(format!("Failed to get state for {}: {}", entity_id, error))))
         }
     }
 
     async fn call_service(&self, domain: &str, service: &str, service_data: Value) -> Result<Value, Box<dyn Error + Send + Sync>> {
-        let auth_header = self.get_auth_header()?;
-        let client = reqwest::Client::new();
-        let url = format!("{}/api/services/{}/{}", self.base_url, domain, service);
-        
         debug!("Calling service {}.{} with data: {:?}", domain, service, service_data);
-        let response = client
-            .post(&url)
-            .header("Authorization", &auth_header)
-            .header("Content-Type", "application/json")
-            .json(&service_data)
-            .send()
+        let response = self.send(Method::POST, &format!("/api/services/{}/{}", domain, service), Some(&service_data))
             .await
             .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to call service: {}", e))))?;
 
         if response.status().is_success() {
-            let result = response.json().await
+            let result = read_json(response).await
                 .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to parse service response: {}", e))))?;
             Ok(result)
         } else {
-            let error = response.text().await
+            let error = read_text(response).await
                 .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to read error response: {}", e))))?;
             Err(Box::new(HomeAssistantPluginError(format!("Failed to call service {}.{}: {}", domain, service, error))))
         }
     }
 
     async fn get_services(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
-        let auth_header = self.get_auth_header()?;
-        let client = reqwest::Client::new();
-        let url = format!("{}/api/services", self.base_url);
-        
         debug!("Fetching available services");
-        let response = client
-            .get(&url)
-            .header("Authorization", &auth_header)
-            .header("Content-Type", "application/json")
-            .send()
+        let response = self.send(Method::GET, "/api/services", None)
             .await
             .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to fetch services: {}", e))))?;
 
         if response.status().is_success() {
-            let services = response.json().await
+            let services = read_json(response).await
                 .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to parse services response: {}", e))))?;
             Ok(services)
         } else {
-            let error = response.text().await
+            let error = read_text(response).await
                 .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to read error response: {}", e))))?;
             Err(Box::new(HomeAssistantPluginError(format!("Failed to get services: {}", error))))
         }
     }
+
+    /// The current image of a camera entity and its MIME type
+    pub async fn camera_snapshot(&self, entity_id: &str) -> Result<(Vec<u8>, String), Box<dyn Error + Send + Sync>> {
+        debug!("Fetching snapshot of camera: {}", entity_id);
+        let response = self.send(Method::GET, &format!("/api/camera_proxy/{}", entity_id), None)
+            .await
+            .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to fetch camera snapshot: {}", e))))?;
+
+        if response.status().is_success() {
+            let mime_type = response.headers().get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("image/jpeg")
+                .to_string();
+            let image = read(response).await
+                .map_err(|e| Box::new(HomeAssistantPluginError(format!("Failed to read camera snapshot: {}", e))))?;
+            Ok((image, mime_type))
+        } else {
+            let status = response.status();
+            let error = read_text(response).await.unwrap_or_default();
+            Err(Box::new(HomeAssistantPluginError(format!("Failed to get snapshot of {} ({}): {}", entity_id, status, error))))
+        }
+    }
+}
+
+/// The body of an HA response, counted against the calling session's budget
+async fn read(response: Response) -> reqwest::Result<Vec<u8>> {
+    let body = response.bytes().await?;
+    crate::mcp::budget::count_upstream_bytes(body.len());
+    Ok(body.to_vec())
+}
+
+async fn read_text(response: Response) -> reqwest::Result<String> {
+    Ok(String::from_utf8_lossy(&read(response).await?).into_owned())
+}
+
+async fn read_json(response: Response) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    Ok(serde_json::from_slice(&read(response).await?)?)
 }
 
 #[async_trait]
@@ -158,6 +277,8 @@ impl Plugin for HomeAssistantPlugin {
                 name: "get_states".to_string(),
                 description: "Get all entity states from Home Assistant".to_string(),
                 parameters: vec![],
+                version: None,
+                deprecated: None,
             },
             Capability {
                 name: "get_state".to_string(),
@@ -170,6 +291,8 @@ impl Plugin for HomeAssistantPlugin {
                         required: true,
                     },
                 ],
+                version: None,
+                deprecated: None,
             },
             Capability {
                 name: "call_service".to_string(),
@@ -194,11 +317,15 @@ impl Plugin for HomeAssistantPlugin {
                         required: false,
                     },
                 ],
+                version: None,
+                deprecated: None,
             },
             Capability {
                 name: "get_services".to_string(),
                 description: "Get list of available Home Assistant services".to_string(),
                 parameters: vec![],
+                version: None,
+                deprecated: None,
             },
         ]
     }
@@ -268,4 +395,187 @@ impl Plugin for HomeAssistantPlugin {
             _ => Err(Box::new(HomeAssistantPluginError(format!("Unknown capability: {}", capability)))),
         }
     }
-}
\ No newline at end of file
+
+    async fn health_check(&self) -> HealthStatus {
+        let details = json!({ "base_url": self.config.base_url });
+        match self.send(Method::GET, "/api/", None).await {
+            Ok(response) if response.status().is_success() => HealthStatus::healthy().with_details(details),
+            Ok(response) => HealthStatus::unhealthy(format!("Home Assistant returned {}", response.status()))
+                .with_details(details),
+            Err(e) => HealthStatus::unhealthy(format!("Home Assistant unreachable: {}", e))
+                .with_details(details),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn test_config(base_url: &str) -> HomeAssistantConfig {
+        HomeAssistantConfig {
+            base_url: base_url.to_string(),
+            token: Some(Secret::from("test-token")),
+            timeout: Duration::from_secs(2),
+            connect_timeout: Duration::from_secs(1),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_states_uses_auth_header() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/states"))
+            .and(header("Authorization", "Bearer test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{"entity_id": "light.kitchen"}])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let plugin = HomeAssistantPlugin::with_config(test_config(&mock_server.uri()));
+        let states = plugin.get_states().await.unwrap();
+        assert_eq!(states[0]["entity_id"], "light.kitchen");
+    }
+
+    #[tokio::test]
+    async fn test_counts_the_body_read_against_the_budget() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/camera_proxy/camera.door"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(vec![0xff; 1000], "image/jpeg"))
+            .mount(&mock_server)
+            .await;
+
+        let plugin = HomeAssistantPlugin::with_config(test_config(&mock_server.uri()));
+        let (snapshot, bytes) = crate::mcp::budget::metered(plugin.camera_snapshot("camera.door")).await;
+        assert_eq!(snapshot.unwrap().0.len(), 1000);
+        assert_eq!(bytes, 1000);
+    }
+
+    #[tokio::test]
+    async fn test_retries_on_server_error() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/services"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/services"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let plugin = HomeAssistantPlugin::with_config(test_config(&mock_server.uri()));
+        assert_eq!(plugin.get_services().await.unwrap(), json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/states/light.kitchen"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        let plugin = HomeAssistantPlugin::with_config(test_config(&mock_server.uri()));
+        let error = plugin.get_state("light.kitchen").await.unwrap_err().to_string();
+        assert!(error.contains("boom"));
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_service_calls_that_may_have_run() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/services/light/turn_on"))
+            .respond_with(ResponseTemplate::new(502).set_body_string("bad gateway"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/services/lock/unlock"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut config = test_config(&mock_server.uri());
+        config.timeout = Duration::from_millis(100);
+        let plugin = HomeAssistantPlugin::with_config(config);
+        let error = plugin.call_service("light", "turn_on", json!({})).await.unwrap_err().to_string();
+        assert!(error.contains("bad gateway"));
+        assert!(plugin.call_service("lock", "unlock", json!({})).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_client_errors() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/states"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("unauthorized"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let plugin = HomeAssistantPlugin::with_config(test_config(&mock_server.uri()));
+        assert!(plugin.get_states().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"message": "API running."})))
+            .mount(&mock_server)
+            .await;
+
+        let plugin = HomeAssistantPlugin::with_config(test_config(&mock_server.uri()));
+        assert!(plugin.health_check().await.healthy);
+
+        let mut config = test_config(&mock_server.uri());
+        config.token = None;
+        let status = HomeAssistantPlugin::with_config(config).health_check().await;
+        assert!(!status.healthy);
+        assert!(status.message.unwrap().contains("token not configured"));
+    }
+
+    #[tokio::test]
+    async fn test_rotated_secret_token_is_fetched_again_when_rejected() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/states"))
+            .and(header("Authorization", "Bearer new-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/states"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&mock_server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("ha")).unwrap();
+        std::fs::write(dir.path().join("ha/token"), "old-token").unwrap();
+        let secrets = Arc::new(Secrets::new(Box::new(crate::secrets::FileSecrets::new(dir.path())), Duration::from_secs(3600)));
+        let mut config = test_config(&mock_server.uri());
+        config.token = Some(Secret::from("secret://ha/token"));
+        let plugin = HomeAssistantPlugin::with_secrets(config, secrets);
+        assert!(plugin.get_states().await.is_err());
+
+        std::fs::write(dir.path().join("ha/token"), "new-token").unwrap();
+        assert_eq!(plugin.get_states().await.unwrap(), json!([]));
+    }
+}
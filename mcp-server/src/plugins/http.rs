@@ -1,6 +1,6 @@
 use async_trait::async_trait;
-use log::{info, error, debug};
-use serde_json::{json, Value};
+use tracing::{info, debug};
+use serde_json::json;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
@@ -58,6 +58,7 @@ impl HttpPlugin {
         // Add body for POST, PUT, PATCH
         if matches!(method, "POST" | "PUT" | "PATCH") {
             if let Some(body_str) = body {
+                crate::mcp::budget::count_upstream_bytes(body_str.len());
                 request = request.body(body_str);
             }
         }
@@ -74,6 +75,7 @@ impl HttpPlugin {
         
         let body = response.text().await
             .map_err(|e| Box::new(HttpPluginError(format!("Failed to read response body: {}", e))))?;
+        crate::mcp::budget::count_upstream_bytes(body.len());
 
         Ok(json!({
             "status": status.as_u16(),
@@ -131,6 +133,8 @@ impl Plugin for HttpPlugin {
                         required: false,
                     },
                 ],
+                version: None,
+                deprecated: None,
             }
         ]
     }
@@ -189,7 +193,6 @@ impl Plugin for HttpPlugin {
 mod tests {
     use super::*;
     use std::collections::HashMap;
-    use chrono::Utc;
 
     #[test]
     fn test_http_plugin_error_display() {
@@ -240,11 +243,7 @@ mod tests {
     #[tokio::test]
     async fn test_unsupported_capability() {
         let plugin = HttpPlugin::new();
-        let context = Context {
-            correlation_id: "test-123".to_string(),
-            timestamp: Utc::now(),
-            parameters: HashMap::new(),
-        };
+        let context = Context::for_test();
         
         let result = plugin.execute(
             "unsupported_capability",
@@ -0,0 +1,587 @@
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use tracing::{info, debug, error};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::secrets::{Secret, Secrets};
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
+
+/// Fields fetched for each issue in search results
+const SUMMARY_FIELDS: [&str; 10] = [
+    "summary", "status", "issuetype", "priority", "assignee", "reporter", "labels", "project", "created", "updated",
+];
+
+const MAX_SEARCH_RESULTS: u64 = 100;
+
+/// Comments returned with an issue, the most recent ones
+const MAX_COMMENTS: usize = 10;
+
+#[derive(Debug)]
+struct JiraPluginError(String);
+
+impl fmt::Display for JiraPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for JiraPluginError {}
+
+fn error(message: impl Into<String>) -> Box<dyn Error + Send + Sync> {
+    Box::new(JiraPluginError(message.into()))
+}
+
+/// Connection settings for the Jira REST API (v2, which Jira Cloud and
+/// Data Center both serve)
+#[derive(Debug, Clone)]
+pub struct JiraConfig {
+    pub base_url: Option<String>,
+    /// Account email; with it the token is a Cloud API token sent with basic
+    /// auth, without it a Data Center personal access token
+    pub email: Option<String>,
+    /// API token, or a `secret://` reference to it
+    pub token: Option<Secret>,
+    pub timeout: Duration,
+}
+
+impl JiraConfig {
+    pub fn from_env() -> Self {
+        Self {
+            base_url: std::env::var("JIRA_URL").ok().filter(|v| !v.is_empty()),
+            email: std::env::var("JIRA_EMAIL").ok().filter(|v| !v.is_empty()),
+            token: std::env::var("JIRA_TOKEN").ok().map(Secret::from),
+            timeout: Duration::from_secs(
+                std::env::var("JIRA_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(10),
+            ),
+        }
+    }
+}
+
+/// Searches, reads, creates and transitions Jira issues, returning them in
+/// a flat shape rather than Jira's nested fields
+pub struct JiraPlugin {
+    config: JiraConfig,
+    client: reqwest::Client,
+    secrets: Arc<Secrets>,
+}
+
+impl Default for JiraPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JiraPlugin {
+    pub fn new() -> Self {
+        Self::with_config(JiraConfig::from_env())
+    }
+
+    pub fn with_config(config: JiraConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .unwrap_or_else(|e| {
+                error!("Failed to build Jira HTTP client, using defaults: {}", e);
+                reqwest::Client::new()
+            });
+        Self { config, client, secrets: Secrets::global() }
+    }
+
+    fn base_url(&self) -> Result<&str, Box<dyn Error + Send + Sync>> {
+        self.config.base_url.as_deref()
+            .map(|url| url.trim_end_matches('/'))
+            .ok_or_else(|| error("Jira is not configured. Set the JIRA_URL and JIRA_TOKEN environment variables."))
+    }
+
+    async fn auth_header(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let token = self.config.token.as_ref()
+            .ok_or_else(|| error("Jira token not configured. Set the JIRA_TOKEN environment variable."))?;
+        let token = self.secrets.resolve(token).await
+            .map_err(|e| error(format!("Failed to look up the Jira token: {:#}", e)))?;
+        Ok(match &self.config.email {
+            Some(email) => format!("Basic {}", BASE64.encode(format!("{}:{}", email, token))),
+            None => format!("Bearer {}", token),
+        })
+    }
+
+    /// Sends a request and returns the JSON body, `Value::Null` for none;
+    /// Jira's error messages become the error
+    async fn request(&self, method: Method, path: &str, body: Option<Value>) -> Result<(StatusCode, Value), Box<dyn Error + Send + Sync>> {
+        let url = format!("{}/rest/api/2{}", self.base_url()?, path);
+        debug!("Jira request: {} {}", method, url);
+        let mut request = self.client.request(method, &url)
+            .header("Authorization", self.auth_header().await?)
+            .header("Accept", "application/json");
+        if let Some(body) = body {
+            crate::mcp::budget::count_upstream_bytes(body.to_string().len());
+            request = request.json(&body);
+        }
+        let response = request.send().await
+            .map_err(|e| error(format!("Jira unreachable: {}", e)))?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        crate::mcp::budget::count_upstream_bytes(text.len());
+        let body: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+        if status.is_success() || status == StatusCode::NOT_FOUND {
+            return Ok((status, body));
+        }
+        // {"errorMessages": [...], "errors": {"field": "message"}}
+        let mut messages: Vec<String> = body["errorMessages"].as_array().into_iter().flatten()
+            .filter_map(|message| message.as_str().map(str::to_string))
+            .collect();
+        if let Some(errors) = body["errors"].as_object() {
+            messages.extend(errors.iter().map(|(field, message)| format!("{}: {}", field, message.as_str().unwrap_or_default())));
+        }
+        let detail = if messages.is_empty() { text.chars().take(200).collect() } else { messages.join("; ") };
+        Err(error(format!("Jira returned {}: {}", status, detail)))
+    }
+
+    fn browse_url(&self, key: &str) -> Option<String> {
+        self.base_url().ok().map(|base| format!("{}/browse/{}", base, key))
+    }
+
+    /// An issue's key and the fields people ask about, flattened
+    fn summarize(&self, issue: &Value) -> Value {
+        let fields = &issue["fields"];
+        let key = issue["key"].as_str().unwrap_or_default();
+        json!({
+            "key": key,
+            "url": self.browse_url(key),
+            "summary": fields["summary"],
+            "status": fields["status"]["name"],
+            "status_category": fields["status"]["statusCategory"]["name"],
+            "type": fields["issuetype"]["name"],
+            "priority": fields["priority"]["name"],
+            "assignee": fields["assignee"]["displayName"],
+            "reporter": fields["reporter"]["displayName"],
+            "labels": fields["labels"].as_array().cloned().unwrap_or_default(),
+            "project": fields["project"]["key"],
+            "created": fields["created"],
+            "updated": fields["updated"],
+        })
+    }
+
+    async fn search(&self, jql: &str, max_results: u64) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let max_results = max_results.clamp(1, MAX_SEARCH_RESULTS);
+        let body = json!({ "jql": jql, "maxResults": max_results, "fields": SUMMARY_FIELDS });
+        // Jira Cloud replaced /search with /search/jql; Data Center only has /search
+        let (status, mut response) = self.request(Method::POST, "/search/jql", Some(body.clone())).await?;
+        if status == StatusCode::NOT_FOUND {
+            let (status, fallback) = self.request(Method::POST, "/search", Some(body)).await?;
+            if status == StatusCode::NOT_FOUND {
+                return Err(error(format!("No Jira search API at {}", self.base_url()?)));
+            }
+            response = fallback;
+        }
+        let issues: Vec<Value> = response["issues"].as_array().into_iter().flatten()
+            .map(|issue| self.summarize(issue))
+            .collect();
+        let more = response["total"].as_u64().map(|total| total > issues.len() as u64)
+            .or_else(|| response["isLast"].as_bool().map(|last| !last))
+            .unwrap_or(false);
+        Ok(json!({
+            "jql": jql,
+            "count": issues.len(),
+            "total": response["total"],
+            "more": more,
+            "issues": issues,
+        }))
+    }
+
+    async fn get_issue(&self, key: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let key = issue_key(key)?;
+        let fields = format!("{},description,comment", SUMMARY_FIELDS.join(","));
+        let (status, issue) = self.request(Method::GET, &format!("/issue/{}?fields={}", key, fields), None).await?;
+        if status == StatusCode::NOT_FOUND {
+            return Err(error(format!("Issue {} does not exist or is not visible", key)));
+        }
+        let comments = issue["fields"]["comment"]["comments"].as_array().cloned().unwrap_or_default();
+        let recent: Vec<Value> = comments[comments.len().saturating_sub(MAX_COMMENTS)..].iter()
+            .map(|comment| json!({
+                "author": comment["author"]["displayName"],
+                "created": comment["created"],
+                "body": comment["body"],
+            }))
+            .collect();
+        let mut result = self.summarize(&issue);
+        result["description"] = issue["fields"]["description"].clone();
+        result["comment_count"] = json!(comments.len());
+        result["comments"] = json!(recent);
+        Ok(result)
+    }
+
+    async fn create_issue(&self, params: &HashMap<String, Value>) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let string = |name: &str| params.get(name).and_then(|v| v.as_str()).filter(|v| !v.is_empty());
+        let project = string("project").ok_or_else(|| error("project is required"))?;
+        let summary = string("summary").ok_or_else(|| error("summary is required"))?;
+        let mut fields = json!({
+            "project": { "key": project },
+            "summary": summary,
+            "issuetype": { "name": string("issue_type").unwrap_or("Task") },
+        });
+        if let Some(description) = string("description") {
+            fields["description"] = json!(description);
+        }
+        if let Some(priority) = string("priority") {
+            fields["priority"] = json!({ "name": priority });
+        }
+        if let Some(labels) = params.get("labels").and_then(|v| v.as_array()) {
+            fields["labels"] = json!(labels);
+        }
+        let (_, created) = self.request(Method::POST, "/issue", Some(json!({ "fields": fields }))).await?;
+        let key = created["key"].as_str().ok_or_else(|| error("Jira did not return the new issue's key"))?;
+        info!("Created Jira issue {}", key);
+        Ok(json!({ "key": key, "id": created["id"], "url": self.browse_url(key) }))
+    }
+
+    /// Moves an issue along its workflow by the transition's name or the
+    /// status it leads to
+    async fn transition(&self, key: &str, to: &str, comment: Option<&str>) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let key = issue_key(key)?;
+        let path = format!("/issue/{}/transitions", key);
+        let (status, available) = self.request(Method::GET, &path, None).await?;
+        if status == StatusCode::NOT_FOUND {
+            return Err(error(format!("Issue {} does not exist or is not visible", key)));
+        }
+        let transitions = available["transitions"].as_array().cloned().unwrap_or_default();
+        let matches = |value: &Value| value.as_str().is_some_and(|name| name.eq_ignore_ascii_case(to.trim()));
+        let transition = transitions.iter()
+            .find(|transition| matches(&transition["name"]))
+            .or_else(|| transitions.iter().find(|transition| matches(&transition["to"]["name"])))
+            .ok_or_else(|| {
+                let names: Vec<&str> = transitions.iter().filter_map(|t| t["name"].as_str()).collect();
+                error(format!("{} cannot move to {} now; available transitions: {}", key, to, names.join(", ")))
+            })?;
+
+        let mut body = json!({ "transition": { "id": transition["id"] } });
+        if let Some(comment) = comment.filter(|comment| !comment.is_empty()) {
+            body["update"] = json!({ "comment": [{ "add": { "body": comment } }] });
+        }
+        self.request(Method::POST, &path, Some(body)).await?;
+        info!("Moved Jira issue {} with transition {}", key, transition["name"]);
+        Ok(json!({
+            "key": key,
+            "transition": transition["name"],
+            "status": transition["to"]["name"],
+            "url": self.browse_url(&key),
+        }))
+    }
+}
+
+/// An issue key like `OPS-123`, or a numeric id, checked before it goes in
+/// a URL path
+fn issue_key(key: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let key = key.trim().to_uppercase();
+    let valid = match key.rsplit_once('-') {
+        Some((project, number)) => {
+            project.starts_with(|c: char| c.is_ascii_alphabetic())
+                && project.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !number.is_empty()
+                && number.chars().all(|c| c.is_ascii_digit())
+        }
+        None => !key.is_empty() && key.chars().all(|c| c.is_ascii_digit()),
+    };
+    if valid { Ok(key) } else { Err(error(format!("Invalid issue key: {}", key))) }
+}
+
+#[async_trait]
+impl Plugin for JiraPlugin {
+    fn name(&self) -> &str {
+        "jira"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        let key = ParameterDefinition {
+            name: "key".to_string(),
+            description: "Issue key, e.g. OPS-123".to_string(),
+            parameter_type: ParameterType::String,
+            required: true,
+        };
+        vec![
+            Capability {
+                name: "search".to_string(),
+                description: "Find issues with a JQL query".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "jql".to_string(),
+                        description: "JQL, e.g. project = OPS AND status != Done ORDER BY updated DESC".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "max_results".to_string(),
+                        description: format!("Issues to return, at most {} (default: 20)", MAX_SEARCH_RESULTS),
+                        parameter_type: ParameterType::Number,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "get_issue".to_string(),
+                description: "Get an issue with its description and recent comments".to_string(),
+                parameters: vec![key.clone()],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "create_issue".to_string(),
+                description: "Create an issue".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "project".to_string(),
+                        description: "Project key, e.g. OPS".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "summary".to_string(),
+                        description: "Issue title".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "issue_type".to_string(),
+                        description: "Issue type name (default: Task)".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                    ParameterDefinition {
+                        name: "description".to_string(),
+                        description: "Issue description".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                    ParameterDefinition {
+                        name: "priority".to_string(),
+                        description: "Priority name, e.g. High".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                    ParameterDefinition {
+                        name: "labels".to_string(),
+                        description: "Labels to add".to_string(),
+                        parameter_type: ParameterType::Array,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "transition".to_string(),
+                description: "Move an issue to another status".to_string(),
+                parameters: vec![
+                    key,
+                    ParameterDefinition {
+                        name: "to".to_string(),
+                        description: "Transition or target status name, e.g. In Progress or Done".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "comment".to_string(),
+                        description: "Comment to add with the transition".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+        ]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        _context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        info!("Executing Jira plugin capability: {}", capability);
+        let string = |name: &str| params.get(name).and_then(|v| v.as_str());
+        let required = |name: &str| string(name).ok_or_else(|| error(format!("{} is required", name)));
+
+        let data = match capability {
+            "search" => {
+                let max_results = params.get("max_results").and_then(|v| v.as_u64()).unwrap_or(20);
+                self.search(required("jql")?, max_results).await?
+            }
+            "get_issue" => self.get_issue(required("key")?).await?,
+            "create_issue" => self.create_issue(&params).await?,
+            "transition" => self.transition(required("key")?, required("to")?, string("comment")).await?,
+            _ => return Err(error(format!("Unknown capability: {}", capability))),
+        };
+
+        Ok(PluginResult {
+            success: true,
+            data,
+            metrics: None,
+            context_updates: None,
+        })
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        let details = json!({ "base_url": self.config.base_url });
+        match self.request(Method::GET, "/myself", None).await {
+            Ok((status, user)) if status.is_success() => HealthStatus::healthy().with_details(json!({
+                "base_url": self.config.base_url,
+                "user": user["displayName"],
+            })),
+            Ok((status, _)) => HealthStatus::unhealthy(format!("Jira returned {}", status)).with_details(details),
+            Err(e) => HealthStatus::unhealthy(e.to_string()).with_details(details),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{body_json, body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn plugin(base_url: &str, email: Option<&str>) -> JiraPlugin {
+        JiraPlugin::with_config(JiraConfig {
+            base_url: Some(base_url.to_string()),
+            email: email.map(str::to_string),
+            token: Some(Secret::from("test-token")),
+            timeout: Duration::from_secs(2),
+        })
+    }
+
+    fn issue(key: &str, status: &str) -> Value {
+        json!({
+            "key": key,
+            "fields": {
+                "summary": "Disk almost full on nas",
+                "status": {"name": status, "statusCategory": {"name": "To Do"}},
+                "issuetype": {"name": "Task"},
+                "assignee": {"displayName": "Sam Ops"},
+                "labels": ["homelab"],
+                "project": {"key": "OPS"},
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_search_falls_back_to_the_data_center_endpoint() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/rest/api/2/search/jql"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/rest/api/2/search"))
+            .and(header("Authorization", "Bearer test-token"))
+            .and(body_partial_json(json!({"jql": "project = OPS", "maxResults": 100})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "total": 3,
+                "issues": [issue("OPS-1", "Open"), issue("OPS-2", "Open")],
+            })))
+            .mount(&server)
+            .await;
+
+        let params = HashMap::from([("jql".to_string(), json!("project = OPS")), ("max_results".to_string(), json!(500))]);
+        let jira = plugin(&server.uri(), None);
+        let (result, bytes) = crate::mcp::budget::metered(jira.execute("search", Context::for_test(), params)).await;
+        let result = result.unwrap();
+        // Both searches and the issues that came back count against the budget
+        let issues = json!({"total": 3, "issues": [issue("OPS-1", "Open"), issue("OPS-2", "Open")]});
+        assert!(bytes as usize > issues.to_string().len());
+        assert_eq!(result.data["count"], 2);
+        assert_eq!(result.data["more"], true);
+        assert_eq!(result.data["issues"][1]["key"], "OPS-2");
+        assert_eq!(result.data["issues"][0]["assignee"], "Sam Ops");
+        assert_eq!(result.data["issues"][0]["url"], format!("{}/browse/OPS-1", server.uri()));
+    }
+
+    #[tokio::test]
+    async fn test_create_and_get_issue() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/rest/api/2/issue"))
+            .and(header("Authorization", format!("Basic {}", BASE64.encode("ops@example.com:test-token")).as_str()))
+            .and(body_json(json!({"fields": {
+                "project": {"key": "OPS"},
+                "summary": "Disk almost full on nas",
+                "issuetype": {"name": "Task"},
+                "labels": ["homelab"],
+            }})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({"id": "10001", "key": "OPS-7"})))
+            .mount(&server)
+            .await;
+        let mut found = issue("OPS-7", "Open");
+        found["fields"]["description"] = json!("Pool at 92%");
+        found["fields"]["comment"] = json!({"comments": [{"author": {"displayName": "Sam Ops"}, "body": "Looking"}]});
+        Mock::given(method("GET"))
+            .and(path("/rest/api/2/issue/OPS-7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(found))
+            .mount(&server)
+            .await;
+        let plugin = plugin(&server.uri(), Some("ops@example.com"));
+
+        let params = HashMap::from([
+            ("project".to_string(), json!("OPS")),
+            ("summary".to_string(), json!("Disk almost full on nas")),
+            ("labels".to_string(), json!(["homelab"])),
+        ]);
+        let created = plugin.execute("create_issue", Context::for_test(), params).await.unwrap();
+        assert_eq!(created.data["key"], "OPS-7");
+
+        let result = plugin.execute("get_issue", Context::for_test(), HashMap::from([("key".to_string(), json!("ops-7"))])).await.unwrap();
+        assert_eq!(result.data["description"], "Pool at 92%");
+        assert_eq!(result.data["comments"][0]["body"], "Looking");
+        assert!(plugin.execute("get_issue", Context::for_test(), HashMap::from([("key".to_string(), json!("OPS-7/../x"))])).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transition_by_status_name() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/rest/api/2/issue/OPS-7/transitions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"transitions": [
+                {"id": "21", "name": "Start work", "to": {"name": "In Progress"}},
+                {"id": "31", "name": "Resolve", "to": {"name": "Done"}},
+            ]})))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/rest/api/2/issue/OPS-7/transitions"))
+            .and(body_json(json!({
+                "transition": {"id": "31"},
+                "update": {"comment": [{"add": {"body": "Cleaned up snapshots"}}]},
+            })))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+        let plugin = plugin(&server.uri(), None);
+
+        let params = HashMap::from([
+            ("key".to_string(), json!("OPS-7")),
+            ("to".to_string(), json!("done")),
+            ("comment".to_string(), json!("Cleaned up snapshots")),
+        ]);
+        let result = plugin.execute("transition", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["transition"], "Resolve");
+        assert_eq!(result.data["status"], "Done");
+
+        let params = HashMap::from([("key".to_string(), json!("OPS-7")), ("to".to_string(), json!("Won't do"))]);
+        let error = plugin.execute("transition", Context::for_test(), params).await.unwrap_err();
+        assert!(error.to_string().contains("available transitions: Start work, Resolve"));
+    }
+}
@@ -0,0 +1,313 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::info;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::context::{ContextStore, Entity, Provenance, Relation, DEFAULT_TENANT};
+use crate::mcp::sampling::{CreateMessageParams, SamplingMessage};
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
+
+/// Entities `query` starts from when the caller gives no limit
+const DEFAULT_QUERY_LIMIT: usize = 20;
+
+/// Room for the extracted JSON of a few paragraphs
+const EXTRACTION_MAX_TOKENS: u32 = 2048;
+
+const EXTRACTION_PROMPT: &str = "Extract the entities and the relations between them that the user's text states. \
+Reply with JSON only, in the form {\"entities\": [{\"name\": \"Ada\", \"type\": \"person\"}], \
+\"relations\": [{\"source\": \"Ada\", \"relation\": \"works_at\", \"target\": \"Acme\"}]}. \
+The source and target of a relation are entity names. Name relations in lowercase snake_case.";
+
+/// Words too common to look for in entity names
+const STOP_WORDS: &[&str] = &[
+    "about", "all", "and", "any", "are", "can", "did", "does", "for", "from", "had", "has", "have", "how",
+    "into", "its", "know", "not", "tell", "that", "the", "their", "there", "these", "they", "this", "those",
+    "was", "were", "what", "when", "where", "which", "who", "whom", "why", "with", "you", "your",
+];
+
+#[derive(Debug)]
+struct KnowledgeGraphPluginError(String);
+
+impl fmt::Display for KnowledgeGraphPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for KnowledgeGraphPluginError {}
+
+/// What the model found in a text
+#[derive(Debug, Default, PartialEq, Deserialize)]
+struct Extraction {
+    #[serde(default)]
+    entities: Vec<Entity>,
+    #[serde(default)]
+    relations: Vec<Relation>,
+}
+
+/// Reads the model's reply, tolerating text or code fences around the JSON.
+/// Names are trimmed, relation names made snake_case and entities a relation
+/// mentions but the model did not list are added without a type.
+fn parse_extraction(reply: &str) -> Result<Extraction, String> {
+    let json = match (reply.find('{'), reply.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => return Err(format!("The model did not reply with JSON: {}", reply)),
+    };
+    let raw: Extraction = serde_json::from_str(json).map_err(|e| format!("The model's reply is not an extraction: {}", e))?;
+
+    fn add_entity(entities: &mut Vec<Entity>, name: &str, kind: &str) {
+        if let Some(known) = entities.iter_mut().find(|entity| entity.name == name) {
+            if known.kind.is_empty() {
+                known.kind = kind.to_string();
+            }
+        } else {
+            entities.push(Entity { name: name.to_string(), kind: kind.to_string() });
+        }
+    }
+
+    let mut extraction = Extraction::default();
+    for entity in raw.entities {
+        let name = entity.name.trim();
+        if !name.is_empty() {
+            add_entity(&mut extraction.entities, name, entity.kind.trim());
+        }
+    }
+    for relation in raw.relations {
+        let (source, target) = (relation.source.trim(), relation.target.trim());
+        let name = relation
+            .relation
+            .trim()
+            .to_lowercase()
+            .split(|c: char| c.is_whitespace() || c == '-')
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("_");
+        if source.is_empty() || target.is_empty() || name.is_empty() {
+            continue;
+        }
+        add_entity(&mut extraction.entities, source, "");
+        add_entity(&mut extraction.entities, target, "");
+        let relation = Relation { source: source.to_string(), relation: name, target: target.to_string(), provenance: None };
+        if !extraction.relations.contains(&relation) {
+            extraction.relations.push(relation);
+        }
+    }
+    Ok(extraction)
+}
+
+/// The words of a question worth looking for in entity names, lowercase
+fn query_terms(question: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in question.to_lowercase().split(|c: char| !c.is_alphanumeric()) {
+        if word.chars().count() >= 3 && !STOP_WORDS.contains(&word) && !terms.iter().any(|term| term == word) {
+            terms.push(word.to_string());
+        }
+    }
+    terms
+}
+
+/// A knowledge graph built from free text: the configured model extracts
+/// entities and relations, which are kept in the context store together
+/// with the text they came from
+pub struct KnowledgeGraphPlugin {
+    store: Option<Arc<dyn ContextStore>>,
+}
+
+impl KnowledgeGraphPlugin {
+    /// Without a store every capability fails
+    pub fn new(store: Option<Arc<dyn ContextStore>>) -> Self {
+        Self { store }
+    }
+
+    /// The store, scoped to the calling session's tenant
+    fn store(&self, context: &Context) -> Result<Arc<dyn ContextStore>, Box<dyn Error + Send + Sync>> {
+        let store = self.store
+            .clone()
+            .ok_or_else(|| Box::new(KnowledgeGraphPluginError("The knowledge graph needs a context store, such as Neo4j".to_string())) as Box<dyn Error + Send + Sync>)?;
+        Ok(store.with_tenant(context.tenant.as_deref().unwrap_or(DEFAULT_TENANT)))
+    }
+}
+
+fn required_str<'a>(params: &'a HashMap<String, Value>, name: &str) -> Result<&'a str, Box<dyn Error + Send + Sync>> {
+    params.get(name)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Box::new(KnowledgeGraphPluginError(format!("{} is required", name))) as Box<dyn Error + Send + Sync>)
+}
+
+#[async_trait]
+impl Plugin for KnowledgeGraphPlugin {
+    fn name(&self) -> &str {
+        "knowledge_graph"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![
+            Capability {
+                name: "ingest".to_string(),
+                description: "Extract entities and relations from text with the model and add them to the graph".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "text".to_string(),
+                        description: "Text to extract facts from".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "source".to_string(),
+                        description: "Where the text came from, such as a URL, kept as its provenance".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "query".to_string(),
+                description: "Find the entities a question names, the facts about them and where those came from".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "question".to_string(),
+                        description: "Question or keywords naming the entities to look up".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "limit".to_string(),
+                        description: format!("Maximum number of matching entities (default: {})", DEFAULT_QUERY_LIMIT),
+                        parameter_type: ParameterType::Number,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+        ]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        info!("Executing knowledge graph plugin capability: {}", capability);
+
+        let data = match capability {
+            "ingest" => {
+                let text = required_str(&params, "text")?;
+                let source = params.get("source").and_then(|v| v.as_str()).map(str::to_string);
+                let store = self.store(&context)?;
+                let sampler = context.sampling
+                    .ok_or_else(|| Box::new(KnowledgeGraphPluginError("Sampling is not available for this call".to_string())))?;
+
+                let reply = sampler
+                    .create_message(CreateMessageParams {
+                        messages: vec![SamplingMessage::user(text)],
+                        system_prompt: Some(EXTRACTION_PROMPT.to_string()),
+                        max_tokens: EXTRACTION_MAX_TOKENS,
+                        model_preferences: None,
+                    })
+                    .await
+                    .map_err(|e| Box::new(KnowledgeGraphPluginError(e.to_string())))?;
+                let extraction = parse_extraction(reply.text()).map_err(|e| Box::new(KnowledgeGraphPluginError(e)))?;
+
+                let provenance = Provenance {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    text: text.to_string(),
+                    source,
+                    model: Some(reply.model.clone()).filter(|model| !model.is_empty()),
+                    ingested_at: chrono::Utc::now(),
+                };
+                info!(
+                    "Ingesting {} entities and {} relations as {}",
+                    extraction.entities.len(),
+                    extraction.relations.len(),
+                    provenance.id
+                );
+                let relations: Vec<Relation> = extraction
+                    .relations
+                    .into_iter()
+                    .map(|relation| Relation { provenance: Some(provenance.id.clone()), ..relation })
+                    .collect();
+                let result = json!({
+                    "provenance": provenance,
+                    "entities": extraction.entities,
+                    "relations": relations,
+                });
+                store.ingest_knowledge(provenance, extraction.entities, relations).await?;
+                result
+            }
+            "query" => {
+                let question = required_str(&params, "question")?;
+                let limit = params.get("limit").and_then(|v| v.as_u64()).map_or(DEFAULT_QUERY_LIMIT, |limit| limit as usize);
+                let terms = query_terms(question);
+                if terms.is_empty() {
+                    return Err(Box::new(KnowledgeGraphPluginError(format!("Nothing to look for in: {}", question))));
+                }
+                let graph = self.store(&context)?.query_knowledge(&terms, limit).await?;
+                json!({
+                    "terms": terms,
+                    "entities": graph.entities,
+                    "relations": graph.relations,
+                    "sources": graph.sources,
+                })
+            }
+            _ => return Err(Box::new(KnowledgeGraphPluginError(format!("Unknown capability: {}", capability)))),
+        };
+
+        Ok(PluginResult {
+            success: true,
+            data,
+            metrics: None,
+            context_updates: None,
+        })
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        match self.store {
+            Some(_) => HealthStatus::healthy(),
+            None => HealthStatus::unhealthy("No context store; the knowledge graph cannot be kept"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extraction_is_parsed_and_tidied() {
+        let reply = "Here you go:\n```json\n{\"entities\": [{\"name\": \" Ada \", \"type\": \"person\"}, {\"name\": \"\"}],
+            \"relations\": [{\"source\": \"Ada\", \"relation\": \"Works At\", \"target\": \"Acme\"},
+                            {\"source\": \"Ada\", \"relation\": \"works-at\", \"target\": \"Acme\"},
+                            {\"source\": \"Ada\", \"relation\": \" \", \"target\": \"Bob\"}]}\n```";
+        let extraction = parse_extraction(reply).unwrap();
+        assert_eq!(
+            extraction.entities,
+            vec![
+                Entity { name: "Ada".to_string(), kind: "person".to_string() },
+                Entity { name: "Acme".to_string(), kind: String::new() },
+            ]
+        );
+        assert_eq!(extraction.relations.len(), 1);
+        assert_eq!(extraction.relations[0].relation, "works_at");
+
+        assert!(parse_extraction("I could not find anything").is_err());
+        assert_eq!(parse_extraction("{}").unwrap(), Extraction::default());
+    }
+
+    #[test]
+    fn test_query_terms_skip_common_words() {
+        assert_eq!(query_terms("Where does Ada work? Tell me about ADA and Acme-Corp"), vec!["ada", "work", "acme", "corp"]);
+        assert!(query_terms("who is it?").is_empty());
+    }
+}
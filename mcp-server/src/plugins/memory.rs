@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use tracing::info;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::context::{ContextStore, Memory, DEFAULT_TENANT};
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
+
+/// Memories `search` returns when the caller gives no limit
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+#[derive(Debug)]
+struct MemoryPluginError(String);
+
+impl fmt::Display for MemoryPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for MemoryPluginError {}
+
+/// Explicit long-term memory for the model: values it chose to remember
+/// under a key, kept in the context store and optionally forgotten after a
+/// time to live
+pub struct MemoryPlugin {
+    store: Option<Arc<dyn ContextStore>>,
+}
+
+impl MemoryPlugin {
+    /// Without a store every capability fails
+    pub fn new(store: Option<Arc<dyn ContextStore>>) -> Self {
+        Self { store }
+    }
+
+    /// The store, scoped to the calling session's tenant
+    fn store(&self, context: &Context) -> Result<Arc<dyn ContextStore>, Box<dyn Error + Send + Sync>> {
+        let store = self.store
+            .clone()
+            .ok_or_else(|| Box::new(MemoryPluginError("Memory needs a context store, such as Neo4j".to_string())) as Box<dyn Error + Send + Sync>)?;
+        Ok(store.with_tenant(context.tenant.as_deref().unwrap_or(DEFAULT_TENANT)))
+    }
+}
+
+fn required_str<'a>(params: &'a HashMap<String, Value>, name: &str) -> Result<&'a str, Box<dyn Error + Send + Sync>> {
+    params.get(name)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Box::new(MemoryPluginError(format!("{} is required", name))) as Box<dyn Error + Send + Sync>)
+}
+
+#[async_trait]
+impl Plugin for MemoryPlugin {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        let key = ParameterDefinition {
+            name: "key".to_string(),
+            description: "Name the memory is stored under".to_string(),
+            parameter_type: ParameterType::String,
+            required: true,
+        };
+
+        vec![
+            Capability {
+                name: "remember".to_string(),
+                description: "Store a value under a key, replacing what was stored there".to_string(),
+                parameters: vec![
+                    key.clone(),
+                    ParameterDefinition {
+                        name: "value".to_string(),
+                        description: "What to remember; any JSON value".to_string(),
+                        parameter_type: ParameterType::Object,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "ttl".to_string(),
+                        description: "Seconds until the memory is forgotten (default: kept)".to_string(),
+                        parameter_type: ParameterType::Number,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "recall".to_string(),
+                description: "Get the value stored under a key".to_string(),
+                parameters: vec![key],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "search".to_string(),
+                description: "Find memories whose key or value contains some text".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "text".to_string(),
+                        description: "Text to look for, ignoring case".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "limit".to_string(),
+                        description: "Maximum number of memories to return (default: 10)".to_string(),
+                        parameter_type: ParameterType::Number,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+        ]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        info!("Executing memory plugin capability: {}", capability);
+
+        let data = match capability {
+            "remember" => {
+                let key = required_str(&params, "key")?;
+                let value = params.get("value")
+                    .cloned()
+                    .ok_or_else(|| Box::new(MemoryPluginError("value is required".to_string())))?;
+                let ttl = match params.get("ttl") {
+                    None | Some(Value::Null) => None,
+                    Some(ttl) => Some(ttl.as_u64().filter(|secs| *secs > 0).ok_or_else(|| {
+                        Box::new(MemoryPluginError(format!("ttl must be a positive number of seconds, got {}", ttl)))
+                    })?),
+                };
+                let stored_at = chrono::Utc::now();
+                let memory = Memory {
+                    key: key.to_string(),
+                    value,
+                    stored_at,
+                    expires_at: ttl.map(|secs| stored_at + chrono::Duration::seconds(secs as i64)),
+                };
+                let expires_at = memory.expires_at;
+                self.store(&context)?.remember(memory).await?;
+                json!({ "key": key, "stored_at": stored_at, "expires_at": expires_at })
+            }
+            "recall" => {
+                let key = required_str(&params, "key")?;
+                let memory = self.store(&context)?.recall(key).await?;
+                json!({ "key": key, "found": memory.is_some(), "memory": memory })
+            }
+            "search" => {
+                let text = required_str(&params, "text")?;
+                let limit = params.get("limit").and_then(|v| v.as_u64()).map_or(DEFAULT_SEARCH_LIMIT, |limit| limit as usize);
+                let memories = self.store(&context)?.search_memories(text, limit).await?;
+                json!({ "text": text, "memories": memories })
+            }
+            _ => return Err(Box::new(MemoryPluginError(format!("Unknown capability: {}", capability)))),
+        };
+
+        Ok(PluginResult {
+            success: true,
+            data,
+            metrics: None,
+            context_updates: None,
+        })
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        match self.store {
+            Some(_) => HealthStatus::healthy(),
+            None => HealthStatus::unhealthy("No context store; memories cannot be kept"),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use tracing::info;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::context::{ContextStore, MetricAggregate, DEFAULT_TENANT};
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
+
+/// Bucket size when the caller gives none: five minutes
+const DEFAULT_WINDOW_SECS: i64 = 300;
+
+/// How far back `aggregate` looks when the caller gives no `since`
+const DEFAULT_RANGE_HOURS: i64 = 24;
+
+#[derive(Debug)]
+struct MetricsPluginError(String);
+
+impl fmt::Display for MetricsPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for MetricsPluginError {}
+
+/// Trends of the metrics stored in the context store, downsampled there so
+/// that raw readings never have to be pulled out
+pub struct MetricsPlugin {
+    store: Option<Arc<dyn ContextStore>>,
+}
+
+impl MetricsPlugin {
+    /// Without a store every capability fails
+    pub fn new(store: Option<Arc<dyn ContextStore>>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Plugin for MetricsPlugin {
+    fn name(&self) -> &str {
+        "metrics"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![Capability {
+            name: "aggregate".to_string(),
+            description: "Combine a stored metric's readings into one value per time window".to_string(),
+            parameters: vec![
+                ParameterDefinition {
+                    name: "metric_type".to_string(),
+                    description: "Metric to aggregate, e.g. cpu_usage".to_string(),
+                    parameter_type: ParameterType::String,
+                    required: true,
+                },
+                ParameterDefinition {
+                    name: "window".to_string(),
+                    description: format!("Seconds per bucket (default: {})", DEFAULT_WINDOW_SECS),
+                    parameter_type: ParameterType::Number,
+                    required: false,
+                },
+                ParameterDefinition {
+                    name: "function".to_string(),
+                    description: "avg, min, max or p95 (default: avg)".to_string(),
+                    parameter_type: ParameterType::String,
+                    required: false,
+                },
+                ParameterDefinition {
+                    name: "since".to_string(),
+                    description: format!("RFC 3339 time to start from (default: {} hours ago)", DEFAULT_RANGE_HOURS),
+                    parameter_type: ParameterType::String,
+                    required: false,
+                },
+            ],
+            version: None,
+            deprecated: None,
+        }]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        _context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        if capability != "aggregate" {
+            return Err(Box::new(MetricsPluginError(format!("Unknown capability: {}", capability))));
+        }
+        let metric_type = params.get("metric_type")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| Box::new(MetricsPluginError("metric_type is required".to_string())))?;
+        let window = match params.get("window") {
+            None | Some(Value::Null) => DEFAULT_WINDOW_SECS,
+            Some(window) => window.as_i64().filter(|secs| *secs > 0).ok_or_else(|| {
+                Box::new(MetricsPluginError(format!("window must be a positive number of seconds, got {}", window)))
+            })?,
+        };
+        let function: MetricAggregate = match params.get("function") {
+            None | Some(Value::Null) => MetricAggregate::default(),
+            Some(function) => serde_json::from_value(function.clone()).map_err(|_| {
+                Box::new(MetricsPluginError(format!("function must be avg, min, max or p95, got {}", function)))
+            })?,
+        };
+        let since = match params.get("since").and_then(|v| v.as_str()) {
+            Some(since) => chrono::DateTime::parse_from_rfc3339(since)
+                .map_err(|e| Box::new(MetricsPluginError(format!("since is not an RFC 3339 time: {}", e))))?
+                .with_timezone(&chrono::Utc),
+            None => chrono::Utc::now() - chrono::Duration::hours(DEFAULT_RANGE_HOURS),
+        };
+        // The sampler stores host metrics under the default tenant, whichever
+        // tenant the caller's session is in
+        let store = self.store
+            .clone()
+            .ok_or_else(|| Box::new(MetricsPluginError("Metrics need a context store, such as Neo4j".to_string())))?
+            .with_tenant(DEFAULT_TENANT);
+
+        info!("Aggregating {} by {}s windows since {}", metric_type, window, since);
+        let buckets = store
+            .aggregate_metrics(metric_type, chrono::Duration::seconds(window), function, since)
+            .await?;
+
+        Ok(PluginResult {
+            success: true,
+            data: json!({
+                "metric_type": metric_type,
+                "window": window,
+                "function": function,
+                "since": since,
+                "buckets": buckets,
+            }),
+            metrics: None,
+            context_updates: None,
+        })
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        match self.store {
+            Some(_) => HealthStatus::healthy(),
+            None => HealthStatus::unhealthy("No context store; there are no metrics to aggregate"),
+        }
+    }
+}
@@ -4,10 +4,28 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::sync::Arc;
 
+use crate::mcp::Deprecation;
+
 pub mod system_info;
+pub mod container;
+pub mod gpu;
 pub mod home_assistant;
 pub mod http;
 pub mod neo4j;
+pub mod notification;
+pub mod embeddings;
+pub mod memory;
+pub mod knowledge_graph;
+pub mod metrics;
+pub mod ollama;
+pub mod speech;
+pub mod vision;
+pub mod network;
+pub mod netdiag;
+pub mod jira;
+pub mod summarize;
+pub mod filesystem;
+pub mod proxy;
 
 /// Represents the capability of a plugin
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +33,11 @@ pub struct Capability {
     pub name: String,
     pub description: String,
     pub parameters: Vec<ParameterDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Set on capabilities kept only for older callers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<Deprecation>,
 }
 
 /// Defines a parameter for a plugin capability
@@ -42,6 +65,30 @@ pub struct Context {
     pub correlation_id: String,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub parameters: HashMap<String, serde_json::Value>,
+    /// Asks the calling client (or the configured fallback) for an LLM
+    /// completion; `None` where no sampling is available
+    pub sampling: Option<crate::mcp::sampling::Sampler>,
+    /// Directories the calling client approved for file access; `None` when
+    /// the client does not declare roots
+    pub roots: Option<Vec<std::path::PathBuf>>,
+    /// Tenant of the calling session; context data is scoped to it, and
+    /// `None` means the default tenant
+    pub tenant: Option<String>,
+}
+
+#[cfg(test)]
+impl Context {
+    /// A context with no parameters, sampling, roots or tenant, for plugin tests
+    pub fn for_test() -> Self {
+        Self {
+            correlation_id: "test-123".to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        }
+    }
 }
 
 /// Plugin execution result
@@ -53,6 +100,37 @@ pub struct PluginResult {
     pub context_updates: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// Plugin health report
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthStatus {
+    pub healthy: bool,
+    pub message: Option<String>,
+    pub details: Option<serde_json::Value>,
+}
+
+impl HealthStatus {
+    pub fn healthy() -> Self {
+        Self {
+            healthy: true,
+            message: None,
+            details: None,
+        }
+    }
+
+    pub fn unhealthy(message: impl Into<String>) -> Self {
+        Self {
+            healthy: false,
+            message: Some(message.into()),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
 /// Core plugin trait that all plugins must implement
 #[async_trait]
 pub trait Plugin: Send + Sync {
@@ -73,6 +151,11 @@ pub trait Plugin: Send + Sync {
         params: HashMap<String, serde_json::Value>,
     ) -> Result<PluginResult, Box<dyn Error + Send + Sync>>;
     
+    /// Called once when the plugin is registered, before `initialize`, with
+    /// the bus to publish events on and subscribe to
+    #[allow(unused_variables)]
+    fn attach_events(&self, events: &crate::mcp::events::EventBus) {}
+
     /// Called when the plugin is loaded
     #[allow(unused_variables)]
     async fn initialize(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
@@ -84,6 +167,11 @@ pub trait Plugin: Send + Sync {
     async fn shutdown(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
         Ok(())
     }
+
+    /// Reports whether the plugin and any upstream it depends on are reachable
+    async fn health_check(&self) -> HealthStatus {
+        HealthStatus::healthy()
+    }
 }
 
 /// Plugin manager to handle plugin lifecycle
@@ -2,29 +2,47 @@ use async_trait::async_trait;
 use neo4rs::*;
 use serde_json::{Value, json};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::RwLock;
 use std::error::Error as StdError;
-use tracing::debug;
+use tracing::{debug, info};
 
-use crate::plugins::{Plugin, Context, Capability, ParameterDefinition, ParameterType, PluginResult};
+use crate::plugins::{Plugin, Context, Capability, HealthStatus, ParameterDefinition, ParameterType, PluginResult};
+use crate::secrets::{Secret, Secrets};
 
 type Result<T> = std::result::Result<T, Box<dyn StdError + Send + Sync>>;
 
+/// Connects when initialized rather than when created, so the server can
+/// start without Neo4j and connect once it is up
 pub struct Neo4jPlugin {
-    graph: Graph,
+    uri: String,
+    user: String,
+    /// `None` when NEO4J_PASSWORD is not set
+    password: Option<Secret>,
+    graph: RwLock<Option<Graph>>,
 }
 
 impl Neo4jPlugin {
-    pub async fn new(uri: &str, user: &str, password: &str) -> Result<Self> {
-        let config = ConfigBuilder::new()
-            .uri(uri)
-            .user(user)
-            .password(password)
-            .max_connections(4)
-            .build()?;
-            
-        let graph = Graph::connect(config).await?;
-        Ok(Self { graph })
+    pub fn new(uri: &str, user: &str, password: Option<Secret>) -> Self {
+        Self {
+            uri: uri.to_string(),
+            user: user.to_string(),
+            password,
+            graph: RwLock::new(None),
+        }
+    }
+
+    /// Configured from NEO4J_URI, NEO4J_USER and NEO4J_PASSWORD, which may
+    /// be a secret reference
+    pub fn from_env() -> Self {
+        Self::new(
+            &std::env::var("NEO4J_URI").unwrap_or_else(|_| "bolt://localhost:7687".to_string()),
+            &std::env::var("NEO4J_USER").unwrap_or_else(|_| "neo4j".to_string()),
+            std::env::var("NEO4J_PASSWORD").ok().map(Secret::from),
+        )
+    }
+
+    fn graph(&self) -> Result<Graph> {
+        self.graph.read().unwrap().clone().ok_or_else(|| format!("Not connected to Neo4j at {}", self.uri).into())
     }
 
     pub fn get_capabilities() -> Vec<Capability> {
@@ -46,6 +64,8 @@ impl Neo4jPlugin {
                         required: false,
                     }
                 ],
+                version: None,
+                deprecated: None,
             }
         ]
     }
@@ -54,7 +74,7 @@ impl Neo4jPlugin {
         debug!("Executing Neo4j query: {} with params: {:?}", query, params);
         
         let mut rows = Vec::new();
-        let mut result = self.graph.execute(Query::new(query.to_string())).await?;
+        let mut result = self.graph()?.execute(Query::new(query.to_string())).await?;
         
         while let Some(row) = result.next().await? {
             let mut row_data = serde_json::Map::new();
@@ -102,6 +122,39 @@ impl Plugin for Neo4jPlugin {
         "1.0.0"
     }
     
+    async fn initialize(&self) -> Result<()> {
+        let password = self.password.as_ref().ok_or("NEO4J_PASSWORD must be set")?;
+        let password = Secrets::global().resolve(password).await?;
+        let config = ConfigBuilder::new()
+            .uri(&self.uri)
+            .user(&self.user)
+            .password(password)
+            .max_connections(4)
+            .build()?;
+
+        let graph = Graph::connect(config).await?;
+        *self.graph.write().unwrap() = Some(graph);
+        info!("Connected to Neo4j at {}", self.uri);
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        self.graph.write().unwrap().take();
+        Ok(())
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        let details = json!({ "uri": self.uri });
+        let graph = match self.graph() {
+            Ok(graph) => graph,
+            Err(e) => return HealthStatus::unhealthy(e.to_string()).with_details(details),
+        };
+        match graph.run(Query::new("RETURN 1".to_string())).await {
+            Ok(()) => HealthStatus::healthy().with_details(details),
+            Err(e) => HealthStatus::unhealthy(format!("Neo4j unreachable: {}", e)).with_details(details),
+        }
+    }
+
     fn capabilities(&self) -> Vec<Capability> {
         vec![
             Capability {
@@ -121,6 +174,8 @@ impl Plugin for Neo4jPlugin {
                         required: false,
                     }
                 ],
+                version: None,
+                deprecated: None,
             }
         ]
     }
@@ -0,0 +1,708 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::proto::rr::RecordType;
+use hickory_resolver::{Name, TokioAsyncResolver};
+use tracing::{info, debug};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use x509_parser::extensions::GeneralName;
+
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType};
+use super::network::NetworkLimits;
+
+const RECORD_TYPES: [&str; 10] = ["A", "AAAA", "CNAME", "MX", "NS", "TXT", "SOA", "SRV", "CAA", "PTR"];
+
+/// Where the WHOIS lookup of a domain starts; IANA refers it to the registry
+const IANA_WHOIS: &str = "whois.iana.org:43";
+
+/// Most of a WHOIS response that is read, and of it returned as `raw`
+const MAX_WHOIS_BYTES: u64 = 1024 * 1024;
+const MAX_RAW_CHARS: usize = 4096;
+
+/// Ports `tls_cert_info` connects to unless `NETDIAG_TLS_PORTS` names others:
+/// HTTPS, the TLS ports of mail, LDAP, DNS, FTP, Telnet, SIP and IRC, and
+/// the usual alternative HTTPS port
+const TLS_PORTS: [u16; 13] = [443, 465, 636, 853, 989, 990, 992, 993, 995, 5061, 6697, 8443, 9443];
+
+#[derive(Debug)]
+struct NetDiagPluginError(String);
+
+impl fmt::Display for NetDiagPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for NetDiagPluginError {}
+
+fn error(message: impl Into<String>) -> Box<dyn Error + Send + Sync> {
+    Box::new(NetDiagPluginError(message.into()))
+}
+
+#[derive(Debug, Clone)]
+pub struct NetDiagConfig {
+    /// WHOIS server asked instead of following IANA's referral, as host:port
+    pub whois_server: Option<String>,
+    pub timeout: Duration,
+    /// Which hosts `tls_cert_info` may connect to, as for the network tool
+    pub limits: NetworkLimits,
+    /// The only ports `tls_cert_info` connects to, so it cannot probe others
+    pub tls_ports: Vec<u16>,
+}
+
+impl NetDiagConfig {
+    pub fn from_env() -> Self {
+        Self {
+            whois_server: std::env::var("WHOIS_SERVER").ok().filter(|v| !v.is_empty()),
+            timeout: Duration::from_secs(
+                std::env::var("NETDIAG_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(10),
+            ),
+            limits: NetworkLimits::from_env(),
+            tls_ports: std::env::var("NETDIAG_TLS_PORTS").ok()
+                .map(|ports| ports.split(',').filter_map(|port| port.trim().parse().ok()).collect())
+                .unwrap_or_else(|| TLS_PORTS.to_vec()),
+        }
+    }
+}
+
+/// DNS, WHOIS and TLS certificate checks for operators: what a name resolves
+/// to on one or several resolvers, when a domain expires, and when a host's
+/// certificate does
+pub struct NetDiagPlugin {
+    config: NetDiagConfig,
+}
+
+impl Default for NetDiagPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NetDiagPlugin {
+    pub fn new() -> Self {
+        Self::with_config(NetDiagConfig::from_env())
+    }
+
+    pub fn with_config(config: NetDiagConfig) -> Self {
+        Self { config }
+    }
+
+    /// The system resolver, or with `server` only that one, uncached
+    fn resolver(&self, server: Option<SocketAddr>) -> TokioAsyncResolver {
+        let (config, mut options) = match server {
+            Some(server) => (
+                ResolverConfig::from_parts(None, vec![], NameServerConfigGroup::from_ips_clear(&[server.ip()], server.port(), true)),
+                ResolverOpts::default(),
+            ),
+            None => hickory_resolver::system_conf::read_system_conf().unwrap_or_else(|e| {
+                debug!("No system DNS configuration ({}), using public resolvers", e);
+                (ResolverConfig::default(), ResolverOpts::default())
+            }),
+        };
+        options.timeout = self.config.timeout;
+        options.attempts = 1;
+        options.cache_size = 0;
+        TokioAsyncResolver::tokio(config, options)
+    }
+
+    /// The records of `name` as one resolver sees them, with the response
+    /// code when there are none
+    async fn lookup(&self, name: &Name, record_type: RecordType, server: Option<SocketAddr>) -> Value {
+        match self.resolver(server).lookup(name.clone(), record_type).await {
+            Ok(lookup) => {
+                let records: Vec<Value> = lookup.record_iter()
+                    .filter(|record| record.record_type() == record_type || record_type == RecordType::ANY)
+                    .filter_map(|record| Some(json!({
+                        "name": record.name().to_string(),
+                        "type": record.record_type().to_string(),
+                        "ttl": record.ttl(),
+                        "value": record.data()?.to_string(),
+                    })))
+                    .collect();
+                json!({ "status": "NOERROR", "records": records })
+            }
+            Err(e) => match e.kind() {
+                ResolveErrorKind::NoRecordsFound { response_code, .. } => {
+                    json!({ "status": format!("{:?}", response_code).to_uppercase(), "records": [] })
+                }
+                _ => json!({ "status": "ERROR", "error": e.to_string(), "records": [] }),
+            },
+        }
+    }
+
+    /// Looks `name` up on the system resolver, or on each of `servers` to
+    /// see whether a change has reached them all
+    async fn dns_lookup(&self, name: &str, record_type: &str, servers: &[String]) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let record_type = record_type.to_uppercase();
+        if !RECORD_TYPES.contains(&record_type.as_str()) {
+            return Err(error(format!("Unsupported record type {}; use one of {}", record_type, RECORD_TYPES.join(", "))));
+        }
+        let record_type = RecordType::from_str(&record_type)?;
+        // An address asked for PTR means its reverse name
+        let query = match (record_type, name.parse::<IpAddr>()) {
+            (RecordType::PTR, Ok(address)) => Name::from(address),
+            _ => Name::from_str(name).map_err(|e| error(format!("Invalid name {}: {}", name, e)))?,
+        };
+
+        if servers.is_empty() {
+            let mut result = self.lookup(&query, record_type, None).await;
+            result["name"] = json!(name);
+            result["type"] = json!(record_type.to_string());
+            return Ok(result);
+        }
+
+        let servers: Vec<SocketAddr> = servers.iter()
+            .map(|server| server.parse()
+                .or_else(|_| server.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+                .map_err(|_| error(format!("Invalid DNS server {}; use an IP address, optionally with port", server))))
+            .collect::<Result<_, _>>()?;
+        let answers = futures_util::future::join_all(servers.iter().map(|server| self.lookup(&query, record_type, Some(*server)))).await;
+
+        // Every server answering with the same values means the change has propagated
+        let value_sets: Vec<BTreeSet<String>> = answers.iter()
+            .map(|answer| answer["records"].as_array().into_iter().flatten()
+                .filter_map(|record| record["value"].as_str().map(str::to_string))
+                .collect())
+            .collect();
+        let consistent = answers.iter().all(|answer| answer["status"] != "ERROR")
+            && value_sets.windows(2).all(|pair| pair[0] == pair[1]);
+        let results: Vec<Value> = servers.iter().zip(answers)
+            .map(|(server, mut answer)| {
+                answer["server"] = json!(server.to_string());
+                answer
+            })
+            .collect();
+        Ok(json!({
+            "name": name,
+            "type": record_type.to_string(),
+            "consistent": consistent,
+            "results": results,
+        }))
+    }
+
+    async fn whois_query(&self, server: &str, query: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        debug!("WHOIS {} for {}", server, query);
+        let exchange = async {
+            let mut stream = TcpStream::connect(server).await?;
+            stream.write_all(format!("{}\r\n", query).as_bytes()).await?;
+            let mut response = Vec::new();
+            stream.take(MAX_WHOIS_BYTES).read_to_end(&mut response).await?;
+            Ok::<_, std::io::Error>(response)
+        };
+        let response = tokio::time::timeout(self.config.timeout, exchange).await
+            .map_err(|_| error(format!("WHOIS server {} did not answer in time", server)))?
+            .map_err(|e| error(format!("WHOIS server {} unreachable: {}", server, e)))?;
+        Ok(String::from_utf8_lossy(&response).into_owned())
+    }
+
+    /// Asks the registry for `domain`, then the registrar it names, and
+    /// picks the usual fields out of their answers
+    async fn whois(&self, domain: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let domain = domain.trim().trim_end_matches('.').to_lowercase();
+        if domain.len() > 253
+            || !domain.contains('.')
+            || !domain.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        {
+            return Err(error(format!("Invalid domain {}; internationalized names must be given in punycode", domain)));
+        }
+
+        let registry = match &self.config.whois_server {
+            Some(server) => server.clone(),
+            None => {
+                let tld = domain.rsplit('.').next().unwrap_or_default();
+                let iana = self.whois_query(IANA_WHOIS, tld).await?;
+                whois_referral(&iana).ok_or_else(|| error(format!("No WHOIS server is known for .{}", tld)))?
+            }
+        };
+        let registry_response = self.whois_query(&registry, &domain).await?;
+        let mut responses = vec![(registry.clone(), registry_response)];
+        // A thin registry only knows the registrar; the registrar's answer is
+        // a bonus, so failing to get it is not an error
+        if let Some(registrar) = whois_referral(&responses[0].1).filter(|registrar| *registrar != registry) {
+            match self.whois_query(&registrar, &domain).await {
+                Ok(response) => responses.push((registrar, response)),
+                Err(e) => debug!("Skipping registrar WHOIS: {}", e),
+            }
+        }
+
+        let mut fields = serde_json::Map::new();
+        for (_, response) in &responses {
+            for (key, value) in parse_whois(response) {
+                fields.entry(key).or_insert(value);
+            }
+        }
+        let registered = !responses[0].1.lines().any(|line| {
+            let line = line.trim().to_lowercase();
+            ["no match for", "not found", "no data found", "no entries found", "status: free", "status: available"]
+                .iter()
+                .any(|marker| line.starts_with(marker))
+        });
+        let days_until_expiry = fields.get("expires")
+            .and_then(|expires| expires.as_str())
+            .and_then(parse_whois_date)
+            .map(|expires| (expires - Utc::now()).num_days());
+        let (server, raw) = responses.last().expect("the registry answered");
+        Ok(json!({
+            "domain": domain,
+            "registered": registered,
+            "registrar": fields.get("registrar"),
+            "created": fields.get("created"),
+            "updated": fields.get("updated"),
+            "expires": fields.get("expires"),
+            "days_until_expiry": days_until_expiry,
+            "name_servers": fields.get("name_servers").cloned().unwrap_or_else(|| json!([])),
+            "status": fields.get("status").cloned().unwrap_or_else(|| json!([])),
+            "whois_server": server,
+            "raw": raw.trim().chars().take(MAX_RAW_CHARS).collect::<String>(),
+        }))
+    }
+
+    async fn tls_handshake(&self, host: &str, address: IpAddr, port: u16, verify: bool) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let connector = tokio_native_tls::native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(!verify)
+            .danger_accept_invalid_hostnames(!verify)
+            .build()?;
+        let handshake = async {
+            let stream = TcpStream::connect((address, port)).await
+                .map_err(|e| error(format!("Cannot connect to {}:{}: {}", host, port, e)))?;
+            let tls = tokio_native_tls::TlsConnector::from(connector).connect(host, stream).await?;
+            let certificate = tls.get_ref().peer_certificate()?
+                .ok_or_else(|| error(format!("{}:{} sent no certificate", host, port)))?;
+            Ok::<_, Box<dyn Error + Send + Sync>>(certificate.to_der()?)
+        };
+        tokio::time::timeout(self.config.timeout, handshake).await
+            .map_err(|_| error(format!("TLS handshake with {}:{} timed out", host, port)))?
+    }
+
+    /// The certificate `host` presents, whether it is trusted, and how long
+    /// it has left. Only TLS ports of hosts the network limits allow are
+    /// tried, so that connection errors say nothing about other services.
+    async fn tls_cert_info(&self, host: &str, port: u16, warn_days: i64) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        if !self.config.tls_ports.contains(&port) {
+            return Err(error(format!(
+                "Port {} is not a TLS port; use one of {:?} or add it to NETDIAG_TLS_PORTS",
+                port, self.config.tls_ports
+            )));
+        }
+        // Connected to by address, so the name cannot resolve elsewhere later
+        let address = self.config.limits.resolve(host).await?;
+        // A certificate that fails verification is the one worth seeing, so
+        // fetch it again without verifying
+        let (der, verification_error) = match self.tls_handshake(host, address, port, true).await {
+            Ok(der) => (der, None),
+            Err(e) => (self.tls_handshake(host, address, port, false).await?, Some(e.to_string())),
+        };
+        let (_, certificate) = x509_parser::parse_x509_certificate(&der)
+            .map_err(|e| error(format!("Cannot parse the certificate of {}:{}: {}", host, port, e)))?;
+
+        let not_before = certificate.validity().not_before.timestamp();
+        let not_after = certificate.validity().not_after.timestamp();
+        let days_remaining = (not_after - Utc::now().timestamp()).div_euclid(86400);
+        let alt_names: Vec<String> = certificate.subject_alternative_name().ok().flatten()
+            .map(|extension| extension.value.general_names.iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) => Some(name.to_string()),
+                    GeneralName::IPAddress(bytes) => match bytes.len() {
+                        4 => Some(IpAddr::from(<[u8; 4]>::try_from(*bytes).ok()?).to_string()),
+                        16 => Some(IpAddr::from(<[u8; 16]>::try_from(*bytes).ok()?).to_string()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect())
+            .unwrap_or_default();
+        let rfc3339 = |timestamp: i64| DateTime::from_timestamp(timestamp, 0).map(|time| time.to_rfc3339());
+
+        Ok(json!({
+            "host": host,
+            "port": port,
+            "verified": verification_error.is_none(),
+            "verification_error": verification_error,
+            "subject": certificate.subject().to_string(),
+            "issuer": certificate.issuer().to_string(),
+            "self_signed": certificate.subject() == certificate.issuer(),
+            "serial": certificate.raw_serial_as_string(),
+            "subject_alt_names": alt_names,
+            "not_before": rfc3339(not_before),
+            "not_after": rfc3339(not_after),
+            "days_remaining": days_remaining,
+            "expired": days_remaining < 0,
+            "expiring_soon": days_remaining < warn_days,
+        }))
+    }
+}
+
+/// The next WHOIS server named in a response: IANA's `refer:`/`whois:` or a
+/// registry's `Registrar WHOIS Server:`
+fn whois_referral(response: &str) -> Option<String> {
+    response.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| matches!(key.trim().to_lowercase().as_str(), "refer" | "whois" | "registrar whois server"))
+        .map(|(_, server)| server.trim().trim_start_matches("whois://").trim_end_matches('/').to_lowercase())
+        // Referrals go to port 43 of a host name, never elsewhere
+        .filter(|server| !server.is_empty() && server.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.'))
+        .map(|server| format!("{}:43", server))
+}
+
+/// The registrar, dates, name servers and statuses in a WHOIS response,
+/// under the names registries use most
+fn parse_whois(response: &str) -> Vec<(String, Value)> {
+    const SINGLE: [(&str, &[&str]); 4] = [
+        ("registrar", &["registrar"]),
+        ("created", &["creation date", "created", "created on", "registered on", "registration time"]),
+        ("updated", &["updated date", "last-modified", "last updated", "changed"]),
+        ("expires", &[
+            "registry expiry date", "registrar registration expiration date", "expiration date",
+            "expiry date", "expire date", "expires", "expires on", "paid-till", "expiration time",
+        ]),
+    ];
+    let mut fields = Vec::new();
+    let mut name_servers = Vec::new();
+    let mut statuses = Vec::new();
+    for line in response.lines() {
+        let line = line.trim();
+        if line.starts_with('%') || line.starts_with('#') || line.starts_with(">>>") {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_lowercase(), value.trim());
+        if value.is_empty() {
+            continue;
+        }
+        match key.as_str() {
+            "name server" | "nserver" | "nameserver" => {
+                let server = value.split_whitespace().next().unwrap_or(value).trim_end_matches('.').to_lowercase();
+                if !name_servers.contains(&server) {
+                    name_servers.push(server);
+                }
+            }
+            "domain status" | "status" | "state" => {
+                let status = value.split_whitespace().next().unwrap_or(value).to_string();
+                if !statuses.contains(&status) {
+                    statuses.push(status);
+                }
+            }
+            key => {
+                if let Some((field, _)) = SINGLE.iter().find(|(_, keys)| keys.contains(&key)) {
+                    if !fields.iter().any(|(existing, _)| existing == field) {
+                        fields.push((field.to_string(), json!(value)));
+                    }
+                }
+            }
+        }
+    }
+    if !name_servers.is_empty() {
+        fields.push(("name_servers".to_string(), json!(name_servers)));
+    }
+    if !statuses.is_empty() {
+        fields.push(("status".to_string(), json!(statuses)));
+    }
+    fields
+}
+
+/// WHOIS dates come as RFC 3339, `2030-01-15 04:00:00`, `2030-01-15` or
+/// `2030.01.15`
+fn parse_whois_date(date: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Some(Ok(date)) = date.get(..19).map(|date| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")) {
+        return Some(date.and_utc());
+    }
+    let day = date.get(..10)?;
+    NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(day, "%Y.%m.%d"))
+        .ok()
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+#[async_trait]
+impl Plugin for NetDiagPlugin {
+    fn name(&self) -> &str {
+        "netdiag"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![
+            Capability {
+                name: "dns_lookup".to_string(),
+                description: "Look up DNS records, optionally on several resolvers to check propagation".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "name".to_string(),
+                        description: "Name to look up, or an IP address for PTR".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "type".to_string(),
+                        description: format!("Record type: {} (default: A)", RECORD_TYPES.join(", ")),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                    ParameterDefinition {
+                        name: "servers".to_string(),
+                        description: "Resolvers to ask, e.g. [\"1.1.1.1\", \"8.8.8.8\"] (default: the system resolver)".to_string(),
+                        parameter_type: ParameterType::Array,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "whois".to_string(),
+                description: "Look up a domain's registrar, name servers and expiry date".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "domain".to_string(),
+                        description: "Domain name, e.g. example.com".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "tls_cert_info".to_string(),
+                description: "Show the TLS certificate a host presents, whether it is trusted and when it expires".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "host".to_string(),
+                        description: "Host name to connect to".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "port".to_string(),
+                        description: "TLS port, e.g. 443 (default), 993 or 8443".to_string(),
+                        parameter_type: ParameterType::Number,
+                        required: false,
+                    },
+                    ParameterDefinition {
+                        name: "warn_days".to_string(),
+                        description: "Days before expiry to report expiring_soon (default: 30)".to_string(),
+                        parameter_type: ParameterType::Number,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+        ]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        _context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        info!("Executing netdiag plugin capability: {}", capability);
+        let required = |name: &str| params.get(name).and_then(|v| v.as_str()).ok_or_else(|| error(format!("{} is required", name)));
+
+        let data = match capability {
+            "dns_lookup" => {
+                let record_type = params.get("type").and_then(|v| v.as_str()).unwrap_or("A");
+                let servers: Vec<String> = params.get("servers")
+                    .and_then(|v| v.as_array())
+                    .map(|servers| servers.iter().filter_map(|s| s.as_str().map(str::to_string)).collect())
+                    .unwrap_or_default();
+                self.dns_lookup(required("name")?, record_type, &servers).await?
+            }
+            "whois" => self.whois(required("domain")?).await?,
+            "tls_cert_info" => {
+                let port = params.get("port").and_then(|v| v.as_u64()).unwrap_or(443);
+                let port = u16::try_from(port).map_err(|_| error(format!("Invalid port: {}", port)))?;
+                let warn_days = params.get("warn_days").and_then(|v| v.as_i64()).unwrap_or(30);
+                self.tls_cert_info(required("host")?, port, warn_days).await?
+            }
+            _ => return Err(error(format!("Unknown capability: {}", capability))),
+        };
+
+        Ok(PluginResult {
+            success: true,
+            data,
+            metrics: None,
+            context_updates: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::proto::op::{Message, MessageType, ResponseCode};
+    use hickory_resolver::proto::rr::{rdata::A, RData, Record};
+    use tokio::net::{TcpListener, UdpSocket};
+
+    fn plugin(whois_server: Option<String>) -> NetDiagPlugin {
+        NetDiagPlugin::with_config(NetDiagConfig {
+            whois_server,
+            timeout: Duration::from_secs(2),
+            limits: NetworkLimits::default(),
+            tls_ports: TLS_PORTS.to_vec(),
+        })
+    }
+
+    /// A DNS server answering 192.0.2.10 for every name but those starting
+    /// with `missing`, which do not exist
+    async fn dns_server() -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buffer = [0u8; 512];
+            while let Ok((length, peer)) = socket.recv_from(&mut buffer).await {
+                let request = Message::from_vec(&buffer[..length]).unwrap();
+                let mut response = Message::new();
+                response.set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_recursion_desired(true)
+                    .set_recursion_available(true);
+                for query in request.queries() {
+                    response.add_query(query.clone());
+                    if query.name().to_string().starts_with("missing") {
+                        response.set_response_code(ResponseCode::NXDomain);
+                    } else {
+                        response.add_answer(Record::from_rdata(query.name().clone(), 300, RData::A(A::new(192, 0, 2, 10))));
+                    }
+                }
+                socket.send_to(&response.to_vec().unwrap(), peer).await.unwrap();
+            }
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn test_dns_lookup_compares_resolvers() {
+        let server = dns_server().await.to_string();
+        let plugin = plugin(None);
+
+        let params = HashMap::from([
+            ("name".to_string(), json!("nas.example.com")),
+            ("servers".to_string(), json!([server, server])),
+        ]);
+        let result = plugin.execute("dns_lookup", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["consistent"], true);
+        assert_eq!(result.data["results"][0]["records"][0]["value"], "192.0.2.10");
+        assert_eq!(result.data["results"][1]["records"][0]["ttl"], 300);
+
+        let params = HashMap::from([
+            ("name".to_string(), json!("missing.example.com")),
+            ("servers".to_string(), json!([server])),
+        ]);
+        let result = plugin.execute("dns_lookup", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["results"][0]["status"], "NXDOMAIN");
+
+        let params = HashMap::from([("name".to_string(), json!("example.com")), ("type".to_string(), json!("AXFR"))]);
+        assert!(plugin.execute("dns_lookup", Context::for_test(), params).await.is_err());
+    }
+
+    #[test]
+    fn test_parse_whois() {
+        let response = "Domain Name: EXAMPLE.COM\r\n\
+            Registrar WHOIS Server: whois.registrar.example\r\n\
+            Creation Date: 1995-08-14T04:00:00Z\r\n\
+            Registry Expiry Date: 2030-08-13T04:00:00Z\r\n\
+            Registrar: Example Registrar, Inc.\r\n\
+            Domain Status: clientTransferProhibited https://icann.org/epp#clientTransferProhibited\r\n\
+            Name Server: A.IANA-SERVERS.NET\r\n\
+            Name Server: B.IANA-SERVERS.NET\r\n\
+            >>> Last update of whois database: 2024-01-01T00:00:00Z <<<\r\n";
+        let fields: HashMap<String, Value> = parse_whois(response).into_iter().collect();
+        assert_eq!(fields["registrar"], "Example Registrar, Inc.");
+        assert_eq!(fields["expires"], "2030-08-13T04:00:00Z");
+        assert_eq!(fields["name_servers"], json!(["a.iana-servers.net", "b.iana-servers.net"]));
+        assert_eq!(fields["status"], json!(["clientTransferProhibited"]));
+        assert_eq!(whois_referral(response).as_deref(), Some("whois.registrar.example:43"));
+        assert_eq!(whois_referral("refer:        whois.verisign-grs.com\n").as_deref(), Some("whois.verisign-grs.com:43"));
+        assert_eq!(whois_referral("refer: 10.0.0.1:8080\n"), None);
+
+        assert_eq!(parse_whois_date("2030-08-13 04:00:00 CLST").unwrap().to_rfc3339(), "2030-08-13T04:00:00+00:00");
+        assert_eq!(parse_whois_date("2030.08.13").unwrap().to_rfc3339(), "2030-08-13T00:00:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn test_whois_reports_expiry() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut query = [0u8; 256];
+                let length = stream.read(&mut query).await.unwrap();
+                let response = if query[..length].starts_with(b"example.org\r\n") {
+                    "Domain Name: example.org\nRegistrar: Example Registrar\nRegistry Expiry Date: 2099-01-01T00:00:00Z\n"
+                } else {
+                    "NOT FOUND\n"
+                };
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        let plugin = plugin(Some(server));
+
+        let result = plugin.execute("whois", Context::for_test(), HashMap::from([("domain".to_string(), json!("Example.org."))])).await.unwrap();
+        assert_eq!(result.data["registered"], true);
+        assert_eq!(result.data["registrar"], "Example Registrar");
+        assert!(result.data["days_until_expiry"].as_i64().unwrap() > 365);
+
+        let result = plugin.execute("whois", Context::for_test(), HashMap::from([("domain".to_string(), json!("free.org"))])).await.unwrap();
+        assert_eq!(result.data["registered"], false);
+        assert!(plugin.execute("whois", Context::for_test(), HashMap::from([("domain".to_string(), json!("a.org\r\nx"))])).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tls_cert_info_reads_an_untrusted_certificate() {
+        let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let identity = tokio_native_tls::native_tls::Identity::from_pkcs8(
+            generated.cert.pem().as_bytes(),
+            generated.key_pair.serialize_pem().as_bytes(),
+        ).unwrap();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(tokio_native_tls::native_tls::TlsAcceptor::new(identity).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    if let Ok(mut tls) = acceptor.accept(stream).await {
+                        let _ = tls.read(&mut [0u8; 1]).await;
+                    }
+                });
+            }
+        });
+
+        let params = HashMap::from([("host".to_string(), json!("127.0.0.1")), ("port".to_string(), json!(port))]);
+        // Only TLS ports are tried, so the plugin cannot probe for other services
+        let error = plugin(None).execute("tls_cert_info", Context::for_test(), params.clone()).await.unwrap_err();
+        assert!(error.to_string().contains("not a TLS port"));
+        let plugin = NetDiagPlugin::with_config(NetDiagConfig { tls_ports: vec![port], ..plugin(None).config });
+        let result = plugin.execute("tls_cert_info", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["verified"], false);
+        assert!(result.data["verification_error"].is_string());
+        assert_eq!(result.data["self_signed"], true);
+        assert_eq!(result.data["subject_alt_names"], json!(["localhost"]));
+        assert_eq!(result.data["expired"], false);
+
+        // Nor hosts outside the local network, unless those are allowed
+        let params = HashMap::from([("host".to_string(), json!("192.0.2.1")), ("port".to_string(), json!(port))]);
+        let error = plugin.execute("tls_cert_info", Context::for_test(), params).await.unwrap_err();
+        assert!(error.to_string().contains("NETWORK_ALLOW_PUBLIC_TARGETS"));
+    }
+}
@@ -0,0 +1,590 @@
+use async_trait::async_trait;
+use futures_util::stream::{self, StreamExt};
+use tracing::{info, debug};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::process::Command;
+
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType};
+
+/// Ports probed when the `ping` command is missing; a refused connection
+/// still shows the host is up
+const TCP_PING_PORTS: [u16; 4] = [22, 80, 443, 445];
+
+#[derive(Debug)]
+struct NetworkPluginError(String);
+
+impl fmt::Display for NetworkPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for NetworkPluginError {}
+
+fn error(message: impl Into<String>) -> Box<dyn Error + Send + Sync> {
+    Box::new(NetworkPluginError(message.into()))
+}
+
+/// What the plugin may do to the network
+#[derive(Debug, Clone)]
+pub struct NetworkLimits {
+    /// Allow targets outside private, loopback and link-local ranges
+    pub allow_public: bool,
+    pub max_ports: usize,
+    /// Connections a port scan keeps open at once
+    pub concurrency: usize,
+    pub connect_timeout: Duration,
+    /// Prefix length of the largest subnet arp_scan sweeps
+    pub min_prefix: u8,
+    pub max_ping_count: u32,
+}
+
+impl Default for NetworkLimits {
+    fn default() -> Self {
+        Self {
+            allow_public: false,
+            max_ports: 1024,
+            concurrency: 64,
+            connect_timeout: Duration::from_millis(1000),
+            min_prefix: 24,
+            max_ping_count: 5,
+        }
+    }
+}
+
+impl NetworkLimits {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            allow_public: std::env::var("NETWORK_ALLOW_PUBLIC_TARGETS")
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+                .unwrap_or(false),
+            max_ports: std::env::var("NETWORK_MAX_PORTS").ok().and_then(|v| v.parse().ok()).unwrap_or(defaults.max_ports),
+            ..defaults
+        }
+    }
+
+    /// The address `host` resolves to, if these limits allow it as a target;
+    /// other plugins that connect to hosts check them here too
+    pub async fn resolve(&self, host: &str) -> Result<IpAddr, Box<dyn Error + Send + Sync>> {
+        let address = match host.parse::<IpAddr>() {
+            Ok(address) => address,
+            Err(_) => tokio::net::lookup_host((host, 0)).await
+                .map_err(|e| error(format!("Cannot resolve {}: {}", host, e)))?
+                .map(|address| address.ip())
+                .next()
+                .ok_or_else(|| error(format!("{} has no addresses", host)))?,
+        };
+        if !self.allow_public && !is_local(&address) {
+            return Err(error(format!(
+                "{} ({}) is not on the local network; set NETWORK_ALLOW_PUBLIC_TARGETS to allow it",
+                host, address
+            )));
+        }
+        Ok(address)
+    }
+}
+
+/// Wake-on-LAN, reachability checks, port scans and neighbour discovery for
+/// the local network. Targets must be on the LAN unless
+/// `NETWORK_ALLOW_PUBLIC_TARGETS` is set, so the server cannot be used to
+/// scan the internet.
+pub struct NetworkPlugin {
+    limits: NetworkLimits,
+}
+
+impl Default for NetworkPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NetworkPlugin {
+    pub fn new() -> Self {
+        Self::with_limits(NetworkLimits::from_env())
+    }
+
+    pub fn with_limits(limits: NetworkLimits) -> Self {
+        Self { limits }
+    }
+
+    /// Sends a magic packet for `mac` to the broadcast address
+    async fn wol(&self, mac: &str, broadcast: Option<&str>) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let mac_bytes = parse_mac(mac)?;
+        let target: SocketAddr = match broadcast {
+            Some(address) => address.parse()
+                .or_else(|_| address.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 9)))
+                .map_err(|_| error(format!("Invalid broadcast address: {}", address)))?,
+            None => SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), 9),
+        };
+        if !self.limits.allow_public && !is_local(&target.ip()) {
+            return Err(error(format!("{} is not on the local network", target.ip())));
+        }
+        let socket = UdpSocket::bind(if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).await?;
+        socket.set_broadcast(true)?;
+        socket.send_to(&magic_packet(&mac_bytes), target).await?;
+        info!("Sent Wake-on-LAN packet for {} to {}", mac, target);
+        Ok(json!({ "mac": format_mac(&mac_bytes), "sent_to": target.to_string() }))
+    }
+
+    /// Pings with the system `ping`, or where it is missing probes common
+    /// TCP ports
+    async fn ping(&self, host: &str, count: u32) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let address = self.limits.resolve(host).await?;
+        let count = count.clamp(1, self.limits.max_ping_count);
+        // The resolved address, never the caller's text, goes on the command line
+        let output = Command::new("ping")
+            .args(["-c", &count.to_string(), &address.to_string()])
+            .kill_on_drop(true)
+            .output();
+        let wait = self.limits.connect_timeout * (count + 1) + Duration::from_secs(count as u64);
+        match tokio::time::timeout(wait, output).await {
+            Ok(Ok(output)) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let times = ping_times(&stdout);
+                let average = (!times.is_empty()).then(|| times.iter().sum::<f64>() / times.len() as f64);
+                Ok(json!({
+                    "host": host,
+                    "address": address,
+                    "method": "icmp",
+                    "reachable": !times.is_empty(),
+                    "sent": count,
+                    "received": times.len(),
+                    "rtt_ms": average,
+                }))
+            }
+            Ok(Err(e)) => {
+                debug!("ping unavailable ({}), probing TCP ports instead", e);
+                self.tcp_ping(host, address).await
+            }
+            Err(_) => Ok(json!({
+                "host": host,
+                "address": address,
+                "method": "icmp",
+                "reachable": false,
+                "sent": count,
+                "received": 0,
+                "rtt_ms": null,
+            })),
+        }
+    }
+
+    async fn tcp_ping(&self, host: &str, address: IpAddr) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let started = Instant::now();
+        for port in TCP_PING_PORTS {
+            let attempt = tokio::time::timeout(self.limits.connect_timeout, TcpStream::connect((address, port))).await;
+            let answered = match attempt {
+                Ok(Ok(_)) => true,
+                Ok(Err(e)) => e.kind() == std::io::ErrorKind::ConnectionRefused,
+                Err(_) => false,
+            };
+            if answered {
+                return Ok(json!({
+                    "host": host,
+                    "address": address,
+                    "method": "tcp",
+                    "reachable": true,
+                    "port": port,
+                    "rtt_ms": started.elapsed().as_secs_f64() * 1000.0,
+                }));
+            }
+        }
+        Ok(json!({ "host": host, "address": address, "method": "tcp", "reachable": false, "rtt_ms": null }))
+    }
+
+    async fn port_scan(&self, host: &str, ports: Vec<u16>) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        if ports.is_empty() {
+            return Err(error("ports is empty"));
+        }
+        if ports.len() > self.limits.max_ports {
+            return Err(error(format!("{} ports asked for, at most {} allowed per scan", ports.len(), self.limits.max_ports)));
+        }
+        let address = self.limits.resolve(host).await?;
+        let timeout = self.limits.connect_timeout;
+        let started = Instant::now();
+        let mut open: Vec<u16> = stream::iter(ports.iter().copied())
+            .map(|port| async move {
+                matches!(tokio::time::timeout(timeout, TcpStream::connect((address, port))).await, Ok(Ok(_))).then_some(port)
+            })
+            .buffer_unordered(self.limits.concurrency.max(1))
+            .filter_map(|port| async move { port })
+            .collect()
+            .await;
+        open.sort_unstable();
+        Ok(json!({
+            "host": host,
+            "address": address,
+            "scanned": ports.len(),
+            "open": open,
+            "duration_ms": started.elapsed().as_millis() as u64,
+        }))
+    }
+
+    /// The neighbour (ARP) table, after sending one empty datagram to every
+    /// address of `subnet` so the kernel resolves those that answer
+    async fn arp_scan(&self, subnet: Option<&str>) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        if let Some(subnet) = subnet {
+            let hosts = subnet_hosts(subnet, self.limits.min_prefix)?;
+            if !self.limits.allow_public && !hosts.iter().all(|host| is_local(&IpAddr::V4(*host))) {
+                return Err(error(format!("{} is not on the local network", subnet)));
+            }
+            let socket = UdpSocket::bind("0.0.0.0:0").await?;
+            for host in &hosts {
+                // Port 9 is discard; only the ARP request matters
+                let _ = socket.send_to(&[], (*host, 9)).await;
+            }
+            tokio::time::sleep(self.limits.connect_timeout).await;
+        }
+
+        let neighbours = match tokio::fs::read_to_string("/proc/net/arp").await {
+            Ok(table) => parse_proc_arp(&table),
+            Err(_) => {
+                let output = Command::new("arp").arg("-an").kill_on_drop(true).output().await
+                    .map_err(|e| error(format!("No neighbour table available (/proc/net/arp or arp): {}", e)))?;
+                parse_arp_an(&String::from_utf8_lossy(&output.stdout))
+            }
+        };
+        let neighbours: Vec<Value> = neighbours.into_iter()
+            .filter(|(ip, _, _)| match subnet.and_then(|subnet| subnet_hosts(subnet, self.limits.min_prefix).ok()) {
+                Some(hosts) => ip.parse().map(|ip| hosts.contains(&ip)).unwrap_or(false),
+                None => true,
+            })
+            .map(|(ip, mac, interface)| json!({ "ip": ip, "mac": mac, "interface": interface }))
+            .collect();
+        Ok(json!({ "count": neighbours.len(), "neighbours": neighbours }))
+    }
+}
+
+/// Private, loopback, link-local and unique local addresses
+fn is_local(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_broadcast(),
+        IpAddr::V6(v6) => {
+            v6.is_loopback() || (v6.segments()[0] & 0xfe00) == 0xfc00 || (v6.segments()[0] & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+fn parse_mac(mac: &str) -> Result<[u8; 6], Box<dyn Error + Send + Sync>> {
+    let digits: String = mac.chars().filter(|c| !matches!(c, ':' | '-' | '.')).collect();
+    if digits.len() != 12 {
+        return Err(error(format!("Invalid MAC address: {}", mac)));
+    }
+    let mut bytes = [0u8; 6];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).map_err(|_| error(format!("Invalid MAC address: {}", mac)))?;
+    }
+    Ok(bytes)
+}
+
+fn format_mac(mac: &[u8; 6]) -> String {
+    mac.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(":")
+}
+
+/// Six 0xFF bytes followed by the MAC sixteen times
+fn magic_packet(mac: &[u8; 6]) -> Vec<u8> {
+    let mut packet = vec![0xFF; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(mac);
+    }
+    packet
+}
+
+/// Ports from numbers or a string like `22,80,8000-8100`
+fn parse_ports(value: &Value) -> Result<Vec<u16>, Box<dyn Error + Send + Sync>> {
+    let invalid = |what: &str| error(format!("Invalid port: {}", what));
+    let mut ports = Vec::new();
+    let mut add = |spec: &str| -> Result<(), Box<dyn Error + Send + Sync>> {
+        let spec = spec.trim();
+        match spec.split_once('-') {
+            Some((start, end)) => {
+                let start: u16 = start.trim().parse().map_err(|_| invalid(spec))?;
+                let end: u16 = end.trim().parse().map_err(|_| invalid(spec))?;
+                if start == 0 || start > end {
+                    return Err(invalid(spec));
+                }
+                // Bounded so a huge range fails the max_ports check rather than allocating
+                ports.extend((start..=end).take(65536));
+            }
+            None => ports.push(spec.parse().ok().filter(|port| *port > 0).ok_or_else(|| invalid(spec))?),
+        }
+        Ok(())
+    };
+    match value {
+        Value::String(spec) => spec.split(',').filter(|part| !part.trim().is_empty()).try_for_each(&mut add)?,
+        Value::Array(items) => {
+            for item in items {
+                match item {
+                    Value::Number(number) => add(&number.to_string())?,
+                    Value::String(spec) => add(spec)?,
+                    other => return Err(invalid(&other.to_string())),
+                }
+            }
+        }
+        Value::Number(number) => add(&number.to_string())?,
+        other => return Err(invalid(&other.to_string())),
+    }
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+/// The host addresses of an IPv4 CIDR subnet no larger than `/min_prefix`
+fn subnet_hosts(subnet: &str, min_prefix: u8) -> Result<Vec<Ipv4Addr>, Box<dyn Error + Send + Sync>> {
+    let invalid = || error(format!("Invalid IPv4 subnet: {} (expected e.g. 192.168.1.0/24)", subnet));
+    let (network, prefix) = subnet.split_once('/').ok_or_else(invalid)?;
+    let network: Ipv4Addr = network.parse().map_err(|_| invalid())?;
+    let prefix: u8 = prefix.parse().ok().filter(|prefix| *prefix <= 32).ok_or_else(invalid)?;
+    if prefix < min_prefix {
+        return Err(error(format!("{} is larger than the /{} allowed", subnet, min_prefix)));
+    }
+    let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+    let first = u32::from(network) & mask;
+    let last = first | !mask;
+    // Leave out the network and broadcast addresses where there are any
+    let hosts = if prefix >= 31 { first..=last } else { first + 1..=last - 1 };
+    Ok(hosts.map(Ipv4Addr::from).collect())
+}
+
+/// Round-trip times in `time=0.123 ms` lines of `ping` output
+fn ping_times(output: &str) -> Vec<f64> {
+    output.lines()
+        .filter_map(|line| {
+            let time = line.split("time=").nth(1)?;
+            time.split_whitespace().next()?.trim_end_matches("ms").parse().ok()
+        })
+        .collect()
+}
+
+/// `/proc/net/arp` rows with a resolved MAC: (ip, mac, interface)
+fn parse_proc_arp(table: &str) -> Vec<(String, String, String)> {
+    table.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [ip, _, flags, mac, _, interface] = fields[..] else {
+                return None;
+            };
+            // 0x0 means the address did not answer
+            (flags != "0x0" && mac != "00:00:00:00:00:00").then(|| (ip.to_string(), mac.to_string(), interface.to_string()))
+        })
+        .collect()
+}
+
+/// `arp -an` lines like `? (192.168.1.10) at 0:11:32:aa:bb:cc on en0 ifscope [ethernet]`
+fn parse_arp_an(output: &str) -> Vec<(String, String, String)> {
+    output.lines()
+        .filter_map(|line| {
+            let ip = line.split('(').nth(1)?.split(')').next()?;
+            let mut words = line.split(" at ").nth(1)?.split_whitespace();
+            let mac = words.next().filter(|mac| mac.contains(':'))?;
+            let interface = line.split(" on ").nth(1).and_then(|rest| rest.split_whitespace().next()).unwrap_or_default();
+            Some((ip.to_string(), mac.to_string(), interface.to_string()))
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Plugin for NetworkPlugin {
+    fn name(&self) -> &str {
+        "network"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        let host = ParameterDefinition {
+            name: "host".to_string(),
+            description: "Host name or IP address on the local network".to_string(),
+            parameter_type: ParameterType::String,
+            required: true,
+        };
+        vec![
+            Capability {
+                name: "wol".to_string(),
+                description: "Wake a device with a Wake-on-LAN magic packet".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "mac".to_string(),
+                        description: "MAC address of the device, e.g. 00:11:32:aa:bb:cc".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "broadcast".to_string(),
+                        description: "Broadcast address, optionally with port (default: 255.255.255.255:9)".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "ping".to_string(),
+                description: "Check whether a host is up and how fast it answers".to_string(),
+                parameters: vec![
+                    host.clone(),
+                    ParameterDefinition {
+                        name: "count".to_string(),
+                        description: format!("Pings to send, at most {} (default: 3)", self.limits.max_ping_count),
+                        parameter_type: ParameterType::Number,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "port_scan".to_string(),
+                description: "Find which TCP ports of a host accept connections".to_string(),
+                parameters: vec![
+                    host,
+                    ParameterDefinition {
+                        name: "ports".to_string(),
+                        description: format!("Ports as numbers or a string like \"22,80,8000-8100\", at most {}", self.limits.max_ports),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "arp_scan".to_string(),
+                description: "List the devices in the neighbour (ARP) table, optionally sweeping a subnet first".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "subnet".to_string(),
+                        description: format!("IPv4 subnet to sweep, at most /{}, e.g. 192.168.1.0/24", self.limits.min_prefix),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+        ]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        _context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        info!("Executing network plugin capability: {}", capability);
+        let string = |name: &str| params.get(name).and_then(|v| v.as_str());
+        let required = |name: &str| string(name).ok_or_else(|| error(format!("{} is required", name)));
+
+        let data = match capability {
+            "wol" => self.wol(required("mac")?, string("broadcast")).await?,
+            "ping" => {
+                let count = params.get("count").and_then(|v| v.as_u64()).unwrap_or(3) as u32;
+                self.ping(required("host")?, count).await?
+            }
+            "port_scan" => {
+                let ports = parse_ports(params.get("ports").ok_or_else(|| error("ports is required"))?)?;
+                self.port_scan(required("host")?, ports).await?
+            }
+            "arp_scan" => self.arp_scan(string("subnet")).await?,
+            _ => return Err(error(format!("Unknown capability: {}", capability))),
+        };
+
+        Ok(PluginResult {
+            success: true,
+            data,
+            metrics: None,
+            context_updates: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsers() {
+        assert_eq!(parse_mac("00-11-32-AA-BB-CC").unwrap(), [0x00, 0x11, 0x32, 0xaa, 0xbb, 0xcc]);
+        assert!(parse_mac("00:11:32:aa:bb").is_err());
+        let packet = magic_packet(&[1, 2, 3, 4, 5, 6]);
+        assert_eq!(packet.len(), 102);
+        assert_eq!(&packet[..6], &[0xFF; 6]);
+        assert_eq!(&packet[96..], &[1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(parse_ports(&json!("22, 80,8000-8002")).unwrap(), vec![22, 80, 8000, 8001, 8002]);
+        assert_eq!(parse_ports(&json!([443, "22", 443])).unwrap(), vec![22, 443]);
+        assert!(parse_ports(&json!("0")).is_err());
+        assert!(parse_ports(&json!("90-80")).is_err());
+
+        assert_eq!(subnet_hosts("192.168.1.0/24", 24).unwrap().len(), 254);
+        assert_eq!(subnet_hosts("192.168.1.77/30", 24).unwrap(), vec![Ipv4Addr::new(192, 168, 1, 77), Ipv4Addr::new(192, 168, 1, 78)]);
+        assert!(subnet_hosts("10.0.0.0/16", 24).unwrap_err().to_string().contains("larger than the /24"));
+
+        assert_eq!(ping_times("64 bytes from 10.0.0.2: icmp_seq=1 ttl=64 time=0.412 ms\n64 bytes: time=1.6 ms\n"), vec![0.412, 1.6]);
+        let table = "IP address       HW type     Flags       HW address            Mask     Device\n\
+                     192.168.1.10     0x1         0x2         00:11:32:aa:bb:cc     *        eth0\n\
+                     192.168.1.11     0x1         0x0         00:00:00:00:00:00     *        eth0\n";
+        assert_eq!(parse_proc_arp(table), vec![("192.168.1.10".to_string(), "00:11:32:aa:bb:cc".to_string(), "eth0".to_string())]);
+        assert_eq!(
+            parse_arp_an("? (192.168.1.10) at 0:11:32:aa:bb:cc on en0 ifscope [ethernet]\n? (192.168.1.12) at (incomplete) on en0\n"),
+            vec![("192.168.1.10".to_string(), "0:11:32:aa:bb:cc".to_string(), "en0".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wol_sends_the_magic_packet() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let plugin = NetworkPlugin::with_limits(NetworkLimits::default());
+        let params = HashMap::from([
+            ("mac".to_string(), json!("00:11:32:AA:BB:CC")),
+            ("broadcast".to_string(), json!(receiver.local_addr().unwrap().to_string())),
+        ]);
+        let result = plugin.execute("wol", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["mac"], "00:11:32:aa:bb:cc");
+
+        let mut packet = [0u8; 200];
+        let received = tokio::time::timeout(Duration::from_secs(2), receiver.recv(&mut packet)).await.unwrap().unwrap();
+        assert_eq!(&packet[..received], magic_packet(&[0x00, 0x11, 0x32, 0xaa, 0xbb, 0xcc]).as_slice());
+    }
+
+    #[tokio::test]
+    async fn test_port_scan_finds_open_ports_within_the_limits() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap().port();
+        let plugin = NetworkPlugin::with_limits(NetworkLimits { max_ports: 10, ..NetworkLimits::default() });
+
+        let params = HashMap::from([("host".to_string(), json!("127.0.0.1")), ("ports".to_string(), json!([open, 1]))]);
+        let result = plugin.execute("port_scan", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["open"], json!([open]));
+        assert_eq!(result.data["scanned"], 2);
+
+        let params = HashMap::from([("host".to_string(), json!("127.0.0.1")), ("ports".to_string(), json!("1-11"))]);
+        let error = plugin.execute("port_scan", Context::for_test(), params).await.unwrap_err();
+        assert!(error.to_string().contains("at most 10 allowed"));
+
+        let params = HashMap::from([("host".to_string(), json!("8.8.8.8")), ("ports".to_string(), json!("53"))]);
+        let error = plugin.execute("port_scan", Context::for_test(), params).await.unwrap_err();
+        assert!(error.to_string().contains("not on the local network"));
+    }
+
+    #[tokio::test]
+    async fn test_ping_reports_a_local_host_as_reachable() {
+        // Falls back to TCP where the ping command is missing; 127.0.0.1
+        // refusing the connection still counts as up
+        let plugin = NetworkPlugin::with_limits(NetworkLimits::default());
+        let params = HashMap::from([("host".to_string(), json!("127.0.0.1")), ("count".to_string(), json!(1))]);
+        let result = plugin.execute("ping", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["reachable"], true);
+        assert!(plugin.execute("ping", Context::for_test(), HashMap::from([("host".to_string(), json!("1.1.1.1"))])).await.is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use async_trait::async_trait;
+use tracing::{info, debug, warn};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::mcp::events::EventBus;
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
+
+#[derive(Debug)]
+struct NotificationPluginError(String);
+
+impl fmt::Display for NotificationPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for NotificationPluginError {}
+
+/// Delivers notifications to a webhook (e.g. an n8n workflow or Home Assistant automation).
+/// Without a configured webhook, notifications are only written to the log.
+/// Every notification is also published on the event bus as
+/// `notification.<severity>`.
+#[derive(Clone)]
+pub struct NotificationPlugin {
+    webhook_url: Option<String>,
+    client: reqwest::Client,
+    /// Topic patterns of other plugins' events to send as notifications
+    forwarded_topics: Vec<String>,
+    events: OnceLock<EventBus>,
+}
+
+impl Default for NotificationPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NotificationPlugin {
+    pub fn new() -> Self {
+        let forwarded_topics = std::env::var("NOTIFICATION_EVENT_TOPICS")
+            .map(|topics| topics.split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default();
+        Self::with_webhook(std::env::var("NOTIFICATION_WEBHOOK_URL").ok()).with_forwarded_events(forwarded_topics)
+    }
+
+    pub fn with_webhook(webhook_url: Option<String>) -> Self {
+        Self {
+            webhook_url,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_else(|_| reqwest::Client::new()),
+            forwarded_topics: Vec::new(),
+            events: OnceLock::new(),
+        }
+    }
+
+    /// Send events whose topic matches one of `topics` (e.g. `motion.*`) as
+    /// notifications, once the plugin is attached to an event bus
+    pub fn with_forwarded_events(mut self, topics: Vec<String>) -> Self {
+        self.forwarded_topics = topics;
+        self
+    }
+
+    /// Sends a notification, returning whether it was delivered to the webhook
+    pub async fn notify(&self, title: &str, message: &str, severity: &str, data: Option<Value>) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        warn!("[{}] {}: {}", severity, title, message);
+        if let Some(events) = self.events.get() {
+            events.publish(
+                self.name(),
+                &format!("notification.{}", severity),
+                json!({ "title": title, "message": message, "data": data }),
+            );
+        }
+
+        let url = match &self.webhook_url {
+            Some(url) => url,
+            None => {
+                debug!("No notification webhook configured, notification logged only");
+                return Ok(false);
+            }
+        };
+
+        let payload = json!({
+            "title": title,
+            "message": message,
+            "severity": severity,
+            "data": data,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        });
+
+        let response = self.client.post(url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| Box::new(NotificationPluginError(format!("Failed to send notification: {}", e))))?;
+
+        if !response.status().is_success() {
+            return Err(Box::new(NotificationPluginError(format!("Notification webhook returned {}", response.status()))));
+        }
+
+        info!("Delivered notification '{}' to webhook", title);
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl Plugin for NotificationPlugin {
+    fn name(&self) -> &str {
+        "notification"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn attach_events(&self, events: &EventBus) {
+        if self.events.set(events.clone()).is_err() {
+            return;
+        }
+        for topic in &self.forwarded_topics {
+            let mut subscription = events.subscribe(topic);
+            let plugin = self.clone();
+            tokio::spawn(async move {
+                while let Some(event) = subscription.recv().await {
+                    // Our own notification events would loop forever
+                    if event.source == plugin.name() {
+                        continue;
+                    }
+                    let message = format!("{} event from {}", event.topic, event.source);
+                    if let Err(e) = plugin.notify(&event.topic, &message, "info", Some(event.data)).await {
+                        warn!("Failed to forward event {}: {}", event.topic, e);
+                    }
+                }
+            });
+        }
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![
+            Capability {
+                name: "send_notification".to_string(),
+                description: "Send a notification to the configured webhook".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "title".to_string(),
+                        description: "Short notification title".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "message".to_string(),
+                        description: "Notification body".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "severity".to_string(),
+                        description: "Severity level: info, warning or critical (default: info)".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                    ParameterDefinition {
+                        name: "data".to_string(),
+                        description: "Additional structured data to include".to_string(),
+                        parameter_type: ParameterType::Object,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+        ]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        _context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        info!("Executing notification plugin capability: {}", capability);
+
+        match capability {
+            "send_notification" => {
+                let title = params.get("title")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| Box::new(NotificationPluginError("title is required".to_string())))?;
+                let message = params.get("message")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| Box::new(NotificationPluginError("message is required".to_string())))?;
+                let severity = params.get("severity")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("info");
+
+                let delivered = self.notify(title, message, severity, params.get("data").cloned()).await?;
+                Ok(PluginResult {
+                    success: true,
+                    data: json!({ "delivered": delivered }),
+                    metrics: None,
+                    context_updates: None,
+                })
+            }
+            _ => Err(Box::new(NotificationPluginError(format!("Unknown capability: {}", capability)))),
+        }
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        match &self.webhook_url {
+            Some(url) => HealthStatus::healthy().with_details(json!({ "webhook_url": url })),
+            None => HealthStatus::healthy().with_details(json!({ "webhook_url": null, "mode": "log_only" })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_notify_without_webhook_logs_only() {
+        let plugin = NotificationPlugin::with_webhook(None);
+        let delivered = plugin.notify("CPU high", "CPU at 95%", "warning", None).await.unwrap();
+        assert!(!delivered);
+    }
+
+    #[tokio::test]
+    async fn test_notify_posts_to_webhook() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let plugin = NotificationPlugin::with_webhook(Some(format!("{}/hook", mock_server.uri())));
+        let delivered = plugin.notify("Disk full", "/ at 97%", "critical", Some(json!({"mount": "/"}))).await.unwrap();
+        assert!(delivered);
+    }
+
+    #[tokio::test]
+    async fn test_forwards_matching_events_and_publishes_its_own() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let plugin = NotificationPlugin::with_webhook(Some(format!("{}/hook", mock_server.uri())))
+            .with_forwarded_events(vec!["motion.*".to_string(), "notification.*".to_string()]);
+        let events = EventBus::new(10);
+        let mut sent = events.subscribe("notification.*");
+        plugin.attach_events(&events);
+
+        events.publish("mqtt", "door.front", json!({ "open": true }));
+        events.publish("mqtt", "motion.hallway", json!({ "detected": true }));
+
+        let notification = tokio::time::timeout(Duration::from_secs(5), sent.recv()).await.unwrap().unwrap();
+        assert_eq!(notification.topic, "notification.info");
+        assert_eq!(notification.data["title"], "motion.hallway");
+        assert_eq!(notification.data["data"]["detected"], true);
+        // Our own notification event is not forwarded back to the webhook
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    #[tokio::test]
+    async fn test_send_notification_requires_title() {
+        let plugin = NotificationPlugin::with_webhook(None);
+        let context = Context::for_test();
+        let params = HashMap::from([("message".to_string(), json!("hello"))]);
+
+        let result = plugin.execute("send_notification", context, params).await;
+        assert!(result.unwrap_err().to_string().contains("title is required"));
+    }
+}
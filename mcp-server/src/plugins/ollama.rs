@@ -0,0 +1,349 @@
+use async_trait::async_trait;
+use tracing::{info, debug, error};
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
+
+#[derive(Debug)]
+struct OllamaPluginError(String);
+
+impl fmt::Display for OllamaPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for OllamaPluginError {}
+
+/// Where Ollama runs; the same `OLLAMA_BASE_URL` the sampling fallback uses
+#[derive(Debug, Clone)]
+pub struct OllamaConfig {
+    pub base_url: String,
+    pub timeout: Duration,
+}
+
+impl OllamaConfig {
+    pub fn from_env() -> Self {
+        Self {
+            base_url: std::env::var("OLLAMA_BASE_URL").unwrap_or_else(|_| "http://localhost:11434".to_string()),
+            timeout: Duration::from_secs(
+                std::env::var("OLLAMA_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(10),
+            ),
+        }
+    }
+}
+
+/// Reports what the local Ollama has: the models pulled, the details of one,
+/// and the models loaded right now with the memory they hold
+pub struct OllamaPlugin {
+    config: OllamaConfig,
+    client: reqwest::Client,
+}
+
+impl Default for OllamaPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OllamaPlugin {
+    pub fn new() -> Self {
+        Self::with_config(OllamaConfig::from_env())
+    }
+
+    pub fn with_config(config: OllamaConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .unwrap_or_else(|e| {
+                error!("Failed to build Ollama HTTP client, using defaults: {}", e);
+                reqwest::Client::new()
+            });
+        Self { config, client }
+    }
+
+    async fn request(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let url = format!("{}{}", self.config.base_url.trim_end_matches('/'), path);
+        debug!("Ollama request: {} {}", method, url);
+        let mut request = self.client.request(method, &url);
+        if let Some(body) = body {
+            crate::mcp::budget::count_upstream_bytes(body.to_string().len());
+            request = request.json(&body);
+        }
+        let response = request.send().await
+            .map_err(|e| Box::new(OllamaPluginError(format!("Ollama unreachable at {}: {}", self.config.base_url, e))))?;
+
+        let status = response.status();
+        let text = response.text().await
+            .map_err(|e| Box::new(OllamaPluginError(format!("Failed to read Ollama response: {}", e))))?;
+        crate::mcp::budget::count_upstream_bytes(text.len());
+        if !status.is_success() {
+            // Ollama explains errors in {"error": "..."}
+            let message = serde_json::from_str::<Value>(&text).ok()
+                .and_then(|body| body["error"].as_str().map(str::to_string))
+                .unwrap_or(text);
+            return Err(Box::new(OllamaPluginError(format!("Ollama returned {}: {}", status, message))));
+        }
+        serde_json::from_str(&text)
+            .map_err(|e| Box::new(OllamaPluginError(format!("Failed to parse Ollama response: {}", e))) as Box<dyn Error + Send + Sync>)
+    }
+
+    /// The models pulled, from `/api/tags`
+    async fn list_models(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let tags = self.request(Method::GET, "/api/tags", None).await?;
+        let models: Vec<Value> = models_of(&tags).iter()
+            .map(|model| json!({
+                "name": model["name"],
+                "size_bytes": model["size"],
+                "modified_at": model["modified_at"],
+                "family": model["details"]["family"],
+                "parameter_size": model["details"]["parameter_size"],
+                "quantization_level": model["details"]["quantization_level"],
+            }))
+            .collect();
+        Ok(json!({ "count": models.len(), "models": models }))
+    }
+
+    /// One model's details, parameters and context length, from `/api/show`
+    async fn model_info(&self, model: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let show = self.request(Method::POST, "/api/show", Some(json!({ "model": model }))).await?;
+        // model_info keys are prefixed with the architecture, e.g. llama.context_length
+        let context_length = show["model_info"].as_object()
+            .and_then(|info| info.iter().find(|(key, _)| key.ends_with(".context_length")))
+            .map(|(_, length)| length.clone())
+            .unwrap_or(Value::Null);
+        Ok(json!({
+            "name": model,
+            "details": show["details"],
+            "parameters": show["parameters"],
+            "capabilities": show["capabilities"],
+            "context_length": context_length,
+            "modified_at": show["modified_at"],
+        }))
+    }
+
+    /// The models loaded now, from `/api/ps`, with the memory each holds in
+    /// VRAM and in system RAM
+    async fn running_models(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let ps = self.request(Method::GET, "/api/ps", None).await?;
+        let (mut total_bytes, mut total_vram_bytes) = (0u64, 0u64);
+        let models: Vec<Value> = models_of(&ps).iter()
+            .map(|model| {
+                let size = model["size"].as_u64().unwrap_or(0);
+                let vram = model["size_vram"].as_u64().unwrap_or(0);
+                total_bytes += size;
+                total_vram_bytes += vram;
+                json!({
+                    "name": model["name"],
+                    "size_bytes": size,
+                    "vram_bytes": vram,
+                    "ram_bytes": size.saturating_sub(vram),
+                    "gpu_percent": if size > 0 { vram as f64 / size as f64 * 100.0 } else { 0.0 },
+                    "expires_at": model["expires_at"],
+                    "parameter_size": model["details"]["parameter_size"],
+                    "quantization_level": model["details"]["quantization_level"],
+                })
+            })
+            .collect();
+        Ok(json!({
+            "count": models.len(),
+            "total_bytes": total_bytes,
+            "total_vram_bytes": total_vram_bytes,
+            "models": models,
+        }))
+    }
+}
+
+fn models_of(body: &Value) -> &[Value] {
+    body["models"].as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+#[async_trait]
+impl Plugin for OllamaPlugin {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![
+            Capability {
+                name: "list_models".to_string(),
+                description: "List the models Ollama has pulled, with their size and quantization".to_string(),
+                parameters: vec![],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "model_info".to_string(),
+                description: "Get a model's details, parameters and context length".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "model".to_string(),
+                        description: "Model name, e.g. llama3:8b".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "running_models".to_string(),
+                description: "List the models loaded now and the VRAM and RAM each uses".to_string(),
+                parameters: vec![],
+                version: None,
+                deprecated: None,
+            },
+        ]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        _context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        info!("Executing ollama plugin capability: {}", capability);
+
+        let data = match capability {
+            "list_models" => self.list_models().await?,
+            "model_info" => {
+                let model = params.get("model")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| Box::new(OllamaPluginError("model is required".to_string())))?;
+                self.model_info(model).await?
+            }
+            "running_models" => self.running_models().await?,
+            _ => return Err(Box::new(OllamaPluginError(format!("Unknown capability: {}", capability)))),
+        };
+
+        Ok(PluginResult {
+            success: true,
+            data,
+            metrics: None,
+            context_updates: None,
+        })
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        let details = json!({ "base_url": self.config.base_url });
+        match self.request(Method::GET, "/api/version", None).await {
+            Ok(version) => HealthStatus::healthy().with_details(json!({
+                "base_url": self.config.base_url,
+                "version": version["version"],
+            })),
+            Err(e) => HealthStatus::unhealthy(e.to_string()).with_details(details),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn plugin(base_url: &str) -> OllamaPlugin {
+        OllamaPlugin::with_config(OllamaConfig {
+            base_url: base_url.to_string(),
+            timeout: Duration::from_secs(2),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_running_models_reports_memory_per_model() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/ps"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"models": [
+                {
+                    "name": "llama3:8b",
+                    "size": 6_000_000_000u64,
+                    "size_vram": 4_500_000_000u64,
+                    "expires_at": "2026-10-17T12:05:00Z",
+                    "details": {"parameter_size": "8.0B", "quantization_level": "Q4_0"}
+                },
+                {"name": "nomic-embed-text:latest", "size": 500_000_000u64, "size_vram": 500_000_000u64, "details": {}}
+            ]})))
+            .mount(&server)
+            .await;
+
+        let result = plugin(&server.uri()).execute("running_models", Context::for_test(), HashMap::new()).await.unwrap();
+        assert_eq!(result.data["count"], 2);
+        assert_eq!(result.data["total_bytes"], 6_500_000_000u64);
+        assert_eq!(result.data["total_vram_bytes"], 5_000_000_000u64);
+        let llama = &result.data["models"][0];
+        assert_eq!(llama["ram_bytes"], 1_500_000_000u64);
+        assert_eq!(llama["gpu_percent"], 75.0);
+        assert_eq!(llama["quantization_level"], "Q4_0");
+    }
+
+    #[tokio::test]
+    async fn test_list_models_and_model_info() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"models": [
+                {"name": "llama3:8b", "size": 4_661_224_676u64, "modified_at": "2026-10-01T09:00:00Z",
+                 "details": {"family": "llama", "parameter_size": "8.0B", "quantization_level": "Q4_0"}}
+            ]})))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/show"))
+            .and(body_json(json!({"model": "llama3:8b"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "details": {"family": "llama"},
+                "parameters": "stop \"<|eot_id|>\"",
+                "model_info": {"general.architecture": "llama", "llama.context_length": 8192}
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/show"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({"error": "model 'mistral' not found"})))
+            .mount(&server)
+            .await;
+
+        let plugin = plugin(&server.uri());
+        let models = plugin.execute("list_models", Context::for_test(), HashMap::new()).await.unwrap().data;
+        assert_eq!(models["count"], 1);
+        assert_eq!(models["models"][0]["family"], "llama");
+        assert_eq!(models["models"][0]["size_bytes"], 4_661_224_676u64);
+
+        let params = HashMap::from([("model".to_string(), json!("llama3:8b"))]);
+        let info = plugin.execute("model_info", Context::for_test(), params).await.unwrap().data;
+        assert_eq!(info["context_length"], 8192);
+
+        let params = HashMap::from([("model".to_string(), json!("mistral"))]);
+        let error = plugin.execute("model_info", Context::for_test(), params).await.unwrap_err();
+        assert!(error.to_string().contains("model 'mistral' not found"));
+        let error = plugin.execute("model_info", Context::for_test(), HashMap::new()).await.unwrap_err();
+        assert!(error.to_string().contains("model is required"));
+    }
+
+    #[tokio::test]
+    async fn test_health_check_reports_the_version() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"version": "0.3.12"})))
+            .mount(&server)
+            .await;
+
+        let health = plugin(&server.uri()).health_check().await;
+        assert!(health.healthy);
+        assert!(!plugin("http://127.0.0.1:9").health_check().await.healthy);
+    }
+}
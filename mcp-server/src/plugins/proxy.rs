@@ -0,0 +1,458 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use super::{Capability, Context, HealthStatus, ParameterDefinition, ParameterType, Plugin, PluginResult};
+use crate::mcp::types::{ContentBlock, Deprecation, ToolAnnotations, ToolDefinition};
+use crate::mcp::version::SUPPORTED_PROTOCOL_VERSIONS;
+
+/// Trouble talking to the child, as opposed to errors it answers with
+fn proxy_error(message: impl Into<String>) -> Box<dyn Error + Send + Sync> {
+    Box::new(mcp_core::Error::Transport(message.into()))
+}
+
+/// How long to wait for the child to answer a request, unless its config
+/// says otherwise
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// How to start an external MCP server that speaks JSON-RPC over stdio
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProxyConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Set for the child on top of this server's environment
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Seconds to wait for each answer from the child (default 60)
+    #[serde(default, rename = "timeoutSecs")]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ProxyConfigFile {
+    #[serde(rename = "mcpServers")]
+    mcp_servers: HashMap<String, ProxyConfig>,
+}
+
+impl ProxyConfig {
+    /// Servers by name from a JSON file in the usual MCP client format,
+    /// `{"mcpServers": {"fetch": {"command": "uvx", "args": ["mcp-server-fetch"]}}}`
+    pub fn load(path: &Path) -> anyhow::Result<Vec<(String, Self)>> {
+        use anyhow::Context as _;
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read proxy servers from {}", path.display()))?;
+        let file: ProxyConfigFile =
+            serde_json::from_str(&text).with_context(|| format!("Invalid proxy servers in {}", path.display()))?;
+        let mut servers: Vec<(String, Self)> = file.mcp_servers.into_iter().collect();
+        servers.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(servers)
+    }
+
+    /// Servers from the file `MCP_PROXY_SERVERS` names, if set and valid
+    pub fn from_env() -> Vec<(String, Self)> {
+        let Ok(path) = std::env::var("MCP_PROXY_SERVERS") else {
+            return Vec::new();
+        };
+        match Self::load(Path::new(&path)) {
+            Ok(servers) => servers,
+            Err(e) => {
+                warn!("Ignoring MCP_PROXY_SERVERS: {:#}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// A tool as other servers list it; only the name is required
+#[derive(Deserialize)]
+struct RemoteTool {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(rename = "inputSchema", alias = "input_schema", default)]
+    input_schema: Value,
+    #[serde(rename = "outputSchema", alias = "output_schema", default)]
+    output_schema: Option<Value>,
+    #[serde(default)]
+    annotations: Option<ToolAnnotations>,
+    /// Only servers like this one send these
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    deprecated: Option<Deprecation>,
+}
+
+/// The running child and its pipes. Requests are answered one at a time.
+struct Connection {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    next_id: u64,
+    timeout: Duration,
+    /// Set once a pipe to the child fails or its output ends, after which
+    /// no request can be answered and the child has to be started again
+    broken: bool,
+}
+
+impl Connection {
+    fn spawn(config: &ProxyConfig) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| proxy_error(format!("Failed to start {}: {}", config.command, e)))?;
+        let stdin = child.stdin.take().ok_or_else(|| proxy_error("Child has no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| proxy_error("Child has no stdout"))?;
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+            next_id: 1,
+            timeout: Duration::from_secs(config.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS)),
+            broken: false,
+        })
+    }
+
+    /// Whether the child can still answer: its pipes work and it has not exited
+    fn is_usable(&mut self) -> bool {
+        !self.broken && matches!(self.child.try_wait(), Ok(None))
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let written = async {
+            self.stdin.write_all(format!("{}\n", message).as_bytes()).await?;
+            self.stdin.flush().await
+        };
+        if let Err(e) = written.await {
+            self.broken = true;
+            return Err(proxy_error(format!("Failed to write to the server: {}", e)));
+        }
+        Ok(())
+    }
+
+    async fn notify(&mut self, method: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method })).await
+    }
+
+    /// Sends a request and waits, up to the timeout, for its response. A
+    /// request that times out is cancelled, and its late response skipped.
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })).await?;
+        match tokio::time::timeout(self.timeout, self.response(id, method)).await {
+            Ok(response) => response,
+            Err(_) => {
+                let cancelled = json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/cancelled",
+                    "params": { "requestId": id, "reason": "timed out" }
+                });
+                self.send(&cancelled).await?;
+                Err(proxy_error(format!("{} got no answer within {:?}", method, self.timeout)))
+            }
+        }
+    }
+
+    /// Reads until the response to request `id`. Notifications from the
+    /// child are skipped and its own requests refused, since there is no
+    /// client behind this server to forward them to.
+    async fn response(&mut self, id: u64, method: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        loop {
+            let line = match self.stdout.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => {
+                    self.broken = true;
+                    return Err(proxy_error("The server closed its output"));
+                }
+                Err(e) => {
+                    self.broken = true;
+                    return Err(proxy_error(format!("Failed to read from the server: {}", e)));
+                }
+            };
+            let message: Value = match serde_json::from_str(&line) {
+                Ok(message) => message,
+                Err(_) => {
+                    debug!("Skipping non-JSON output: {}", line);
+                    continue;
+                }
+            };
+            if message.get("method").is_some() {
+                if let Some(request_id) = message.get("id") {
+                    let refusal = json!({
+                        "jsonrpc": "2.0",
+                        "id": request_id,
+                        "error": { "code": -32601, "message": "Method not found" }
+                    });
+                    self.send(&refusal).await?;
+                }
+                continue;
+            }
+            if message["id"] != json!(id) {
+                continue;
+            }
+            // Kept as the child sent it, so the caller gets the same refusal
+            if let Some(error) = message.get("error") {
+                return Err(match serde_json::from_value::<mcp_core::JsonRpcError>(error.clone()) {
+                    Ok(error) => Box::new(mcp_core::Error::Rpc(error).context(format!("{} failed", method))),
+                    Err(_) => proxy_error(format!("{} failed: {}", method, error)),
+                });
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+}
+
+/// Runs an external stdio MCP server (such as the Python or Node reference
+/// servers) as a child process and offers its tools as capabilities. The
+/// server registers each of them as a tool named `<plugin>.<tool>`.
+pub struct ProxyPlugin {
+    name: String,
+    config: ProxyConfig,
+    connection: Mutex<Option<Connection>>,
+    tools: Mutex<Vec<ToolDefinition>>,
+}
+
+impl ProxyPlugin {
+    pub fn new(name: &str, config: ProxyConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            config,
+            connection: Mutex::new(None),
+            tools: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The child's tools as of the last time it was started
+    pub async fn tools(&self) -> Vec<ToolDefinition> {
+        self.tools.lock().await.clone()
+    }
+
+    /// Starts the child, performs the initialize handshake and lists its tools
+    async fn start(&self) -> Result<Connection, Box<dyn Error + Send + Sync>> {
+        let mut connection = Connection::spawn(&self.config)?;
+        let initialized = connection
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": SUPPORTED_PROTOCOL_VERSIONS[0],
+                    "capabilities": {},
+                    "clientInfo": { "name": "mcp-server", "version": env!("CARGO_PKG_VERSION") }
+                }),
+            )
+            .await?;
+        connection.notify("notifications/initialized").await?;
+        let listed = connection.request("tools/list", json!({})).await?;
+        let tools: Vec<RemoteTool> = serde_json::from_value(listed["tools"].clone())
+            .map_err(|e| proxy_error(format!("Invalid tools/list result: {}", e)))?;
+        let tools: Vec<ToolDefinition> = tools
+            .into_iter()
+            .map(|tool| ToolDefinition {
+                name: tool.name,
+                description: tool.description,
+                input_schema: tool.input_schema,
+                output_schema: tool.output_schema,
+                version: tool.version,
+                deprecated: tool.deprecated,
+                tags: Vec::new(),
+                annotations: tool.annotations,
+            })
+            .collect();
+        info!(
+            "Proxying {} ({}) with tools {:?}",
+            self.name,
+            initialized["serverInfo"]["name"].as_str().unwrap_or(&self.config.command),
+            tools.iter().map(|tool| &tool.name).collect::<Vec<_>>()
+        );
+        *self.tools.lock().await = tools;
+        Ok(connection)
+    }
+
+    /// Calls one of the child's tools and returns its content. Image and
+    /// audio blocks become `data` blocks and unknown ones `json` blocks, and
+    /// structured content not already in a `json` block is added as one.
+    /// A child that exited or closed its output is started again first.
+    pub async fn call_tool(&self, tool: &str, arguments: HashMap<String, Value>) -> Result<Vec<ContentBlock>, Box<dyn Error + Send + Sync>> {
+        let mut slot = self.connection.lock().await;
+        match slot.as_mut().map(Connection::is_usable) {
+            Some(true) => {}
+            Some(false) => {
+                warn!("{} stopped answering; starting it again", self.name);
+                *slot = Some(self.start().await?);
+            }
+            None => return Err(proxy_error(format!("{} is not running", self.name))),
+        }
+        let connection = slot.as_mut().ok_or_else(|| proxy_error(format!("{} is not running", self.name)))?;
+        let result = connection
+            .request("tools/call", json!({ "name": tool, "arguments": arguments }))
+            .await?;
+        let mut content: Vec<ContentBlock> = result["content"]
+            .as_array()
+            .map(|blocks| blocks.iter().map(content_block).collect())
+            .unwrap_or_default();
+        if result["isError"].as_bool().unwrap_or(false) {
+            let message: Vec<&str> = content.iter().filter_map(ContentBlock::as_text).collect();
+            return Err(proxy_error(message.join("\n")));
+        }
+        if let Some(structured) = result.get("structuredContent").filter(|structured| structured.is_object()) {
+            if !content.iter().any(|block| matches!(block, ContentBlock::Json { json } if json == structured)) {
+                content.push(ContentBlock::Json { json: structured.clone() });
+            }
+        }
+        Ok(content)
+    }
+}
+
+fn content_block(block: &Value) -> ContentBlock {
+    match (block["type"].as_str(), block["text"].as_str(), block["mimeType"].as_str(), block["data"].as_str()) {
+        (Some("text"), Some(text), _, _) => ContentBlock::text(text),
+        (Some("image" | "audio" | "data"), _, Some(mime_type), Some(data)) => ContentBlock::Data {
+            mime_type: mime_type.to_string(),
+            data: data.to_string(),
+        },
+        _ => ContentBlock::Json { json: block.clone() },
+    }
+}
+
+/// Parameters for `capabilities`, from the top level of a JSON schema
+fn parameters(schema: &Value) -> Vec<ParameterDefinition> {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let Some(properties) = schema["properties"].as_object() else {
+        return Vec::new();
+    };
+    properties
+        .iter()
+        .map(|(name, property)| ParameterDefinition {
+            name: name.clone(),
+            description: property["description"].as_str().unwrap_or_default().to_string(),
+            parameter_type: match property["type"].as_str() {
+                Some("number" | "integer") => ParameterType::Number,
+                Some("boolean") => ParameterType::Boolean,
+                Some("array") => ParameterType::Array,
+                Some("object") => ParameterType::Object,
+                _ => ParameterType::String,
+            },
+            required: required.contains(&name.as_str()),
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Plugin for ProxyPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        let Ok(tools) = self.tools.try_lock() else {
+            return Vec::new();
+        };
+        tools
+            .iter()
+            .map(|tool| Capability {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                parameters: parameters(&tool.input_schema),
+                version: None,
+                deprecated: None,
+            })
+            .collect()
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        _context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        let content = self.call_tool(capability, params).await?;
+        Ok(PluginResult {
+            success: true,
+            data: json!({ "content": content }),
+            metrics: None,
+            context_updates: None,
+        })
+    }
+
+    /// Starts the child and keeps its connection for the calls to come
+    async fn initialize(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let connection = self.start().await?;
+        *self.connection.lock().await = Some(connection);
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if let Some(mut connection) = self.connection.lock().await.take() {
+            connection.child.kill().await?;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        match self.connection.lock().await.as_mut().map(|connection| connection.child.try_wait()) {
+            Some(Ok(None)) => HealthStatus::healthy(),
+            Some(Ok(Some(status))) => HealthStatus::unhealthy(format!("{} exited with {}", self.config.command, status)),
+            Some(Err(e)) => HealthStatus::unhealthy(e.to_string()),
+            None => HealthStatus::unhealthy(format!("{} is not running", self.name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_reads_mcp_servers() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"{"mcpServers": {"fetch": {"command": "uvx", "args": ["mcp-server-fetch"], "env": {"LOG": "1"}}}}"#,
+        )
+        .unwrap();
+        let servers = ProxyConfig::load(file.path()).unwrap();
+        assert_eq!(servers[0].0, "fetch");
+        assert_eq!(servers[0].1.args, ["mcp-server-fetch"]);
+        assert_eq!(servers[0].1.env["LOG"], "1");
+    }
+
+    #[test]
+    fn test_content_blocks_and_parameters() {
+        assert_eq!(content_block(&json!({"type": "text", "text": "hi"})).as_text(), Some("hi"));
+        assert!(matches!(
+            content_block(&json!({"type": "image", "mimeType": "image/png", "data": "iVBO"})),
+            ContentBlock::Data { mime_type, .. } if mime_type == "image/png"
+        ));
+        assert!(matches!(content_block(&json!({"type": "resource"})), ContentBlock::Json { .. }));
+
+        let parameters = parameters(&json!({
+            "type": "object",
+            "properties": {"url": {"type": "string", "description": "Page to fetch"}, "max_length": {"type": "integer"}},
+            "required": ["url"]
+        }));
+        let url = parameters.iter().find(|parameter| parameter.name == "url").unwrap();
+        assert!(url.required);
+        assert_eq!(url.description, "Page to fetch");
+    }
+}
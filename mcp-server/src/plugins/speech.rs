@@ -0,0 +1,508 @@
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use tracing::{info, debug, error};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
+use crate::mcp::roots;
+
+/// Largest audio transcribe accepts, as OpenAI's transcription API does
+const MAX_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+
+#[derive(Debug)]
+struct SpeechPluginError(String);
+
+impl fmt::Display for SpeechPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for SpeechPluginError {}
+
+fn error(message: impl Into<String>) -> Box<dyn Error + Send + Sync> {
+    Box::new(SpeechPluginError(message.into()))
+}
+
+/// How speech is turned into text
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpeechToText {
+    /// A Whisper server: whisper.cpp's `/inference` or an OpenAI-compatible
+    /// `/v1/audio/transcriptions`, as the full URL
+    Http { url: String, model: String },
+    /// The whisper.cpp command line, e.g. `whisper-cli`, with a ggml model
+    WhisperCpp { binary: PathBuf, model: PathBuf },
+}
+
+/// How text is turned into speech
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextToSpeech {
+    /// An OpenAI-compatible `/v1/audio/speech`, as the full URL
+    Http { url: String, model: String, voice: String },
+    /// The Piper command line with a voice model
+    Piper { binary: PathBuf, model: PathBuf },
+}
+
+#[derive(Debug, Clone)]
+pub struct SpeechConfig {
+    pub stt: Option<SpeechToText>,
+    pub tts: Option<TextToSpeech>,
+    pub timeout: Duration,
+}
+
+impl SpeechConfig {
+    /// `WHISPER_URL` or `WHISPER_CPP_BIN` with `WHISPER_CPP_MODEL`, and
+    /// `TTS_URL` or `PIPER_BIN` with `PIPER_MODEL`; the HTTP backends win
+    /// when both are set
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let stt = match (var("WHISPER_URL"), var("WHISPER_CPP_BIN"), var("WHISPER_CPP_MODEL")) {
+            (Some(url), _, _) => Some(SpeechToText::Http {
+                url,
+                model: var("WHISPER_MODEL").unwrap_or_else(|| "whisper-1".to_string()),
+            }),
+            (None, Some(binary), Some(model)) => Some(SpeechToText::WhisperCpp { binary: binary.into(), model: model.into() }),
+            _ => None,
+        };
+        let tts = match (var("TTS_URL"), var("PIPER_BIN"), var("PIPER_MODEL")) {
+            (Some(url), _, _) => Some(TextToSpeech::Http {
+                url,
+                model: var("TTS_MODEL").unwrap_or_else(|| "tts-1".to_string()),
+                voice: var("TTS_VOICE").unwrap_or_else(|| "alloy".to_string()),
+            }),
+            (None, Some(binary), Some(model)) => Some(TextToSpeech::Piper { binary: binary.into(), model: model.into() }),
+            _ => None,
+        };
+        Self {
+            stt,
+            tts,
+            timeout: Duration::from_secs(var("SPEECH_TIMEOUT_SECS").and_then(|v| v.parse().ok()).unwrap_or(120)),
+        }
+    }
+}
+
+/// Audio handed to or produced by a backend
+struct Audio {
+    bytes: Vec<u8>,
+    mime_type: String,
+}
+
+/// Speech to text and text to speech through local backends, for a voice
+/// front end to the agent. Audio files are read from and written to the
+/// filesystem roots only (`MCP_FS_ROOTS`, narrowed by the client's).
+pub struct SpeechPlugin {
+    config: SpeechConfig,
+    roots: Vec<PathBuf>,
+    client: reqwest::Client,
+}
+
+impl Default for SpeechPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpeechPlugin {
+    pub fn new() -> Self {
+        Self::with_config(SpeechConfig::from_env(), roots::configured())
+    }
+
+    pub fn with_config(config: SpeechConfig, roots: Vec<PathBuf>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .unwrap_or_else(|e| {
+                error!("Failed to build speech HTTP client, using defaults: {}", e);
+                reqwest::Client::new()
+            });
+        Self { config, roots, client }
+    }
+
+    async fn transcribe(&self, audio: Vec<u8>, file_name: &str, language: Option<&str>) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        if audio.len() > MAX_AUDIO_BYTES {
+            return Err(error(format!("Audio is {} bytes, more than the {} allowed", audio.len(), MAX_AUDIO_BYTES)));
+        }
+        let text = match &self.config.stt {
+            None => return Err(error("No speech-to-text backend configured; set WHISPER_URL, or WHISPER_CPP_BIN and WHISPER_CPP_MODEL")),
+            Some(SpeechToText::Http { url, model }) => {
+                debug!("Transcribing {} bytes with {}", audio.len(), url);
+                crate::mcp::budget::count_upstream_bytes(audio.len());
+                let mut form = reqwest::multipart::Form::new()
+                    .part("file", reqwest::multipart::Part::bytes(audio).file_name(file_name.to_string()))
+                    .text("model", model.clone())
+                    .text("response_format", "json");
+                if let Some(language) = language {
+                    form = form.text("language", language.to_string());
+                }
+                let response = self.client.post(url).multipart(form).send().await
+                    .map_err(|e| error(format!("Whisper server unreachable at {}: {}", url, e)))?;
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                crate::mcp::budget::count_upstream_bytes(text.len());
+                if !status.is_success() {
+                    return Err(error(format!("Whisper server returned {}: {}", status, text)));
+                }
+                let body: Value = serde_json::from_str(&text)
+                    .map_err(|e| error(format!("Failed to parse Whisper response: {}", e)))?;
+                body["text"].as_str().ok_or_else(|| error("Whisper response has no text"))?.to_string()
+            }
+            Some(SpeechToText::WhisperCpp { binary, model }) => {
+                // whisper.cpp reads the audio from a file
+                let input = TempFile::new(file_name);
+                tokio::fs::write(&input.0, &audio).await?;
+                let mut command = Command::new(binary);
+                command.arg("-m").arg(model).arg("-f").arg(&input.0).args(["-nt", "-np"]);
+                if let Some(language) = language {
+                    command.args(["-l", language]);
+                }
+                let output = self.run(command, None).await?;
+                String::from_utf8_lossy(&output).into_owned()
+            }
+        };
+        Ok(json!({ "text": text.trim(), "backend": self.stt_backend() }))
+    }
+
+    async fn speak(&self, text: &str, voice: Option<&str>) -> Result<Audio, Box<dyn Error + Send + Sync>> {
+        match &self.config.tts {
+            None => Err(error("No text-to-speech backend configured; set TTS_URL, or PIPER_BIN and PIPER_MODEL")),
+            Some(TextToSpeech::Http { url, model, voice: default_voice }) => {
+                debug!("Speaking {} characters with {}", text.len(), url);
+                let body = json!({
+                    "model": model,
+                    "input": text,
+                    "voice": voice.unwrap_or(default_voice),
+                    "response_format": "wav",
+                });
+                crate::mcp::budget::count_upstream_bytes(body.to_string().len());
+                let response = self.client.post(url)
+                    .json(&body)
+                    .send()
+                    .await
+                    .map_err(|e| error(format!("TTS server unreachable at {}: {}", url, e)))?;
+                let status = response.status();
+                if !status.is_success() {
+                    let message = response.text().await.unwrap_or_default();
+                    crate::mcp::budget::count_upstream_bytes(message.len());
+                    return Err(error(format!("TTS server returned {}: {}", status, message)));
+                }
+                let mime_type = response.headers().get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or("audio/wav")
+                    .to_string();
+                let bytes = response.bytes().await?.to_vec();
+                crate::mcp::budget::count_upstream_bytes(bytes.len());
+                Ok(Audio { bytes, mime_type })
+            }
+            Some(TextToSpeech::Piper { binary, model }) => {
+                let output = TempFile::new("speech.wav");
+                let mut command = Command::new(binary);
+                command.arg("--model").arg(model).arg("--output_file").arg(&output.0);
+                if let Some(speaker) = voice {
+                    command.args(["--speaker", speaker]);
+                }
+                self.run(command, Some(text)).await?;
+                Ok(Audio { bytes: tokio::fs::read(&output.0).await?, mime_type: "audio/wav".to_string() })
+            }
+        }
+    }
+
+    /// Runs a backend command, feeding it `stdin`, and returns its stdout
+    async fn run(&self, mut command: Command, stdin: Option<&str>) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let program = command.as_std().get_program().to_string_lossy().into_owned();
+        let mut child = command
+            .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| error(format!("Failed to start {}: {}", program, e)))?;
+        if let (Some(text), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(text.as_bytes()).await?;
+        }
+        let output = tokio::time::timeout(self.config.timeout, child.wait_with_output()).await
+            .map_err(|_| error(format!("{} did not finish within {} s", program, self.config.timeout.as_secs())))??;
+        if !output.status.success() {
+            return Err(error(format!("{} exited with {}: {}", program, output.status, String::from_utf8_lossy(&output.stderr).trim())));
+        }
+        Ok(output.stdout)
+    }
+
+    fn stt_backend(&self) -> Option<&'static str> {
+        self.config.stt.as_ref().map(|stt| match stt {
+            SpeechToText::Http { .. } => "http",
+            SpeechToText::WhisperCpp { .. } => "whisper.cpp",
+        })
+    }
+
+    fn tts_backend(&self) -> Option<&'static str> {
+        self.config.tts.as_ref().map(|tts| match tts {
+            TextToSpeech::Http { .. } => "http",
+            TextToSpeech::Piper { .. } => "piper",
+        })
+    }
+}
+
+/// A file in the temp directory, removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        let extension = Path::new(name).extension().and_then(|e| e.to_str()).unwrap_or("wav");
+        Self(std::env::temp_dir().join(format!("mcp-speech-{}.{}", uuid::Uuid::new_v4(), extension)))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[async_trait]
+impl Plugin for SpeechPlugin {
+    fn name(&self) -> &str {
+        "speech"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![
+            Capability {
+                name: "transcribe".to_string(),
+                description: "Transcribe speech in an audio file or base64 audio to text".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "audio_path".to_string(),
+                        description: "Path of the audio file, within the filesystem roots".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                    ParameterDefinition {
+                        name: "audio_base64".to_string(),
+                        description: "The audio itself, base64 encoded, instead of audio_path".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                    ParameterDefinition {
+                        name: "language".to_string(),
+                        description: "Spoken language as an ISO 639-1 code, e.g. en; detected if omitted".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "speak".to_string(),
+                description: "Turn text into speech audio".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "text".to_string(),
+                        description: "Text to speak".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: true,
+                    },
+                    ParameterDefinition {
+                        name: "voice".to_string(),
+                        description: "Voice (TTS server) or speaker id (Piper); the backend's default if omitted".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                    ParameterDefinition {
+                        name: "output_path".to_string(),
+                        description: "Write the audio to this path, within the filesystem roots, instead of returning it".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+        ]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        info!("Executing speech plugin capability: {}", capability);
+        let allowed = roots::effective(&self.roots, context.roots.as_deref());
+        let string = |name: &str| params.get(name).and_then(|v| v.as_str());
+
+        let data = match capability {
+            "transcribe" => {
+                let (audio, file_name) = match (string("audio_path"), string("audio_base64")) {
+                    (Some(path), _) => {
+                        let path = roots::resolve(path, &allowed)?;
+                        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                        (tokio::fs::read(&path).await?, name)
+                    }
+                    (None, Some(encoded)) => {
+                        let audio = BASE64.decode(encoded.trim()).map_err(|e| error(format!("Invalid audio_base64: {}", e)))?;
+                        (audio, "audio.wav".to_string())
+                    }
+                    (None, None) => return Err(error("audio_path or audio_base64 is required")),
+                };
+                self.transcribe(audio, &file_name, string("language")).await?
+            }
+            "speak" => {
+                let text = string("text").filter(|text| !text.trim().is_empty()).ok_or_else(|| error("text is required"))?;
+                // Resolved first so a path outside the roots fails before the backend runs
+                let output_path = string("output_path").map(|path| roots::resolve_new(path, &allowed)).transpose()?;
+                let audio = self.speak(text, string("voice")).await?;
+                let mut data = json!({
+                    "mime_type": audio.mime_type,
+                    "bytes": audio.bytes.len(),
+                    "backend": self.tts_backend(),
+                });
+                match output_path {
+                    Some(path) => {
+                        // create_new neither follows a link nor overwrites a file put there since
+                        let mut file = tokio::fs::OpenOptions::new().write(true).create_new(true).open(&path).await
+                            .map_err(|e| error(format!("Cannot create {}: {}", path.display(), e)))?;
+                        file.write_all(&audio.bytes).await?;
+                        file.flush().await?;
+                        data["path"] = json!(path);
+                    }
+                    None => data["data_base64"] = json!(BASE64.encode(&audio.bytes)),
+                }
+                data
+            }
+            _ => return Err(error(format!("Unknown capability: {}", capability))),
+        };
+
+        Ok(PluginResult {
+            success: true,
+            data,
+            metrics: None,
+            context_updates: None,
+        })
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        let details = json!({
+            "speech_to_text": self.stt_backend(),
+            "text_to_speech": self.tts_backend(),
+        });
+        if self.config.stt.is_none() && self.config.tts.is_none() {
+            return HealthStatus::unhealthy("No speech backends configured").with_details(details);
+        }
+        HealthStatus::healthy().with_details(details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn plugin(server: &MockServer, roots: Vec<PathBuf>) -> SpeechPlugin {
+        SpeechPlugin::with_config(
+            SpeechConfig {
+                stt: Some(SpeechToText::Http { url: format!("{}/inference", server.uri()), model: "whisper-1".to_string() }),
+                tts: Some(TextToSpeech::Http {
+                    url: format!("{}/v1/audio/speech", server.uri()),
+                    model: "tts-1".to_string(),
+                    voice: "alloy".to_string(),
+                }),
+                timeout: Duration::from_secs(5),
+            },
+            roots,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_reads_audio_within_the_roots() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/inference"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"text": " Turn on the kitchen lights.\n"})))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path().canonicalize().unwrap();
+        std::fs::write(root_path.join("command.wav"), b"RIFF....WAVE").unwrap();
+        let plugin = plugin(&server, vec![root_path.clone()]);
+
+        let params = HashMap::from([("audio_path".to_string(), json!(root_path.join("command.wav")))]);
+        let result = plugin.execute("transcribe", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["text"], "Turn on the kitchen lights.");
+        assert_eq!(result.data["backend"], "http");
+
+        let params = HashMap::from([("audio_base64".to_string(), json!(BASE64.encode(b"RIFF....WAVE")))]);
+        assert!(plugin.execute("transcribe", Context::for_test(), params).await.is_ok());
+
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        let params = HashMap::from([("audio_path".to_string(), json!(outside.path()))]);
+        let error = plugin.execute("transcribe", Context::for_test(), params).await.unwrap_err();
+        assert!(error.to_string().contains("outside the allowed roots"));
+        let error = plugin.execute("transcribe", Context::for_test(), HashMap::new()).await.unwrap_err();
+        assert!(error.to_string().contains("audio_path or audio_base64 is required"));
+    }
+
+    #[tokio::test]
+    async fn test_speak_returns_or_writes_the_audio() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/audio/speech"))
+            .and(body_partial_json(json!({"input": "Good morning", "voice": "nova"})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(b"RIFFaudio".to_vec(), "audio/wav"))
+            .mount(&server)
+            .await;
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path().canonicalize().unwrap();
+        let plugin = plugin(&server, vec![root_path.clone()]);
+
+        let params = HashMap::from([("text".to_string(), json!("Good morning")), ("voice".to_string(), json!("nova"))]);
+        let result = plugin.execute("speak", Context::for_test(), params.clone()).await.unwrap();
+        assert_eq!(result.data["mime_type"], "audio/wav");
+        assert_eq!(result.data["data_base64"], BASE64.encode(b"RIFFaudio"));
+
+        let mut params = params;
+        params.insert("output_path".to_string(), json!(root_path.join("greeting.wav")));
+        let result = plugin.execute("speak", Context::for_test(), params.clone()).await.unwrap();
+        assert!(result.data.get("data_base64").is_none());
+        assert_eq!(std::fs::read(root_path.join("greeting.wav")).unwrap(), b"RIFFaudio");
+
+        // Neither an existing file nor a link is written through
+        let error = plugin.execute("speak", Context::for_test(), params.clone()).await.unwrap_err();
+        assert!(error.to_string().contains("Cannot create"));
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        std::os::unix::fs::symlink(outside.path(), root_path.join("link.wav")).unwrap();
+        params.insert("output_path".to_string(), json!(root_path.join("link.wav")));
+        let error = plugin.execute("speak", Context::for_test(), params).await.unwrap_err();
+        assert!(error.to_string().contains("is a symbolic link"));
+        assert_eq!(std::fs::read(outside.path()).unwrap(), b"");
+    }
+
+    #[tokio::test]
+    async fn test_unconfigured_backends_fail_with_a_hint() {
+        let plugin = SpeechPlugin::with_config(SpeechConfig { stt: None, tts: None, timeout: Duration::from_secs(1) }, Vec::new());
+        let params = HashMap::from([("audio_base64".to_string(), json!(BASE64.encode(b"RIFF")))]);
+        let error = plugin.execute("transcribe", Context::for_test(), params).await.unwrap_err();
+        assert!(error.to_string().contains("WHISPER_URL"));
+        let params = HashMap::from([("text".to_string(), json!("hello"))]);
+        let error = plugin.execute("speak", Context::for_test(), params).await.unwrap_err();
+        assert!(error.to_string().contains("TTS_URL"));
+        assert!(!plugin.health_check().await.healthy);
+    }
+}
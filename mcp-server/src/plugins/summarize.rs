@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+use tracing::info;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType};
+use crate::mcp::sampling::{CreateMessageParams, SamplingMessage};
+
+/// Default length of a summary, in words
+const DEFAULT_MAX_WORDS: u64 = 100;
+
+#[derive(Debug)]
+struct SummarizePluginError(String);
+
+impl fmt::Display for SummarizePluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for SummarizePluginError {}
+
+/// Summarizes text, such as another tool's result, with whatever model the
+/// connected client samples from
+#[derive(Default)]
+pub struct SummarizePlugin;
+
+impl SummarizePlugin {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Plugin for SummarizePlugin {
+    fn name(&self) -> &str {
+        "summarize"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![Capability {
+            name: "summarize".to_string(),
+            description: "Summarize text using the client's model".to_string(),
+            parameters: vec![
+                ParameterDefinition {
+                    name: "text".to_string(),
+                    description: "Text to summarize".to_string(),
+                    parameter_type: ParameterType::String,
+                    required: true,
+                },
+                ParameterDefinition {
+                    name: "max_words".to_string(),
+                    description: format!("Rough length of the summary (default: {})", DEFAULT_MAX_WORDS),
+                    parameter_type: ParameterType::Number,
+                    required: false,
+                },
+            ],
+            version: None,
+            deprecated: None,
+        }]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        if capability != "summarize" {
+            return Err(Box::new(SummarizePluginError(format!("Unknown capability: {}", capability))));
+        }
+        let text = params.get("text")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| Box::new(SummarizePluginError("text is required".to_string())))?;
+        let max_words = params.get("max_words").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_MAX_WORDS);
+        let sampler = context.sampling
+            .ok_or_else(|| Box::new(SummarizePluginError("Sampling is not available for this call".to_string())))?;
+
+        info!("Summarizing {} characters in about {} words", text.len(), max_words);
+        let result = sampler
+            .create_message(CreateMessageParams {
+                messages: vec![SamplingMessage::user(text)],
+                system_prompt: Some(format!(
+                    "Summarize the user's text in at most {} words. Reply with the summary only.",
+                    max_words
+                )),
+                // Generous for English; the prompt does the real limiting
+                max_tokens: (max_words * 2) as u32 + 50,
+                model_preferences: None,
+            })
+            .await
+            .map_err(|e| Box::new(SummarizePluginError(e.to_string())))?;
+
+        Ok(PluginResult {
+            success: true,
+            data: json!({ "summary": result.text(), "model": result.model }),
+            metrics: None,
+            context_updates: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::client_requests::ClientRequests;
+    use crate::mcp::notifications::NotificationDispatcher;
+    use crate::mcp::sampling::{Sampler, SamplingBroker};
+    use std::sync::Arc;
+
+    fn context(sampling: Option<Sampler>) -> Context {
+        Context { sampling, ..Context::for_test() }
+    }
+
+    #[tokio::test]
+    async fn test_summarize_samples_through_client() {
+        let notifications = Arc::new(NotificationDispatcher::new());
+        let mut stream = notifications.subscribe("session");
+        let requests = Arc::new(ClientRequests::new(notifications));
+        let sampler = Sampler::new(Arc::new(SamplingBroker::new(requests.clone())), Some("session".to_string()));
+
+        tokio::spawn(async move {
+            let request: Value = serde_json::from_str(&stream.recv().await.unwrap()).unwrap();
+            assert!(request["params"]["systemPrompt"].as_str().unwrap().contains("at most 20 words"));
+            requests.resolve(&request["id"], Ok(json!({
+                "role": "assistant",
+                "content": {"type": "text", "text": "Disks are fine."},
+                "model": "llama3.1"
+            })));
+        });
+
+        let params = HashMap::from([
+            ("text".to_string(), json!("/: 40% used, /data: 71% used")),
+            ("max_words".to_string(), json!(20)),
+        ]);
+        let result = SummarizePlugin::new()
+            .execute("summarize", context(Some(sampler)), params)
+            .await
+            .unwrap();
+        assert_eq!(result.data["summary"], "Disks are fine.");
+        assert_eq!(result.data["model"], "llama3.1");
+    }
+
+    #[tokio::test]
+    async fn test_summarize_requires_text_and_sampling() {
+        let plugin = SummarizePlugin::new();
+        assert!(plugin.execute("summarize", context(None), HashMap::new()).await.is_err());
+
+        let params = HashMap::from([("text".to_string(), json!("hello"))]);
+        let error = plugin.execute("summarize", context(None), params).await.unwrap_err();
+        assert!(error.to_string().contains("Sampling is not available"));
+    }
+}
@@ -1,15 +1,18 @@
 use async_trait::async_trait;
 use chrono::Utc;
-use log::{info, error, debug};
+use tracing::{info, error, debug, warn};
 use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::error::Error;
 use std::fmt;
-use sysinfo::{System, SystemExt, CpuExt};
+use std::time::Instant;
+use sysinfo::{System, SystemExt, CpuExt, DiskExt, NetworkExt, NetworksExt, ProcessExt};
 
-use crate::context::{Neo4jContext, get_neo4j_context, RelationType};
+use crate::context::{ContextStore, Neo4jContext, get_neo4j_context};
 use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType};
+use super::container::{self, Container};
+use super::gpu;
 
 #[derive(Debug)]
 struct SystemPluginError(String);
@@ -23,21 +26,59 @@ impl fmt::Display for SystemPluginError {
 // SystemPluginError automatically implements Send + Sync because String does
 impl Error for SystemPluginError {}
 
+type ContextSlot = Arc<tokio::sync::RwLock<Option<Arc<Neo4jContext>>>>;
+
 pub struct SystemInfoPlugin {
     sys: Arc<tokio::sync::Mutex<System>>,
-    context: Arc<tokio::sync::RwLock<Option<Arc<Neo4jContext>>>>,
+    context: ContextSlot,
+    last_network_refresh: Arc<tokio::sync::Mutex<Instant>>,
+    /// The container's CPU time at the previous `get_system_info`, for its
+    /// CPU usage since then
+    last_container_cpu: Arc<tokio::sync::Mutex<Option<(Instant, u64)>>>,
+    store_metrics: bool,
 }
 
 impl SystemInfoPlugin {
     pub fn new() -> Self {
+        // Metric persistence can be turned off for deployments without Neo4j
+        let store_metrics = std::env::var("SYSTEM_INFO_STORE_METRICS")
+            .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "no" | "off"))
+            .unwrap_or(true);
+
         Self {
             sys: Arc::new(tokio::sync::Mutex::new(System::new_all())),
             context: Arc::new(tokio::sync::RwLock::new(None)),
+            last_network_refresh: Arc::new(tokio::sync::Mutex::new(Instant::now())),
+            last_container_cpu: Arc::new(tokio::sync::Mutex::new(None)),
+            store_metrics,
         }
     }
+
+    pub fn with_metric_storage(mut self, enabled: bool) -> Self {
+        self.store_metrics = enabled;
+        self
+    }
+
+    /// Queues metrics for best-effort persistence in Neo4j without blocking the caller.
+    /// Returns whether the metrics were queued; whether they were then stored is only logged.
+    fn spawn_store_metrics(&self, metrics: HashMap<String, serde_json::Value>) -> bool {
+        if !self.store_metrics {
+            debug!("Metric storage disabled, skipping Neo4j persistence");
+            return false;
+        }
+
+        let context = self.context.clone();
+        tokio::spawn(async move {
+            match Self::store_metrics(&context, &metrics).await {
+                Ok(_) => info!("Successfully stored metrics in Neo4j"),
+                Err(e) => warn!("Failed to store metrics in Neo4j: {:#}", e),
+            }
+        });
+        true
+    }
     
-    async fn ensure_context(&self) -> Result<Arc<Neo4jContext>, Box<dyn Error + Send + Sync>> {
-        let mut context = self.context.write().await;
+    async fn ensure_context(context: &ContextSlot) -> Result<Arc<Neo4jContext>, Box<dyn Error + Send + Sync>> {
+        let mut context = context.write().await;
         if context.is_none() {
             *context = Some(get_neo4j_context().await.map_err(|e| {
                 Box::new(SystemPluginError(format!("Failed to get Neo4j context: {}", e))) as Box<dyn Error + Send + Sync>
@@ -46,11 +87,11 @@ impl SystemInfoPlugin {
         Ok(context.as_ref().unwrap().clone())
     }
     
-    async fn store_metrics(&self, metrics: &HashMap<String, serde_json::Value>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    async fn store_metrics(context: &ContextSlot, metrics: &HashMap<String, serde_json::Value>) -> Result<(), Box<dyn Error + Send + Sync>> {
         info!("Attempting to store system metrics");
         debug!("Metrics to store: {:?}", metrics);
         
-        let context = match self.ensure_context().await {
+        let context = match Self::ensure_context(context).await {
             Ok(ctx) => {
                 info!("Successfully obtained Neo4j context");
                 debug!("Neo4j context acquired successfully");
@@ -62,63 +103,15 @@ impl SystemInfoPlugin {
                 return Err(Box::new(SystemPluginError(format!("Failed to get Neo4j context: {:#}", e))) as Box<dyn Error + Send + Sync>);
             }
         };
-        // Store the complete system state
-        debug!("Storing complete system state...");
-        let state_node = context.store_system_state(metrics.clone()).await
-            .map_err(|e| {
-                error!("Failed to store system state: {}", e);
-                Box::new(SystemPluginError(format!("Failed to store system state: {}", e))) as Box<dyn Error + Send + Sync>
-            })?;
-        debug!("System state stored successfully");
-        
-        // Store individual metrics
-        for (metric_name, value) in metrics {
-            debug!("Storing metric '{}' with value: {:?}", metric_name, value);
-            let metric_node = context.store_metric(metric_name, value.clone(), Utc::now()).await
-                .map_err(|e| {
-                    error!("Failed to store metric '{}': {}", metric_name, e);
-                    Box::new(SystemPluginError(format!("Failed to store metric '{}': {}", metric_name, e))) as Box<dyn Error + Send + Sync>
-                })?;
-            debug!("Metric '{}' stored successfully", metric_name);
-            
-            // Create relationship between state and metric
-            debug!("Creating relationship for metric '{}'...", metric_name);
-            let mut props = HashMap::new();
-            props.insert("timestamp".to_string(), json!(Utc::now().to_rfc3339()));
-            
-            let state_id = state_node.get::<String>("id")
-                .map_err(|e| {
-                    error!("Failed to get state ID: {}", e);
-                    Box::new(SystemPluginError(format!("Failed to get state ID: {}", e))) as Box<dyn Error + Send + Sync>
-                })?;
-            let metric_id = metric_node.get::<String>("id")
-                .map_err(|e| {
-                    error!("Failed to get metric ID: {}", e);
-                    Box::new(SystemPluginError(format!("Failed to get metric ID: {}", e))) as Box<dyn Error + Send + Sync>
-                })?;
-            debug!("Creating relationship between state '{}' and metric '{}'", state_id, metric_id);
-                
-            context.create_relationship(
-                &state_id,
-                &metric_id,
-                RelationType::Contains,
-                Some(props),
-            ).await
-                .map_err(|e| {
-                    error!("Failed to create relationship for metric '{}': {}", metric_name, e);
-                    Box::new(SystemPluginError(format!("Failed to create relationship: {}", e))) as Box<dyn Error + Send + Sync>
-                })?;
-            debug!("Relationship created successfully for metric '{}'", metric_name);
-        }
-        
-        Ok(())
+        persist_metrics(context.as_ref(), metrics).await
     }
 
-    async fn get_system_info(&self) -> HashMap<String, serde_json::Value> {
+    pub(crate) async fn get_system_info(&self) -> HashMap<String, serde_json::Value> {
         debug!("Getting system information...");
         let mut sys = self.sys.lock().await;
         debug!("Refreshing system metrics...");
         sys.refresh_all();
+        *self.last_network_refresh.lock().await = Instant::now();
         
         let mut info = HashMap::new();
         
@@ -129,9 +122,10 @@ impl SystemInfoPlugin {
         debug!("CPU usage: {:.2}%", cpu_usage);
         info.insert("cpu_usage".to_string(), json!(cpu_usage));
         
-        // Memory information
-        let total_memory = sys.total_memory();
-        let used_memory = sys.used_memory();
+        // Memory information, the container's where it is limited to less
+        // than the host has
+        let container = container::detect();
+        let (total_memory, used_memory) = effective_memory(&container, sys.total_memory(), sys.used_memory());
         let memory_usage = (used_memory as f64 / total_memory as f64) * 100.0;
         
         info.insert("total_memory_kb".to_string(), json!(total_memory));
@@ -148,9 +142,150 @@ impl SystemInfoPlugin {
         if let Some(hostname) = sys.host_name() {
             info.insert("hostname".to_string(), json!(hostname));
         }
+        info.insert("cpu_count".to_string(), json!(sys.cpus().len()));
+        info.insert("uptime_secs".to_string(), json!(sys.uptime()));
+        let load = sys.load_average();
+        info.insert("load_average".to_string(), json!({
+            "one": load.one,
+            "five": load.five,
+            "fifteen": load.fifteen,
+        }));
+
+        // Container information, with the host's totals for comparison
+        info.insert("containerized".to_string(), json!(container.is_containerized()));
+        if container.is_containerized() {
+            let cpu_usage = self.container_cpu_usage(&container, sys.cpus().len()).await;
+            let mut details = json!(container);
+            details["cpu_usage_percent"] = json!(cpu_usage);
+            details["host_total_memory_bytes"] = json!(sys.total_memory());
+            details["host_used_memory_bytes"] = json!(sys.used_memory());
+            info.insert("container".to_string(), details);
+        }
 
         info
     }
+
+    /// The container's CPU usage since the previous call, in percent of the
+    /// CPUs it may use; `None` on the first call
+    async fn container_cpu_usage(&self, container: &Container, cpu_count: usize) -> Option<f64> {
+        let cpu_time = container.cpu_time_usec?;
+        let mut last = self.last_container_cpu.lock().await;
+        let previous = last.replace((Instant::now(), cpu_time));
+        let (at, previous_cpu_time) = previous?;
+        let elapsed_usec = at.elapsed().as_micros() as f64;
+        let cpus = container.cpu_limit.unwrap_or(cpu_count as f64);
+        if elapsed_usec <= 0.0 || cpus <= 0.0 {
+            return None;
+        }
+        Some(cpu_time.saturating_sub(previous_cpu_time) as f64 / (elapsed_usec * cpus) * 100.0)
+    }
+
+    async fn get_processes(&self, limit: usize, sort_by: &str) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
+        debug!("Getting top {} processes sorted by {}", limit, sort_by);
+        let mut sys = self.sys.lock().await;
+        sys.refresh_processes();
+
+        let mut processes: Vec<_> = sys.processes().iter().collect();
+        match sort_by {
+            "cpu" => processes.sort_by(|(_, a), (_, b)| b.cpu_usage().total_cmp(&a.cpu_usage())),
+            "memory" => processes.sort_by_key(|(_, p)| std::cmp::Reverse(p.memory())),
+            _ => return Err(Box::new(SystemPluginError(format!("Unsupported sort_by value: {}", sort_by)))),
+        }
+
+        let total = processes.len();
+        let top: Vec<_> = processes.into_iter()
+            .take(limit)
+            .map(|(pid, process)| json!({
+                "pid": pid.to_string(),
+                "name": process.name(),
+                "cpu_usage": process.cpu_usage(),
+                "memory_bytes": process.memory(),
+                "status": process.status().to_string(),
+            }))
+            .collect();
+
+        Ok(json!({
+            "sort_by": sort_by,
+            "total_processes": total,
+            "processes": top,
+        }))
+    }
+
+    pub(crate) async fn get_disks(&self) -> serde_json::Value {
+        debug!("Getting disk usage...");
+        let mut sys = self.sys.lock().await;
+        sys.refresh_disks_list();
+        sys.refresh_disks();
+
+        let disks: Vec<_> = sys.disks().iter()
+            .map(|disk| {
+                let total = disk.total_space();
+                let available = disk.available_space();
+                let used = total.saturating_sub(available);
+                let usage_percent = if total > 0 { (used as f64 / total as f64) * 100.0 } else { 0.0 };
+                json!({
+                    "name": disk.name().to_string_lossy(),
+                    "mount_point": disk.mount_point().to_string_lossy(),
+                    "file_system": String::from_utf8_lossy(disk.file_system()),
+                    "total_bytes": total,
+                    "available_bytes": available,
+                    "used_bytes": used,
+                    "usage_percent": usage_percent,
+                    "removable": disk.is_removable(),
+                })
+            })
+            .collect();
+
+        json!({ "disks": disks })
+    }
+
+    async fn get_network(&self) -> serde_json::Value {
+        debug!("Getting network interface throughput...");
+        let mut sys = self.sys.lock().await;
+        let mut last_refresh = self.last_network_refresh.lock().await;
+        sys.refresh_networks();
+        let elapsed = last_refresh.elapsed().as_secs_f64();
+        *last_refresh = Instant::now();
+
+        // received()/transmitted() are deltas since the previous refresh
+        let per_second = |bytes: u64| if elapsed > 0.0 { bytes as f64 / elapsed } else { 0.0 };
+        let interfaces: Vec<_> = sys.networks().iter()
+            .map(|(name, data)| json!({
+                "interface": name,
+                "received_bytes_per_sec": per_second(data.received()),
+                "transmitted_bytes_per_sec": per_second(data.transmitted()),
+                "total_received_bytes": data.total_received(),
+                "total_transmitted_bytes": data.total_transmitted(),
+            }))
+            .collect();
+
+        json!({
+            "sample_interval_secs": elapsed,
+            "interfaces": interfaces,
+        })
+    }
+}
+
+/// Total and used memory, the container's when its limit is below the
+/// host's total
+fn effective_memory(container: &Container, host_total: u64, host_used: u64) -> (u64, u64) {
+    match (container.memory_limit_bytes, container.memory_used_bytes) {
+        (Some(limit), Some(used)) if limit < host_total => (limit, used.min(limit)),
+        _ => (host_total, host_used),
+    }
+}
+
+/// Stores a system state snapshot plus one linked node per metric, all at
+/// once where the store supports it
+pub(crate) async fn persist_metrics(store: &dyn ContextStore, metrics: &HashMap<String, serde_json::Value>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    debug!("Storing system state with {} metrics...", metrics.len());
+    let state_id = store.store_snapshot(metrics.clone(), Utc::now()).await
+        .map_err(|e| {
+            error!("Failed to store system state: {}", e);
+            Box::new(SystemPluginError(format!("Failed to store system state: {}", e))) as Box<dyn Error + Send + Sync>
+        })?;
+    debug!("System state {} stored successfully", state_id);
+    Ok(())
 }
 
 #[async_trait]
@@ -167,8 +302,10 @@ impl Plugin for SystemInfoPlugin {
         vec![
             Capability {
                 name: "get_system_info".to_string(),
-                description: "Get current system information including CPU, memory, and OS details".to_string(),
+                description: "Get current system information including CPU, memory, OS details, uptime, load averages and container limits".to_string(),
                 parameters: vec![],
+                version: None,
+                deprecated: None,
             },
             Capability {
                 name: "get_memory_usage".to_string(),
@@ -181,6 +318,49 @@ impl Plugin for SystemInfoPlugin {
                         required: false,
                     },
                 ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "get_processes".to_string(),
+                description: "Get the top processes by CPU or memory usage".to_string(),
+                parameters: vec![
+                    ParameterDefinition {
+                        name: "limit".to_string(),
+                        description: "Maximum number of processes to return (default: 10)".to_string(),
+                        parameter_type: ParameterType::Number,
+                        required: false,
+                    },
+                    ParameterDefinition {
+                        name: "sort_by".to_string(),
+                        description: "Sort order, either \"cpu\" or \"memory\" (default: cpu)".to_string(),
+                        parameter_type: ParameterType::String,
+                        required: false,
+                    },
+                ],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "get_disks".to_string(),
+                description: "Get disk usage per mount point".to_string(),
+                parameters: vec![],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "get_network".to_string(),
+                description: "Get per-interface network throughput since the previous sample".to_string(),
+                parameters: vec![],
+                version: None,
+                deprecated: None,
+            },
+            Capability {
+                name: "get_gpu_info".to_string(),
+                description: "Get utilization, memory and temperature per GPU (NVIDIA, AMD or Apple)".to_string(),
+                parameters: vec![],
+                version: None,
+                deprecated: None,
             },
         ]
     }
@@ -200,23 +380,13 @@ impl Plugin for SystemInfoPlugin {
                 let info = self.get_system_info().await;
                 debug!("Collected system info: {:?}", info);
                 
-                // Store metrics in Neo4j
-                info!("Attempting to store metrics in Neo4j");
-                match self.store_metrics(&info).await {
-                    Ok(_) => {
-                        info!("Successfully stored metrics in Neo4j");
-                    },
-                    Err(e) => {
-                        error!("Failed to store metrics in Neo4j: {:#}", e);
-                        debug!("Full error context: {:?}", e);
-                        return Err(Box::new(SystemPluginError(format!("Failed to store metrics in Neo4j: {:#}", e))) as Box<dyn Error + Send + Sync>);
-                    }
-                }
-                info!("Successfully stored metrics in Neo4j");
+                let queued = self.spawn_store_metrics(info.clone());
+                let mut data = json!(info);
+                data["storage_queued"] = json!(queued);
                 
                 Ok(PluginResult {
                     success: true,
-                    data: json!(info),
+                    data,
                     metrics: Some(HashMap::from([
                         ("execution_time_ms".to_string(), 0.0),
                     ])),
@@ -234,33 +404,81 @@ impl Plugin for SystemInfoPlugin {
                     .and_then(|v| v.as_bool())
                     .unwrap_or(false);
 
+                let container = container::detect();
+                let (total_memory, used_memory) = effective_memory(&container, sys.total_memory(), sys.used_memory());
+                let limited = total_memory != sys.total_memory();
+
                 let mut memory_info = HashMap::new();
-                memory_info.insert("total_memory_kb".to_string(), json!(sys.total_memory()));
-                memory_info.insert("used_memory_kb".to_string(), json!(sys.used_memory()));
+                memory_info.insert("total_memory_kb".to_string(), json!(total_memory));
+                memory_info.insert("used_memory_kb".to_string(), json!(used_memory));
                 
                 if include_details {
-                    memory_info.insert("free_memory_kb".to_string(), json!(sys.free_memory()));
-                    memory_info.insert("available_memory_kb".to_string(), json!(sys.available_memory()));
+                    // Within a container limit, whatever it does not use is free
+                    let (free, available) = if limited {
+                        (total_memory - used_memory, total_memory - used_memory)
+                    } else {
+                        (sys.free_memory(), sys.available_memory())
+                    };
+                    memory_info.insert("free_memory_kb".to_string(), json!(free));
+                    memory_info.insert("available_memory_kb".to_string(), json!(available));
+                    memory_info.insert("container_limited".to_string(), json!(limited));
                 }
                 drop(sys); // Release the lock before async operations
                 
-                // Store memory metrics in Neo4j
-                info!("Attempting to store memory metrics in Neo4j");
-                if let Err(e) = self.store_metrics(&memory_info).await {
-                    error!("Failed to store memory metrics in Neo4j: {}", e);
-                    return Err(e);
-                }
-                info!("Successfully stored memory metrics in Neo4j");
+                let queued = self.spawn_store_metrics(memory_info.clone());
+                let mut data = json!(memory_info);
+                data["storage_queued"] = json!(queued);
 
                 Ok(PluginResult {
                     success: true,
-                    data: json!(memory_info),
+                    data,
                     metrics: Some(HashMap::from([
                         ("execution_time_ms".to_string(), 0.0),
                     ])),
                     context_updates: None,
                 })
             },
+            "get_processes" => {
+                let limit = params.get("limit")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(10) as usize;
+                let sort_by = params.get("sort_by")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("cpu");
+
+                let processes = self.get_processes(limit, sort_by).await?;
+                Ok(PluginResult {
+                    success: true,
+                    data: processes,
+                    metrics: None,
+                    context_updates: None,
+                })
+            },
+            "get_disks" => {
+                Ok(PluginResult {
+                    success: true,
+                    data: self.get_disks().await,
+                    metrics: None,
+                    context_updates: None,
+                })
+            },
+            "get_network" => {
+                Ok(PluginResult {
+                    success: true,
+                    data: self.get_network().await,
+                    metrics: None,
+                    context_updates: None,
+                })
+            },
+            "get_gpu_info" => {
+                let gpus = gpu::query().await;
+                Ok(PluginResult {
+                    success: true,
+                    data: json!({ "count": gpus.len(), "gpus": gpus }),
+                    metrics: None,
+                    context_updates: None,
+                })
+            },
             _ => Err(Box::new(SystemPluginError(String::from("Unsupported capability")))),
         }
     }
@@ -298,14 +516,14 @@ mod tests {
         let plugin = SystemInfoPlugin::new();
         let capabilities = plugin.capabilities();
         
-        assert_eq!(capabilities.len(), 2);
+        assert_eq!(capabilities.len(), 6);
         
         // Check get_system_info capability
         let get_info_cap = capabilities.iter()
             .find(|c| c.name == "get_system_info")
             .expect("get_system_info capability should exist");
         
-        assert_eq!(get_info_cap.description, "Get current system information including CPU, memory, and OS details");
+        assert_eq!(get_info_cap.description, "Get current system information including CPU, memory, OS details, uptime, load averages and container limits");
         assert_eq!(get_info_cap.parameters.len(), 0);
         
         // Check get_memory_usage capability
@@ -347,10 +565,71 @@ mod tests {
         assert!(used_memory <= total_memory);
         
         let memory_usage = info["memory_usage_percent"].as_f64().unwrap();
-        assert!(memory_usage >= 0.0 && memory_usage <= 100.0);
+        assert!((0.0..=100.0).contains(&memory_usage));
         
         let cpu_usage = info["cpu_usage"].as_f64().unwrap();
         assert!(cpu_usage >= 0.0);
+
+        assert!(info["cpu_count"].as_u64().unwrap() > 0);
+        assert!(info["uptime_secs"].is_u64());
+        assert!(info["load_average"]["one"].is_number());
+        let containerized = info["containerized"].as_bool().unwrap();
+        assert_eq!(info.contains_key("container"), containerized);
+        if containerized {
+            assert!(info["container"]["host_total_memory_bytes"].as_u64().unwrap() >= total_memory);
+        }
+    }
+
+    #[test]
+    fn test_effective_memory_uses_a_container_limit_below_the_host_total() {
+        let gib = 1 << 30;
+        let limited = Container {
+            memory_limit_bytes: Some(2 * gib),
+            memory_used_bytes: Some(gib),
+            ..Default::default()
+        };
+        assert_eq!(effective_memory(&limited, 16 * gib, 10 * gib), (2 * gib, gib));
+        // A limit above what the host has limits nothing
+        assert_eq!(effective_memory(&limited, gib, gib / 2), (gib, gib / 2));
+        assert_eq!(effective_memory(&Container::default(), 16 * gib, 10 * gib), (16 * gib, 10 * gib));
+    }
+
+    #[tokio::test]
+    async fn test_get_processes() {
+        let plugin = SystemInfoPlugin::new();
+
+        let by_memory = plugin.get_processes(3, "memory").await.unwrap();
+        let processes = by_memory["processes"].as_array().unwrap();
+        assert!(!processes.is_empty() && processes.len() <= 3);
+        let memory: Vec<u64> = processes.iter()
+            .map(|p| p["memory_bytes"].as_u64().unwrap())
+            .collect();
+        assert!(memory.windows(2).all(|w| w[0] >= w[1]));
+
+        assert!(plugin.get_processes(3, "bogus").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_disks_and_network() {
+        let plugin = SystemInfoPlugin::new();
+
+        let disks = plugin.get_disks().await;
+        assert!(disks["disks"].is_array());
+
+        let network = plugin.get_network().await;
+        assert!(network["interfaces"].is_array());
+        assert!(network["sample_interval_secs"].as_f64().unwrap() >= 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_get_gpu_info_without_gpus() {
+        // Machines without GPUs or vendor tools report an empty list, not an error
+        let plugin = SystemInfoPlugin::new();
+        let context = Context::for_test();
+
+        let result = plugin.execute("get_gpu_info", context, HashMap::new()).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.data["count"].as_u64().unwrap() as usize, result.data["gpus"].as_array().unwrap().len());
     }
 
     #[tokio::test]
@@ -391,11 +670,7 @@ mod tests {
     #[tokio::test]
     async fn test_unsupported_capability() {
         let plugin = SystemInfoPlugin::new();
-        let context = Context {
-            correlation_id: "test-123".to_string(),
-            timestamp: Utc::now(),
-            parameters: HashMap::new(),
-        };
+        let context = Context::for_test();
         
         let result = plugin.execute(
             "unsupported_capability",
@@ -408,6 +683,31 @@ mod tests {
         assert!(error_msg.contains("Unsupported capability"));
     }
 
+    #[tokio::test]
+    async fn test_execute_without_metric_storage() {
+        let plugin = SystemInfoPlugin::new().with_metric_storage(false);
+        let context = Context::for_test();
+
+        let result = plugin.execute("get_system_info", context, HashMap::new()).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.data["storage_queued"], json!(false));
+        assert!(result.data["cpu_usage"].is_number());
+    }
+
+    #[tokio::test]
+    async fn test_execute_does_not_wait_for_storage() {
+        // Storage runs in the background, so an unreachable Neo4j must not fail or delay the call
+        let plugin = SystemInfoPlugin::new().with_metric_storage(true);
+        let context = Context::for_test();
+
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            plugin.execute("get_memory_usage", context, HashMap::new()),
+        ).await.expect("execute should not block on Neo4j").unwrap();
+        assert!(result.success);
+        assert_eq!(result.data["storage_queued"], json!(true));
+    }
+
     #[test]
     fn test_parameter_types() {
         let plugin = SystemInfoPlugin::new();
@@ -459,6 +759,9 @@ mod tests {
                 params.insert("test_param".to_string(), json!("test_value"));
                 params
             },
+            sampling: None,
+            roots: None,
+            tenant: None,
         };
         
         assert_eq!(context.correlation_id, "test-correlation-id");
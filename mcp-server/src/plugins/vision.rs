@@ -0,0 +1,372 @@
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use tracing::{info, debug, error};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::home_assistant::HomeAssistantPlugin;
+use super::{Plugin, Context, PluginResult, Capability, ParameterDefinition, ParameterType, HealthStatus};
+use crate::mcp::roots;
+
+/// Largest image sent to the model
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+const DEFAULT_PROMPT: &str = "Describe this image in a few sentences.";
+
+#[derive(Debug)]
+struct VisionPluginError(String);
+
+impl fmt::Display for VisionPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for VisionPluginError {}
+
+fn error(message: impl Into<String>) -> Box<dyn Error + Send + Sync> {
+    Box::new(VisionPluginError(message.into()))
+}
+
+/// The multimodal Ollama model images are sent to
+#[derive(Debug, Clone)]
+pub struct VisionConfig {
+    pub base_url: String,
+    pub model: String,
+    pub timeout: Duration,
+}
+
+impl VisionConfig {
+    pub fn from_env() -> Self {
+        Self {
+            base_url: std::env::var("OLLAMA_BASE_URL").unwrap_or_else(|_| "http://localhost:11434".to_string()),
+            model: std::env::var("VISION_MODEL").ok().filter(|m| !m.is_empty()).unwrap_or_else(|| "llava".to_string()),
+            timeout: Duration::from_secs(
+                std::env::var("VISION_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(120),
+            ),
+        }
+    }
+}
+
+/// Describes or classifies images with a vision model on Ollama. Images come
+/// from Home Assistant cameras, from files within the filesystem roots, or
+/// from the caller as base64.
+pub struct VisionPlugin {
+    config: VisionConfig,
+    home_assistant: Arc<HomeAssistantPlugin>,
+    roots: Vec<PathBuf>,
+    client: reqwest::Client,
+}
+
+impl VisionPlugin {
+    pub fn new(home_assistant: Arc<HomeAssistantPlugin>) -> Self {
+        Self::with_config(VisionConfig::from_env(), home_assistant, roots::configured())
+    }
+
+    pub fn with_config(config: VisionConfig, home_assistant: Arc<HomeAssistantPlugin>, roots: Vec<PathBuf>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .unwrap_or_else(|e| {
+                error!("Failed to build vision HTTP client, using defaults: {}", e);
+                reqwest::Client::new()
+            });
+        Self { config, home_assistant, roots, client }
+    }
+
+    /// The image named by `camera`, `image_path` or `image_base64`, with its
+    /// MIME type and where it came from
+    async fn load_image(&self, params: &HashMap<String, Value>, allowed: &[PathBuf]) -> Result<(Vec<u8>, String, String), Box<dyn Error + Send + Sync>> {
+        let string = |name: &str| params.get(name).and_then(|v| v.as_str());
+        let (image, mime_type, source) = if let Some(camera) = string("camera") {
+            let (image, mime_type) = self.home_assistant.camera_snapshot(camera).await?;
+            (image, mime_type, camera.to_string())
+        } else if let Some(path) = string("image_path") {
+            let resolved = roots::resolve(path, allowed)?;
+            let image = tokio::fs::read(&resolved).await?;
+            let mime_type = image_type(&image).unwrap_or("application/octet-stream").to_string();
+            (image, mime_type, resolved.display().to_string())
+        } else if let Some(encoded) = string("image_base64") {
+            let image = BASE64.decode(encoded.trim()).map_err(|e| error(format!("Invalid image_base64: {}", e)))?;
+            let mime_type = image_type(&image).unwrap_or("application/octet-stream").to_string();
+            (image, mime_type, "base64".to_string())
+        } else {
+            return Err(error("camera, image_path or image_base64 is required"));
+        };
+
+        if image.len() > MAX_IMAGE_BYTES {
+            return Err(error(format!("Image is {} bytes, more than the {} allowed", image.len(), MAX_IMAGE_BYTES)));
+        }
+        if !mime_type.starts_with("image/") {
+            return Err(error(format!("{} is not a PNG, JPEG, GIF or WebP image", source)));
+        }
+        Ok((image, mime_type, source))
+    }
+
+    /// Asks the model about the image; with `format: json` the answer is JSON
+    async fn ask(&self, image: &[u8], prompt: &str, json_format: bool) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let url = format!("{}/api/chat", self.config.base_url.trim_end_matches('/'));
+        let mut body = json!({
+            "model": self.config.model,
+            "stream": false,
+            "messages": [{ "role": "user", "content": prompt, "images": [BASE64.encode(image)] }],
+        });
+        if json_format {
+            body["format"] = json!("json");
+        }
+        debug!("Sending {} byte image to {} ({})", image.len(), self.config.model, url);
+        // Serialized once, since the base64 image makes the body large
+        let body = serde_json::to_vec(&body)?;
+        crate::mcp::budget::count_upstream_bytes(body.len());
+        let response = self.client.post(&url).header(reqwest::header::CONTENT_TYPE, "application/json").body(body).send().await
+            .map_err(|e| error(format!("Ollama unreachable at {}: {}", self.config.base_url, e)))?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        crate::mcp::budget::count_upstream_bytes(text.len());
+        if !status.is_success() {
+            return Err(error(format!("Ollama returned {}: {}", status, text)));
+        }
+        let answer: Value = serde_json::from_str(&text)
+            .map_err(|e| error(format!("Failed to parse Ollama response: {}", e)))?;
+        answer["message"]["content"].as_str()
+            .map(|content| content.trim().to_string())
+            .ok_or_else(|| error("Ollama response has no message content"))
+    }
+
+    /// Picks one of `labels` for the image. The model answers JSON; a label
+    /// it makes up is reported as `null` rather than passed on.
+    async fn classify(&self, image: &[u8], labels: &[String], prompt: Option<&str>) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let prompt = format!(
+            "{}Classify this image as exactly one of: {}. Answer with JSON like {{\"label\": \"...\", \"confidence\": 0.0 to 1.0, \"reason\": \"...\"}}.",
+            prompt.map(|p| format!("{}\n", p)).unwrap_or_default(),
+            labels.join(", "),
+        );
+        let answer = self.ask(image, &prompt, true).await?;
+        let parsed: Value = serde_json::from_str(&answer).unwrap_or_else(|_| json!({ "reason": answer }));
+        let label = parsed["label"].as_str()
+            .and_then(|label| labels.iter().find(|known| known.eq_ignore_ascii_case(label.trim())));
+        Ok(json!({
+            "label": label,
+            "confidence": parsed["confidence"].as_f64(),
+            "reason": parsed["reason"],
+        }))
+    }
+}
+
+/// The MIME type of an image, from its first bytes
+fn image_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl Plugin for VisionPlugin {
+    fn name(&self) -> &str {
+        "vision"
+    }
+
+    fn version(&self) -> &str {
+        "0.1.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        let string = |name: &str, description: &str| ParameterDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameter_type: ParameterType::String,
+            required: false,
+        };
+        vec![Capability {
+            name: "analyze".to_string(),
+            description: "Describe an image, answer a question about it, or classify it into one of given labels".to_string(),
+            parameters: vec![
+                string("camera", "Home Assistant camera entity to take a snapshot of, e.g. camera.front_door"),
+                string("image_path", "Image file within the filesystem roots"),
+                string("image_base64", "The image itself, base64 encoded"),
+                string("prompt", "What to ask about the image (default: describe it)"),
+                ParameterDefinition {
+                    name: "labels".to_string(),
+                    description: "Classify the image as one of these instead of describing it".to_string(),
+                    parameter_type: ParameterType::Array,
+                    required: false,
+                },
+                ParameterDefinition {
+                    name: "include_image".to_string(),
+                    description: "Return the analyzed image as well (default: false)".to_string(),
+                    parameter_type: ParameterType::Boolean,
+                    required: false,
+                },
+            ],
+            version: None,
+            deprecated: None,
+        }]
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        info!("Executing vision plugin capability: {}", capability);
+        if capability != "analyze" {
+            return Err(error(format!("Unknown capability: {}", capability)));
+        }
+
+        let allowed = roots::effective(&self.roots, context.roots.as_deref());
+        let (image, mime_type, source) = self.load_image(&params, &allowed).await?;
+        let prompt = params.get("prompt").and_then(|v| v.as_str()).filter(|p| !p.trim().is_empty());
+        let labels: Option<Vec<String>> = params.get("labels")
+            .map(|labels| serde_json::from_value(labels.clone()).map_err(|e| error(format!("Invalid labels: {}", e))))
+            .transpose()?
+            .filter(|labels: &Vec<String>| !labels.is_empty());
+
+        let mut data = match &labels {
+            Some(labels) => self.classify(&image, labels, prompt).await?,
+            None => json!({ "description": self.ask(&image, prompt.unwrap_or(DEFAULT_PROMPT), false).await? }),
+        };
+        data["model"] = json!(self.config.model);
+        data["source"] = json!(source);
+        data["mime_type"] = json!(mime_type);
+        if params.get("include_image").and_then(|v| v.as_bool()).unwrap_or(false) {
+            data["data_base64"] = json!(BASE64.encode(&image));
+        }
+
+        Ok(PluginResult {
+            success: true,
+            data,
+            metrics: None,
+            context_updates: None,
+        })
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        let details = json!({ "base_url": self.config.base_url, "model": self.config.model });
+        let url = format!("{}/api/tags", self.config.base_url.trim_end_matches('/'));
+        let tags = match self.client.get(&url).timeout(Duration::from_secs(5)).send().await {
+            Ok(response) if response.status().is_success() => response.json::<Value>().await.unwrap_or_default(),
+            Ok(response) => return HealthStatus::unhealthy(format!("Ollama returned {}", response.status())).with_details(details),
+            Err(e) => return HealthStatus::unhealthy(format!("Ollama unreachable: {}", e)).with_details(details),
+        };
+        // Ollama names untagged models name:latest
+        let pulled = tags["models"].as_array().into_iter().flatten().filter_map(|model| model["name"].as_str())
+            .any(|name| name == self.config.model || name.strip_suffix(":latest") == Some(self.config.model.as_str()));
+        if !pulled {
+            return HealthStatus::unhealthy(format!("Ollama does not have {}; pull it with `ollama pull`", self.config.model))
+                .with_details(details);
+        }
+        HealthStatus::healthy().with_details(details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::home_assistant::HomeAssistantConfig;
+    use crate::secrets::Secret;
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const PNG: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0];
+
+    fn plugin(ollama: &MockServer, home_assistant: &MockServer, roots: Vec<PathBuf>) -> VisionPlugin {
+        let home_assistant = HomeAssistantPlugin::with_config(HomeAssistantConfig {
+            base_url: home_assistant.uri(),
+            token: Some(Secret::from("test-token")),
+            timeout: Duration::from_secs(2),
+            connect_timeout: Duration::from_secs(1),
+            max_retries: 0,
+            retry_backoff: Duration::from_millis(1),
+        });
+        let config = VisionConfig { base_url: ollama.uri(), model: "llava".to_string(), timeout: Duration::from_secs(5) };
+        VisionPlugin::with_config(config, Arc::new(home_assistant), roots)
+    }
+
+    fn answer(content: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({"message": {"role": "assistant", "content": content}, "done": true}))
+    }
+
+    #[tokio::test]
+    async fn test_describes_a_camera_snapshot() {
+        let (ollama, home_assistant) = (MockServer::start().await, MockServer::start().await);
+        Mock::given(method("GET"))
+            .and(path("/api/camera_proxy/camera.front_door"))
+            .and(header("Authorization", "Bearer test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(PNG.to_vec(), "image/png"))
+            .mount(&home_assistant)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(json!({"model": "llava", "messages": [{"content": DEFAULT_PROMPT, "images": [BASE64.encode(PNG)]}]})))
+            .respond_with(answer(" A parcel lies on the doormat. "))
+            .expect(1)
+            .mount(&ollama)
+            .await;
+
+        let params = HashMap::from([("camera".to_string(), json!("camera.front_door")), ("include_image".to_string(), json!(true))]);
+        let result = plugin(&ollama, &home_assistant, Vec::new()).execute("analyze", Context::for_test(), params).await.unwrap();
+        assert_eq!(result.data["description"], "A parcel lies on the doormat.");
+        assert_eq!(result.data["source"], "camera.front_door");
+        assert_eq!(result.data["mime_type"], "image/png");
+        assert_eq!(result.data["data_base64"], BASE64.encode(PNG));
+    }
+
+    #[tokio::test]
+    async fn test_classifies_into_the_given_labels_only() {
+        let (ollama, home_assistant) = (MockServer::start().await, MockServer::start().await);
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(json!({"format": "json"})))
+            .respond_with(answer(r#"{"label": "Person", "confidence": 0.9, "reason": "someone at the door"}"#))
+            .up_to_n_times(1)
+            .mount(&ollama)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(answer(r#"{"label": "dragon", "confidence": 0.2}"#))
+            .mount(&ollama)
+            .await;
+        let plugin = plugin(&ollama, &home_assistant, Vec::new());
+        let params = HashMap::from([
+            ("image_base64".to_string(), json!(BASE64.encode(PNG))),
+            ("labels".to_string(), json!(["person", "vehicle", "animal"])),
+        ]);
+
+        let result = plugin.execute("analyze", Context::for_test(), params.clone()).await.unwrap();
+        assert_eq!(result.data["label"], "person");
+        assert_eq!(result.data["confidence"], 0.9);
+        assert!(result.data.get("data_base64").is_none());
+
+        let made_up = plugin.execute("analyze", Context::for_test(), params).await.unwrap();
+        assert_eq!(made_up.data["label"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_image_paths_must_be_images_within_the_roots() {
+        let (ollama, home_assistant) = (MockServer::start().await, MockServer::start().await);
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path().canonicalize().unwrap();
+        std::fs::write(root_path.join("notes.txt"), "not an image").unwrap();
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(outside.path(), PNG).unwrap();
+        let plugin = plugin(&ollama, &home_assistant, vec![root_path.clone()]);
+
+        let analyze = |path: &std::path::Path| plugin.execute("analyze", Context::for_test(), HashMap::from([("image_path".to_string(), json!(path))]));
+        assert!(analyze(outside.path()).await.unwrap_err().to_string().contains("outside the allowed roots"));
+        assert!(analyze(&root_path.join("notes.txt")).await.unwrap_err().to_string().contains("is not a PNG"));
+        assert!(plugin.execute("analyze", Context::for_test(), HashMap::new()).await.unwrap_err().to_string().contains("is required"));
+    }
+}
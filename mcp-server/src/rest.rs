@@ -0,0 +1,86 @@
+//! The plain HTTP endpoints the bridge calls: `GET /tools/list` answers
+//! with the `tools/list` result and `POST /tools/call` takes any JSON-RPC
+//! request and answers with its response. The bridge sends its request's
+//! `X-Request-Id` as the JSON-RPC id, and as the header for `GET /tools/list`,
+//! which has no body, so the lines logged for it carry that id.
+//! `GET /version` answers with the server's version as plain text, which the
+//! bridge reports in its health.
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::mcp::McpServer;
+
+pub fn router(server: Arc<McpServer>) -> Router {
+    Router::new()
+        .route("/tools/list", get(list_tools))
+        .route("/tools/call", post(tool_call))
+        .route("/version", get(|| async { env!("CARGO_PKG_VERSION") }))
+        .with_state(server)
+}
+
+async fn list_tools(
+    State(server): State<Arc<McpServer>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let id = match headers.get("x-request-id").and_then(|value| value.to_str().ok()) {
+        Some(request_id) => serde_json::json!(request_id),
+        None => serde_json::json!(1),
+    };
+    // Create a tools/list JSON-RPC request
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "tools/list"
+    });
+
+    match server.handle_message(&request.to_string()).await {
+        Ok(response) => {
+            match serde_json::from_str::<serde_json::Value>(&response) {
+                Ok(json) => {
+                    if let Some(result) = json.as_object().and_then(|obj| obj.get("result")) {
+                        // Return the tools array directly without nesting
+                        Json(result.clone()).into_response()
+                    } else {
+                        Json(json).into_response()
+                    }
+                },
+                Err(e) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to parse response: {}", e),
+                ).into_response(),
+            }
+        },
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get tools: {}", e),
+        ).into_response(),
+    }
+}
+
+async fn tool_call(
+    State(server): State<Arc<McpServer>>,
+    Json(request): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    match server.handle_message(&serde_json::to_string(&request).unwrap()).await {
+        Ok(response) => {
+            match serde_json::from_str::<serde_json::Value>(&response) {
+                Ok(json) => Json(json).into_response(),
+                Err(e) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to parse response: {}", e),
+                ).into_response(),
+            }
+        },
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to handle tool call: {}", e),
+        ).into_response(),
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use crate::context::ContextStore;
+use crate::plugins::notification::NotificationPlugin;
+use crate::plugins::system_info::{persist_metrics, SystemInfoPlugin};
+
+/// Settings for the background metrics sampler, read from the environment
+#[derive(Debug, Clone)]
+pub struct SamplerConfig {
+    pub interval: std::time::Duration,
+    pub cpu_threshold_percent: f64,
+    pub cpu_sustain: Duration,
+    pub disk_threshold_percent: f64,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        Self {
+            interval: std::time::Duration::from_secs(60),
+            cpu_threshold_percent: 90.0,
+            cpu_sustain: Duration::minutes(5),
+            disk_threshold_percent: 95.0,
+        }
+    }
+}
+
+impl SamplerConfig {
+    /// Returns `None` unless `METRICS_SAMPLER_INTERVAL_SECS` is set to a non-zero value
+    pub fn from_env() -> Option<Self> {
+        let interval_secs = std::env::var("METRICS_SAMPLER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)?;
+
+        let float = |name: &str, default: f64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(default)
+        };
+        let defaults = Self::default();
+
+        Some(Self {
+            interval: std::time::Duration::from_secs(interval_secs),
+            cpu_threshold_percent: float("METRICS_ALERT_CPU_PERCENT", defaults.cpu_threshold_percent),
+            cpu_sustain: Duration::seconds(float("METRICS_ALERT_CPU_SUSTAIN_SECS", 300.0) as i64),
+            disk_threshold_percent: float("METRICS_ALERT_DISK_PERCENT", defaults.disk_threshold_percent),
+        })
+    }
+}
+
+/// An alert raised when a threshold is crossed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub title: String,
+    pub message: String,
+    pub severity: &'static str,
+    pub data: Value,
+}
+
+/// Tracks threshold state between samples so each crossing alerts once
+#[derive(Debug, Default)]
+pub struct AlertEvaluator {
+    cpu_high_since: Option<DateTime<Utc>>,
+    cpu_alerting: bool,
+    disks_alerting: HashSet<String>,
+}
+
+impl AlertEvaluator {
+    pub fn evaluate(
+        &mut self,
+        config: &SamplerConfig,
+        cpu_usage: f64,
+        disks: &[(String, f64)],
+        now: DateTime<Utc>,
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+
+        if cpu_usage > config.cpu_threshold_percent {
+            let since = *self.cpu_high_since.get_or_insert(now);
+            if !self.cpu_alerting && now - since >= config.cpu_sustain {
+                self.cpu_alerting = true;
+                alerts.push(Alert {
+                    title: "High CPU usage".to_string(),
+                    message: format!(
+                        "CPU usage has been above {:.0}% for {} seconds (currently {:.1}%)",
+                        config.cpu_threshold_percent,
+                        (now - since).num_seconds(),
+                        cpu_usage
+                    ),
+                    severity: "warning",
+                    data: json!({ "cpu_usage": cpu_usage, "since": since.to_rfc3339() }),
+                });
+            }
+        } else {
+            self.cpu_high_since = None;
+            self.cpu_alerting = false;
+        }
+
+        for (mount_point, usage) in disks {
+            if *usage > config.disk_threshold_percent {
+                if self.disks_alerting.insert(mount_point.clone()) {
+                    alerts.push(Alert {
+                        title: "Disk almost full".to_string(),
+                        message: format!("{} is {:.1}% full", mount_point, usage),
+                        severity: "critical",
+                        data: json!({ "mount_point": mount_point, "usage_percent": usage }),
+                    });
+                }
+            } else {
+                self.disks_alerting.remove(mount_point);
+            }
+        }
+
+        alerts
+    }
+}
+
+/// Periodically collects system metrics, stores them and raises threshold alerts
+pub struct MetricsSampler {
+    config: SamplerConfig,
+    system_info: Arc<SystemInfoPlugin>,
+    store: Option<Arc<dyn ContextStore>>,
+    notifier: Arc<NotificationPlugin>,
+    alerts: AlertEvaluator,
+}
+
+impl MetricsSampler {
+    pub fn new(
+        config: SamplerConfig,
+        system_info: Arc<SystemInfoPlugin>,
+        store: Option<Arc<dyn ContextStore>>,
+        notifier: Arc<NotificationPlugin>,
+    ) -> Self {
+        Self {
+            config,
+            system_info,
+            store,
+            notifier,
+            alerts: AlertEvaluator::default(),
+        }
+    }
+
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        info!("Starting metrics sampler with interval {:?}", self.config.interval);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.config.interval);
+            loop {
+                ticker.tick().await;
+                self.sample_once().await;
+            }
+        })
+    }
+
+    async fn sample_once(&mut self) {
+        let info = self.system_info.get_system_info().await;
+        let disks = self.system_info.get_disks().await;
+        debug!("Sampled system metrics: {:?}", info);
+
+        if let Some(store) = &self.store {
+            if let Err(e) = persist_metrics(store.as_ref(), &info).await {
+                error!("Failed to store sampled metrics: {}", e);
+            }
+        }
+
+        let cpu_usage = info.get("cpu_usage").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        let disk_usage: Vec<(String, f64)> = disks["disks"]
+            .as_array()
+            .map(|disks| {
+                disks.iter()
+                    .filter_map(|disk| Some((
+                        disk["mount_point"].as_str()?.to_string(),
+                        disk["usage_percent"].as_f64()?,
+                    )))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for alert in self.alerts.evaluate(&self.config, cpu_usage, &disk_usage, Utc::now()) {
+            if let Err(e) = self.notifier
+                .notify(&alert.title, &alert.message, alert.severity, Some(alert.data))
+                .await
+            {
+                warn!("Failed to deliver alert '{}': {}", alert.title, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_alert_requires_sustained_usage() {
+        let config = SamplerConfig::default();
+        let mut evaluator = AlertEvaluator::default();
+        let start = Utc::now();
+
+        assert!(evaluator.evaluate(&config, 95.0, &[], start).is_empty());
+        assert!(evaluator.evaluate(&config, 95.0, &[], start + Duration::minutes(4)).is_empty());
+
+        let alerts = evaluator.evaluate(&config, 95.0, &[], start + Duration::minutes(5));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].title, "High CPU usage");
+
+        // Already alerting, no repeat until usage recovers
+        assert!(evaluator.evaluate(&config, 97.0, &[], start + Duration::minutes(6)).is_empty());
+    }
+
+    #[test]
+    fn test_cpu_dip_resets_window() {
+        let config = SamplerConfig::default();
+        let mut evaluator = AlertEvaluator::default();
+        let start = Utc::now();
+
+        evaluator.evaluate(&config, 95.0, &[], start);
+        evaluator.evaluate(&config, 50.0, &[], start + Duration::minutes(3));
+        assert!(evaluator.evaluate(&config, 95.0, &[], start + Duration::minutes(6)).is_empty());
+    }
+
+    #[test]
+    fn test_disk_alert_fires_once_per_crossing() {
+        let config = SamplerConfig::default();
+        let mut evaluator = AlertEvaluator::default();
+        let now = Utc::now();
+        let full = vec![("/".to_string(), 96.5), ("/data".to_string(), 40.0)];
+
+        let alerts = evaluator.evaluate(&config, 10.0, &full, now);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].data["mount_point"], "/");
+        assert!(evaluator.evaluate(&config, 10.0, &full, now).is_empty());
+
+        evaluator.evaluate(&config, 10.0, &[("/".to_string(), 80.0)], now);
+        assert_eq!(evaluator.evaluate(&config, 10.0, &full, now).len(), 1);
+    }
+}
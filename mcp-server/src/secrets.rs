@@ -0,0 +1,475 @@
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Configuration values starting with this name a secret instead of
+/// holding it, e.g. `secret://ha/token`
+pub const SECRET_SCHEME: &str = "secret://";
+
+/// How long a fetched secret is used before it is fetched again
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Where secrets are kept
+#[async_trait]
+pub trait SecretProvider: Send + Sync {
+    /// Name for logs, e.g. `vault`
+    fn name(&self) -> &'static str;
+
+    /// The current value of the secret at `path`, e.g. `ha/token`
+    async fn fetch(&self, path: &str) -> Result<String>;
+}
+
+/// Splits `ha/token` into the secret `ha` and the key `token` in it
+fn split_key(path: &str) -> Result<(&str, &str)> {
+    match path.rsplit_once('/') {
+        Some((secret, key)) if !secret.is_empty() && !key.is_empty() => Ok((secret, key)),
+        _ => bail!("Secret path {} needs the form <secret>/<key>", path),
+    }
+}
+
+/// Secrets in environment variables: `ha/token` is `SECRET_HA_TOKEN`
+pub struct EnvSecrets;
+
+impl EnvSecrets {
+    fn variable(path: &str) -> String {
+        let name: String = path.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+        format!("SECRET_{}", name)
+    }
+}
+
+#[async_trait]
+impl SecretProvider for EnvSecrets {
+    fn name(&self) -> &'static str {
+        "env"
+    }
+
+    async fn fetch(&self, path: &str) -> Result<String> {
+        let variable = Self::variable(path);
+        std::env::var(&variable).with_context(|| format!("Secret {} is not set; expected it in {}", path, variable))
+    }
+}
+
+/// Secrets in files under a directory, such as mounted Docker or Kubernetes
+/// secrets: `ha/token` is the file `<dir>/ha/token`. Files are read on every
+/// fetch, so replacing one rotates the secret.
+pub struct FileSecrets {
+    dir: PathBuf,
+}
+
+impl FileSecrets {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl SecretProvider for FileSecrets {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn fetch(&self, path: &str) -> Result<String> {
+        if !Path::new(path).components().all(|component| matches!(component, Component::Normal(_))) {
+            bail!("Secret path {} must stay inside the secrets directory", path);
+        }
+        let file = self.dir.join(path);
+        let value = tokio::fs::read_to_string(&file)
+            .await
+            .with_context(|| format!("Failed to read secret {} from {}", path, file.display()))?;
+        Ok(value.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Secrets in a HashiCorp Vault KV version 2 engine: `ha/token` is the key
+/// `token` of the secret `ha`
+pub struct VaultSecrets {
+    client: reqwest::Client,
+    addr: String,
+    token: String,
+    mount: String,
+}
+
+impl VaultSecrets {
+    pub fn new(addr: &str, token: &str, mount: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            addr: addr.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            mount: mount.trim_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl SecretProvider for VaultSecrets {
+    fn name(&self) -> &'static str {
+        "vault"
+    }
+
+    async fn fetch(&self, path: &str) -> Result<String> {
+        let (secret, key) = split_key(path)?;
+        let url = format!("{}/v1/{}/data/{}", self.addr, self.mount, secret);
+        let response = self.client.get(&url).header("X-Vault-Token", &self.token).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("Vault returned {} for secret {}: {}", status, secret, response.text().await.unwrap_or_default());
+        }
+        let body: Value = response.json().await?;
+        string_at(&body["data"]["data"], key).with_context(|| format!("Vault secret {} has no string {}", secret, key))
+    }
+}
+
+/// Secrets in AWS Secrets Manager: `ha/token` is the key `token` of the
+/// JSON secret `ha`. Requests are signed with Signature Version 4.
+pub struct AwsSecrets {
+    client: reqwest::Client,
+    endpoint: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl AwsSecrets {
+    /// Region and credentials from the standard `AWS_*` variables;
+    /// `AWS_ENDPOINT_URL_SECRETS_MANAGER` points elsewhere, e.g. LocalStack
+    pub fn from_env() -> Result<Self> {
+        let region = std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .context("AWS_REGION must be set for AWS Secrets Manager")?;
+        let endpoint = std::env::var("AWS_ENDPOINT_URL_SECRETS_MANAGER")
+            .unwrap_or_else(|_| format!("https://secretsmanager.{}.amazonaws.com", region));
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            region,
+            access_key_id: std::env::var("AWS_ACCESS_KEY_ID").context("AWS_ACCESS_KEY_ID must be set for AWS Secrets Manager")?,
+            secret_access_key: std::env::var("AWS_SECRET_ACCESS_KEY")
+                .context("AWS_SECRET_ACCESS_KEY must be set for AWS Secrets Manager")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+}
+
+#[async_trait]
+impl SecretProvider for AwsSecrets {
+    fn name(&self) -> &'static str {
+        "aws"
+    }
+
+    async fn fetch(&self, path: &str) -> Result<String> {
+        let (secret, key) = split_key(path)?;
+        let url = reqwest::Url::parse(&format!("{}/", self.endpoint))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => bail!("AWS endpoint {} has no host", self.endpoint),
+        };
+        let payload = json!({ "SecretId": secret }).to_string();
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", host),
+            ("x-amz-date", amz_date.clone()),
+            ("x-amz-target", "secretsmanager.GetSecretValue".to_string()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let signing = SigningKey {
+            access_key_id: &self.access_key_id,
+            secret_access_key: &self.secret_access_key,
+            region: &self.region,
+            service: "secretsmanager",
+        };
+        let authorization = signing.authorization("POST", "/", &headers, &payload, &amz_date);
+
+        let mut request = self.client.post(url).header("Authorization", authorization).body(payload);
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.header(*name, value);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("AWS Secrets Manager returned {} for secret {}: {}", status, secret, response.text().await.unwrap_or_default());
+        }
+        let body: Value = response.json().await?;
+        let secret_string = body["SecretString"]
+            .as_str()
+            .with_context(|| format!("AWS secret {} has no SecretString", secret))?;
+        let fields: Value = serde_json::from_str(secret_string)
+            .with_context(|| format!("AWS secret {} is not a JSON object of keys", secret))?;
+        string_at(&fields, key).with_context(|| format!("AWS secret {} has no string {}", secret, key))
+    }
+}
+
+fn string_at(object: &Value, key: &str) -> Option<String> {
+    object.get(key)?.as_str().map(str::to_string)
+}
+
+/// Credentials and scope of an AWS Signature Version 4
+struct SigningKey<'a> {
+    access_key_id: &'a str,
+    secret_access_key: &'a str,
+    region: &'a str,
+    service: &'a str,
+}
+
+impl SigningKey<'_> {
+    /// The `Authorization` header for a request without a query string;
+    /// `headers` are the signed headers, with lowercase names
+    fn authorization(&self, method: &str, path: &str, headers: &[(&str, String)], payload: &str, amz_date: &str) -> String {
+        let mut headers = headers.to_vec();
+        headers.sort_by(|a, b| a.0.cmp(b.0));
+        let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+        let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            method,
+            path,
+            canonical_headers,
+            signed_headers,
+            hex(&Sha256::digest(payload.as_bytes()))
+        );
+
+        let date = &amz_date[..8];
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = [date, self.region, self.service, "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", self.secret_access_key).into_bytes(), |key, part| hmac(&key, part.as_bytes()));
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id,
+            scope,
+            signed_headers,
+            hex(&hmac(&key, string_to_sign.as_bytes()))
+        )
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// A credential from configuration: the value itself, or a `secret://`
+/// reference looked up through the secrets provider whenever it is used
+#[derive(Clone, PartialEq, Eq)]
+pub enum Secret {
+    Value(String),
+    Reference(String),
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        match value.strip_prefix(SECRET_SCHEME) {
+            Some(path) => Secret::Reference(path.to_string()),
+            None => Secret::Value(value.to_string()),
+        }
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret::from(value.as_str())
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Secret::Value(_) => write!(f, "Secret(***)"),
+            Secret::Reference(path) => write!(f, "Secret({}{})", SECRET_SCHEME, path),
+        }
+    }
+}
+
+/// Looks up secret references through one provider, reusing each value for
+/// a while so that every request does not go to the provider
+pub struct Secrets {
+    provider: Box<dyn SecretProvider>,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl Secrets {
+    pub fn new(provider: Box<dyn SecretProvider>, ttl: Duration) -> Self {
+        Self { provider, ttl, cache: Mutex::new(HashMap::new()) }
+    }
+
+    /// The provider `SECRETS_PROVIDER` names (`env`, `file`, `vault` or
+    /// `aws`; default `env`), with values reused for `SECRETS_CACHE_SECS`.
+    /// A provider that is not fully configured is reported and replaced by
+    /// `env`.
+    pub fn from_env() -> Self {
+        let ttl = std::env::var("SECRETS_CACHE_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map_or(DEFAULT_CACHE_TTL, Duration::from_secs);
+        let kind = std::env::var("SECRETS_PROVIDER").unwrap_or_else(|_| "env".to_string());
+        let provider: Result<Box<dyn SecretProvider>> = match kind.to_lowercase().as_str() {
+            "env" => Ok(Box::new(EnvSecrets)),
+            "file" => Ok(Box::new(FileSecrets::new(
+                std::env::var("SECRETS_DIR").unwrap_or_else(|_| "/run/secrets".to_string()),
+            ))),
+            "vault" => std::env::var("VAULT_ADDR")
+                .context("VAULT_ADDR must be set")
+                .and_then(|addr| Ok((addr, std::env::var("VAULT_TOKEN").context("VAULT_TOKEN must be set")?)))
+                .map(|(addr, token)| {
+                    let mount = std::env::var("VAULT_MOUNT").unwrap_or_else(|_| "secret".to_string());
+                    Box::new(VaultSecrets::new(&addr, &token, &mount)) as Box<dyn SecretProvider>
+                }),
+            "aws" => AwsSecrets::from_env().map(|aws| Box::new(aws) as Box<dyn SecretProvider>),
+            other => Err(anyhow!("unknown provider {}", other)),
+        };
+        let provider = provider.unwrap_or_else(|e| {
+            warn!("Ignoring SECRETS_PROVIDER={}, reading secrets from the environment: {:#}", kind, e);
+            Box::new(EnvSecrets)
+        });
+        Self::new(provider, ttl)
+    }
+
+    /// The secrets of this process, set up from the environment on first use
+    pub fn global() -> Arc<Secrets> {
+        static SECRETS: OnceLock<Arc<Secrets>> = OnceLock::new();
+        SECRETS.get_or_init(|| Arc::new(Secrets::from_env())).clone()
+    }
+
+    /// The value of `secret`, fetched from the provider unless it was
+    /// fetched less than the cache time ago
+    pub async fn resolve(&self, secret: &Secret) -> Result<String> {
+        let path = match secret {
+            Secret::Value(value) => return Ok(value.clone()),
+            Secret::Reference(path) => path,
+        };
+        if let Some((value, fetched_at)) = self.cache.lock().unwrap().get(path) {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(value.clone());
+            }
+        }
+        debug!("Fetching secret {} from the {} provider", path, self.provider.name());
+        let value = self.provider.fetch(path).await?;
+        self.cache.lock().unwrap().insert(path.clone(), (value.clone(), Instant::now()));
+        Ok(value)
+    }
+
+    /// Forgets the cached value of `secret`, e.g. after the service it is
+    /// for rejected it, so the next use fetches the rotated one
+    pub fn invalidate(&self, secret: &Secret) {
+        if let Secret::Reference(path) = secret {
+            self.cache.lock().unwrap().remove(path);
+        }
+    }
+
+    /// Forgets every cached value, so each secret is fetched again
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+}
+
+impl fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Secrets").field("provider", &self.provider.name()).field("ttl", &self.ttl).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_references_are_cached_until_invalidated() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("ha")).unwrap();
+        std::fs::write(dir.path().join("ha/token"), "first\n").unwrap();
+        let secrets = Secrets::new(Box::new(FileSecrets::new(dir.path())), Duration::from_secs(60));
+        let token = Secret::from("secret://ha/token");
+
+        assert_eq!(secrets.resolve(&token).await.unwrap(), "first");
+        std::fs::write(dir.path().join("ha/token"), "second\n").unwrap();
+        assert_eq!(secrets.resolve(&token).await.unwrap(), "first");
+        secrets.invalidate(&token);
+        assert_eq!(secrets.resolve(&token).await.unwrap(), "second");
+
+        assert_eq!(secrets.resolve(&Secret::from("plain")).await.unwrap(), "plain");
+        assert!(secrets.resolve(&Secret::from("secret://../etc/passwd")).await.is_err());
+        assert_eq!(format!("{:?}", Secret::from("plain")), "Secret(***)");
+    }
+
+    #[tokio::test]
+    async fn test_vault_reads_kv_v2_secrets() {
+        let vault = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/ha"))
+            .and(header("X-Vault-Token", "root"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": {"data": {"token": "abc"}}})))
+            .mount(&vault)
+            .await;
+
+        let provider = VaultSecrets::new(&vault.uri(), "root", "secret");
+        assert_eq!(provider.fetch("ha/token").await.unwrap(), "abc");
+        assert!(provider.fetch("ha/missing").await.is_err());
+        assert!(provider.fetch("ha").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_aws_requests_are_signed_and_keys_read_from_the_secret_string() {
+        let aws = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("X-Amz-Target", "secretsmanager.GetSecretValue"))
+            .and(header_exists("Authorization"))
+            .and(body_json(json!({"SecretId": "ha"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"SecretString": "{\"token\":\"abc\"}"})))
+            .mount(&aws)
+            .await;
+
+        let provider = AwsSecrets {
+            client: reqwest::Client::new(),
+            endpoint: aws.uri(),
+            region: "us-east-1".to_string(),
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: None,
+        };
+        assert_eq!(provider.fetch("ha/token").await.unwrap(), "abc");
+    }
+
+    #[test]
+    fn test_signature_matches_the_aws_test_suite() {
+        // get-vanilla from the AWS Signature Version 4 test suite
+        let signing = SigningKey {
+            access_key_id: "AKIDEXAMPLE",
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            region: "us-east-1",
+            service: "service",
+        };
+        let headers = [("host", "example.amazonaws.com".to_string()), ("x-amz-date", "20150830T123600Z".to_string())];
+        assert_eq!(
+            signing.authorization("GET", "/", &headers, "", "20150830T123600Z"),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+}
@@ -1,48 +1,257 @@
 use async_trait::async_trait;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 use anyhow::Result;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-use crate::mcp::{ContentBlock, ToolDefinition};
+use crate::mcp::{ContentBlock, Deprecation, ToolAnnotations, ToolDefinition, ToolTag};
 
 mod plugin_tools;
-pub use plugin_tools::{SystemInfoTool, HomeAssistantTool, HttpTool, Neo4jTool};
+mod virtual_tools;
+pub mod scheduler;
+pub mod schema;
+pub use plugin_tools::{SystemInfoTool, HomeAssistantTool, HttpTool, Neo4jTool, EmbeddingsTool, MemoryTool, KnowledgeGraphTool, MetricsTool, OllamaTool, SpeechTool, AnalyzeImageTool, NetworkTool, NetDiagTool, JiraTool, SummarizeTool, FileSystemTool, ProxyTool};
+pub use virtual_tools::VirtualTool;
+pub use scheduler::{ConcurrencyConfig, ToolScheduler};
 
 #[async_trait]
 pub trait Tool: Send + Sync {
     fn name(&self) -> &str;
     fn description(&self) -> &str;
     fn input_schema(&self) -> Value;
+
+    /// JSON schema of the tool's structured result, for tools that always
+    /// return a JSON object of the same shape
+    fn output_schema(&self) -> Option<Value> {
+        None
+    }
+
+    /// Set when a tool replaces an older one of the same name, which stays
+    /// registered; the tool is then registered as `name@version`
+    fn version(&self) -> Option<&str> {
+        None
+    }
+
+    /// Set on tools kept only for clients that have not moved on yet
+    fn deprecation(&self) -> Option<Deprecation> {
+        None
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        Vec::new()
+    }
+
+    /// MCP annotations; by default the hints the tags imply
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        ToolAnnotations::from_tags(&self.tags())
+    }
+
     async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>>;
 }
 
+/// Another name a tool can be called by
+#[derive(Debug, Clone, PartialEq)]
+struct Alias {
+    target: String,
+    /// Calls by this name still work but log a warning naming the target
+    deprecated: bool,
+}
+
+/// Tools by name. Tools can be registered under a namespace (`ha.call_service`,
+/// `fs.read_file`) so plugins with similar tools don't collide, and can be
+/// called by aliases, e.g. the names they had before they were moved.
+/// Virtual tools call another tool with preset arguments. Its scheduler
+/// limits how many calls of a tool run at once.
 pub struct ToolRegistry {
     tools: HashMap<String, Box<dyn Tool>>,
+    aliases: HashMap<String, Alias>,
+    virtual_tools: HashMap<String, VirtualTool>,
+    scheduler: Arc<ToolScheduler>,
 }
 
 impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             tools: HashMap::new(),
+            aliases: HashMap::new(),
+            virtual_tools: HashMap::new(),
+            scheduler: Arc::new(ToolScheduler::new(ConcurrencyConfig::default())),
+        }
+    }
+
+    /// Aliases from `MCP_TOOL_ALIASES` and deprecated names from
+    /// `MCP_DEPRECATED_TOOL_NAMES`, both as `name=target` pairs separated by
+    /// commas, virtual tools from the file `MCP_VIRTUAL_TOOLS` names, and
+    /// concurrency limits from `MCP_TOOL_CONCURRENCY`
+    pub fn from_env() -> Self {
+        let mut registry = Self::new();
+        if let Some(config) = ConcurrencyConfig::from_env() {
+            registry.set_concurrency(config);
+        }
+        for tool in VirtualTool::from_env() {
+            registry.add_virtual(tool);
+        }
+        for (variable, deprecated) in [("MCP_TOOL_ALIASES", false), ("MCP_DEPRECATED_TOOL_NAMES", true)] {
+            let Ok(spec) = std::env::var(variable) else {
+                continue;
+            };
+            match parse_aliases(&spec) {
+                Ok(aliases) => {
+                    for (alias, target) in aliases {
+                        registry.add_alias(&alias, &target, deprecated);
+                    }
+                }
+                Err(e) => warn!("Ignoring {}: {}", variable, e),
+            }
         }
+        registry
+    }
+
+    /// Limits how many calls of each tool in `config` may run at once
+    pub fn set_concurrency(&mut self, config: ConcurrencyConfig) {
+        info!("Tool concurrency limits: {:?}", config);
+        self.scheduler = Arc::new(ToolScheduler::new(config));
     }
 
+    /// The scheduler calls wait on, so they can run without holding the
+    /// registry
+    pub fn scheduler(&self) -> Arc<ToolScheduler> {
+        self.scheduler.clone()
+    }
+
+    /// Registers `tool` under its own name, or `name@version` for a
+    /// versioned tool. A name that is already taken keeps its tool and the
+    /// new one is dropped with a warning.
     pub fn register(&mut self, tool: Box<dyn Tool>) {
-        let name = tool.name().to_string();
+        let name = versioned_name(tool.name(), tool.version());
+        self.insert(name, tool);
+    }
+
+    /// Registers `tool` as `namespace.name`
+    pub fn register_in(&mut self, namespace: &str, tool: Box<dyn Tool>) {
+        let name = versioned_name(&format!("{}.{}", namespace, tool.name()), tool.version());
+        self.insert(name, tool);
+    }
+
+    /// Registers a virtual tool under its own name, unless that is taken
+    pub fn add_virtual(&mut self, tool: VirtualTool) {
+        if self.is_taken(&tool.name) {
+            warn!("Tool name '{}' is already taken; not registering the virtual tool", tool.name);
+            return;
+        }
+        info!("Virtual tool '{}' calls '{}' with {:?}", tool.name, tool.tool, tool.arguments);
+        self.virtual_tools.insert(tool.name.clone(), tool);
+    }
+
+    fn insert(&mut self, name: String, tool: Box<dyn Tool>) {
+        if self.is_taken(&name) {
+            warn!("Tool name '{}' is already taken; not registering it again", name);
+            return;
+        }
         self.tools.insert(name, tool);
     }
 
+    fn is_taken(&self, name: &str) -> bool {
+        self.tools.contains_key(name) || self.aliases.contains_key(name) || self.virtual_tools.contains_key(name)
+    }
+
+    /// Lets `alias` be used wherever `target` is; with `deprecated`, each use
+    /// logs a warning
+    pub fn add_alias(&mut self, alias: &str, target: &str, deprecated: bool) {
+        self.aliases.insert(alias.to_string(), Alias { target: target.to_string(), deprecated });
+    }
+
+    /// The name `name` refers to: the target of an alias, the latest
+    /// version of a tool only registered with versions, or `name` itself.
+    /// Registered names take precedence over aliases.
+    pub fn resolve(&self, name: &str) -> String {
+        if self.tools.contains_key(name) {
+            return name.to_string();
+        }
+        match self.aliases.get(name) {
+            Some(alias) => {
+                if alias.deprecated {
+                    warn!("Tool name '{}' is deprecated; use '{}' instead", name, alias.target);
+                }
+                alias.target.clone()
+            }
+            None => self.latest_version(name).unwrap_or_else(|| name.to_string()),
+        }
+    }
+
+    /// The registered `name@version` with the highest version, if any
+    fn latest_version(&self, name: &str) -> Option<String> {
+        self.tools
+            .iter()
+            .filter_map(|(registered, tool)| {
+                let version = tool.version()?;
+                (*registered == versioned_name(name, Some(version))).then(|| (version_order(version), registered))
+            })
+            .max()
+            .map(|(_, registered)| registered.clone())
+    }
+
+    /// Whether a tool is registered under `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+    }
+
+    /// The output schema of the tool registered under `name`
+    pub fn output_schema(&self, name: &str) -> Option<Value> {
+        self.tools.get(name).and_then(|tool| tool.output_schema())
+    }
+
+    /// Why the tool registered under `name` is deprecated, if it is
+    pub fn deprecation(&self, name: &str) -> Option<Deprecation> {
+        self.tools.get(name).and_then(|tool| tool.deprecation())
+    }
+
+    /// The tool a call of `name` runs and the arguments to run it with:
+    /// virtual tools become the tool they wrap, with their preset arguments
+    pub fn expand(&self, name: &str, args: HashMap<String, Value>) -> Result<(String, HashMap<String, Value>)> {
+        let name = self.resolve(name);
+        match self.virtual_tools.get(&name) {
+            Some(tool) => Ok((self.resolve(&tool.tool), tool.expand(args)?)),
+            None => Ok((name, args)),
+        }
+    }
+
     pub async fn list_tools(&self) -> Vec<ToolDefinition> {
         debug!("Listing available tools: {:?}", self.tools.keys().collect::<Vec<_>>());
-        self.tools
-            .values()
-            .map(|tool| ToolDefinition {
-                name: tool.name().to_string(),
+        let mut tools: Vec<ToolDefinition> = self.tools
+            .iter()
+            .map(|(name, tool)| ToolDefinition {
+                name: name.clone(),
                 description: tool.description().to_string(),
                 input_schema: tool.input_schema(),
+                output_schema: tool.output_schema(),
+                version: tool.version().map(str::to_string),
+                deprecated: tool.deprecation(),
+                tags: tool.tags(),
+                annotations: tool.annotations(),
             })
-            .collect()
+            .collect();
+        tools.extend(self.virtual_tools.values().map(|tool| {
+            let target = self.tools.get(&self.resolve(&tool.tool));
+            let tags = match (tool.tags.is_empty(), target) {
+                (true, Some(target)) => target.tags(),
+                _ => tool.tags.clone(),
+            };
+            ToolDefinition {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                input_schema: tool.input_schema.clone(),
+                // The result is the target's
+                output_schema: target.and_then(|target| target.output_schema()),
+                version: None,
+                deprecated: None,
+                annotations: ToolAnnotations::from_tags(&tags),
+                tags,
+            }
+        }));
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
     }
 
     pub async fn call_tool(
@@ -51,6 +260,8 @@ impl ToolRegistry {
         args: HashMap<String, Value>,
     ) -> Result<Vec<ContentBlock>> {
         debug!("Attempting to call tool '{}' with args: {:?}", name, args);
+        let (name, args) = self.expand(name, args)?;
+        let name = &name;
         match self.tools.get(name) {
             Some(tool) => {
                 debug!("Found tool '{}', executing...", name);
@@ -67,4 +278,155 @@ impl ToolRegistry {
             },
         }
     }
-}
\ No newline at end of file
+}
+
+/// The name a tool is registered under: `name@version` for versioned tools
+fn versioned_name(name: &str, version: Option<&str>) -> String {
+    match version {
+        Some(version) => format!("{}@{}", name, version),
+        None => name.to_string(),
+    }
+}
+
+/// Orders versions like `v2` < `v10` < `v10.1` by their numbers
+fn version_order(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+/// `name=target` pairs separated by commas
+fn parse_aliases(spec: &str) -> Result<Vec<(String, String)>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((alias, target)) if !alias.trim().is_empty() && !target.trim().is_empty() => {
+                Ok((alias.trim().to_string(), target.trim().to_string()))
+            }
+            _ => Err(anyhow::anyhow!("Expected name=target, got '{}'", pair)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    #[async_trait]
+    impl Tool for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn description(&self) -> &str {
+            "Echoes its arguments"
+        }
+        fn input_schema(&self) -> Value {
+            serde_json::json!({ "type": "object" })
+        }
+        async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+            Ok(vec![ContentBlock::text(&serde_json::to_string(&args)?)])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_and_aliases() {
+        let mut registry = ToolRegistry::new();
+        registry.register_in("text", Box::new(Echo));
+        registry.register_in("debug", Box::new(Echo));
+        registry.register_in("text", Box::new(Echo));
+        registry.add_alias("echo", "text.echo", true);
+
+        let names: Vec<String> = registry.list_tools().await.into_iter().map(|tool| tool.name).collect();
+        assert_eq!(names, ["debug.echo", "text.echo"]);
+        assert_eq!(registry.resolve("echo"), "text.echo");
+        assert_eq!(registry.resolve("debug.echo"), "debug.echo");
+        assert!(registry.call_tool("echo", HashMap::new()).await.is_ok());
+        assert!(registry.call_tool("text", HashMap::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_virtual_tools_call_their_target_with_presets() {
+        let mut registry = ToolRegistry::new();
+        registry.register_in("text", Box::new(Echo));
+        registry.add_alias("echo", "text.echo", false);
+        registry.add_virtual(serde_json::from_value(serde_json::json!({
+            "name": "shout",
+            "description": "Echoes loudly",
+            "tool": "echo",
+            "arguments": { "volume": "loud" },
+            "inputSchema": { "type": "object", "properties": { "message": { "type": "string" } } }
+        })).unwrap());
+
+        let tools = registry.list_tools().await;
+        assert_eq!(tools[0].name, "shout");
+        assert_eq!(tools[0].input_schema["properties"]["message"]["type"], "string");
+
+        let args = HashMap::from([("message".to_string(), Value::from("hi"))]);
+        let output = registry.call_tool("shout", args).await.unwrap();
+        let echoed: Value = serde_json::from_str(output[0].as_text().unwrap()).unwrap();
+        assert_eq!(echoed, serde_json::json!({ "message": "hi", "volume": "loud" }));
+        assert!(registry.call_tool("shout", HashMap::from([("volume".to_string(), Value::from("quiet"))])).await.is_err());
+    }
+
+    /// `echo` with a version and deprecation
+    struct Versioned(&'static str, bool);
+
+    #[async_trait]
+    impl Tool for Versioned {
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn description(&self) -> &str {
+            "Echoes its arguments"
+        }
+        fn input_schema(&self) -> Value {
+            serde_json::json!({ "type": "object" })
+        }
+        fn version(&self) -> Option<&str> {
+            Some(self.0)
+        }
+        fn deprecation(&self) -> Option<Deprecation> {
+            self.1.then(|| Deprecation { message: "Use a newer version".to_string(), replaced_by: None })
+        }
+        async fn call(&self, _args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+            Ok(vec![ContentBlock::text(self.0)])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_versions_of_a_tool_coexist() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Box::new(Versioned("v2", true)));
+        registry.register(Box::new(Versioned("v10", false)));
+
+        let tools = registry.list_tools().await;
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_str()).collect();
+        assert_eq!(names, ["echo@v10", "echo@v2"]);
+        assert_eq!(tools[1].version.as_deref(), Some("v2"));
+        assert!(tools[1].deprecated.is_some());
+        assert!(registry.deprecation("echo@v2").is_some());
+        assert!(registry.deprecation("echo@v10").is_none());
+
+        // The bare name is the latest version, until a tool takes it
+        assert_eq!(registry.resolve("echo"), "echo@v10");
+        registry.register(Box::new(Echo));
+        assert_eq!(registry.resolve("echo"), "echo");
+        assert_eq!(registry.call_tool("echo@v2", HashMap::new()).await.unwrap()[0].as_text(), Some("v2"));
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        assert_eq!(
+            parse_aliases("weather = http.request, lights=ha.call_service").unwrap(),
+            [
+                ("weather".to_string(), "http.request".to_string()),
+                ("lights".to_string(), "ha.call_service".to_string())
+            ]
+        );
+        assert!(parse_aliases("weather").is_err());
+    }
+}
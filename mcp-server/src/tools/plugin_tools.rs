@@ -4,13 +4,26 @@ use serde_json::Value;
 use anyhow::Result;
 use async_trait::async_trait;
 
-use crate::mcp::ContentBlock;
+use crate::mcp::{ContentBlock, Deprecation, ToolAnnotations, ToolDefinition, ToolTag};
 use crate::plugins::{
     Plugin,
     system_info::SystemInfoPlugin,
     home_assistant::HomeAssistantPlugin,
     http::HttpPlugin,
     neo4j::Neo4jPlugin,
+    embeddings::EmbeddingsPlugin,
+    memory::MemoryPlugin,
+    knowledge_graph::KnowledgeGraphPlugin,
+    metrics::MetricsPlugin,
+    ollama::OllamaPlugin,
+    speech::SpeechPlugin,
+    vision::VisionPlugin,
+    network::NetworkPlugin,
+    netdiag::NetDiagPlugin,
+    jira::JiraPlugin,
+    summarize::SummarizePlugin,
+    filesystem::FileSystemPlugin,
+    proxy::ProxyPlugin,
     Context,
 };
 
@@ -33,7 +46,11 @@ impl Tool for SystemInfoTool {
     }
 
     fn description(&self) -> &str {
-        "Get system information like memory usage, CPU load, etc."
+        "Get system information like memory usage, CPU load, uptime, load averages, container limits, top processes, disk usage, network throughput and GPU utilization, memory and temperature"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        vec![ToolTag::ReadOnly]
     }
 
     fn input_schema(&self) -> Value {
@@ -42,22 +59,44 @@ impl Tool for SystemInfoTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["get_system_info"],
+                    "enum": ["get_system_info", "get_memory_usage", "get_processes", "get_disks", "get_network", "get_gpu_info"],
                     "default": "get_system_info"
+                },
+                "include_details": {
+                    "type": "boolean",
+                    "description": "Include detailed memory statistics (get_memory_usage)"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of processes to return (get_processes)",
+                    "default": 10
+                },
+                "sort_by": {
+                    "type": "string",
+                    "enum": ["cpu", "memory"],
+                    "description": "Sort order for processes (get_processes)",
+                    "default": "cpu"
                 }
             }
         })
     }
 
     async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let action = args.get("action")
+            .and_then(|v| v.as_str())
+            .unwrap_or("get_system_info")
+            .to_string();
         let context = Context {
             correlation_id: uuid::Uuid::new_v4().to_string(),
             timestamp: chrono::Utc::now(),
             parameters: args.clone(),
+            sampling: None,
+            roots: None,
+            tenant: None,
         };
-        let result = self.plugin.execute("get_system_info", context, args).await
+        let result = self.plugin.execute(&action, context, args).await
             .map_err(|e| anyhow::anyhow!(e))?;
-        Ok(vec![ContentBlock::text(&serde_json::to_string_pretty(&result.data)?)])
+        Ok(ContentBlock::structured(result.data))
     }
 }
 
@@ -81,6 +120,11 @@ impl Tool for HomeAssistantTool {
         "Interact with Home Assistant devices and services"
     }
 
+    fn tags(&self) -> Vec<ToolTag> {
+        // call_service switches devices
+        vec![ToolTag::Network, ToolTag::Destructive]
+    }
+
     fn input_schema(&self) -> Value {
         serde_json::json!({
             "type": "object",
@@ -115,10 +159,13 @@ impl Tool for HomeAssistantTool {
             correlation_id: uuid::Uuid::new_v4().to_string(),
             timestamp: chrono::Utc::now(),
             parameters: args.clone(),
+            sampling: None,
+            roots: None,
+            tenant: None,
         };
         let result = self.plugin.execute(action, context, args.clone()).await
             .map_err(|e| anyhow::anyhow!(e))?;
-        Ok(vec![ContentBlock::text(&serde_json::to_string_pretty(&result.data)?)])
+        Ok(ContentBlock::structured(result.data))
     }
 }
 
@@ -142,6 +189,11 @@ impl Tool for HttpTool {
         "Make HTTP requests to external services"
     }
 
+    fn tags(&self) -> Vec<ToolTag> {
+        // Any method may be used, including DELETE
+        vec![ToolTag::Network, ToolTag::Destructive]
+    }
+
     fn input_schema(&self) -> Value {
         serde_json::json!({
             "type": "object",
@@ -169,10 +221,13 @@ impl Tool for HttpTool {
             correlation_id: uuid::Uuid::new_v4().to_string(),
             timestamp: chrono::Utc::now(),
             parameters: args.clone(),
+            sampling: None,
+            roots: None,
+            tenant: None,
         };
         let result = self.plugin.execute("request", context, args.clone()).await
             .map_err(|e| anyhow::anyhow!(e))?;
-        Ok(vec![ContentBlock::text(&serde_json::to_string_pretty(&result.data)?)])
+        Ok(ContentBlock::structured(result.data))
     }
 }
 
@@ -196,6 +251,11 @@ impl Tool for Neo4jTool {
         "Execute Cypher queries against a Neo4j database"
     }
 
+    fn tags(&self) -> Vec<ToolTag> {
+        // Cypher queries may write
+        vec![ToolTag::Network, ToolTag::Destructive]
+    }
+
     fn input_schema(&self) -> Value {
         serde_json::json!({
             "type": "object",
@@ -219,9 +279,1023 @@ impl Tool for Neo4jTool {
             correlation_id: uuid::Uuid::new_v4().to_string(),
             timestamp: chrono::Utc::now(),
             parameters: args.clone(),
+            sampling: None,
+            roots: None,
+            tenant: None,
         };
         let result = self.plugin.execute("query", context, args.clone()).await
             .map_err(|e| anyhow::anyhow!(e))?;
-        Ok(vec![ContentBlock::text(&serde_json::to_string_pretty(&result.data)?)])
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+pub struct EmbeddingsTool {
+    plugin: Arc<EmbeddingsPlugin>,
+}
+
+impl EmbeddingsTool {
+    pub fn new(plugin: Arc<EmbeddingsPlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for EmbeddingsTool {
+    fn name(&self) -> &str {
+        "embeddings"
+    }
+
+    fn description(&self) -> &str {
+        "Store text chunks with embedding vectors and search them by similarity"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        // Embedding goes through Ollama; store and delete_collection write
+        vec![ToolTag::Network, ToolTag::Destructive, ToolTag::Slow]
+    }
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "required": ["action"],
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["store", "search", "list_collections", "delete_collection"]
+                },
+                "collection": {
+                    "type": "string",
+                    "description": "Name of the collection (store, search, delete_collection)"
+                },
+                "items": {
+                    "type": "array",
+                    "description": "Chunks to store, each {id, text, embedding, metadata} (store)",
+                    "items": {"type": "object"}
+                },
+                "embedding": {
+                    "type": "array",
+                    "description": "Query embedding vector (search)",
+                    "items": {"type": "number"}
+                },
+                "top_k": {
+                    "type": "integer",
+                    "description": "Number of results to return (search)",
+                    "default": 5
+                }
+            }
+        })
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let action = args.get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing action parameter"))?
+            .to_string();
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute(&action, context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+pub struct OllamaTool {
+    plugin: Arc<OllamaPlugin>,
+}
+
+impl OllamaTool {
+    pub fn new(plugin: Arc<OllamaPlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for OllamaTool {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn description(&self) -> &str {
+        "Report which Ollama models are pulled and loaded, a model's details, and how much VRAM and RAM loaded models use"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        vec![ToolTag::ReadOnly, ToolTag::Network]
+    }
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["list_models", "model_info", "running_models"],
+                    "default": "running_models"
+                },
+                "model": {
+                    "type": "string",
+                    "description": "Model name, e.g. llama3:8b (model_info)"
+                }
+            }
+        })
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let action = args.get("action")
+            .and_then(|v| v.as_str())
+            .unwrap_or("running_models")
+            .to_string();
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute(&action, context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+pub struct SpeechTool {
+    plugin: Arc<SpeechPlugin>,
+}
+
+impl SpeechTool {
+    pub fn new(plugin: Arc<SpeechPlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for SpeechTool {
+    fn name(&self) -> &str {
+        "speech"
+    }
+
+    fn description(&self) -> &str {
+        "Transcribe speech from an audio file or base64 audio, or turn text into spoken audio"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        // The backends may be servers; speak can write a file
+        vec![ToolTag::Network, ToolTag::Destructive, ToolTag::Slow]
+    }
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "required": ["action"],
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["transcribe", "speak"]
+                },
+                "audio_path": {
+                    "type": "string",
+                    "description": "Audio file within the filesystem roots (transcribe)"
+                },
+                "audio_base64": {
+                    "type": "string",
+                    "description": "Base64 audio, instead of audio_path (transcribe)"
+                },
+                "language": {
+                    "type": "string",
+                    "description": "ISO 639-1 language code; detected if omitted (transcribe)"
+                },
+                "text": {
+                    "type": "string",
+                    "description": "Text to speak (speak)"
+                },
+                "voice": {
+                    "type": "string",
+                    "description": "Voice or speaker id (speak)"
+                },
+                "output_path": {
+                    "type": "string",
+                    "description": "Write the audio here, within the filesystem roots, instead of returning it (speak)"
+                }
+            }
+        })
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let action = args.get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing action parameter"))?
+            .to_string();
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute(&action, context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::from_plugin_data(result.data))
+    }
+}
+
+pub struct AnalyzeImageTool {
+    plugin: Arc<VisionPlugin>,
+}
+
+impl AnalyzeImageTool {
+    pub fn new(plugin: Arc<VisionPlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for AnalyzeImageTool {
+    fn name(&self) -> &str {
+        "analyze_image"
+    }
+
+    fn description(&self) -> &str {
+        "Describe or classify an image from a Home Assistant camera, a file or base64 with an Ollama vision model"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        vec![ToolTag::ReadOnly, ToolTag::Network, ToolTag::Slow]
+    }
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "camera": {
+                    "type": "string",
+                    "description": "Home Assistant camera entity, e.g. camera.front_door"
+                },
+                "image_path": {
+                    "type": "string",
+                    "description": "Image file within the filesystem roots"
+                },
+                "image_base64": {
+                    "type": "string",
+                    "description": "PNG, JPEG, GIF or WebP image, base64 encoded"
+                },
+                "prompt": {
+                    "type": "string",
+                    "description": "What to ask about the image; describes it if omitted"
+                },
+                "labels": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Classify the image as one of these instead of describing it"
+                },
+                "include_image": {
+                    "type": "boolean",
+                    "description": "Return the analyzed image as well",
+                    "default": false
+                }
+            }
+        })
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute("analyze", context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::from_plugin_data(result.data))
+    }
+}
+
+pub struct NetworkTool {
+    plugin: Arc<NetworkPlugin>,
+}
+
+impl NetworkTool {
+    pub fn new(plugin: Arc<NetworkPlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for NetworkTool {
+    fn name(&self) -> &str {
+        "network"
+    }
+
+    fn description(&self) -> &str {
+        "Wake a device with Wake-on-LAN, ping a host, scan its TCP ports or list the devices on the local network"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        // wol powers machines on
+        vec![ToolTag::Network, ToolTag::Destructive]
+    }
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "required": ["action"],
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["wol", "ping", "port_scan", "arp_scan"]
+                },
+                "mac": {
+                    "type": "string",
+                    "description": "MAC address of the device to wake (wol)"
+                },
+                "broadcast": {
+                    "type": "string",
+                    "description": "Broadcast address, optionally with port (wol, default: 255.255.255.255:9)"
+                },
+                "host": {
+                    "type": "string",
+                    "description": "Host name or IP address on the local network (ping, port_scan)"
+                },
+                "count": {
+                    "type": "integer",
+                    "description": "Pings to send (ping)",
+                    "default": 3
+                },
+                "ports": {
+                    "type": ["string", "array"],
+                    "items": {"type": "integer"},
+                    "description": "Ports as a list or a string like \"22,80,8000-8100\" (port_scan)"
+                },
+                "subnet": {
+                    "type": "string",
+                    "description": "IPv4 subnet to sweep first, at most /24 (arp_scan)"
+                }
+            }
+        })
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let action = args.get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing action parameter"))?
+            .to_string();
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute(&action, context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+pub struct NetDiagTool {
+    plugin: Arc<NetDiagPlugin>,
+}
+
+impl NetDiagTool {
+    pub fn new(plugin: Arc<NetDiagPlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for NetDiagTool {
+    fn name(&self) -> &str {
+        "netdiag"
+    }
+
+    fn description(&self) -> &str {
+        "Look up DNS records across resolvers, a domain's WHOIS registration and expiry, or a host's TLS certificate and when it expires"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        vec![ToolTag::ReadOnly, ToolTag::Network]
     }
-}
\ No newline at end of file
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "required": ["action"],
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["dns_lookup", "whois", "tls_cert_info"]
+                },
+                "name": {
+                    "type": "string",
+                    "description": "Name to look up, or an IP address for PTR (dns_lookup)"
+                },
+                "type": {
+                    "type": "string",
+                    "enum": ["A", "AAAA", "CNAME", "MX", "NS", "TXT", "SOA", "SRV", "CAA", "PTR"],
+                    "default": "A"
+                },
+                "servers": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Resolvers to compare, e.g. [\"1.1.1.1\", \"8.8.8.8\"] (dns_lookup)"
+                },
+                "domain": {
+                    "type": "string",
+                    "description": "Domain name (whois)"
+                },
+                "host": {
+                    "type": "string",
+                    "description": "Host name (tls_cert_info)"
+                },
+                "port": {
+                    "type": "integer",
+                    "default": 443
+                },
+                "warn_days": {
+                    "type": "integer",
+                    "description": "Days before expiry to report expiring_soon (tls_cert_info)",
+                    "default": 30
+                }
+            }
+        })
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let action = args.get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing action parameter"))?
+            .to_string();
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute(&action, context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+pub struct JiraTool {
+    plugin: Arc<JiraPlugin>,
+}
+
+impl JiraTool {
+    pub fn new(plugin: Arc<JiraPlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for JiraTool {
+    fn name(&self) -> &str {
+        "jira"
+    }
+
+    fn description(&self) -> &str {
+        "Search Jira issues with JQL, read an issue with its comments, create issues and move them through their workflow"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        vec![ToolTag::Network, ToolTag::Destructive]
+    }
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "required": ["action"],
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["search", "get_issue", "create_issue", "transition"]
+                },
+                "jql": {
+                    "type": "string",
+                    "description": "JQL query (search)"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Issues to return, at most 100 (search)",
+                    "default": 20
+                },
+                "key": {
+                    "type": "string",
+                    "description": "Issue key, e.g. OPS-123 (get_issue, transition)"
+                },
+                "project": {
+                    "type": "string",
+                    "description": "Project key (create_issue)"
+                },
+                "summary": {
+                    "type": "string",
+                    "description": "Issue title (create_issue)"
+                },
+                "issue_type": {
+                    "type": "string",
+                    "default": "Task"
+                },
+                "description": {
+                    "type": "string"
+                },
+                "priority": {
+                    "type": "string"
+                },
+                "labels": {
+                    "type": "array",
+                    "items": {"type": "string"}
+                },
+                "to": {
+                    "type": "string",
+                    "description": "Transition or target status name, e.g. Done (transition)"
+                },
+                "comment": {
+                    "type": "string",
+                    "description": "Comment to add with the transition (transition)"
+                }
+            }
+        })
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let action = args.get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing action parameter"))?
+            .to_string();
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute(&action, context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+pub struct MemoryTool {
+    plugin: Arc<MemoryPlugin>,
+}
+
+impl MemoryTool {
+    pub fn new(plugin: Arc<MemoryPlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for MemoryTool {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn description(&self) -> &str {
+        "Remember values under a key across conversations, recall them and search what was remembered"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        // remember replaces what was stored under the key
+        vec![ToolTag::Destructive]
+    }
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "required": ["action"],
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["remember", "recall", "search"]
+                },
+                "key": {
+                    "type": "string",
+                    "description": "Name the memory is stored under (remember, recall)"
+                },
+                "value": {
+                    "description": "What to remember; any JSON value (remember)"
+                },
+                "ttl": {
+                    "type": "integer",
+                    "description": "Seconds until the memory is forgotten; kept if omitted (remember)"
+                },
+                "text": {
+                    "type": "string",
+                    "description": "Text to look for in keys and values, ignoring case (search)"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of memories to return (search)",
+                    "default": 10
+                }
+            }
+        })
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let action = args.get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing action parameter"))?
+            .to_string();
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute(&action, context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+pub struct MetricsTool {
+    plugin: Arc<MetricsPlugin>,
+}
+
+impl MetricsTool {
+    pub fn new(plugin: Arc<MetricsPlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for MetricsTool {
+    fn name(&self) -> &str {
+        "aggregate_metrics"
+    }
+
+    fn description(&self) -> &str {
+        "Chart a stored metric's trend: its readings combined into avg, min, max or p95 per time window"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        vec![ToolTag::ReadOnly]
+    }
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "required": ["metric_type"],
+            "properties": {
+                "metric_type": {
+                    "type": "string",
+                    "description": "Metric to aggregate, e.g. cpu_usage"
+                },
+                "window": {
+                    "type": "integer",
+                    "description": "Seconds per bucket",
+                    "default": 300
+                },
+                "function": {
+                    "type": "string",
+                    "enum": ["avg", "min", "max", "p95"],
+                    "default": "avg"
+                },
+                "since": {
+                    "type": "string",
+                    "description": "RFC 3339 time to start from; 24 hours ago if omitted"
+                }
+            }
+        })
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        Some(serde_json::json!({
+            "type": "object",
+            "required": ["metric_type", "window", "function", "since", "buckets"],
+            "properties": {
+                "metric_type": { "type": "string" },
+                "window": { "type": "integer" },
+                "function": { "type": "string" },
+                "since": { "type": "string" },
+                "buckets": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["start", "value", "count"],
+                        "properties": {
+                            "start": { "type": "string", "description": "Start of the window" },
+                            "value": { "type": "number" },
+                            "count": { "type": "integer", "description": "Readings in the window" }
+                        }
+                    }
+                }
+            }
+        }))
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute("aggregate", context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+/// Calls one capability of the knowledge graph plugin; `kg_ingest` and
+/// `kg_query` are two tools so each can have its own schema and tags
+pub struct KnowledgeGraphTool {
+    plugin: Arc<KnowledgeGraphPlugin>,
+    capability: &'static str,
+}
+
+impl KnowledgeGraphTool {
+    pub fn ingest(plugin: Arc<KnowledgeGraphPlugin>) -> Self {
+        Self { plugin, capability: "ingest" }
+    }
+
+    pub fn query(plugin: Arc<KnowledgeGraphPlugin>) -> Self {
+        Self { plugin, capability: "query" }
+    }
+}
+
+#[async_trait]
+impl Tool for KnowledgeGraphTool {
+    fn name(&self) -> &str {
+        match self.capability {
+            "ingest" => "kg_ingest",
+            _ => "kg_query",
+        }
+    }
+
+    fn description(&self) -> &str {
+        match self.capability {
+            "ingest" => "Extract entities and relations from text with the model and add them to the knowledge graph, noting where they came from",
+            _ => "Look up the entities a question names in the knowledge graph, with the facts about them and their sources",
+        }
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        match self.capability {
+            // Extraction asks a model, then writes to the graph
+            "ingest" => vec![ToolTag::Network, ToolTag::Destructive, ToolTag::Slow],
+            _ => vec![ToolTag::ReadOnly],
+        }
+    }
+
+    fn input_schema(&self) -> Value {
+        match self.capability {
+            "ingest" => serde_json::json!({
+                "type": "object",
+                "required": ["text"],
+                "properties": {
+                    "text": {
+                        "type": "string",
+                        "description": "Text to extract facts from"
+                    },
+                    "source": {
+                        "type": "string",
+                        "description": "Where the text came from, such as a URL, kept as its provenance"
+                    }
+                }
+            }),
+            _ => serde_json::json!({
+                "type": "object",
+                "required": ["question"],
+                "properties": {
+                    "question": {
+                        "type": "string",
+                        "description": "Question or keywords naming the entities to look up"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximum number of matching entities",
+                        "default": 20
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute(self.capability, context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+pub struct SummarizeTool {
+    plugin: Arc<SummarizePlugin>,
+}
+
+impl SummarizeTool {
+    pub fn new(plugin: Arc<SummarizePlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for SummarizeTool {
+    fn name(&self) -> &str {
+        "summarize"
+    }
+
+    fn description(&self) -> &str {
+        "Summarize text, such as a long tool result, using the client's model"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        // Asks a model for the summary
+        vec![ToolTag::ReadOnly, ToolTag::Network, ToolTag::Slow]
+    }
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "required": ["text"],
+            "properties": {
+                "text": {
+                    "type": "string",
+                    "description": "Text to summarize"
+                },
+                "max_words": {
+                    "type": "integer",
+                    "description": "Rough length of the summary",
+                    "default": 100
+                }
+            }
+        })
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        Some(serde_json::json!({
+            "type": "object",
+            "required": ["summary", "model"],
+            "properties": {
+                "summary": { "type": "string" },
+                "model": { "type": "string", "description": "Model that wrote the summary" }
+            }
+        }))
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute("summarize", context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+pub struct FileSystemTool {
+    plugin: Arc<FileSystemPlugin>,
+}
+
+impl FileSystemTool {
+    pub fn new(plugin: Arc<FileSystemPlugin>) -> Self {
+        Self { plugin }
+    }
+}
+
+#[async_trait]
+impl Tool for FileSystemTool {
+    fn name(&self) -> &str {
+        "filesystem"
+    }
+
+    fn description(&self) -> &str {
+        "Read files and list directories within the roots the server and client allow"
+    }
+
+    fn tags(&self) -> Vec<ToolTag> {
+        vec![ToolTag::ReadOnly]
+    }
+
+    fn input_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "required": ["action"],
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["list_roots", "list_directory", "read_file", "file_info"]
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Absolute path, or relative to the first allowed root"
+                },
+                "max_bytes": {
+                    "type": "integer",
+                    "description": "Maximum bytes to return (read_file)",
+                    "default": 65536
+                }
+            }
+        })
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        let action = args.get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("action is required for filesystem"))?
+            .to_string();
+        let context = Context {
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: None,
+        };
+        let result = self.plugin.execute(&action, context, args).await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(ContentBlock::structured(result.data))
+    }
+}
+
+/// One tool of an external MCP server, called through its `ProxyPlugin`
+pub struct ProxyTool {
+    plugin: Arc<ProxyPlugin>,
+    definition: ToolDefinition,
+}
+
+impl ProxyTool {
+    pub fn new(plugin: Arc<ProxyPlugin>, definition: ToolDefinition) -> Self {
+        Self { plugin, definition }
+    }
+}
+
+#[async_trait]
+impl Tool for ProxyTool {
+    /// Without the `@version` the other server lists versioned tools with,
+    /// since the registry adds it back
+    fn name(&self) -> &str {
+        let name = &self.definition.name;
+        match &self.definition.version {
+            Some(version) => name
+                .strip_suffix(version.as_str())
+                .and_then(|name| name.strip_suffix('@'))
+                .unwrap_or(name),
+            None => name,
+        }
+    }
+
+    fn description(&self) -> &str {
+        &self.definition.description
+    }
+
+    fn version(&self) -> Option<&str> {
+        self.definition.version.as_deref()
+    }
+
+    fn deprecation(&self) -> Option<Deprecation> {
+        self.definition.deprecated.clone()
+    }
+
+    fn input_schema(&self) -> Value {
+        self.definition.input_schema.clone()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        self.definition.output_schema.clone()
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        self.definition.annotations.clone()
+    }
+
+    async fn call(&self, args: HashMap<String, Value>) -> Result<Vec<ContentBlock>> {
+        self.plugin.call_tool(&self.definition.name, args).await.map_err(|e| match e.downcast::<mcp_core::Error>() {
+            // Kept typed, so the server passes the child's refusals on
+            Ok(e) => anyhow::Error::new(*e),
+            Err(e) => anyhow::anyhow!(e),
+        })
+    }
+}
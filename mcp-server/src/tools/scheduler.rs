@@ -0,0 +1,193 @@
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use tracing::debug;
+
+use crate::mcp::Priority;
+
+/// How many calls of each tool may run at once, parsed from a spec such as
+/// `homeassistant=2,http_request=4`; other tools are not limited
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConcurrencyConfig {
+    pub limits: HashMap<String, usize>,
+}
+
+impl FromStr for ConcurrencyConfig {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut config = ConcurrencyConfig::default();
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (tool, limit) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected tool=calls, got '{}'", setting))?;
+            let limit: usize = limit
+                .trim()
+                .parse()
+                .ok()
+                .filter(|limit| *limit > 0)
+                .ok_or_else(|| format!("Invalid concurrency for {}: '{}'", tool.trim(), limit.trim()))?;
+            config.limits.insert(tool.trim().to_string(), limit);
+        }
+        Ok(config)
+    }
+}
+
+impl ConcurrencyConfig {
+    /// Limits from `MCP_TOOL_CONCURRENCY`, if set and valid
+    pub fn from_env() -> Option<Self> {
+        let spec = std::env::var("MCP_TOOL_CONCURRENCY").ok()?;
+        match spec.parse() {
+            Ok(config) => Some(config),
+            Err(e) => {
+                tracing::warn!("Ignoring MCP_TOOL_CONCURRENCY: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Calls of one tool: how many run, and who waits for a slot
+#[derive(Default)]
+struct Slots {
+    running: usize,
+    interactive: VecDeque<oneshot::Sender<()>>,
+    background: VecDeque<oneshot::Sender<()>>,
+}
+
+/// Keeps tools within their concurrency limits. A call that finds its tool
+/// busy waits in a queue for its priority, and a freed slot goes to the
+/// oldest interactive call before any background one, so a client is not
+/// stuck behind queued jobs.
+pub struct ToolScheduler {
+    limits: HashMap<String, usize>,
+    slots: Mutex<HashMap<String, Slots>>,
+}
+
+/// A running call's slot, given back when dropped
+pub struct ToolPermit {
+    slot: Option<(Arc<ToolScheduler>, String)>,
+}
+
+impl Drop for ToolPermit {
+    fn drop(&mut self) {
+        if let Some((scheduler, tool)) = self.slot.take() {
+            scheduler.release(&tool);
+        }
+    }
+}
+
+/// A queued call; if it is given up after being handed a slot, the slot is
+/// passed on
+struct Waiting {
+    receiver: oneshot::Receiver<()>,
+    scheduler: Arc<ToolScheduler>,
+    tool: String,
+    done: bool,
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        if !self.done {
+            self.receiver.close();
+            if self.receiver.try_recv().is_ok() {
+                self.scheduler.release(&self.tool);
+            }
+        }
+    }
+}
+
+impl ToolScheduler {
+    pub fn new(config: ConcurrencyConfig) -> Self {
+        Self { limits: config.limits, slots: Mutex::new(HashMap::new()) }
+    }
+
+    /// Waits for a slot to run `tool` in; tools without a limit get one at once
+    pub async fn acquire(self: &Arc<Self>, tool: &str, priority: Priority) -> ToolPermit {
+        let Some(&limit) = self.limits.get(tool) else {
+            return ToolPermit { slot: None };
+        };
+        let receiver = {
+            let mut all = self.slots.lock().unwrap();
+            let slots = all.entry(tool.to_string()).or_default();
+            if slots.running < limit {
+                slots.running += 1;
+                return ToolPermit { slot: Some((self.clone(), tool.to_string())) };
+            }
+            let (sender, receiver) = oneshot::channel();
+            match priority {
+                Priority::Interactive => slots.interactive.push_back(sender),
+                Priority::Background => slots.background.push_back(sender),
+            }
+            receiver
+        };
+        debug!("{} is running {} calls; queueing a {:?} call", tool, limit, priority);
+        let mut waiting = Waiting { receiver, scheduler: self.clone(), tool: tool.to_string(), done: false };
+        // Senders are only dropped unsent along with the scheduler
+        let _ = (&mut waiting.receiver).await;
+        waiting.done = true;
+        ToolPermit { slot: Some((self.clone(), tool.to_string())) }
+    }
+
+    /// Hands the slot to the next waiting call, interactive calls first
+    fn release(&self, tool: &str) {
+        let mut all = self.slots.lock().unwrap();
+        let Some(slots) = all.get_mut(tool) else {
+            return;
+        };
+        while let Some(next) = slots.interactive.pop_front().or_else(|| slots.background.pop_front()) {
+            if next.send(()).is_ok() {
+                return;
+            }
+        }
+        slots.running -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_spec() {
+        let config: ConcurrencyConfig = "homeassistant=2, http_request=4".parse().unwrap();
+        assert_eq!(config.limits["homeassistant"], 2);
+        assert_eq!(config.limits["http_request"], 4);
+        assert!("homeassistant=0".parse::<ConcurrencyConfig>().is_err());
+        assert!("homeassistant".parse::<ConcurrencyConfig>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_interactive_calls_go_before_queued_background_calls() {
+        let scheduler = Arc::new(ToolScheduler::new("slow=1".parse().unwrap()));
+        let running = scheduler.acquire("slow", Priority::Background).await;
+        let (order, mut started) = tokio::sync::mpsc::unbounded_channel();
+
+        let mut waiters = Vec::new();
+        for (name, priority) in [("job 1", Priority::Background), ("job 2", Priority::Background), ("client", Priority::Interactive)] {
+            let scheduler = scheduler.clone();
+            let order = order.clone();
+            waiters.push(tokio::spawn(async move {
+                let _permit = scheduler.acquire("slow", priority).await;
+                order.send(name).unwrap();
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // A queued call that is given up does not hold on to a slot
+        let abandoned = tokio::time::timeout(Duration::from_millis(10), scheduler.acquire("slow", Priority::Interactive)).await;
+        assert!(abandoned.is_err());
+
+        drop(running);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+        let order: Vec<&str> = std::iter::from_fn(|| started.try_recv().ok()).collect();
+        assert_eq!(order, ["client", "job 1", "job 2"]);
+
+        let _unlimited = scheduler.acquire("fast", Priority::Background).await;
+        let _again = scheduler.acquire("slow", Priority::Background).await;
+    }
+}
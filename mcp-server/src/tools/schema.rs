@@ -0,0 +1,120 @@
+//! Checks a tool's structured result against its `outputSchema`. Covers the
+//! JSON Schema keywords output schemas use in practice: `type`,
+//! `properties`, `required`, `additionalProperties: false`, `items` and
+//! `enum`. Anything else is ignored.
+
+use serde_json::Value;
+
+/// Every way `value` breaks `schema`, each as `path: message` with a JSON
+/// pointer path; empty when `value` is valid
+pub fn violations(schema: &Value, value: &Value) -> Vec<String> {
+    let mut violations = Vec::new();
+    check(schema, value, "", &mut violations);
+    violations
+}
+
+fn check(schema: &Value, value: &Value, path: &str, violations: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+    let at = if path.is_empty() { "/" } else { path };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+            violations.push(format!("{}: expected {}, got {}", at, types.join(" or "), type_name(value)));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            violations.push(format!("{}: {} is not one of the allowed values", at, value));
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                violations.push(format!("{}: missing required property '{}'", at, name));
+            }
+        }
+        for (name, field) in object {
+            let field_path = format!("{}/{}", path, name);
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(field_schema) => check(field_schema, field, &field_path, violations),
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    violations.push(format!("{}: unexpected property", field_path));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (index, item) in array.iter().enumerate() {
+            check(items, item, &format!("{}/{}", path, index), violations);
+        }
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_violations() {
+        let schema = json!({
+            "type": "object",
+            "required": ["summary"],
+            "additionalProperties": false,
+            "properties": {
+                "summary": { "type": "string" },
+                "buckets": { "type": "array", "items": { "type": "object", "required": ["value"] } },
+                "unit": { "enum": ["s", "ms"] }
+            }
+        });
+        assert!(violations(&schema, &json!({ "summary": "ok", "buckets": [{ "value": 1 }], "unit": "s" })).is_empty());
+        assert_eq!(
+            violations(&schema, &json!({ "buckets": [{}], "unit": "h", "extra": 1 })),
+            [
+                "/: missing required property 'summary'",
+                "/buckets/0: missing required property 'value'",
+                "/extra: unexpected property",
+                "/unit: \"h\" is not one of the allowed values",
+            ]
+        );
+        assert_eq!(violations(&schema, &json!([1])), ["/: expected object, got array"]);
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::mcp::ToolTag;
+
+/// A tool defined in configuration that calls another tool with some of its
+/// arguments fixed, e.g. `turn_on_living_room_lights` calling `homeassistant`
+/// with `action=call_service` and the entity preset. Models get a narrow,
+/// self-explanatory tool instead of the general one.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VirtualTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The tool it calls; may be an alias
+    pub tool: String,
+    /// Arguments always passed to `tool`; callers cannot override them
+    #[serde(default)]
+    pub arguments: Map<String, Value>,
+    /// The arguments callers may pass, which must be declared under
+    /// `properties`; by default none
+    #[serde(default = "no_arguments", alias = "input_schema")]
+    pub input_schema: Value,
+    /// By default those of the tool it calls
+    #[serde(default)]
+    pub tags: Vec<ToolTag>,
+}
+
+fn no_arguments() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+impl VirtualTool {
+    /// Virtual tools from a JSON file holding an array of definitions
+    pub fn load(path: &Path) -> Result<Vec<Self>> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read virtual tools from {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid virtual tools in {}", path.display()))
+    }
+
+    /// Virtual tools from the file `MCP_VIRTUAL_TOOLS` names, if set and valid
+    pub fn from_env() -> Vec<Self> {
+        let Ok(path) = std::env::var("MCP_VIRTUAL_TOOLS") else {
+            return Vec::new();
+        };
+        match Self::load(Path::new(&path)) {
+            Ok(tools) => tools,
+            Err(e) => {
+                tracing::warn!("Ignoring MCP_VIRTUAL_TOOLS: {:#}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// The arguments to call `tool` with: the caller's, which must be
+    /// declared in the input schema, plus the preset ones
+    pub fn expand(&self, mut args: HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let declared = self.input_schema.get("properties").and_then(Value::as_object);
+        if let Some(name) = args.keys().find(|name| !declared.is_some_and(|declared| declared.contains_key(*name))) {
+            anyhow::bail!("Tool '{}' does not take argument '{}'", self.name, name);
+        }
+        args.extend(self.arguments.iter().map(|(name, value)| (name.clone(), value.clone())));
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lights() -> VirtualTool {
+        serde_json::from_value(json!({
+            "name": "turn_on_living_room_lights",
+            "description": "Turns on the living room lights",
+            "tool": "homeassistant",
+            "arguments": {
+                "action": "call_service",
+                "domain": "light",
+                "service": "turn_on",
+                "service_data": { "entity_id": "light.living_room" }
+            },
+            "inputSchema": {
+                "type": "object",
+                "properties": { "brightness": { "type": "integer" } }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_expand_adds_presets_and_rejects_undeclared_arguments() {
+        let tool = lights();
+        let args = tool.expand(HashMap::from([("brightness".to_string(), json!(80))])).unwrap();
+        assert_eq!(args["brightness"], 80);
+        assert_eq!(args["action"], "call_service");
+        assert_eq!(args["service_data"]["entity_id"], "light.living_room");
+
+        let args = HashMap::from([("service_data".to_string(), json!({ "entity_id": "light.all" }))]);
+        assert!(tool.expand(args).is_err());
+    }
+
+    #[test]
+    fn test_defaults_take_no_arguments() {
+        let tool: VirtualTool = serde_json::from_value(json!({ "name": "uptime", "tool": "system_info" })).unwrap();
+        assert!(tool.expand(HashMap::new()).is_ok());
+        assert!(tool.expand(HashMap::from([("action".to_string(), json!("get_processes"))])).is_err());
+    }
+}
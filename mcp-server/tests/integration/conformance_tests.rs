@@ -0,0 +1,129 @@
+//! The examples of the JSON-RPC 2.0 specification, section 7, and the
+//! envelope rules of sections 4 and 5, sent as raw messages
+
+use mcp_server::mcp::McpServer;
+use serde_json::{json, Value};
+
+async fn send(server: &McpServer, message: &str) -> Option<Value> {
+    let response = server.handle_message(message).await.unwrap();
+    (!response.is_empty()).then(|| serde_json::from_str(&response).unwrap())
+}
+
+async fn error_code(server: &McpServer, message: &str) -> (i64, Value) {
+    let response = send(server, message).await.expect("an error response");
+    assert_eq!(response["jsonrpc"], "2.0");
+    assert!(response.get("result").is_none());
+    (response["error"]["code"].as_i64().unwrap(), response["id"].clone())
+}
+
+#[tokio::test]
+async fn test_requests_get_a_response_with_their_id() {
+    let server = McpServer::new();
+
+    for id in [json!(1), json!("abc"), json!(-7), json!(2.5)] {
+        let message = json!({"jsonrpc": "2.0", "id": id, "method": "ping", "params": {}});
+        let response = send(&server, &message.to_string()).await.unwrap();
+        assert_eq!(response["id"], id);
+        assert_eq!(response["result"], json!({}));
+        assert!(response.get("error").is_none());
+    }
+
+    // Params may be left out, or be by position
+    let response = send(&server, r#"{"jsonrpc":"2.0","id":3,"method":"ping"}"#).await.unwrap();
+    assert_eq!(response["result"], json!({}));
+    let response = send(&server, r#"{"jsonrpc":"2.0","id":4,"method":"ping","params":[]}"#).await.unwrap();
+    assert_eq!(response["id"], 4);
+}
+
+#[tokio::test]
+async fn test_notifications_are_never_answered() {
+    let server = McpServer::new();
+
+    assert!(send(&server, r#"{"jsonrpc":"2.0","method":"update","params":[1,2,3,4,5]}"#).await.is_none());
+    assert!(send(&server, r#"{"jsonrpc":"2.0","method":"foobar"}"#).await.is_none());
+    // Not even a request method sent without an id
+    assert!(send(&server, r#"{"jsonrpc":"2.0","method":"tools/list"}"#).await.is_none());
+}
+
+#[tokio::test]
+async fn test_unknown_methods() {
+    let server = McpServer::new();
+    assert_eq!(error_code(&server, r#"{"jsonrpc":"2.0","method":"foobar","id":"1"}"#).await, (-32601, json!("1")));
+}
+
+#[tokio::test]
+async fn test_unreadable_messages_are_parse_errors() {
+    let server = McpServer::new();
+    assert_eq!(error_code(&server, r#"{"jsonrpc":"2.0","method":"foobar,"params":"bar","baz]"#).await, (-32700, Value::Null));
+    assert_eq!(
+        error_code(&server, r#"[{"jsonrpc":"2.0","method":"sum","params":[1,2,4],"id":"1"},{"jsonrpc":"2.0","method""#).await,
+        (-32700, Value::Null)
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_envelopes_are_invalid_requests() {
+    let server = McpServer::new();
+
+    // The id is echoed when it is a valid one
+    for (message, id) in [
+        (r#"{"jsonrpc":"2.0","method":1,"params":"bar"}"#, Value::Null),
+        (r#"{"jsonrpc":"1.0","id":1,"method":"ping"}"#, json!(1)),
+        (r#"{"id":2,"method":"ping"}"#, json!(2)),
+        (r#"{"jsonrpc":2.0,"id":3,"method":"ping"}"#, Value::Null),
+        (r#"{"jsonrpc":"2.0","id":null,"method":"ping"}"#, Value::Null),
+        (r#"{"jsonrpc":"2.0","id":{"n":1},"method":"ping"}"#, Value::Null),
+        (r#"{"jsonrpc":"2.0","id":true,"method":"ping"}"#, Value::Null),
+        (r#"{"jsonrpc":"2.0","id":5,"method":"ping","params":"bar"}"#, json!(5)),
+        (r#"{"jsonrpc":"2.0","id":6}"#, json!(6)),
+        (r#"{"jsonrpc":"2.0","id":7,"method":"ping","result":{}}"#, json!(7)),
+        ("42", Value::Null),
+        (r#""ping""#, Value::Null),
+    ] {
+        assert_eq!(error_code(&server, message).await, (-32600, id), "{}", message);
+    }
+
+    // Notifications with a bad envelope cannot be told apart from requests
+    assert_eq!(error_code(&server, r#"{"jsonrpc":"1.0","method":"update"}"#).await, (-32600, Value::Null));
+}
+
+#[tokio::test]
+async fn test_invalid_replies_are_not_answered() {
+    let server = McpServer::new();
+    assert!(send(&server, r#"{"jsonrpc":"1.0","id":1,"result":{}}"#).await.is_none());
+    assert!(send(&server, r#"{"jsonrpc":"2.0","id":1,"result":{},"error":{"code":1,"message":"x"}}"#).await.is_none());
+}
+
+#[tokio::test]
+async fn test_batches() {
+    let server = McpServer::new();
+
+    assert_eq!(error_code(&server, "[]").await, (-32600, Value::Null));
+
+    let response = send(&server, "[1]").await.unwrap();
+    assert_eq!(response.as_array().unwrap().len(), 1);
+    assert_eq!(response[0]["error"]["code"], -32600);
+
+    let response = send(&server, "[1,2,3]").await.unwrap();
+    let codes: Vec<&Value> = response.as_array().unwrap().iter().map(|response| &response["error"]["code"]).collect();
+    assert_eq!(codes, [-32600, -32600, -32600]);
+
+    let batch = r#"[
+        {"jsonrpc":"2.0","method":"ping","params":[],"id":"1"},
+        {"jsonrpc":"2.0","method":"notify_hello","params":[7]},
+        {"jsonrpc":"2.0","method":"ping","id":"2"},
+        {"foo":"boo"},
+        {"jsonrpc":"2.0","method":"foo.get","params":{"name":"myself"},"id":"5"},
+        {"jsonrpc":"2.0","method":"ping","id":"9"}
+    ]"#;
+    let response = send(&server, batch).await.unwrap();
+    let responses = response.as_array().unwrap();
+    assert_eq!(responses.len(), 5);
+    let ids: Vec<&Value> = responses.iter().map(|response| &response["id"]).collect();
+    assert_eq!(ids, [&json!("1"), &json!("2"), &Value::Null, &json!("5"), &json!("9")]);
+    assert_eq!(responses[2]["error"]["code"], -32600);
+    assert_eq!(responses[3]["error"]["code"], -32601);
+
+    let notifications = r#"[{"jsonrpc":"2.0","method":"notify_sum","params":[1,2,4]},{"jsonrpc":"2.0","method":"notify_hello","params":[7]}]"#;
+    assert!(send(&server, notifications).await.is_none());
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+use mcp_server::grpc::{content::Block, mcp_service_client::McpServiceClient, mcp_service_server::McpServiceServer};
+use mcp_server::grpc::{CallToolRequest, GrpcService, ListToolsRequest};
+use mcp_server::McpServer;
+use mcp_test_support::FakeTool;
+use tokio_stream::StreamExt;
+
+/// Serves gRPC for `server` on a free local port and connects a client to it
+async fn connect(server: Arc<McpServer>) -> McpServiceClient<tonic::transport::Channel> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(McpServiceServer::new(GrpcService::new(server)))
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+    );
+    McpServiceClient::connect(format!("http://{}", addr)).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_grpc_lists_and_calls_tools() {
+    let server = Arc::new(McpServer::new());
+    server.register_tool(Box::new(FakeTool::new("weather", "sunny"))).await;
+    let mut client = connect(server).await;
+
+    let tools = client.list_tools(ListToolsRequest {}).await.unwrap().into_inner().tools;
+    assert_eq!(tools[0].name, "weather");
+    assert!(tools[0].input_schema.as_ref().unwrap().fields.contains_key("type"));
+
+    let request = CallToolRequest { name: "weather".to_string(), arguments: None };
+    let response = client.call_tool(request.clone()).await.unwrap().into_inner();
+    assert_eq!(response.content[0].block, Some(Block::Text("sunny".to_string())));
+
+    let mut stream = client.stream_tool_call(request).await.unwrap().into_inner();
+    let first = stream.next().await.unwrap().unwrap();
+    assert_eq!(first.block, Some(Block::Text("sunny".to_string())));
+    assert!(stream.next().await.is_none());
+
+    let missing = CallToolRequest { name: "nope".to_string(), arguments: None };
+    let status = client.call_tool(missing).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+    assert!(status.message().contains("Unknown tool: nope"));
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+use serde_json::json;
+use mcp_test_support::{spawn_test_server, FakePlugin, RpcRequest, TestServer};
+
+#[tokio::test]
+async fn test_harness_serves_fake_tools_and_plugins() {
+    let server = spawn_test_server().await;
+
+    let tools = server.request(RpcRequest::new("tools/list")).await;
+    assert_eq!(tools["result"]["tools"][0]["name"], "fake_tool");
+
+    let called = server.request(RpcRequest::tool_call("fake_tool", json!({}))).await;
+    assert_eq!(called["result"]["content"][0]["text"], "fake result");
+
+    let echoed = server
+        .request(RpcRequest::new("plugins/call").params(json!({"name": "fake", "action": "echo", "args": {}})))
+        .await;
+    assert_eq!(echoed["result"]["data"], json!({"echoed": true}));
+
+    let failed = server
+        .request(RpcRequest::new("plugins/call").params(json!({"name": "fake", "action": "fail", "args": {}})))
+        .await;
+    assert_eq!(failed["error"]["data"], json!({"kind": "internal", "detail": "fake failure"}));
+}
+
+#[tokio::test]
+async fn test_fake_plugin_records_calls() {
+    let plugin = Arc::new(FakePlugin::new("weather").with_capability("forecast", json!({"sky": "clear"})));
+    let server = TestServer::start(vec![plugin.clone()], vec![]).await;
+
+    server
+        .request(RpcRequest::new("plugins/call").params(json!({"name": "weather", "action": "forecast", "args": {"city": "Oslo"}})))
+        .await;
+    let calls = plugin.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].0, "forecast");
+    assert_eq!(calls[0].1["city"], "Oslo");
+}
@@ -0,0 +1,207 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use serde_json::{json, Value};
+use mcp_server::mock::{MockScript, MockServer};
+
+/// Serves the mock on a free local port and returns its base URL
+async fn serve(server: Arc<MockServer>) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(server.router().into_make_service()));
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn test_mock_server_answers_over_http() {
+    let server = Arc::new(MockServer::new(MockScript::default()));
+    let url = serve(server.clone()).await;
+    let client = reqwest::Client::new();
+
+    // The bridge's endpoints
+    let tools: Value = client.get(format!("{}/tools/list", url)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(tools["tools"][0]["name"], "echo");
+    assert!(tools["tools"][0]["inputSchema"].is_object());
+
+    let call = json!({
+        "jsonrpc": "2.0", "id": 7, "method": "tools/call",
+        "params": {"name": "echo", "arguments": {"message": "hi"}}
+    });
+    let echoed: Value = client.post(format!("{}/tools/call", url)).json(&call).send().await.unwrap().json().await.unwrap();
+    assert_eq!(echoed["id"], 7);
+    assert_eq!(echoed["result"]["content"][0]["text"], r#"{"message":"hi"}"#);
+
+    // JSON-RPC clients' endpoint, with a session id handed out on initialize
+    let initialize = json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": {"protocolVersion": "2024-11-05", "capabilities": {}, "clientInfo": {"name": "test", "version": "0.1.0"}}
+    });
+    let response = client.post(format!("{}/mcp", url)).json(&initialize).send().await.unwrap();
+    assert!(response.headers().contains_key("mcp-session-id"));
+    let result: Value = response.json().await.unwrap();
+    assert_eq!(result["result"]["protocolVersion"], "2024-11-05");
+
+    let notified = client
+        .post(format!("{}/mcp", url))
+        .json(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(notified.status(), 202);
+
+    let calls: Value = client.get(format!("{}/mock/calls", url)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(calls, json!([{"tool": "echo", "arguments": {"message": "hi"}}]));
+}
+
+#[test]
+fn test_mock_server_binary_speaks_stdio() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("script.json");
+    std::fs::write(&script, json!({
+        "tools": [{"name": "weather", "responses": [{"text": "sunny"}]}]
+    }).to_string()).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mcp-mock-server"))
+        .args(["--stdio", "--script"])
+        .arg(&script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, r#"{{"jsonrpc":"2.0","method":"notifications/initialized"}}"#).unwrap();
+    writeln!(stdin, r#"{{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{{"name":"weather"}}}}"#).unwrap();
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    let lines: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1, "notifications get no response");
+    assert_eq!(lines[0]["result"]["content"][0]["text"], "sunny");
+}
+
+#[test]
+fn test_mock_server_binary_answers_content_length_framing_in_kind() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mcp-mock-server"))
+        .arg("--stdio")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let request = r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+    write!(stdin, "Content-Length: {}\r\n\r\n{}", request.len(), request).unwrap();
+    writeln!(stdin, r#"{{"jsonrpc":"2.0","id":2,"method":"tools/list"}}"#).unwrap();
+    drop(stdin);
+
+    let output = String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap();
+    let (headers, rest) = output.split_once("\r\n\r\n").unwrap();
+    let length: usize = headers.strip_prefix("Content-Length: ").unwrap().parse().unwrap();
+    let framed: Value = serde_json::from_str(&rest[..length]).unwrap();
+    assert_eq!(framed["id"], 1);
+    let line: Value = serde_json::from_str(rest[length..].trim_end_matches('\n')).unwrap();
+    assert_eq!(line["id"], 2);
+}
+
+#[tokio::test]
+async fn test_proxy_plugin_offers_the_mock_binarys_tools() {
+    let server = mcp_server::McpServer::new();
+    let config = mcp_server::plugins::proxy::ProxyConfig {
+        command: env!("CARGO_BIN_EXE_mcp-mock-server").to_string(),
+        args: vec!["--stdio".to_string(), "--log-level".to_string(), "error".to_string()],
+        env: Default::default(),
+        timeout_secs: None,
+    };
+    server.add_proxy_server("mock", config).await.unwrap();
+
+    let list = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}).to_string();
+    let listed: Value = serde_json::from_str(&server.handle_message(&list).await.unwrap()).unwrap();
+    let names: Vec<&str> = listed["result"]["tools"].as_array().unwrap().iter().map(|tool| tool["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["mock.echo", "mock.fail"]);
+
+    let call = json!({
+        "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+        "params": {"name": "mock.echo", "arguments": {"message": "hi"}}
+    });
+    let echoed: Value = serde_json::from_str(&server.handle_message(&call.to_string()).await.unwrap()).unwrap();
+    assert_eq!(echoed["result"]["content"][0]["text"], r#"{"message":"hi"}"#);
+
+    let call = json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {"name": "mock.fail"}});
+    let failed: Value = serde_json::from_str(&server.handle_message(&call.to_string()).await.unwrap()).unwrap();
+    assert_eq!(failed["result"]["isError"], true);
+
+    // The child's refusals are passed on as it sent them
+    let call = json!({"jsonrpc": "2.0", "id": 4, "method": "plugins/call", "params": {"name": "mock", "action": "nope", "args": {}}});
+    let refused: Value = serde_json::from_str(&server.handle_message(&call.to_string()).await.unwrap()).unwrap();
+    assert_eq!(refused["error"]["code"], -32602);
+    assert_eq!(refused["error"]["data"], json!({"kind": "notFound", "detail": "Unknown tool: nope"}));
+}
+
+#[tokio::test]
+async fn test_proxy_plugin_keeps_structured_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("script.json");
+    let tools = json!({"tools": [{"name": "forecast", "responses": [{"structured": {"sky": "clear", "high": 21}}]}]});
+    std::fs::write(&script, tools.to_string()).unwrap();
+
+    let server = mcp_server::McpServer::new();
+    let config = mcp_server::plugins::proxy::ProxyConfig {
+        command: env!("CARGO_BIN_EXE_mcp-mock-server").to_string(),
+        args: vec!["--stdio".to_string(), "--script".to_string(), script.display().to_string()],
+        env: Default::default(),
+        timeout_secs: None,
+    };
+    server.add_proxy_server("weather", config).await.unwrap();
+
+    let call = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"name": "weather.forecast"}});
+    let result: Value = serde_json::from_str(&server.handle_message(&call.to_string()).await.unwrap()).unwrap();
+    assert_eq!(result["result"]["structuredContent"], json!({"sky": "clear", "high": 21}));
+    assert_eq!(result["result"]["content"][0]["text"], r#"{"high":21,"sky":"clear"}"#);
+}
+
+#[tokio::test]
+async fn test_proxy_plugin_times_out_and_starts_a_dead_child_again() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("script.json");
+    let tools = json!({"tools": [
+        {"name": "slow", "responses": [{"text": "late", "delayMs": 1500}]},
+        {"name": "quick", "responses": [{"text": "ok"}]}
+    ]});
+    std::fs::write(&script, tools.to_string()).unwrap();
+    // The first child closes its output after the handshake but keeps
+    // running; later ones keep answering
+    let started = dir.path().join("started");
+    let mock = format!("{} --stdio --script {}", env!("CARGO_BIN_EXE_mcp-mock-server"), script.display());
+    let command = format!(
+        "if [ -e {started} ]; then exec {mock}; else touch {started}; \
+         for _ in 1 2 3; do IFS= read -r line && printf '%s\\n' \"$line\"; done | {mock}; exec >&-; sleep 30; fi",
+        started = started.display(),
+        mock = mock
+    );
+
+    let server = mcp_server::McpServer::new();
+    let config = mcp_server::plugins::proxy::ProxyConfig {
+        command: "sh".to_string(),
+        args: vec!["-c".to_string(), command],
+        env: Default::default(),
+        timeout_secs: Some(1),
+    };
+    server.add_proxy_server("flaky", config).await.unwrap();
+    let call = |id: u64, name: &str| json!({"jsonrpc": "2.0", "id": id, "method": "tools/call", "params": {"name": name}}).to_string();
+
+    let failed: Value = serde_json::from_str(&server.handle_message(&call(1, "flaky.quick")).await.unwrap()).unwrap();
+    assert_eq!(failed["result"]["isError"], true, "{}", failed);
+    let answered: Value = serde_json::from_str(&server.handle_message(&call(2, "flaky.quick")).await.unwrap()).unwrap();
+    assert_eq!(answered["result"]["content"][0]["text"], "ok", "{}", answered);
+
+    let timed_out: Value = serde_json::from_str(&server.handle_message(&call(3, "flaky.slow")).await.unwrap()).unwrap();
+    assert!(timed_out["result"]["content"][0]["text"].as_str().unwrap().contains("no answer within"), "{}", timed_out);
+    // The late answer is skipped rather than taken for the next call's
+    let answered: Value = serde_json::from_str(&server.handle_message(&call(4, "flaky.quick")).await.unwrap()).unwrap();
+    assert_eq!(answered["result"]["content"][0]["text"], "ok", "{}", answered);
+}
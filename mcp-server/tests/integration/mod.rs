@@ -1,4 +1,9 @@
 mod server_tests;
+mod mock_tests;
+mod harness_tests;
+mod conformance_tests;
+#[cfg(feature = "grpc")]
+mod grpc_tests;
 
 // Re-export test modules for easier access
 pub use server_tests::*;
\ No newline at end of file
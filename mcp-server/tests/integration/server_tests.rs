@@ -1,6 +1,8 @@
 use std::sync::Arc;
-use serde_json::json;
+use serde_json::{json, Value};
 use mcp_server::mcp::{McpServer, JsonRpcRequest, JsonRpcResponse};
+use mcp_server::mcp::logs::LogFilter;
+use mcp_server::mcp::jobs::JobStore;
 
 #[tokio::test]
 async fn test_mcp_server_initialization() {
@@ -89,8 +91,7 @@ async fn test_unknown_method_request() {
             
             if let Some(error) = response.error {
                 // Accept various error codes for unknown method
-                // -32002 is also a valid error code for implementation-specific errors
-                assert!(error.code == -32601 || error.code == -32002);
+                assert_eq!(error.code, -32601);
             }
         }
         Err(_) => {
@@ -189,4 +190,1544 @@ fn test_json_rpc_response_structure() {
     let serialized = serde_json::to_string(&error_response).unwrap();
     assert!(serialized.contains("error"));
     assert!(!serialized.contains("result")); // Should be omitted when None
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_initialize_negotiates_protocol_version() {
+    let server = McpServer::new();
+
+    let initialize = |version: &str| serde_json::to_string(&JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: Some(json!(1)),
+        method: "initialize".to_string(),
+        params: Some(json!({
+            "protocolVersion": version,
+            "capabilities": {},
+            "clientInfo": {"name": "test", "version": "0.1.0"}
+        })),
+    }).unwrap();
+
+    // A supported version is echoed back
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&initialize("2024-11-05")).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.result.unwrap()["protocolVersion"], "2024-11-05");
+
+    // An unknown version gets the server's newest; the second handshake
+    // starts a session of its own
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&initialize("1999-01-01")).await.unwrap()
+    ).unwrap();
+    assert!(response.error.is_none());
+    assert_eq!(
+        response.result.unwrap()["protocolVersion"],
+        mcp_server::mcp::SUPPORTED_PROTOCOL_VERSIONS[0]
+    );
+}
+
+fn initialize_request() -> String {
+    serde_json::to_string(&JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: Some(json!(1)),
+        method: "initialize".to_string(),
+        params: Some(json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "test", "version": "0.1.0"}
+        })),
+    }).unwrap()
+}
+
+fn request(method: &str, params: Option<Value>) -> String {
+    serde_json::to_string(&JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: Some(json!(2)),
+        method: method.to_string(),
+        params,
+    }).unwrap()
+}
+
+const INITIALIZED: &str = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+
+/// Runs the initialize handshake and returns the new session's id
+async fn start_session(server: &McpServer) -> String {
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&initialize_request()).await.unwrap()
+    ).unwrap();
+    let session = response.result.unwrap()["sessionId"].as_str().unwrap().to_string();
+    let reply = server.handle_session_message(Some(&session), INITIALIZED).await.unwrap();
+    assert!(reply.is_empty(), "notifications get no response");
+    session
+}
+
+#[tokio::test]
+async fn test_session_lifecycle() {
+    let server = McpServer::new();
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&initialize_request()).await.unwrap()
+    ).unwrap();
+    let session = response.result.unwrap()["sessionId"].as_str().unwrap().to_string();
+
+    // Only ping is served before the initialized notification
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("tools/list", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32003);
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("ping", None)).await.unwrap()
+    ).unwrap();
+    assert!(response.error.is_none());
+
+    server.handle_session_message(Some(&session), INITIALIZED).await.unwrap();
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("tools/list", None)).await.unwrap()
+    ).unwrap();
+    assert!(response.error.is_none());
+
+    // Shutdown answers, then ends the session
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("shutdown", None)).await.unwrap()
+    ).unwrap();
+    assert!(response.error.is_none());
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("ping", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32001);
+}
+
+#[tokio::test]
+async fn test_sessions_do_not_share_lifecycle() {
+    let server = McpServer::new();
+    let ready = start_session(&server).await;
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&initialize_request()).await.unwrap()
+    ).unwrap();
+    let pending = response.result.unwrap()["sessionId"].as_str().unwrap().to_string();
+
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&ready), &request("tools/list", None)).await.unwrap()
+    ).unwrap();
+    assert!(response.error.is_none());
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&pending), &request("tools/list", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32003);
+}
+
+#[tokio::test]
+async fn test_session_rejects_second_initialize() {
+    let server = McpServer::new();
+    let session = start_session(&server).await;
+
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &initialize_request()).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32600);
+}
+
+#[tokio::test]
+async fn test_sessions_list_and_end() {
+    let server = McpServer::new();
+    let first = start_session(&server).await;
+    let second = start_session(&server).await;
+    assert_ne!(first, second);
+
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&first), &request("sessions/list", None)).await.unwrap()
+    ).unwrap();
+    let sessions = response.result.unwrap()["sessions"].as_array().unwrap().clone();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["id"], first.as_str());
+    assert_eq!(sessions[0]["clientInfo"]["name"], "test");
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("sessions/list", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.result.unwrap()["sessions"], json!([]));
+
+    // Other sessions can only be ended on the admin API
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&first), &request("sessions/end", Some(json!({"sessionId": second})))).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32602);
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("sessions/end", Some(json!({"sessionId": second})))).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32602);
+    assert_eq!(server.list_sessions().await.len(), 2);
+
+    // Ending the caller's own session
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&first), &request("sessions/end", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.result.unwrap()["ended"], first.as_str());
+
+    // Requests in an ended session are rejected
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&first), &request("tools/list", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32001);
+}
+
+#[tokio::test]
+async fn test_per_client_tenants_need_a_known_token_and_refuse_raw_cypher() {
+    let server = McpServer::new().with_tenant_per_client();
+    let initialize = initialize_request();
+    let handling = server.handle_message(&initialize);
+    let response: JsonRpcResponse = serde_json::from_str(
+        &mcp_server::mcp::session::with_credential(Some("not-a-tenant-token".to_string()), handling).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32600);
+
+    // Without a token the session shares the default tenant, which raw
+    // Cypher would still read past
+    let session = start_session(&server).await;
+    let query = json!({"query": "MATCH (n) RETURN n"});
+    for (call, code) in [
+        (request("tools/call", Some(json!({"name": "neo4j_query", "arguments": query}))), -32602),
+        (request("plugins/call", Some(json!({"name": "neo4j", "action": "query", "args": query}))), -32601),
+    ] {
+        let response: JsonRpcResponse =
+            serde_json::from_str(&server.handle_session_message(Some(&session), &call).await.unwrap()).unwrap();
+        let error = response.error.unwrap();
+        assert_eq!(error.code, code);
+        assert!(error.to_string().contains("disabled while tenants are per client"));
+    }
+}
+
+#[tokio::test]
+async fn test_session_rate_limit() {
+    let server = McpServer::new().with_session_rate_limit(1);
+    let session = start_session(&server).await;
+
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("tools/list", None)).await.unwrap()
+    ).unwrap();
+    assert!(response.error.is_none());
+
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("tools/list", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32005);
+}
+
+#[tokio::test]
+async fn test_session_budget_refuses_tool_calls() {
+    let server = McpServer::new().with_session_budget("calls=1".parse().unwrap());
+    let session = start_session(&server).await;
+    let call = request("tools/call", Some(json!({"name": "system_info", "arguments": {}})));
+
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_session_message(Some(&session), &call).await.unwrap()).unwrap();
+    assert!(response.error.is_none());
+
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_session_message(Some(&session), &call).await.unwrap()).unwrap();
+    let error = response.error.unwrap();
+    assert_eq!(error.code, -32006);
+    assert_eq!(error.data.unwrap(), json!({"kind": "budgetExceeded", "budget": "toolCalls", "limit": 1, "used": 1}));
+
+    // Calls outside a session are not budgeted
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&call).await.unwrap()).unwrap();
+    assert!(response.error.is_none());
+}
+
+#[tokio::test]
+async fn test_tool_rate_limit_is_shared_by_sessions_and_says_when_to_retry() {
+    let server = McpServer::new().with_tool_rate_limits("echo=2/min".parse().unwrap());
+    server.register_tool(Box::new(Echo)).await;
+    let call = request("tools/call", Some(json!({"name": "echo", "arguments": {"n": 1}})));
+    let first = start_session(&server).await;
+    let second = start_session(&server).await;
+
+    for session in [&first, &second] {
+        let response: JsonRpcResponse =
+            serde_json::from_str(&server.handle_session_message(Some(session), &call).await.unwrap()).unwrap();
+        assert!(response.error.is_none());
+    }
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&call).await.unwrap()).unwrap();
+    let error = response.error.unwrap();
+    assert_eq!(error.code, -32005);
+    assert_eq!(error.data.unwrap(), json!({"kind": "rateLimited", "name": "echo", "limit": 2, "periodSecs": 60, "retryAfter": 30}));
+}
+
+#[tokio::test]
+async fn test_oversized_deep_and_too_frequent_messages_are_refused() {
+    let server = McpServer::new().with_message_limits("bytes=256,depth=4,rate=2".parse().unwrap());
+    let session = start_session(&server).await;
+
+    let large = request("ping", Some(json!({"pad": "x".repeat(300)})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_session_message(Some(&session), &large).await.unwrap()).unwrap();
+    let error = response.error.unwrap();
+    assert_eq!(error.code, -32600);
+    assert_eq!(error.data.unwrap(), json!({"kind": "invalidRequest", "limit": "bytes", "max": 256}));
+
+    let deep = request("ping", Some(json!({"a": {"b": [[{}]]}})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_session_message(Some(&session), &deep).await.unwrap()).unwrap();
+    assert_eq!(response.error.unwrap().data.unwrap(), json!({"kind": "invalidRequest", "limit": "depth", "max": 4}));
+
+    // Each connection gets its own rate
+    let ping = request("ping", None);
+    for connection in ["one", "one", "two"] {
+        let response: JsonRpcResponse =
+            serde_json::from_str(&server.handle_connection_message(connection, Some(&session), &ping).await.unwrap()).unwrap();
+        assert!(response.error.is_none());
+    }
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_connection_message("one", Some(&session), &ping).await.unwrap()).unwrap();
+    assert_eq!(response.id, Some(json!(2)));
+    let error = response.error.unwrap();
+    assert_eq!(error.code, -32005);
+    assert_eq!(error.data.unwrap(), json!({"kind": "rateLimited", "limit": "rate", "max": 2, "retryAfter": 1}));
+    assert!(server.handle_connection_message("one", Some(&session), INITIALIZED).await.unwrap().is_empty());
+
+    // A batch takes a token per message, and is refused whole when too few are left
+    let batch = format!("[{},{}]", request("ping", None), request("ping", None));
+    let response = server.handle_connection_message("three", Some(&session), &batch).await.unwrap();
+    assert_eq!(serde_json::from_str::<Vec<JsonRpcResponse>>(&response).unwrap().len(), 2);
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_connection_message("three", Some(&session), &ping).await.unwrap()).unwrap();
+    assert_eq!(response.error.unwrap().code, -32005);
+    let batch = format!("[{},{},{}]", ping, ping, ping);
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_connection_message("four", Some(&session), &batch).await.unwrap()).unwrap();
+    assert_eq!(response.error.unwrap().code, -32005);
+}
+
+#[tokio::test]
+async fn test_batches_over_the_size_limit_are_refused() {
+    let server = McpServer::new().with_message_limits("batch=2".parse().unwrap());
+    let session = start_session(&server).await;
+    let ping = request("ping", None);
+    let batch = format!("[{},{},{}]", ping, ping, ping);
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_session_message(Some(&session), &batch).await.unwrap()).unwrap();
+    let error = response.error.unwrap();
+    assert_eq!(error.code, -32600);
+    assert_eq!(error.data.unwrap(), json!({"kind": "invalidRequest", "limit": "batch", "max": 2}));
+}
+
+#[tokio::test]
+async fn test_batches_get_a_response_per_request() {
+    let server = McpServer::new();
+    let session = start_session(&server).await;
+
+    let batch = json!([
+        {"jsonrpc": "2.0", "id": 1, "method": "ping"},
+        {"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 9}},
+        {"jsonrpc": "2.0", "id": "two", "method": "no/such/method"},
+        42
+    ]);
+    let response = server.handle_session_message(Some(&session), &batch.to_string()).await.unwrap();
+    let responses: Vec<JsonRpcResponse> = serde_json::from_str(&response).unwrap();
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0].id, Some(json!(1)));
+    assert!(responses[0].error.is_none());
+    assert_eq!(responses[1].id, Some(json!("two")));
+    assert_eq!(responses[1].error.as_ref().unwrap().code, -32601);
+    assert_eq!(responses[2].error.as_ref().unwrap().code, -32600);
+
+    // Only notifications: nothing to answer
+    let notifications = json!([{"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 9}}]);
+    assert!(server.handle_session_message(Some(&session), &notifications.to_string()).await.unwrap().is_empty());
+
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_session_message(Some(&session), "[]").await.unwrap()).unwrap();
+    assert_eq!(response.error.unwrap().code, -32600);
+
+    // Elements that are not objects each get an error
+    for (batch, count) in [("[1]", 1), ("[1,2]", 2)] {
+        let responses: Vec<JsonRpcResponse> =
+            serde_json::from_str(&server.handle_session_message(Some(&session), batch).await.unwrap()).unwrap();
+        assert_eq!(responses.len(), count);
+        assert!(responses.iter().all(|response| response.error.as_ref().unwrap().code == -32600));
+    }
+}
+
+#[tokio::test]
+async fn test_plugin_reload_notifies_subscribed_sessions() {
+    let server = McpServer::new();
+    let session = start_session(&server).await;
+    let mut notifications = server.subscribe_notifications(&session).await.unwrap();
+    assert!(server.subscribe_notifications("unknown").await.is_none());
+
+    // Clients cannot reload plugins; operators do it on the admin API
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("plugins/reload", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32600);
+    assert!(notifications.try_recv().is_err());
+
+    server.reload_plugins().await.unwrap();
+    let notification: Value = serde_json::from_str(&notifications.try_recv().unwrap()).unwrap();
+    assert_eq!(notification["method"], "notifications/tools/list_changed");
+}
+
+#[tokio::test]
+async fn test_logging_set_level() {
+    let server = McpServer::new();
+    let session = start_session(&server).await;
+
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("logging/setLevel", Some(json!({"level": "warning"})))).await.unwrap()
+    ).unwrap();
+    assert!(response.error.is_none());
+
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("logging/setLevel", Some(json!({"level": "verbose"})))).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32602);
+}
+
+#[tokio::test]
+async fn test_only_operators_change_the_server_log_filter() {
+    use mcp_server::mcp::notifications::LogLevel;
+    let target = "mcp_server::plugins::home_assistant";
+
+    // Without a filter to change, as when the server is embedded
+    assert!(McpServer::new().set_log_target_level(target, LogLevel::Debug).is_err());
+
+    // The layer has to stay alive for the filter to be reloaded
+    let (_layer, filter) = LogFilter::new("info");
+    let server = McpServer::new().with_log_filter(filter);
+    assert_eq!(
+        server.set_log_target_level(target, LogLevel::Debug).unwrap(),
+        "info,mcp_server::plugins::home_assistant=debug"
+    );
+    assert!(server.set_log_target_level("a=b", LogLevel::Info).is_err());
+
+    // Clients only set the level of their own session's log messages
+    let session = start_session(&server).await;
+    let params = json!({"level": "trace", "target": "mcp_server"});
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&session), &request("logging/setLevel", Some(params))).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32602);
+}
+
+#[tokio::test]
+async fn test_client_responses_get_no_reply() {
+    let server = McpServer::new();
+    let session = start_session(&server).await;
+
+    // A response to a request the server never sent is dropped silently
+    let reply = server.handle_session_message(
+        Some(&session),
+        r#"{"jsonrpc":"2.0","id":"sampling-unknown","result":{"role":"assistant"}}"#,
+    ).await.unwrap();
+    assert!(reply.is_empty());
+}
+
+#[tokio::test]
+async fn test_notifications_get_no_reply_and_keepalive_pings_reach_the_client() {
+    let server = Arc::new(McpServer::new());
+    let session = start_session(&server).await;
+    for notification in [
+        r#"{"jsonrpc":"2.0","method":"notifications/cancelled","params":{"requestId":7}}"#,
+        r#"{"jsonrpc":"2.0","method":"no/such/notification"}"#,
+        "  ",
+    ] {
+        assert!(server.handle_session_message(Some(&session), notification).await.unwrap().is_empty());
+    }
+
+    let mut stream = server.subscribe_notifications(&session).await.unwrap();
+    let ping = {
+        let (server, session) = (server.clone(), session.clone());
+        tokio::spawn(async move { server.ping_client(&session).await })
+    };
+    let asked: Value = serde_json::from_str(&stream.recv().await.unwrap()).unwrap();
+    assert_eq!(asked["method"], "ping");
+    let pong = json!({"jsonrpc": "2.0", "id": asked["id"], "result": {}});
+    assert!(server.handle_session_message(Some(&session), &pong.to_string()).await.unwrap().is_empty());
+    assert!(ping.await.unwrap().unwrap().is_ok());
+
+    server.end_session(&session).await;
+    assert!(server.ping_client(&session).await.is_none());
+}
+
+/// Starts a ready session that asked for `version`, returning its id and
+/// the advertised capabilities
+async fn start_session_with_version(server: &McpServer, version: &str) -> (String, Value) {
+    let request = serde_json::to_string(&JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: Some(json!(1)),
+        method: "initialize".to_string(),
+        params: Some(json!({
+            "protocolVersion": version,
+            "capabilities": {},
+            "clientInfo": {"name": "test", "version": "0.1.0"}
+        })),
+    }).unwrap();
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&request).await.unwrap()).unwrap();
+    let result = response.result.unwrap();
+    let session = result["sessionId"].as_str().unwrap().to_string();
+    server.handle_session_message(Some(&session), INITIALIZED).await.unwrap();
+    (session, result)
+}
+
+#[tokio::test]
+async fn test_features_follow_negotiated_version() {
+    let server = McpServer::new();
+
+    let (old, result) = start_session_with_version(&server, "2024-11-05").await;
+    assert!(result["capabilities"].get("prompts").is_none());
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&old), &request("prompts/list", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32601);
+
+    // A newer client gets our newest version and its features
+    let (new, result) = start_session_with_version(&server, "2025-06-18").await;
+    assert_eq!(result["protocolVersion"], "2025-03-26");
+    assert!(result["capabilities"]["prompts"].is_object());
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&new), &request(
+            "prompts/get",
+            Some(json!({"name": "system_health_report", "arguments": {"focus": "memory"}})),
+        )).await.unwrap()
+    ).unwrap();
+    let text = response.result.unwrap()["messages"][0]["content"]["text"].as_str().unwrap().to_string();
+    assert!(text.contains("memory"));
+
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_session_message(Some(&new), &request("resources/list", None)).await.unwrap()
+    ).unwrap();
+    assert!(!response.result.unwrap()["resources"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_initialize_requires_valid_params() {
+    let server = McpServer::new();
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("initialize", Some(json!({"protocolVersion": "2024-11-05"})))).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().code, -32602);
+}
+
+#[tokio::test]
+async fn test_roots_are_asked_from_the_client_and_narrow_server_roots() {
+    let server_root = tempfile::tempdir().unwrap();
+    let project = server_root.path().canonicalize().unwrap().join("project");
+    std::fs::create_dir(&project).unwrap();
+    std::env::set_var("MCP_FS_ROOTS", server_root.path());
+
+    let server = Arc::new(McpServer::new());
+    let request_json = serde_json::to_string(&JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: Some(json!(1)),
+        method: "initialize".to_string(),
+        params: Some(json!({
+            "protocolVersion": "2025-03-26",
+            "capabilities": {"roots": {"listChanged": true}},
+            "clientInfo": {"name": "test", "version": "0.1.0"}
+        })),
+    }).unwrap();
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&request_json).await.unwrap()).unwrap();
+    let session = response.result.unwrap()["sessionId"].as_str().unwrap().to_string();
+    server.handle_session_message(Some(&session), INITIALIZED).await.unwrap();
+    let mut stream = server.subscribe_notifications(&session).await.unwrap();
+
+    let roots_list = |server: Arc<McpServer>, session: String| tokio::spawn(async move {
+        let response: JsonRpcResponse = serde_json::from_str(
+            &server.handle_session_message(Some(&session), &request("roots/list", None)).await.unwrap()
+        ).unwrap();
+        response.result.unwrap()["roots"].clone()
+    });
+    // Plays the client: answers the server's roots/list request with `roots`
+    async fn answer(server: &McpServer, session: &str, asked: String, roots: Value) {
+        let asked: Value = serde_json::from_str(&asked).unwrap();
+        assert_eq!(asked["method"], "roots/list");
+        let reply = json!({"jsonrpc": "2.0", "id": asked["id"], "result": {"roots": roots}});
+        assert!(server.handle_session_message(Some(session), &reply.to_string()).await.unwrap().is_empty());
+    }
+
+    let pending = roots_list(server.clone(), session.clone());
+    let project_uri = format!("file://{}", project.display());
+    answer(&server, &session, stream.recv().await.unwrap(), json!([{"uri": project_uri}])).await;
+    let roots = pending.await.unwrap();
+    assert_eq!(roots.as_array().unwrap().len(), 1);
+    assert_eq!(roots[0]["uri"], project_uri);
+
+    // Cached until the client reports a change
+    let roots = roots_list(server.clone(), session.clone()).await.unwrap();
+    assert_eq!(roots[0]["uri"], project_uri);
+    assert!(stream.try_recv().is_err());
+
+    // Speech and vision touch files too, so they are held to the client's narrower roots
+    let speech = mcp_server::plugins::speech::SpeechPlugin::new();
+    server.register_plugin(Arc::new(speech)).await.unwrap();
+    let home_assistant = Arc::new(mcp_server::plugins::home_assistant::HomeAssistantPlugin::new());
+    server.register_plugin(Arc::new(mcp_server::plugins::vision::VisionPlugin::new(home_assistant))).await.unwrap();
+    std::fs::write(server_root.path().join("photo.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+    for (name, arguments) in [
+        ("speech", json!({"action": "speak", "text": "Hello", "output_path": server_root.path().join("hello.wav")})),
+        ("analyze_image", json!({"image_path": server_root.path().join("photo.png")})),
+    ] {
+        let call = request("tools/call", Some(json!({"name": name, "arguments": arguments})));
+        let reply = server.handle_session_message(Some(&session), &call).await.unwrap();
+        assert!(reply.contains("outside the allowed roots"), "{}", reply);
+    }
+
+    server.handle_session_message(
+        Some(&session),
+        r#"{"jsonrpc":"2.0","method":"notifications/roots/list_changed"}"#,
+    ).await.unwrap();
+    let pending = roots_list(server.clone(), session.clone());
+    answer(&server, &session, stream.recv().await.unwrap(), json!([{"uri": "file:///somewhere/else"}])).await;
+    assert_eq!(pending.await.unwrap(), json!([]));
+}
+
+#[tokio::test]
+async fn test_logs_tail_returns_tagged_lines() {
+    use mcp_server::mcp::logs::LogCaptureLayer;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let server = McpServer::new();
+    let subscriber = tracing_subscriber::registry().with(LogCaptureLayer::new(server.logs()));
+    let _guard = tracing::subscriber::set_default(subscriber);
+    let session = start_session(&server).await;
+    let other = start_session(&server).await;
+
+    let list_tools = json!({"jsonrpc": "2.0", "id": "list-1", "method": "tools/list"});
+    server.handle_session_message(Some(&session), &list_tools.to_string()).await.unwrap();
+    server.handle_session_message(Some(&other), &list_tools.to_string()).await.unwrap();
+
+    let tail = request("logs/tail", Some(json!({"requestId": "list-1"})));
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_session_message(Some(&session), &tail).await.unwrap()).unwrap();
+    let entries = response.result.unwrap()["entries"].clone();
+    let entries = entries.as_array().unwrap();
+    assert!(!entries.is_empty());
+    assert!(entries.iter().all(|entry| entry["requestId"] == "list-1" && entry["sessionId"] == session.as_str()));
+    assert!(entries.iter().any(|entry| entry["message"] == "Handling tools/list request"));
+
+    // Other sessions' lines, and those of no session, are for operators only
+    for (caller, params) in [
+        (Some(session.as_str()), json!({"sessionId": other})),
+        (None, json!({})),
+    ] {
+        let tail = request("logs/tail", Some(params));
+        let response: JsonRpcResponse =
+            serde_json::from_str(&server.handle_session_message(caller, &tail).await.unwrap()).unwrap();
+        assert_eq!(response.error.unwrap().code, -32602);
+    }
+
+    let invalid = request("logs/tail", Some(json!({"level": "loud"})));
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_session_message(Some(&session), &invalid).await.unwrap()).unwrap();
+    assert_eq!(response.error.unwrap().code, -32602);
+}
+
+#[tokio::test]
+async fn test_plugin_events_reach_subscribers_and_events_tail() {
+    use mcp_server::plugins::notification::NotificationPlugin;
+
+    let server = McpServer::new();
+    server.register_plugin(Arc::new(NotificationPlugin::with_webhook(None))).await.unwrap();
+    let mut subscription = server.events().subscribe("notification.*");
+
+    let call = request("plugins/call", Some(json!({
+        "name": "notification",
+        "action": "send_notification",
+        "args": {"title": "Door open", "message": "Front door", "severity": "warning"}
+    })));
+    server.handle_message(&call).await.unwrap();
+    server.events().publish("scheduler", "pipeline.started", json!({"pipeline": "nightly"}));
+
+    let event = subscription.recv().await.unwrap();
+    assert_eq!(event.source, "notification");
+    assert_eq!(event.topic, "notification.warning");
+    assert_eq!(event.data["title"], "Door open");
+
+    let tail = request("events/tail", Some(json!({"topic": "pipeline.*"})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&tail).await.unwrap()).unwrap();
+    let events = response.result.unwrap()["events"].clone();
+    assert_eq!(events.as_array().unwrap().len(), 1);
+    assert_eq!(events[0]["source"], "scheduler");
+    assert_eq!(events[0]["data"]["pipeline"], "nightly");
+}
+
+#[tokio::test]
+async fn test_stats_tools_counts_calls_and_errors() {
+    // Nothing is registered, so every call fails
+    let server = McpServer::new();
+    let call = |id: u32, tool: &str| json!({
+        "jsonrpc": "2.0", "id": id, "method": "tools/call",
+        "params": {"name": tool, "arguments": {}}
+    }).to_string();
+    server.handle_message(&call(1, "system_info")).await.unwrap();
+    server.handle_message(&call(2, "system_info")).await.unwrap();
+    // Unknown tools are refused before they are called
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&call(3, "http")).await.unwrap()).unwrap();
+    assert_eq!(response.error.unwrap().data.unwrap()["kind"], "notFound");
+
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_message(&request("stats/tools", None)).await.unwrap()).unwrap();
+    let tools = response.result.unwrap()["tools"].clone();
+    assert_eq!(tools.as_array().unwrap().len(), 1);
+    assert_eq!(tools[0]["tool"], "system_info");
+    assert_eq!(tools[0]["calls"], 2);
+    assert_eq!(tools[0]["errors"], 2);
+    assert_eq!(tools[0]["errorRate"], 1.0);
+    assert!(tools[0]["averageLatencyMs"].as_f64().is_some());
+}
+
+#[tokio::test]
+async fn test_journal_replays_against_a_new_server() {
+    use mcp_server::mcp::journal::{self, Journal};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("journal.jsonl");
+    let recording = McpServer::new().with_journal(Journal::open(&path).unwrap());
+    let session = start_session(&recording).await;
+    recording.handle_session_message(Some(&session), &request("ping", None)).await.unwrap();
+    recording.handle_session_message(Some(&session), &request("tools/list", None)).await.unwrap();
+
+    // Sessions started during replay stand in for the recorded ones
+    let report = journal::replay(&McpServer::new(), &path).await.unwrap();
+    assert_eq!(report.replayed, 4);
+    assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+
+    // A response that no longer matches is reported with its line
+    let changed = json!({
+        "timestamp": "2025-01-01T00:00:00Z",
+        "request": {"jsonrpc": "2.0", "id": 9, "method": "ping"},
+        "response": {"jsonrpc": "2.0", "id": 9, "result": {"pong": true}}
+    });
+    let mut journal = std::fs::read_to_string(&path).unwrap();
+    journal.push_str(&format!("{}\n", changed));
+    std::fs::write(&path, journal).unwrap();
+
+    let report = journal::replay(&McpServer::new(), &path).await.unwrap();
+    assert_eq!(report.mismatches.len(), 1);
+    assert_eq!(report.mismatches[0].line, 5);
+    assert_eq!(report.mismatches[0].method, "ping");
+    assert_eq!(report.mismatches[0].replayed.as_ref().unwrap()["result"], json!({}));
+}
+
+#[tokio::test]
+async fn test_fault_injection_fails_plugin_executions() {
+    let server = McpServer::new().with_fault_injection("fail=100".parse().unwrap());
+
+    let params = json!({"name": "system_info", "arguments": {}});
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("tools/call", Some(params))).await.unwrap()
+    ).unwrap();
+    let result = response.result.expect("an isError result");
+    assert_eq!(result["isError"], true);
+    assert_eq!(result["_meta"]["faultInjection"], json!({"kind": "failure"}));
+}
+
+struct Greeter;
+
+#[async_trait::async_trait]
+impl mcp_server::tools::Tool for Greeter {
+    fn name(&self) -> &str {
+        "greet"
+    }
+    fn description(&self) -> &str {
+        "Says hello"
+    }
+    fn input_schema(&self) -> Value {
+        json!({"type": "object"})
+    }
+    async fn call(&self, _args: std::collections::HashMap<String, Value>) -> anyhow::Result<Vec<mcp_server::mcp::ContentBlock>> {
+        Ok(vec![mcp_server::mcp::ContentBlock::text("hello")])
+    }
+}
+
+#[tokio::test]
+async fn test_namespaced_tools_answer_to_aliases() {
+    let server = McpServer::new();
+    server.register_tool_in("demo", Box::new(Greeter)).await;
+    server.add_tool_alias("greet", "demo.greet", true).await;
+
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_message(&request("tools/list", None)).await.unwrap()).unwrap();
+    let tools = response.result.unwrap()["tools"].clone();
+    assert_eq!(tools, json!([{"name": "demo.greet", "description": "Says hello", "inputSchema": {"type": "object"}}]));
+
+    let call = request("tools/call", Some(json!({"name": "greet", "arguments": {}})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&call).await.unwrap()).unwrap();
+    assert_eq!(response.result.unwrap()["content"][0]["text"], "hello");
+
+    let stats: JsonRpcResponse =
+        serde_json::from_str(&server.handle_message(&request("stats/tools", None)).await.unwrap()).unwrap();
+    assert_eq!(stats.result.unwrap()["tools"][0]["tool"], "demo.greet");
+}
+
+#[tokio::test]
+async fn test_virtual_tools_wrap_registered_tools() {
+    let server = McpServer::new();
+    server.register_tool(Box::new(Greeter)).await;
+    server
+        .add_virtual_tool(serde_json::from_value(json!({"name": "greet_ada", "tool": "greet", "arguments": {"who": "ada"}})).unwrap())
+        .await;
+
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_message(&request("tools/list", None)).await.unwrap()).unwrap();
+    let tools = response.result.unwrap()["tools"].clone();
+    assert_eq!(tools[1], json!({"name": "greet_ada", "description": "", "inputSchema": {"type": "object", "properties": {}}}));
+
+    let call = request("tools/call", Some(json!({"name": "greet_ada", "arguments": {}})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&call).await.unwrap()).unwrap();
+    assert_eq!(response.result.unwrap()["content"][0]["text"], "hello");
+
+    let call = request("tools/call", Some(json!({"name": "greet_ada", "arguments": {"who": "bob"}})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&call).await.unwrap()).unwrap();
+    assert_eq!(response.error.unwrap().code, -32602);
+}
+
+struct Thermometer;
+
+#[async_trait::async_trait]
+impl mcp_server::tools::Tool for Thermometer {
+    fn name(&self) -> &str {
+        "thermometer"
+    }
+    fn description(&self) -> &str {
+        "Reads the temperature"
+    }
+    fn input_schema(&self) -> Value {
+        json!({"type": "object"})
+    }
+    fn output_schema(&self) -> Option<Value> {
+        Some(json!({"type": "object", "required": ["celsius"], "properties": {"celsius": {"type": "number"}}}))
+    }
+    async fn call(&self, _args: std::collections::HashMap<String, Value>) -> anyhow::Result<Vec<mcp_server::mcp::ContentBlock>> {
+        Ok(mcp_server::mcp::ContentBlock::structured(json!({"celsius": 21.5})))
+    }
+}
+
+#[tokio::test]
+async fn test_tools_list_output_schemas() {
+    let server = McpServer::new();
+    server.register_tool(Box::new(Thermometer)).await;
+    server.add_virtual_tool(serde_json::from_value(json!({"name": "indoor", "tool": "thermometer"})).unwrap()).await;
+
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_message(&request("tools/list", None)).await.unwrap()).unwrap();
+    let tools = response.result.unwrap()["tools"].clone();
+    // Virtual tools return what their target returns
+    assert_eq!(tools[0]["name"], "indoor");
+    assert_eq!(tools[0]["outputSchema"], tools[1]["outputSchema"]);
+    assert_eq!(tools[1]["outputSchema"]["required"], json!(["celsius"]));
+
+    let call = request("tools/call", Some(json!({"name": "indoor", "arguments": {}})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&call).await.unwrap()).unwrap();
+    assert_eq!(response.result.unwrap()["structuredContent"], json!({"celsius": 21.5}));
+}
+
+struct Barometer(&'static str);
+
+#[async_trait::async_trait]
+impl mcp_server::tools::Tool for Barometer {
+    fn name(&self) -> &str {
+        "barometer"
+    }
+    fn description(&self) -> &str {
+        "Reads the air pressure"
+    }
+    fn input_schema(&self) -> Value {
+        json!({"type": "object"})
+    }
+    fn version(&self) -> Option<&str> {
+        Some(self.0)
+    }
+    fn deprecation(&self) -> Option<mcp_server::mcp::Deprecation> {
+        (self.0 == "v1").then(|| mcp_server::mcp::Deprecation {
+            message: "Reports millibars".to_string(),
+            replaced_by: Some("barometer@v2".to_string()),
+        })
+    }
+    async fn call(&self, _args: std::collections::HashMap<String, Value>) -> anyhow::Result<Vec<mcp_server::mcp::ContentBlock>> {
+        Ok(vec![mcp_server::mcp::ContentBlock::text(self.0)])
+    }
+}
+
+#[tokio::test]
+async fn test_deprecated_tool_versions_warn_callers() {
+    let server = McpServer::new();
+    server.register_tool(Box::new(Barometer("v1"))).await;
+    server.register_tool(Box::new(Barometer("v2"))).await;
+
+    let response: JsonRpcResponse =
+        serde_json::from_str(&server.handle_message(&request("tools/list", None)).await.unwrap()).unwrap();
+    let tools = response.result.unwrap()["tools"].clone();
+    assert_eq!(tools[0]["name"], "barometer@v1");
+    assert_eq!(tools[0]["version"], "v1");
+    assert_eq!(tools[0]["deprecated"], json!({"message": "Reports millibars", "replacedBy": "barometer@v2"}));
+    assert_eq!(tools[1]["name"], "barometer@v2");
+    assert!(tools[1].get("deprecated").is_none());
+
+    let call = request("tools/call", Some(json!({"name": "barometer@v1", "arguments": {}})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&call).await.unwrap()).unwrap();
+    let result = response.result.unwrap();
+    assert_eq!(result["content"][0]["text"], "v1");
+    assert_eq!(result["_meta"]["deprecation"]["replacedBy"], "barometer@v2");
+
+    // The bare name calls the latest version
+    let call = request("tools/call", Some(json!({"name": "barometer", "arguments": {}})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&call).await.unwrap()).unwrap();
+    let result = response.result.unwrap();
+    assert_eq!(result["content"][0]["text"], "v2");
+    assert!(result.get("_meta").is_none());
+}
+
+struct Chatty;
+
+#[async_trait::async_trait]
+impl mcp_server::tools::Tool for Chatty {
+    fn name(&self) -> &str {
+        "chatty"
+    }
+    fn description(&self) -> &str {
+        "Says a lot"
+    }
+    fn input_schema(&self) -> Value {
+        json!({"type": "object"})
+    }
+    async fn call(&self, _args: std::collections::HashMap<String, Value>) -> anyhow::Result<Vec<mcp_server::mcp::ContentBlock>> {
+        Ok(vec![mcp_server::mcp::ContentBlock::text(&"blah ".repeat(100))])
+    }
+}
+
+#[tokio::test]
+async fn test_oversized_results_are_truncated() {
+    let server = McpServer::new().with_result_transforms("steps=truncate,max_chars=20".parse().unwrap());
+    server.register_tool(Box::new(Chatty)).await;
+
+    let call = request("tools/call", Some(json!({"name": "chatty", "arguments": {}})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&call).await.unwrap()).unwrap();
+    let result = response.result.unwrap();
+    assert_eq!(result["content"][0]["text"], format!("{}\n[truncated 480 of 500 characters]", "blah ".repeat(4)));
+    assert_eq!(result["_meta"]["resultTransforms"], json!({"steps": ["truncate"], "originalChars": 500, "droppedBlocks": 0}));
+}
+
+#[tokio::test]
+async fn test_jobs_run_in_the_background_and_keep_their_results() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = Arc::new(McpServer::new().with_jobs(JobStore::open(&dir.path().join("jobs.db")).unwrap()));
+    server.register_tool(Box::new(Greeter)).await;
+    server.spawn_job_worker();
+
+    let submit = request("jobs/submit", Some(json!({"name": "greet", "arguments": {"who": "ada"}})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&submit).await.unwrap()).unwrap();
+    let job = response.result.unwrap()["job"].clone();
+    assert_eq!(job["status"], "queued");
+
+    let get = request("jobs/get", Some(json!({"jobId": job["id"]})));
+    let job = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&get).await.unwrap()).unwrap();
+            let job = response.result.unwrap()["job"].clone();
+            if job["status"] == "completed" {
+                return job;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(job["result"]["content"][0]["text"], "hello");
+
+    let list = request("jobs/list", Some(json!({"status": "completed"})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&list).await.unwrap()).unwrap();
+    assert_eq!(response.result.unwrap()["jobs"][0]["id"], job["id"]);
+
+    let get = request("jobs/get", Some(json!({"jobId": "nope"})));
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&get).await.unwrap()).unwrap();
+    assert_eq!(response.error.unwrap().code, -32602);
+}
+
+struct Echo;
+
+#[async_trait::async_trait]
+impl mcp_server::tools::Tool for Echo {
+    fn name(&self) -> &str {
+        "echo"
+    }
+    fn description(&self) -> &str {
+        "Returns its arguments"
+    }
+    fn input_schema(&self) -> Value {
+        json!({"type": "object"})
+    }
+    async fn call(&self, args: std::collections::HashMap<String, Value>) -> anyhow::Result<Vec<mcp_server::mcp::ContentBlock>> {
+        Ok(mcp_server::mcp::ContentBlock::structured(json!(args)))
+    }
+}
+
+#[tokio::test]
+async fn test_automation_rules_run_their_actions_on_events() {
+    use mcp_server::automation::{Rule, RuleEngine};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rules.yaml");
+    std::fs::write(&path, r#"
+rules:
+  - name: greet_visitors
+    trigger:
+      event: { topic: "door.*" }
+    actions:
+      - tool: echo
+        arguments: { who: "${event.data.person}", door: "${event.topic}" }
+      - tool: echo
+        arguments: { message: "Hello ${previous.who} at ${previous.door}" }
+  - name: broken
+    trigger:
+      event: { topic: "door.back" }
+    actions:
+      - tool: missing
+"#).unwrap();
+
+    let server = Arc::new(McpServer::new());
+    server.register_tool(Box::new(Echo)).await;
+    let mut runs = server.events().subscribe("automation.*");
+    RuleEngine::new(server.clone(), Rule::load(&path).unwrap()).spawn();
+
+    server.events().publish("mqtt", "door.front", json!({"person": "ada"}));
+    let completed = tokio::time::timeout(std::time::Duration::from_secs(5), runs.recv()).await.unwrap().unwrap();
+    assert_eq!(completed.topic, "automation.completed");
+    assert_eq!(completed.data["rule"], "greet_visitors");
+    assert_eq!(completed.data["result"]["message"], "Hello ada at door.front");
+
+    server.events().publish("mqtt", "door.back", json!({}));
+    let mut outcomes = Vec::new();
+    for _ in 0..2 {
+        let run = tokio::time::timeout(std::time::Duration::from_secs(5), runs.recv()).await.unwrap().unwrap();
+        outcomes.push((run.data["rule"].as_str().unwrap().to_string(), run.topic));
+    }
+    outcomes.sort();
+    assert_eq!(outcomes, vec![
+        ("broken".to_string(), "automation.failed".to_string()),
+        ("greet_visitors".to_string(), "automation.completed".to_string()),
+    ]);
+}
+
+#[tokio::test]
+async fn test_workflows_persist_steps_across_calls() {
+    let server = McpServer::new();
+    server.register_tool(Box::new(Echo)).await;
+    let call = |method: &str, params: Value| {
+        let server = &server;
+        let message = request(method, Some(params));
+        async move { serde_json::from_str::<JsonRpcResponse>(&server.handle_message(&message).await.unwrap()).unwrap() }
+    };
+
+    let create = json!({"goal": "Greet the team", "steps": [
+        {"description": "Look up who is in", "tool": "echo", "arguments": {"who": "ada"}},
+        {"description": "Write the greeting"}
+    ]});
+    assert_eq!(call("workflows/create", create.clone()).await.error.unwrap().code, -32603);
+
+    server.set_context_store(Arc::new(mcp_test_support::InMemoryContextStore::new()));
+    let created = call("workflows/create", create).await.result.unwrap();
+    let id = created["workflow"]["id"].as_str().unwrap().to_string();
+    assert_eq!(created["workflow"]["status"], "running");
+
+    let ran = call("workflows/advance", json!({"workflowId": id})).await.result.unwrap();
+    assert_eq!(ran["workflow"]["currentStep"], 1);
+    assert_eq!(ran["workflow"]["steps"][0]["status"], "completed");
+    assert_eq!(ran["workflow"]["steps"][0]["result"]["content"][1]["json"]["who"], "ada");
+
+    // The second step has no tool, so the agent reports its outcome
+    assert_eq!(call("workflows/advance", json!({"workflowId": id})).await.error.unwrap().code, -32602);
+    call("workflows/advance", json!({"workflowId": id, "action": "pause"})).await.result.unwrap();
+    let paused = call("workflows/status", json!({"status": "paused"})).await.result.unwrap();
+    assert_eq!(paused["workflows"][0]["id"], id);
+    let error = call("workflows/advance", json!({"workflowId": id, "action": "complete", "result": "Hi"})).await.error;
+    assert!(error.unwrap().data.unwrap()["detail"].as_str().unwrap().contains("paused"));
+
+    call("workflows/advance", json!({"workflowId": id, "action": "resume"})).await.result.unwrap();
+    call("workflows/advance", json!({"workflowId": id, "action": "complete", "result": "Hello ada"})).await.result.unwrap();
+    let status = call("workflows/status", json!({"workflowId": id})).await.result.unwrap();
+    assert_eq!(status["workflow"]["status"], "completed");
+    assert_eq!(status["workflow"]["steps"][1]["result"], "Hello ada");
+    assert_eq!(call("workflows/status", json!({"workflowId": "missing"})).await.error.unwrap().code, -32602);
+}
+
+#[tokio::test]
+async fn test_clients_only_see_context_data_of_their_own_tenant() {
+    use mcp_server::mcp::session::with_credential;
+    use mcp_server::plugins::memory::MemoryPlugin;
+    use mcp_server::plugins::{Context, Plugin};
+
+    let store = Arc::new(mcp_test_support::InMemoryContextStore::new());
+    let tokens = [("token-a", "alpha"), ("token-b", "beta")]
+        .into_iter()
+        .map(|(token, tenant)| (token.to_string(), tenant.to_string()))
+        .collect();
+    let server = McpServer::new().with_tenant_per_client().with_tenant_tokens(tokens);
+    server.set_context_store(store.clone());
+    let start = |token: &str| {
+        let server = &server;
+        let token = Some(token.to_string());
+        let initialize = serde_json::to_string(&JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(json!(1)),
+            method: "initialize".to_string(),
+            params: Some(json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": {"name": "same-name", "version": "0.1.0"}
+            })),
+        }).unwrap();
+        async move {
+            let reply = with_credential(token.clone(), server.handle_message(&initialize)).await.unwrap();
+            let response: JsonRpcResponse = serde_json::from_str(&reply).unwrap();
+            let session = response.result.unwrap()["sessionId"].as_str().unwrap().to_string();
+            with_credential(token, server.handle_session_message(Some(&session), INITIALIZED)).await.unwrap();
+            session
+        }
+    };
+    let call = |session: &str, token: &str, method: &str, params: Value| {
+        let server = &server;
+        let message = request(method, Some(params));
+        let session = session.to_string();
+        let token = Some(token.to_string());
+        async move {
+            let reply = with_credential(token, server.handle_session_message(Some(&session), &message)).await;
+            serde_json::from_str::<JsonRpcResponse>(&reply.unwrap()).unwrap()
+        }
+    };
+    // Both clients send the same name; only their tokens tell them apart
+    let alpha = start("token-a").await;
+    let beta = start("token-b").await;
+
+    let created = call(&alpha, "token-a", "workflows/create", json!({"goal": "Tidy up", "steps": [{"description": "Sweep"}]})).await;
+    let id = created.result.unwrap()["workflow"]["id"].clone();
+    let listed = call(&alpha, "token-a", "workflows/status", json!({})).await.result.unwrap();
+    assert_eq!(listed["workflows"][0]["id"], id);
+    let listed = call(&beta, "token-b", "workflows/status", json!({})).await.result.unwrap();
+    assert_eq!(listed["workflows"], json!([]));
+    assert_eq!(call(&beta, "token-b", "workflows/status", json!({"workflowId": id})).await.error.unwrap().code, -32602);
+
+    // Knowing another tenant's session id is not enough to use it
+    let refused = call(&alpha, "token-b", "workflows/status", json!({})).await.error.unwrap();
+    assert_eq!(refused.code, -32600);
+    assert_eq!(refused.message, "Credential does not match the session");
+    let refused = serde_json::from_str::<JsonRpcResponse>(
+        &server.handle_session_message(Some(&alpha), &request("workflows/status", Some(json!({})))).await.unwrap(),
+    ).unwrap();
+    assert_eq!(refused.error.unwrap().code, -32600);
+
+    // Plugins scope the store to the tenant in their context
+    let plugin = MemoryPlugin::new(Some(store));
+    let context = |tenant: &str| Context {
+        correlation_id: "test".to_string(),
+        timestamp: chrono::Utc::now(),
+        parameters: std::collections::HashMap::new(),
+        sampling: None,
+        roots: None,
+        tenant: Some(tenant.to_string()),
+    };
+    let args = |args: Value| serde_json::from_value(args).unwrap();
+    plugin.execute("remember", context("alpha"), args(json!({"key": "door_code", "value": "1234"}))).await.unwrap();
+    let recalled = plugin.execute("recall", context("alpha"), args(json!({"key": "door_code"}))).await.unwrap();
+    assert_eq!(recalled.data["found"], true);
+    let recalled = plugin.execute("recall", context("beta"), args(json!({"key": "door_code"}))).await.unwrap();
+    assert_eq!(recalled.data["found"], false);
+}
+
+async fn call_memory(
+    plugin: &mcp_server::plugins::memory::MemoryPlugin,
+    action: &str,
+    args: Value,
+) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+    use mcp_server::plugins::Plugin;
+    let context = mcp_server::plugins::Context {
+        correlation_id: "test".to_string(),
+        timestamp: chrono::Utc::now(),
+        parameters: std::collections::HashMap::new(),
+        sampling: None,
+        roots: None,
+        tenant: None,
+    };
+    let result = plugin.execute(action, context, serde_json::from_value(args).unwrap()).await?;
+    Ok(result.data)
+}
+
+#[tokio::test]
+async fn test_memory_plugin_remembers_recalls_and_searches() {
+    use mcp_server::plugins::memory::MemoryPlugin;
+    use mcp_server::plugins::Plugin;
+
+    let without_store = MemoryPlugin::new(None);
+    let error = call_memory(&without_store, "recall", json!({"key": "k"})).await.unwrap_err();
+    assert!(error.to_string().contains("context store"));
+    assert!(!without_store.health_check().await.healthy);
+
+    let plugin = MemoryPlugin::new(Some(Arc::new(mcp_test_support::InMemoryContextStore::new())));
+    call_memory(&plugin, "remember", json!({"key": "favourite_colour", "value": "Teal"})).await.unwrap();
+    call_memory(&plugin, "remember", json!({"key": "partner", "value": {"name": "Sam", "likes": "teal mugs"}})).await.unwrap();
+    let stored = call_memory(&plugin, "remember", json!({"key": "parking", "value": "level 3", "ttl": 3600})).await.unwrap();
+    assert!(stored["expires_at"].is_string());
+    assert!(call_memory(&plugin, "remember", json!({"key": "x", "value": 1, "ttl": -5})).await.is_err());
+
+    let recalled = call_memory(&plugin, "recall", json!({"key": "favourite_colour"})).await.unwrap();
+    assert_eq!(recalled["found"], true);
+    assert_eq!(recalled["memory"]["value"], "Teal");
+    assert_eq!(call_memory(&plugin, "recall", json!({"key": "unknown"})).await.unwrap()["found"], false);
+
+    let found = call_memory(&plugin, "search", json!({"text": "TEAL"})).await.unwrap();
+    let keys: Vec<&str> = found["memories"].as_array().unwrap().iter().map(|m| m["key"].as_str().unwrap()).collect();
+    assert_eq!(keys, vec!["partner", "favourite_colour"]);
+    assert_eq!(call_memory(&plugin, "search", json!({"text": "teal", "limit": 1})).await.unwrap()["memories"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_knowledge_graph_ingests_extracted_facts_and_answers_queries() {
+    use mcp_server::mcp::client_requests::ClientRequests;
+    use mcp_server::mcp::notifications::NotificationDispatcher;
+    use mcp_server::mcp::sampling::{Sampler, SamplingBroker};
+    use mcp_server::plugins::knowledge_graph::KnowledgeGraphPlugin;
+    use mcp_server::plugins::{Context, Plugin};
+
+    let notifications = Arc::new(NotificationDispatcher::new());
+    let mut stream = notifications.subscribe("session");
+    let requests = Arc::new(ClientRequests::new(notifications));
+    let sampler = Sampler::new(Arc::new(SamplingBroker::new(requests.clone())), Some("session".to_string()));
+    tokio::spawn(async move {
+        let request: Value = serde_json::from_str(&stream.recv().await.unwrap()).unwrap();
+        assert_eq!(request["params"]["messages"][0]["content"]["text"], "Ada works at Acme, which Bob founded.");
+        let extraction = json!({
+            "entities": [{"name": "Ada", "type": "person"}, {"name": "Acme", "type": "company"}],
+            "relations": [
+                {"source": "Ada", "relation": "works at", "target": "Acme"},
+                {"source": "Bob", "relation": "founded", "target": "Acme"}
+            ]
+        });
+        requests.resolve(&request["id"], Ok(json!({
+            "role": "assistant",
+            "content": {"type": "text", "text": format!("```json\n{}\n```", extraction)},
+            "model": "llama3.1"
+        })));
+    });
+    let context = |sampling: Option<Sampler>| Context {
+        correlation_id: "test".to_string(),
+        timestamp: chrono::Utc::now(),
+        parameters: std::collections::HashMap::new(),
+        sampling,
+        roots: None,
+        tenant: None,
+    };
+    let params = |args: Value| serde_json::from_value(args).unwrap();
+
+    let store = Arc::new(mcp_test_support::InMemoryContextStore::new());
+    let plugin = KnowledgeGraphPlugin::new(Some(store.clone()));
+    let text = json!({"text": "Ada works at Acme, which Bob founded.", "source": "https://example.com/about"});
+    let ingested = plugin.execute("ingest", context(Some(sampler)), params(text.clone())).await.unwrap().data;
+    assert_eq!(ingested["provenance"]["model"], "llama3.1");
+    assert_eq!(ingested["entities"].as_array().unwrap().len(), 3);
+    assert_eq!(ingested["relations"][0]["relation"], "works_at");
+    assert_eq!(store.knowledge().sources[0].source.as_deref(), Some("https://example.com/about"));
+
+    let found = plugin.execute("query", context(None), params(json!({"question": "Where does Ada work?"}))).await.unwrap().data;
+    assert_eq!(found["terms"], json!(["ada", "work"]));
+    assert_eq!(found["relations"], json!([{
+        "source": "Ada", "relation": "works_at", "target": "Acme", "provenance": ingested["provenance"]["id"]
+    }]));
+    let names: Vec<&str> = found["entities"].as_array().unwrap().iter().map(|e| e["name"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["Ada", "Acme"]);
+    assert_eq!(found["sources"][0]["text"], "Ada works at Acme, which Bob founded.");
+
+    let error = plugin.execute("ingest", context(None), params(text)).await.unwrap_err();
+    assert!(error.to_string().contains("Sampling is not available"));
+    let without_store = KnowledgeGraphPlugin::new(None);
+    let error = without_store.execute("query", context(None), params(json!({"question": "Ada"}))).await.unwrap_err();
+    assert!(error.to_string().contains("context store"));
+}
+
+#[tokio::test]
+async fn test_metrics_plugin_aggregates_readings_into_windows() {
+    use mcp_server::context::ContextStore;
+    use mcp_server::plugins::metrics::MetricsPlugin;
+    use mcp_server::plugins::{Context, Plugin};
+
+    let store = Arc::new(mcp_test_support::InMemoryContextStore::new());
+    let start = chrono::DateTime::from_timestamp(1_700_000_100, 0).unwrap();
+    for (offset, value) in [(0, json!(10.0)), (60, json!(30.0)), (120, json!(20.0)), (400, json!(90.0)), (420, json!("n/a"))] {
+        store.store_metric("cpu_usage", value, start + chrono::Duration::seconds(offset)).await.unwrap();
+    }
+    store.store_metric("memory_usage", json!(50.0), start).await.unwrap();
+
+    // Host metrics are stored under the default tenant and read from there
+    // whatever tenant the caller is in
+    let plugin = MetricsPlugin::new(Some(store));
+    let aggregate = |args: Value| {
+        let context = Context {
+            correlation_id: "test".to_string(),
+            timestamp: chrono::Utc::now(),
+            parameters: std::collections::HashMap::new(),
+            sampling: None,
+            roots: None,
+            tenant: Some("alpha".to_string()),
+        };
+        plugin.execute("aggregate", context, serde_json::from_value(args).unwrap())
+    };
+
+    let result = aggregate(json!({"metric_type": "cpu_usage", "since": start.to_rfc3339()})).await.unwrap().data;
+    let buckets = result["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[0]["start"], "2023-11-14T22:15:00Z");
+    assert_eq!(buckets[0]["value"], 20.0);
+    assert_eq!(buckets[0]["count"], 3);
+    assert_eq!(buckets[1]["value"], 90.0);
+
+    let max = aggregate(json!({"metric_type": "cpu_usage", "since": start.to_rfc3339(), "window": 3600, "function": "max"}))
+        .await
+        .unwrap()
+        .data;
+    assert_eq!(max["buckets"], json!([{"start": "2023-11-14T22:00:00Z", "value": 90.0, "count": 4}]));
+    let p95 = aggregate(json!({"metric_type": "cpu_usage", "since": start.to_rfc3339(), "window": 3600, "function": "p95"}))
+        .await
+        .unwrap()
+        .data;
+    assert!((p95["buckets"][0]["value"].as_f64().unwrap() - 81.0).abs() < 1e-9);
+
+    assert!(aggregate(json!({"metric_type": "cpu_usage", "function": "median"})).await.is_err());
+    assert!(aggregate(json!({"metric_type": "cpu_usage", "window": 0})).await.is_err());
+    assert!(aggregate(json!({"metric_type": "cpu_usage"})).await.unwrap().data["buckets"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_buffered_store_replays_writes_made_while_the_backend_was_down() {
+    use mcp_server::context::{BufferedStore, ContextStore, RelationType, WriteBuffer};
+
+    let backend = Arc::new(mcp_test_support::InMemoryContextStore::new());
+    let store = BufferedStore::new(backend.clone(), WriteBuffer::in_memory());
+    let timestamp = chrono::Utc::now();
+
+    backend.set_unavailable(true);
+    let state = store.store_system_state(std::collections::HashMap::new()).await.unwrap();
+    let metric = store.store_metric("cpu_usage", json!(42.5), timestamp).await.unwrap();
+    assert!(state.starts_with("buffered-"));
+    store.create_relationship(&state, &metric, RelationType::Contains, None).await.unwrap();
+    store.save_workflow("w1", json!({"id": "w1"})).await.unwrap();
+    assert_eq!(store.pending(), 4);
+    assert!(backend.metrics().is_empty());
+    assert!(!store.replay().await.unwrap());
+
+    backend.set_unavailable(false);
+    assert!(store.replay().await.unwrap());
+    assert_eq!(store.pending(), 0);
+    assert_eq!(backend.metrics(), vec![("cpu_usage".to_string(), json!(42.5), timestamp)]);
+    let relationships = backend.relationships();
+    assert_eq!((relationships[0].from_id.as_str(), relationships[0].to_id.as_str()), ("state-1", "metric-1"));
+    assert_eq!(backend.load_workflow("w1").await.unwrap(), Some(json!({"id": "w1"})));
+
+    // Links written after the replay still find records that were buffered
+    store.merge_relationship(&state, &metric, RelationType::Related, None).await.unwrap();
+    assert_eq!(backend.relationships()[1].from_id, "state-1");
+    assert_eq!(store.store_metric("cpu_usage", json!(1.0), timestamp).await.unwrap(), "metric-2");
+}
+
+#[tokio::test]
+async fn test_disabled_plugins_hide_their_tools_until_enabled() {
+    let server = McpServer::new();
+    let plugin = Arc::new(mcp_server::plugins::system_info::SystemInfoPlugin::new());
+    server.register_plugin(plugin.clone()).await.unwrap();
+    server.register_tool(Box::new(mcp_server::tools::SystemInfoTool::new(plugin))).await;
+    async fn listed(server: &McpServer) -> bool {
+        let response: JsonRpcResponse = serde_json::from_str(
+            &server.handle_message(&request("tools/list", None)).await.unwrap()
+        ).unwrap();
+        response.result.unwrap()["tools"].as_array().unwrap().iter().any(|tool| tool["name"] == "system_info")
+    }
+    assert!(listed(&server).await);
+
+    assert!(server.set_plugin_enabled("system_info", false).await);
+    assert!(!server.set_plugin_enabled("nonexistent", false).await);
+    assert_eq!(server.plugin_states().await, vec![("system_info".to_string(), false)]);
+    assert!(!listed(&server).await);
+    let params = json!({"name": "system_info", "arguments": {}});
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("tools/call", Some(params))).await.unwrap()
+    ).unwrap();
+    let error = response.error.unwrap();
+    assert_eq!((error.code, &error.data.unwrap()["kind"]), (-32602, &json!("notFound")));
+
+    assert!(server.set_plugin_enabled("system_info", true).await);
+    assert!(listed(&server).await);
+}
+
+#[tokio::test]
+async fn test_draining_refuses_new_sessions() {
+    let server = McpServer::new();
+    let session = start_session(&server).await;
+    server.drain();
+    assert!(server.is_draining());
+
+    let response: JsonRpcResponse = serde_json::from_str(&server.handle_message(&initialize_request()).await.unwrap()).unwrap();
+    let error = response.error.unwrap();
+    assert_eq!((error.code, &error.data.unwrap()["kind"]), (-32007, &json!("shuttingDown")));
+
+    // Open sessions keep working until they are ended
+    let reply = server.handle_session_message(Some(&session), &request("ping", None)).await.unwrap();
+    assert!(serde_json::from_str::<JsonRpcResponse>(&reply).unwrap().error.is_none());
+    server.end_all_sessions().await;
+    assert!(server.list_sessions().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_unavailable_plugins_refuse_calls_while_others_keep_working() {
+    use mcp_server::plugins::neo4j::Neo4jPlugin;
+
+    let server = McpServer::new();
+    let system_info = Arc::new(mcp_server::plugins::system_info::SystemInfoPlugin::new());
+    assert!(server.register_plugin_lazily(system_info.clone()).await);
+    server.register_tool(Box::new(mcp_server::tools::SystemInfoTool::new(system_info))).await;
+    // Without a password Neo4j fails to initialize
+    let neo4j = Arc::new(Neo4jPlugin::new("bolt://127.0.0.1:9", "neo4j", None));
+    assert!(!server.register_plugin_lazily(neo4j.clone()).await);
+    server.register_tool(Box::new(mcp_server::tools::Neo4jTool::new(neo4j))).await;
+
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("plugins/status", None)).await.unwrap()
+    ).unwrap();
+    let plugins = response.result.unwrap()["plugins"].clone();
+    assert_eq!(plugins[0]["name"], "neo4j");
+    assert_eq!((&plugins[0]["available"], &plugins[0]["attempts"]), (&json!(false), &json!(1)));
+    assert!(plugins[0]["error"].as_str().unwrap().contains("NEO4J_PASSWORD"));
+    assert_eq!(plugins[1], json!({"name": "system_info", "enabled": true, "available": true}));
+
+    let params = json!({"name": "neo4j_query", "arguments": {"query": "RETURN 1"}});
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("tools/call", Some(params))).await.unwrap()
+    ).unwrap();
+    let error = response.error.unwrap();
+    let data = error.data.unwrap();
+    assert_eq!((error.code, &data["kind"], &data["plugin"]), (-32008, &json!("pluginUnavailable"), &json!("neo4j")));
+
+    let params = json!({"name": "system_info", "arguments": {}});
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("tools/call", Some(params))).await.unwrap()
+    ).unwrap();
+    assert!(response.error.is_none());
+
+    // A retry that fails again is counted
+    assert!(server.retry_unavailable_plugins().await.is_empty());
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("plugins/status", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.result.unwrap()["plugins"][0]["attempts"], 2);
+
+    // A plugin depending on an unavailable one is unavailable too
+    server.add_plugin_dependency("system_info", "neo4j").await.unwrap();
+    assert!(server.add_plugin_dependency("neo4j", "system_info").await.is_err());
+    let params = json!({"name": "system_info", "arguments": {}});
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("tools/call", Some(params))).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.error.unwrap().data.unwrap()["detail"], "Depends on neo4j, which is unavailable");
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("plugins/status", None)).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.result.unwrap()["plugins"][1]["dependsOn"], json!(["neo4j"]));
+}
+
+/// Sleeps for `ms` milliseconds, then counts the call as finished
+struct Sleeper(Arc<std::sync::atomic::AtomicUsize>);
+
+#[async_trait::async_trait]
+impl mcp_server::tools::Tool for Sleeper {
+    fn name(&self) -> &str {
+        "sleep"
+    }
+    fn description(&self) -> &str {
+        "Sleeps"
+    }
+    fn input_schema(&self) -> Value {
+        json!({"type": "object", "properties": {"ms": {"type": "integer"}}})
+    }
+    async fn call(&self, args: std::collections::HashMap<String, Value>) -> anyhow::Result<Vec<mcp_server::mcp::ContentBlock>> {
+        let ms = args.get("ms").and_then(Value::as_u64).unwrap_or(0);
+        tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(vec![mcp_server::mcp::ContentBlock::text("awake")])
+    }
+}
+
+#[tokio::test]
+async fn test_tool_calls_stop_at_the_callers_deadline() {
+    let server = McpServer::new();
+    let finished = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    server.register_tool(Box::new(Sleeper(finished.clone()))).await;
+
+    let params = json!({"name": "sleep", "arguments": {"ms": 5000}, "_meta": {"timeoutMs": 50}});
+    let started = std::time::Instant::now();
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("tools/call", Some(params))).await.unwrap()
+    ).unwrap();
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    let error = response.error.unwrap();
+    assert_eq!(error.code, -32009);
+    assert_eq!(error.data.unwrap(), json!({"kind": "deadlineExceeded", "detail": "sleep did not finish within 50 ms", "timeoutMs": 50}));
+    assert_eq!(finished.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+    // Calls finishing in time are unaffected
+    let params = json!({"name": "sleep", "arguments": {"ms": 1}, "_meta": {"timeoutMs": 5000}});
+    let response: JsonRpcResponse = serde_json::from_str(
+        &server.handle_message(&request("tools/call", Some(params))).await.unwrap()
+    ).unwrap();
+    assert_eq!(response.result.unwrap()["content"][0]["text"], "awake");
+    assert_eq!(finished.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_stdio_answers_requests_still_running_at_eof() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mcp-server"))
+        .arg("--stdio")
+        .current_dir(dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, r#"{{"jsonrpc":"2.0","id":1,"method":"initialize","params":{{"protocolVersion":"2024-11-05","capabilities":{{}},"clientInfo":{{"name":"test","version":"0.1.0"}}}}}}"#).unwrap();
+    writeln!(stdin, r#"{{"jsonrpc":"2.0","id":1.5,"method":"ping"}}"#).unwrap();
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    let lines: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["id"], 1.5);
+    assert_eq!(lines[1]["result"], json!({}));
+}
+
+#[test]
+fn test_stdio_answers_batches_of_non_objects() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mcp-server"))
+        .arg("--stdio")
+        .current_dir(dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "[1]").unwrap();
+    writeln!(stdin, "[INFO] wrapper started").unwrap();
+    writeln!(stdin, "[1,2]").unwrap();
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    let batches: Vec<Vec<JsonRpcResponse>> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), [1, 2]);
+    assert!(batches.iter().flatten().all(|response| response.error.as_ref().unwrap().code == -32600));
+}
@@ -0,0 +1,13 @@
+//! Test scaffolding shared by mcp-server, mcp-http-bridge and mcp-client:
+//! JSON-RPC request builders, wiremock helpers for the endpoints the crates
+//! talk to, and (with the `server` feature) fake plugins and tools with an
+//! in-process `McpServer` to run them in.
+
+pub mod mocks;
+pub mod rpc;
+#[cfg(feature = "server")]
+pub mod server;
+
+pub use rpc::RpcRequest;
+#[cfg(feature = "server")]
+pub use server::{spawn_test_server, FakePlugin, FakeTool, InMemoryContextStore, TestServer};
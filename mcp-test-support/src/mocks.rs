@@ -0,0 +1,117 @@
+//! wiremock helpers for the HTTP APIs the crates call: the bridge's REST
+//! endpoints, mcp-server's JSON-RPC `/tools/call` and Ollama
+
+use serde_json::{json, Value};
+use wiremock::matchers::{body_json, body_partial_json, method, path};
+use wiremock::{Mock, MockBuilder, Request, Respond, ResponseTemplate};
+
+/// A tool as the bridge and the server list it, taking no arguments
+pub fn tool_definition(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "description": description,
+        "inputSchema": { "type": "object", "properties": {} }
+    })
+}
+
+/// The bridge's `GET /tools`, listing `tools`
+pub fn bridge_tools(tools: Vec<Value>) -> Mock {
+    Mock::given(method("GET"))
+        .and(path("/tools"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "tools": tools })))
+}
+
+/// The bridge's `POST /tools/call` for `tool_name` with exactly `arguments`,
+/// succeeding with `text`
+pub fn bridge_tool_call(tool_name: &str, arguments: Value, text: &str) -> Mock {
+    Mock::given(method("POST"))
+        .and(path("/tools/call"))
+        .and(body_json(json!({ "tool_name": tool_name, "arguments": arguments })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "content": [{ "type": "text", "text": text }],
+            "error": null
+        })))
+}
+
+/// JSON-RPC requests for `rpc_method` on mcp-server's `POST /tools/call`; add
+/// matchers, then answer with an [`RpcResult`]
+pub fn rpc_call(rpc_method: &str) -> MockBuilder {
+    Mock::given(method("POST"))
+        .and(path("/tools/call"))
+        .and(body_partial_json(json!({ "method": rpc_method })))
+}
+
+/// Answers a JSON-RPC request under its own id
+pub struct RpcResult {
+    result: Option<Value>,
+    error: Option<Value>,
+}
+
+impl RpcResult {
+    pub fn new(result: Value) -> Self {
+        Self { result: Some(result), error: None }
+    }
+
+    pub fn error(code: i64, message: &str) -> Self {
+        Self {
+            result: None,
+            error: Some(json!({ "code": code, "message": message })),
+        }
+    }
+}
+
+impl Respond for RpcResult {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let id = serde_json::from_slice::<Value>(&request.body)
+            .ok()
+            .and_then(|body| body.get("id").cloned())
+            .unwrap_or(Value::Null);
+        let mut body = json!({ "jsonrpc": "2.0", "id": id });
+        if let Some(result) = &self.result {
+            body["result"] = result.clone();
+        }
+        if let Some(error) = &self.error {
+            body["error"] = error.clone();
+        }
+        ResponseTemplate::new(200).set_body_json(body)
+    }
+}
+
+/// Ollama's `POST /api/generate` for `prompt` to `model`, answering with
+/// `response`
+pub fn ollama_generate(model: &str, prompt: &str, response: &str) -> Mock {
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .and(body_json(json!({ "model": model, "prompt": prompt })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "response": response,
+            "done": true
+        })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::MockServer;
+
+    #[tokio::test]
+    async fn test_rpc_result_answers_under_the_request_id() {
+        let server = MockServer::start().await;
+        rpc_call("logs/tail")
+            .respond_with(RpcResult::new(json!({ "entries": [] })))
+            .mount(&server)
+            .await;
+
+        let response: Value = reqwest::Client::new()
+            .post(format!("{}/tools/call", server.uri()))
+            .json(&json!({ "jsonrpc": "2.0", "id": 42, "method": "logs/tail" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response, json!({ "jsonrpc": "2.0", "id": 42, "result": { "entries": [] } }));
+    }
+}
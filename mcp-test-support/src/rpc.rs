@@ -0,0 +1,100 @@
+use serde_json::{json, Value};
+use std::fmt;
+
+/// Builds a JSON-RPC 2.0 request (or notification) for tests
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcRequest {
+    id: Option<Value>,
+    method: String,
+    params: Option<Value>,
+}
+
+impl RpcRequest {
+    /// A request with id 1 and no params
+    pub fn new(method: &str) -> Self {
+        Self {
+            id: Some(json!(1)),
+            method: method.to_string(),
+            params: None,
+        }
+    }
+
+    /// A message without an id, which gets no response
+    pub fn notification(method: &str) -> Self {
+        Self {
+            id: None,
+            ..Self::new(method)
+        }
+    }
+
+    pub fn id(mut self, id: impl Into<Value>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn params(mut self, params: Value) -> Self {
+        self.params = Some(params);
+        self
+    }
+
+    /// `initialize` for a client asking for `protocol_version`
+    pub fn initialize(protocol_version: &str) -> Self {
+        Self::new("initialize").params(json!({
+            "protocolVersion": protocol_version,
+            "capabilities": {},
+            "clientInfo": { "name": "mcp-test-support", "version": env!("CARGO_PKG_VERSION") }
+        }))
+    }
+
+    pub fn tool_call(name: &str, arguments: Value) -> Self {
+        Self::new("tools/call").params(json!({ "name": name, "arguments": arguments }))
+    }
+
+    pub fn to_value(&self) -> Value {
+        let mut message = json!({ "jsonrpc": "2.0", "method": self.method });
+        if let Some(id) = &self.id {
+            message["id"] = id.clone();
+        }
+        if let Some(params) = &self.params {
+            message["params"] = params.clone();
+        }
+        message
+    }
+}
+
+impl fmt::Display for RpcRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_value())
+    }
+}
+
+#[cfg(feature = "server")]
+impl From<RpcRequest> for mcp_server::mcp::JsonRpcRequest {
+    fn from(request: RpcRequest) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: request.id,
+            method: request.method,
+            params: request.params,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builds_requests_and_notifications() {
+        let call = RpcRequest::tool_call("echo", json!({ "text": "hi" })).id(7);
+        assert_eq!(call.to_value(), json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "tools/call",
+            "params": { "name": "echo", "arguments": { "text": "hi" } }
+        }));
+
+        let initialized = RpcRequest::notification("notifications/initialized").to_string();
+        assert_eq!(initialized, r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#);
+    }
+}
@@ -0,0 +1,513 @@
+//! Fakes for mcp-server's extension points and an in-process server to run
+//! them in, without Neo4j or any real plugin
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mcp_server::context::{
+    ContextStore, DEFAULT_TENANT, Entity, KnowledgeGraph, Memory, MetricAggregate, MetricBucket, Provenance, Relation, RelationType,
+};
+use mcp_server::mcp::{ContentBlock, McpServer};
+use mcp_server::plugins::{Capability, Context, Plugin, PluginResult};
+use mcp_server::tools::Tool;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::RpcRequest;
+
+/// A plugin whose capabilities return canned data (or fail) and that
+/// remembers how it was called
+pub struct FakePlugin {
+    name: String,
+    results: HashMap<String, Result<Value, String>>,
+    calls: Mutex<Vec<(String, HashMap<String, Value>)>>,
+}
+
+impl FakePlugin {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            results: HashMap::new(),
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// `capability` succeeds with `data`
+    pub fn with_capability(mut self, capability: &str, data: Value) -> Self {
+        self.results.insert(capability.to_string(), Ok(data));
+        self
+    }
+
+    /// `capability` fails with `message`
+    pub fn with_failure(mut self, capability: &str, message: &str) -> Self {
+        self.results.insert(capability.to_string(), Err(message.to_string()));
+        self
+    }
+
+    /// Capabilities executed so far with their parameters, oldest first
+    pub fn calls(&self) -> Vec<(String, HashMap<String, Value>)> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Plugin for FakePlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        "0.0.0"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        let mut names: Vec<&String> = self.results.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| Capability {
+                name: name.clone(),
+                description: format!("Fake {}", name),
+                parameters: vec![],
+                version: None,
+                deprecated: None,
+            })
+            .collect()
+    }
+
+    async fn execute(
+        &self,
+        capability: &str,
+        _context: Context,
+        params: HashMap<String, Value>,
+    ) -> Result<PluginResult, Box<dyn Error + Send + Sync>> {
+        self.calls.lock().unwrap().push((capability.to_string(), params));
+        match self.results.get(capability) {
+            Some(Ok(data)) => Ok(PluginResult {
+                success: true,
+                data: data.clone(),
+                metrics: None,
+                context_updates: None,
+            }),
+            Some(Err(message)) => Err(message.clone().into()),
+            None => Err(format!("Unknown capability: {}", capability).into()),
+        }
+    }
+}
+
+/// A tool answering every call with the same text
+pub struct FakeTool {
+    name: String,
+    text: String,
+}
+
+impl FakeTool {
+    pub fn new(name: &str, text: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            text: text.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for FakeTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        "Fake tool for tests"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn call(&self, _args: HashMap<String, Value>) -> anyhow::Result<Vec<ContentBlock>> {
+        Ok(vec![ContentBlock::text(&self.text)])
+    }
+}
+
+/// A relationship stored in an [`InMemoryContextStore`]
+#[derive(Debug, Clone)]
+pub struct StoredRelationship {
+    pub from_id: String,
+    pub to_id: String,
+    pub rel_type: RelationType,
+}
+
+/// A `ContextStore` that keeps everything in memory, in place of Neo4j
+#[derive(Default)]
+pub struct InMemoryContextStore {
+    metrics: Mutex<Vec<(String, Value, DateTime<Utc>)>>,
+    states: Mutex<Vec<HashMap<String, Value>>>,
+    relationships: Mutex<Vec<StoredRelationship>>,
+    /// Workflow states by id, least recently saved first
+    workflows: Mutex<Vec<(String, Value)>>,
+    /// Memories, least recently stored first
+    memories: Mutex<Vec<Memory>>,
+    knowledge: Mutex<KnowledgeGraph>,
+    /// Set while the store pretends its backend is down, for every tenant
+    unavailable: Arc<AtomicBool>,
+    /// The store of each tenant that `with_tenant` was asked for, shared by
+    /// all of them
+    tenants: Arc<Mutex<HashMap<String, Arc<InMemoryContextStore>>>>,
+}
+
+impl InMemoryContextStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stored metrics as (type, value, timestamp)
+    pub fn metrics(&self) -> Vec<(String, Value, DateTime<Utc>)> {
+        self.metrics.lock().unwrap().clone()
+    }
+
+    pub fn system_states(&self) -> Vec<HashMap<String, Value>> {
+        self.states.lock().unwrap().clone()
+    }
+
+    pub fn relationships(&self) -> Vec<StoredRelationship> {
+        self.relationships.lock().unwrap().clone()
+    }
+
+    /// Everything ingested into the knowledge graph
+    pub fn knowledge(&self) -> KnowledgeGraph {
+        self.knowledge.lock().unwrap().clone()
+    }
+
+    /// While unavailable, writes and pings fail as if the database were down
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.unavailable.store(unavailable, Ordering::SeqCst);
+    }
+
+    fn check_available(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.unavailable.load(Ordering::SeqCst) {
+            return Err("context store is unavailable".into());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ContextStore for InMemoryContextStore {
+    fn with_tenant(self: Arc<Self>, tenant: &str) -> Arc<dyn ContextStore> {
+        let mut tenants = self.tenants.lock().unwrap();
+        if tenants.is_empty() {
+            tenants.insert(DEFAULT_TENANT.to_string(), self.clone());
+        }
+        let store = tenants.entry(tenant.to_string()).or_insert_with(|| {
+            Arc::new(InMemoryContextStore {
+                unavailable: self.unavailable.clone(),
+                tenants: self.tenants.clone(),
+                ..Default::default()
+            })
+        });
+        store.clone()
+    }
+
+    async fn store_metric(
+        &self,
+        metric_type: &str,
+        value: Value,
+        timestamp: DateTime<Utc>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.check_available()?;
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.push((metric_type.to_string(), value, timestamp));
+        Ok(format!("metric-{}", metrics.len()))
+    }
+
+    async fn aggregate_metrics(
+        &self,
+        metric_type: &str,
+        window: chrono::Duration,
+        aggregate: MetricAggregate,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<MetricBucket>, Box<dyn Error + Send + Sync>> {
+        let window = window.num_seconds().max(1);
+        let mut buckets: std::collections::BTreeMap<i64, Vec<f64>> = std::collections::BTreeMap::new();
+        for (stored_type, value, timestamp) in self.metrics.lock().unwrap().iter() {
+            let number = value.as_f64().or_else(|| value.as_str().and_then(|text| text.parse().ok()));
+            if let (true, Some(number)) = (stored_type == metric_type && *timestamp >= since, number) {
+                let seconds = timestamp.timestamp();
+                buckets.entry(seconds - seconds.rem_euclid(window)).or_default().push(number);
+            }
+        }
+        Ok(buckets
+            .into_iter()
+            .map(|(start, values)| MetricBucket {
+                start: DateTime::from_timestamp(start, 0).unwrap_or_default(),
+                value: aggregate.apply(&values),
+                count: values.len() as u64,
+            })
+            .collect())
+    }
+
+    async fn store_system_state(
+        &self,
+        state: HashMap<String, Value>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.check_available()?;
+        let mut states = self.states.lock().unwrap();
+        states.push(state);
+        Ok(format!("state-{}", states.len()))
+    }
+
+    async fn create_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        _properties: Option<HashMap<String, Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.check_available()?;
+        self.relationships.lock().unwrap().push(StoredRelationship {
+            from_id: from_id.to_string(),
+            to_id: to_id.to_string(),
+            rel_type,
+        });
+        Ok(())
+    }
+
+    async fn merge_relationship(
+        &self,
+        from_id: &str,
+        to_id: &str,
+        rel_type: RelationType,
+        _properties: Option<HashMap<String, Value>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.check_available()?;
+        let mut relationships = self.relationships.lock().unwrap();
+        let exists = relationships
+            .iter()
+            .any(|stored| stored.from_id == from_id && stored.to_id == to_id && stored.rel_type == rel_type);
+        if !exists {
+            relationships.push(StoredRelationship {
+                from_id: from_id.to_string(),
+                to_id: to_id.to_string(),
+                rel_type,
+            });
+        }
+        Ok(())
+    }
+
+    async fn save_workflow(&self, id: &str, state: Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.check_available()?;
+        let mut workflows = self.workflows.lock().unwrap();
+        workflows.retain(|(saved, _)| saved != id);
+        workflows.push((id.to_string(), state));
+        Ok(())
+    }
+
+    async fn load_workflow(&self, id: &str) -> Result<Option<Value>, Box<dyn Error + Send + Sync>> {
+        let workflows = self.workflows.lock().unwrap();
+        Ok(workflows.iter().find(|(saved, _)| saved == id).map(|(_, state)| state.clone()))
+    }
+
+    async fn list_workflows(&self) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
+        Ok(self.workflows.lock().unwrap().iter().rev().map(|(_, state)| state.clone()).collect())
+    }
+
+    async fn remember(&self, memory: Memory) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.check_available()?;
+        let mut memories = self.memories.lock().unwrap();
+        memories.retain(|stored| stored.key != memory.key);
+        memories.push(memory);
+        Ok(())
+    }
+
+    async fn recall(&self, key: &str) -> Result<Option<Memory>, Box<dyn Error + Send + Sync>> {
+        let memories = self.memories.lock().unwrap();
+        Ok(memories.iter().find(|memory| memory.key == key && !memory.is_expired(Utc::now())).cloned())
+    }
+
+    async fn search_memories(&self, text: &str, limit: usize) -> Result<Vec<Memory>, Box<dyn Error + Send + Sync>> {
+        let text = text.to_lowercase();
+        let memories = self.memories.lock().unwrap();
+        Ok(memories
+            .iter()
+            .rev()
+            .filter(|memory| !memory.is_expired(Utc::now()))
+            .filter(|memory| {
+                memory.key.to_lowercase().contains(&text) || memory.value.to_string().to_lowercase().contains(&text)
+            })
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn ingest_knowledge(
+        &self,
+        provenance: Provenance,
+        entities: Vec<Entity>,
+        relations: Vec<Relation>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.check_available()?;
+        let mut knowledge = self.knowledge.lock().unwrap();
+        for entity in entities {
+            match knowledge.entities.iter_mut().find(|known| known.name == entity.name) {
+                Some(known) if !entity.kind.is_empty() => known.kind = entity.kind,
+                Some(_) => {}
+                None => knowledge.entities.push(entity),
+            }
+        }
+        knowledge.relations.extend(relations.into_iter().map(|relation| Relation {
+            provenance: Some(provenance.id.clone()),
+            ..relation
+        }));
+        knowledge.sources.push(provenance);
+        Ok(())
+    }
+
+    async fn query_knowledge(&self, terms: &[String], limit: usize) -> Result<KnowledgeGraph, Box<dyn Error + Send + Sync>> {
+        let knowledge = self.knowledge.lock().unwrap();
+        let mut found = KnowledgeGraph::default();
+        let mut matching: Vec<&Entity> = knowledge
+            .entities
+            .iter()
+            .filter(|entity| terms.iter().any(|term| entity.name.to_lowercase().contains(term.as_str())))
+            .collect();
+        matching.sort_by(|a, b| a.name.cmp(&b.name));
+        found.entities = matching.into_iter().take(limit).cloned().collect();
+
+        let names: Vec<String> = found.entities.iter().map(|entity| entity.name.clone()).collect();
+        for relation in &knowledge.relations {
+            if !names.contains(&relation.source) && !names.contains(&relation.target) {
+                continue;
+            }
+            for name in [&relation.source, &relation.target] {
+                if !found.entities.iter().any(|entity| &entity.name == name) {
+                    found.entities.extend(knowledge.entities.iter().find(|entity| &entity.name == name).cloned());
+                }
+            }
+            found.relations.push(relation.clone());
+        }
+        found.sources = knowledge
+            .sources
+            .iter()
+            .rev()
+            .filter(|source| found.relations.iter().any(|relation| relation.provenance.as_ref() == Some(&source.id)))
+            .cloned()
+            .collect();
+        Ok(found)
+    }
+
+    async fn ping(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.check_available()
+    }
+}
+
+/// An `McpServer` running in-process with an initialized session
+pub struct TestServer {
+    pub server: Arc<McpServer>,
+    pub session_id: String,
+}
+
+impl TestServer {
+    /// Starts a server with only `plugins` and `tools` registered
+    pub async fn start(plugins: Vec<Arc<FakePlugin>>, tools: Vec<FakeTool>) -> Self {
+        let server = Arc::new(McpServer::new());
+        for plugin in plugins {
+            server.register_plugin(plugin).await.unwrap();
+        }
+        for tool in tools {
+            server.register_tool(Box::new(tool)).await;
+        }
+        Self::open_session(server).await
+    }
+
+    /// Starts a server with the built-in plugins and tools, as the binary
+    /// does, keeping context in an [`InMemoryContextStore`]. Plugins whose
+    /// services are not running are unavailable.
+    pub async fn builtin() -> Self {
+        let server = Arc::new(McpServer::new());
+        server.set_context_store(Arc::new(InMemoryContextStore::new()));
+        server.initialize().await.unwrap();
+        Self::open_session(server).await
+    }
+
+    async fn open_session(server: Arc<McpServer>) -> Self {
+        let initialized = server
+            .handle_message(&RpcRequest::initialize("2025-03-26").to_string())
+            .await
+            .unwrap();
+        let session_id = serde_json::from_str::<Value>(&initialized).unwrap()["result"]["sessionId"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        server
+            .handle_session_message(Some(&session_id), &RpcRequest::notification("notifications/initialized").to_string())
+            .await
+            .unwrap();
+        Self { server, session_id }
+    }
+
+    /// Sends `request` in the session and returns the parsed response
+    pub async fn request(&self, request: RpcRequest) -> Value {
+        let response = self
+            .server
+            .handle_session_message(Some(&self.session_id), &request.to_string())
+            .await
+            .unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    /// Serves the REST endpoints the bridge calls on a free local port until
+    /// the test's runtime stops, and returns their base URL
+    pub fn serve_rest(&self) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = mcp_server::rest::router(self.server.clone());
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        url
+    }
+}
+
+/// A server with a `fake` plugin (`echo` returns `{"echoed": true}`, `fail`
+/// fails) and a `fake_tool` tool answering "fake result"
+pub async fn spawn_test_server() -> TestServer {
+    let plugin = FakePlugin::new("fake")
+        .with_capability("echo", json!({ "echoed": true }))
+        .with_failure("fail", "fake failure");
+    TestServer::start(vec![Arc::new(plugin)], vec![FakeTool::new("fake_tool", "fake result")]).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_store_keeps_what_was_stored() {
+        let store = InMemoryContextStore::new();
+        let state = store.store_system_state(HashMap::from([("cpu".to_string(), json!(12.5))])).await.unwrap();
+        let metric = store.store_metric("cpu", json!(12.5), Utc::now()).await.unwrap();
+        store.create_relationship(&state, &metric, RelationType::Contains, None).await.unwrap();
+
+        assert_eq!(store.system_states().len(), 1);
+        assert_eq!(store.metrics()[0].0, "cpu");
+        let relationships = store.relationships();
+        assert_eq!((relationships[0].from_id.as_str(), relationships[0].to_id.as_str()), ("state-1", "metric-1"));
+
+        store.merge_relationship(&state, &metric, RelationType::Contains, None).await.unwrap();
+        store.merge_relationship(&state, &metric, RelationType::Related, None).await.unwrap();
+        assert_eq!(store.relationships().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_links_every_metric_to_its_state() {
+        let store = InMemoryContextStore::new();
+        let state = HashMap::from([("cpu".to_string(), json!(12.5)), ("memory".to_string(), json!(40.0))]);
+        let state_id = store.store_snapshot(state, Utc::now()).await.unwrap();
+
+        assert_eq!(state_id, "state-1");
+        assert_eq!(store.metrics().len(), 2);
+        let relationships = store.relationships();
+        assert_eq!(relationships.len(), 2);
+        assert!(relationships.iter().all(|r| r.from_id == "state-1" && r.rel_type == RelationType::Contains));
+    }
+}